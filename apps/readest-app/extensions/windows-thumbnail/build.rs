@@ -0,0 +1,29 @@
+//! Stages the overlay badge for `include_bytes!`.
+//!
+//! The badge is the app icon at `apps/readest-app/public/icon.png` (override
+//! with `READEST_OVERLAY_ICON`). When it isn't there, an empty file is staged
+//! instead so the crate still builds; thumbnails are then drawn without the
+//! badge unless an `icon.png` is found beside the executable at runtime.
+
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=READEST_OVERLAY_ICON");
+    let source = std::env::var_os("READEST_OVERLAY_ICON")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap())
+                .join("../../public/icon.png")
+        });
+    println!("cargo:rerun-if-changed={}", source.display());
+
+    let target = PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("overlay_icon.png");
+    let icon = std::fs::read(&source).unwrap_or_else(|_| {
+        println!(
+            "cargo:warning=overlay icon not found at {}; thumbnails will have no badge",
+            source.display()
+        );
+        Vec::new()
+    });
+    std::fs::write(&target, icon).expect("failed to stage overlay icon");
+}
@@ -9,31 +9,42 @@
 /// ## CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567890}
 use std::cell::UnsafeCell;
 use std::ffi::c_void;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicIsize, AtomicU32, Ordering};
+use std::sync::Once;
 
 use windows::core::{IUnknown, Interface, GUID, HRESULT, PCWSTR, PWSTR};
 use windows::Win32::Foundation::{
-    CLASS_E_NOAGGREGATION, E_FAIL, E_INVALIDARG, E_NOINTERFACE, HMODULE, S_FALSE, S_OK,
+    CLASS_E_NOAGGREGATION, ERROR_BAD_PATHNAME, ERROR_FILE_NOT_FOUND, ERROR_MOD_NOT_FOUND, E_FAIL,
+    E_INVALIDARG, E_NOINTERFACE, E_OUTOFMEMORY, HMODULE, S_FALSE, S_OK,
 };
 use windows::Win32::Graphics::Gdi::{
-    CreateDIBSection, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP,
+    CreateDIBSection, DeleteObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP,
 };
 use windows::Win32::System::Com::{CoTaskMemFree, IClassFactory, IClassFactory_Impl};
+use windows::Win32::System::Diagnostics::Debug::OutputDebugStringW;
 use windows::Win32::System::LibraryLoader::GetModuleFileNameW;
 use windows::Win32::System::Registry::{
-    RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegSetValueExW, HKEY, HKEY_CLASSES_ROOT,
-    KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    RegCloseKey, RegCreateKeyExW, RegDeleteKeyValueW, RegDeleteTreeW, RegGetValueW, RegSetValueExW,
+    HKEY, HKEY_CLASSES_ROOT, HKEY_CURRENT_USER, KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
 };
 use windows::Win32::UI::Shell::{
     AssocQueryStringW, IInitializeWithItem, IInitializeWithItem_Impl, IShellItem,
-    IThumbnailProvider, IThumbnailProvider_Impl, ASSOCF_NONE, ASSOCSTR_EXECUTABLE,
-    SIGDN_FILESYSPATH, WTSAT_ARGB, WTS_ALPHATYPE,
+    IThumbnailProvider, IThumbnailProvider_Impl, SHChangeNotify, ASSOCF_NONE, ASSOCSTR_EXECUTABLE,
+    SHCNE_ASSOCCHANGED, SHCNF_IDLIST, SIGDN_FILESYSPATH, WTSAT_ARGB, WTS_ALPHATYPE,
+    WTS_E_EXTRACTIONPENDING,
 };
 use windows_core::BOOL;
 use windows_core::{implement, Ref};
 
-use super::cached_thumbnail_for_path;
+use super::{
+    book_extension, cached_thumbnail_for_path, extraction_limit, is_heavy_extraction, is_unc_path,
+    lookup_cached_thumbnail, metrics_enabled, note_interactive_request,
+    quick_check_thumbnail_cache, set_metrics_sink, strip_verbatim_prefix,
+    thumbnail_metrics_snapshot, to_wide, use_portable_cache_dir, OverlayPolicy, ThumbnailTiming,
+    DEFAULT_THUMBNAIL_QUALITY,
+};
 
 // ─────────────────────────────────────────────────────────────────────────────
 // CLSID for Readest Thumbnail Provider
@@ -42,11 +53,92 @@ use super::cached_thumbnail_for_path;
 /// CLSID: {A1B2C3D4-E5F6-7890-ABCD-EF1234567890}
 pub const CLSID_READEST_THUMBNAIL: GUID = GUID::from_u128(0xA1B2C3D4_E5F6_7890_ABCD_EF1234567890);
 
-/// Supported file extensions
+/// Marker file beside `Readest.exe` (and this DLL) for portable installs.
+const PORTABLE_MARKER: &str = "portable.txt";
+
+/// Per-user settings key written by the Readest app.
+const SETTINGS_SUBKEY: &str = "Software\\Readest";
+
+/// DWORD under [`SETTINGS_SUBKEY`] holding the thumbnail quality (0–100).
+const THUMBNAIL_QUALITY_VALUE: &str = "ThumbnailQuality";
+
+/// Per-user key with one DWORD per extension (`cbz` = 0/1, no dot) that
+/// overrides whether the Readest badge is drawn on that type's thumbnails.
+const OVERLAY_POLICY_SUBKEY: &str = "Software\\Readest\\ThumbnailOverlay";
+
+/// Folder beside this DLL holding the per-format `.ico` files (bundled from
+/// `icons/` in this crate).
+const FILE_ICONS_DIR: &str = "file-icons";
+
+/// Icon written as `DefaultIcon` for each extension's ProgID, the fallback
+/// Explorer shows in list/detail views and for books without a cover.
+const FILE_ICONS: &[(&str, &str)] = &[
+    (".epub", "epub.ico"),
+    (".epub3", "epub.ico"),
+    (".kepub", "epub.ico"),
+    (".mobi", "mobi.ico"),
+    (".azw", "mobi.ico"),
+    (".azw3", "mobi.ico"),
+    (".kf8", "mobi.ico"),
+    (".prc", "mobi.ico"),
+    (".fb2", "epub.ico"),
+    (".pdf", "pdf.ico"),
+    (".cbz", "cbz.ico"),
+    (".cbr", "cbz.ico"),
+];
+
+/// Value under a ProgID's `DefaultIcon` key keeping the icon it had before
+/// ours, restored on unregister.
+const PREVIOUS_ICON_VALUE: &str = "ReadestPreviousIcon";
+
+/// Supported file extensions. Compound ones (`.kepub.epub`) are listed for
+/// completeness, but the shell keys handlers on the last segment, so they
+/// are registered through it; see [`shell_extensions`]. `.tar.gz` is left
+/// out: registering `.gz` would claim every gzip file on the machine.
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
-    ".epub", ".mobi", ".azw", ".azw3", ".kf8", ".prc", ".fb2", ".cbz", ".cbr", ".txt",
+    ".epub",
+    ".epub3",
+    ".kepub",
+    ".kepub.epub",
+    ".mobi",
+    ".azw",
+    ".azw3",
+    ".kf8",
+    ".prc",
+    ".fb2",
+    ".fbz",
+    ".cbz",
+    ".cbr",
+    ".txt",
+    ".html",
+    ".htm",
+    ".tar",
+    ".tgz",
+    ".lit",
 ];
 
+/// The extension the shell looks up for a file ending in `ext`: its last
+/// segment (`.kepub.epub` → `.epub`).
+fn shell_extension(ext: &str) -> &str {
+    match ext.rfind('.') {
+        Some(dot) => &ext[dot..],
+        None => ext,
+    }
+}
+
+/// [`SUPPORTED_EXTENSIONS`] as registry keys: one per shell extension, so a
+/// compound extension doesn't get a `HKCR\.kepub.epub` key nothing reads.
+fn shell_extensions() -> Vec<&'static str> {
+    let mut exts: Vec<&'static str> = Vec::new();
+    for ext in SUPPORTED_EXTENSIONS {
+        let ext = shell_extension(ext);
+        if !exts.contains(&ext) {
+            exts.push(ext);
+        }
+    }
+    exts
+}
+
 // DLL reference counting
 static DLL_REF_COUNT: AtomicU32 = AtomicU32::new(0);
 static DLL_MODULE_PTR: AtomicIsize = AtomicIsize::new(0);
@@ -101,13 +193,16 @@ fn is_readest_default_for_extension(ext: &str) -> bool {
     false
 }
 
-/// Check if Readest is the default app for a specific file path.
+/// Check if Readest is the default app for a specific file path. A compound
+/// extension (`.kepub.epub`) is tried whole first, then as the shell sees it.
 fn is_readest_default_for_file(path: &PathBuf) -> bool {
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        let ext_with_dot = format!(".{}", ext.to_lowercase());
-        return is_readest_default_for_extension(&ext_with_dot);
-    }
-    false
+    let Some(ext) = book_extension(path) else {
+        return false;
+    };
+    let ext_with_dot = format!(".{}", ext);
+    let shell_ext = shell_extension(&ext_with_dot);
+    is_readest_default_for_extension(&ext_with_dot)
+        || (shell_ext != ext_with_dot && is_readest_default_for_extension(shell_ext))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -142,9 +237,31 @@ pub struct ThumbnailProvider {
     should_provide: ComCell<bool>,
 }
 
+/// Guards the once-per-load cache setup in [`ThumbnailProvider::new`].
+static CACHE_CHECK: Once = Once::new();
+
 impl ThumbnailProvider {
     pub fn new() -> Self {
         dll_add_ref();
+        CACHE_CHECK.call_once(|| {
+            // Looked up here rather than in `DllMain`, since checking for the
+            // portable marker touches the disk under the loader lock. Every
+            // cache access goes through a provider, so none comes earlier.
+            if let Some(data_dir) = portable_data_dir() {
+                use_portable_cache_dir(&data_dir);
+            }
+            // Sweep entries damaged by an earlier crash mid-write, off the
+            // Explorer thread. `read_cache` catches any the sweep hasn't
+            // reached.
+            std::thread::spawn(|| match quick_check_thumbnail_cache() {
+                Ok(report) if report.removed > 0 => debug_output(&format!(
+                    "removed {} of {} cached thumbnails",
+                    report.removed, report.checked
+                )),
+                Ok(_) => {}
+                Err(e) => debug_output(&format!("thumbnail cache check failed: {e}")),
+            });
+        });
         Self {
             file_path: ComCell::new(None),
             file_ext: ComCell::new(None),
@@ -192,11 +309,7 @@ impl IInitializeWithItem_Impl for ThumbnailProvider_Impl {
                 return Ok(());
             }
 
-            let ext = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| s.to_lowercase())
-                .unwrap_or_default();
+            let ext = book_extension(&path).unwrap_or_default();
 
             self.file_path.set(Some(path));
             self.file_ext.set(Some(ext));
@@ -218,46 +331,59 @@ impl IThumbnailProvider_Impl for ThumbnailProvider_Impl {
 
         let path = self.file_path.get().as_ref().ok_or(E_FAIL)?;
         let ext = self.file_ext.get().as_ref().ok_or(E_FAIL)?;
-
-        let png_bytes = cached_thumbnail_for_path(path, ext, cx).map_err(|_| E_FAIL)?;
-        let img = image::load_from_memory(&png_bytes).map_err(|_| E_FAIL)?;
-        let rgba = img.to_rgba8();
-        let (width, height) = (rgba.width(), rgba.height());
-
-        let bmi = BITMAPINFO {
-            bmiHeader: BITMAPINFOHEADER {
-                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
-                biWidth: width as i32,
-                biHeight: -(height as i32),
-                biPlanes: 1,
-                biBitCount: 32,
-                biCompression: BI_RGB.0,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-
-        let mut bits: *mut c_void = std::ptr::null_mut();
-
-        unsafe {
-            let hbmp = CreateDIBSection(None, &bmi, DIB_RGB_COLORS, &mut bits, None, 0)
-                .map_err(|_| E_FAIL)?;
-            if bits.is_null() {
-                return Err(E_FAIL.into());
+        // Someone is waiting for this one; background warming steps aside.
+        note_interactive_request();
+
+        // Explorer already asks for device pixels, so render at @1x.
+        let quality = thumbnail_quality();
+        let overlay = overlay_policy(ext);
+        // Cache hits are served straight away; only extractions queue for a
+        // permit. The cache is checked again once one is granted, since a
+        // request for the same file may have filled it meanwhile.
+        let cached = lookup_cached_thumbnail(path, ext, cx, 1, quality, &overlay)
+            .ok()
+            .flatten();
+        let thumbnail = match cached {
+            Some(thumbnail) => thumbnail,
+            None => {
+                let _permit = is_heavy_extraction(ext).then(|| extraction_limit().acquire());
+                cached_thumbnail_for_path(path, ext, cx, 1, quality, &overlay)
+                    .map_err(|_| E_FAIL)?
             }
+        };
+        let img = image::load_from_memory(&thumbnail.bytes).map_err(|_| E_FAIL)?;
+        let rgba = img.to_rgba8();
 
-            // RGBA -> BGRA
-            let dst =
-                std::slice::from_raw_parts_mut(bits as *mut u8, (width * height * 4) as usize);
-            let src = rgba.as_raw();
-            for i in 0..(width * height) as usize {
-                let si = i * 4;
-                dst[si] = src[si + 2]; // B
-                dst[si + 1] = src[si + 1]; // G
-                dst[si + 2] = src[si]; // R
-                dst[si + 3] = src[si + 3]; // A
+        // A bare E_FAIL here makes Explorer stop asking for the rest of the
+        // folder, so retry at half size and otherwise ask to be rescheduled.
+        // The SDK has no `WTS_E_FASTEXTRACTIONUNAVAILABLE`; the nearest name,
+        // `WTS_E_FASTEXTRACTIONNOTSUPPORTED`, means the provider never does
+        // fast extraction, while `WTS_E_EXTRACTIONPENDING` asks for a retry.
+        let hbmp = match create_bgra_dib(&rgba) {
+            Ok(hbmp) => hbmp,
+            Err(e) => {
+                debug_output(&format!(
+                    "CreateDIBSection failed for {}x{}: {e}",
+                    rgba.width(),
+                    rgba.height()
+                ));
+                let smaller = image::imageops::thumbnail(
+                    &rgba,
+                    (rgba.width() / 2).max(1),
+                    (rgba.height() / 2).max(1),
+                );
+                create_bgra_dib(&smaller).map_err(|e| {
+                    debug_output(&format!(
+                        "CreateDIBSection failed for {}x{}: {e}",
+                        smaller.width(),
+                        smaller.height()
+                    ));
+                    WTS_E_EXTRACTIONPENDING
+                })?
             }
+        };
 
+        unsafe {
             *phbmp = hbmp;
             *pdwalpha = WTSAT_ARGB;
         }
@@ -265,6 +391,57 @@ impl IThumbnailProvider_Impl for ThumbnailProvider_Impl {
     }
 }
 
+/// Bytes in a 32-bit top-down DIB of this size, or `None` when it can't be
+/// described by a `BITMAPINFOHEADER` or addressed in memory.
+fn dib_len(width: u32, height: u32) -> Option<usize> {
+    if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+        return None;
+    }
+    (width as usize)
+        .checked_mul(height as usize)?
+        .checked_mul(4)
+        .filter(|len| *len <= isize::MAX as usize)
+}
+
+/// Copy `rgba` into a new 32-bit BGRA DIB section.
+fn create_bgra_dib(rgba: &image::RgbaImage) -> windows::core::Result<HBITMAP> {
+    let (width, height) = (rgba.width(), rgba.height());
+    let len = dib_len(width, height).ok_or(E_OUTOFMEMORY)?;
+
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut bits: *mut c_void = std::ptr::null_mut();
+
+    unsafe {
+        let hbmp = CreateDIBSection(None, &bmi, DIB_RGB_COLORS, &mut bits, None, 0)?;
+        if bits.is_null() {
+            let _ = DeleteObject(hbmp.into());
+            return Err(E_OUTOFMEMORY.into());
+        }
+
+        // RGBA -> BGRA
+        let dst = std::slice::from_raw_parts_mut(bits as *mut u8, len);
+        for (d, s) in dst.chunks_exact_mut(4).zip(rgba.as_raw().chunks_exact(4)) {
+            d[0] = s[2]; // B
+            d[1] = s[1]; // G
+            d[2] = s[0]; // R
+            d[3] = s[3]; // A
+        }
+        Ok(hbmp)
+    }
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // ClassFactory
 // ─────────────────────────────────────────────────────────────────────────────
@@ -328,13 +505,45 @@ impl IClassFactory_Impl for ThumbnailProviderFactory_Impl {
 
 #[no_mangle]
 pub extern "system" fn DllMain(hinstance: HMODULE, reason: u32, _reserved: *mut c_void) -> BOOL {
+    const DLL_PROCESS_DETACH: u32 = 0;
     const DLL_PROCESS_ATTACH: u32 = 1;
     if reason == DLL_PROCESS_ATTACH {
         set_dll_module(hinstance);
+        // Size the limiter now, so the variable is read once per load.
+        extraction_limit();
+        if metrics_enabled() {
+            set_metrics_sink(report_timing);
+        }
+    } else if reason == DLL_PROCESS_DETACH && metrics_enabled() {
+        report_metrics_totals();
     }
     BOOL::from(true)
 }
 
+/// Metrics sink: one debugger line per thumbnail request.
+fn report_timing(timing: &ThumbnailTiming) {
+    debug_output(&timing.to_string());
+}
+
+/// Per-format totals for this Explorer session, written when the DLL
+/// unloads.
+fn report_metrics_totals() {
+    for (ext, totals) in thumbnail_metrics_snapshot() {
+        debug_output(&format!(
+            "thumbnail-metrics total {ext} requests={} hits={} misses={} failures={} \
+             extract={}ms decode={}ms encode={}ms slowest={}ms",
+            totals.requests,
+            totals.cache_hits,
+            totals.cache_misses,
+            totals.failures,
+            totals.extract.as_millis(),
+            totals.decode.as_millis(),
+            totals.encode.as_millis(),
+            totals.slowest.as_millis(),
+        ));
+    }
+}
+
 #[no_mangle]
 pub extern "system" fn DllCanUnloadNow() -> HRESULT {
     if DLL_REF_COUNT.load(Ordering::SeqCst) == 0 {
@@ -384,19 +593,212 @@ pub unsafe extern "system" fn DllUnregisterServer() -> HRESULT {
 // Registry helpers
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// Read a DWORD from `HKEY_CURRENT_USER\\<subkey>`, if present.
+fn read_user_dword(subkey: &str, name: &str) -> Option<u32> {
+    let subkey = to_wide(subkey);
+    let value_name = to_wide(name);
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value_name.as_ptr()),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        )
+    };
+    result.is_ok().then_some(value)
+}
+
+/// Read a string from `HKEY_CLASSES_ROOT\<subkey>` (`""` for the default
+/// value), if present. `REG_EXPAND_SZ` data comes back expanded.
+fn read_class_string(subkey: &str, name: &str) -> Option<String> {
+    let subkey = to_wide(subkey);
+    let value_name = to_wide(name);
+    let mut buffer = vec![0u16; 1024];
+    let mut size = (buffer.len() * 2) as u32;
+
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CLASSES_ROOT,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value_name.as_ptr()),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
+        )
+    };
+    if result.is_err() {
+        return None;
+    }
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+/// Thumbnail quality chosen in Readest's settings, or the default when the
+/// value is missing or out of range.
+fn thumbnail_quality() -> u8 {
+    read_user_dword(SETTINGS_SUBKEY, THUMBNAIL_QUALITY_VALUE)
+        .and_then(|value| u8::try_from(value).ok())
+        .filter(|quality| *quality <= 100)
+        .unwrap_or(DEFAULT_THUMBNAIL_QUALITY)
+}
+
+/// Overlay policy for `ext`: the built-in default, overridden by the user's
+/// setting for that extension when one exists.
+fn overlay_policy(ext: &str) -> OverlayPolicy {
+    let mut policy = OverlayPolicy::new();
+    if let Some(value) = read_user_dword(OVERLAY_POLICY_SUBKEY, ext) {
+        policy.set(ext, value != 0);
+    }
+    policy
+}
+
+/// `data` folder of a portable install, when this DLL sits beside a
+/// `portable.txt` marker (see `portable.rs` in the app).
+fn portable_data_dir() -> Option<PathBuf> {
+    let dll = PathBuf::from(get_dll_path()?);
+    let dir = dll.parent()?;
+    dir.join(PORTABLE_MARKER)
+        .is_file()
+        .then(|| dir.join("data"))
+}
+
+/// Path of this DLL as the loader reports it. Sized for long paths; a
+/// result that fills the buffer was truncated and is rejected.
 fn get_dll_path() -> Option<String> {
     let module = get_dll_module()?;
-    let mut buffer = [0u16; 260];
+    let mut buffer = vec![0u16; 32_768];
     unsafe {
-        let len = GetModuleFileNameW(Some(module), &mut buffer);
-        if len == 0 {
+        let len = GetModuleFileNameW(Some(module), &mut buffer) as usize;
+        if len == 0 || len >= buffer.len() {
             None
         } else {
-            Some(String::from_utf16_lossy(&buffer[..len as usize]))
+            Some(String::from_utf16_lossy(&buffer[..len]))
         }
     }
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// DLL path validation
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Why the provider can't be registered from where the DLL is. Each case
+/// maps to its own HRESULT, so the installer (or `regsvr32`) reports what
+/// went wrong instead of a bare `E_FAIL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DllPathError {
+    /// The loader didn't give us a usable module path.
+    Unresolved,
+    /// Nothing exists at the module path.
+    NotFound(String),
+    /// The path has characters that can't appear in a loadable file path.
+    InvalidPath(String),
+}
+
+impl DllPathError {
+    fn hresult(&self) -> HRESULT {
+        match self {
+            DllPathError::Unresolved => ERROR_MOD_NOT_FOUND.to_hresult(),
+            DllPathError::NotFound(_) => ERROR_FILE_NOT_FOUND.to_hresult(),
+            DllPathError::InvalidPath(_) => ERROR_BAD_PATHNAME.to_hresult(),
+        }
+    }
+}
+
+impl std::fmt::Display for DllPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DllPathError::Unresolved => write!(f, "could not resolve the provider DLL path"),
+            DllPathError::NotFound(path) => write!(f, "file not found at {}", path),
+            DllPathError::InvalidPath(path) => {
+                write!(f, "path has invalid characters: {:?}", path)
+            }
+        }
+    }
+}
+
+/// Send a diagnostic to the debugger (DebugView, or the installer's log if
+/// it captures debug output). The DLL has no console or log file of its own.
+fn debug_output(message: &str) {
+    let wide = to_wide(&format!("Readest thumbnail provider: {}\n", message));
+    unsafe { OutputDebugStringW(PCWSTR(wide.as_ptr())) };
+}
+
+/// Characters Windows never allows in a path outside the drive colon.
+fn has_invalid_path_chars(path: &str) -> bool {
+    let body = match path.as_bytes() {
+        [_, b':', ..] => &path[2..],
+        _ => path,
+    };
+    body.chars()
+        .any(|c| c.is_control() || matches!(c, '"' | '<' | '>' | '|' | '?' | '*' | ':'))
+}
+
+/// Canonical, validated form of a file shipped with the DLL: it must exist,
+/// and the path is made absolute and stripped of the verbatim prefix.
+fn resolve_installed_file(raw: String) -> Result<String, DllPathError> {
+    if !Path::new(&raw).is_file() {
+        return Err(DllPathError::NotFound(raw));
+    }
+    let canonical = std::fs::canonicalize(&raw).map_err(|_| DllPathError::NotFound(raw))?;
+    let path = strip_verbatim_prefix(&canonical.to_string_lossy());
+    if has_invalid_path_chars(&path) {
+        return Err(DllPathError::InvalidPath(path));
+    }
+    Ok(path)
+}
+
+/// Canonical, validated path to write into `InprocServer32`. A UNC path is
+/// accepted (it can work) but reported, since Explorer often fails to load
+/// shell extensions from network shares.
+fn resolve_dll_path() -> Result<String, DllPathError> {
+    let raw = get_dll_path().ok_or(DllPathError::Unresolved)?;
+    let path = resolve_installed_file(raw)?;
+    if is_unc_path(&path) {
+        debug_output(&format!(
+            "warning: registering from a network path ({}); Explorer may not load it",
+            path
+        ));
+    }
+    Ok(path)
+}
+
+/// Canonical path of the bundled icon `name`, resolved from the DLL's
+/// folder the same way as the DLL itself.
+fn resolve_icon_path(dll_path: &str, name: &str) -> Result<String, DllPathError> {
+    let dir = Path::new(dll_path)
+        .parent()
+        .ok_or(DllPathError::Unresolved)?;
+    let raw = dir.join(FILE_ICONS_DIR).join(name);
+    resolve_installed_file(raw.to_string_lossy().into_owned())
+}
+
+/// Whether a registry path value (a command line or an icon location,
+/// possibly quoted) points at a file inside `dir`.
+fn points_into(value: &str, dir: &str) -> bool {
+    let value = value.trim_start().trim_start_matches('"').to_lowercase();
+    let dir = dir.trim_end_matches('\\').to_lowercase();
+    !dir.is_empty() && value.starts_with(&dir) && value[dir.len()..].starts_with('\\')
+}
+
+/// The ProgID `ext` is associated with, when that ProgID opens files with
+/// the Readest install this DLL belongs to. Other apps' ProgIDs are never
+/// touched.
+fn readest_prog_id(ext: &str, install_dir: &str) -> Option<String> {
+    let prog_id = read_class_string(ext, "")?;
+    if prog_id.is_empty() {
+        return None;
+    }
+    let command = read_class_string(&format!("{}\\shell\\open\\command", prog_id), "")?;
+    points_into(&command, install_dir).then_some(prog_id)
+}
+
 fn clsid_string() -> String {
     format!(
         "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
@@ -414,10 +816,6 @@ fn clsid_string() -> String {
     )
 }
 
-fn to_wide(s: &str) -> Vec<u16> {
-    s.encode_utf16().chain(std::iter::once(0)).collect()
-}
-
 unsafe fn set_reg_value(key: HKEY, name: &str, value: &str) -> Result<(), HRESULT> {
     let name_w = to_wide(name);
     let value_w = to_wide(value);
@@ -451,7 +849,10 @@ unsafe fn create_reg_key(parent: HKEY, subkey: &str) -> Result<HKEY, HRESULT> {
 }
 
 unsafe fn register_server_impl() -> Result<(), HRESULT> {
-    let dll_path = get_dll_path().ok_or(E_FAIL)?;
+    let dll_path = resolve_dll_path().map_err(|e| {
+        debug_output(&e.to_string());
+        e.hresult()
+    })?;
     let clsid = clsid_string();
 
     // CLSID key
@@ -479,7 +880,7 @@ unsafe fn register_server_impl() -> Result<(), HRESULT> {
     let _ = RegCloseKey(clsid_key);
 
     // Register ShellEx thumbnail handler for each extension
-    for ext in SUPPORTED_EXTENSIONS {
+    for ext in shell_extensions() {
         let ext_shellex_path =
             format!("{}\\ShellEx\\{{e357fccd-a995-4576-b01f-234630154e96}}", ext);
         if let Ok(ext_shellex_key) = create_reg_key(HKEY_CLASSES_ROOT, &ext_shellex_path) {
@@ -487,20 +888,167 @@ unsafe fn register_server_impl() -> Result<(), HRESULT> {
             let _ = RegCloseKey(ext_shellex_key);
         }
     }
+
+    register_file_icons(&dll_path);
+    SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None);
     Ok(())
 }
 
+/// Point `DefaultIcon` of each Readest-owned ProgID at its per-format icon,
+/// keeping the previous icon in [`PREVIOUS_ICON_VALUE`]. Best effort: a
+/// missing icon or a ProgID owned by another app is skipped.
+unsafe fn register_file_icons(dll_path: &str) {
+    let Some(install_dir) = Path::new(dll_path).parent().map(|d| d.to_string_lossy()) else {
+        return;
+    };
+    for (ext, name) in FILE_ICONS {
+        let Some(prog_id) = readest_prog_id(ext, &install_dir) else {
+            continue;
+        };
+        let icon = match resolve_icon_path(dll_path, name) {
+            Ok(path) => format!("{},0", path),
+            Err(e) => {
+                debug_output(&format!("skipping file icon for {}: {}", ext, e));
+                continue;
+            }
+        };
+        let icon_subkey = format!("{}\\DefaultIcon", prog_id);
+        let current = read_class_string(&icon_subkey, "");
+        let Ok(icon_key) = create_reg_key(HKEY_CLASSES_ROOT, &icon_subkey) else {
+            continue;
+        };
+        match current {
+            // Re-registering: keep the backup taken the first time.
+            Some(current) if points_into(&current, &install_dir) => {}
+            Some(current) => {
+                let _ = set_reg_value(icon_key, PREVIOUS_ICON_VALUE, &current);
+            }
+            None => {}
+        }
+        let _ = set_reg_value(icon_key, "", &icon);
+        let _ = RegCloseKey(icon_key);
+    }
+}
+
+/// Undo [`register_file_icons`]: restore the icon each ProgID had before,
+/// or drop `DefaultIcon` if it had none. Icons someone else set since are
+/// left alone.
+unsafe fn unregister_file_icons(install_dir: &str) {
+    for (ext, _) in FILE_ICONS {
+        let Some(prog_id) = read_class_string(ext, "").filter(|id| !id.is_empty()) else {
+            continue;
+        };
+        let icon_subkey = format!("{}\\DefaultIcon", prog_id);
+        let icons_dir = format!("{}\\{}", install_dir, FILE_ICONS_DIR);
+        match read_class_string(&icon_subkey, "") {
+            Some(current) if points_into(&current, &icons_dir) => {}
+            _ => continue,
+        }
+        match read_class_string(&icon_subkey, PREVIOUS_ICON_VALUE) {
+            Some(previous) => {
+                if let Ok(icon_key) = create_reg_key(HKEY_CLASSES_ROOT, &icon_subkey) {
+                    let _ = set_reg_value(icon_key, "", &previous);
+                    let _ = RegCloseKey(icon_key);
+                }
+                let subkey = to_wide(&icon_subkey);
+                let value_name = to_wide(PREVIOUS_ICON_VALUE);
+                let _ = RegDeleteKeyValueW(
+                    HKEY_CLASSES_ROOT,
+                    PCWSTR(subkey.as_ptr()),
+                    PCWSTR(value_name.as_ptr()),
+                );
+            }
+            None => {
+                let subkey = to_wide(&icon_subkey);
+                let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(subkey.as_ptr()));
+            }
+        }
+    }
+}
+
 unsafe fn unregister_server_impl() -> Result<(), HRESULT> {
     let clsid = clsid_string();
     let clsid_path = to_wide(&format!("CLSID\\{}", clsid));
     let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(clsid_path.as_ptr()));
 
-    for ext in SUPPORTED_EXTENSIONS {
+    for ext in shell_extensions() {
         let ext_path = to_wide(&format!(
             "{}\\ShellEx\\{{e357fccd-a995-4576-b01f-234630154e96}}",
             ext
         ));
         let _ = RegDeleteTreeW(HKEY_CLASSES_ROOT, PCWSTR(ext_path.as_ptr()));
     }
+
+    let install_dir = get_dll_path().and_then(|dll| {
+        Path::new(&dll)
+            .parent()
+            .map(|dir| strip_verbatim_prefix(&dir.to_string_lossy()))
+    });
+    if let Some(install_dir) = install_dir {
+        unregister_file_icons(&install_dir);
+    }
+    SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dib_len, has_invalid_path_chars, is_unc_path, points_into, shell_extension,
+        shell_extensions,
+    };
+
+    #[test]
+    fn network_paths_are_detected() {
+        assert!(is_unc_path(r"\\server\share\x.dll"));
+        assert!(!is_unc_path(r"C:\x.dll"));
+    }
+
+    #[test]
+    fn invalid_characters_are_rejected() {
+        assert!(!has_invalid_path_chars(
+            r"C:\Program Files (x86)\Readest\x.dll"
+        ));
+        assert!(has_invalid_path_chars("C:\\Readest\\x\n.dll"));
+        assert!(has_invalid_path_chars(r"C:\Read|est\x.dll"));
+        assert!(has_invalid_path_chars(r"C:\a:b\x.dll"));
+    }
+
+    #[test]
+    fn install_dir_paths_are_matched() {
+        let dir = r"C:\Program Files\Readest";
+        assert!(points_into(
+            r#""C:\Program Files\Readest\readest.exe" "%1""#,
+            dir
+        ));
+        assert!(points_into(
+            r"c:\program files\readest\file-icons\epub.ico,0",
+            r"C:\Program Files\Readest\"
+        ));
+        assert!(!points_into(
+            r#""C:\Program Files\Readest Beta\readest.exe" "%1""#,
+            dir
+        ));
+        assert!(!points_into(r"C:\Windows\system32\shell32.dll,-1", dir));
+        assert!(!points_into(r"C:\x.exe", ""));
+    }
+
+    #[test]
+    fn compound_extensions_register_through_their_last_segment() {
+        assert_eq!(shell_extension(".kepub.epub"), ".epub");
+        assert_eq!(shell_extension(".kepub"), ".kepub");
+        let exts = shell_extensions();
+        assert!(exts.contains(&".kepub") && exts.contains(&".epub3"));
+        assert!(!exts.contains(&".kepub.epub"));
+        assert_eq!(exts.iter().filter(|ext| **ext == ".epub").count(), 1);
+        assert!(exts.contains(&".tgz") && !exts.contains(&".gz"));
+    }
+
+    #[test]
+    fn dib_size_is_overflow_checked() {
+        assert_eq!(dib_len(256, 384), Some(256 * 384 * 4));
+        assert_eq!(dib_len(0, 10), None);
+        assert_eq!(dib_len(u32::MAX, 1), None);
+        assert_eq!(dib_len(i32::MAX as u32, i32::MAX as u32), None);
+    }
+}
@@ -1,6 +1,9 @@
 /// Cover image extraction for various eBook formats
 ///
-/// Supports: EPUB, MOBI/AZW3/KF8, FB2, CBZ/CBR, TXT
+/// Supports: EPUB, MOBI/AZW3/KF8, FB2/FBZ, CBZ/CBR, TXT, tar/tar.gz
+/// wrapping a book or an image sequence, and LIT books whose images are
+/// stored uncompressed
+/// Recognizes but rejects: KFX (see [`CoverError::Unsupported`])
 use anyhow::{anyhow, Result};
 use base64::engine::general_purpose;
 use base64::Engine as _;
@@ -8,106 +11,363 @@ use directories_next::ProjectDirs;
 use image::{imageops, DynamicImage, Rgba};
 use md5::Context;
 use once_cell::sync::Lazy;
-use std::io::{Cursor, Read, Seek, SeekFrom};
-use std::path::Path;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use std::io::{BufRead, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use zip::result::ZipError;
 use zip::ZipArchive;
 
-/// Thumbnail cache directory (per-user)
+/// Quality used when the caller has no preference. Opaque covers are stored
+/// as JPEG at this quality, which keeps the cache small without visible loss
+/// at Explorer thumbnail sizes.
+pub const DEFAULT_THUMBNAIL_QUALITY: u8 = 80;
+
+/// Whether the Readest badge is stamped on thumbnails, per file extension.
+///
+/// Extensions without an explicit setting use [`OverlayPolicy::default_for`]:
+/// badge on, except comics, where it covers part of the cover art.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlayPolicy {
+    overrides: std::collections::HashMap<String, bool>,
+}
+
+impl OverlayPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the badge for `ext` (with or without the leading dot).
+    pub fn set(&mut self, ext: &str, enabled: bool) {
+        self.overrides.insert(normalize_ext(ext), enabled);
+    }
+
+    /// Built-in setting for `ext` when nothing overrides it.
+    pub fn default_for(ext: &str) -> bool {
+        !matches!(normalize_ext(ext).as_str(), "cbz" | "cbr")
+    }
+
+    /// Effective setting for `ext`.
+    pub fn is_enabled(&self, ext: &str) -> bool {
+        self.overrides
+            .get(&normalize_ext(ext))
+            .copied()
+            .unwrap_or_else(|| Self::default_for(ext))
+    }
+}
+
+fn normalize_ext(ext: &str) -> String {
+    ext.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Environment variable naming a thumbnail cache directory to use instead of
+/// the per-user default, so side-by-side builds (stable and beta, say) can
+/// share one cache.
+pub const CACHE_DIR_ENV: &str = "READEST_THUMBNAIL_CACHE_DIR";
+
+/// Cache directory of a portable install, set by [`use_portable_cache_dir`].
+static PORTABLE_CACHE_DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Cache thumbnails in the portable install rooted at `data_dir` (the `data`
+/// folder beside `Readest.exe`) instead of the user profile. Only effective
+/// before the first cache access; [`CACHE_DIR_ENV`] still takes precedence.
+pub fn use_portable_cache_dir(data_dir: &Path) {
+    let _ = PORTABLE_CACHE_DIR.set(data_dir.join("cache").join("thumbnails"));
+}
+
+/// Thumbnail cache directory: [`CACHE_DIR_ENV`] when set, else the portable
+/// install's, else per-user.
 static CACHE_DIR: Lazy<Option<std::path::PathBuf>> = Lazy::new(|| {
-    ProjectDirs::from("app", "Readest", "").map(|pd| {
-        let dir = pd.cache_dir().join("thumbnails");
-        let _ = std::fs::create_dir_all(&dir);
-        dir
-    })
+    let dir = std::env::var_os(CACHE_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| PORTABLE_CACHE_DIR.get().cloned())
+        .or_else(|| {
+            ProjectDirs::from("app", "Readest", "").map(|pd| pd.cache_dir().join("thumbnails"))
+        })?;
+    let _ = std::fs::create_dir_all(&dir);
+    Some(dir)
 });
 
+/// Version of the cache key scheme, written as a `v<N>-` prefix on entry
+/// names. Bump it whenever the digest inputs, the entry encoding or the
+/// rendered pixels change: a shared cache may hold entries from builds on
+/// either side of the change, and each build only reads, checks and removes
+/// entries of its own version. Names without a prefix predate versioning.
+///
+/// - v5: an EPUB's OPF-declared cover outranks cover-named images.
+/// - v6: JPEG entries up to [`FULL_CHROMA_MAX_EDGE`] keep full chroma.
+/// - v7: an EPUB2 `<guide>` cover page's image ranks between the metadata
+///   cover and the first manifest image.
+/// - v8: fallback EPUB images are ranked by shape rather than file size.
+/// - v9: data URLs are rendered like thumbnails, so small covers are no
+///   longer upscaled to the requested size.
+const CACHE_KEY_VERSION: u32 = 9;
+
+/// Key-scheme version of the cache entry `name`, if it carries a prefix.
+fn cache_key_version(name: &str) -> Option<u32> {
+    let (version, _) = name.strip_prefix('v')?.split_once('-')?;
+    version.parse().ok()
+}
+
+/// Whether `name` is a cache entry: `v<N>-<md5 hex>.thumb` or `.url`. Cache
+/// maintenance only ever touches files named like this, so a cache dir
+/// pointed at a shared folder through [`CACHE_DIR_ENV`] keeps everything
+/// else in it.
+pub fn is_cache_entry_name(name: &str) -> bool {
+    let Some(stem) = name
+        .strip_suffix(".thumb")
+        .or_else(|| name.strip_suffix(".url"))
+    else {
+        return false;
+    };
+    let Some((version, digest)) = stem.strip_prefix('v').and_then(|rest| rest.split_once('-'))
+    else {
+        return false;
+    };
+    version.parse::<u32>().is_ok()
+        && digest.len() == 32
+        && digest
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Errors
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Typed failures callers may want to tell apart from a broken file.
+///
+/// Returned wrapped in [`anyhow::Error`]; use `err.downcast_ref::<CoverError>()`
+/// to pick it out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoverError {
+    /// The format was recognized, but cover extraction isn't implemented for
+    /// it. The message is user-facing and explains why.
+    Unsupported(String),
+    /// The caller asked to stop before the work was done.
+    Cancelled,
+    /// The file is damaged or hostile, e.g. a cover image too large to
+    /// decode safely.
+    Corrupt(String),
+    /// The book is locked to its owner, so its contents can't be read. The
+    /// message is user-facing.
+    DrmProtected(String),
+    /// The archive is password-protected and no password, or the wrong one,
+    /// was given. The message is user-facing and never holds the password.
+    Encrypted(String),
+    /// The book couldn't be opened or read, after retries when it is on a
+    /// network share. `retriable` is set when the failure looked transient
+    /// (not "not found" or "permission denied"), so trying again later may
+    /// succeed.
+    Io { message: String, retriable: bool },
+}
+
+impl std::fmt::Display for CoverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverError::Unsupported(msg) => write!(f, "{}", msg),
+            CoverError::Cancelled => write!(f, "Extraction cancelled"),
+            CoverError::Corrupt(msg) => write!(f, "{}", msg),
+            CoverError::DrmProtected(msg) => write!(f, "{}", msg),
+            CoverError::Encrypted(msg) => write!(f, "{}", msg),
+            CoverError::Io { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CoverError {}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // EPUB extraction
 // ─────────────────────────────────────────────────────────────────────────────
 
 /// Extract cover image bytes from an EPUB file.
+///
+/// This is the best-ranked entry of [`rank_epub_covers`].
 pub fn extract_epub_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    extract_epub_cover_bytes_with_password(reader, None)
+}
+
+/// Like [`extract_epub_cover_bytes`], for an EPUB whose entries may be
+/// encrypted with `password` (traditional ZipCrypto or AES).
+pub fn extract_epub_cover_bytes_with_password<R: Read + Seek>(
+    reader: R,
+    password: Option<&str>,
+) -> Result<Vec<u8>> {
+    let password = password.map(str::as_bytes);
     let mut archive = ZipArchive::new(reader)?;
+    let best = rank_epub_covers(&mut archive, password)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No cover image found in EPUB"))?;
+    read_zip_index(&mut archive, best.index, password)
+}
 
-    // Pass 1: Look for files with "cover" in the name
-    let mut candidates: Vec<(usize, String, u64)> = Vec::new();
+/// An archive entry that may be the cover, with its heuristic score.
+struct RankedEntry {
+    index: usize,
+    name: String,
+    score: u8,
+}
+
+/// Every image in an EPUB ranked by how likely it is to be the cover, best
+/// first, in tiers:
+///
+///   1. what the OPF declares: the `<meta name="cover">` item, then the
+///      image on the EPUB2 `<guide>` cover page;
+///   2. names containing "cover" or "front" (an exact `cover.*` first), then
+///      the first manifest image;
+///   3. any other image, scored by [`fallback_cover_score`]: cover-shaped
+///      portraits and title-page names first, logos and icons left out.
+///
+/// The publisher's declaration wins over file names, which also match
+/// series banners and back covers.
+///
+/// Within a tier larger images rank higher (by pixel area in tier 3, else by
+/// file size); an image found by several passes keeps its best score.
+fn rank_epub_covers<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    password: Option<&[u8]>,
+) -> Result<Vec<RankedEntry>> {
+    let mut images: Vec<(usize, String, u64)> = Vec::new();
     for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let name = file.name().to_lowercase();
+        // Raw, so listing works before any entry is decrypted.
+        let file = archive.by_index_raw(i)?;
+        let name = file.name().to_string();
         let size = file.size();
         drop(file);
 
-        if is_image_extension(&name) && (name.contains("cover") || name.contains("front")) {
-            candidates.push((i, name, size));
+        if is_image_extension(&name.to_lowercase()) {
+            images.push((i, name, size));
         }
     }
 
-    // Sort by priority: exact "cover" match first, then by size
-    if !candidates.is_empty() {
-        candidates.sort_by(|a, b| {
-            let a_exact = a.1.contains("cover.") || a.1.ends_with("cover");
-            let b_exact = b.1.contains("cover.") || b.1.ends_with("cover");
-            match (a_exact, b_exact) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => b.2.cmp(&a.2),
-            }
-        });
-
-        let idx = candidates[0].0;
-        let mut file = archive.by_index(idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
-    }
+    let mut scores: std::collections::HashMap<usize, u8> = std::collections::HashMap::new();
+    let mut bump = |index: usize, score: u8| {
+        let entry = scores.entry(index).or_insert(score);
+        *entry = (*entry).max(score);
+    };
 
-    // Pass 2: Parse container.xml to find OPF, then parse OPF for cover
-    let container_xml = read_zip_file_to_string(&mut archive, "META-INF/container.xml");
+    // Pass 1: Parse container.xml to find OPF, then parse OPF for cover
+    let container_xml = read_zip_file_to_string(archive, "META-INF/container.xml", password);
     if let Ok(xml) = container_xml {
         if let Some(rootfile) = extract_attribute(&xml, "rootfile", "full-path") {
-            let opf_content = read_zip_file_to_string(&mut archive, &rootfile);
-            if let Ok(opf) = opf_content {
-                if let Some(cover_id) = find_cover_id_in_opf(&opf) {
-                    if let Some(href) = find_href_by_id_in_opf(&opf, &cover_id) {
-                        let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
-                        let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
-                        if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
-                            return Ok(bytes);
-                        }
-                    }
+            if let Ok(opf) = read_zip_file_to_string(archive, &rootfile, password) {
+                let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
+                let resolve = |href: &str| base.join(href).to_string_lossy().replace('\\', "/");
+
+                let cover_href = find_cover_id_in_opf(&opf)
+                    .and_then(|cover_id| find_href_by_id_in_opf(&opf, &cover_id));
+                if let Some(index) = cover_href.and_then(|h| archive.index_for_name(&resolve(&h))) {
+                    bump(index, 100);
                 }
-                if let Some(href) = find_first_image_in_manifest(&opf) {
-                    let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
-                    let cover_path = base.join(&href).to_string_lossy().replace('\\', "/");
-                    if let Ok(bytes) = read_zip_file_to_bytes(&mut archive, &cover_path) {
-                        return Ok(bytes);
-                    }
+                let opf_dir = base.to_string_lossy().replace('\\', "/");
+                let guide_image = find_guide_cover_in_opf(&opf).and_then(|page| {
+                    guide_cover_image(archive, &join_zip_path(&opf_dir, &page), password)
+                });
+                if let Some(index) = guide_image.and_then(|name| archive.index_for_name(&name)) {
+                    bump(index, 95);
+                }
+                let first_href = find_first_image_in_manifest(&opf);
+                if let Some(index) = first_href.and_then(|h| archive.index_for_name(&resolve(&h))) {
+                    bump(index, 60);
                 }
             }
         }
     }
 
-    // Pass 3: Just grab the largest image file
-    let mut largest: Option<(usize, u64)> = None;
-    for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let name = file.name().to_lowercase();
-        let size = file.size();
-        drop(file);
+    // Pass 2: Look for files with "cover" in the name
+    for (i, name, _) in &images {
+        let name = name.to_lowercase();
+        if name.contains("cover.") || name.ends_with("cover") {
+            bump(*i, 90);
+        } else if name.contains("cover") || name.contains("front") {
+            bump(*i, 80);
+        }
+    }
 
-        if is_image_extension(&name) && (largest.is_none() || size > largest.unwrap().1) {
-            largest = Some((i, size));
+    // Pass 3: every other image, by shape rather than file size, which
+    // favours full-page illustrations over the cover in image-heavy books.
+    let mut areas: std::collections::HashMap<usize, u64> = std::collections::HashMap::new();
+    for (i, name, _) in &images {
+        if scores.contains_key(i) {
+            continue;
+        }
+        let dimensions = zip_image_dimensions(archive, *i, password);
+        if let Some(score) = fallback_cover_score(name, dimensions) {
+            scores.insert(*i, score);
+            let (width, height) = dimensions.unwrap_or_default();
+            areas.insert(*i, u64::from(width) * u64::from(height));
         }
     }
 
-    if let Some((idx, _)) = largest {
-        let mut file = archive.by_index(idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
+    let mut ranked: Vec<(RankedEntry, u64, u64)> = images
+        .into_iter()
+        .filter_map(|(index, name, size)| {
+            let score = *scores.get(&index)?;
+            let area = areas.get(&index).copied().unwrap_or(0);
+            Some((RankedEntry { index, name, score }, area, size))
+        })
+        .collect();
+    ranked.sort_by(|(a, a_area, a_size), (b, b_area, b_size)| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b_area.cmp(a_area))
+            .then_with(|| b_size.cmp(a_size))
+            .then_with(|| a.index.cmp(&b.index))
+    });
+    Ok(ranked.into_iter().map(|(entry, _, _)| entry).collect())
+}
+
+/// Images whose shorter edge is below this many pixels are logos, ornaments
+/// or icons, never a cover.
+const MIN_COVER_EDGE: u32 = 100;
+
+/// Most of an image read to find its dimensions; headers (even with EXIF)
+/// sit well inside it.
+const IMAGE_HEADER_SCAN: u64 = 64 * 1024;
+
+/// Score (20–45) for an image no other pass picked: 20, plus 15 for a
+/// cover-like portrait (height 1.2–1.8× the width, 5 for any other portrait)
+/// and 10 for a `title` name. `None` for images too small to be a cover.
+/// Images whose size can't be read get the base score.
+fn fallback_cover_score(name: &str, dimensions: Option<(u32, u32)>) -> Option<u8> {
+    let mut score = 20;
+    if let Some((width, height)) = dimensions {
+        if width.min(height) < MIN_COVER_EDGE {
+            return None;
+        }
+        let ratio = height as f32 / width as f32;
+        score += if (1.2..=1.8).contains(&ratio) {
+            15
+        } else if ratio > 1.0 {
+            5
+        } else {
+            0
+        };
+    }
+    if name.to_lowercase().contains("title") {
+        score += 10;
     }
+    Some(score)
+}
 
-    Err(anyhow!("No cover image found in EPUB"))
+/// Width and height of the image at `index`, from its header alone.
+fn zip_image_dimensions<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    password: Option<&[u8]>,
+) -> Option<(u32, u32)> {
+    let file = open_zip_index(archive, index, password).ok()?;
+    let mut head = Vec::new();
+    file.take(IMAGE_HEADER_SCAN).read_to_end(&mut head).ok()?;
+    image::ImageReader::new(Cursor::new(head))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -115,11 +375,28 @@ pub fn extract_epub_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
 // ─────────────────────────────────────────────────────────────────────────────
 
 /// Extract cover image from MOBI/AZW3/KF8 files.
-pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>> {
+///
+/// If the cover is the last record, its end is found by seeking to EOF. Use
+/// [`extract_mobi_cover_bytes_with_len`] when the total length is already known.
+pub fn extract_mobi_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    extract_mobi_cover_bytes_with_len(reader, None)
+}
+
+/// Like [`extract_mobi_cover_bytes`], but a cover stored in the last record
+/// ends at `total_len` when it is given instead of at a seek to EOF. Other
+/// records always end where the next one starts.
+pub fn extract_mobi_cover_bytes_with_len<R: Read + Seek>(
+    mut reader: R,
+    total_len: Option<u64>,
+) -> Result<Vec<u8>> {
     let mut header = [0u8; 78];
     reader.read_exact(&mut header)?;
 
     if &header[60..68] != b"BOOKMOBI" {
+        // Kindle downloads keep the `.azw` name even when the book is KFX.
+        if let Some(kind) = detect_kfx(&header) {
+            return Err(kfx_unsupported(kind).into());
+        }
         return Err(anyhow!("Not a valid MOBI file"));
     }
 
@@ -178,13 +455,6 @@ pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>
     let exth_count = u32::from_be_bytes(exth_count_bytes) as usize;
 
     let mut cover_offset: Option<u32> = None;
-    let first_img_idx = u32::from_be_bytes([
-        mobi_header[108],
-        mobi_header[109],
-        mobi_header[110],
-        mobi_header[111],
-    ]);
-
     for _ in 0..exth_count {
         let mut rec_header = [0u8; 8];
         if reader.read_exact(&mut rec_header).is_err() {
@@ -207,23 +477,22 @@ pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>
         }
     }
 
-    let cover_record_idx = if let Some(offset) = cover_offset {
-        first_img_idx + offset
-    } else {
-        first_img_idx
-    };
-
-    if cover_record_idx as usize >= record_offsets.len() {
-        return Err(anyhow!("Cover record index out of bounds"));
-    }
+    let cover_record_idx = mobi_image_record_index(
+        &mobi_header,
+        cover_offset.unwrap_or(0),
+        record_offsets.len(),
+    )
+    .ok_or_else(|| anyhow!("Cover record index out of bounds"))?;
 
-    let start = record_offsets[cover_record_idx as usize] as u64;
-    let end = if (cover_record_idx as usize + 1) < record_offsets.len() {
-        record_offsets[cover_record_idx as usize + 1] as u64
-    } else {
-        reader.seek(SeekFrom::End(0))?;
-        reader.stream_position()?
+    let start = record_offsets[cover_record_idx] as u64;
+    let end = match (record_offsets.get(cover_record_idx + 1), total_len) {
+        (Some(&next), _) => next as u64,
+        (None, Some(len)) => len,
+        (None, None) => reader.seek(SeekFrom::End(0))?,
     };
+    if end <= start {
+        return Err(anyhow!("Cover record {} is empty", cover_record_idx));
+    }
 
     let len = (end - start) as usize;
     reader.seek(SeekFrom::Start(start))?;
@@ -240,409 +509,6815 @@ pub fn extract_mobi_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>
     Err(anyhow!("No valid cover image found in MOBI"))
 }
 
+/// PalmDOC compression type of HUFF/CDIC-compressed text (`"DH"`).
+const MOBI_COMPRESSION_HUFF_CDIC: u16 = 17480;
+/// MOBI header value for "no such record".
+const MOBI_NO_INDEX: u32 = 0xFFFF_FFFF;
+
+/// Global record index of the `offset`-th image record, given record 0's
+/// PalmDOC + MOBI header.
+///
+/// Images start at the header's first image index, or right after the text
+/// records when that is missing or points into them. KindleGen's
+/// HUFF/CDIC compression stores its decompression tables as records too,
+/// and some writers count them into the image range; when the header
+/// declares that compression, the records it lists as HUFF/CDIC are
+/// skipped so the cover isn't read from a compression table.
+fn mobi_image_record_index(mobi_header: &[u8], offset: u32, record_count: usize) -> Option<usize> {
+    let be32 = |at: usize| {
+        u32::from_be_bytes([
+            mobi_header[at],
+            mobi_header[at + 1],
+            mobi_header[at + 2],
+            mobi_header[at + 3],
+        ])
+    };
+    let compression = u16::from_be_bytes([mobi_header[0], mobi_header[1]]);
+    let text_records = u16::from_be_bytes([mobi_header[8], mobi_header[9]]) as u32;
+
+    let huff_start = be32(112);
+    let huff_count = be32(116);
+    let huff_records = (compression == MOBI_COMPRESSION_HUFF_CDIC && huff_start != MOBI_NO_INDEX)
+        .then(|| huff_start..huff_start.saturating_add(huff_count));
+
+    let first_img_idx = match be32(108) {
+        MOBI_NO_INDEX => text_records + 1,
+        first if first <= text_records => text_records + 1,
+        first => first,
+    };
+    (first_img_idx..record_count as u32)
+        .filter(|idx| !huff_records.as_ref().is_some_and(|huff| huff.contains(idx)))
+        .nth(offset as usize)
+        .map(|idx| idx as usize)
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // CBZ extraction
 // ─────────────────────────────────────────────────────────────────────────────
 
 /// Extract cover image from CBZ (comic book ZIP) file.
+///
+/// This is the best-ranked page of [`rank_cbz_covers`], with the reading
+/// direction taken from ComicInfo.xml.
 pub fn extract_cbz_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    extract_cbz_cover_bytes_with_direction(reader, None)
+}
+
+/// Like [`extract_cbz_cover_bytes`], with a reading direction that overrides
+/// the one in ComicInfo.xml when given: for right-to-left manga the last
+/// page in natural order is taken as the cover.
+pub fn extract_cbz_cover_bytes_with_direction<R: Read + Seek>(
+    reader: R,
+    direction: Option<ReadingDirection>,
+) -> Result<Vec<u8>> {
+    extract_cbz_cover_bytes_with_password(reader, direction, None)
+}
+
+/// Like [`extract_cbz_cover_bytes_with_direction`], for a comic whose pages
+/// may be encrypted with `password`.
+pub fn extract_cbz_cover_bytes_with_password<R: Read + Seek>(
+    reader: R,
+    direction: Option<ReadingDirection>,
+    password: Option<&str>,
+) -> Result<Vec<u8>> {
+    let password = password.map(str::as_bytes);
+    let mut archive = ZipArchive::new(reader)?;
+    let best = rank_cbz_covers_with_direction(&mut archive, direction, password)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No images found in CBZ"))?;
+    read_zip_index(&mut archive, best.index, password)
+}
+
+/// Page progression of a comic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadingDirection {
+    #[default]
+    LeftToRight,
+    /// ComicInfo `<Manga>YesAndRightToLeft</Manga>`.
+    RightToLeft,
+}
+
+/// Reading direction a CBZ declares in its ComicInfo.xml, left-to-right
+/// when it has none.
+pub fn cbz_reading_direction<R: Read + Seek>(reader: R) -> Result<ReadingDirection> {
     let mut archive = ZipArchive::new(reader)?;
+    Ok(read_cbz_layout(&mut archive, None)?
+        .comic_info
+        .map(|info| info.direction)
+        .unwrap_or_default())
+}
+
+/// What ComicInfo.xml says about covers and page order.
+#[derive(Debug, Default)]
+struct ComicInfo {
+    /// Page indices marked as covers, in document order.
+    cover_pages: Vec<(usize, ComicCover)>,
+    direction: ReadingDirection,
+}
+
+/// Image entries of a CBZ in natural (reading) order, with its ComicInfo.
+struct CbzLayout {
+    images: Vec<(usize, String)>,
+    comic_info: Option<ComicInfo>,
+}
 
+fn read_cbz_layout<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    password: Option<&[u8]>,
+) -> Result<CbzLayout> {
     let mut images: Vec<(usize, String)> = Vec::new();
+    let mut comic_info: Option<usize> = None;
     for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
+        let file = archive.by_index_raw(i)?;
         let name = file.name().to_string();
         drop(file);
 
-        if is_image_extension(&name.to_lowercase()) {
+        let lower = name.to_lowercase();
+        // macOS archivers add `__MACOSX/._page.jpg` resource forks, which
+        // look like images by name but aren't.
+        if lower.starts_with("__macosx/") {
+            continue;
+        }
+        if is_image_extension(&lower) {
             images.push((i, name));
+        } else if lower.rsplit('/').next() == Some("comicinfo.xml") {
+            // Prefer the root-level ComicInfo.xml if several are present.
+            if comic_info.is_none() || !lower.contains('/') {
+                comic_info = Some(i);
+            }
         }
     }
 
-    images.sort_by(|a, b| a.1.cmp(&b.1));
+    images.sort_by(|a, b| natural_cmp(&a.1, &b.1));
 
-    if let Some((idx, _)) = images.first() {
-        let mut file = archive.by_index(*idx)?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        return Ok(buf);
-    }
+    let comic_info = comic_info.and_then(|idx| {
+        let mut file = open_zip_index(archive, idx, password).ok()?;
+        let mut xml = Vec::new();
+        file.read_to_end(&mut xml).ok()?;
+        Some(parse_comic_info(&xml))
+    });
+    Ok(CbzLayout { images, comic_info })
+}
 
-    Err(anyhow!("No images found in CBZ"))
+/// Pages of a CBZ that may be the cover, best first: the ComicInfo
+/// `FrontCover` page, the first page in reading order, then pages ComicInfo
+/// marks as `InnerCover` or `BackCover`.
+fn rank_cbz_covers<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<RankedEntry>> {
+    rank_cbz_covers_with_direction(archive, None, None)
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// FB2 extraction
-// ─────────────────────────────────────────────────────────────────────────────
+/// [`rank_cbz_covers`] for a given reading direction, or the one ComicInfo
+/// declares when `None`. Right-to-left, the first page is the last one in
+/// natural order.
+fn rank_cbz_covers_with_direction<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    direction: Option<ReadingDirection>,
+    password: Option<&[u8]>,
+) -> Result<Vec<RankedEntry>> {
+    let CbzLayout { images, comic_info } = read_cbz_layout(archive, password)?;
+    let comic_info = comic_info.unwrap_or_default();
+    let direction = direction.unwrap_or(comic_info.direction);
+    // ComicInfo `<Page Image="n" Type="..."/>` indexes into the archive's
+    // pages in reading order.
+    let typed_pages = comic_info.cover_pages;
 
-/// Extract cover image from FB2 (FictionBook) file.
-pub fn extract_fb2_cover_bytes<R: Read>(mut reader: R) -> Result<Vec<u8>> {
-    let mut content = String::new();
-    reader.read_to_string(&mut content)?;
-
-    let cover_id = if let Some(start) = content.find("<coverpage>") {
-        let end = content[start..].find("</coverpage>").unwrap_or(500);
-        let coverpage = &content[start..start + end];
-        if let Some(href_pos) = coverpage.find("href=\"#") {
-            let id_start = href_pos + 7;
-            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
-            Some(coverpage[id_start..id_start + id_end].to_string())
-        } else if let Some(href_pos) = coverpage.find("l:href=\"#") {
-            let id_start = href_pos + 9;
-            let id_end = coverpage[id_start..].find('"').unwrap_or(50);
-            Some(coverpage[id_start..id_start + id_end].to_string())
-        } else {
-            None
+    let mut ranked: Vec<RankedEntry> = Vec::new();
+    let mut push = |page: usize, score: u8| {
+        if let Some((index, name)) = images.get(page) {
+            if !ranked.iter().any(|r| r.index == *index) {
+                ranked.push(RankedEntry {
+                    index: *index,
+                    name: name.clone(),
+                    score,
+                });
+            }
         }
-    } else {
-        None
-    };
-
-    let search_pattern = if let Some(ref id) = cover_id {
-        format!("<binary id=\"{}\"", id)
-    } else {
-        "<binary".to_string()
     };
-
-    if let Some(pos) = content.find(&search_pattern) {
-        if let Some(tag_end) = content[pos..].find('>') {
-            let data_start = pos + tag_end + 1;
-            if let Some(data_end) = content[data_start..].find("</binary>") {
-                let b64_data = content[data_start..data_start + data_end].trim();
-                let b64_clean: String = b64_data.chars().filter(|c| !c.is_whitespace()).collect();
-                let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
-                return Ok(bytes);
-            }
+    for (page, kind) in &typed_pages {
+        if *kind == ComicCover::Front {
+            push(*page, 100);
         }
     }
-
-    if cover_id.is_some() {
-        if let Some(pos) = content.find("<binary") {
-            if let Some(tag_end) = content[pos..].find('>') {
-                let data_start = pos + tag_end + 1;
-                if let Some(data_end) = content[data_start..].find("</binary>") {
-                    let b64_data = content[data_start..data_start + data_end].trim();
-                    let b64_clean: String =
-                        b64_data.chars().filter(|c| !c.is_whitespace()).collect();
-                    let bytes = general_purpose::STANDARD.decode(&b64_clean)?;
-                    return Ok(bytes);
-                }
-            }
+    let first_page = match direction {
+        ReadingDirection::LeftToRight => 0,
+        ReadingDirection::RightToLeft => images.len().saturating_sub(1),
+    };
+    push(first_page, 80);
+    for (page, kind) in &typed_pages {
+        match kind {
+            ComicCover::Inner => push(*page, 60),
+            ComicCover::Back => push(*page, 40),
+            ComicCover::Front => {}
         }
     }
 
-    Err(anyhow!("No cover image found in FB2"))
+    ranked.sort_by_key(|r| std::cmp::Reverse(r.score));
+    Ok(ranked)
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// TXT "cover" (placeholder)
-// ─────────────────────────────────────────────────────────────────────────────
-
-/// Generate a placeholder thumbnail for TXT files.
-pub fn extract_txt_cover_bytes<R: Read>(mut reader: R, size: u32) -> Result<Vec<u8>> {
-    let mut buf = vec![0u8; 4096];
-    let _n = reader.read(&mut buf)?;
+/// ComicInfo page types that show a cover (`FrontCover`, `InnerCover`,
+/// `BackCover`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComicCover {
+    Front,
+    Inner,
+    Back,
+}
 
-    let mut img = image::RgbaImage::from_pixel(size, size, Rgba([245, 245, 245, 255]));
+/// Cover pages and reading direction from ComicInfo.xml.
+fn parse_comic_info(xml: &[u8]) -> ComicInfo {
+    let mut reader = XmlReader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut info = ComicInfo::default();
+    let mut in_manga = false;
 
-    for x in 0..size {
-        img.put_pixel(x, 0, Rgba([200, 200, 200, 255]));
-        img.put_pixel(x, size - 1, Rgba([200, 200, 200, 255]));
-    }
-    for y in 0..size {
-        img.put_pixel(0, y, Rgba([200, 200, 200, 255]));
-        img.put_pixel(size - 1, y, Rgba([200, 200, 200, 255]));
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.local_name().as_ref() == b"Page" => {
+                let mut image = None;
+                let mut kind = None;
+                for attr in e.attributes().flatten() {
+                    match attr.key.local_name().as_ref() {
+                        b"Image" => {
+                            image = std::str::from_utf8(&attr.value)
+                                .ok()
+                                .and_then(|v| v.trim().parse::<usize>().ok());
+                        }
+                        b"Type" => {
+                            kind = match attr.value.as_ref() {
+                                b"FrontCover" => Some(ComicCover::Front),
+                                b"InnerCover" => Some(ComicCover::Inner),
+                                b"BackCover" => Some(ComicCover::Back),
+                                _ => None,
+                            };
+                        }
+                        _ => {}
+                    }
+                }
+                if let (Some(image), Some(kind)) = (image, kind) {
+                    info.cover_pages.push((image, kind));
+                }
+            }
+            Ok(Event::Start(e)) if e.local_name().as_ref() == b"Manga" => in_manga = true,
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"Manga" => in_manga = false,
+            // `Yes` alone is manga read left to right (e.g. flipped scans).
+            Ok(Event::Text(t)) if in_manga && t.as_ref().trim_ascii() == b"YesAndRightToLeft" => {
+                info.direction = ReadingDirection::RightToLeft;
+            }
+            Ok(Event::Eof) | Err(_) => return info,
+            _ => {}
+        }
+        buf.clear();
     }
-
-    let mut out = Vec::new();
-    DynamicImage::ImageRgba8(img).write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
-    Ok(out)
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// Unified extraction by extension
-// ─────────────────────────────────────────────────────────────────────────────
+/// Compare file names the way a person would order pages: runs of digits
+/// compare by value, so `page2.jpg` sorts before `page10.jpg`.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
 
-/// Extract cover image bytes based on file extension.
-pub fn extract_cover_bytes_by_ext(path: &Path, ext: &str) -> Result<Vec<u8>> {
-    let file = std::fs::File::open(path)?;
-    match ext.to_lowercase().as_str() {
-        "epub" => extract_epub_cover_bytes(file),
-        "mobi" | "azw" | "azw3" | "kf8" | "prc" => extract_mobi_cover_bytes(file),
-        "cbz" | "cbr" => extract_cbz_cover_bytes(file),
-        "fb2" => extract_fb2_cover_bytes(file),
-        "txt" => extract_txt_cover_bytes(file, 256),
-        _ => Err(anyhow!("Unsupported format: {}", ext)),
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    while let (Some(&ca), Some(&cb)) = (a.first(), b.first()) {
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let a_len = a.iter().take_while(|c| c.is_ascii_digit()).count();
+            let b_len = b.iter().take_while(|c| c.is_ascii_digit()).count();
+            let (a_num, a_rest) = a.split_at(a_len);
+            let (b_num, b_rest) = b.split_at(b_len);
+            let a_trim = &a_num[a_num.iter().take_while(|&&c| c == b'0').count()..];
+            let b_trim = &b_num[b_num.iter().take_while(|&&c| c == b'0').count()..];
+            let ord = a_trim
+                .len()
+                .cmp(&b_trim.len())
+                .then_with(|| a_trim.cmp(b_trim))
+                .then_with(|| a_len.cmp(&b_len));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            a = a_rest;
+            b = b_rest;
+        } else {
+            let ord = ca.to_ascii_lowercase().cmp(&cb.to_ascii_lowercase());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            a = &a[1..];
+            b = &b[1..];
+        }
     }
+    a.len().cmp(&b.len())
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// Thumbnail creation with overlay
+// FB2 extraction
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Create a thumbnail from cover image bytes with Readest icon overlay.
-pub fn create_thumbnail_with_overlay(cover_bytes: &[u8], requested_size: u32) -> Result<Vec<u8>> {
-    let img = image::load_from_memory(cover_bytes)?;
-    let thumbnail = img.thumbnail(requested_size, requested_size);
-
-    let overlay_img = load_overlay_icon();
+/// Extract cover image from FB2 (FictionBook) file.
+///
+/// FB2 is commonly shipped zipped (`.fb2.zip` / `.fbz`) under an `.fb2`
+/// name, so a ZIP signature is routed through [`extract_fbz_cover_bytes`].
+pub fn extract_fb2_cover_bytes<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if bytes.starts_with(ZIP_MAGIC) {
+        return extract_fbz_cover_bytes(Cursor::new(bytes));
+    }
 
-    let mut base = thumbnail.to_rgba8();
-    let (base_w, base_h) = (base.width(), base.height());
+    let (cover_ids, binaries) = scan_fb2(&bytes)?;
+    let (cover, _) = rank_fb2_covers(&cover_ids, &binaries)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No cover image found in FB2"))?;
+    cover.decode()
+}
 
-    if let Some(ov) = overlay_img {
-        let overlay_size = (requested_size / 5).clamp(24, 48);
-        let ov_resized = ov.resize(overlay_size, overlay_size, imageops::FilterType::Lanczos3);
-        let ovb = ov_resized.to_rgba8();
-        let (ov_w, ov_h) = (ovb.width(), ovb.height());
+/// An image `<binary>` block from an FB2 document, body still base64.
+struct Fb2Binary {
+    id: String,
+    data: String,
+}
 
-        let x = base_w.saturating_sub(ov_w + 4);
-        let y = base_h.saturating_sub(ov_h + 4);
+impl Fb2Binary {
+    fn decode(&self) -> Result<Vec<u8>> {
+        let b64_clean: String = self.data.chars().filter(|c| !c.is_whitespace()).collect();
+        Ok(general_purpose::STANDARD.decode(&b64_clean)?)
+    }
+}
 
-        for oy in 0..ov_h {
-            for ox in 0..ov_w {
-                let dst_x = x + ox;
-                let dst_y = y + oy;
+/// Image binaries ranked as covers, best first: the first `<coverpage>`
+/// image, any further coverpage images (often the back cover), then every
+/// other image in document order.
+fn rank_fb2_covers<'a>(
+    cover_ids: &[String],
+    binaries: &'a [Fb2Binary],
+) -> Vec<(&'a Fb2Binary, u8)> {
+    let mut ranked: Vec<(&Fb2Binary, u8)> = binaries
+        .iter()
+        .map(|binary| {
+            let score = match cover_ids.iter().position(|id| *id == binary.id) {
+                Some(0) => 100,
+                Some(_) => 70,
+                None => 30,
+            };
+            (binary, score)
+        })
+        .collect();
+    ranked.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    ranked
+}
 
-                if dst_x < base_w && dst_y < base_h {
-                    let src_pixel = ovb.get_pixel(ox, oy);
-                    let alpha = src_pixel.0[3] as f32 / 255.0;
+/// Walk an FB2 document once, returning the ids referenced by
+/// `<coverpage><image href="#id"/>` and every image `<binary>` in document
+/// order. Tags and attributes are matched by local name, so namespaced forms
+/// (`l:href`, `xlink:href`, `fb:binary`) and any attribute order are handled.
+/// Binaries without a `content-type` are kept, since older FB2 generators
+/// omit it.
+fn scan_fb2(bytes: &[u8]) -> Result<(Vec<String>, Vec<Fb2Binary>)> {
+    let mut reader = XmlReader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
 
-                    if alpha > 0.0 {
-                        let dst_pixel = base.get_pixel(dst_x, dst_y);
-                        let mut result = dst_pixel.0;
+    let mut cover_ids: Vec<String> = Vec::new();
+    let mut binaries: Vec<Fb2Binary> = Vec::new();
+    let mut in_coverpage = false;
+    let mut current_binary: Option<Fb2Binary> = None;
 
-                        for c in 0..3 {
-                            let fg = src_pixel.0[c] as f32;
-                            let bg = result[c] as f32;
-                            result[c] = (fg * alpha + bg * (1.0 - alpha)) as u8;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"coverpage" => in_coverpage = true,
+                b"image" if in_coverpage => cover_ids.extend(fb2_image_href(&e)),
+                b"binary" => {
+                    let mut id = String::new();
+                    let mut is_image = true;
+                    for attr in e.attributes().with_checks(false).flatten() {
+                        match attr.key.local_name().as_ref() {
+                            b"id" => id = String::from_utf8_lossy(&attr.value).into_owned(),
+                            b"content-type" => {
+                                is_image = attr.value.starts_with(b"image/");
+                            }
+                            _ => {}
                         }
-                        result[3] = 255;
-
-                        base.put_pixel(dst_x, dst_y, Rgba(result));
                     }
+                    if is_image {
+                        current_binary = Some(Fb2Binary {
+                            id,
+                            data: String::new(),
+                        });
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Empty(e)) if in_coverpage && e.local_name().as_ref() == b"image" => {
+                cover_ids.extend(fb2_image_href(&e));
+            }
+            Ok(Event::Text(t)) => {
+                if let Some(binary) = current_binary.as_mut() {
+                    binary.data.push_str(&String::from_utf8_lossy(&t));
                 }
             }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"coverpage" => in_coverpage = false,
+                b"binary" => binaries.extend(current_binary.take()),
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(anyhow!("FB2 XML error: {}", e)),
+            _ => {}
         }
+        buf.clear();
     }
 
-    let mut out = Vec::new();
-    DynamicImage::ImageRgba8(base).write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
-    Ok(out)
+    Ok((cover_ids, binaries))
 }
 
-/// Load the Readest overlay icon.
-fn load_overlay_icon() -> Option<DynamicImage> {
-    // Try embedded icon
-    let icon_bytes = include_bytes!("../../../public/icon.png");
-    if let Ok(img) = image::load_from_memory(icon_bytes) {
-        return Some(img);
-    }
+/// `href`/`l:href`/`xlink:href` of a coverpage `<image>`, without the `#`.
+fn fb2_image_href(e: &BytesStart) -> Option<String> {
+    e.attributes()
+        .with_checks(false)
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == b"href")
+        .map(|attr| {
+            String::from_utf8_lossy(&attr.value)
+                .trim_start_matches('#')
+                .to_string()
+        })
+        .filter(|id| !id.is_empty())
+}
 
-    // Fallback: try loading from filesystem
-    if let Ok(exe) = std::env::current_exe() {
-        let candidates = [
-            exe.parent().map(|p| p.join("icon.png")),
-            exe.parent().map(|p| p.join("resources").join("icon.png")),
-            exe.parent()
-                .and_then(|p| p.parent())
-                .map(|p| p.join("resources").join("icon.png")),
-        ];
+/// Extract cover image from a zipped FB2 (`.fb2.zip` / `.fbz`) by unpacking
+/// its `.fb2` entry and feeding it to the plain FB2 parser.
+pub fn extract_fbz_cover_bytes<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    extract_fb2_cover_bytes(Cursor::new(read_fbz_document(reader)?))
+}
 
-        for candidate in candidates.into_iter().flatten() {
-            if candidate.exists() {
-                if let Ok(bytes) = std::fs::read(&candidate) {
-                    if let Ok(img) = image::load_from_memory(&bytes) {
-                        return Some(img);
-                    }
-                }
-            }
+/// The `.fb2` document inside a zipped FB2.
+fn read_fbz_document<R: Read + Seek>(reader: R) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    let mut fb2_idx = None;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        if file.is_file() && file.name().to_lowercase().ends_with(".fb2") {
+            fb2_idx = Some(i);
+            break;
         }
     }
 
-    None
-}
-
-// ─────────────────────────────────────────────────────────────────────────────
-// Caching
+    let idx = fb2_idx.ok_or_else(|| anyhow!("No .fb2 entry found in FB2 archive"))?;
+    let mut file = archive.by_index(idx)?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    if buf.starts_with(ZIP_MAGIC) {
+        return Err(anyhow!("Nested archive in FB2 archive"));
+    }
+    Ok(buf)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// KFX (recognized, not supported)
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// Generate a thumbnail with disk caching.
-pub fn cached_thumbnail_for_path(path: &Path, ext: &str, size: u32) -> Result<Vec<u8>> {
-    // Compute cache key by hashing file parts for stability without loading entire file
-    let mut hasher = Context::new();
-    hasher.consume(ext.as_bytes());
-    hasher.consume(&size.to_le_bytes());
+/// Signature of a KFX container (`CONT` followed by the Ion-encoded header).
+const KFX_CONTAINER_MAGIC: &[u8] = b"CONT";
+/// Signature of a DRM-wrapped KFX container.
+const KFX_DRMION_MAGIC: &[u8] = b"\xeaDRMION\xee";
+/// Signature of the SQLite database used by KDF (Kindle Create) books.
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
 
-    let file = std::fs::File::open(path)?;
-    let metadata = file.metadata()?;
-    let file_len = metadata.len();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KfxKind {
+    Container,
+    Drm,
+    Zip,
+    Kdf,
+}
 
-    // Read partial chunks like the TypeScript partialMD5 implementation
-    const STEP: u64 = 1024;
-    const SIZE: u64 = 1024;
-    let mut file = file;
+/// Identify the KFX flavour from the first bytes of a file, if it is one.
+fn detect_kfx(head: &[u8]) -> Option<KfxKind> {
+    if head.starts_with(KFX_DRMION_MAGIC) {
+        Some(KfxKind::Drm)
+    } else if head.starts_with(KFX_CONTAINER_MAGIC) {
+        Some(KfxKind::Container)
+    } else if head.starts_with(SQLITE_MAGIC) {
+        Some(KfxKind::Kdf)
+    } else {
+        None
+    }
+}
 
-    for i in -1i32..=10 {
-        let pos = if i == -1 {
-            256u64
-        } else {
-            STEP << (2 * i as u32)
-        };
-        let start = pos.min(file_len);
-        let end = (start + SIZE).min(file_len);
+fn kfx_unsupported(kind: KfxKind) -> CoverError {
+    let what = match kind {
+        KfxKind::Container => "KFX book",
+        KfxKind::Drm => "DRM-protected KFX book",
+        KfxKind::Zip => "KFX-ZIP archive",
+        KfxKind::Kdf => "KDF (Kindle Create) book",
+    };
+    CoverError::Unsupported(format!(
+        "Cannot read the cover of this {what}: KFX, KFX-ZIP and KDF are not supported. \
+         Supported Kindle formats are MOBI, PRC, AZW and AZW3/KF8."
+    ))
+}
 
-        if start >= file_len {
-            break;
+/// Recognize a KFX-family file and report why its cover can't be extracted.
+///
+/// Always fails: with [`CoverError::Unsupported`] for KFX containers (DRM
+/// noted when present), KFX-ZIP archives and KDF databases, or with a plain
+/// error when the file isn't KFX at all.
+pub fn extract_kfx_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>> {
+    let mut head = [0u8; 16];
+    let mut filled = 0;
+    while filled < head.len() {
+        match reader.read(&mut head[filled..])? {
+            0 => break,
+            n => filled += n,
         }
+    }
+    let head = &head[..filled];
 
-        file.seek(SeekFrom::Start(start))?;
-        let mut buf = vec![0u8; (end - start) as usize];
-        file.read_exact(&mut buf)?;
-        hasher.consume(&buf);
+    let kind = if head.starts_with(ZIP_MAGIC) {
+        reader.seek(SeekFrom::Start(0))?;
+        let archive = ZipArchive::new(reader)?;
+        let has_kfx_part = archive
+            .file_names()
+            .any(|name| name.to_lowercase().ends_with(".kfx"));
+        if !has_kfx_part {
+            return Err(anyhow!("Not a valid KFX-ZIP file"));
+        }
+        KfxKind::Zip
+    } else {
+        detect_kfx(head).ok_or_else(|| anyhow!("Not a valid KFX file"))?
+    };
+
+    Err(kfx_unsupported(kind).into())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// LIT (Microsoft Reader)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Signature of a Microsoft Reader `.lit` file.
+const LIT_MAGIC: &[u8] = b"ITOLITLS";
+/// Directory entry present in books locked to their owner ("owner
+/// exclusive", DRM level 5). Sealed and inscribed books carry a key anyone
+/// can derive, and their images aren't encrypted.
+const LIT_OWNER_LICENSE: &str = "/DRMStorage/Licenses/EUL";
+/// Largest directory (header piece 1) read, and largest image returned.
+const MAX_LIT_DIRECTORY: u32 = 16 * 1024 * 1024;
+const MAX_LIT_IMAGE: u64 = 64 * 1024 * 1024;
+
+/// A file inside a LIT book. Section 0 holds data stored as is; the others
+/// are LZX-compressed and usually sealed (DES-encrypted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LitEntry {
+    name: String,
+    section: u64,
+    offset: u64,
+    size: u64,
+}
+
+/// Whether `head` starts like a LIT book.
+pub fn is_lit(head: &[u8]) -> bool {
+    head.starts_with(LIT_MAGIC)
+}
+
+fn lit_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("Truncated LIT header"))
+}
+
+/// A LIT variable-length integer: 7 bits per byte, most significant first,
+/// the high bit set on all but the last byte.
+fn lit_encint(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for _ in 0..9 {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// The directory of a LIT book and the offset its section 0 starts at.
+fn read_lit_directory<R: Read + Seek>(reader: &mut R) -> Result<(Vec<LitEntry>, u64)> {
+    let mut header = [0u8; 24];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    if !is_lit(&header) {
+        return Err(anyhow!("Not a valid LIT file"));
+    }
+    let header_len = lit_u32(&header, 12)?;
+    let pieces = lit_u32(&header, 16)?;
+    let secondary_len = lit_u32(&header, 20)?;
+    if !(2..=16).contains(&pieces) || secondary_len > 4096 {
+        return Err(anyhow!("Invalid LIT header"));
     }
 
-    let digest = hasher.finalize();
-    let key = format!("{:x}.png", digest);
+    // Piece table (offset and size, both 64-bit) then the secondary header.
+    let mut table = vec![0u8; pieces as usize * 16 + secondary_len as usize];
+    reader.seek(SeekFrom::Start(u64::from(header_len)))?;
+    reader.read_exact(&mut table)?;
+    let secondary = &table[pieces as usize * 16..];
+    let mut content_offset = None;
+    let mut block = lit_u32(secondary, 4)? as usize;
+    while block + 48 <= secondary.len() {
+        match &secondary[block..block + 4] {
+            b"ITSF" => content_offset = Some(lit_u32(secondary, block + 16)?),
+            b"CAOL" => {}
+            _ => break,
+        }
+        block += 48;
+    }
+    let content_offset = content_offset.ok_or_else(|| anyhow!("LIT content offset not found"))?;
 
-    if let Some(ref dir) = *CACHE_DIR {
-        let cache_path = dir.join(&key);
-        if cache_path.exists() {
-            if let Ok(cached) = std::fs::read(&cache_path) {
-                return Ok(cached);
+    // Piece 1 is the directory: an `IFCM` header and `AOLL` listing chunks.
+    let (dir_offset, dir_len) = (lit_u32(&table, 16)?, lit_u32(&table, 24)?);
+    if lit_u32(&table, 20)? != 0 || lit_u32(&table, 28)? != 0 || dir_len > MAX_LIT_DIRECTORY {
+        return Err(anyhow!("Invalid LIT directory"));
+    }
+    let mut dir = vec![0u8; dir_len as usize];
+    reader.seek(SeekFrom::Start(u64::from(dir_offset)))?;
+    reader.read_exact(&mut dir)?;
+    if !dir.starts_with(b"IFCM") {
+        return Err(anyhow!("Invalid LIT directory"));
+    }
+    let chunk_len = lit_u32(&dir, 8)? as usize;
+    let chunks = lit_u32(&dir, 24)? as usize;
+    if chunk_len < 50 || 32 + chunks.saturating_mul(chunk_len) > dir.len() {
+        return Err(anyhow!("Invalid LIT directory"));
+    }
+
+    let mut entries = Vec::new();
+    for chunk in dir[32..32 + chunks * chunk_len].chunks_exact(chunk_len) {
+        if !chunk.starts_with(b"AOLL") {
+            continue;
+        }
+        // The quick-reference area at the end of the chunk isn't listing.
+        let quickref = lit_u32(chunk, 4)? as usize;
+        let end = chunk_len.saturating_sub(quickref).max(48);
+        let listing = &chunk[..end];
+        let mut pos = 48;
+        while pos < listing.len() {
+            let Some(name_len) = lit_encint(listing, &mut pos) else {
+                break;
+            };
+            let Some(name) = listing
+                .get(pos..pos.saturating_add(name_len as usize))
+                .and_then(|name| std::str::from_utf8(name).ok())
+            else {
+                break;
+            };
+            pos += name_len as usize;
+            let (Some(section), Some(offset), Some(size)) = (
+                lit_encint(listing, &mut pos),
+                lit_encint(listing, &mut pos),
+                lit_encint(listing, &mut pos),
+            ) else {
+                break;
+            };
+            if name_len == 0 {
+                break;
             }
+            entries.push(LitEntry {
+                name: name.to_string(),
+                section,
+                offset,
+                size,
+            });
         }
     }
+    Ok((entries, u64::from(content_offset)))
+}
 
-    let cover = extract_cover_bytes_by_ext(path, ext)?;
-    let thumbnail = create_thumbnail_with_overlay(&cover, size)?;
-
-    if let Some(ref dir) = *CACHE_DIR {
-        let cache_path = dir.join(&key);
-        let _ = std::fs::write(&cache_path, &thumbnail);
+/// Extract the cover of a Microsoft Reader `.lit` book.
+///
+/// LIT books name no cover and keep their text compressed and sealed, but
+/// images are stored as is, so the cover is the uncompressed image under
+/// `/data/` whose name mentions "cover", else the first one. Fails with
+/// [`CoverError::DrmProtected`] for owner-exclusive books and with
+/// [`CoverError::Unsupported`] when no image is stored uncompressed.
+pub fn extract_lit_cover_bytes<R: Read + Seek>(mut reader: R) -> Result<Vec<u8>> {
+    let (entries, content_offset) = read_lit_directory(&mut reader)?;
+    if entries.iter().any(|entry| entry.name == LIT_OWNER_LICENSE) {
+        return Err(CoverError::DrmProtected(
+            "Cannot read the cover of this LIT book: it is locked to its owner's Microsoft \
+             Reader activation."
+                .to_string(),
+        )
+        .into());
     }
 
-    Ok(thumbnail)
+    let mut read_entry = |entry: &LitEntry, len: u64| -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(content_offset + entry.offset))?;
+        let mut bytes = Vec::new();
+        (&mut reader).take(len).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    };
+    let mut images = Vec::new();
+    for entry in entries.iter().filter(|entry| {
+        entry.section == 0
+            && entry.name.starts_with("/data/")
+            && (16..=MAX_LIT_IMAGE).contains(&entry.size)
+    }) {
+        if image::guess_format(&read_entry(entry, 16)?).is_ok() {
+            images.push(entry);
+        }
+    }
+    images.sort_by_key(|entry| !entry.name.to_lowercase().contains("cover"));
+    let cover = images.first().ok_or_else(|| {
+        CoverError::Unsupported(
+            "Cannot read the cover of this LIT book: its images are compressed, which isn't \
+             supported."
+                .to_string(),
+        )
+    })?;
+    read_entry(cover, cover.size)
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// Helper functions
+// TXT "cover" (placeholder)
 // ─────────────────────────────────────────────────────────────────────────────
 
-fn is_image_extension(name: &str) -> bool {
-    name.ends_with(".jpg")
-        || name.ends_with(".jpeg")
-        || name.ends_with(".png")
-        || name.ends_with(".gif")
-        || name.ends_with(".webp")
-        || name.ends_with(".bmp")
+/// Light grey tile with a one-pixel border, used wherever a book has no
+/// cover image of its own.
+fn placeholder_image(width: u32, height: u32) -> image::RgbaImage {
+    let mut img = image::RgbaImage::from_pixel(width, height, Rgba([245, 245, 245, 255]));
+
+    for x in 0..width {
+        img.put_pixel(x, 0, Rgba([200, 200, 200, 255]));
+        img.put_pixel(x, height - 1, Rgba([200, 200, 200, 255]));
+    }
+    for y in 0..height {
+        img.put_pixel(0, y, Rgba([200, 200, 200, 255]));
+        img.put_pixel(width - 1, y, Rgba([200, 200, 200, 255]));
+    }
+    img
 }
 
-fn read_zip_file_to_string<R: Read + Seek>(
-    archive: &mut ZipArchive<R>,
-    name: &str,
-) -> Result<String> {
-    let mut file = archive.by_name(name)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    Ok(content)
+/// Most of a TXT file read by [`read_txt_sample`].
+pub const TXT_SAMPLE_BYTES: usize = 4096;
+
+/// The opening text of a TXT file, decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSample {
+    pub text: String,
+    /// WHATWG name of the encoding, e.g. `UTF-8`, `UTF-16LE`, `Shift_JIS`.
+    pub encoding: &'static str,
 }
 
-fn read_zip_file_to_bytes<R: Read + Seek>(
-    archive: &mut ZipArchive<R>,
-    name: &str,
+/// Decode the first [`TXT_SAMPLE_BYTES`] of a TXT file for a text preview.
+/// See [`decode_text_sample`].
+pub fn read_txt_sample<R: Read>(reader: R) -> Result<TextSample> {
+    let mut bytes = Vec::with_capacity(TXT_SAMPLE_BYTES);
+    reader
+        .take(TXT_SAMPLE_BYTES as u64)
+        .read_to_end(&mut bytes)?;
+    Ok(decode_text_sample(&bytes, bytes.len() < TXT_SAMPLE_BYTES))
+}
+
+/// Decode `bytes` from the start of a text file. A byte-order mark decides
+/// the encoding; failing that, BOM-less UTF-16 is recognised by its zero
+/// bytes, and anything else is left to `chardetng`. When the sample isn't
+/// the `complete` file, a multi-byte sequence cut off at its end is dropped
+/// rather than decoded as U+FFFD.
+pub fn decode_text_sample(bytes: &[u8], complete: bool) -> TextSample {
+    let (encoding, body) = match encoding_rs::Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        None => (
+            sniff_utf16(bytes).unwrap_or_else(|| {
+                let mut detector = chardetng::EncodingDetector::new();
+                detector.feed(bytes, complete);
+                detector.guess(None, true)
+            }),
+            bytes,
+        ),
+    };
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length(body.len())
+            .unwrap_or(body.len() * 3),
+    );
+    // With `last` unset the decoder keeps a trailing partial sequence
+    // pending instead of replacing it.
+    let _ = decoder.decode_to_string(body, &mut text, complete);
+    TextSample {
+        text,
+        encoding: encoding.name(),
+    }
+}
+
+/// UTF-16 without a BOM: in Latin-script text most code units have a zero
+/// high byte, so one byte position is mostly zeros and the other never is.
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    let units = bytes.len().min(1024) / 2;
+    if units < 4 {
+        return None;
+    }
+    let (mut even, mut odd) = (0, 0);
+    for pair in bytes[..units * 2].chunks_exact(2) {
+        even += usize::from(pair[0] == 0);
+        odd += usize::from(pair[1] == 0);
+    }
+    if odd * 2 > units && even == 0 {
+        Some(encoding_rs::UTF_16LE)
+    } else if even * 2 > units && odd == 0 {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Generate a placeholder thumbnail for TXT files. The tile carries no text,
+/// as this crate has no font to draw it with, so `_reader` is never read; it
+/// is taken like every other extractor's. [`read_txt_sample`] decodes the
+/// opening text for callers that render a preview themselves.
+pub fn extract_txt_cover_bytes<R: Read>(_reader: R, size: u32) -> Result<Vec<u8>> {
+    let img = placeholder_image(size, size);
+
+    let mut out = Vec::new();
+    DynamicImage::ImageRgba8(img).write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
+    Ok(out)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// HTML extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Most of an HTML book read. SingleFile exports inline their images, so
+/// this is generous, but a runaway file no longer fills memory.
+const MAX_HTML_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Extract a cover from a single-file HTML book (a SingleFile export, a
+/// Project Gutenberg HTML edition).
+///
+/// Images are tried best first (see [`rank_html_images`]): `data:` URIs are
+/// decoded inline and relative paths are read from `base_dir`, the book's
+/// directory. Remote URLs and absolute paths are never followed. When no
+/// image decodes, the TXT placeholder is returned; it carries no `<title>`
+/// text, as this crate has no font to draw it with.
+pub fn extract_html_cover_bytes<R: Read>(
+    reader: R,
+    base_dir: Option<&Path>,
+    size: u32,
 ) -> Result<Vec<u8>> {
-    let mut file = archive.by_name(name)?;
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
-    Ok(buf)
+    let mut bytes = Vec::new();
+    reader.take(MAX_HTML_BYTES).read_to_end(&mut bytes)?;
+    let html = String::from_utf8_lossy(&bytes);
+
+    let cover = rank_html_images(&html)
+        .into_iter()
+        .filter_map(|(src, _)| html_image_bytes(&src, base_dir))
+        .find(|bytes| image::guess_format(bytes).is_ok());
+    match cover {
+        Some(bytes) => Ok(bytes),
+        None => extract_txt_cover_bytes(&bytes[..], size),
+    }
 }
 
-fn extract_attribute(xml: &str, tag: &str, attr: &str) -> Option<String> {
-    let pattern = format!("<{}", tag);
-    if let Some(tag_pos) = xml.find(&pattern) {
-        let tag_end = xml[tag_pos..].find('>').unwrap_or(500) + tag_pos;
-        let tag_content = &xml[tag_pos..tag_end];
+/// `src` of every `<img>` (and SVG `<image>`) in `html`, ranked as covers,
+/// best first: images naming a cover in their id, class, alt text or file
+/// name, then images inside `<header>`, then the first image, then the rest
+/// in document order. Comments, scripts and styles are skipped.
+fn rank_html_images(html: &str) -> Vec<(String, u8)> {
+    let lower = html.to_ascii_lowercase();
+    let mut ranked: Vec<(String, u8)> = Vec::new();
+    let mut in_header = false;
+    let mut pos = 0;
 
-        let attr_pattern = format!("{}=\"", attr);
-        if let Some(attr_pos) = tag_content.find(&attr_pattern) {
-            let value_start = attr_pos + attr_pattern.len();
-            if let Some(value_end) = tag_content[value_start..].find('"') {
-                return Some(tag_content[value_start..value_start + value_end].to_string());
+    while let Some(offset) = lower[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &lower[start..];
+        if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(lower.len(), |end| start + end + 3);
+            continue;
+        }
+        let name_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '/' || c == ':'))
+            .unwrap_or(rest.len() - 1);
+        let name = &rest[1..1 + name_len];
+        let end = html_tag_end(&lower, start + 1 + name_len);
+        let body_end = if lower[..end].ends_with('>') {
+            end - 1
+        } else {
+            end
+        };
+        let attrs = html_attributes(&html[start + 1 + name_len..body_end]);
+        pos = end;
+
+        match name.rsplit(':').next().unwrap_or(name) {
+            "header" => in_header = true,
+            "/header" => in_header = false,
+            "script" | "style" => {
+                let close = format!("</{name}");
+                pos = lower[pos..].find(&close).map_or(lower.len(), |i| pos + i);
             }
+            "img" | "image" => {
+                let Some(src) = attrs
+                    .iter()
+                    .find(|(key, _)| matches!(key.as_str(), "src" | "href"))
+                    .map(|(_, value)| value.replace("&amp;", "&"))
+                    .filter(|src| !src.trim().is_empty())
+                else {
+                    continue;
+                };
+                let names_cover = attrs.iter().any(|(key, value)| {
+                    matches!(key.as_str(), "id" | "class" | "alt")
+                        && value.to_lowercase().contains("cover")
+                }) || (!src.trim_start().to_lowercase().starts_with("data:")
+                    && src.to_lowercase().contains("cover"));
+                let score = if names_cover {
+                    100
+                } else if in_header {
+                    80
+                } else if ranked.is_empty() {
+                    50
+                } else {
+                    30
+                };
+                ranked.push((src, score));
+            }
+            _ => {}
         }
     }
-    None
+
+    ranked.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    ranked
 }
 
-fn find_cover_id_in_opf(opf: &str) -> Option<String> {
-    if let Some(pos) = opf.find("name=\"cover\"") {
-        let window_start = pos.saturating_sub(50);
-        let window_end = (pos + 100).min(opf.len());
-        let window = &opf[window_start..window_end];
+/// Offset just past the `>` closing the tag whose attributes start at
+/// `from`, ignoring any `>` inside quoted values.
+fn html_tag_end(lower: &str, from: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in lower[from..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return from + i + 1,
+            _ => {}
+        }
+    }
+    lower.len()
+}
 
-        if let Some(content_pos) = window.find("content=\"") {
-            let start = content_pos + 9;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
+/// Attributes of a tag body as (lower-cased local name, value) pairs; values
+/// may be double-, single- or unquoted.
+fn html_attributes(body: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = body.trim_start_matches('/');
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        let key_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let key = rest[..key_len].to_ascii_lowercase();
+        let key = key.rsplit(':').next().unwrap_or_default().to_string();
+        rest = rest[key_len..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let len = body.find(q).unwrap_or(body.len());
+                    value = body[..len].to_string();
+                    rest = body.get(len + 1..).unwrap_or_default();
+                }
+                _ => {
+                    let len = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    value = after_eq[..len].to_string();
+                    rest = &after_eq[len..];
+                }
             }
         }
+        if !key.is_empty() {
+            attrs.push((key, value));
+        }
     }
+    attrs
+}
 
-    if let Some(pos) = opf.find("properties=\"cover-image\"") {
-        let window_start = pos.saturating_sub(200);
-        let window_end = pos;
-        let window = &opf[window_start..window_end];
+/// Bytes of the image at `src`: a base64 `data:` URI, or a path relative to
+/// `base_dir`.
+fn html_image_bytes(src: &str, base_dir: Option<&Path>) -> Option<Vec<u8>> {
+    let src = src.trim();
+    if src.len() > 5 && src[..5].eq_ignore_ascii_case("data:") {
+        let (meta, payload) = src[5..].split_once(',')?;
+        if !meta.to_ascii_lowercase().ends_with(";base64") {
+            return None;
+        }
+        let clean: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+        return general_purpose::STANDARD.decode(clean).ok();
+    }
+    std::fs::read(base_dir?.join(relative_image_path(src)?)).ok()
+}
 
-        if let Some(id_pos) = window.rfind("id=\"") {
-            let start = id_pos + 4;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
-            }
+/// `src` as a path relative to the document, percent-decoded and without
+/// query or fragment. URLs, drive and UNC paths, rooted paths and `..`
+/// segments are refused, so a downloaded page can't make Explorer read from
+/// outside the book's folder.
+fn relative_image_path(src: &str) -> Option<PathBuf> {
+    let src = src.split(['?', '#']).next()?;
+    let decoded = percent_decode(src)?;
+    if decoded.is_empty() || decoded.contains(':') || decoded.starts_with(['/', '\\']) {
+        return None;
+    }
+    let segments = decoded
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".");
+    let mut path = PathBuf::new();
+    for segment in segments {
+        if segment == ".." {
+            return None;
         }
+        path.push(segment);
     }
+    Some(path)
+}
 
-    None
+/// Decode `%XX` escapes; `None` on a malformed escape or invalid UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
 }
 
-fn find_href_by_id_in_opf(opf: &str, id: &str) -> Option<String> {
-    let pattern = format!("id=\"{}\"", id);
-    if let Some(pos) = opf.find(&pattern) {
-        let window_start = pos.saturating_sub(10);
-        let window_end = (pos + 200).min(opf.len());
-        let window = &opf[window_start..window_end];
+// ─────────────────────────────────────────────────────────────────────────────
+// Tar archives
+// ─────────────────────────────────────────────────────────────────────────────
 
-        if let Some(href_pos) = window.find("href=\"") {
-            let start = href_pos + 6;
-            if let Some(end) = window[start..].find('"') {
-                return Some(window[start..start + end].to_string());
-            }
+/// Gzip member header; `.tgz` and `.tar.gz` start with it.
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// Most tar data read (after decompression) before giving up, so a tar bomb
+/// costs bounded time instead of inflating for as long as it likes.
+const MAX_TAR_SCAN: u64 = 512 * 1024 * 1024;
+
+/// Largest member buffered in memory; bigger ones are skipped.
+const MAX_TAR_MEMBER: u64 = 128 * 1024 * 1024;
+
+/// Tars inside tars followed before giving up.
+const MAX_TAR_DEPTH: u32 = 2;
+
+/// Book formats recognized inside a tar, after [`format_ext`].
+const TAR_MEMBER_FORMATS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "kf8", "prc", "fb2", "fbz", "fb2.zip", "cbz", "cbr", "tar",
+    "tgz", "tar.gz",
+];
+
+/// Extract a cover from a `.tar`, `.tgz` or `.tar.gz`, compressed or not
+/// whatever the extension says.
+///
+/// A tar holding a single recognized book yields that book's cover; one
+/// holding only images is read as a comic and yields its first page in
+/// natural order. Nothing is written to disk, and members with absolute or
+/// `..` paths are ignored rather than resolved.
+pub fn extract_tar_cover_bytes<R: Read>(reader: R) -> Result<Vec<u8>> {
+    extract_tar_cover_bytes_at_depth(reader, 0)
+}
+
+fn extract_tar_cover_bytes_at_depth<R: Read>(reader: R, depth: u32) -> Result<Vec<u8>> {
+    if depth > MAX_TAR_DEPTH {
+        return Err(anyhow!("Tar archives nested too deeply"));
+    }
+    let mut reader = std::io::BufReader::new(reader);
+    let gzipped = reader.fill_buf()?.starts_with(GZIP_MAGIC);
+    let reader: Box<dyn Read> = if gzipped {
+        Box::new(flate2::read::GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+    let mut archive = tar::Archive::new(reader.take(MAX_TAR_SCAN));
+
+    let mut book: Option<(String, Vec<u8>)> = None;
+    let mut first_page: Option<(String, Vec<u8>)> = None;
+    for entry in archive.entries()? {
+        // Past `MAX_TAR_SCAN` the stream ends mid-member; keep what was found.
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(_) if book.is_some() || first_page.is_some() => break,
+            Err(e) => return Err(e.into()),
+        };
+        if !entry.header().entry_type().is_file() || entry.size() > MAX_TAR_MEMBER {
+            continue;
+        }
+        let Some(name) = tar_member_name(&entry.path()?) else {
+            continue;
+        };
+        let format = tar_member_format(&name);
+        let is_earlier_page = format.is_none()
+            && is_image_extension(&name)
+            && match &first_page {
+                Some((page, _)) => natural_cmp(&name, page).is_lt(),
+                None => true,
+            };
+        if format.is_none() && !is_earlier_page {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        match format {
+            Some(_) if book.is_some() => return Err(anyhow!("Tar archive holds several books")),
+            Some(format) => book = Some((format, bytes)),
+            None => first_page = Some((name, bytes)),
         }
     }
-    None
+
+    match (book, first_page) {
+        (Some((format, bytes)), page) => extract_tar_member_cover_bytes(bytes, &format, depth)
+            .or_else(|e| page.map(|(_, bytes)| bytes).ok_or(e)),
+        (None, Some((_, bytes))) => Ok(bytes),
+        (None, None) => Err(anyhow!("No book or images found in tar archive")),
+    }
 }
 
-fn find_first_image_in_manifest(opf: &str) -> Option<String> {
-    let manifest_start = opf.find("<manifest")?;
-    let manifest_end = opf[manifest_start..]
-        .find("</manifest>")
-        .map(|e| manifest_start + e)?;
-    let manifest = &opf[manifest_start..manifest_end];
+/// Lower-cased `/`-separated name of a tar member, or `None` when its path is
+/// absolute, climbs out with `..`, or is a macOS `._` resource fork.
+fn tar_member_name(path: &Path) -> Option<String> {
+    use std::path::Component;
 
-    for media_type in ["image/jpeg", "image/png", "image/gif", "image/webp"] {
-        let pattern = format!("media-type=\"{}\"", media_type);
-        if let Some(pos) = manifest.find(&pattern) {
-            let window_start = pos.saturating_sub(200);
-            let window = &manifest[window_start..pos];
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?.to_lowercase()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let file_name = parts.last()?;
+    if file_name.starts_with("._") || parts.first().is_some_and(|p| p == "__macosx") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
 
-            if let Some(href_pos) = window.rfind("href=\"") {
-                let start = href_pos + 6;
-                if let Some(end) = window[start..].find('"') {
-                    return Some(window[start..start + end].to_string());
-                }
-            }
+/// Format of the book at member `name`, if it's one a tar may wrap.
+fn tar_member_format(name: &str) -> Option<String> {
+    let format = if name.ends_with(".fb2.zip") {
+        "fb2.zip".to_string()
+    } else {
+        format_ext(&book_extension(Path::new(name))?)
+    };
+    TAR_MEMBER_FORMATS
+        .contains(&format.as_str())
+        .then_some(format)
+}
+
+/// Cover of the book a tar wrapped, read from memory.
+fn extract_tar_member_cover_bytes(bytes: Vec<u8>, format: &str, depth: u32) -> Result<Vec<u8>> {
+    match format {
+        "epub" => extract_epub_cover_bytes(Cursor::new(bytes)),
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" => {
+            let len = bytes.len() as u64;
+            extract_mobi_cover_bytes_with_len(Cursor::new(bytes), Some(len))
         }
+        "cbz" | "cbr" => extract_cbz_cover_bytes(Cursor::new(bytes)),
+        "fb2" => extract_fb2_cover_bytes(Cursor::new(bytes)),
+        "fbz" | "fb2.zip" => extract_fbz_cover_bytes(Cursor::new(bytes)),
+        "tar" | "tgz" | "tar.gz" => extract_tar_cover_bytes_at_depth(Cursor::new(bytes), depth + 1),
+        _ => Err(anyhow!("Unsupported format in tar archive: {}", format)),
     }
+}
 
-    None
+// ─────────────────────────────────────────────────────────────────────────────
+// Unified extraction by extension
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Extensions of books that are EPUBs under another name: Kobo's `.kepub`
+/// (often saved as `.kepub.epub`) and `.epub3`.
+const EPUB_VARIANTS: &[&str] = &["kepub", "kepub.epub", "epub3"];
+
+/// Compound extensions whose last segment alone would misname the format.
+const COMPOUND_EXTENSIONS: &[&str] = &["kepub.epub", "tar.gz"];
+
+/// Lower-cased extension of the book at `path`, without the dot. Compound
+/// extensions such as `kepub.epub` and `tar.gz` are returned whole.
+pub fn book_extension(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if let Some(ext) = COMPOUND_EXTENSIONS
+        .iter()
+        .find(|ext| name.len() > ext.len() + 1 && name.ends_with(&format!(".{}", ext)))
+    {
+        return Some(ext.to_string());
+    }
+    let (_, ext) = name.rsplit_once('.')?;
+    (!ext.is_empty()).then(|| ext.to_string())
+}
+
+/// The format `ext` is extracted as: lower-cased, without the dot, and with
+/// EPUB variants folded into `epub`.
+fn format_ext(ext: &str) -> String {
+    let ext = normalize_ext(ext);
+    if EPUB_VARIANTS.contains(&ext.as_str()) {
+        "epub".to_string()
+    } else {
+        ext
+    }
+}
+
+/// Image types a cover sidecar may have.
+const COVER_SIDECAR_EXTENSIONS: &[&str] = &["jpg", "png", "gif"];
+
+/// Cover the user picked in Readest for a book that can't embed one, stored
+/// beside it as `<book>.readest-cover.<jpg|png|gif>` (see `book_cover.rs` in
+/// the app).
+fn cover_sidecar_path(path: &Path) -> Option<PathBuf> {
+    COVER_SIDECAR_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            let mut name = path.as_os_str().to_os_string();
+            name.push(format!(".readest-cover.{ext}"));
+            PathBuf::from(name)
+        })
+        .find(|sidecar| sidecar.is_file())
+}
+
+/// Waits between attempts to open a book on a network share.
+const NETWORK_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_millis(100),
+    Duration::from_millis(400),
+    Duration::from_millis(1600),
+];
+
+/// An open slower than this marks the book as remote, e.g. on a mapped
+/// drive or an SMB mount that has no UNC prefix.
+const SLOW_OPEN: Duration = Duration::from_millis(250);
+
+/// Bytes read to make sure a remote book is actually readable.
+const NETWORK_PROBE_BYTES: u64 = 4096;
+
+/// Whether `path` names a network share: `\\server\share\...`, its
+/// `\\?\UNC\` form, or the same with forward slashes. Device paths
+/// (`\\.\`, `\\?\C:\`) are local.
+pub(crate) fn is_unc_path(path: &str) -> bool {
+    let path = path.replace('/', "\\");
+    if let Some(rest) = path.strip_prefix(r"\\?\") {
+        return rest
+            .get(..4)
+            .is_some_and(|unc| unc.eq_ignore_ascii_case(r"UNC\"))
+            && rest.len() > 4;
+    }
+    path.strip_prefix(r"\\")
+        .is_some_and(|rest| !rest.starts_with(['\\', '.', '?']) && !rest.is_empty())
+}
+
+/// `\\?\C:\x` → `C:\x`, `\\?\UNC\server\share` → `\\server\share`.
+/// `std::fs::canonicalize` returns verbatim paths, which the shell's
+/// `InprocServer32` loading doesn't expect.
+pub fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{rest}")
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// `s` as a NUL-terminated UTF-16 string for Win32 calls.
+pub fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Whether an I/O error may go away on its own, unlike a missing file or a
+/// denied access.
+fn is_transient_io(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    !matches!(
+        err.kind(),
+        ErrorKind::NotFound | ErrorKind::PermissionDenied | ErrorKind::InvalidInput
+    )
+}
+
+/// Run `attempt` until it succeeds, fails for good, or `delays` run out,
+/// sleeping for the next delay after each transient failure.
+fn retry_transient<T>(
+    delays: &[Duration],
+    mut attempt: impl FnMut() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let mut result = attempt();
+    for delay in delays {
+        match &result {
+            Err(err) if is_transient_io(err) => std::thread::sleep(*delay),
+            _ => break,
+        }
+        result = attempt();
+    }
+    result
+}
+
+/// Read the first bytes of `file`, then rewind it.
+fn probe_read(mut file: std::fs::File) -> std::io::Result<std::fs::File> {
+    let mut probe = Vec::new();
+    Read::by_ref(&mut file)
+        .take(NETWORK_PROBE_BYTES)
+        .read_to_end(&mut probe)?;
+    file.rewind()?;
+    Ok(file)
+}
+
+/// Open the book at `path`. Local books open as `File::open` does; books on
+/// a network share, by UNC prefix or because the first open was slow, get
+/// their open and first read retried with backoff, and fail with
+/// [`CoverError::Io`].
+fn open_book(path: &Path) -> Result<std::fs::File> {
+    let started = Instant::now();
+    let opened = std::fs::File::open(path);
+    let remote =
+        (cfg!(windows) && is_unc_path(&path.to_string_lossy())) || started.elapsed() >= SLOW_OPEN;
+    if !remote {
+        return Ok(opened?);
+    }
+    let mut first = Some(opened);
+    retry_transient(&NETWORK_RETRY_DELAYS, || {
+        first
+            .take()
+            .unwrap_or_else(|| std::fs::File::open(path))
+            .and_then(probe_read)
+    })
+    .map_err(|err| {
+        CoverError::Io {
+            message: format!("Cannot read {}: {err}", path.display()),
+            retriable: is_transient_io(&err),
+        }
+        .into()
+    })
+}
+
+/// Extract cover image bytes based on file extension. A cover sidecar, when
+/// present, wins over the book's own cover.
+///
+/// `book.fb2.zip` reports a `zip` extension, so the full file name is checked
+/// to route it to the FBZ extractor without claiming arbitrary ZIP files.
+/// Books on a network share that can't be read fail with [`CoverError::Io`].
+pub fn extract_cover_bytes_by_ext(path: &Path, ext: &str) -> Result<Vec<u8>> {
+    extract_cover_bytes_with_password(path, ext, None)
+}
+
+/// [`extract_cover_bytes_by_ext`] for a book that may be a password-protected
+/// ZIP. `decrypt_password` is used for EPUB and CBZ entries and ignored by
+/// other formats.
+pub fn extract_cover_bytes_with_password(
+    path: &Path,
+    ext: &str,
+    decrypt_password: Option<&str>,
+) -> Result<Vec<u8>> {
+    if let Some(sidecar) = cover_sidecar_path(path) {
+        return Ok(std::fs::read(sidecar)?);
+    }
+    let file = open_book(path)?;
+    let is_fb2_zip = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_lowercase().ends_with(".fb2.zip"));
+    if is_fb2_zip {
+        return extract_fbz_cover_bytes(file);
+    }
+    match format_ext(ext).as_str() {
+        "epub" => extract_epub_cover_bytes_with_password(file, decrypt_password),
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" => {
+            let len = file.metadata()?.len();
+            extract_mobi_cover_bytes_with_len(file, Some(len))
+        }
+        "cbz" | "cbr" => extract_cbz_cover_bytes_with_password(file, None, decrypt_password),
+        "fb2" => extract_fb2_cover_bytes(file),
+        "fbz" => extract_fbz_cover_bytes(file),
+        "kfx" | "kfx-zip" | "kdf" => extract_kfx_cover_bytes(file),
+        "tar" | "tgz" | "tar.gz" => extract_tar_cover_bytes(file),
+        "lit" => extract_lit_cover_bytes(file),
+        "txt" => extract_txt_cover_bytes(file, 256),
+        "html" | "htm" => extract_html_cover_bytes(file, path.parent(), 256),
+        _ => Err(anyhow!("Unsupported format: {}", ext)),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Partial downloads
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Suffixes download managers and sync clients give incomplete files.
+const PARTIAL_DOWNLOAD_SUFFIXES: &[&str] = &[".part", ".partial", ".crdownload", ".download"];
+
+/// Most of an incomplete file read when looking for its cover. EPUB covers
+/// and the first MOBI image records sit near the start; past this the cover
+/// is either already there or not worth waiting for.
+const MAX_PARTIAL_SCAN: u64 = 32 * 1024 * 1024;
+
+/// Most a single entry of a partial ZIP inflates to. Entries that would grow
+/// past it are treated as unreadable rather than filled into memory.
+const MAX_PARTIAL_ENTRY: u64 = 64 * 1024 * 1024;
+
+/// Book extension of an in-progress download such as `Dune.epub.part`, or
+/// `None` if `path` isn't one.
+pub fn partial_download_ext(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    let book = PARTIAL_DOWNLOAD_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))?;
+    let (_, ext) = book.rsplit_once('.')?;
+    (!ext.is_empty()).then(|| ext.to_string())
+}
+
+/// What an incomplete file yields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartialCover {
+    /// The cover was already downloaded and decodes.
+    Cover(Vec<u8>),
+    /// Not enough of the file is there yet, or the format keeps its cover
+    /// at the end.
+    Downloading,
+}
+
+/// Best-effort cover of a file still being downloaded, treated as `ext`.
+///
+/// EPUB and CBZ archives are read through their local file headers, front
+/// to back, since the central directory at the end isn't there yet; entries
+/// cut off by the end of the data are ignored. MOBI covers are read once the
+/// record holding them has arrived. Other formats are always
+/// [`PartialCover::Downloading`].
+pub fn extract_partial_cover_bytes(path: &Path, ext: &str) -> Result<PartialCover> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?
+        .take(MAX_PARTIAL_SCAN)
+        .read_to_end(&mut bytes)?;
+    let cover = match format_ext(ext).as_str() {
+        "epub" => partial_epub_cover(&local_zip_entries(&bytes)),
+        "cbz" => partial_cbz_cover(&local_zip_entries(&bytes)),
+        // A cover in the last record would be cut short; the decode check
+        // below turns that into `Downloading`.
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" => {
+            extract_mobi_cover_bytes_with_len(Cursor::new(&bytes), None).ok()
+        }
+        _ => None,
+    };
+    Ok(match cover {
+        Some(cover) if decode_cover(&cover).is_ok() => PartialCover::Cover(cover),
+        _ => PartialCover::Downloading,
+    })
+}
+
+/// Thumbnail for a file still being downloaded: its cover when
+/// [`extract_partial_cover_bytes`] finds one, else the "downloading" tile.
+/// Nothing is cached, since the file is still changing.
+pub fn partial_thumbnail_for_path(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay_policy: &OverlayPolicy,
+) -> Result<ScaledThumbnail> {
+    let scale = scale.clamp(1, MAX_THUMBNAIL_SCALE);
+    match extract_partial_cover_bytes(path, ext)? {
+        PartialCover::Cover(cover) => {
+            render_thumbnail(&cover, size, scale, quality, overlay_policy.is_enabled(ext))
+        }
+        PartialCover::Downloading => {
+            let edge = size.saturating_mul(scale).max(3);
+            let tile = DynamicImage::ImageRgba8(downloading_placeholder(edge, edge));
+            Ok(ScaledThumbnail {
+                bytes: encode_thumbnail(&tile, quality)?,
+                width: edge,
+                height: edge,
+                native_limited: false,
+            })
+        }
+    }
+}
+
+/// The placeholder tile with three dots across the middle, so a download in
+/// progress doesn't look like a book without a cover.
+fn downloading_placeholder(width: u32, height: u32) -> image::RgbaImage {
+    let mut img = placeholder_image(width, height);
+    let dot = (width.min(height) / 16).max(1);
+    let (cx, cy) = (width / 2, height / 2);
+    for step in [0, 2, 4] {
+        let x0 = (cx + step * dot).saturating_sub(3 * dot - dot / 2);
+        for y in cy.saturating_sub(dot / 2)..(cy.saturating_sub(dot / 2) + dot).min(height) {
+            for x in x0..(x0 + dot).min(width) {
+                img.put_pixel(x, y, Rgba([150, 150, 150, 255]));
+            }
+        }
+    }
+    img
+}
+
+/// A ZIP entry found by walking local file headers.
+struct LocalEntry<'a> {
+    name: String,
+    /// 0 (stored) or 8 (deflate); others are skipped.
+    method: u16,
+    data: &'a [u8],
+}
+
+impl LocalEntry<'_> {
+    /// The entry's data, or `None` if it is compressed with another method
+    /// or inflates past [`MAX_PARTIAL_ENTRY`].
+    fn contents(&self) -> Option<Vec<u8>> {
+        match self.method {
+            0 => Some(self.data.to_vec()),
+            8 => {
+                let mut out = Vec::new();
+                flate2::read::DeflateDecoder::new(self.data)
+                    .take(MAX_PARTIAL_ENTRY + 1)
+                    .read_to_end(&mut out)
+                    .ok()?;
+                (out.len() as u64 <= MAX_PARTIAL_ENTRY).then_some(out)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Complete entries at the front of a possibly truncated ZIP, in file order.
+/// Stops at the central directory, at anything that isn't a local header,
+/// and at the first entry whose data runs past the end of `bytes`.
+fn local_zip_entries(bytes: &[u8]) -> Vec<LocalEntry<'_>> {
+    const HEADER_LEN: usize = 30;
+    const DATA_DESCRIPTOR: &[u8] = b"PK\x07\x08";
+    let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while bytes.len() >= pos + HEADER_LEN && bytes[pos..].starts_with(ZIP_MAGIC) {
+        let flags = u16_at(pos + 6);
+        let method = u16_at(pos + 8);
+        let compressed = u32_at(pos + 18) as usize;
+        let name_len = u16_at(pos + 26) as usize;
+        let extra_len = u16_at(pos + 28) as usize;
+        let start = pos + HEADER_LEN + name_len + extra_len;
+        if start > bytes.len() {
+            break;
+        }
+        let name = String::from_utf8_lossy(&bytes[pos + HEADER_LEN..pos + HEADER_LEN + name_len])
+            .into_owned();
+
+        // Bit 3: sizes follow the data in a descriptor. Deflate streams end
+        // themselves, so inflating finds the length; stored data can't be
+        // delimited.
+        let data_len = if flags & 0x08 == 0 {
+            compressed
+        } else if method == 8 {
+            let mut decoder = flate2::read::DeflateDecoder::new(&bytes[start..]);
+            if std::io::copy(&mut decoder, &mut std::io::sink()).is_err() {
+                break;
+            }
+            decoder.total_in() as usize
+        } else {
+            break;
+        };
+        let end = start + data_len;
+        if end > bytes.len() {
+            break;
+        }
+        entries.push(LocalEntry {
+            name,
+            method,
+            data: &bytes[start..end],
+        });
+
+        pos = end;
+        if flags & 0x08 != 0 {
+            pos += if bytes[pos..].starts_with(DATA_DESCRIPTOR) {
+                16
+            } else {
+                12
+            };
+        }
+    }
+    entries
+}
+
+fn local_entry<'a, 'b>(entries: &'b [LocalEntry<'a>], name: &str) -> Option<&'b LocalEntry<'a>> {
+    entries.iter().find(|entry| entry.name == name)
+}
+
+/// The EPUB cover among `entries`: the OPF cover item, then an image named
+/// like a cover, then the first manifest image, when present.
+fn partial_epub_cover(entries: &[LocalEntry]) -> Option<Vec<u8>> {
+    let opf_image = || -> Option<Vec<u8>> {
+        let container = local_entry(entries, "META-INF/container.xml")?.contents()?;
+        let rootfile = extract_attribute(
+            &String::from_utf8_lossy(&container),
+            "rootfile",
+            "full-path",
+        )?;
+        let opf = local_entry(entries, &rootfile)?.contents()?;
+        let opf = String::from_utf8_lossy(&opf);
+        let base = Path::new(&rootfile).parent().unwrap_or(Path::new(""));
+        let resolve = |href: String| base.join(href).to_string_lossy().replace('\\', "/");
+        let cover = find_cover_id_in_opf(&opf).and_then(|id| find_href_by_id_in_opf(&opf, &id));
+        [cover, find_first_image_in_manifest(&opf)]
+            .into_iter()
+            .flatten()
+            .find_map(|href| local_entry(entries, &resolve(href))?.contents())
+    };
+    let named_cover = || {
+        entries
+            .iter()
+            .filter(|entry| {
+                let name = entry.name.to_lowercase();
+                is_image_extension(&name) && name.contains("cover")
+            })
+            .find_map(LocalEntry::contents)
+    };
+    opf_image().or_else(named_cover)
+}
+
+/// The first page among `entries` in natural order. Pages that haven't
+/// arrived yet may sort earlier, so this is only a stand-in until the
+/// download completes.
+fn partial_cbz_cover(entries: &[LocalEntry]) -> Option<Vec<u8>> {
+    entries
+        .iter()
+        .filter(|entry| is_image_extension(&entry.name.to_lowercase()))
+        .min_by(|a, b| natural_cmp(&a.name.to_lowercase(), &b.name.to_lowercase()))?
+        .contents()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Cover candidates
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Long edge, in pixels, of [`CoverCandidate::preview`].
+pub const CANDIDATE_PREVIEW_SIZE: u32 = 160;
+
+/// At most this many candidates are returned; past the first few the
+/// heuristics are only listing illustrations.
+pub const MAX_COVER_CANDIDATES: usize = 8;
+
+/// Where a [`CoverCandidate`] lives in the book, for
+/// [`cover_candidate_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoverSource {
+    /// Entry path inside the archive (EPUB, CBZ).
+    ZipEntry(String),
+    /// `<binary id>` of an FB2 document, plain or zipped.
+    Fb2Binary(String),
+    /// Formats with a single cover location (MOBI, TXT): whatever
+    /// [`extract_cover_bytes_by_ext`] returns.
+    Primary,
+}
+
+/// One image that may be the book's cover.
+#[derive(Debug, Clone)]
+pub struct CoverCandidate {
+    pub source: CoverSource,
+    /// Heuristic confidence, 0–100. Candidates are returned best first, and
+    /// the first is the one [`extract_cover_bytes_by_ext`] uses.
+    pub score: u8,
+    /// Size of the full image.
+    pub width: u32,
+    pub height: u32,
+    /// PNG downscaled to fit [`CANDIDATE_PREVIEW_SIZE`]; fetch the full image
+    /// with [`cover_candidate_bytes`] once one is picked.
+    pub preview: Vec<u8>,
+}
+
+/// Every plausible cover of the book at `path`, best first, so the user can
+/// pick a different one than the heuristics did (a back cover, or the volume
+/// cover rather than a series banner). Images that fail to decode are
+/// skipped.
+pub fn list_cover_candidates(path: &Path, ext: &str) -> Result<Vec<CoverCandidate>> {
+    let mut candidates = Vec::new();
+    for (source, score, bytes) in gather_cover_candidates(path, ext)? {
+        let Ok(img) = decode_cover(&bytes) else {
+            continue;
+        };
+        let mut preview = Vec::new();
+        img.thumbnail(CANDIDATE_PREVIEW_SIZE, CANDIDATE_PREVIEW_SIZE)
+            .write_to(&mut Cursor::new(&mut preview), image::ImageFormat::Png)?;
+        candidates.push(CoverCandidate {
+            source,
+            score,
+            width: img.width(),
+            height: img.height(),
+            preview,
+        });
+    }
+    Ok(candidates)
+}
+
+/// Full image bytes of a candidate returned by [`list_cover_candidates`].
+pub fn cover_candidate_bytes(path: &Path, ext: &str, source: &CoverSource) -> Result<Vec<u8>> {
+    match source {
+        CoverSource::Primary => extract_cover_bytes_by_ext(path, ext),
+        CoverSource::ZipEntry(name) => {
+            let mut archive = ZipArchive::new(std::fs::File::open(path)?)?;
+            let index = archive
+                .index_for_name(name)
+                .ok_or_else(|| anyhow!("No entry {} in {}", name, path.display()))?;
+            read_zip_index(&mut archive, index, None)
+        }
+        CoverSource::Fb2Binary(id) => {
+            let (_, binaries) = scan_fb2(&read_fb2_document(path)?)?;
+            binaries
+                .iter()
+                .find(|b| b.id == *id)
+                .ok_or_else(|| anyhow!("No binary {} in {}", id, path.display()))?
+                .decode()
+        }
+    }
+}
+
+/// Candidate sources, scores and full bytes, capped at
+/// [`MAX_COVER_CANDIDATES`].
+fn gather_cover_candidates(path: &Path, ext: &str) -> Result<Vec<(CoverSource, u8, Vec<u8>)>> {
+    let ext = format_ext(ext);
+    let is_fb2 = matches!(ext.as_str(), "fb2" | "fbz")
+        || path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.to_lowercase().ends_with(".fb2.zip"));
+
+    if is_fb2 {
+        let (cover_ids, binaries) = scan_fb2(&read_fb2_document(path)?)?;
+        let mut candidates = Vec::new();
+        for (binary, score) in rank_fb2_covers(&cover_ids, &binaries)
+            .into_iter()
+            .take(MAX_COVER_CANDIDATES)
+        {
+            if let Ok(bytes) = binary.decode() {
+                candidates.push((CoverSource::Fb2Binary(binary.id.clone()), score, bytes));
+            }
+        }
+        return Ok(candidates);
+    }
+
+    let rank: fn(&mut ZipArchive<std::fs::File>) -> Result<Vec<RankedEntry>> = match ext.as_str() {
+        "epub" => |archive| rank_epub_covers(archive, None),
+        "cbz" | "cbr" => rank_cbz_covers,
+        _ => {
+            let bytes = extract_cover_bytes_by_ext(path, &ext)?;
+            return Ok(vec![(CoverSource::Primary, 100, bytes)]);
+        }
+    };
+
+    let mut archive = ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut candidates = Vec::new();
+    for entry in rank(&mut archive)?.into_iter().take(MAX_COVER_CANDIDATES) {
+        let bytes = read_zip_index(&mut archive, entry.index, None)?;
+        candidates.push((CoverSource::ZipEntry(entry.name), entry.score, bytes));
+    }
+    Ok(candidates)
+}
+
+/// FB2 document bytes of a plain or zipped FB2 file.
+fn read_fb2_document(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.starts_with(ZIP_MAGIC) {
+        read_fbz_document(Cursor::new(bytes))
+    } else {
+        Ok(bytes)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Contact sheets
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Largest width or height of a [`generate_contact_sheet`] image. At 4 bytes
+/// per pixel the canvas alone is 256 MB at this size.
+pub const MAX_CONTACT_SHEET_EDGE: u32 = 8192;
+
+/// Layout of a contact sheet beyond its grid size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContactSheetOptions {
+    /// Gap between cells and around the grid, in pixels.
+    pub spacing: u32,
+    pub background: Rgba<u8>,
+    /// Frame of each cover. Its shadow is drawn inside the cell, so covers
+    /// shrink to make room for it.
+    pub style: ThumbnailStyle,
+}
+
+impl Default for ContactSheetOptions {
+    fn default() -> Self {
+        Self {
+            spacing: 16,
+            background: Rgba([255, 255, 255, 255]),
+            style: ThumbnailStyle::default(),
+        }
+    }
+}
+
+/// Render the covers of `paths` as one PNG grid, in order, `columns` wide,
+/// for sharing a reading list.
+///
+/// Cells are `cell_size` pixels wide and 1.5 times as tall (the usual 2:3
+/// cover shape); each cover is scaled to fit its cell and centered. Books
+/// whose cover can't be extracted get the same placeholder tile as TXT files,
+/// so the grid stays aligned. Fails without extracting anything if the sheet
+/// would exceed [`MAX_CONTACT_SHEET_EDGE`] on either side. Each cover is
+/// extracted under a permit of `limiter`.
+pub fn generate_contact_sheet(
+    paths: &[PathBuf],
+    columns: u32,
+    cell_size: u32,
+    options: &ContactSheetOptions,
+    limiter: &ExtractionLimiter,
+) -> Result<Vec<u8>> {
+    if paths.is_empty() || columns == 0 || cell_size < 2 {
+        return Err(anyhow!(
+            "Contact sheet needs books, columns and a cell size of at least 2 px"
+        ));
+    }
+    let (cell_w, cell_h) = (cell_size, cell_size.saturating_mul(3) / 2);
+    let count = paths.len() as u64;
+    let cols = u64::from(columns).min(count);
+    let rows = count.div_ceil(cols);
+    let spacing = u64::from(options.spacing);
+    let width = cols * u64::from(cell_w) + (cols + 1) * spacing;
+    let height = rows * u64::from(cell_h) + (rows + 1) * spacing;
+    let max = u64::from(MAX_CONTACT_SHEET_EDGE);
+    if width > max || height > max {
+        return Err(anyhow!(
+            "Contact sheet would be {}x{} px, over the {} px limit",
+            width,
+            height,
+            MAX_CONTACT_SHEET_EDGE
+        ));
+    }
+
+    let mut sheet = image::RgbaImage::from_pixel(width as u32, height as u32, options.background);
+    let covers = load_cell_covers(paths, cell_w, cell_h, &options.style, limiter);
+    for (index, cover) in covers.into_iter().enumerate() {
+        let (col, row) = (index as u64 % cols, index as u64 / cols);
+        let x = spacing + col * (u64::from(cell_w) + spacing);
+        let y = spacing + row * (u64::from(cell_h) + spacing);
+        match cover {
+            Some(cover) => {
+                let x = x + u64::from(cell_w.saturating_sub(cover.width()) / 2);
+                let y = y + u64::from(cell_h.saturating_sub(cover.height()) / 2);
+                imageops::overlay(&mut sheet, &cover, x as i64, y as i64);
+            }
+            None => {
+                let placeholder = placeholder_image(cell_w, cell_h);
+                imageops::overlay(&mut sheet, &placeholder, x as i64, y as i64);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    DynamicImage::ImageRgba8(sheet)
+        .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
+    Ok(out)
+}
+
+/// Covers of `paths` framed by `style` and scaled to fit `width` x `height`,
+/// in order, extracted on a few threads at once since each is independent
+/// file I/O and decoding. The threads only bound the work in flight; each
+/// book is read under a permit of `limiter`, so a large sheet doesn't
+/// starve Explorer and the library grid. `None` where a book has no usable
+/// cover.
+fn load_cell_covers(
+    paths: &[PathBuf],
+    width: u32,
+    height: u32,
+    style: &ThumbnailStyle,
+    limiter: &ExtractionLimiter,
+) -> Vec<Option<image::RgbaImage>> {
+    let workers = std::thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(paths.len());
+    let chunk_len = paths.len().div_ceil(workers.max(1));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_len)
+            .map(|chunk| {
+                let handle = scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| load_cell_cover(path, width, height, style, limiter))
+                        .collect::<Vec<_>>()
+                });
+                (chunk.len(), handle)
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|(len, handle)| handle.join().unwrap_or_else(|_| vec![None; len]))
+            .collect()
+    })
+}
+
+fn load_cell_cover(
+    path: &Path,
+    width: u32,
+    height: u32,
+    style: &ThumbnailStyle,
+    limiter: &ExtractionLimiter,
+) -> Option<image::RgbaImage> {
+    let ext = book_extension(path)?;
+    let cover = {
+        let _permit = is_heavy_extraction(&ext).then(|| limiter.acquire());
+        extract_cover_bytes_by_ext(path, &ext).ok()?
+    };
+    let img = decode_cover(&cover).ok()?;
+    let (left, top, right, bottom) = style.shadow_padding(1);
+    let fit_w = width.saturating_sub(left + right).max(1);
+    let fit_h = height.saturating_sub(top + bottom).max(1);
+    let cover = img
+        .resize(fit_w, fit_h, imageops::FilterType::Triangle)
+        .to_rgba8();
+    Some(style.apply(cover, 1))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Page previews
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// One-shot PNG of the cover of `path`, fitted to `size` pixels (or the
+/// format's [`preferred_thumbnail_size`] for [`FORMAT_DEFAULT_SIZE`]), for
+/// transient previews such as the import confirmation screen.
+///
+/// Extraction and resizing are the same as for [`cached_thumbnail_for_path`],
+/// but the badge is never drawn and the disk cache is neither read nor
+/// written, so previews of books that end up not being imported leave
+/// nothing behind. Covers smaller than `size` keep their native size.
+/// `password` opens an encrypted EPUB or CBZ.
+pub fn preview_cover(path: &Path, ext: &str, size: u32, password: Option<&str>) -> Result<Vec<u8>> {
+    let size = resolve_thumbnail_size(ext, size);
+    let cover = extract_cover_bytes_with_password(path, ext, password)?;
+    let img = decode_cover(&cover)?;
+    let target = size.min(img.width().max(img.height()));
+    let mut encoded = Vec::new();
+    img.thumbnail(target, target)
+        .write_to(&mut Cursor::new(&mut encoded), image::ImageFormat::Png)?;
+    Ok(encoded)
+}
+
+/// At most this many pages are rendered by one [`preview_pages`] call.
+pub const MAX_PREVIEW_PAGES: usize = 32;
+
+/// Thumbnails of the first `count` pages of a comic, in natural (reading)
+/// order, for a swipeable import preview. Each fits `size` pixels, has no
+/// badge, and is encoded by [`encode_thumbnail`]. A shorter book returns
+/// fewer pages, and pages that fail to decode are skipped.
+///
+/// The whole call holds one permit of `limiter`, like any heavy extraction.
+/// `cancel` is checked before each page; once it is set the call stops with
+/// [`CoverError::Cancelled`]. PDF pages would need a PDF renderer, which
+/// this crate doesn't have, so they fail with [`CoverError::Unsupported`].
+pub fn preview_pages(
+    path: &Path,
+    ext: &str,
+    count: usize,
+    size: u32,
+    limiter: &ExtractionLimiter,
+    cancel: &std::sync::atomic::AtomicBool,
+) -> Result<Vec<Vec<u8>>> {
+    match ext.to_lowercase().as_str() {
+        "cbz" | "cbr" => {}
+        "pdf" => {
+            return Err(CoverError::Unsupported(
+                "Page previews aren't available for PDF files.".to_string(),
+            )
+            .into())
+        }
+        other => return Err(anyhow!("No page previews for {}", other)),
+    }
+    let cancelled = || cancel.load(std::sync::atomic::Ordering::Relaxed);
+    let count = count.min(MAX_PREVIEW_PAGES);
+
+    if cancelled() {
+        return Err(CoverError::Cancelled.into());
+    }
+    let _permit = limiter.acquire();
+    let mut archive = ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut pages = Vec::with_capacity(count);
+    for (index, _) in read_cbz_layout(&mut archive, None)?.images {
+        if pages.len() == count {
+            break;
+        }
+        if cancelled() {
+            return Err(CoverError::Cancelled.into());
+        }
+        let Ok(img) =
+            read_zip_index(&mut archive, index, None).and_then(|bytes| decode_cover(&bytes))
+        else {
+            continue;
+        };
+        pages.push(encode_thumbnail(
+            &img.thumbnail(size, size),
+            DEFAULT_THUMBNAIL_QUALITY,
+        )?);
+    }
+    Ok(pages)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Animated previews
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// At most this many pages are cycled through by [`animated_preview`].
+pub const MAX_ANIMATED_PREVIEW_FRAMES: usize = 12;
+
+/// Time each page of an [`animated_preview`] stays on screen.
+pub const ANIMATED_PREVIEW_FRAME_DELAY_MS: u32 = 1200;
+
+/// Largest encoded [`animated_preview`]; trailing pages are dropped until
+/// the animation fits.
+pub const MAX_ANIMATED_PREVIEW_BYTES: usize = 4 * 1024 * 1024;
+
+/// An animated GIF cycling through the first `frames` pages of a comic in
+/// reading order (the last page in natural order first for right-to-left
+/// manga), for the book detail view. Pages are fitted to `size` pixels and
+/// centered on a white canvas the size of the largest one; each stays up for
+/// [`ANIMATED_PREVIEW_FRAME_DELAY_MS`] and the animation loops forever.
+///
+/// A book with a single readable page gets that page as a still thumbnail
+/// from [`encode_thumbnail`] instead. Like [`preview_pages`], the call holds
+/// one permit of `limiter` and undecodable pages are skipped.
+pub fn animated_preview(
+    path: &Path,
+    ext: &str,
+    frames: usize,
+    size: u32,
+    limiter: &ExtractionLimiter,
+) -> Result<Vec<u8>> {
+    match ext.to_lowercase().as_str() {
+        "cbz" | "cbr" => {}
+        other => return Err(anyhow!("No animated preview for {}", other)),
+    }
+    let frames = frames.clamp(1, MAX_ANIMATED_PREVIEW_FRAMES);
+
+    let _permit = limiter.acquire();
+    let mut archive = ZipArchive::new(std::fs::File::open(path)?)?;
+    let CbzLayout {
+        mut images,
+        comic_info,
+    } = read_cbz_layout(&mut archive, None)?;
+    if comic_info.is_some_and(|info| info.direction == ReadingDirection::RightToLeft) {
+        images.reverse();
+    }
+    let mut pages = Vec::with_capacity(frames);
+    for (index, _) in images {
+        if pages.len() == frames {
+            break;
+        }
+        if let Ok(img) =
+            read_zip_index(&mut archive, index, None).and_then(|bytes| decode_cover(&bytes))
+        {
+            pages.push(img.thumbnail(size, size).to_rgba8());
+        }
+    }
+
+    match pages.len() {
+        0 => Err(anyhow!("No readable pages in {}", path.display())),
+        1 => encode_thumbnail(
+            &DynamicImage::ImageRgba8(pages.remove(0)),
+            DEFAULT_THUMBNAIL_QUALITY,
+        ),
+        _ => {
+            let mut count = pages.len();
+            loop {
+                let gif = encode_animation(&pages[..count])?;
+                if gif.len() <= MAX_ANIMATED_PREVIEW_BYTES || count == 2 {
+                    return Ok(gif);
+                }
+                count -= 1;
+            }
+        }
+    }
+}
+
+/// `pages` as a looping GIF, each centered on a shared white canvas.
+fn encode_animation(pages: &[image::RgbaImage]) -> Result<Vec<u8>> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame};
+
+    let width = pages.iter().map(|page| page.width()).max().unwrap_or(1);
+    let height = pages.iter().map(|page| page.height()).max().unwrap_or(1);
+    let delay = Delay::from_numer_denom_ms(ANIMATED_PREVIEW_FRAME_DELAY_MS, 1);
+    let mut out = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut out, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        for page in pages {
+            let mut canvas = image::RgbaImage::from_pixel(width, height, Rgba([255; 4]));
+            let x = (width - page.width()) / 2;
+            let y = (height - page.height()) / 2;
+            imageops::overlay(&mut canvas, page, i64::from(x), i64::from(y));
+            encoder.encode_frame(Frame::from_parts(canvas, 0, 0, delay))?;
+        }
+    }
+    Ok(out)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Color management
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Linear-light XYZ (D50, the ICC connection space) to linear sRGB, with the
+/// Bradford adaptation the sRGB profile itself uses.
+const XYZ_D50_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_867, -0.490_615],
+    [-0.978_768, 1.916_142, 0.033_454],
+    [0.071_945, -0.228_991, 1.405_243],
+];
+
+/// Steps of the linear-to-sRGB lookup table; fine enough that no 8-bit
+/// output level is skipped.
+const SRGB_ENCODE_STEPS: usize = 4095;
+
+/// The ICC profile embedded in a JPEG, PNG or WebP cover, if any. Only the
+/// header is parsed.
+fn embedded_icc_profile(bytes: &[u8]) -> Option<Vec<u8>> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    decoder
+        .icc_profile()
+        .ok()
+        .flatten()
+        .filter(|icc| !icc.is_empty())
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.040_45 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn icc_u32(icc: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(icc.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn icc_s15_fixed16(icc: &[u8], at: usize) -> Option<f32> {
+    Some(icc_u32(icc, at)? as i32 as f32 / 65536.0)
+}
+
+/// The data of tag `signature` in the tag table of `icc`.
+fn icc_tag<'a>(icc: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = icc_u32(icc, 128)? as usize;
+    (0..count.min(icc.len() / 12)).find_map(|i| {
+        let entry = 132 + i * 12;
+        if icc.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = icc_u32(icc, entry + 4)? as usize;
+        let size = icc_u32(icc, entry + 8)? as usize;
+        icc.get(offset..offset.checked_add(size)?)
+    })
+}
+
+/// An `XYZ ` tag: one colorant in the connection space.
+fn icc_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+    if !tag.starts_with(b"XYZ ") {
+        return None;
+    }
+    Some([
+        icc_s15_fixed16(tag, 8)?,
+        icc_s15_fixed16(tag, 12)?,
+        icc_s15_fixed16(tag, 16)?,
+    ])
+}
+
+/// A `curv` or `para` tone curve, as the linear light of each 8-bit level.
+fn icc_curve(tag: &[u8]) -> Option<[f32; 256]> {
+    let eval: Box<dyn Fn(f32) -> f32> = match tag.get(..4)? {
+        b"curv" => {
+            let count = icc_u32(tag, 8)? as usize;
+            let entry = |i: usize| {
+                let at = 12 + i * 2;
+                tag.get(at..at + 2)
+                    .map(|b| f32::from(u16::from_be_bytes([b[0], b[1]])))
+            };
+            match count {
+                0 => Box::new(|x| x),
+                1 => {
+                    let gamma = entry(0)? / 256.0;
+                    Box::new(move |x| x.powf(gamma))
+                }
+                _ => {
+                    let table: Vec<f32> = (0..count)
+                        .map(|i| entry(i).map(|v| v / 65535.0))
+                        .collect::<Option<_>>()?;
+                    Box::new(move |x| {
+                        let pos = x * (table.len() - 1) as f32;
+                        let i = (pos as usize).min(table.len() - 2);
+                        let t = pos - i as f32;
+                        table[i] * (1.0 - t) + table[i + 1] * t
+                    })
+                }
+            }
+        }
+        b"para" => {
+            let kind = u16::from_be_bytes(tag.get(8..10)?.try_into().ok()?);
+            let params = [1, 3, 4, 5, 7].get(usize::from(kind))?;
+            let p: Vec<f32> = (0..*params)
+                .map(|i| icc_s15_fixed16(tag, 12 + i * 4))
+                .collect::<Option<_>>()?;
+            let p = move |i: usize| p.get(i).copied().unwrap_or(0.0);
+            let (g, a, b, c, d, e, f) = (p(0), p(1), p(2), p(3), p(4), p(5), p(6));
+            match kind {
+                0 => Box::new(move |x| x.powf(g)),
+                1 => Box::new(move |x| {
+                    if a * x + b >= 0.0 {
+                        (a * x + b).powf(g)
+                    } else {
+                        0.0
+                    }
+                }),
+                2 => Box::new(move |x| {
+                    if a * x + b >= 0.0 {
+                        (a * x + b).powf(g) + c
+                    } else {
+                        c
+                    }
+                }),
+                3 => Box::new(move |x| if x >= d { (a * x + b).powf(g) } else { c * x }),
+                _ => Box::new(move |x| {
+                    if x >= d {
+                        (a * x + b).powf(g) + e
+                    } else {
+                        c * x + f
+                    }
+                }),
+            }
+        }
+        _ => return None,
+    };
+    let mut curve = [0.0; 256];
+    for (level, value) in curve.iter_mut().enumerate() {
+        *value = eval(level as f32 / 255.0).clamp(0.0, 1.0);
+    }
+    Some(curve)
+}
+
+/// An RGB matrix/TRC ICC profile: a tone curve per channel and the
+/// colorants taking linear RGB to the connection space. Nearly every RGB
+/// profile embedded in images, Display P3 and Adobe RGB included, is one.
+struct MatrixShaper {
+    /// Linear light of each 8-bit input level, per channel.
+    curves: [[f32; 256]; 3],
+    /// Linear input RGB to linear sRGB.
+    to_srgb: [[f32; 3]; 3],
+}
+
+impl MatrixShaper {
+    /// `None` for anything but an RGB matrix/TRC profile.
+    fn parse(icc: &[u8]) -> Option<Self> {
+        if icc.get(16..20)? != b"RGB " || icc.get(36..40)? != b"acsp" {
+            return None;
+        }
+        let colorants =
+            [b"rXYZ", b"gXYZ", b"bXYZ"].map(|signature| icc_tag(icc, signature).and_then(icc_xyz));
+        let curves = [b"rTRC", b"gTRC", b"bTRC"]
+            .map(|signature| icc_tag(icc, signature).and_then(icc_curve));
+        let [Some(r), Some(g), Some(b)] = colorants else {
+            return None;
+        };
+        let [Some(r_curve), Some(g_curve), Some(b_curve)] = curves else {
+            return None;
+        };
+        let mut to_srgb = [[0.0; 3]; 3];
+        for (row, out) in XYZ_D50_TO_LINEAR_SRGB.iter().zip(&mut to_srgb) {
+            for (channel, colorant) in [r, g, b].iter().enumerate() {
+                out[channel] = (0..3).map(|k| row[k] * colorant[k]).sum();
+            }
+        }
+        Some(MatrixShaper {
+            curves: [r_curve, g_curve, b_curve],
+            to_srgb,
+        })
+    }
+
+    /// Whether the profile is sRGB, to within what 8-bit output can show.
+    fn is_srgb(&self) -> bool {
+        let identity = self.to_srgb.iter().enumerate().all(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(j, v)| (v - if i == j { 1.0 } else { 0.0 }).abs() < 0.02)
+        });
+        identity
+            && self.curves.iter().all(|curve| {
+                curve
+                    .iter()
+                    .enumerate()
+                    .all(|(level, v)| (v - srgb_to_linear(level as f32 / 255.0)).abs() < 0.002)
+            })
+    }
+
+    fn apply(&self, img: DynamicImage) -> DynamicImage {
+        let encode: Vec<u8> = (0..=SRGB_ENCODE_STEPS)
+            .map(|i| {
+                let v = linear_to_srgb(i as f32 / SRGB_ENCODE_STEPS as f32);
+                (v * 255.0).round() as u8
+            })
+            .collect();
+        let convert = |px: &mut [u8]| {
+            let linear = [
+                self.curves[0][usize::from(px[0])],
+                self.curves[1][usize::from(px[1])],
+                self.curves[2][usize::from(px[2])],
+            ];
+            for (out, row) in px.iter_mut().zip(&self.to_srgb) {
+                let v = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+                *out = encode[(v.clamp(0.0, 1.0) * SRGB_ENCODE_STEPS as f32).round() as usize];
+            }
+        };
+        if img.color().has_alpha() {
+            let mut rgba = img.to_rgba8();
+            rgba.chunks_exact_mut(4)
+                .for_each(|px| convert(&mut px[..3]));
+            DynamicImage::ImageRgba8(rgba)
+        } else {
+            let mut rgb = img.to_rgb8();
+            rgb.chunks_exact_mut(3).for_each(convert);
+            DynamicImage::ImageRgb8(rgb)
+        }
+    }
+}
+
+/// Convert `img`, decoded from a cover tagged with the profile `icc`, to
+/// sRGB, so wide-gamut covers (Display P3, Adobe RGB) don't come out
+/// oversaturated or dull. sRGB-tagged covers, grayscale ones and profiles
+/// that can't be used are left as decoded.
+///
+/// Matrix/TRC profiles are converted directly. With the `lcms2` feature
+/// every RGB profile goes through Little CMS first, which also handles the
+/// LUT-based profiles some print workflows embed; profiles it rejects fall
+/// back to the direct conversion.
+fn convert_to_srgb(img: DynamicImage, icc: &[u8]) -> DynamicImage {
+    if !img.color().has_color() {
+        return img;
+    }
+    let shaper = MatrixShaper::parse(icc);
+    if shaper.as_ref().is_some_and(MatrixShaper::is_srgb) {
+        return img;
+    }
+    #[cfg(feature = "lcms2")]
+    let img = match lcms_to_srgb(img, icc) {
+        Ok(converted) => return converted,
+        Err(img) => img,
+    };
+    match shaper {
+        Some(shaper) => shaper.apply(img),
+        None => img,
+    }
+}
+
+/// [`convert_to_srgb`] through Little CMS; `img` back untouched when the
+/// profile can't be used.
+#[cfg(feature = "lcms2")]
+fn lcms_to_srgb(img: DynamicImage, icc: &[u8]) -> std::result::Result<DynamicImage, DynamicImage> {
+    use lcms2::{ColorSpaceSignature, Intent, PixelFormat, Profile, Transform};
+
+    let profile = match Profile::new_icc(icc) {
+        Ok(profile) if profile.color_space() == ColorSpaceSignature::RgbData => profile,
+        _ => return Err(img),
+    };
+    let has_alpha = img.color().has_alpha();
+    let format = if has_alpha {
+        PixelFormat::RGBA_8
+    } else {
+        PixelFormat::RGB_8
+    };
+    let srgb = Profile::new_srgb();
+    let Ok(transform) =
+        Transform::<u8, u8>::new(&profile, format, &srgb, format, Intent::Perceptual)
+    else {
+        return Err(img);
+    };
+    Ok(if has_alpha {
+        let mut rgba = img.to_rgba8();
+        transform.transform_in_place(&mut rgba);
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        let mut rgb = img.to_rgb8();
+        transform.transform_in_place(&mut rgb);
+        DynamicImage::ImageRgb8(rgb)
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Thumbnail creation with overlay
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Device-pixel ratios [`cached_thumbnail_for_path`] renders for (@1x–@3x).
+pub const MAX_THUMBNAIL_SCALE: u32 = 3;
+
+/// Pass as the `size` of [`cached_thumbnail_for_path`] to render at the
+/// format's [`preferred_thumbnail_size`].
+pub const FORMAT_DEFAULT_SIZE: u32 = 0;
+
+/// Logical size thumbnails of `ext` are rendered at for
+/// [`FORMAT_DEFAULT_SIZE`]. Comic pages are large scans that keep their
+/// detail in a bigger grid cell; ebook covers are often only a few hundred
+/// pixels, so asking for more would only hit the native-size limit.
+pub fn preferred_thumbnail_size(ext: &str) -> u32 {
+    match normalize_ext(ext).as_str() {
+        "cbz" | "cbr" => 512,
+        _ => 256,
+    }
+}
+
+fn resolve_thumbnail_size(ext: &str, size: u32) -> u32 {
+    if size == FORMAT_DEFAULT_SIZE {
+        preferred_thumbnail_size(ext)
+    } else {
+        size
+    }
+}
+
+/// A thumbnail rendered for a logical size at a device-pixel ratio.
+#[derive(Debug, Clone)]
+pub struct ScaledThumbnail {
+    /// Encoded image, as produced by [`encode_thumbnail`].
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// The cover is smaller than `size * scale`, so it was returned at its
+    /// native resolution instead of being upscaled.
+    pub native_limited: bool,
+}
+
+impl ScaledThumbnail {
+    /// Rebuild the size information of a cached thumbnail from its header.
+    fn from_encoded(bytes: Vec<u8>, target: u32) -> Result<Self> {
+        let (width, height) = image::ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        Ok(Self {
+            bytes,
+            width,
+            height,
+            native_limited: width.max(height) < target,
+        })
+    }
+}
+
+/// Create a thumbnail from cover image bytes, with the Readest icon overlay
+/// unless `overlay` is false.
+///
+/// `quality` (0–100) is passed through to [`encode_thumbnail`]. Covers
+/// smaller than `requested_size` are kept at their native size.
+pub fn create_thumbnail_with_overlay(
+    cover_bytes: &[u8],
+    requested_size: u32,
+    quality: u8,
+    overlay: bool,
+) -> Result<Vec<u8>> {
+    Ok(render_thumbnail(cover_bytes, requested_size, 1, quality, overlay)?.bytes)
+}
+
+/// Render `cover_bytes` to fit `size * scale` device pixels, never past the
+/// cover's own resolution. The badge is sized for the logical `size` and
+/// scaled along with the cover.
+fn render_thumbnail(
+    cover_bytes: &[u8],
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay: bool,
+) -> Result<ScaledThumbnail> {
+    render_thumbnail_timed(
+        cover_bytes,
+        size,
+        scale,
+        quality,
+        overlay,
+        &ThumbnailStyle::default(),
+        &mut ThumbnailTiming::default(),
+    )
+}
+
+/// [`render_thumbnail`] framed by `style`, adding the time spent decoding
+/// the cover and encoding the result to `timing`.
+fn render_thumbnail_timed(
+    cover_bytes: &[u8],
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay: bool,
+    style: &ThumbnailStyle,
+    timing: &mut ThumbnailTiming,
+) -> Result<ScaledThumbnail> {
+    let started = Instant::now();
+    let img = decode_cover(cover_bytes)?;
+    timing.decode += started.elapsed();
+    let target = size.saturating_mul(scale);
+    let native = img.width().max(img.height());
+    let thumbnail = img.thumbnail(target.min(native), target.min(native));
+
+    let overlay_img = if overlay { load_overlay_icon() } else { None };
+
+    let mut base = thumbnail.to_rgba8();
+    let (base_w, base_h) = (base.width(), base.height());
+
+    if let Some(ov) = overlay_img {
+        let logical_size = base_w.max(base_h) / scale;
+        let overlay_size = (logical_size / 5).clamp(24, 48) * scale;
+        let ov_resized = ov.resize(overlay_size, overlay_size, imageops::FilterType::Lanczos3);
+        let ovb = ov_resized.to_rgba8();
+        let (ov_w, ov_h) = (ovb.width(), ovb.height());
+
+        let x = base_w.saturating_sub(ov_w + 4);
+        let y = base_h.saturating_sub(ov_h + 4);
+
+        for oy in 0..ov_h {
+            for ox in 0..ov_w {
+                let dst_x = x + ox;
+                let dst_y = y + oy;
+
+                if dst_x < base_w && dst_y < base_h {
+                    let src_pixel = ovb.get_pixel(ox, oy);
+                    let alpha = src_pixel.0[3] as f32 / 255.0;
+
+                    if alpha > 0.0 {
+                        let dst_pixel = base.get_pixel(dst_x, dst_y);
+                        let mut result = dst_pixel.0;
+
+                        for c in 0..3 {
+                            let fg = src_pixel.0[c] as f32;
+                            let bg = result[c] as f32;
+                            result[c] = (fg * alpha + bg * (1.0 - alpha)) as u8;
+                        }
+                        result[3] = 255;
+
+                        base.put_pixel(dst_x, dst_y, Rgba(result));
+                    }
+                }
+            }
+        }
+    }
+
+    let base = style.apply(base, scale);
+    let (width, height) = (base.width(), base.height());
+
+    let started = Instant::now();
+    let bytes = encode_thumbnail_with_subsampling(
+        &DynamicImage::ImageRgba8(base),
+        quality,
+        style.subsampling,
+    )?;
+    timing.encode += started.elapsed();
+
+    Ok(ScaledThumbnail {
+        bytes,
+        width,
+        height,
+        native_limited: native < target,
+    })
+}
+
+/// JPEG start-of-image marker.
+#[cfg(feature = "mozjpeg")]
+const JPEG_MAGIC: &[u8] = b"\xFF\xD8\xFF";
+
+/// Environment variable overriding [`DEFAULT_MAX_IMAGE_DIMENSION`]. Read
+/// once, on the first decode.
+pub const MAX_IMAGE_DIMENSION_ENV: &str = "READEST_THUMBNAIL_MAX_DIMENSION";
+
+/// Widest or tallest cover decoded when [`MAX_IMAGE_DIMENSION_ENV`] is unset
+/// or invalid. Real covers are a few thousand pixels at most.
+pub const DEFAULT_MAX_IMAGE_DIMENSION: u32 = 20_000;
+
+/// Most pixels a cover may have whatever its shape: 100 MP, 400 MB as
+/// RGBA.
+pub const MAX_IMAGE_PIXELS: u64 = 100_000_000;
+
+static MAX_IMAGE_DIMENSION: Lazy<u32> =
+    Lazy::new(|| parse_max_image_dimension(std::env::var(MAX_IMAGE_DIMENSION_ENV).ok().as_deref()));
+
+/// Dimension limit from the raw value of [`MAX_IMAGE_DIMENSION_ENV`]: a
+/// positive integer, or the default for anything else.
+pub fn parse_max_image_dimension(value: Option<&str>) -> u32 {
+    value
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_IMAGE_DIMENSION)
+}
+
+/// Reject an image whose header declares more than `max_edge` pixels on a
+/// side or [`MAX_IMAGE_PIXELS`] in total, before anything is allocated for
+/// its pixels: a small, highly compressed PNG can otherwise claim
+/// 50000x50000 and exhaust memory mid-decode.
+fn check_image_dimensions(bytes: &[u8], max_edge: u32) -> Result<()> {
+    let (width, height) = image::ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    if width > max_edge
+        || height > max_edge
+        || u64::from(width) * u64::from(height) > MAX_IMAGE_PIXELS
+    {
+        return Err(CoverError::Corrupt(format!(
+            "Cover image is {}x{} px, over the {} px limit",
+            width, height, max_edge
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Decode a cover image, after [`check_image_dimensions`].
+///
+/// With the `mozjpeg` feature, JPEG covers (the bulk of any library) go
+/// through libjpeg-turbo's SIMD decoder; anything it rejects (CMYK, corrupt
+/// streams) and every other format falls back to `image`. Both decoders
+/// produce the same pixels to within one level of IDCT rounding, and the
+/// thumbnail cache is keyed on the source file, so switching the feature on
+/// or off doesn't invalidate existing cache entries.
+///
+/// A cover with an embedded ICC profile is converted to sRGB
+/// ([`convert_to_srgb`]); one without is taken to be sRGB already.
+fn decode_cover(bytes: &[u8]) -> Result<DynamicImage> {
+    check_image_dimensions(bytes, *MAX_IMAGE_DIMENSION)?;
+    let img = decode_cover_pixels(bytes)?;
+    Ok(match embedded_icc_profile(bytes) {
+        Some(icc) => convert_to_srgb(img, &icc),
+        None => img,
+    })
+}
+
+/// The pixels of a cover as stored, before any color conversion.
+fn decode_cover_pixels(bytes: &[u8]) -> Result<DynamicImage> {
+    #[cfg(feature = "mozjpeg")]
+    if bytes.starts_with(JPEG_MAGIC) {
+        if let Some(img) = decode_jpeg_turbo(bytes) {
+            return Ok(img);
+        }
+    }
+    Ok(image::load_from_memory(bytes)?)
+}
+
+/// Full-scale RGB decode through libjpeg-turbo. `None` on any failure so the
+/// caller can retry with the pure-Rust decoder.
+#[cfg(feature = "mozjpeg")]
+fn decode_jpeg_turbo(bytes: &[u8]) -> Option<DynamicImage> {
+    // libjpeg reports fatal errors by unwinding out of its error handler.
+    std::panic::catch_unwind(|| {
+        let mut started = mozjpeg::Decompress::new_mem(bytes).ok()?.rgb().ok()?;
+        let (width, height) = (started.width() as u32, started.height() as u32);
+        let pixels: Vec<[u8; 3]> = started.read_scanlines().ok()?;
+        started.finish().ok()?;
+        image::RgbImage::from_raw(width, height, pixels.concat()).map(DynamicImage::ImageRgb8)
+    })
+    .ok()
+    .flatten()
+}
+
+/// Largest edge, in pixels, of a JPEG thumbnail that keeps full chroma
+/// resolution when no [`ChromaSubsampling`] is asked for. Subsampled color
+/// smears around the lettering of small covers; past this size the savings
+/// are worth more.
+pub const FULL_CHROMA_MAX_EDGE: u32 = 512;
+
+/// Chroma subsampling of a JPEG thumbnail: how much color resolution is
+/// traded for a smaller file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// Full color resolution; sharpest around text and line art.
+    Yuv444,
+    /// Color at half the horizontal resolution.
+    Yuv422,
+    /// Color at half the resolution both ways; the smallest files.
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    /// The default for a thumbnail whose largest edge is `edge` pixels.
+    pub fn for_edge(edge: u32) -> Self {
+        if edge <= FULL_CHROMA_MAX_EDGE {
+            ChromaSubsampling::Yuv444
+        } else {
+            ChromaSubsampling::Yuv420
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChromaSubsampling::Yuv444 => "444",
+            ChromaSubsampling::Yuv422 => "422",
+            ChromaSubsampling::Yuv420 => "420",
+        }
+    }
+
+    fn sampling_factor(self) -> jpeg_encoder::SamplingFactor {
+        match self {
+            ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+impl std::str::FromStr for ChromaSubsampling {
+    type Err = anyhow::Error;
+
+    /// `"444"`, `"422"` or `"420"`, with or without colons.
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().replace(':', "").as_str() {
+            "444" => Ok(ChromaSubsampling::Yuv444),
+            "422" => Ok(ChromaSubsampling::Yuv422),
+            "420" => Ok(ChromaSubsampling::Yuv420),
+            _ => Err(anyhow!("Unknown chroma subsampling: {}", value)),
+        }
+    }
+}
+
+/// Encode a finished thumbnail.
+///
+/// `quality` is clamped to 0–100. At 100, or when the image has transparent
+/// pixels, the thumbnail is stored as lossless PNG and `quality` only picks
+/// the zlib level (lower quality spends more effort on a smaller file).
+/// Otherwise it is stored as JPEG at that quality, with
+/// [`ChromaSubsampling::for_edge`] subsampling. The Explorer side decodes
+/// either format, so callers never need to know which one was chosen.
+pub fn encode_thumbnail(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    encode_thumbnail_with_subsampling(img, quality, None)
+}
+
+/// [`encode_thumbnail`] with the JPEG chroma subsampling chosen by the
+/// caller. `None` picks it from the size; PNGs ignore it.
+pub fn encode_thumbnail_with_subsampling(
+    img: &DynamicImage,
+    quality: u8,
+    subsampling: Option<ChromaSubsampling>,
+) -> Result<Vec<u8>> {
+    use image::codecs::png::{FilterType, PngEncoder};
+
+    let quality = quality.min(100);
+    let rgba = img.to_rgba8();
+    let opaque = rgba.pixels().all(|p| p.0[3] == u8::MAX);
+
+    let mut out = Vec::new();
+    if quality < 100 && opaque {
+        let rgb = DynamicImage::ImageRgba8(rgba).to_rgb8();
+        let (width, height) = match (u16::try_from(rgb.width()), u16::try_from(rgb.height())) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => {
+                return Err(anyhow!(
+                    "{}x{} px is too large for JPEG",
+                    rgb.width(),
+                    rgb.height()
+                ))
+            }
+        };
+        let subsampling = subsampling
+            .unwrap_or_else(|| ChromaSubsampling::for_edge(rgb.width().max(rgb.height())));
+        // Quality 0 is rejected by the encoder; 1 is its lowest setting.
+        let mut encoder = jpeg_encoder::Encoder::new(&mut out, quality.max(1));
+        encoder.set_sampling_factor(subsampling.sampling_factor());
+        encoder.encode(rgb.as_raw(), width, height, jpeg_encoder::ColorType::Rgb)?;
+    } else {
+        let encoder = PngEncoder::new_with_quality(
+            &mut out,
+            png_compression_for_quality(quality),
+            FilterType::Adaptive,
+        );
+        rgba.write_with_encoder(encoder)?;
+    }
+    Ok(out)
+}
+
+/// Map 0–100 onto zlib levels 9–1: PNG is lossless, so the knob trades
+/// encode time for file size instead of fidelity.
+fn png_compression_for_quality(quality: u8) -> image::codecs::png::CompressionType {
+    let level = 9 - (u32::from(quality.min(100)) * 8 / 100) as u8;
+    image::codecs::png::CompressionType::Level(level)
+}
+
+/// Badge staged by `build.rs`; empty when the app icon was missing at build
+/// time.
+static EMBEDDED_OVERLAY_ICON: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/overlay_icon.png"));
+
+/// Load the Readest overlay icon, or `None` to draw thumbnails without it.
+fn load_overlay_icon() -> Option<DynamicImage> {
+    // Try embedded icon
+    if !EMBEDDED_OVERLAY_ICON.is_empty() {
+        if let Ok(img) = image::load_from_memory(EMBEDDED_OVERLAY_ICON) {
+            return Some(img);
+        }
+    }
+
+    // Fallback: try loading from filesystem
+    if let Ok(exe) = std::env::current_exe() {
+        let candidates = [
+            exe.parent().map(|p| p.join("icon.png")),
+            exe.parent().map(|p| p.join("resources").join("icon.png")),
+            exe.parent()
+                .and_then(|p| p.parent())
+                .map(|p| p.join("resources").join("icon.png")),
+        ];
+
+        for candidate in candidates.into_iter().flatten() {
+            if candidate.exists() {
+                if let Ok(bytes) = std::fs::read(&candidate) {
+                    if let Ok(img) = image::load_from_memory(&bytes) {
+                        return Some(img);
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Thumbnail styling
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Drop shadow cast by a styled thumbnail. Lengths are logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowSpec {
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// Blur radius; 0 gives a hard-edged shadow.
+    pub blur: u32,
+    pub color: Rgba<u8>,
+}
+
+impl Default for ShadowSpec {
+    fn default() -> Self {
+        Self {
+            offset_x: 0,
+            offset_y: 2,
+            blur: 6,
+            color: Rgba([0, 0, 0, 90]),
+        }
+    }
+}
+
+/// How a thumbnail is framed. The default is a plain rectangle, which is what
+/// Explorer gets; rounded corners and shadows are for exports and contact
+/// sheets. Lengths are logical pixels, multiplied by the render scale.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThumbnailStyle {
+    pub corner_radius: u32,
+    pub shadow: Option<ShadowSpec>,
+    /// Chroma subsampling if the thumbnail is stored as JPEG; `None` picks
+    /// it from the size, as [`encode_thumbnail`] does.
+    pub subsampling: Option<ChromaSubsampling>,
+}
+
+impl ThumbnailStyle {
+    pub fn is_plain(&self) -> bool {
+        self.corner_radius == 0 && self.shadow.is_none()
+    }
+
+    /// Append the style to a cache-key variant. The default style adds
+    /// nothing, so unstyled thumbnails keep the keys they had before styles
+    /// existed.
+    fn extend_cache_variant(&self, variant: &mut Vec<u8>) {
+        if let Some(subsampling) = self.subsampling {
+            variant.extend_from_slice(b"chroma");
+            variant.extend_from_slice(subsampling.as_str().as_bytes());
+        }
+        if self.is_plain() {
+            return;
+        }
+        variant.extend_from_slice(b"style");
+        variant.extend_from_slice(&self.corner_radius.to_le_bytes());
+        if let Some(shadow) = self.shadow {
+            variant.extend_from_slice(&shadow.offset_x.to_le_bytes());
+            variant.extend_from_slice(&shadow.offset_y.to_le_bytes());
+            variant.extend_from_slice(&shadow.blur.to_le_bytes());
+            variant.extend_from_slice(&shadow.color.0);
+        }
+    }
+
+    /// Transparent margin the shadow needs on each side, as (left, top,
+    /// right, bottom) device pixels.
+    fn shadow_padding(&self, scale: u32) -> (u32, u32, u32, u32) {
+        let Some(shadow) = self.shadow else {
+            return (0, 0, 0, 0);
+        };
+        // The blur fades out within about three sigmas; sigma is blur / 2.
+        let reach = i64::from(shadow.blur.saturating_mul(scale)) * 3 / 2;
+        let dx = i64::from(shadow.offset_x) * i64::from(scale);
+        let dy = i64::from(shadow.offset_y) * i64::from(scale);
+        let side = |v: i64| v.clamp(0, i64::from(u32::MAX)) as u32;
+        (
+            side(reach - dx),
+            side(reach - dy),
+            side(reach + dx),
+            side(reach + dy),
+        )
+    }
+
+    /// Frame `cover`: clip it to rounded corners, then set it on a
+    /// transparent canvas over its shadow.
+    fn apply(&self, mut cover: image::RgbaImage, scale: u32) -> image::RgbaImage {
+        let radius = self.corner_radius.saturating_mul(scale);
+        if radius > 0 {
+            round_corners(&mut cover, radius);
+        }
+        let Some(shadow) = self.shadow else {
+            return cover;
+        };
+
+        let (left, top, right, bottom) = self.shadow_padding(scale);
+        let (width, height) = (cover.width(), cover.height());
+        let [r, g, b, a] = shadow.color.0;
+        // Transparent pixels carry the shadow color too, so blurring doesn't
+        // darken the shadow's edge.
+        let mut canvas = image::RgbaImage::from_pixel(
+            width.saturating_add(left).saturating_add(right),
+            height.saturating_add(top).saturating_add(bottom),
+            Rgba([r, g, b, 0]),
+        );
+        for (x, y, pixel) in cover.enumerate_pixels() {
+            let alpha = (u16::from(pixel.0[3]) * u16::from(a) / 255) as u8;
+            let sx = i64::from(x + left) + i64::from(shadow.offset_x) * i64::from(scale);
+            let sy = i64::from(y + top) + i64::from(shadow.offset_y) * i64::from(scale);
+            if (0..i64::from(canvas.width())).contains(&sx)
+                && (0..i64::from(canvas.height())).contains(&sy)
+            {
+                canvas.put_pixel(sx as u32, sy as u32, Rgba([r, g, b, alpha]));
+            }
+        }
+        let blur = shadow.blur.saturating_mul(scale);
+        if blur > 0 {
+            canvas = imageops::blur(&canvas, blur as f32 / 2.0);
+        }
+        imageops::overlay(&mut canvas, &cover, i64::from(left), i64::from(top));
+        canvas
+    }
+}
+
+/// Make the corners of `img` transparent outside quarter circles of
+/// `radius`, with one pixel of anti-aliasing along the arc.
+fn round_corners(img: &mut image::RgbaImage, radius: u32) {
+    let (width, height) = (img.width(), img.height());
+    let radius = radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return;
+    }
+    let r = radius as f32;
+    for y in 0..radius {
+        for x in 0..radius {
+            // Distance from the pixel center to the arc's center.
+            let dx = r - (x as f32 + 0.5);
+            let dy = r - (y as f32 + 0.5);
+            let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+            for (cx, cy) in [
+                (x, y),
+                (width - 1 - x, y),
+                (x, height - 1 - y),
+                (width - 1 - x, height - 1 - y),
+            ] {
+                let pixel = img.get_pixel_mut(cx, cy);
+                pixel.0[3] = (f32::from(pixel.0[3]) * coverage).round() as u8;
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Caching
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Cache key and target edge, in device pixels, of a thumbnail request.
+fn thumbnail_cache_key(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay: bool,
+    style: &ThumbnailStyle,
+) -> Result<(String, u32)> {
+    let mut variant = vec![quality.min(100), u8::from(overlay), scale as u8];
+    style.extend_cache_variant(&mut variant);
+    // Entries may be PNG or JPEG (see `encode_thumbnail`), hence the neutral
+    // extension.
+    let key = format!(
+        "v{CACHE_KEY_VERSION}-{}.thumb",
+        cache_digest(path, ext, size, &variant)?
+    );
+    Ok((key, size.saturating_mul(scale)))
+}
+
+/// The cached thumbnail [`cached_thumbnail_for_path`] would return for these
+/// arguments, without extracting anything on a miss.
+pub fn lookup_cached_thumbnail(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay_policy: &OverlayPolicy,
+) -> Result<Option<ScaledThumbnail>> {
+    lookup_cached_styled_thumbnail(
+        path,
+        ext,
+        size,
+        scale,
+        quality,
+        overlay_policy,
+        &ThumbnailStyle::default(),
+    )
+}
+
+/// [`lookup_cached_thumbnail`] for [`cached_styled_thumbnail_for_path`].
+fn lookup_cached_styled_thumbnail(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay_policy: &OverlayPolicy,
+    style: &ThumbnailStyle,
+) -> Result<Option<ScaledThumbnail>> {
+    let size = resolve_thumbnail_size(ext, size);
+    let scale = scale.clamp(1, MAX_THUMBNAIL_SCALE);
+    let overlay = overlay_policy.is_enabled(ext);
+    let (key, target) = thumbnail_cache_key(path, ext, size, scale, quality, overlay, style)?;
+    let started = Instant::now();
+    let cached =
+        read_cache(&key).and_then(|cached| ScaledThumbnail::from_encoded(cached, target).ok());
+    // Misses are recorded by the `cached_thumbnail_for_path` call that follows.
+    if cached.is_some() && metrics_enabled() {
+        record_metrics(ThumbnailTiming {
+            ext: ext.to_ascii_lowercase(),
+            cache_hit: true,
+            lookup: started.elapsed(),
+            ..Default::default()
+        });
+    }
+    Ok(cached)
+}
+
+/// Generate a thumbnail with disk caching.
+///
+/// The image fits `size * scale` device pixels, where `size` is the logical
+/// (CSS) size and `scale` the device-pixel ratio, clamped to
+/// 1..=[`MAX_THUMBNAIL_SCALE`]. A `size` of [`FORMAT_DEFAULT_SIZE`] uses
+/// [`preferred_thumbnail_size`] for `ext`. Covers smaller than that are
+/// returned at their native resolution with
+/// [`ScaledThumbnail::native_limited`] set; the result's `width` and
+/// `height` are always the actual output dimensions.
+///
+/// `scale`, `quality` and the effective overlay setting for `ext` are part of
+/// the cache key, so a @2x thumbnail never stands in for a @1x one at twice
+/// the size, and changing a setting regenerates thumbnails instead of serving
+/// ones made with the previous settings.
+pub fn cached_thumbnail_for_path(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay_policy: &OverlayPolicy,
+) -> Result<ScaledThumbnail> {
+    cached_styled_thumbnail_for_path(
+        path,
+        ext,
+        size,
+        scale,
+        quality,
+        overlay_policy,
+        &ThumbnailStyle::default(),
+    )
+}
+
+/// [`cached_thumbnail_for_path`] framed by `style`. A styled thumbnail is
+/// larger than `size * scale` by its shadow, and its transparent corners and
+/// margins make it a PNG. The style is part of the cache key.
+pub fn cached_styled_thumbnail_for_path(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    scale: u32,
+    quality: u8,
+    overlay_policy: &OverlayPolicy,
+    style: &ThumbnailStyle,
+) -> Result<ScaledThumbnail> {
+    let size = resolve_thumbnail_size(ext, size);
+    let scale = scale.clamp(1, MAX_THUMBNAIL_SCALE);
+    let overlay = overlay_policy.is_enabled(ext);
+    let (key, target) = thumbnail_cache_key(path, ext, size, scale, quality, overlay, style)?;
+    let mut timing = ThumbnailTiming {
+        ext: ext.to_ascii_lowercase(),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    if let Some(cached) = read_cache(&key) {
+        if let Ok(thumbnail) = ScaledThumbnail::from_encoded(cached, target) {
+            if metrics_enabled() {
+                timing.cache_hit = true;
+                timing.lookup = started.elapsed();
+                record_metrics(timing);
+            }
+            return Ok(thumbnail);
+        }
+    }
+    timing.lookup = started.elapsed();
+
+    let result = (|| -> Result<ScaledThumbnail> {
+        let started = Instant::now();
+        let cover = extract_cover_bytes_by_ext(path, ext)?;
+        timing.extract = started.elapsed();
+        render_thumbnail_timed(&cover, size, scale, quality, overlay, style, &mut timing)
+    })();
+    if metrics_enabled() {
+        timing.failed = result.is_err();
+        record_metrics(timing);
+    }
+    let thumbnail = result?;
+    write_cache(&key, &thumbnail.bytes);
+
+    Ok(thumbnail)
+}
+
+/// Largest edge, in pixels, of a [`thumbnail_data_url`] image. A full-size
+/// cover as base64 runs to several MB, which is too much to pass around as a
+/// string; larger requests are clamped to this.
+pub const MAX_DATA_URL_SIZE: u32 = 512;
+
+/// Image format of a [`thumbnail_data_url`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataUrlFormat {
+    #[default]
+    Png,
+    /// Lossless WebP, usually noticeably smaller than PNG for covers.
+    WebP,
+}
+
+impl DataUrlFormat {
+    fn mime(self) -> &'static str {
+        match self {
+            DataUrlFormat::Png => "image/png",
+            DataUrlFormat::WebP => "image/webp",
+        }
+    }
+
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            DataUrlFormat::Png => image::ImageFormat::Png,
+            DataUrlFormat::WebP => image::ImageFormat::WebP,
+        }
+    }
+}
+
+/// Cover thumbnail of `path` as a `data:` URL, for places where asset URLs
+/// don't work (canvas exports, generated documents).
+///
+/// Uses the same extraction, rendering and cache as
+/// [`cached_thumbnail_for_path`], but without the Readest badge, which only
+/// marks files in Explorer. `size` is
+/// clamped to 1..=[`MAX_DATA_URL_SIZE`]. The encoded string is cached.
+/// `password` opens an encrypted EPUB or CBZ, as in
+/// [`extract_cover_bytes_with_password`].
+pub fn thumbnail_data_url(
+    path: &Path,
+    ext: &str,
+    size: u32,
+    format: DataUrlFormat,
+    password: Option<&str>,
+) -> Result<String> {
+    let size = size.clamp(1, MAX_DATA_URL_SIZE);
+    let variant = match format {
+        DataUrlFormat::Png => b"data-url:png".as_slice(),
+        DataUrlFormat::WebP => b"data-url:webp".as_slice(),
+    };
+    let key = format!(
+        "v{CACHE_KEY_VERSION}-{}.url",
+        cache_digest(path, ext, size, variant)?
+    );
+
+    if let Some(cached) = read_cache(&key).and_then(|bytes| String::from_utf8(bytes).ok()) {
+        return Ok(cached);
+    }
+
+    let cover = extract_cover_bytes_with_password(path, ext, password)?;
+    // Quality 100 renders a lossless PNG; WebP re-encodes its pixels.
+    let png = render_thumbnail(&cover, size, 1, 100, false)?.bytes;
+    let encoded = match format {
+        DataUrlFormat::Png => png,
+        DataUrlFormat::WebP => {
+            let mut webp = Vec::new();
+            image::load_from_memory_with_format(&png, image::ImageFormat::Png)?
+                .write_to(&mut Cursor::new(&mut webp), format.image_format())?;
+            webp
+        }
+    };
+
+    let url = format!(
+        "data:{};base64,{}",
+        format.mime(),
+        general_purpose::STANDARD.encode(&encoded)
+    );
+    write_cache(&key, url.as_bytes());
+
+    Ok(url)
+}
+
+/// Cached bytes for `key`. Entries that fail [`is_valid_cache_entry`]'s quick
+/// check (a write cut short by a crash, say) are deleted and reported as a
+/// miss, so they get regenerated instead of shown as broken images.
+fn read_cache(key: &str) -> Option<Vec<u8>> {
+    let cache_path = CACHE_DIR.as_ref()?.join(key);
+    if !cache_path.exists() {
+        return None;
+    }
+    let bytes = std::fs::read(&cache_path).ok()?;
+    if !is_valid_cache_entry(&cache_path, &bytes, false) {
+        let _ = std::fs::remove_file(&cache_path);
+        return None;
+    }
+    Some(bytes)
+}
+
+fn write_cache(key: &str, bytes: &[u8]) {
+    if let Some(ref dir) = *CACHE_DIR {
+        let _ = std::fs::write(dir.join(key), bytes);
+    }
+}
+
+/// Hex digest identifying one rendering of `path`: the extension, size and
+/// `variant` (encoding settings) plus the file's length, sampled chunks of
+/// it, and the size and age of its cover sidecar.
+fn cache_digest(path: &Path, ext: &str, size: u32, variant: &[u8]) -> Result<String> {
+    // Compute cache key by hashing file parts for stability without loading entire file
+    let mut hasher = Context::new();
+    hasher.consume(ext.as_bytes());
+    hasher.consume(&size.to_le_bytes());
+    hasher.consume(variant);
+
+    // A replaced cover changes the sidecar, or the length of an EPUB repacked
+    // around it, where the sampled chunks alone could miss it.
+    if let Some(sidecar) = cover_sidecar_path(path) {
+        let metadata = std::fs::metadata(&sidecar)?;
+        hasher.consume(metadata.len().to_le_bytes());
+        if let Ok(modified) = metadata.modified() {
+            let since_epoch = modified
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            hasher.consume(since_epoch.as_nanos().to_le_bytes());
+        }
+    }
+
+    let file = std::fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    let file_len = metadata.len();
+    hasher.consume(file_len.to_le_bytes());
+
+    // Read partial chunks like the TypeScript partialMD5 implementation
+    const STEP: u64 = 1024;
+    const SIZE: u64 = 1024;
+    let mut file = file;
+
+    for i in -1i32..=10 {
+        let pos = if i == -1 {
+            256u64
+        } else {
+            STEP << (2 * i as u32)
+        };
+        let start = pos.min(file_len);
+        let end = (start + SIZE).min(file_len);
+
+        if start >= file_len {
+            break;
+        }
+
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = vec![0u8; (end - start) as usize];
+        file.read_exact(&mut buf)?;
+        hasher.consume(&buf);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Metrics
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable that turns on per-thumbnail timing metrics. Any
+/// value other than empty, `0` or `false` enables them; read once.
+pub const METRICS_ENV: &str = "READEST_THUMBNAIL_METRICS";
+
+static METRICS_ENABLED: Lazy<bool> = Lazy::new(|| {
+    std::env::var(METRICS_ENV).is_ok_and(|v| {
+        let v = v.trim();
+        !(v.is_empty() || v == "0" || v.eq_ignore_ascii_case("false"))
+    })
+});
+
+static METRICS: Lazy<std::sync::Mutex<std::collections::BTreeMap<String, FormatMetrics>>> =
+    Lazy::new(Default::default);
+
+static METRICS_SINK: std::sync::OnceLock<fn(&ThumbnailTiming)> = std::sync::OnceLock::new();
+
+/// Whether [`METRICS_ENV`] is set. When it isn't, nothing is recorded.
+pub fn metrics_enabled() -> bool {
+    *METRICS_ENABLED
+}
+
+/// Where one [`cached_thumbnail_for_path`] request spent its time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThumbnailTiming {
+    /// Lower-cased book extension.
+    pub ext: String,
+    pub cache_hit: bool,
+    /// Extraction or rendering failed.
+    pub failed: bool,
+    /// Reading and validating the cache entry.
+    pub lookup: Duration,
+    /// Pulling the cover image out of the book.
+    pub extract: Duration,
+    /// Decoding the cover image.
+    pub decode: Duration,
+    /// Encoding the finished thumbnail.
+    pub encode: Duration,
+}
+
+impl ThumbnailTiming {
+    pub fn total(&self) -> Duration {
+        self.lookup + self.extract + self.decode + self.encode
+    }
+}
+
+/// One line per request, prefixed `thumbnail-metrics`, for logs and
+/// debugger output.
+impl std::fmt::Display for ThumbnailTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "thumbnail-metrics {} {}{} lookup={}ms extract={}ms decode={}ms encode={}ms",
+            self.ext,
+            if self.cache_hit { "hit" } else { "miss" },
+            if self.failed { " failed" } else { "" },
+            self.lookup.as_millis(),
+            self.extract.as_millis(),
+            self.decode.as_millis(),
+            self.encode.as_millis(),
+        )
+    }
+}
+
+/// Running totals for one format, as returned by
+/// [`thumbnail_metrics_snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatMetrics {
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub failures: u64,
+    pub lookup: Duration,
+    pub extract: Duration,
+    pub decode: Duration,
+    pub encode: Duration,
+    /// Longest single request.
+    pub slowest: Duration,
+}
+
+impl FormatMetrics {
+    fn add(&mut self, timing: &ThumbnailTiming) {
+        self.requests += 1;
+        if timing.cache_hit {
+            self.cache_hits += 1;
+        } else {
+            self.cache_misses += 1;
+        }
+        if timing.failed {
+            self.failures += 1;
+        }
+        self.lookup += timing.lookup;
+        self.extract += timing.extract;
+        self.decode += timing.decode;
+        self.encode += timing.encode;
+        self.slowest = self.slowest.max(timing.total());
+    }
+}
+
+/// Have every recorded [`ThumbnailTiming`] passed to `sink` as well, e.g. to
+/// emit it as an event or write it to a log. Only the first sink set sticks.
+pub fn set_metrics_sink(sink: fn(&ThumbnailTiming)) {
+    let _ = METRICS_SINK.set(sink);
+}
+
+fn record_metrics(timing: ThumbnailTiming) {
+    if let Some(sink) = METRICS_SINK.get() {
+        sink(&timing);
+    }
+    METRICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(timing.ext.clone())
+        .or_default()
+        .add(&timing);
+}
+
+/// Totals per format since the library was loaded. Empty unless
+/// [`METRICS_ENV`] is set.
+pub fn thumbnail_metrics_snapshot() -> std::collections::BTreeMap<String, FormatMetrics> {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Extraction throttling
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable capping concurrent cover extractions per process,
+/// in Explorer's provider and in the app alike. Read once, by the first
+/// [`extraction_limit`] call.
+pub const EXTRACTION_CONCURRENCY_ENV: &str = "READEST_THUMBNAIL_CONCURRENCY";
+
+/// Concurrent extractions allowed when [`EXTRACTION_CONCURRENCY_ENV`] is
+/// unset or invalid.
+pub const DEFAULT_EXTRACTION_CONCURRENCY: usize = 4;
+
+/// Upper bound for [`EXTRACTION_CONCURRENCY_ENV`].
+pub const MAX_EXTRACTION_CONCURRENCY: usize = 64;
+
+/// Concurrency limit from the raw value of [`EXTRACTION_CONCURRENCY_ENV`]:
+/// a positive integer, capped at [`MAX_EXTRACTION_CONCURRENCY`], or the
+/// default for anything else.
+pub fn parse_extraction_concurrency(value: Option<&str>) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .map_or(DEFAULT_EXTRACTION_CONCURRENCY, |n| {
+            n.min(MAX_EXTRACTION_CONCURRENCY)
+        })
+}
+
+static EXTRACTION_LIMIT: Lazy<ExtractionLimiter> = Lazy::new(|| {
+    let value = std::env::var(EXTRACTION_CONCURRENCY_ENV).ok();
+    ExtractionLimiter::new(parse_extraction_concurrency(value.as_deref()))
+});
+
+/// The process-wide limiter, sized from [`EXTRACTION_CONCURRENCY_ENV`] on
+/// first use. Interactive requests and batches in one process should all
+/// take their permits from it.
+pub fn extraction_limit() -> &'static ExtractionLimiter {
+    &EXTRACTION_LIMIT
+}
+
+/// Whether extracting a cover for `ext` is worth queueing for. TXT files
+/// only get the generated placeholder, which is cheap.
+pub fn is_heavy_extraction(ext: &str) -> bool {
+    !ext.eq_ignore_ascii_case("txt")
+}
+
+/// Counting semaphore bounding concurrent extractions. Opening a folder of
+/// large CBZs otherwise starts one full archive read per visible file at
+/// once, saturating disk and memory; past the limit, callers block until a
+/// permit is released.
+pub struct ExtractionLimiter {
+    permits: usize,
+    available: std::sync::Mutex<usize>,
+    released: std::sync::Condvar,
+}
+
+/// Held for the duration of one extraction; dropping it wakes a waiter.
+pub struct ExtractionPermit<'a> {
+    limiter: &'a ExtractionLimiter,
+}
+
+impl ExtractionLimiter {
+    /// A limiter admitting `permits` extractions at once (at least one).
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: permits.max(1),
+            available: std::sync::Mutex::new(permits.max(1)),
+            released: std::sync::Condvar::new(),
+        }
+    }
+
+    /// Extractions admitted at once.
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Wait for a free permit.
+    pub fn acquire(&self) -> ExtractionPermit<'_> {
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= 1;
+        ExtractionPermit { limiter: self }
+    }
+}
+
+impl Drop for ExtractionPermit<'_> {
+    fn drop(&mut self) {
+        let mut available = self
+            .limiter
+            .available
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *available += 1;
+        self.limiter.released.notify_one();
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Power source
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Battery charge, in percent, below which background work pauses until the
+/// machine is plugged in again. Above it, background work only slows down.
+pub const LOW_BATTERY_PERCENT: u8 = 30;
+
+static BATTERY_SAVER: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+
+/// Where the machine draws power from, as far as the OS reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerStatus {
+    /// Running on battery rather than AC. `false` when unknown, and on
+    /// machines without a battery.
+    pub on_battery: bool,
+    /// Remaining charge, if there is a battery and the OS knows it.
+    pub percent: Option<u8>,
+}
+
+/// How much background work the power source allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PowerThrottle {
+    Full,
+    /// On battery: one worker at a time.
+    Reduced,
+    /// On battery below [`LOW_BATTERY_PERCENT`]: wait for AC.
+    Paused,
+}
+
+/// Let background batches and cache warming throttle on battery (the
+/// default), or run them at full speed whatever the power source.
+pub fn set_battery_saver(enabled: bool) {
+    BATTERY_SAVER.store(enabled, std::sync::atomic::Ordering::Relaxed);
+}
+
+fn power_throttle(status: PowerStatus) -> PowerThrottle {
+    if !status.on_battery || !BATTERY_SAVER.load(std::sync::atomic::Ordering::Relaxed) {
+        PowerThrottle::Full
+    } else if status.percent.is_some_and(|p| p < LOW_BATTERY_PERCENT) {
+        PowerThrottle::Paused
+    } else {
+        PowerThrottle::Reduced
+    }
+}
+
+/// The current power source, from `GetSystemPowerStatus`.
+#[cfg(windows)]
+pub fn power_status() -> PowerStatus {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SAFETY: `status` outlives the call that fills it.
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerStatus::default();
+    }
+    PowerStatus {
+        // 1 is AC, 255 unknown.
+        on_battery: status.ACLineStatus == 0,
+        // 255 when unknown or there is no battery.
+        percent: (status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent),
+    }
+}
+
+/// The current power source, from IOKit's power source snapshot.
+#[cfg(target_os = "macos")]
+pub fn power_status() -> PowerStatus {
+    use std::ffi::{c_char, c_void, CStr};
+
+    type CFTypeRef = *const c_void;
+    const CF_NUMBER_SINT32: isize = 3;
+    const CF_STRING_UTF8: u32 = 0x0800_0100;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+        fn IOPSCopyPowerSourcesList(blob: CFTypeRef) -> CFTypeRef;
+        fn IOPSGetPowerSourceDescription(blob: CFTypeRef, source: CFTypeRef) -> CFTypeRef;
+        fn IOPSGetProvidingPowerSourceType(blob: CFTypeRef) -> CFTypeRef;
+    }
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: CFTypeRef);
+        fn CFArrayGetCount(array: CFTypeRef) -> isize;
+        fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+        fn CFDictionaryGetValue(dict: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+        fn CFNumberGetValue(number: CFTypeRef, kind: isize, value: *mut c_void) -> bool;
+        fn CFStringCreateWithCString(
+            alloc: CFTypeRef,
+            text: *const c_char,
+            encoding: u32,
+        ) -> CFTypeRef;
+        fn CFStringGetCString(
+            text: CFTypeRef,
+            buffer: *mut c_char,
+            size: isize,
+            encoding: u32,
+        ) -> bool;
+    }
+
+    /// The `i32` under `key` in `dict`, if there is one.
+    unsafe fn number(dict: CFTypeRef, key: &CStr) -> Option<i32> {
+        let key = CFStringCreateWithCString(std::ptr::null(), key.as_ptr(), CF_STRING_UTF8);
+        if key.is_null() {
+            return None;
+        }
+        let value = CFDictionaryGetValue(dict, key);
+        CFRelease(key);
+        let mut out = 0i32;
+        (!value.is_null()
+            && CFNumberGetValue(value, CF_NUMBER_SINT32, &mut out as *mut i32 as *mut c_void))
+        .then_some(out)
+    }
+
+    // SAFETY: the Copy results are released below; the Get results are
+    // owned by the snapshot and not used after it is released.
+    unsafe {
+        let blob = IOPSCopyPowerSourcesInfo();
+        if blob.is_null() {
+            return PowerStatus::default();
+        }
+        let mut source_type = [0 as c_char; 64];
+        let providing = IOPSGetProvidingPowerSourceType(blob);
+        let on_battery = !providing.is_null()
+            && CFStringGetCString(
+                providing,
+                source_type.as_mut_ptr(),
+                source_type.len() as isize,
+                CF_STRING_UTF8,
+            )
+            && CStr::from_ptr(source_type.as_ptr()).to_bytes() == b"Battery Power";
+
+        let mut percent = None;
+        let list = IOPSCopyPowerSourcesList(blob);
+        if !list.is_null() {
+            for index in 0..CFArrayGetCount(list) {
+                let source = CFArrayGetValueAtIndex(list, index);
+                let description = IOPSGetPowerSourceDescription(blob, source);
+                if description.is_null() {
+                    continue;
+                }
+                let current = number(description, c"Current Capacity");
+                let max = number(description, c"Max Capacity");
+                if let (Some(current), Some(max)) = (current, max) {
+                    if max > 0 {
+                        percent = Some((current.clamp(0, max) * 100 / max) as u8);
+                        break;
+                    }
+                }
+            }
+            CFRelease(list);
+        }
+        CFRelease(blob);
+        PowerStatus {
+            on_battery,
+            percent,
+        }
+    }
+}
+
+/// The current power source, from `/sys/class/power_supply`.
+#[cfg(target_os = "linux")]
+pub fn power_status() -> PowerStatus {
+    sysfs_power_status(Path::new("/sys/class/power_supply"))
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn power_status() -> PowerStatus {
+    PowerStatus::default()
+}
+
+/// Power status from a `power_supply` class directory. The machine is on
+/// battery when a system battery is discharging and no mains or USB supply
+/// is online; the charge is the average over system batteries. Peripheral
+/// batteries (`scope` = `Device`), like a mouse's, are ignored.
+#[cfg(any(target_os = "linux", test))]
+fn sysfs_power_status(root: &Path) -> PowerStatus {
+    let read = |dir: &Path, name: &str| {
+        std::fs::read_to_string(dir.join(name)).map(|value| value.trim().to_string())
+    };
+    let mut ac_online = false;
+    let mut discharging = false;
+    let mut charges = Vec::new();
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return PowerStatus::default();
+    };
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        match read(&dir, "type").as_deref() {
+            Ok("Battery") => {
+                if read(&dir, "scope").is_ok_and(|scope| scope == "Device") {
+                    continue;
+                }
+                discharging |= read(&dir, "status").is_ok_and(|status| status == "Discharging");
+                charges.extend(
+                    read(&dir, "capacity")
+                        .ok()
+                        .and_then(|capacity| capacity.parse::<u32>().ok()),
+                );
+            }
+            Ok(_) => ac_online |= read(&dir, "online").is_ok_and(|online| online == "1"),
+            Err(_) => {}
+        }
+    }
+    let percent = (!charges.is_empty())
+        .then(|| (charges.iter().sum::<u32>() / charges.len() as u32).min(100) as u8);
+    PowerStatus {
+        on_battery: discharging && !ac_online,
+        percent,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Batch generation
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable overriding the worker count of every
+/// [`generate_thumbnails_batch`], whatever the caller asked for. Read on
+/// each call.
+pub const BATCH_CONCURRENCY_ENV: &str = "READEST_THUMBNAIL_BATCH_CONCURRENCY";
+
+/// Upper bound for batch workers, however they are configured.
+pub const MAX_BATCH_CONCURRENCY: usize = 32;
+
+/// How a batch competes with thumbnails someone is waiting for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchPriority {
+    /// The user is looking at the result, e.g. a freshly opened folder.
+    #[default]
+    Foreground,
+    /// A library scan: workers run at background thread (and I/O) priority
+    /// and always leave one extraction permit free.
+    Background,
+}
+
+/// Settings of one [`generate_thumbnails_batch`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// As for [`cached_thumbnail_for_path`].
+    pub size: u32,
+    pub scale: u32,
+    pub quality: u8,
+    /// JPEG chroma subsampling; `None` picks it from the size, as
+    /// [`encode_thumbnail`] does.
+    pub subsampling: Option<ChromaSubsampling>,
+    /// Worker threads; `None` uses one per available core.
+    pub concurrency: Option<usize>,
+    pub priority: BatchPriority,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            size: FORMAT_DEFAULT_SIZE,
+            scale: 1,
+            quality: DEFAULT_THUMBNAIL_QUALITY,
+            subsampling: None,
+            concurrency: None,
+            priority: BatchPriority::Foreground,
+        }
+    }
+}
+
+impl BatchOptions {
+    /// Style the batch renders with: plain, at the chosen subsampling.
+    fn style(&self) -> ThumbnailStyle {
+        ThumbnailStyle {
+            subsampling: self.subsampling,
+            ..Default::default()
+        }
+    }
+}
+
+/// Worker count for a batch. A valid [`BATCH_CONCURRENCY_ENV`] value (`env`)
+/// wins over `requested`, which wins over `cores`; the result is clamped to
+/// 1..=[`MAX_BATCH_CONCURRENCY`].
+pub fn batch_concurrency(requested: Option<usize>, env: Option<&str>, cores: usize) -> usize {
+    env.and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .or(requested.filter(|n| *n > 0))
+        .unwrap_or(cores)
+        .clamp(1, MAX_BATCH_CONCURRENCY)
+}
+
+/// Drop the calling thread to background priority. On Windows this also
+/// lowers its disk and memory priority, which matters more for archive
+/// reads than CPU time does. The workers exit with the batch, so it is
+/// never raised again.
+#[cfg(windows)]
+fn lower_thread_priority() {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+    };
+    // SAFETY: the pseudo-handle of the current thread is always valid.
+    let _ = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) };
+}
+
+#[cfg(not(windows))]
+fn lower_thread_priority() {}
+
+/// Cached thumbnails of `paths`, in order, generated on several threads.
+///
+/// Workers take paths from a shared queue; cache hits are served without
+/// waiting, while extractions of heavy formats (see [`is_heavy_extraction`])
+/// hold a permit of `limiter` like any other. Share the limiter with the
+/// interactive requests: batch workers beyond its permits then only queue,
+/// so `concurrency` bounds threads and I/O handles while the limiter bounds
+/// the archives being read at once across the whole process. A
+/// [`BatchPriority::Background`] batch uses at most `permits - 1` workers,
+/// so an interactive request always finds a permit free (a limiter with one
+/// permit is shared, and requests wait for the current extraction), and a
+/// single one on battery unless [`set_battery_saver`] turned that off.
+pub fn generate_thumbnails_batch(
+    paths: &[PathBuf],
+    options: &BatchOptions,
+    overlay_policy: &OverlayPolicy,
+    limiter: &ExtractionLimiter,
+) -> Vec<Result<ScaledThumbnail>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let env = std::env::var(BATCH_CONCURRENCY_ENV).ok();
+    let mut workers = batch_concurrency(options.concurrency, env.as_deref(), cores);
+    if options.priority == BatchPriority::Background {
+        workers = workers.min(limiter.permits().saturating_sub(1).max(1));
+        if power_throttle(power_status()) != PowerThrottle::Full {
+            workers = 1;
+        }
+    }
+    let workers = workers.min(paths.len());
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<ScaledThumbnail>>> =
+        std::iter::repeat_with(|| None).take(paths.len()).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    if options.priority == BatchPriority::Background {
+                        lower_thread_priority();
+                    }
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(index) else {
+                            break;
+                        };
+                        done.push((
+                            index,
+                            batch_thumbnail(path, options, overlay_policy, limiter),
+                        ));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            // A panicking worker leaves its paths unset; they fail below.
+            if let Ok(done) = handle.join() {
+                for (index, result) in done {
+                    results[index] = Some(result);
+                }
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow!("Thumbnail worker panicked"))))
+        .collect()
+}
+
+fn batch_thumbnail(
+    path: &Path,
+    options: &BatchOptions,
+    overlay_policy: &OverlayPolicy,
+    limiter: &ExtractionLimiter,
+) -> Result<ScaledThumbnail> {
+    let ext =
+        book_extension(path).ok_or_else(|| anyhow!("Unsupported file: {}", path.display()))?;
+    let (size, scale, quality) = (options.size, options.scale, options.quality);
+    let style = options.style();
+    if let Some(cached) =
+        lookup_cached_styled_thumbnail(path, &ext, size, scale, quality, overlay_policy, &style)?
+    {
+        return Ok(cached);
+    }
+    let _permit = is_heavy_extraction(&ext).then(|| limiter.acquire());
+    cached_styled_thumbnail_for_path(path, &ext, size, scale, quality, overlay_policy, &style)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Background cache warming
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable capping the thumbnail cache, in MiB, for
+/// [`warm_cache_background`]. Read when warming starts.
+pub const CACHE_LIMIT_ENV: &str = "READEST_THUMBNAIL_CACHE_LIMIT_MB";
+
+/// Cache size warming stops at when [`CACHE_LIMIT_ENV`] is unset or invalid.
+pub const DEFAULT_CACHE_LIMIT_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Quiet time after the last interactive request before warming resumes.
+pub const WARM_IDLE_DELAY: Duration = Duration::from_secs(3);
+
+/// Books warmed between checks for stop, pause and the cache limit.
+const WARM_CHUNK: usize = 8;
+
+/// Formats warming generates thumbnails for. KFX is left out: it never has
+/// an extractable cover.
+const WARM_FORMATS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "kf8", "prc", "fb2", "fbz", "cbz", "cbr", "tar", "tgz",
+    "tar.gz", "lit", "txt", "html", "htm",
+];
+
+static WARM_RUNNING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static WARM_STOP: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static WARM_PAUSED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+/// When the last interactive request came in, if any did.
+static LAST_INTERACTIVE: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+
+/// Progress of [`warm_cache_background`], reported to its callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmEvent {
+    /// `done` of `total` books handled so far.
+    Progress {
+        done: usize,
+        total: usize,
+    },
+    /// Waiting for the host to go idle, or for AC power.
+    Paused,
+    Resumed,
+    /// Always the last event.
+    Finished(WarmReport),
+}
+
+/// Why warming ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarmOutcome {
+    /// Every book has a thumbnail or failed to get one.
+    #[default]
+    Completed,
+    /// [`stop_cache_warming`] was called.
+    Stopped,
+    /// The next books would have pushed the cache past its limit.
+    CacheFull,
+}
+
+/// Totals of one warming run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WarmReport {
+    /// Thumbnails generated.
+    pub generated: usize,
+    /// Books whose thumbnail was already cached.
+    pub cached: usize,
+    pub failed: usize,
+    /// Oldest entries deleted afterwards to bring the cache back under its
+    /// limit.
+    pub pruned: usize,
+    pub outcome: WarmOutcome,
+}
+
+/// Tell warming the host is in use: it pauses until nothing has called
+/// this for [`WARM_IDLE_DELAY`]. Call it for each thumbnail someone is
+/// waiting for.
+pub fn note_interactive_request() {
+    *LAST_INTERACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+}
+
+/// Hold warming while `paused`, e.g. while the app window has focus.
+pub fn set_cache_warming_paused(paused: bool) {
+    WARM_PAUSED.store(paused, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Stop a running [`warm_cache_background`] after its current books.
+/// Returns whether one was running.
+pub fn stop_cache_warming() -> bool {
+    let running = WARM_RUNNING.load(std::sync::atomic::Ordering::Relaxed);
+    if running {
+        WARM_STOP.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    running
+}
+
+/// Cache limit from the raw value of [`CACHE_LIMIT_ENV`]: a positive number
+/// of MiB, or [`DEFAULT_CACHE_LIMIT_BYTES`] for anything else.
+pub fn parse_cache_limit(value: Option<&str>) -> u64 {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+        .map_or(DEFAULT_CACHE_LIMIT_BYTES, |mb| {
+            mb.saturating_mul(1024 * 1024)
+        })
+}
+
+/// Bytes used by this build's cache entries, and how many there are.
+fn cache_usage(dir: &Path) -> (u64, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .filter(|entry| is_own_cache_entry(&entry.path()))
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(bytes, count), meta| {
+            (bytes + meta.len(), count + 1)
+        })
+}
+
+/// Whether `pending` more entries would take a cache holding `count`
+/// entries in `used` bytes past `limit`, judging by their average size.
+fn would_exceed_cache_limit(used: u64, count: u64, pending: usize, limit: u64) -> bool {
+    /// Entry size assumed while the cache is empty.
+    const TYPICAL_ENTRY: u64 = 64 * 1024;
+    let average = used.checked_div(count).unwrap_or(TYPICAL_ENTRY);
+    used.saturating_add(average.saturating_mul(pending as u64)) > limit
+}
+
+/// Delete this build's least recently written entries in `dir` until they
+/// fit in `limit` bytes. Returns how many were deleted.
+fn prune_cache_dir(dir: &Path, limit: u64) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut entries: Vec<(std::time::SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter(|entry| is_own_cache_entry(&entry.path()))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+            Some((modified, meta.len(), entry.path()))
+        })
+        .collect();
+    let mut used: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    entries.sort();
+    let mut pruned = 0;
+    for (_, len, path) in entries {
+        if used <= limit {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            used = used.saturating_sub(len);
+            pruned += 1;
+        }
+    }
+    pruned
+}
+
+/// Books under `root`, recursively, in a format warming handles, sorted so
+/// runs are repeatable. Unreadable folders are skipped.
+fn warm_candidates(root: &Path) -> Vec<PathBuf> {
+    let mut books = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => dirs.push(path),
+                Ok(kind)
+                    if kind.is_file()
+                        && book_extension(&path).is_some_and(|ext| {
+                            WARM_FORMATS.contains(&format_ext(&ext).as_str())
+                        }) =>
+                {
+                    books.push(path)
+                }
+                _ => {}
+            }
+        }
+    }
+    books.sort();
+    books
+}
+
+/// Wait while warming is paused, the host was used within
+/// [`WARM_IDLE_DELAY`], or the battery is below [`LOW_BATTERY_PERCENT`],
+/// reporting the pause. Returns early on stop.
+fn wait_for_idle(on_event: &dyn Fn(WarmEvent)) {
+    use std::sync::atomic::Ordering;
+    let busy = || {
+        WARM_PAUSED.load(Ordering::Relaxed)
+            || power_throttle(power_status()) == PowerThrottle::Paused
+            || LAST_INTERACTIVE
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_some_and(|at| at.elapsed() < WARM_IDLE_DELAY)
+    };
+    if !busy() {
+        return;
+    }
+    on_event(WarmEvent::Paused);
+    while busy() && !WARM_STOP.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(250));
+    }
+    on_event(WarmEvent::Resumed);
+}
+
+/// Fill the cache for every book under `root` on a background thread, so
+/// covers are ready before anyone browses the library. Returns once the
+/// thread is started; `on_event` gets progress and, last,
+/// [`WarmEvent::Finished`].
+///
+/// Books go through [`generate_thumbnails_batch`] at background priority, a
+/// few at a time. Between those, warming waits while the host is busy (see
+/// [`note_interactive_request`] and [`set_cache_warming_paused`]) or short
+/// on battery (see [`set_battery_saver`]), honours
+/// [`stop_cache_warming`], and ends with [`WarmOutcome::CacheFull`] rather
+/// than grow the cache past [`CACHE_LIMIT_ENV`]. Once the pass ends, the
+/// oldest entries are deleted until the cache fits that limit again, since
+/// covers rendered on demand are written regardless of it.
+/// Fails if warming is already running or there is no cache directory.
+pub fn warm_cache_background(
+    root: &Path,
+    options: BatchOptions,
+    overlay_policy: OverlayPolicy,
+    limiter: &'static ExtractionLimiter,
+    on_event: impl Fn(WarmEvent) + Send + 'static,
+) -> Result<()> {
+    use std::sync::atomic::Ordering;
+
+    let cache_dir = CACHE_DIR
+        .clone()
+        .ok_or_else(|| anyhow!("No thumbnail cache directory"))?;
+    if WARM_RUNNING.swap(true, Ordering::AcqRel) {
+        return Err(anyhow!("Cache warming is already running"));
+    }
+    WARM_STOP.store(false, Ordering::Relaxed);
+    let root = root.to_path_buf();
+    let limit = parse_cache_limit(std::env::var(CACHE_LIMIT_ENV).ok().as_deref());
+    let options = BatchOptions {
+        priority: BatchPriority::Background,
+        ..options
+    };
+    let spawned = std::thread::Builder::new()
+        .name("readest-cache-warm".into())
+        .spawn(move || {
+            lower_thread_priority();
+            let mut report = warm_cache(
+                &root,
+                &cache_dir,
+                limit,
+                &options,
+                &overlay_policy,
+                limiter,
+                &on_event,
+            );
+            report.pruned = prune_cache_dir(&cache_dir, limit);
+            WARM_RUNNING.store(false, Ordering::Release);
+            on_event(WarmEvent::Finished(report));
+        });
+    if let Err(e) = spawned {
+        WARM_RUNNING.store(false, Ordering::Release);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn warm_cache(
+    root: &Path,
+    cache_dir: &Path,
+    limit: u64,
+    options: &BatchOptions,
+    overlay_policy: &OverlayPolicy,
+    limiter: &ExtractionLimiter,
+    on_event: &dyn Fn(WarmEvent),
+) -> WarmReport {
+    use std::sync::atomic::Ordering;
+
+    let books = warm_candidates(root);
+    let total = books.len();
+    let mut report = WarmReport::default();
+    for (chunk_index, chunk) in books.chunks(WARM_CHUNK).enumerate() {
+        wait_for_idle(on_event);
+        if WARM_STOP.load(Ordering::Relaxed) {
+            report.outcome = WarmOutcome::Stopped;
+            return report;
+        }
+        let missing: Vec<PathBuf> = chunk
+            .iter()
+            .filter(|path| {
+                let cached = book_extension(path).is_some_and(|ext| {
+                    let (size, scale, quality) = (options.size, options.scale, options.quality);
+                    let style = options.style();
+                    lookup_cached_styled_thumbnail(
+                        path,
+                        &ext,
+                        size,
+                        scale,
+                        quality,
+                        overlay_policy,
+                        &style,
+                    )
+                    .is_ok_and(|hit| hit.is_some())
+                });
+                if cached {
+                    report.cached += 1;
+                }
+                !cached
+            })
+            .cloned()
+            .collect();
+        let (used, count) = cache_usage(cache_dir);
+        if !missing.is_empty() && would_exceed_cache_limit(used, count, missing.len(), limit) {
+            report.outcome = WarmOutcome::CacheFull;
+            return report;
+        }
+        for result in generate_thumbnails_batch(&missing, options, overlay_policy, limiter) {
+            match result {
+                Ok(_) => report.generated += 1,
+                Err(_) => report.failed += 1,
+            }
+        }
+        on_event(WarmEvent::Progress {
+            done: (chunk_index * WARM_CHUNK + chunk.len()).min(total),
+            total,
+        });
+    }
+    report
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Cache verification
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Largest edge a cached thumbnail can have: Explorer's biggest request
+/// (2560 px) at the highest scale.
+const MAX_CACHED_EDGE: u32 = 2560 * MAX_THUMBNAIL_SCALE;
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// Final chunk of every complete PNG: `IEND` with its CRC.
+const PNG_TRAILER: &[u8] = b"IEND\xAE\x42\x60\x82";
+const JPEG_SOI: &[u8] = b"\xFF\xD8\xFF";
+const JPEG_EOI: &[u8] = b"\xFF\xD9";
+
+/// Result of a thumbnail cache scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheReport {
+    /// Entries examined.
+    pub checked: usize,
+    /// Entries deleted because they were damaged.
+    pub removed: usize,
+}
+
+/// Check every entry in the thumbnail cache by fully decoding it, and delete
+/// the ones that don't decode to an image of plausible size.
+pub fn verify_thumbnail_cache() -> Result<CacheReport> {
+    match CACHE_DIR.as_ref() {
+        Some(dir) => scan_cache_dir(dir, true),
+        None => Ok(CacheReport::default()),
+    }
+}
+
+/// Like [`verify_thumbnail_cache`], but only checks signatures, trailers and
+/// image headers. Cheap enough to run whenever the provider loads.
+pub fn quick_check_thumbnail_cache() -> Result<CacheReport> {
+    match CACHE_DIR.as_ref() {
+        Some(dir) => scan_cache_dir(dir, false),
+        None => Ok(CacheReport::default()),
+    }
+}
+
+/// Whether `path` is a cache entry of this build's key-scheme version. Other
+/// files are left alone by a scan: entries of other versions belong to the
+/// builds that wrote them, and anything else isn't the cache's.
+fn is_own_cache_entry(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            is_cache_entry_name(name) && cache_key_version(name) == Some(CACHE_KEY_VERSION)
+        })
+}
+
+fn scan_cache_dir(dir: &Path, full: bool) -> Result<CacheReport> {
+    let mut report = CacheReport::default();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_file() || !is_own_cache_entry(&path) {
+            continue;
+        }
+        report.checked += 1;
+        let valid = std::fs::read(&path)
+            .map(|bytes| is_valid_cache_entry(&path, &bytes, full))
+            .unwrap_or(false);
+        if !valid && std::fs::remove_file(&path).is_ok() {
+            report.removed += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// Whether `bytes` are an intact entry for the cache file `path`: a
+/// PNG/JPEG `.thumb` or a `.url` image data URL. With `full`, images are
+/// decoded rather than only having their header read.
+fn is_valid_cache_entry(path: &Path, bytes: &[u8], full: bool) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("thumb") => is_valid_cached_image(bytes, full),
+        Some("url") => std::str::from_utf8(bytes).is_ok_and(|url| is_valid_data_url(url, full)),
+        _ => false,
+    }
+}
+
+fn is_valid_cached_image(bytes: &[u8], full: bool) -> bool {
+    let complete = (bytes.starts_with(PNG_MAGIC) && bytes.ends_with(PNG_TRAILER))
+        || (bytes.starts_with(JPEG_SOI) && bytes.ends_with(JPEG_EOI));
+    if !complete {
+        return false;
+    }
+    let dimensions = if full {
+        image::load_from_memory(bytes)
+            .ok()
+            .map(|img| (img.width(), img.height()))
+    } else {
+        image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+    };
+    dimensions.is_some_and(|(w, h)| (1..=MAX_CACHED_EDGE).contains(&w.max(h)) && w.min(h) > 0)
+}
+
+fn is_valid_data_url(url: &str, full: bool) -> bool {
+    let Some(payload) = [DataUrlFormat::Png, DataUrlFormat::WebP]
+        .iter()
+        .find_map(|format| url.strip_prefix(&format!("data:{};base64,", format.mime())))
+    else {
+        return false;
+    };
+    if !full {
+        return !payload.is_empty() && payload.len() % 4 == 0;
+    }
+    general_purpose::STANDARD
+        .decode(payload)
+        .ok()
+        .and_then(|bytes| image::load_from_memory(&bytes).ok())
+        .is_some_and(|img| img.width().max(img.height()) <= MAX_DATA_URL_SIZE)
+}
+
+/// Result of [`migrate_cache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Entries moved into the destination.
+    pub moved: usize,
+    /// Entries the destination already had; the source copy was deleted.
+    pub duplicates: usize,
+    /// Damaged entries, left where they were. Files not named like cache
+    /// entries aren't counted.
+    pub skipped: usize,
+}
+
+/// Move every intact entry of the thumbnail cache at `from` into the one at
+/// `to`, e.g. to fold a beta build's cache into the shared one named by
+/// [`CACHE_DIR_ENV`]. Entries of every key-scheme version are carried over;
+/// files not named like cache entries stay behind untouched. Since names
+/// are content digests, an entry `to` already has is identical and the
+/// source copy is simply dropped.
+pub fn migrate_cache(from: &Path, to: &Path) -> Result<MigrationReport> {
+    if std::fs::canonicalize(from).ok() == std::fs::canonicalize(to).ok() && from.exists() {
+        return Err(anyhow!("source and destination are the same cache"));
+    }
+    std::fs::create_dir_all(to)?;
+    let mut report = MigrationReport::default();
+    for entry in std::fs::read_dir(from)?.flatten() {
+        let source = entry.path();
+        let is_entry = entry.file_name().to_str().is_some_and(is_cache_entry_name);
+        if !is_entry || !source.is_file() {
+            continue;
+        }
+        let valid = std::fs::read(&source)
+            .map(|bytes| is_valid_cache_entry(&source, &bytes, false))
+            .unwrap_or(false);
+        if !valid {
+            report.skipped += 1;
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if target.exists() {
+            std::fs::remove_file(&source)?;
+            report.duplicates += 1;
+            continue;
+        }
+        // `rename` fails across volumes; fall back to copying.
+        if std::fs::rename(&source, &target).is_err() {
+            std::fs::copy(&source, &target)?;
+            std::fs::remove_file(&source)?;
+        }
+        report.moved += 1;
+    }
+    Ok(report)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Helper functions
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Local file header signature that every non-empty ZIP archive starts with.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+fn is_image_extension(name: &str) -> bool {
+    name.ends_with(".jpg")
+        || name.ends_with(".jpeg")
+        || name.ends_with(".png")
+        || name.ends_with(".gif")
+        || name.ends_with(".webp")
+        || name.ends_with(".bmp")
+}
+
+/// Entry `index` of `archive`, decrypted with `password` if it is
+/// encrypted. An encrypted entry without a password, or with the wrong one,
+/// fails with [`CoverError::Encrypted`]; the password is never part of an
+/// error.
+fn open_zip_index<'a, R: Read + Seek>(
+    archive: &'a mut ZipArchive<R>,
+    index: usize,
+    password: Option<&[u8]>,
+) -> Result<zip::read::ZipFile<'a, R>> {
+    let file = match password {
+        Some(password) => archive.by_index_decrypt(index, password),
+        None => archive.by_index(index),
+    };
+    file.map_err(|e| match e {
+        ZipError::UnsupportedArchive(msg) if msg == ZipError::PASSWORD_REQUIRED => {
+            CoverError::Encrypted("This archive is password-protected.".to_string()).into()
+        }
+        ZipError::InvalidPassword => {
+            CoverError::Encrypted("The archive password is incorrect.".to_string()).into()
+        }
+        e => e.into(),
+    })
+}
+
+fn read_zip_file_to_string<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    password: Option<&[u8]>,
+) -> Result<String> {
+    let index = archive
+        .index_for_name(name)
+        .ok_or_else(|| anyhow!("{} not found in archive", name))?;
+    let mut file = open_zip_index(archive, index, password)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn read_zip_index<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    password: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let mut file = open_zip_index(archive, index, password)?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn extract_attribute(xml: &str, tag: &str, attr: &str) -> Option<String> {
+    let pattern = format!("<{}", tag);
+    // Skip longer names sharing the prefix, e.g. `<rootfiles>` for `rootfile`.
+    let tag_pos = xml
+        .match_indices(&pattern)
+        .map(|(pos, _)| pos)
+        .find(|&pos| {
+            xml[pos + pattern.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_whitespace() || c == '/' || c == '>')
+        });
+    if let Some(tag_pos) = tag_pos {
+        let tag_end = xml[tag_pos..].find('>').unwrap_or(500) + tag_pos;
+        let tag_content = &xml[tag_pos..tag_end];
+
+        let attr_pattern = format!("{}=\"", attr);
+        if let Some(attr_pos) = tag_content.find(&attr_pattern) {
+            let value_start = attr_pos + attr_pattern.len();
+            if let Some(value_end) = tag_content[value_start..].find('"') {
+                return Some(tag_content[value_start..value_start + value_end].to_string());
+            }
+        }
+    }
+    None
+}
+
+fn find_cover_id_in_opf(opf: &str) -> Option<String> {
+    if let Some(pos) = opf.find("name=\"cover\"") {
+        let window_start = pos.saturating_sub(50);
+        let window_end = (pos + 100).min(opf.len());
+        let window = &opf[window_start..window_end];
+
+        if let Some(content_pos) = window.find("content=\"") {
+            let start = content_pos + 9;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+
+    if let Some(pos) = opf.find("properties=\"cover-image\"") {
+        let window_start = pos.saturating_sub(200);
+        let window_end = pos;
+        let window = &opf[window_start..window_end];
+
+        if let Some(id_pos) = window.rfind("id=\"") {
+            let start = id_pos + 4;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+
+    None
+}
+
+fn find_href_by_id_in_opf(opf: &str, id: &str) -> Option<String> {
+    let pattern = format!("id=\"{}\"", id);
+    if let Some(pos) = opf.find(&pattern) {
+        let window_start = pos.saturating_sub(10);
+        let window_end = (pos + 200).min(opf.len());
+        let window = &opf[window_start..window_end];
+
+        if let Some(href_pos) = window.find("href=\"") {
+            let start = href_pos + 6;
+            if let Some(end) = window[start..].find('"') {
+                return Some(window[start..start + end].to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Href of the `<guide><reference type="cover">` page. EPUB2 books often
+/// name the cover page this way instead of the image itself.
+fn find_guide_cover_in_opf(opf: &str) -> Option<String> {
+    let guide_start = opf.find("<guide")?;
+    let guide_end = opf[guide_start..]
+        .find("</guide>")
+        .map_or(opf.len(), |e| guide_start + e);
+    let guide = &opf[guide_start..guide_end];
+
+    guide.match_indices("<reference").find_map(|(pos, _)| {
+        let tag_end = guide[pos..].find('>').map_or(guide.len(), |e| pos + e);
+        let attrs = html_attributes(&guide[pos + "<reference".len()..tag_end]);
+        let is_cover = attrs
+            .iter()
+            .any(|(key, value)| key == "type" && value.trim().eq_ignore_ascii_case("cover"));
+        if !is_cover {
+            return None;
+        }
+        attrs
+            .into_iter()
+            .find(|(key, value)| key == "href" && !value.is_empty())
+            .map(|(_, href)| href)
+    })
+}
+
+/// Zip path of the image shown on the cover page `page`: its best-ranked
+/// `<img>` or SVG `<image>`, resolved against the page's folder.
+fn guide_cover_image<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    page: &str,
+    password: Option<&[u8]>,
+) -> Option<String> {
+    let xhtml = read_zip_file_to_string(archive, page, password).ok()?;
+    let (src, _) = rank_html_images(&xhtml).into_iter().next()?;
+    if src.contains(':') {
+        // `data:` URIs and links out of the book.
+        return None;
+    }
+    let page_dir = page.rsplit_once('/').map_or("", |(dir, _)| dir);
+    Some(join_zip_path(page_dir, &src))
+}
+
+/// `href` resolved against the archive folder `dir`: query and fragment
+/// dropped, percent-escapes decoded, and `.`/`..` segments folded.
+fn join_zip_path(dir: &str, href: &str) -> String {
+    let href = href.split(['?', '#']).next().unwrap_or_default();
+    let href = percent_decode(href).unwrap_or_else(|| href.to_string());
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in dir.split('/').chain(href.split('/')) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            other => segments.push(other),
+        }
+    }
+    segments.join("/")
+}
+
+fn find_first_image_in_manifest(opf: &str) -> Option<String> {
+    let manifest_start = opf.find("<manifest")?;
+    let manifest_end = opf[manifest_start..]
+        .find("</manifest>")
+        .map(|e| manifest_start + e)?;
+    let manifest = &opf[manifest_start..manifest_end];
+
+    for media_type in ["image/jpeg", "image/png", "image/gif", "image/webp"] {
+        let pattern = format!("media-type=\"{}\"", media_type);
+        if let Some(pos) = manifest.find(&pattern) {
+            let window_start = pos.saturating_sub(200);
+            let window = &manifest[window_start..pos];
+
+            if let Some(href_pos) = window.rfind("href=\"") {
+                let start = href_pos + 6;
+                if let Some(end) = window[start..].find('"') {
+                    return Some(window[start..start + end].to_string());
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // 1x1 transparent PNG, small enough to inline as the FB2 cover binary.
+    const PIXEL_PNG_B64: &str =
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+    fn sample_fb2() -> String {
+        format!(
+            r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <description>
+    <title-info>
+      <book-title>Sample</book-title>
+      <coverpage><image l:href="#cover.png"/></coverpage>
+    </title-info>
+  </description>
+  <body><section><p>Text</p></section></body>
+  <binary id="cover.png" content-type="image/png">{PIXEL_PNG_B64}</binary>
+</FictionBook>"##
+        )
+    }
+
+    fn zip_with(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            let opts = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            for (name, data) in entries {
+                w.start_file(*name, opts).unwrap();
+                w.write_all(data).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn html_cover_prefers_named_and_header_images() {
+        let html = r#"<!DOCTYPE html><html><head><title>Book</title>
+<script>document.write('<img src="script.png">')</script></head>
+<body><!-- <img src="commented.png"> -->
+<p><img src='first.png' alt="A map"></p>
+<header><IMG SRC=header.png></header>
+<svg><image xlink:href="images/Cover%20Art.jpg?v=2" /></svg>
+</body></html>"#;
+        let ranked = rank_html_images(html);
+        let srcs: Vec<&str> = ranked.iter().map(|(src, _)| src.as_str()).collect();
+        assert_eq!(
+            srcs,
+            ["images/Cover%20Art.jpg?v=2", "header.png", "first.png"]
+        );
+        assert_eq!(
+            relative_image_path(srcs[0]),
+            Some(Path::new("images").join("Cover Art.jpg"))
+        );
+        for refused in [
+            "http://example.com/c.png",
+            "/etc/c.png",
+            "C:/c.png",
+            "%5C%5Chost/c.png",
+            "../../secret.jpg",
+            "img/../../secret.jpg",
+            "..%5Csecret.jpg",
+            "%2E%2E/secret.jpg",
+        ] {
+            assert_eq!(relative_image_path(refused), None, "{refused}");
+        }
+    }
+
+    #[test]
+    fn html_cover_reads_data_uris_and_relative_files() {
+        let inline = format!(
+            r#"<html><body><img class="cover" src="data:image/png;base64,{PIXEL_PNG_B64}"></body></html>"#
+        );
+        let bytes = extract_html_cover_bytes(Cursor::new(inline), None, 64).unwrap();
+        assert_eq!(
+            bytes,
+            general_purpose::STANDARD.decode(PIXEL_PNG_B64).unwrap()
+        );
+
+        let dir = std::env::temp_dir().join(format!("readest-html-cover-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        let jpeg = sample_jpeg(8, 12);
+        std::fs::write(dir.join("img").join("front.jpg"), &jpeg).unwrap();
+        let html = r#"<img src="missing.png"><img src="./img/front.jpg">"#;
+        let bytes = extract_html_cover_bytes(Cursor::new(html), Some(&dir), 64).unwrap();
+        assert_eq!(bytes, jpeg);
+
+        // An image outside the book's folder is never read.
+        let book_dir = dir.join("book");
+        std::fs::create_dir_all(&book_dir).unwrap();
+        let html = r#"<img class="cover" src="../img/front.jpg">"#;
+        let bytes = extract_html_cover_bytes(Cursor::new(html), Some(&book_dir), 64).unwrap();
+        assert_ne!(bytes, jpeg);
+
+        // Nothing usable: the placeholder, as for TXT.
+        let html = r#"<title>No pictures</title><img src="https://example.com/c.png">"#;
+        let bytes = extract_html_cover_bytes(Cursor::new(html), Some(&dir), 64).unwrap();
+        assert_eq!(image::load_from_memory(&bytes).unwrap().width(), 64);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn epub_guide_cover_page_outranks_first_manifest_image() {
+        let container = br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+        let opf = br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <metadata/>
+  <manifest>
+    <item id="map" href="images/map.png" media-type="image/png"/>
+    <item id="art" href="images/art%20work.png" media-type="image/png"/>
+    <item id="title" href="text/titlepage.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <guide>
+    <reference type="toc" title="Contents" href="text/toc.xhtml"/>
+    <reference type="cover" title="Cover" href="text/titlepage.xhtml#start"/>
+  </guide>
+</package>"#;
+        let page = br#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+<div><img src="../images/art%20work.png" alt="Title page"/></div></body></html>"#;
+        let pixel = general_purpose::STANDARD.decode(PIXEL_PNG_B64).unwrap();
+        let archive = zip_with(&[
+            ("mimetype", b"application/epub+zip"),
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/images/map.png", &pixel),
+            ("OEBPS/images/art work.png", &pixel),
+            ("OEBPS/text/titlepage.xhtml", page),
+        ]);
+
+        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let ranked = rank_epub_covers(&mut zip, None).unwrap();
+        let names: Vec<(&str, u8)> = ranked.iter().map(|e| (e.name.as_str(), e.score)).collect();
+        assert_eq!(
+            names,
+            [
+                ("OEBPS/images/art work.png", 95),
+                ("OEBPS/images/map.png", 60)
+            ]
+        );
+    }
+
+    #[test]
+    fn fb2_plain_cover_decodes() {
+        let bytes = extract_fb2_cover_bytes(Cursor::new(sample_fb2().into_bytes())).unwrap();
+        assert!(bytes.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    fn fb2_zip_cover_decodes() {
+        let fb2 = sample_fb2();
+        let archive = zip_with(&[("book.fb2", fb2.as_bytes())]);
+        let bytes = extract_fbz_cover_bytes(Cursor::new(archive.clone())).unwrap();
+        assert!(bytes.starts_with(&[0x89, b'P', b'N', b'G']));
+        // A zipped payload behind an `.fb2` name is detected by its magic.
+        assert_eq!(
+            extract_fb2_cover_bytes(Cursor::new(archive)).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn fb2_cover_follows_coverpage_reference() {
+        // Namespaced tags, reordered attributes and a decoy binary first: the
+        // coverpage target must still win.
+        let fb2 = format!(
+            r##"<?xml version="1.0" encoding="windows-1251"?>
+<fb:FictionBook xmlns:fb="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <fb:description><fb:title-info>
+    <fb:coverpage><fb:image xlink:href="#real"/></fb:coverpage>
+  </fb:title-info></fb:description>
+  <fb:binary content-type="image/jpeg" id="decoy">/9j/AAAA</fb:binary>
+  <fb:binary content-type="image/png" id="real">
+    {PIXEL_PNG_B64}
+  </fb:binary>
+</fb:FictionBook>"##
+        );
+        let bytes = extract_fb2_cover_bytes(Cursor::new(fb2.into_bytes())).unwrap();
+        assert!(bytes.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    fn fb2_cover_falls_back_to_first_image_binary() {
+        let fb2 = format!(
+            r##"<FictionBook>
+  <description><title-info><coverpage><image href="#missing"/></coverpage></title-info></description>
+  <binary id="font" content-type="application/x-font-ttf">AAAA</binary>
+  <binary id="img" content-type="image/png">{PIXEL_PNG_B64}</binary>
+</FictionBook>"##
+        );
+        let bytes = extract_fb2_cover_bytes(Cursor::new(fb2.into_bytes())).unwrap();
+        assert!(bytes.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    fn data_url_round_trips_in_each_format() {
+        let path =
+            std::env::temp_dir().join(format!("readest-data-url-{}.fb2", std::process::id()));
+        std::fs::write(&path, sample_fb2()).unwrap();
+
+        for (format, prefix) in [
+            (DataUrlFormat::Png, "data:image/png;base64,"),
+            (DataUrlFormat::WebP, "data:image/webp;base64,"),
+        ] {
+            let url = thumbnail_data_url(&path, "fb2", 4096, format, None).unwrap();
+            let payload = url.strip_prefix(prefix).expect("data URL prefix");
+            let bytes = general_purpose::STANDARD.decode(payload).unwrap();
+            let img = image::load_from_memory(&bytes).unwrap();
+            // The one-pixel cover is kept at its size, not blown up to 512.
+            assert_eq!((img.width(), img.height()), (1, 1));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cover_preview_bypasses_the_cache() {
+        let path =
+            std::env::temp_dir().join(format!("readest-preview-cover-{}.fb2", std::process::id()));
+        std::fs::write(&path, sample_fb2()).unwrap();
+
+        let bytes = preview_cover(&path, "fb2", 256, None).unwrap();
+        assert!(bytes.starts_with(&[0x89, b'P', b'N', b'G']));
+        let img = image::load_from_memory(&bytes).unwrap();
+        assert!(img.width() <= 256 && img.height() <= 256);
+
+        let overlay = OverlayPolicy::default();
+        assert!(
+            lookup_cached_thumbnail(&path, "fb2", 256, 1, DEFAULT_THUMBNAIL_QUALITY, &overlay)
+                .unwrap()
+                .is_none()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn scaled_thumbnail_never_upscales_past_the_cover() {
+        let mut cover = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut cover), image::ImageFormat::Png)
+            .unwrap();
+
+        let at_2x = render_thumbnail(&cover, 40, 2, 100, false).unwrap();
+        assert_eq!((at_2x.width, at_2x.height), (53, 80));
+        assert!(!at_2x.native_limited);
+
+        let at_3x = render_thumbnail(&cover, 40, 3, 100, false).unwrap();
+        assert_eq!((at_3x.width, at_3x.height), (64, 96));
+        assert!(at_3x.native_limited);
+
+        let cached = ScaledThumbnail::from_encoded(at_3x.bytes, 120).unwrap();
+        assert_eq!((cached.width, cached.height), (64, 96));
+        assert!(cached.native_limited);
+    }
+
+    #[test]
+    fn format_default_size_follows_the_format() {
+        assert_eq!(resolve_thumbnail_size("cbz", FORMAT_DEFAULT_SIZE), 512);
+        assert_eq!(resolve_thumbnail_size(".EPUB", FORMAT_DEFAULT_SIZE), 256);
+        assert_eq!(resolve_thumbnail_size("cbz", 200), 200);
+    }
+
+    #[test]
+    fn small_epub_cover_keeps_its_native_size() {
+        let container =
+            br#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#;
+        let opf = br#"<package><manifest><item id="c" href="cover.jpg" media-type="image/jpeg" properties="cover-image"/></manifest></package>"#;
+        let epub = zip_with(&[
+            ("META-INF/container.xml", container),
+            ("content.opf", opf),
+            ("cover.jpg", &sample_jpeg(300, 450)),
+        ]);
+        let cover = extract_epub_cover_bytes(Cursor::new(epub)).unwrap();
+
+        let size = resolve_thumbnail_size("epub", FORMAT_DEFAULT_SIZE);
+        let thumbnail = render_thumbnail(&cover, size, 2, 90, false).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (300, 450));
+        assert!(thumbnail.native_limited);
+        let img = image::load_from_memory(&thumbnail.bytes).unwrap();
+        assert_eq!((img.width(), img.height()), (300, 450));
+    }
+
+    #[test]
+    fn large_comic_page_is_downsampled_to_the_comic_default() {
+        let cbz = zip_with(&[("001.jpg", &sample_jpeg(2000, 3000))]);
+        let cover = extract_cbz_cover_bytes(Cursor::new(cbz)).unwrap();
+
+        let size = resolve_thumbnail_size("cbz", FORMAT_DEFAULT_SIZE);
+        let thumbnail = render_thumbnail(&cover, size, 1, 90, false).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (341, 512));
+        assert!(!thumbnail.native_limited);
+    }
+
+    #[test]
+    fn styled_thumbnails_are_rounded_and_shadowed() {
+        let mut cover = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut cover), image::ImageFormat::Png)
+            .unwrap();
+        let render = |style: &ThumbnailStyle| {
+            let thumbnail = render_thumbnail_timed(
+                &cover,
+                40,
+                1,
+                80,
+                false,
+                style,
+                &mut ThumbnailTiming::default(),
+            )
+            .unwrap();
+            let img = image::load_from_memory(&thumbnail.bytes)
+                .unwrap()
+                .to_rgba8();
+            assert_eq!((thumbnail.width, thumbnail.height), img.dimensions());
+            img
+        };
+
+        let plain = render(&ThumbnailStyle::default());
+        assert_eq!(plain.dimensions(), (27, 40));
+        assert_eq!(plain.get_pixel(0, 0).0[3], 255);
+
+        let rounded = render(&ThumbnailStyle {
+            corner_radius: 6,
+            shadow: None,
+            subsampling: None,
+        });
+        assert_eq!(rounded.dimensions(), (27, 40));
+        assert_eq!(rounded.get_pixel(0, 0).0[3], 0);
+        assert_eq!(rounded.get_pixel(26, 39).0[3], 0);
+        assert_eq!(rounded.get_pixel(13, 20).0[3], 255);
+        // The arc is anti-aliased rather than stair-stepped.
+        assert!((0..6).any(|i| !matches!(rounded.get_pixel(i, i).0[3], 0 | 255)));
+
+        let shadow = ShadowSpec {
+            offset_x: 0,
+            offset_y: 4,
+            blur: 4,
+            color: Rgba([0, 0, 0, 128]),
+        };
+        let shadowed = render(&ThumbnailStyle {
+            corner_radius: 0,
+            shadow: Some(shadow),
+            subsampling: None,
+        });
+        // Blur reach 6 on every side, shifted down by the offset.
+        assert_eq!(shadowed.dimensions(), (27 + 12, 40 + 12));
+        assert_eq!(shadowed.get_pixel(6, 2).0[3], 255);
+        let below = shadowed.get_pixel(19, 40 + 2 + 3).0[3];
+        assert!(below > 0 && below < 128, "shadow alpha {below}");
+        assert_eq!(shadowed.get_pixel(0, 0).0[3], 0);
+    }
+
+    #[test]
+    fn plain_style_keeps_existing_cache_keys() {
+        let path = std::env::temp_dir().join(format!("style-key-{}.txt", std::process::id()));
+        std::fs::write(&path, b"cache key").unwrap();
+        let key = |style: &ThumbnailStyle| {
+            thumbnail_cache_key(&path, "txt", 40, 1, 80, true, style)
+                .unwrap()
+                .0
+        };
+        let legacy = format!(
+            "v{CACHE_KEY_VERSION}-{}.thumb",
+            cache_digest(&path, "txt", 40, &[80, 1, 1]).unwrap()
+        );
+        assert_eq!(key(&ThumbnailStyle::default()), legacy);
+
+        let rounded = ThumbnailStyle {
+            corner_radius: 4,
+            shadow: None,
+            subsampling: None,
+        };
+        let shadowed = ThumbnailStyle {
+            shadow: Some(ShadowSpec::default()),
+            ..rounded
+        };
+        assert_ne!(key(&rounded), legacy);
+        assert_ne!(key(&rounded), key(&shadowed));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn jpeg_chroma_subsampling_follows_size_or_choice() {
+        // Horizontal and vertical sampling factors of the luma component in
+        // the JPEG's baseline frame header.
+        let luma_sampling = |jpeg: &[u8]| {
+            let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+            jpeg[sof + 11]
+        };
+        let cover = |width, height| {
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, y| {
+                image::Rgb([(x * 7) as u8, (y * 5) as u8, 128])
+            }))
+        };
+
+        let small = encode_thumbnail(&cover(100, 150), 80).unwrap();
+        assert_eq!(luma_sampling(&small), 0x11);
+        let large = encode_thumbnail(&cover(600, 900), 80).unwrap();
+        assert_eq!(luma_sampling(&large), 0x22);
+        let chosen = encode_thumbnail_with_subsampling(
+            &cover(100, 150),
+            80,
+            Some(ChromaSubsampling::Yuv422),
+        )
+        .unwrap();
+        assert_eq!(luma_sampling(&chosen), 0x21);
+
+        assert_eq!(
+            "4:2:0".parse::<ChromaSubsampling>().unwrap(),
+            ChromaSubsampling::Yuv420
+        );
+        assert!("411".parse::<ChromaSubsampling>().is_err());
+
+        let path = std::env::temp_dir().join(format!("chroma-key-{}.txt", std::process::id()));
+        std::fs::write(&path, b"cache key").unwrap();
+        let key = |subsampling| {
+            let style = ThumbnailStyle {
+                subsampling,
+                ..ThumbnailStyle::default()
+            };
+            thumbnail_cache_key(&path, "txt", 40, 1, 80, true, &style)
+                .unwrap()
+                .0
+        };
+        assert_ne!(key(None), key(Some(ChromaSubsampling::Yuv444)));
+        assert_ne!(
+            key(Some(ChromaSubsampling::Yuv444)),
+            key(Some(ChromaSubsampling::Yuv420))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn contact_sheet_keeps_missing_covers_in_the_grid() {
+        let dir = std::env::temp_dir().join(format!("readest-sheet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let book = dir.join("book.fb2");
+        std::fs::write(&book, sample_fb2()).unwrap();
+        let paths = [book, dir.join("missing.epub"), dir.join("no-extension")];
+
+        let options = ContactSheetOptions::default();
+        // One permit is enough: each cell releases it before the next.
+        let limiter = ExtractionLimiter::new(1);
+        let png = generate_contact_sheet(&paths, 2, 40, &options, &limiter).unwrap();
+        let sheet = image::load_from_memory(&png).unwrap().to_rgba8();
+        // 2 columns x 2 rows of 40x60 cells with 16 px gaps.
+        assert_eq!(sheet.dimensions(), (2 * 40 + 3 * 16, 2 * 60 + 3 * 16));
+        // The missing books get placeholder borders; the empty slot doesn't.
+        assert_eq!(sheet.get_pixel(16 + 40 + 16, 16).0, [200, 200, 200, 255]);
+        assert_eq!(sheet.get_pixel(16, 16 + 60 + 16).0, [200, 200, 200, 255]);
+        assert_eq!(sheet.get_pixel(16 + 40 + 16, 16 + 60 + 16).0, [255; 4]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn page_previews_follow_reading_order_and_stop_when_cancelled() {
+        let page = |alpha| {
+            let mut png = Vec::new();
+            solid_cover(alpha)
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            png
+        };
+        let (opaque, translucent) = (page(255), page(128));
+        let archive = zip_with(&[
+            ("page10.png", &opaque),
+            ("page2.png", &translucent),
+            ("broken.jpg", b"not an image"),
+            ("page1.png", &opaque),
+        ]);
+        let path = std::env::temp_dir().join(format!("readest-preview-{}.cbz", std::process::id()));
+        std::fs::write(&path, archive).unwrap();
+        let limiter = ExtractionLimiter::new(1);
+        let cancel = std::sync::atomic::AtomicBool::new(false);
+
+        let pages = preview_pages(&path, "cbz", 2, 48, &limiter, &cancel).unwrap();
+        assert_eq!(pages.len(), 2);
+        // page1 is opaque and stored as JPEG, page2 keeps its alpha as PNG.
+        assert!(pages[0].starts_with(JPEG_SOI));
+        assert!(pages[1].starts_with(PNG_MAGIC));
+        let first = image::load_from_memory(&pages[0]).unwrap();
+        assert!(first.width() <= 48 && first.height() <= 48);
+
+        // Asking for more than the book has returns what's there.
+        assert_eq!(
+            preview_pages(&path, "CBZ", 10, 48, &limiter, &cancel)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+        let err = preview_pages(&path, "cbz", 2, 48, &limiter, &cancel).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CoverError>(),
+            Some(&CoverError::Cancelled)
+        );
+        assert!(preview_pages(&path, "pdf", 2, 48, &limiter, &cancel)
+            .unwrap_err()
+            .downcast_ref::<CoverError>()
+            .is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn oversized_contact_sheet_is_rejected_up_front() {
+        let paths = vec![PathBuf::from("missing.epub"); 64];
+        let options = ContactSheetOptions::default();
+        let limiter = ExtractionLimiter::new(1);
+        assert!(generate_contact_sheet(&paths, 8, 1200, &options, &limiter).is_err());
+        assert!(generate_contact_sheet(&paths, 0, 100, &options, &limiter).is_err());
+        assert!(generate_contact_sheet(&[], 4, 100, &options, &limiter).is_err());
+    }
+
+    #[test]
+    fn cache_scan_removes_damaged_entries() {
+        let dir = std::env::temp_dir().join(format!("readest-cache-scan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let good = encode_thumbnail(&solid_cover(255), DEFAULT_THUMBNAIL_QUALITY).unwrap();
+        let mut png = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let url = format!(
+            "data:image/png;base64,{}",
+            general_purpose::STANDARD.encode(&png)
+        );
+        let entry = |n: u32, ext: &str| dir.join(format!("v{CACHE_KEY_VERSION}-{n:032x}.{ext}"));
+        std::fs::write(entry(1, "thumb"), &good).unwrap();
+        std::fs::write(entry(2, "url"), &url).unwrap();
+        std::fs::write(entry(3, "thumb"), &good[..good.len() / 2]).unwrap();
+        std::fs::write(entry(4, "thumb"), b"not an image").unwrap();
+        std::fs::write(entry(5, "url"), "data:text/plain;base64,aGk=").unwrap();
+        // Files that aren't the cache's, in a dir shared with other things.
+        std::fs::write(dir.join("leftover.tmp"), b"").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"keep me").unwrap();
+        std::fs::write(dir.join("photo.thumb"), b"not ours").unwrap();
+
+        let report = scan_cache_dir(&dir, true).unwrap();
+        assert_eq!(
+            report,
+            CacheReport {
+                checked: 5,
+                removed: 3
+            }
+        );
+        assert!(entry(1, "thumb").exists() && entry(2, "url").exists());
+        for name in ["leftover.tmp", "notes.txt", "photo.thumb"] {
+            assert!(dir.join(name).exists(), "{name} was deleted");
+        }
+
+        let report = scan_cache_dir(&dir, false).unwrap();
+        assert_eq!(
+            report,
+            CacheReport {
+                checked: 2,
+                removed: 0
+            }
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quick_check_catches_truncated_png_and_jpeg() {
+        let jpeg = encode_thumbnail(&solid_cover(255), 80).unwrap();
+        let png = encode_thumbnail(&solid_cover(255), 100).unwrap();
+        for bytes in [&jpeg, &png] {
+            assert!(is_valid_cached_image(bytes, false));
+            assert!(!is_valid_cached_image(&bytes[..bytes.len() - 1], false));
+        }
+    }
+
+    #[test]
+    fn fb2_zip_without_fb2_entry_fails() {
+        let archive = zip_with(&[("readme.txt", b"hello")]);
+        assert!(extract_fbz_cover_bytes(Cursor::new(archive)).is_err());
+    }
+
+    #[test]
+    fn animated_preview_cycles_pages_in_reading_order() {
+        use image::AnimationDecoder;
+
+        let page = |shade: u8, height: u32| {
+            let img = image::RgbaImage::from_pixel(64, height, Rgba([shade, shade, shade, 255]));
+            let mut png = Vec::new();
+            DynamicImage::ImageRgba8(img)
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            png
+        };
+        let (first, second, last) = (page(0, 96), page(128, 80), page(250, 96));
+        let dir = std::env::temp_dir();
+        let write = |name: &str, entries: &[(&str, &[u8])]| {
+            let path = dir.join(format!(
+                "readest-animated-{}-{name}.cbz",
+                std::process::id()
+            ));
+            std::fs::write(&path, zip_with(entries)).unwrap();
+            path
+        };
+        let frames_of = |gif: Vec<u8>| {
+            let decoder = image::codecs::gif::GifDecoder::new(Cursor::new(gif)).unwrap();
+            decoder.into_frames().collect_frames().unwrap()
+        };
+        let limiter = ExtractionLimiter::new(1);
+
+        let comic = write(
+            "ltr",
+            &[("p10.png", &last), ("p2.png", &second), ("p1.png", &first)],
+        );
+        let frames = frames_of(animated_preview(&comic, "cbz", 2, 48, &limiter).unwrap());
+        assert_eq!(frames.len(), 2);
+        let canvas = frames[0].buffer();
+        assert!(canvas.width() <= 48 && canvas.height() <= 48);
+        assert!(canvas.get_pixel(canvas.width() / 2, canvas.height() / 2)[0] < 20);
+        assert_eq!(frames[1].buffer().dimensions(), canvas.dimensions());
+        assert_eq!(
+            frames[0].delay(),
+            image::Delay::from_numer_denom_ms(ANIMATED_PREVIEW_FRAME_DELAY_MS, 1)
+        );
+
+        let manga = write(
+            "rtl",
+            &[
+                ("p1.png", &first),
+                ("p2.png", &second),
+                ("p3.png", &last),
+                (
+                    "ComicInfo.xml",
+                    b"<ComicInfo><Manga>YesAndRightToLeft</Manga></ComicInfo>",
+                ),
+            ],
+        );
+        let frames = frames_of(animated_preview(&manga, "cbz", 10, 48, &limiter).unwrap());
+        assert_eq!(frames.len(), 3);
+        let canvas = frames[0].buffer();
+        assert!(canvas.get_pixel(canvas.width() / 2, canvas.height() / 2)[0] > 230);
+
+        // One readable page is a still image, not a one-frame animation.
+        let single = write("single", &[("p1.png", &first), ("p2.jpg", b"broken")]);
+        let still = animated_preview(&single, "cbz", 4, 48, &limiter).unwrap();
+        assert_ne!(
+            image::guess_format(&still).unwrap(),
+            image::ImageFormat::Gif
+        );
+        assert!(animated_preview(&single, "epub", 4, 48, &limiter).is_err());
+
+        for path in [comic, manga, single] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn cache_warming_finds_books_and_respects_the_limit() {
+        assert_eq!(parse_cache_limit(Some("64")), 64 * 1024 * 1024);
+        assert_eq!(parse_cache_limit(Some("0")), DEFAULT_CACHE_LIMIT_BYTES);
+        assert_eq!(parse_cache_limit(None), DEFAULT_CACHE_LIMIT_BYTES);
+
+        // 10 entries averaging 100 bytes: 8 more fit in 2000, 11 don't.
+        assert!(!would_exceed_cache_limit(1000, 10, 8, 2000));
+        assert!(would_exceed_cache_limit(1000, 10, 11, 2000));
+        assert!(would_exceed_cache_limit(0, 0, 1, 1024));
+
+        let root = std::env::temp_dir().join(format!("readest-warm-{}", std::process::id()));
+        let nested = root.join("Series").join("Vol 1");
+        std::fs::create_dir_all(&nested).unwrap();
+        for name in ["b.epub", "notes.docx", "a.kepub.epub", "locked.kfx"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        std::fs::write(nested.join("issue.cbz"), b"").unwrap();
+        assert_eq!(
+            warm_candidates(&root),
+            vec![
+                nested.join("issue.cbz"),
+                root.join("a.kepub.epub"),
+                root.join("b.epub"),
+            ]
+        );
+        assert!(!stop_cache_warming());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cache_pruning_deletes_the_oldest_entries() {
+        let dir = std::env::temp_dir().join(format!("readest-cache-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = |n: u32| dir.join(format!("v{CACHE_KEY_VERSION}-{n:032x}.thumb"));
+        let old = std::time::SystemTime::now() - Duration::from_secs(3600);
+        for n in 1..=4 {
+            std::fs::write(entry(n), [0u8; 100]).unwrap();
+            let file = std::fs::File::options().write(true).open(entry(n)).unwrap();
+            file.set_modified(old + Duration::from_secs(n.into()))
+                .unwrap();
+        }
+        // Not the cache's, so neither counted nor deleted.
+        std::fs::write(dir.join("notes.txt"), [0u8; 1000]).unwrap();
+
+        assert_eq!(prune_cache_dir(&dir, 400), 0);
+        assert_eq!(prune_cache_dir(&dir, 250), 2);
+        assert!(!entry(1).exists() && !entry(2).exists());
+        assert!(entry(3).exists() && entry(4).exists());
+        assert!(dir.join("notes.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_archives_need_their_password() {
+        let mut png = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let encrypted = |entries: &[(&str, &[u8])]| {
+            let mut buf = Vec::new();
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            let opts = zip::write::SimpleFileOptions::default()
+                .with_aes_encryption(zip::AesMode::Aes256, "s3cret");
+            for (name, data) in entries {
+                w.start_file(*name, opts).unwrap();
+                w.write_all(data).unwrap();
+            }
+            w.finish().unwrap();
+            buf
+        };
+
+        let comic = encrypted(&[("page2.png", b"not this one"), ("page1.png", &png)]);
+        assert_eq!(
+            extract_cbz_cover_bytes_with_password(Cursor::new(&comic), None, Some("s3cret"))
+                .unwrap(),
+            png
+        );
+        for password in [None, Some("guess")] {
+            let err = extract_cbz_cover_bytes_with_password(Cursor::new(&comic), None, password)
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CoverError>(),
+                Some(CoverError::Encrypted(_))
+            ));
+            assert!(!format!("{err:#}").contains("s3cret"));
+        }
+
+        let book = encrypted(&[
+            ("mimetype", b"application/epub+zip"),
+            ("OEBPS/images/cover.png", &png),
+        ]);
+        assert_eq!(
+            extract_epub_cover_bytes_with_password(Cursor::new(&book), Some("s3cret")).unwrap(),
+            png
+        );
+        let err = extract_epub_cover_bytes(Cursor::new(&book)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CoverError>(),
+            Some(CoverError::Encrypted(_))
+        ));
+
+        // The path-based entry points pass the password through.
+        let path =
+            std::env::temp_dir().join(format!("readest-encrypted-{}.cbz", std::process::id()));
+        std::fs::write(&path, &comic).unwrap();
+        assert!(preview_cover(&path, "cbz", 32, None).is_err());
+        let preview = preview_cover(&path, "cbz", 32, Some("s3cret")).unwrap();
+        assert!(preview.starts_with(PNG_MAGIC));
+        let url = thumbnail_data_url(&path, "cbz", 32, DataUrlFormat::Png, Some("s3cret"));
+        assert!(url.unwrap().starts_with("data:image/png;base64,"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn solid_cover(alpha: u8) -> DynamicImage {
+        let img = image::RgbaImage::from_fn(64, 96, |x, y| {
+            Rgba([(x * 4) as u8, (y * 2) as u8, 128, alpha])
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn opaque_thumbnail_below_full_quality_is_jpeg() {
+        let out = encode_thumbnail(&solid_cover(255), DEFAULT_THUMBNAIL_QUALITY).unwrap();
+        assert_eq!(image::guess_format(&out).unwrap(), image::ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn transparent_or_full_quality_thumbnail_stays_png() {
+        let translucent = encode_thumbnail(&solid_cover(128), 10).unwrap();
+        assert_eq!(
+            image::guess_format(&translucent).unwrap(),
+            image::ImageFormat::Png
+        );
+
+        let lossless = encode_thumbnail(&solid_cover(255), 100).unwrap();
+        assert_eq!(
+            image::guess_format(&lossless).unwrap(),
+            image::ImageFormat::Png
+        );
+    }
+
+    #[test]
+    fn lower_quality_produces_smaller_jpeg() {
+        let low = encode_thumbnail(&solid_cover(255), 20).unwrap();
+        let high = encode_thumbnail(&solid_cover(255), 95).unwrap();
+        assert!(low.len() < high.len());
+    }
+
+    #[test]
+    fn png_level_tracks_quality() {
+        use image::codecs::png::CompressionType;
+        assert_eq!(png_compression_for_quality(0), CompressionType::Level(9));
+        assert_eq!(png_compression_for_quality(100), CompressionType::Level(1));
+        assert_eq!(png_compression_for_quality(255), CompressionType::Level(1));
+    }
+
+    fn unsupported_message(err: anyhow::Error) -> String {
+        match err.downcast_ref::<CoverError>() {
+            Some(CoverError::Unsupported(msg)) => msg.clone(),
+            _ => panic!("expected CoverError::Unsupported, got: {err}"),
+        }
+    }
+
+    #[test]
+    fn kfx_container_is_reported_unsupported() {
+        let mut book = b"CONT\x02\x00\x12\x00".to_vec();
+        book.resize(64, 0);
+        let msg = unsupported_message(extract_kfx_cover_bytes(Cursor::new(book)).unwrap_err());
+        assert!(msg.contains("KFX book"));
+        assert!(msg.contains("AZW3/KF8"));
+    }
+
+    #[test]
+    fn drm_kfx_is_flagged() {
+        let book = b"\xeaDRMION\xee\x00\x00\x00\x00".to_vec();
+        let msg = unsupported_message(extract_kfx_cover_bytes(Cursor::new(book)).unwrap_err());
+        assert!(msg.contains("DRM-protected"));
+    }
+
+    #[test]
+    fn kfx_zip_is_recognized() {
+        let archive = zip_with(&[("book.kfx", b"CONT"), ("book.kfx-metadata", b"")]);
+        let msg = unsupported_message(extract_kfx_cover_bytes(Cursor::new(archive)).unwrap_err());
+        assert!(msg.contains("KFX-ZIP"));
+    }
+
+    /// Minimal MOBI: record 0 holds the MOBI header and an EXTH block whose
+    /// `CoverOffset` points at record 1, followed by `trailing` extra records.
+    fn sample_mobi(cover: &[u8], trailing: &[&[u8]]) -> Vec<u8> {
+        let records: Vec<&[u8]> = std::iter::once(cover)
+            .chain(trailing.iter().copied())
+            .collect();
+        mobi_with_records(mobi_record0(|_| {}), &records)
+    }
+
+    /// Record 0 of [`sample_mobi`], with `patch` applied to its header.
+    fn mobi_record0(patch: impl FnOnce(&mut [u8])) -> Vec<u8> {
+        let mut record0 = vec![0u8; 256];
+        record0[16..20].copy_from_slice(b"MOBI");
+        record0[20..24].copy_from_slice(&240u32.to_be_bytes()); // EXTH at 16 + 240
+        record0[108..112].copy_from_slice(&1u32.to_be_bytes()); // first image record
+        record0[128..132].copy_from_slice(&0x40u32.to_be_bytes()); // has EXTH
+        record0.extend_from_slice(b"EXTH");
+        record0.extend_from_slice(&24u32.to_be_bytes());
+        record0.extend_from_slice(&1u32.to_be_bytes());
+        record0.extend_from_slice(&201u32.to_be_bytes());
+        record0.extend_from_slice(&12u32.to_be_bytes());
+        record0.extend_from_slice(&0u32.to_be_bytes());
+        patch(&mut record0);
+        record0
+    }
+
+    /// A PalmDB of `record0` followed by `rest`.
+    fn mobi_with_records(record0: Vec<u8>, rest: &[&[u8]]) -> Vec<u8> {
+        let records: Vec<&[u8]> = std::iter::once(record0.as_slice())
+            .chain(rest.iter().copied())
+            .collect();
+        let mut header = vec![0u8; 78];
+        header[60..68].copy_from_slice(b"BOOKMOBI");
+        header[76..78].copy_from_slice(&(records.len() as u16).to_be_bytes());
+
+        let mut offset = 78 + 8 * records.len();
+        let mut book = header;
+        for record in &records {
+            book.extend_from_slice(&(offset as u32).to_be_bytes());
+            book.extend_from_slice(&[0u8; 4]);
+            offset += record.len();
+        }
+        for record in records {
+            book.extend_from_slice(record);
+        }
+        book
+    }
+
+    /// Cursor that refuses `SeekFrom::End`, like a stream of unknown length.
+    struct NoEofSeek(Cursor<Vec<u8>>);
+
+    impl Read for NoEofSeek {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Seek for NoEofSeek {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            if let SeekFrom::End(_) = pos {
+                return Err(std::io::Error::other("length unknown"));
+            }
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn mobi_cover_ends_at_next_record_without_eof_seek() {
+        let cover = b"\x89PNG\r\n\x1a\ncover";
+        let book = sample_mobi(cover, &[b"trailing record"]);
+        let bytes = extract_mobi_cover_bytes(NoEofSeek(Cursor::new(book))).unwrap();
+        assert_eq!(bytes, cover);
+    }
+
+    #[test]
+    fn mobi_last_record_cover_uses_known_length() {
+        let cover = b"\x89PNG\r\n\x1a\ncover";
+        let book = sample_mobi(cover, &[]);
+        let len = book.len() as u64;
+
+        let bytes =
+            extract_mobi_cover_bytes_with_len(NoEofSeek(Cursor::new(book.clone())), Some(len))
+                .unwrap();
+        assert_eq!(bytes, cover);
+        assert!(extract_mobi_cover_bytes(NoEofSeek(Cursor::new(book.clone()))).is_err());
+        assert_eq!(extract_mobi_cover_bytes(Cursor::new(book)).unwrap(), cover);
+    }
+
+    #[test]
+    fn huff_cdic_azw_cover_skips_compression_tables() {
+        let cover = b"\xFF\xD8\xFFcover";
+        // One text record, then the HUFF and CDIC tables, then the images;
+        // the header's first image index wrongly points at the HUFF record.
+        let record0 = mobi_record0(|header| {
+            header[0..2].copy_from_slice(&MOBI_COMPRESSION_HUFF_CDIC.to_be_bytes());
+            header[8..10].copy_from_slice(&1u16.to_be_bytes());
+            header[108..112].copy_from_slice(&2u32.to_be_bytes());
+            header[112..116].copy_from_slice(&2u32.to_be_bytes());
+            header[116..120].copy_from_slice(&2u32.to_be_bytes());
+        });
+        let book = mobi_with_records(
+            record0,
+            &[
+                b"text",
+                b"HUFF\0\0\0\x18table",
+                b"CDIC\0\0\0\x10table",
+                cover,
+                b"GIF89a",
+            ],
+        );
+        assert_eq!(extract_mobi_cover_bytes(Cursor::new(book)).unwrap(), cover);
+    }
+
+    #[test]
+    fn kfx_named_azw_is_reported_unsupported() {
+        let mut book = b"CONT".to_vec();
+        book.resize(128, 0);
+        let err = extract_mobi_cover_bytes(Cursor::new(book)).unwrap_err();
+        assert!(err.downcast_ref::<CoverError>().is_some());
+    }
+
+    #[test]
+    fn non_kfx_file_is_a_plain_error() {
+        let err = extract_kfx_cover_bytes(Cursor::new(b"hello".to_vec())).unwrap_err();
+        assert!(err.downcast_ref::<CoverError>().is_none());
+    }
+
+    #[test]
+    fn cbz_prefers_comic_info_front_cover() {
+        let comic_info = br#"<?xml version="1.0"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <Title>Sample</Title>
+  <Pages>
+    <Page Image="0" ImageSize="100" />
+    <Page Image="2" Type="FrontCover" ImageSize="100" />
+  </Pages>
+</ComicInfo>"#;
+        let archive = zip_with(&[
+            ("ComicInfo.xml", comic_info),
+            ("release.nfo", b"scanned by"),
+            ("page10.jpg", b"third"),
+            ("page1.jpg", b"first"),
+            ("page2.jpg", b"second"),
+        ]);
+        // Natural order is page1, page2, page10; Image="2" is page10.
+        let cover = extract_cbz_cover_bytes(Cursor::new(archive)).unwrap();
+        assert_eq!(cover, b"third");
+    }
+
+    #[test]
+    fn cbz_ranks_front_first_and_back_cover_last() {
+        let comic_info = br#"<ComicInfo><Pages>
+    <Page Image="2" Type="BackCover"/>
+    <Page Image="1" Type="FrontCover"/>
+</Pages></ComicInfo>"#;
+        let archive = zip_with(&[
+            ("ComicInfo.xml", comic_info),
+            ("p1.jpg", b"banner"),
+            ("p2.jpg", b"front"),
+            ("p3.jpg", b"back"),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let ranked: Vec<(String, u8)> = rank_cbz_covers(&mut archive)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.score))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                ("p2.jpg".to_string(), 100),
+                ("p1.jpg".to_string(), 80),
+                ("p3.jpg".to_string(), 40)
+            ]
+        );
+    }
+
+    #[test]
+    fn epub_ranking_puts_the_opf_cover_first() {
+        let container = br#"<container><rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+        let opf = br#"<package><metadata><meta name="cover" content="vol"/></metadata>
+<manifest>
+  <item id="fig" href="img/figure.png" media-type="image/png"/>
+  <item id="vol" href="img/volume.png" media-type="image/png"/>
+</manifest></package>"#;
+        let archive = zip_with(&[
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/img/figure.png", b"figure"),
+            ("OEBPS/img/series-cover-banner.png", b"banner"),
+            ("OEBPS/img/volume.png", b"volume"),
+        ]);
+        let mut zip = ZipArchive::new(Cursor::new(archive.clone())).unwrap();
+        let ranked: Vec<(String, u8)> = rank_epub_covers(&mut zip, None)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.score))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                ("OEBPS/img/volume.png".to_string(), 100),
+                ("OEBPS/img/series-cover-banner.png".to_string(), 80),
+                ("OEBPS/img/figure.png".to_string(), 60)
+            ]
+        );
+        assert_eq!(
+            extract_epub_cover_bytes(Cursor::new(archive)).unwrap(),
+            b"volume"
+        );
+    }
+
+    #[test]
+    fn epub_fallback_prefers_cover_shaped_images_over_the_largest() {
+        // No cover metadata and no cover-named files: only pass 3 applies.
+        let illustration = sample_jpeg(1200, 800);
+        let plate = sample_jpeg(600, 900);
+        let square = sample_jpeg(800, 800);
+        let logo = sample_jpeg(240, 60);
+        assert!(illustration.len() > plate.len() && square.len() > plate.len());
+        let archive = zip_with(&[
+            ("OEBPS/images/fig01.jpg", &illustration),
+            ("OEBPS/images/logo.jpg", &logo),
+            ("OEBPS/images/fig02.jpg", &square),
+            ("OEBPS/images/img001.jpg", &plate),
+        ]);
+        let mut zip = ZipArchive::new(Cursor::new(archive.clone())).unwrap();
+        let ranked: Vec<(String, u8)> = rank_epub_covers(&mut zip, None)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.score))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                ("OEBPS/images/img001.jpg".to_string(), 35),
+                ("OEBPS/images/fig01.jpg".to_string(), 20),
+                ("OEBPS/images/fig02.jpg".to_string(), 20),
+            ]
+        );
+        assert_eq!(
+            extract_epub_cover_bytes(Cursor::new(archive)).unwrap(),
+            plate
+        );
+
+        // A title page wins over a larger cover-shaped image.
+        let title = sample_jpeg(400, 620);
+        let archive = zip_with(&[
+            ("OEBPS/images/img001.jpg", &plate),
+            ("OEBPS/images/titlepage.jpg", &title),
+        ]);
+        assert_eq!(
+            extract_epub_cover_bytes(Cursor::new(archive)).unwrap(),
+            title
+        );
+
+        // Logos alone are no cover at all.
+        let archive = zip_with(&[("OEBPS/images/logo.jpg", &logo)]);
+        assert!(extract_epub_cover_bytes(Cursor::new(archive)).is_err());
+    }
+
+    #[test]
+    fn fb2_lists_every_coverpage_image() {
+        let fb2 = sample_fb2()
+            .replace(
+                r##"<image l:href="#cover.png"/>"##,
+                r##"<image l:href="#cover.png"/><image l:href="#back.png"/>"##,
+            )
+            .replace(
+                "</FictionBook>",
+                &format!(
+                    r#"<binary id="back.png" content-type="image/png">{PIXEL_PNG_B64}</binary></FictionBook>"#
+                ),
+            );
+        let path =
+            std::env::temp_dir().join(format!("readest-candidates-{}.fb2", std::process::id()));
+        std::fs::write(&path, fb2).unwrap();
+
+        let candidates = list_cover_candidates(&path, "fb2").unwrap();
+        let sources: Vec<(&CoverSource, u8)> =
+            candidates.iter().map(|c| (&c.source, c.score)).collect();
+        assert_eq!(
+            sources,
+            [
+                (&CoverSource::Fb2Binary("cover.png".to_string()), 100),
+                (&CoverSource::Fb2Binary("back.png".to_string()), 70)
+            ]
+        );
+        assert!(image::load_from_memory(&candidates[1].preview).is_ok());
+        let back = cover_candidate_bytes(&path, "fb2", &candidates[1].source).unwrap();
+        assert_eq!(
+            back,
+            general_purpose::STANDARD.decode(PIXEL_PNG_B64).unwrap()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cbz_without_comic_info_uses_natural_order() {
+        let archive = zip_with(&[
+            ("__MACOSX/._page1.jpg", b"resource fork"),
+            ("page10.jpg", b"tenth"),
+            ("page2.jpg", b"second"),
+        ]);
+        let cover = extract_cbz_cover_bytes(Cursor::new(archive)).unwrap();
+        assert_eq!(cover, b"second");
+    }
+
+    #[test]
+    fn cbz_ignores_out_of_range_front_cover() {
+        let comic_info =
+            br#"<ComicInfo><Pages><Page Image="9" Type="FrontCover"/></Pages></ComicInfo>"#;
+        let archive = zip_with(&[
+            ("ComicInfo.xml", comic_info),
+            ("001.png", b"first"),
+            ("002.png", b"second"),
+        ]);
+        let cover = extract_cbz_cover_bytes(Cursor::new(archive)).unwrap();
+        assert_eq!(cover, b"first");
+    }
+
+    #[test]
+    fn rtl_manga_takes_the_last_page_as_cover() {
+        let comic_info = br#"<?xml version="1.0"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <Title>Sample Manga</Title>
+  <Manga>YesAndRightToLeft</Manga>
+</ComicInfo>"#;
+        let archive = zip_with(&[
+            ("ComicInfo.xml", comic_info),
+            ("p1.jpg", b"back"),
+            ("p2.jpg", b"story"),
+            ("p10.jpg", b"front"),
+        ]);
+        assert_eq!(
+            cbz_reading_direction(Cursor::new(archive.clone())).unwrap(),
+            ReadingDirection::RightToLeft
+        );
+        let cover = extract_cbz_cover_bytes(Cursor::new(archive.clone())).unwrap();
+        assert_eq!(cover, b"front");
+        // An explicit hint wins over ComicInfo.
+        let cover = extract_cbz_cover_bytes_with_direction(
+            Cursor::new(archive),
+            Some(ReadingDirection::LeftToRight),
+        )
+        .unwrap();
+        assert_eq!(cover, b"back");
+    }
+
+    #[test]
+    fn rtl_hint_keeps_an_explicit_front_cover() {
+        let comic_info = br#"<ComicInfo><Manga>Yes</Manga><Pages>
+    <Page Image="1" Type="FrontCover"/>
+</Pages></ComicInfo>"#;
+        let archive = zip_with(&[
+            ("ComicInfo.xml", comic_info),
+            ("001.png", b"first"),
+            ("002.png", b"marked"),
+            ("003.png", b"last"),
+        ]);
+        assert_eq!(
+            cbz_reading_direction(Cursor::new(archive.clone())).unwrap(),
+            ReadingDirection::LeftToRight
+        );
+        let cover = extract_cbz_cover_bytes_with_direction(
+            Cursor::new(archive),
+            Some(ReadingDirection::RightToLeft),
+        )
+        .unwrap();
+        assert_eq!(cover, b"marked");
+    }
+
+    #[test]
+    fn extract_attribute_skips_longer_tag_names() {
+        let xml = r#"<container><rootfiles>
+<rootfile full-path="OEBPS/content.opf"/>
+</rootfiles></container>"#;
+        assert_eq!(
+            extract_attribute(xml, "rootfile", "full-path").as_deref(),
+            Some("OEBPS/content.opf")
+        );
+        assert_eq!(extract_attribute(xml, "rootfiles", "full-path"), None);
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = vec!["p10.jpg", "p2.jpg", "P1.jpg", "p02b.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["P1.jpg", "p2.jpg", "p02b.jpg", "p10.jpg"]);
+    }
+
+    fn sample_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x % 251) as u8, (y % 241) as u8, ((x * y) % 239) as u8])
+        });
+        let mut out = Vec::new();
+        img.write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+            &mut out, 90,
+        ))
+        .unwrap();
+        out
+    }
+
+    #[test]
+    fn decode_cover_matches_image_crate() {
+        let jpeg = sample_jpeg(97, 131);
+        let reference = image::load_from_memory(&jpeg).unwrap().to_rgb8();
+        let decoded = decode_cover(&jpeg).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), reference.dimensions());
+        let max_diff = decoded
+            .as_raw()
+            .iter()
+            .zip(reference.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(max_diff <= 1, "decoders diverge by {max_diff}");
+    }
+
+    #[test]
+    fn decode_cover_falls_back_for_non_jpeg() {
+        let png = base64::engine::general_purpose::STANDARD
+            .decode(PIXEL_PNG_B64)
+            .unwrap();
+        let img = decode_cover(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (1, 1));
+    }
+
+    /// A minimal v4 matrix/TRC profile with the given D50 colorants and the
+    /// sRGB tone curve, as a `para` tag shared by all three channels.
+    fn matrix_trc_profile(colorants: [[f32; 3]; 3]) -> Vec<u8> {
+        let fixed = |v: f32| ((v * 65536.0).round() as i32).to_be_bytes();
+        let xyz = |[x, y, z]: [f32; 3]| {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for v in [x, y, z] {
+                tag.extend(fixed(v));
+            }
+            tag
+        };
+        let mut trc = b"para\0\0\0\0\0\x03\0\0".to_vec();
+        for v in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.040_45] {
+            trc.extend(fixed(v));
+        }
+        let tags: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"wtpt", xyz([0.9642, 1.0, 0.8249])),
+            (b"rXYZ", xyz(colorants[0])),
+            (b"gXYZ", xyz(colorants[1])),
+            (b"bXYZ", xyz(colorants[2])),
+            (b"rTRC", trc.clone()),
+            (b"gTRC", trc.clone()),
+            (b"bTRC", trc),
+        ];
+
+        let mut header = vec![0u8; 128];
+        header[8..12].copy_from_slice(&[4, 0x30, 0, 0]);
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        for (i, v) in [0.9642, 1.0, 0.8249].into_iter().enumerate() {
+            header[68 + i * 4..72 + i * 4].copy_from_slice(&fixed(v));
+        }
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = Vec::new();
+        let data_start = 128 + 4 + tags.len() * 12;
+        for (signature, tag) in &tags {
+            table.extend(*signature);
+            table.extend(((data_start + data.len()) as u32).to_be_bytes());
+            table.extend((tag.len() as u32).to_be_bytes());
+            data.extend(tag);
+        }
+        let mut icc = [header, table, data].concat();
+        let size = (icc.len() as u32).to_be_bytes();
+        icc[..4].copy_from_slice(&size);
+        icc
+    }
+
+    /// Display P3 colorants, adapted to D50.
+    const DISPLAY_P3: [[f32; 3]; 3] = [
+        [0.5151, 0.2412, -0.0011],
+        [0.2920, 0.6922, 0.0419],
+        [0.1571, 0.0666, 0.7841],
+    ];
+
+    /// sRGB colorants, adapted to D50.
+    const SRGB: [[f32; 3]; 3] = [
+        [0.4361, 0.2225, 0.0139],
+        [0.3851, 0.7169, 0.0971],
+        [0.1431, 0.0606, 0.7141],
+    ];
+
+    fn tagged_png(pixels: &[[u8; 3]], icc: Option<Vec<u8>>) -> Vec<u8> {
+        use image::ImageEncoder;
+
+        let raw = pixels.concat();
+        let mut out = Vec::new();
+        let mut encoder = image::codecs::png::PngEncoder::new(&mut out);
+        if let Some(icc) = icc {
+            encoder.set_icc_profile(icc).unwrap();
+        }
+        encoder
+            .write_image(&raw, pixels.len() as u32, 1, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn display_p3_covers_are_converted_to_srgb() {
+        let pixels = [[0, 200, 0], [128, 128, 128], [180, 90, 40]];
+        let p3 = tagged_png(&pixels, Some(matrix_trc_profile(DISPLAY_P3)));
+        assert!(embedded_icc_profile(&p3).is_some());
+
+        let converted = decode_cover(&p3).unwrap().to_rgb8();
+        let [green, gray, orange] = [0, 1, 2].map(|x| converted.get_pixel(x, 0).0);
+        // P3 green lies outside sRGB: red and blue clip, green brightens.
+        assert_eq!((green[0], green[2]), (0, 0));
+        assert!((202..=206).contains(&green[1]), "{green:?}");
+        // The white point is shared, so neutrals stay put.
+        assert!(gray.iter().all(|v| v.abs_diff(128) <= 1), "{gray:?}");
+        assert!(orange[0] > 180 && orange[2] < 40, "{orange:?}");
+
+        // Untagged and sRGB-tagged covers decode as stored.
+        for icc in [None, Some(matrix_trc_profile(SRGB))] {
+            let png = tagged_png(&pixels, icc);
+            let decoded = decode_cover(&png).unwrap().to_rgb8();
+            assert_eq!(decoded.as_raw(), &pixels.concat());
+        }
+    }
+
+    #[test]
+    fn oversized_images_are_rejected_before_decoding() {
+        let png = |width, height| {
+            let mut out = Vec::new();
+            image::GrayImage::new(width, height)
+                .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+                .unwrap();
+            out
+        };
+        let tall = png(1, DEFAULT_MAX_IMAGE_DIMENSION + 1);
+        let err = decode_cover(&tall).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CoverError>(),
+            Some(CoverError::Corrupt(_))
+        ));
+        assert!(check_image_dimensions(&png(64, 32), 64).is_ok());
+        assert!(check_image_dimensions(&png(64, 65), 64).is_err());
+
+        assert_eq!(parse_max_image_dimension(Some(" 8000 ")), 8000);
+        assert_eq!(
+            parse_max_image_dimension(Some("0")),
+            DEFAULT_MAX_IMAGE_DIMENSION
+        );
+        assert_eq!(parse_max_image_dimension(None), DEFAULT_MAX_IMAGE_DIMENSION);
+    }
+
+    /// Decode throughput; run with
+    /// `cargo test --release [--features mozjpeg] -- --ignored --nocapture decode_throughput`
+    /// and compare the two numbers.
+    #[test]
+    #[ignore]
+    fn decode_throughput() {
+        let jpeg = sample_jpeg(1200, 1800);
+        let rounds = 50;
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            decode_cover(&jpeg).unwrap();
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "decode_cover (mozjpeg: {}): {:.1} covers/s",
+            cfg!(feature = "mozjpeg"),
+            rounds as f64 / elapsed.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn overlay_defaults_off_for_comics_only() {
+        let policy = OverlayPolicy::new();
+        assert!(policy.is_enabled("epub"));
+        assert!(policy.is_enabled(".MOBI"));
+        assert!(!policy.is_enabled("cbz"));
+        assert!(!policy.is_enabled(".CBR"));
+    }
+
+    #[test]
+    fn overlay_overrides_win_over_defaults() {
+        let mut policy = OverlayPolicy::new();
+        policy.set(".cbz", true);
+        policy.set("EPUB", false);
+        assert!(policy.is_enabled("cbz"));
+        assert!(!policy.is_enabled("epub"));
+        assert!(policy.is_enabled("fb2"));
+    }
+
+    #[test]
+    fn format_metrics_accumulate_timings() {
+        let ms = Duration::from_millis;
+        let miss = ThumbnailTiming {
+            ext: "cbz".into(),
+            lookup: ms(1),
+            extract: ms(40),
+            decode: ms(15),
+            encode: ms(5),
+            ..Default::default()
+        };
+        let hit = ThumbnailTiming {
+            ext: "cbz".into(),
+            cache_hit: true,
+            lookup: ms(2),
+            ..Default::default()
+        };
+        let failed = ThumbnailTiming {
+            ext: "cbz".into(),
+            failed: true,
+            extract: ms(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            miss.to_string(),
+            "thumbnail-metrics cbz miss lookup=1ms extract=40ms decode=15ms encode=5ms"
+        );
+        assert!(failed
+            .to_string()
+            .starts_with("thumbnail-metrics cbz miss failed "));
+
+        let mut metrics = FormatMetrics::default();
+        for timing in [&miss, &hit, &failed] {
+            metrics.add(timing);
+        }
+        assert_eq!(
+            metrics,
+            FormatMetrics {
+                requests: 3,
+                cache_hits: 1,
+                cache_misses: 2,
+                failures: 1,
+                lookup: ms(3),
+                extract: ms(43),
+                decode: ms(15),
+                encode: ms(5),
+                slowest: ms(61),
+            }
+        );
+    }
+
+    #[test]
+    fn extraction_concurrency_parses_env_value() {
+        assert_eq!(
+            parse_extraction_concurrency(None),
+            DEFAULT_EXTRACTION_CONCURRENCY
+        );
+        assert_eq!(parse_extraction_concurrency(Some(" 2 ")), 2);
+        assert_eq!(
+            parse_extraction_concurrency(Some("0")),
+            DEFAULT_EXTRACTION_CONCURRENCY
+        );
+        assert_eq!(
+            parse_extraction_concurrency(Some("many")),
+            DEFAULT_EXTRACTION_CONCURRENCY
+        );
+        assert_eq!(
+            parse_extraction_concurrency(Some("1000")),
+            MAX_EXTRACTION_CONCURRENCY
+        );
+        assert!(is_heavy_extraction("cbz"));
+        assert!(!is_heavy_extraction("TXT"));
+    }
+
+    #[test]
+    fn extraction_limiter_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let limiter = ExtractionLimiter::new(2);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = limiter.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn text_samples_decode_by_bom_and_content() {
+        let utf8 = decode_text_sample("\u{feff}Caf\u{e9} cr\u{e8}me".as_bytes(), true);
+        assert_eq!(
+            (utf8.text.as_str(), utf8.encoding),
+            ("Caf\u{e9} cr\u{e8}me", "UTF-8")
+        );
+
+        let words = "Über die Brücke gehen wir heute Abend.";
+        let mut le = vec![0xff, 0xfe];
+        le.extend(words.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_text_sample(&le, true).encoding, "UTF-16LE");
+        assert_eq!(decode_text_sample(&le, true).text, words);
+        let be: Vec<u8> = words.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let sample = decode_text_sample(&be, true);
+        assert_eq!((sample.text.as_str(), sample.encoding), (words, "UTF-16BE"));
+
+        // "Les élèves étudièrent à côté", in Latin-1.
+        let latin1: Vec<u8> =
+            "Les \u{e9}l\u{e8}ves \u{e9}tudi\u{e8}rent \u{e0} c\u{f4}t\u{e9} de la for\u{ea}t."
+                .chars()
+                .map(|c| c as u8)
+                .collect();
+        let sample = decode_text_sample(&latin1, true);
+        assert_eq!(sample.encoding, "windows-1252");
+        assert!(sample.text.contains("élèves"));
+    }
+
+    #[test]
+    fn truncated_text_samples_drop_partial_sequences() {
+        let text = "日本語のテキスト".as_bytes();
+        let cut = &text[..text.len() - 1];
+        let sample = decode_text_sample(cut, false);
+        assert_eq!(sample.encoding, "UTF-8");
+        assert_eq!(sample.text, "日本語のテキス");
+
+        let mut utf16 = vec![0xff, 0xfe];
+        utf16.extend("plain words".encode_utf16().flat_map(u16::to_le_bytes));
+        utf16.pop();
+        assert_eq!(decode_text_sample(&utf16, false).text, "plain word");
+
+        let long = "é".repeat(TXT_SAMPLE_BYTES);
+        let sample = read_txt_sample(long.as_bytes()).unwrap();
+        assert_eq!(sample.text.chars().count(), TXT_SAMPLE_BYTES / 2);
+    }
+
+    #[test]
+    fn verbatim_prefixes_are_stripped() {
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\C:\Program Files\Readest\readest_thumbnail.dll"),
+            r"C:\Program Files\Readest\readest_thumbnail.dll"
+        );
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\UNC\server\share\readest_thumbnail.dll"),
+            r"\\server\share\readest_thumbnail.dll"
+        );
+        assert_eq!(strip_verbatim_prefix(r"D:\x.dll"), r"D:\x.dll");
+        assert_eq!(to_wide("ab"), [u16::from(b'a'), u16::from(b'b'), 0]);
+    }
+
+    #[test]
+    fn unc_paths_are_network_paths() {
+        assert!(is_unc_path(r"\\nas\books\dune.epub"));
+        assert!(is_unc_path("//nas/books/dune.epub"));
+        assert!(is_unc_path(r"\\?\UNC\nas\books\dune.epub"));
+        assert!(!is_unc_path(r"\\?\C:\books\dune.epub"));
+        assert!(!is_unc_path(r"\\.\pipe\books"));
+        assert!(!is_unc_path(r"C:\books\dune.epub"));
+        assert!(!is_unc_path(r"\\?\ü"));
+    }
+
+    #[test]
+    fn transient_io_errors_are_retried() {
+        use std::io::{Error, ErrorKind};
+        let delays = [Duration::ZERO; 3];
+
+        let mut calls = 0;
+        let result = retry_transient(&delays, || {
+            calls += 1;
+            match calls {
+                1 | 2 => Err(Error::from(ErrorKind::ConnectionReset)),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_transient(&delays, || {
+            calls += 1;
+            Err(Error::from(ErrorKind::NotFound))
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_transient(&delays, || {
+            calls += 1;
+            Err(Error::from(ErrorKind::TimedOut))
+        });
+        assert!(is_transient_io(&result.unwrap_err()));
+        assert_eq!(calls, delays.len() + 1);
+
+        // Local books keep the plain I/O error.
+        let missing = std::env::temp_dir().join("readest-missing-book.epub");
+        let err = extract_cover_bytes_by_ext(&missing, "epub").unwrap_err();
+        assert!(err.downcast_ref::<CoverError>().is_none());
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+    }
+
+    #[test]
+    fn batch_concurrency_prefers_env_then_request() {
+        assert_eq!(batch_concurrency(None, None, 8), 8);
+        assert_eq!(batch_concurrency(Some(2), None, 8), 2);
+        assert_eq!(batch_concurrency(Some(2), Some(" 3 "), 8), 3);
+        assert_eq!(batch_concurrency(Some(2), Some("fast"), 8), 2);
+        assert_eq!(batch_concurrency(Some(0), Some("0"), 8), 8);
+        assert_eq!(batch_concurrency(Some(500), None, 8), MAX_BATCH_CONCURRENCY);
+        assert_eq!(batch_concurrency(None, None, 0), 1);
+    }
+
+    #[test]
+    fn batch_keeps_order_and_reports_failures() {
+        let dir = std::env::temp_dir().join(format!("readest-batch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<PathBuf> = ["a.txt", "b.bin", "c.txt", "missing.epub"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        std::fs::write(&paths[0], "Alpha").unwrap();
+        std::fs::write(&paths[1], "not a book").unwrap();
+        std::fs::write(&paths[2], "Gamma").unwrap();
+
+        let limiter = ExtractionLimiter::new(2);
+        let options = BatchOptions {
+            size: 64,
+            concurrency: Some(3),
+            priority: BatchPriority::Background,
+            ..Default::default()
+        };
+        let results =
+            generate_thumbnails_batch(&paths, &options, &OverlayPolicy::default(), &limiter);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        assert!(results[3].is_err());
+        assert_eq!(limiter.permits(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn power_status_reads_sysfs_supplies() {
+        let root = std::env::temp_dir().join(format!("readest-power-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            let dir = root.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            for (file, value) in files {
+                std::fs::write(dir.join(file), format!("{value}\n")).unwrap();
+            }
+        };
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "20"),
+            ],
+        );
+        supply(
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("status", "Unknown"),
+                ("capacity", "40"),
+            ],
+        );
+        supply(
+            "hidpp_battery_0",
+            &[
+                ("type", "Battery"),
+                ("scope", "Device"),
+                ("capacity", "100"),
+            ],
+        );
+        let status = sysfs_power_status(&root);
+        assert_eq!(
+            status,
+            PowerStatus {
+                on_battery: true,
+                percent: Some(30),
+            }
+        );
+        assert_eq!(power_throttle(status), PowerThrottle::Reduced);
+        let low = PowerStatus {
+            percent: Some(LOW_BATTERY_PERCENT - 1),
+            ..status
+        };
+        assert_eq!(power_throttle(low), PowerThrottle::Paused);
+
+        std::fs::write(root.join("AC/online"), "1\n").unwrap();
+        let status = sysfs_power_status(&root);
+        assert!(!status.on_battery);
+        assert_eq!(power_throttle(status), PowerThrottle::Full);
+
+        // A desktop: no battery at all.
+        let _ = std::fs::remove_dir_all(&root);
+        supply("AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(sysfs_power_status(&root), PowerStatus::default());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn cache_migration_moves_and_dedups_entries() {
+        let root =
+            std::env::temp_dir().join(format!("readest-cache-migrate-{}", std::process::id()));
+        let (from, to) = (root.join("beta"), root.join("shared"));
+        std::fs::create_dir_all(&from).unwrap();
+        std::fs::create_dir_all(&to).unwrap();
+
+        // Entries of the current key version, and of another one.
+        let ours = |n: u32| format!("v{CACHE_KEY_VERSION}-{n:032x}.thumb");
+        let other = |n: u32| format!("v{}-{n:032x}.thumb", CACHE_KEY_VERSION + 1);
+
+        let thumb = encode_thumbnail(&solid_cover(255), DEFAULT_THUMBNAIL_QUALITY).unwrap();
+        std::fs::write(from.join(ours(0xa)), &thumb).unwrap();
+        std::fs::write(from.join(other(0xb)), &thumb).unwrap();
+        std::fs::write(from.join(ours(0xc)), &thumb).unwrap();
+        std::fs::write(to.join(ours(0xc)), &thumb).unwrap();
+        std::fs::write(from.join(ours(0xd)), b"truncated").unwrap();
+        std::fs::write(from.join("notes.txt"), b"not a cache entry").unwrap();
+
+        let report = migrate_cache(&from, &to).unwrap();
+        assert_eq!(
+            report,
+            MigrationReport {
+                moved: 2,
+                duplicates: 1,
+                skipped: 1
+            }
+        );
+        assert!(to.join(ours(0xa)).exists() && to.join(other(0xb)).exists());
+        assert!(!from.join(ours(0xc)).exists() && from.join(ours(0xd)).exists());
+        assert!(from.join("notes.txt").exists() && !to.join("notes.txt").exists());
+        assert!(migrate_cache(&to, &to).is_err());
+
+        // Entries of another key version are left for the build that wrote them.
+        std::fs::write(to.join(other(0xe)), b"not ours").unwrap();
+        let report = scan_cache_dir(&to, false).unwrap();
+        assert_eq!(
+            report,
+            CacheReport {
+                checked: 2,
+                removed: 0
+            }
+        );
+        assert_eq!(cache_key_version("v2-0f.thumb"), Some(2));
+        assert_eq!(cache_key_version("good.thumb"), None);
+        assert!(is_cache_entry_name(&ours(0xa)));
+        assert!(is_cache_entry_name(&format!("v12-{:032x}.url", 7)));
+        for name in [
+            "good.thumb",
+            "v4-0f.thumb",
+            "v4-notes.url",
+            "vx-00.thumb",
+            &format!("v4-{:032X}.thumb", 0xabc),
+        ] {
+            assert!(!is_cache_entry_name(name), "{name}");
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn partial_epub_yields_cover_once_it_has_arrived() {
+        let mut cover = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut cover), image::ImageFormat::Png)
+            .unwrap();
+        let container = br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+        let opf = br#"<package><metadata><meta name="cover" content="c"/></metadata><manifest><item id="c" href="images/front.png" media-type="image/png"/></manifest></package>"#;
+        let chapter: Vec<u8> = (0..200_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let epub = zip_with(&[
+            ("mimetype", b"application/epub+zip"),
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/images/front.png", &cover),
+            ("OEBPS/chapter1.xhtml", &chapter),
+        ]);
+
+        let partial = &epub[..epub.len() / 2];
+        let entries = local_zip_entries(partial);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(partial_epub_cover(&entries), Some(cover.clone()));
+
+        let before_cover = &epub[..120];
+        assert_eq!(partial_epub_cover(&local_zip_entries(before_cover)), None);
+        // The whole file reads the same way, stopping at the central directory.
+        assert_eq!(local_zip_entries(&epub).len(), 5);
+    }
+
+    #[test]
+    fn partial_entries_stop_inflating_at_the_cap() {
+        let deflate = |len: u64| {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+            std::io::copy(&mut std::io::repeat(0).take(len), &mut encoder).unwrap();
+            encoder.finish().unwrap()
+        };
+        let fits = deflate(MAX_PARTIAL_ENTRY);
+        let bomb = deflate(MAX_PARTIAL_ENTRY + 1);
+        let entry = |data| LocalEntry {
+            name: "OEBPS/chapter1.xhtml".into(),
+            method: 8,
+            data,
+        };
+        assert_eq!(
+            entry(&fits).contents().map(|out| out.len() as u64),
+            Some(MAX_PARTIAL_ENTRY)
+        );
+        assert_eq!(entry(&bomb).contents(), None);
+    }
+
+    #[test]
+    fn epub_variants_route_to_the_epub_extractor() {
+        assert_eq!(
+            book_extension(Path::new("/b/Dune.KEPUB.epub")),
+            Some("kepub.epub".to_string())
+        );
+        assert_eq!(
+            book_extension(Path::new("Dune.epub3")),
+            Some("epub3".to_string())
+        );
+        assert_eq!(
+            book_extension(Path::new("kepub.epub")),
+            Some("epub".to_string())
+        );
+        assert_eq!(book_extension(Path::new("noext")), None);
+
+        let mut cover = Vec::new();
+        solid_cover(255)
+            .write_to(&mut Cursor::new(&mut cover), image::ImageFormat::Png)
+            .unwrap();
+        let container =
+            br#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#;
+        let opf = br#"<package><manifest><item id="c" href="cover.png" media-type="image/png" properties="cover-image"/></manifest></package>"#;
+        let epub = zip_with(&[
+            ("META-INF/container.xml", container),
+            ("content.opf", opf),
+            ("cover.png", &cover),
+        ]);
+        let dir = std::env::temp_dir().join(format!("readest-kepub-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.kepub.epub", "b.kepub", "c.epub3"] {
+            let path = dir.join(name);
+            std::fs::write(&path, &epub).unwrap();
+            let ext = book_extension(&path).unwrap();
+            assert_eq!(extract_cover_bytes_by_ext(&path, &ext).unwrap(), cover);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cover_sidecar_wins_and_changes_the_cache_key() {
+        let dir = std::env::temp_dir().join(format!("readest-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let book = dir.join("book.txt");
+        std::fs::write(&book, b"plain text book").unwrap();
+        let before = cache_digest(&book, "txt", 40, &[]).unwrap();
+
+        let cover = sample_jpeg(20, 30);
+        std::fs::write(dir.join("book.txt.readest-cover.jpg"), &cover).unwrap();
+        assert_eq!(extract_cover_bytes_by_ext(&book, "txt").unwrap(), cover);
+        assert_ne!(cache_digest(&book, "txt", 40, &[]).unwrap(), before);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partial_downloads_are_recognized_by_suffix() {
+        assert_eq!(
+            partial_download_ext(Path::new("Dune.EPUB.part")),
+            Some("epub".to_string())
+        );
+        assert_eq!(
+            partial_download_ext(Path::new("/tmp/a.b.cbz.crdownload")),
+            Some("cbz".to_string())
+        );
+        assert_eq!(partial_download_ext(Path::new("Dune.epub")), None);
+        assert_eq!(partial_download_ext(Path::new("noext.part")), None);
+
+        let tile = downloading_placeholder(64, 64);
+        assert_ne!(
+            tile.get_pixel(32, 32),
+            placeholder_image(64, 64).get_pixel(32, 32)
+        );
+    }
+
+    fn tar_with(entries: &[(&str, &[u8])], gzip: bool) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            // Written raw: `set_path` refuses the `..` names tested below.
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        let tar = builder.into_inner().unwrap();
+        if !gzip {
+            return tar;
+        }
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&tar).unwrap();
+        gz.finish().unwrap()
+    }
+
+    #[test]
+    fn tar_yields_the_cover_of_its_single_book() {
+        let pixel = general_purpose::STANDARD.decode(PIXEL_PNG_B64).unwrap();
+        let fb2 = sample_fb2();
+        let tgz = tar_with(
+            &[
+                ("Book/README", b"read me"),
+                ("Book/Sample.fb2", fb2.as_bytes()),
+            ],
+            true,
+        );
+        assert_eq!(extract_tar_cover_bytes(Cursor::new(&tgz)).unwrap(), pixel);
+
+        // A tar inside a tar.gz, as some bundles ship.
+        let inner = tar_with(&[("Sample.fb2", fb2.as_bytes())], false);
+        let outer = tar_with(&[("bundle/Sample.tar", &inner)], true);
+        assert_eq!(extract_tar_cover_bytes(Cursor::new(&outer)).unwrap(), pixel);
+
+        let two = tar_with(
+            &[("a.fb2", fb2.as_bytes()), ("b.fb2", fb2.as_bytes())],
+            false,
+        );
+        assert!(extract_tar_cover_bytes(Cursor::new(&two)).is_err());
+    }
+
+    #[test]
+    fn tar_of_images_reads_as_a_comic() {
+        let page = |shade: u8| {
+            let mut png = Vec::new();
+            solid_cover(shade)
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            png
+        };
+        let (p1, p2, p10) = (page(10), page(20), page(30));
+        let tar = tar_with(
+            &[
+                ("comic/page10.png", &p10),
+                ("../evil/page0.png", b"outside"),
+                ("/abs/page0.png", b"absolute"),
+                ("comic/._page0.png", b"resource fork"),
+                ("comic/page2.png", &p2),
+                ("comic/page1.png", &p1),
+            ],
+            false,
+        );
+        assert_eq!(extract_tar_cover_bytes(Cursor::new(&tar)).unwrap(), p1);
+
+        let empty = tar_with(&[("notes.txt", b"no pictures")], true);
+        assert!(extract_tar_cover_bytes(Cursor::new(&empty)).is_err());
+    }
+
+    #[test]
+    fn tar_extensions_are_detected() {
+        assert_eq!(
+            book_extension(Path::new("/b/Dune.TAR.GZ")),
+            Some("tar.gz".to_string())
+        );
+        assert_eq!(
+            book_extension(Path::new("Dune.tgz")),
+            Some("tgz".to_string())
+        );
+        assert_eq!(book_extension(Path::new("tar.gz")), Some("gz".to_string()));
+        assert_eq!(
+            tar_member_format("a/dune.kepub.epub").as_deref(),
+            Some("epub")
+        );
+        assert_eq!(
+            tar_member_format("a/dune.fb2.zip").as_deref(),
+            Some("fb2.zip")
+        );
+        assert_eq!(tar_member_format("a/notes.txt"), None);
+        assert_eq!(
+            tar_member_name(Path::new("./a/B.png")).as_deref(),
+            Some("a/b.png")
+        );
+        assert_eq!(tar_member_name(Path::new("a/../../b.png")), None);
+    }
+
+    fn push_encint(out: &mut Vec<u8>, value: u64) {
+        let mut groups = vec![(value & 0x7f) as u8];
+        let mut rest = value >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        out.extend(groups.iter().rev());
+    }
+
+    /// A minimal LIT book: header, piece table, secondary header with the
+    /// content offset, one `AOLL` directory chunk and the entries' data.
+    fn lit_with(entries: &[(&str, u64, &[u8])]) -> Vec<u8> {
+        const CHUNK_LEN: usize = 512;
+        let put = |buf: &mut [u8], at: usize, value: u32| {
+            buf[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        };
+
+        let (mut listing, mut content) = (Vec::new(), Vec::new());
+        for (name, section, data) in entries {
+            push_encint(&mut listing, name.len() as u64);
+            listing.extend_from_slice(name.as_bytes());
+            push_encint(&mut listing, *section);
+            push_encint(&mut listing, content.len() as u64);
+            push_encint(&mut listing, data.len() as u64);
+            content.extend_from_slice(data);
+        }
+        let mut chunk = vec![0u8; CHUNK_LEN];
+        chunk[..4].copy_from_slice(b"AOLL");
+        put(&mut chunk, 4, (CHUNK_LEN - 48 - listing.len()) as u32);
+        chunk[48..48 + listing.len()].copy_from_slice(&listing);
+        chunk[CHUNK_LEN - 2..].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut dir = vec![0u8; 32];
+        dir[..4].copy_from_slice(b"IFCM");
+        put(&mut dir, 8, CHUNK_LEN as u32);
+        put(&mut dir, 24, 1);
+        dir.extend(chunk);
+
+        let (header_len, pieces, secondary_len) = (40usize, 5usize, 104usize);
+        let dir_offset = header_len + pieces * 16 + secondary_len;
+        let content_offset = dir_offset + dir.len();
+        let mut lit = vec![0u8; dir_offset];
+        lit[..8].copy_from_slice(LIT_MAGIC);
+        put(&mut lit, 8, 1);
+        put(&mut lit, 12, header_len as u32);
+        put(&mut lit, 16, pieces as u32);
+        put(&mut lit, 20, secondary_len as u32);
+        put(&mut lit, header_len + 16, dir_offset as u32);
+        put(&mut lit, header_len + 24, dir.len() as u32);
+        let secondary = header_len + pieces * 16;
+        put(&mut lit, secondary + 4, 8);
+        lit[secondary + 8..secondary + 12].copy_from_slice(b"CAOL");
+        put(&mut lit, secondary + 12, 2);
+        lit[secondary + 56..secondary + 60].copy_from_slice(b"ITSF");
+        put(&mut lit, secondary + 60, 4);
+        put(&mut lit, secondary + 72, content_offset as u32);
+        lit.extend(dir);
+        lit.extend(content);
+        lit
+    }
+
+    #[test]
+    fn lit_is_recognized_by_its_magic() {
+        let lit = lit_with(&[("/data/toc/content", 1, b"compressed text")]);
+        assert!(is_lit(&lit));
+        assert!(!is_lit(b"BOOKMOBI"));
+        assert_eq!(
+            book_extension(Path::new("/b/Dune.LIT")),
+            Some("lit".to_string())
+        );
+        // Recognized, but nothing to show: a clear reason, not a bare error.
+        let err = extract_lit_cover_bytes(Cursor::new(&lit)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CoverError>(),
+            Some(CoverError::Unsupported(_))
+        ));
+        let err = extract_lit_cover_bytes(Cursor::new(b"PK\x03\x04 not a lit book")).unwrap_err();
+        assert!(err.downcast_ref::<CoverError>().is_none());
+    }
+
+    #[test]
+    fn lit_cover_is_an_uncompressed_image() {
+        let png = |shade: u8| {
+            let mut png = Vec::new();
+            solid_cover(shade)
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            png
+        };
+        let (page, cover) = (png(10), png(20));
+        let text = [7u8; 300];
+        let lit = lit_with(&[
+            ("/data/toc/content", 1, &text),
+            ("/data/illustration", 0, &page),
+            ("/data/CoverImage", 0, &cover),
+            ("/data/styles", 0, b"p { margin: 0 } body { font: serif }"),
+        ]);
+        assert_eq!(extract_lit_cover_bytes(Cursor::new(&lit)).unwrap(), cover);
+
+        let lit = lit_with(&[("/data/toc/content", 1, &text), ("/data/plate", 0, &page)]);
+        assert_eq!(extract_lit_cover_bytes(Cursor::new(&lit)).unwrap(), page);
+
+        let locked = lit_with(&[
+            ("/DRMStorage/Licenses/EUL", 0, b"license"),
+            ("/data/cover", 0, &cover),
+        ]);
+        let err = extract_lit_cover_bytes(Cursor::new(&locked)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CoverError>(),
+            Some(CoverError::DrmProtected(_))
+        ));
+    }
 }
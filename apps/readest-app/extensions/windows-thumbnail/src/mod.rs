@@ -3,10 +3,14 @@
 //! This module provides Windows Explorer thumbnail support for eBook files.
 //! Thumbnails are only shown when Readest is set as the default application.
 //!
-//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, CBZ, CBR
+//! Supported formats: EPUB, MOBI, AZW, AZW3, KF8, FB2, FBZ, CBZ, CBR
+//!
+//! The app links this crate without the `com-provider` feature for its own
+//! cover commands, so both share extraction, rendering and the disk cache.
 
 #![allow(non_snake_case)]
 
+#[cfg(all(windows, feature = "com-provider"))]
 mod com_provider;
 mod extraction;
 
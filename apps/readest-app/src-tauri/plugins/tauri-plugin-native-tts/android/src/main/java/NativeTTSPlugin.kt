@@ -77,6 +77,11 @@ class SetVoiceArgs(
     val voice: String? = null
 )
 
+@InvokeArg
+class WarmupArgs(
+    val voice: String? = null
+)
+
 @InvokeArg
 class UpdateMediaSessionMetadataArgs {
   var title: String? = null
@@ -411,11 +416,7 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
                 if (!isInitialized.get()) {
                     initializeTTS()
                 }
-                val voices = textToSpeech?.voices
-                val targetVoice = voices?.find { voice ->
-                    val languageTag = voice.locale.toLanguageTag()
-                    voice.name == args.voice || (languageTag.contains(voice.name) && languageTag == args.voice)
-                }
+                val targetVoice = findVoice(args.voice)
 
                 if (targetVoice != null) {
                     val result = textToSpeech?.setVoice(targetVoice)
@@ -433,6 +434,46 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
         }
     }
     
+    private fun findVoice(id: String?): Voice? {
+        return textToSpeech?.voices?.find { voice ->
+            val languageTag = voice.locale.toLanguageTag()
+            voice.name == id || (languageTag.contains(voice.name) && languageTag == id)
+        }
+    }
+
+    @Command
+    fun warmup(invoke: Invoke) {
+        val args = invoke.parseArgs(WarmupArgs::class.java)
+        coroutineScope.launch {
+            try {
+                val ready = isInitialized.get() || initializeTTS()
+                if (!ready) {
+                    invoke.resolve(JSObject().apply { put("success", false) })
+                    return@launch
+                }
+                args.voice?.let { id -> findVoice(id)?.let { textToSpeech?.setVoice(it) } }
+
+                // Synthesizing to a throwaway file loads the engine and the
+                // voice data without playing anything, so the first sentence
+                // the reader speaks starts without the cold-start delay.
+                val file = java.io.File(activity.cacheDir, "tts-warmup.wav")
+                val result = withContext(Dispatchers.Main) {
+                    textToSpeech?.synthesizeToFile(
+                        "a",
+                        Bundle(),
+                        file,
+                        "warmup-${UUID.randomUUID()}"
+                    )
+                }
+                invoke.resolve(JSObject().apply {
+                    put("success", result == TextToSpeech.SUCCESS)
+                })
+            } catch (e: Exception) {
+                invoke.reject("Exception warming up TTS: ${e.message}")
+            }
+        }
+    }
+
     @Command
     fun get_all_voices(invoke: Invoke) {
         coroutineScope.launch {
@@ -453,6 +494,7 @@ class NativeTTSPlugin(private val activity: Activity) : Plugin(activity) {
                         put("name", name)
                         put("lang", language)
                         put("disabled", false)
+                        put("network", voice.isNetworkConnectionRequired)
                     }
                 } ?: emptyList()
 
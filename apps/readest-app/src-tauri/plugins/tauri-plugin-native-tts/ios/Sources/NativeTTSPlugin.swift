@@ -22,6 +22,10 @@ class SetVoiceArgs: Decodable {
   let voice: String?
 }
 
+class WarmupArgs: Decodable {
+  let voice: String?
+}
+
 class UpdateMediaSessionMetadataArgs: Decodable {
   let title: String?
   let artist: String?
@@ -59,6 +63,12 @@ struct VoiceData: Encodable {
   let name: String
   let lang: String
   let disabled: Bool
+  let gender: String?
+  let network: Bool
+}
+
+struct WarmupResponse: Encodable {
+  let success: Bool
 }
 
 struct GetVoicesResponse: Encodable {
@@ -71,6 +81,8 @@ struct GetVoicesResponse: Encodable {
 /// `tts_events` channel contract.
 class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
   private let synthesizer = AVSpeechSynthesizer()
+  // Renders warmup utterances to buffers, never to the speaker.
+  private let warmupSynthesizer = AVSpeechSynthesizer()
 
   // App-level controls. `rate` arrives pre-curved by the JS client (see
   // `avRate(from:)`); `pitch` is a direct multiplier (1.0 == normal).
@@ -222,12 +234,42 @@ class NativeTTSPlugin: Plugin, AVSpeechSynthesizerDelegate {
         id: voice.identifier,
         name: name,
         lang: voice.language,
-        disabled: false
+        disabled: false,
+        gender: self.genderName(voice.gender),
+        // System voices, including downloaded enhanced ones, run on-device.
+        network: false
       )
     }
     invoke.resolve(GetVoicesResponse(voices: voices))
   }
 
+  /// Loads the voice by synthesizing a short utterance into buffers that are
+  /// discarded, so the first spoken sentence doesn't wait for the voice data.
+  @objc public func warmup(_ invoke: Invoke) {
+    do {
+      let args = try invoke.parseArgs(WarmupArgs.self)
+      let voiceId = args.voice ?? currentVoiceId
+      DispatchQueue.main.async {
+        let utterance = AVSpeechUtterance(string: "a")
+        if !voiceId.isEmpty, let voice = AVSpeechSynthesisVoice(identifier: voiceId) {
+          utterance.voice = voice
+        }
+        self.warmupSynthesizer.write(utterance) { _ in }
+        invoke.resolve(WarmupResponse(success: true))
+      }
+    } catch {
+      invoke.reject("Exception warming up TTS: \(error.localizedDescription)")
+    }
+  }
+
+  private func genderName(_ gender: AVSpeechSynthesisVoiceGender) -> String? {
+    switch gender {
+    case .male: return "male"
+    case .female: return "female"
+    default: return nil
+    }
+  }
+
   // MARK: - AVSpeechSynthesizerDelegate
 
   func speechSynthesizer(
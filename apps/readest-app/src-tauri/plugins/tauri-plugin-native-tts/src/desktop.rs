@@ -41,6 +41,9 @@ impl<R: Runtime> NativeTts<R> {
     pub fn get_all_voices(&self) -> crate::Result<GetVoicesResponse> {
         Err(crate::Error::UnsupportedPlatformError)
     }
+    pub fn warmup(&self, _args: WarmupArgs) -> crate::Result<WarmupResponse> {
+        Err(crate::Error::UnsupportedPlatformError)
+    }
     pub fn set_media_session_active(
         &self,
         _payload: SetMediaSessionActiveRequest,
@@ -88,6 +88,14 @@ impl<R: Runtime> NativeTts<R> {
     }
 }
 
+impl<R: Runtime> NativeTts<R> {
+    pub fn warmup(&self, payload: WarmupArgs) -> crate::Result<WarmupResponse> {
+        self.0
+            .run_mobile_plugin("warmup", payload)
+            .map_err(Into::into)
+    }
+}
+
 impl<R: Runtime> NativeTts<R> {
     pub fn set_media_session_active(
         &self,
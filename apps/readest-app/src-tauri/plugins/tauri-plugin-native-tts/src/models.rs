@@ -8,6 +8,12 @@ pub struct TTSVoice {
     pub lang: String,
     #[serde(default)]
     pub disabled: bool,
+    /// `"male"` or `"female"` when the platform reports it.
+    #[serde(default)]
+    pub gender: Option<String>,
+    /// Whether the voice synthesizes on a server rather than on the device.
+    #[serde(default)]
+    pub network: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +62,18 @@ pub struct SetVoiceArgs {
     pub voice: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupArgs {
+    pub voice: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupResponse {
+    pub success: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetVoicesResponse {
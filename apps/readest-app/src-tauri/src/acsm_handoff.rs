@@ -0,0 +1,155 @@
+//! `.acsm` files opened with Readest.
+//!
+//! An `.acsm` is an Adobe fulfillment ticket, not a book: the encrypted EPUB
+//! or PDF only exists after Adobe Digital Editions (or Thorium) redeems it
+//! against the store. Instead of failing the import, every open-files path
+//! (argv at launch, a second instance, macOS `Opened`, File ▸ Open) pulls
+//! these out and passes them to the system's `.acsm` handler, then emits
+//! [`ACSM_HANDOFF_EVENT`] so the UI can explain where the book went. With no
+//! handler installed the user gets a native message box instead, which also
+//! covers a cold launch where the webview isn't listening yet.
+
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::default_reader;
+
+pub const ACSM_HANDOFF_EVENT: &str = "acsm-handoff";
+
+const ACSM_EXTENSION: &str = "acsm";
+
+const NO_HANDLER_TITLE: &str = "Adobe Digital Editions required";
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcsmHandoffPayload {
+    pub path: String,
+    /// The app the file went to, as the platform names it (executable path,
+    /// bundle id or `.desktop` entry).
+    pub handler: Option<String>,
+    pub opened: bool,
+    pub error: Option<String>,
+}
+
+pub fn is_acsm(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(ACSM_EXTENSION))
+}
+
+/// Split `files` into `(acsm, rest)`, keeping the order of each.
+pub fn partition(files: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    files.into_iter().partition(|path| is_acsm(path))
+}
+
+/// Drop `.acsm` paths (plain or `file://`) from a second instance's argv
+/// before it's forwarded to the webview, keeping the executable at `argv[0]`.
+pub fn strip_args(argv: Vec<String>) -> Vec<String> {
+    argv.into_iter()
+        .enumerate()
+        .filter(|(i, arg)| *i == 0 || arg.starts_with('-') || !is_acsm(Path::new(arg)))
+        .map(|(_, arg)| arg)
+        .collect()
+}
+
+fn no_handler_message(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+    format!(
+        "\"{name}\" is an Adobe download ticket, not a book. Open it with Adobe Digital \
+         Editions or Thorium Reader to download the book, then import the downloaded \
+         EPUB or PDF into Readest."
+    )
+}
+
+fn hand_off_one(app: &AppHandle, path: &Path) -> AcsmHandoffPayload {
+    let handler = default_reader::default_handler(ACSM_EXTENSION);
+    let result = match handler {
+        Some(_) => app
+            .opener()
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display())),
+        None => Err(no_handler_message(path)),
+    };
+    if let Err(e) = &result {
+        log::warn!("acsm hand-off failed: {e}");
+    }
+    AcsmHandoffPayload {
+        path: path.to_string_lossy().into_owned(),
+        handler,
+        opened: result.is_ok(),
+        error: result.err(),
+    }
+}
+
+/// Pass each `.acsm` to the system handler and report it to the UI. Failures
+/// are also shown as a native message box, once per batch.
+pub fn hand_off(app: &AppHandle, files: Vec<PathBuf>) {
+    let mut failures = Vec::new();
+    for path in &files {
+        let payload = hand_off_one(app, path);
+        if let Some(error) = &payload.error {
+            failures.push(error.clone());
+        }
+        let _ = app.emit(ACSM_HANDOFF_EVENT, payload);
+    }
+    if !failures.is_empty() {
+        app.dialog()
+            .message(failures.join("\n\n"))
+            .title(NO_HANDLER_TITLE)
+            .kind(MessageDialogKind::Warning)
+            .show(|_| {});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_acsm_by_extension() {
+        assert!(is_acsm(Path::new("/books/URLLink.acsm")));
+        assert!(is_acsm(Path::new("C:\\Downloads\\ticket.ACSM")));
+        assert!(!is_acsm(Path::new("/books/acsm.epub")));
+        assert!(!is_acsm(Path::new("/books/acsm")));
+    }
+
+    #[test]
+    fn partition_keeps_books_in_order() {
+        let files = vec![
+            PathBuf::from("a.epub"),
+            PathBuf::from("b.acsm"),
+            PathBuf::from("c.pdf"),
+            PathBuf::from("d.Acsm"),
+        ];
+        let (acsm, rest) = partition(files);
+        assert_eq!(acsm, vec![PathBuf::from("b.acsm"), PathBuf::from("d.Acsm")]);
+        assert_eq!(rest, vec![PathBuf::from("a.epub"), PathBuf::from("c.pdf")]);
+    }
+
+    #[test]
+    fn strip_args_drops_tickets_only() {
+        let argv = vec![
+            "/usr/bin/readest".to_string(),
+            "--flag".to_string(),
+            "/books/a.epub".to_string(),
+            "file:///books/b.acsm".to_string(),
+            "/books/c.ACSM".to_string(),
+        ];
+        assert_eq!(
+            strip_args(argv),
+            vec!["/usr/bin/readest", "--flag", "/books/a.epub"]
+        );
+    }
+
+    #[test]
+    fn no_handler_message_names_the_file() {
+        let message = no_handler_message(Path::new("/tmp/URLLink.acsm"));
+        assert!(message.contains("\"URLLink.acsm\""));
+        assert!(message.contains("Adobe Digital Editions"));
+    }
+}
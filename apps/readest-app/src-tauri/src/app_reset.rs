@@ -0,0 +1,401 @@
+//! "Reset to factory": remove the app state support most often asks users
+//! to clear, in one place.
+//!
+//! [`reset_app_data`] takes the scopes to clear and removes, best effort:
+//!
+//!   - `thumbnailCache`: the entries of the Explorer thumbnail provider's
+//!     cache (`READEST_THUMBNAIL_CACHE_DIR`, the portable
+//!     `data/cache/thumbnails`, or `%LOCALAPPDATA%\Readest\cache\thumbnails`).
+//!     That folder may be shared, so other files in it are kept; Windows only;
+//!   - `windowState`: the window-state plugin's `.window-state.json`, and the
+//!     saved always-on-top choice. The plugin writes its file again on exit,
+//!     so that one is removed once more then;
+//!   - `recentFiles`: Readest's own recent-files list, and the system's recent
+//!     documents for Readest (the Jump List on Windows, Open Recent on
+//!     macOS). Linux keeps one shared list for all apps, which is left alone;
+//!   - `logs`: the files in the log folder. The one being written may be
+//!     locked and is then reported as a failure;
+//!   - `all`: every scope above.
+//!
+//! A failure doesn't stop the rest; the report lists what was removed and
+//! what couldn't be. Books are never touched: any target that is, contains
+//! or sits inside a library folder (the default one, the custom root, and
+//! the `localBooksDir` from settings) is refused. Settings themselves are
+//! left for the frontend, which owns them.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+use crate::portable;
+use crate::recent_files;
+use crate::window_on_top;
+use crate::window_state::STATE_FILENAME;
+
+/// Same variable the thumbnail provider reads (`CACHE_DIR_ENV` there).
+const THUMBNAIL_CACHE_DIR_ENV: &str = "READEST_THUMBNAIL_CACHE_DIR";
+/// Folder under the app data dir (or custom root) holding the library, as
+/// `DATA_SUBDIR` in the frontend's constants.
+const DATA_SUBDIR: &str = "Readest";
+
+/// Set once the window state is reset, so [`cleanup`] removes what the
+/// window-state plugin saves on exit.
+static WINDOW_STATE_RESET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResetScope {
+    ThumbnailCache,
+    WindowState,
+    RecentFiles,
+    Logs,
+    All,
+}
+
+impl ResetScope {
+    const EACH: [ResetScope; 4] = [
+        ResetScope::ThumbnailCache,
+        ResetScope::WindowState,
+        ResetScope::RecentFiles,
+        ResetScope::Logs,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedItem {
+    pub scope: ResetScope,
+    /// A path, or the name of a system list for `recentFiles`.
+    pub item: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetFailure {
+    pub scope: ResetScope,
+    pub item: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetReport {
+    pub removed: Vec<RemovedItem>,
+    pub failed: Vec<ResetFailure>,
+}
+
+impl ResetReport {
+    fn removed(&mut self, scope: ResetScope, item: impl Into<String>) {
+        self.removed.push(RemovedItem {
+            scope,
+            item: item.into(),
+        });
+    }
+
+    fn failed(&mut self, scope: ResetScope, item: impl Into<String>, error: impl Into<String>) {
+        self.failed.push(ResetFailure {
+            scope,
+            item: item.into(),
+            error: error.into(),
+        });
+    }
+
+    /// Remove the file at `path`, if there is one.
+    fn remove_file(&mut self, scope: ResetScope, path: &Path, books: &[PathBuf]) {
+        if !path.exists() {
+            return;
+        }
+        let item = path.to_string_lossy().into_owned();
+        if touches_books(path, books) {
+            return self.failed(scope, item, "refused: overlaps the library folder");
+        }
+        match fs::remove_file(path) {
+            Ok(()) => self.removed(scope, item),
+            Err(e) => self.failed(scope, item, e.to_string()),
+        }
+    }
+
+    /// Remove the thumbnail cache entries directly inside `dir`, leaving
+    /// anything not named like one.
+    fn clear_cache_entries(&mut self, scope: ResetScope, dir: &Path, books: &[PathBuf]) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let is_cache_entry = entry.file_type().is_ok_and(|kind| kind.is_file())
+                && entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(windows_thumbnail::is_cache_entry_name);
+            if is_cache_entry {
+                self.remove_file(scope, &entry.path(), books);
+            }
+        }
+    }
+
+    /// Remove everything inside `dir`, keeping the folder itself so whoever
+    /// writes there next doesn't have to recreate it.
+    fn clear_dir(&mut self, scope: ResetScope, dir: &Path, books: &[PathBuf]) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        if touches_books(dir, books) {
+            let item = dir.to_string_lossy().into_owned();
+            return self.failed(scope, item, "refused: overlaps the library folder");
+        }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let item = path.to_string_lossy().into_owned();
+            let result = match entry.file_type() {
+                Ok(kind) if kind.is_dir() => fs::remove_dir_all(&path),
+                Ok(_) => fs::remove_file(&path),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => self.removed(scope, item),
+                Err(e) => self.failed(scope, item, e.to_string()),
+            }
+        }
+    }
+}
+
+/// Whether removing `target` could reach a book: it is one of the library
+/// folders, one of their ancestors, or inside one.
+fn touches_books(target: &Path, books: &[PathBuf]) -> bool {
+    books
+        .iter()
+        .any(|dir| dir.starts_with(target) || target.starts_with(dir))
+}
+
+/// Every folder books may live in.
+fn library_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = app.path().app_data_dir() {
+        dirs.push(dir.join(DATA_SUBDIR));
+    }
+    if let Some(dir) = portable::data_dir() {
+        dirs.push(dir.join(DATA_SUBDIR));
+    }
+    let settings = portable::config_dir(app)
+        .ok()
+        .and_then(|dir| fs::read(dir.join(portable::SETTINGS_FILENAME)).ok())
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok());
+    if let Some(settings) = settings {
+        let setting = |key: &str| {
+            settings
+                .get(key)
+                .and_then(|value| value.as_str())
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        };
+        dirs.extend(setting("localBooksDir"));
+        dirs.extend(setting("customRootDir").map(|dir| dir.join(DATA_SUBDIR)));
+    }
+    dirs
+}
+
+pub(crate) fn thumbnail_cache_dir(app: &AppHandle) -> Option<PathBuf> {
+    std::env::var_os(THUMBNAIL_CACHE_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| portable::data_dir().map(|dir| dir.join("cache").join("thumbnails")))
+        .or_else(|| {
+            let local = app.path().local_data_dir().ok()?;
+            Some(local.join("Readest").join("cache").join("thumbnails"))
+        })
+}
+
+fn log_dir(app: &AppHandle) -> Option<PathBuf> {
+    portable::log_dir().or_else(|| app.path().app_log_dir().ok())
+}
+
+/// Clear the app's entries in the system's recent documents. Returns the
+/// name of the list cleared, or `None` where there is nothing of ours.
+#[cfg(target_os = "windows")]
+fn clear_recent_files(_app: &AppHandle) -> Result<Option<&'static str>, String> {
+    use ::windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+        COINIT_APARTMENTTHREADED,
+    };
+    use ::windows::Win32::UI::Shell::{ApplicationDestinations, IApplicationDestinations};
+
+    unsafe {
+        let initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let result = CoCreateInstance::<_, IApplicationDestinations>(
+            &ApplicationDestinations,
+            None,
+            CLSCTX_INPROC_SERVER,
+        )
+        .and_then(|destinations| destinations.RemoveAllDestinations())
+        .map(|()| Some("Jump List"))
+        .map_err(|e| e.to_string());
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn clear_recent_files(app: &AppHandle) -> Result<Option<&'static str>, String> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    app.run_on_main_thread(|| unsafe {
+        let controller: id = msg_send![class!(NSDocumentController), sharedDocumentController];
+        let _: () = msg_send![controller, clearRecentDocuments: nil];
+    })
+    .map(|()| Some("Open Recent"))
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn clear_recent_files(_app: &AppHandle) -> Result<Option<&'static str>, String> {
+    Ok(None)
+}
+
+fn reset_scope(app: &AppHandle, scope: ResetScope, books: &[PathBuf], report: &mut ResetReport) {
+    match scope {
+        ResetScope::ThumbnailCache => {
+            if cfg!(target_os = "windows") {
+                if let Some(dir) = thumbnail_cache_dir(app) {
+                    report.clear_cache_entries(scope, &dir, books);
+                }
+            }
+        }
+        ResetScope::WindowState => match portable::config_dir(app) {
+            Ok(dir) => {
+                WINDOW_STATE_RESET.store(true, Ordering::SeqCst);
+                report.remove_file(scope, &dir.join(STATE_FILENAME), books);
+                report.remove_file(scope, &dir.join(window_on_top::STORE_FILENAME), books);
+            }
+            Err(e) => report.failed(scope, STATE_FILENAME, e),
+        },
+        ResetScope::RecentFiles => {
+            match clear_recent_files(app) {
+                Ok(Some(list)) => report.removed(scope, list),
+                Ok(None) => {}
+                Err(e) => report.failed(scope, "recent documents", e),
+            }
+            match recent_files::store_path(app) {
+                Ok(path) => report.remove_file(scope, &path, books),
+                Err(e) => report.failed(scope, "recent files", e),
+            }
+        }
+        ResetScope::Logs => match log_dir(app) {
+            Some(dir) => report.clear_dir(scope, &dir, books),
+            None => report.failed(scope, "logs", "no log folder"),
+        },
+        ResetScope::All => {
+            for scope in ResetScope::EACH {
+                reset_scope(app, scope, books, report);
+            }
+        }
+    }
+}
+
+/// The scopes to clear, each once, in a fixed order.
+fn expand_scopes(scopes: &[ResetScope]) -> Vec<ResetScope> {
+    ResetScope::EACH
+        .into_iter()
+        .filter(|scope| scopes.contains(scope) || scopes.contains(&ResetScope::All))
+        .collect()
+}
+
+/// Clear the app state in `scopes`. Partial failures are reported, not
+/// returned as errors.
+#[tauri::command]
+pub async fn reset_app_data(
+    app: AppHandle,
+    scopes: Vec<ResetScope>,
+) -> Result<ResetReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let books = library_dirs(&app);
+        let mut report = ResetReport::default();
+        for scope in expand_scopes(&scopes) {
+            reset_scope(&app, scope, &books, &mut report);
+        }
+        log::info!(
+            "Reset {scopes:?}: removed {}, failed {}",
+            report.removed.len(),
+            report.failed.len()
+        );
+        report
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))
+}
+
+/// Remove the window state the window-state plugin saved on exit, when it
+/// was reset during this run. Call from the `Exit` event.
+pub fn cleanup(app: &AppHandle) {
+    if !WINDOW_STATE_RESET.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(dir) = portable::config_dir(app) {
+        let _ = fs::remove_file(dir.join(STATE_FILENAME));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_expands_to_each_scope_once() {
+        assert_eq!(
+            expand_scopes(&[ResetScope::Logs, ResetScope::All]),
+            ResetScope::EACH.to_vec()
+        );
+        assert_eq!(
+            expand_scopes(&[ResetScope::Logs, ResetScope::WindowState, ResetScope::Logs]),
+            vec![ResetScope::WindowState, ResetScope::Logs]
+        );
+    }
+
+    #[test]
+    fn never_clears_around_books() {
+        let root = std::env::temp_dir().join(format!("readest-reset-{}", std::process::id()));
+        let books = root.join("Readest").join("Books");
+        let logs = root.join("logs");
+        fs::create_dir_all(&books).unwrap();
+        fs::create_dir_all(logs.join("old")).unwrap();
+        fs::write(books.join("dune.epub"), b"").unwrap();
+        fs::write(logs.join("readest.log"), b"").unwrap();
+        let library = [books.clone()];
+
+        let mut report = ResetReport::default();
+        report.clear_dir(ResetScope::Logs, &logs, &library);
+        report.clear_dir(ResetScope::Logs, &root, &library);
+        report.remove_file(ResetScope::Logs, &books.join("dune.epub"), &library);
+
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(report.failed.len(), 2);
+        assert!(books.join("dune.epub").exists());
+        assert!(logs.exists() && fs::read_dir(&logs).unwrap().next().is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn thumbnail_reset_keeps_other_files() {
+        let dir = std::env::temp_dir().join(format!("readest-reset-thumbs-{}", std::process::id()));
+        let entry = format!("v5-{:032x}.thumb", 1);
+        fs::create_dir_all(dir.join("v5-nested")).unwrap();
+        fs::write(dir.join(&entry), b"").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+        fs::write(dir.join("v5-nested").join(&entry), b"").unwrap();
+
+        let mut report = ResetReport::default();
+        report.clear_cache_entries(ResetScope::ThumbnailCache, &dir, &[]);
+
+        assert_eq!(report.removed.len(), 1);
+        assert!(!dir.join(&entry).exists());
+        assert!(dir.join("notes.txt").exists());
+        assert!(dir.join("v5-nested").join(&entry).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! In-place AppImage updates.
+//!
+//! An AppImage runs from a read-only squashfs mount, so the Tauri updater has
+//! nothing it can replace: the file to update is the one named by
+//! `$APPIMAGE`. The new image is downloaded next to that file, checked
+//! against the updater signature and the AppImage magic, given the old file's
+//! permissions (keeping it executable) and renamed over it. The rename is
+//! atomic, so an interrupted update leaves the old AppImage untouched.
+//! Relaunching afterwards starts the new version, since Tauri resolves the
+//! running binary through `$APPIMAGE`.
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Channel;
+
+use crate::nightly_update::{verify_signature_impl, NightlyProgress};
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AppImageUpdateError {
+    #[error("not running from an AppImage: {0}")]
+    NotAppImage(String),
+    /// The directory holding the AppImage can't be written, e.g. it was
+    /// installed system-wide. The UI points the user to a manual download.
+    #[error("AppImage location is not writable: {0}")]
+    NotWritable(String),
+    #[error("failed to download update: {0}")]
+    Download(String),
+    #[error("update signature verification failed: {0}")]
+    InvalidSignature(String),
+    #[error("downloaded file is not an AppImage: {0}")]
+    InvalidImage(String),
+    #[error("failed to install update: {0}")]
+    Install(String),
+}
+
+/// ELF header carrying the type 2 AppImage marker (`AI\x02` at offset 8).
+fn is_appimage(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\x7fELF") && bytes.get(8..11) == Some(b"AI\x02")
+}
+
+/// The AppImage file itself, with symlinks resolved so that a launcher link
+/// keeps pointing at the updated file.
+fn appimage_path() -> Result<PathBuf, AppImageUpdateError> {
+    let path = std::env::var_os("APPIMAGE")
+        .ok_or_else(|| AppImageUpdateError::NotAppImage("APPIMAGE is not set".into()))?;
+    fs::canonicalize(&path).map_err(|e| {
+        AppImageUpdateError::NotAppImage(format!("{}: {e}", Path::new(&path).display()))
+    })
+}
+
+/// Hidden sibling of `target` the new image is written to before the swap.
+/// Same directory, so the final rename never crosses filesystems.
+fn staging_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{name}.update"))
+}
+
+fn is_permission_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Map a failure to write into the AppImage's directory, telling an
+/// unwritable location apart from other I/O errors.
+fn write_error(path: &Path, e: io::Error) -> AppImageUpdateError {
+    if is_permission_error(&e) {
+        AppImageUpdateError::NotWritable(format!("{}: {e}", path.display()))
+    } else {
+        AppImageUpdateError::Install(format!("{}: {e}", path.display()))
+    }
+}
+
+/// Create the staging file for `target`, replacing one left behind by an
+/// interrupted update. Done before downloading so an unwritable location is
+/// reported without fetching anything.
+fn create_staging(target: &Path) -> Result<(PathBuf, File), AppImageUpdateError> {
+    let staging = staging_path(target);
+    let _ = fs::remove_file(&staging);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&staging)
+        .map_err(|e| write_error(target.parent().unwrap_or(target), e))?;
+    Ok((staging, file))
+}
+
+/// Give `staging` the permissions of `target`, executable by its owner at
+/// least, and atomically rename it over `target`.
+fn swap_into_place(staging: &Path, target: &Path) -> Result<(), AppImageUpdateError> {
+    let mode = fs::metadata(target)
+        .map(|meta| meta.permissions().mode() & 0o7777)
+        .unwrap_or(0o755)
+        | 0o100;
+    fs::set_permissions(staging, fs::Permissions::from_mode(mode))
+        .map_err(|e| write_error(staging, e))?;
+    fs::rename(staging, target).map_err(|e| write_error(target, e))?;
+    // Persist the rename itself, not just the file contents.
+    if let Some(dir) = target.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+async fn download(
+    url: &str,
+    channel: &Channel<NightlyProgress>,
+) -> Result<Vec<u8>, AppImageUpdateError> {
+    let download_error = |e: reqwest::Error| AppImageUpdateError::Download(e.to_string());
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(download_error)?;
+    let content_length = response.content_length().unwrap_or(0);
+
+    let mut data = Vec::with_capacity(content_length as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        data.extend_from_slice(&chunk.map_err(download_error)?);
+        let _ = channel.send(NightlyProgress {
+            event: "progress".into(),
+            downloaded: data.len() as u64,
+            content_length,
+        });
+    }
+    let _ = channel.send(NightlyProgress {
+        event: "finished".into(),
+        downloaded: 0,
+        content_length: 0,
+    });
+    Ok(data)
+}
+
+async fn download_and_swap(
+    url: &str,
+    signature: &str,
+    pub_key: &str,
+    target: &Path,
+    staging: &Path,
+    mut file: File,
+    channel: &Channel<NightlyProgress>,
+) -> Result<(), AppImageUpdateError> {
+    let data = download(url, channel).await?;
+    if !verify_signature_impl(&data, signature, pub_key) {
+        return Err(AppImageUpdateError::InvalidSignature(url.to_string()));
+    }
+    if !is_appimage(&data) {
+        return Err(AppImageUpdateError::InvalidImage(url.to_string()));
+    }
+    file.write_all(&data)
+        .and_then(|()| file.sync_all())
+        .map_err(|e| write_error(staging, e))?;
+    drop(file);
+    swap_into_place(staging, target)
+}
+
+/// Download the AppImage at `url`, verify it against `signature` with
+/// `pub_key` (the same inputs as `verify_update_signature`) and replace the
+/// running AppImage with it. Progress is streamed over `channel`. Returns the
+/// updated AppImage's path; the caller relaunches.
+#[tauri::command]
+pub async fn install_appimage_update(
+    url: String,
+    signature: String,
+    pub_key: String,
+    channel: Channel<NightlyProgress>,
+) -> Result<String, AppImageUpdateError> {
+    let target = appimage_path()?;
+    let (staging, file) = create_staging(&target)?;
+    let result = download_and_swap(
+        &url, &signature, &pub_key, &target, &staging, file, &channel,
+    )
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result.map(|()| target.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn appimage_magic_requires_elf_and_marker() {
+        let mut image = b"\x7fELF\x02\x01\x01\x00AI\x02".to_vec();
+        image.resize(64, 0);
+        assert!(is_appimage(&image));
+        assert!(!is_appimage(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00"));
+        assert!(!is_appimage(b"\x1f\x8b\x08\x00AI\x02"));
+        assert!(!is_appimage(b""));
+    }
+
+    #[test]
+    fn staging_file_is_a_hidden_sibling() {
+        assert_eq!(
+            staging_path(Path::new("/home/me/Apps/Readest.AppImage")),
+            PathBuf::from("/home/me/Apps/.Readest.AppImage.update")
+        );
+    }
+
+    #[test]
+    fn swap_replaces_target_and_keeps_it_executable() {
+        let dir = temp_dir("appimage-swap");
+        let target = dir.join("Readest.AppImage");
+        fs::write(&target, b"old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let (staging, mut file) = create_staging(&target).unwrap();
+        file.write_all(b"new").unwrap();
+        drop(file);
+        swap_into_place(&staging, &target).unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        let mode = fs::metadata(&target).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o750);
+        assert!(!staging.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn swap_adds_owner_execute_when_missing() {
+        let dir = temp_dir("appimage-exec");
+        let target = dir.join("Readest.AppImage");
+        fs::write(&target, b"old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let (staging, file) = create_staging(&target).unwrap();
+        drop(file);
+        swap_into_place(&staging, &target).unwrap();
+
+        let mode = fs::metadata(&target).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o744);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
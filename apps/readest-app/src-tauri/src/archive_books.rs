@@ -0,0 +1,374 @@
+//! Choosing a book from a `.zip` (or a folder) that holds several.
+//!
+//! `list_archive_books` names every entry in a recognized book format, with
+//! a title read from the book itself where `book_rename` knows how and the
+//! file name otherwise. `extract_archive_book` then copies the chosen entry
+//! to `<cache>/archive-books/` for the reader to open; books in a folder are
+//! opened where they are.
+//!
+//! Extracted copies live only as long as they are read: the frontend
+//! releases one when the book is closed, everything left is removed on exit,
+//! and copies orphaned by a crashed process are swept on the next extraction.
+
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use crate::book_metadata::{read_metadata, read_metadata_from_bytes};
+use crate::book_rename::split_book_name;
+
+/// Extensions (lowercase, as `split_book_name` yields them) of book entries.
+const BOOK_FORMATS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "kf8", "prc", "fb2", "fbz", "fb2.zip", "cbz", "cbr", "pdf",
+    "txt",
+];
+
+/// Formats `book_rename` reads a title from.
+const TITLED_FORMATS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "prc", "fb2", "fbz", "fb2.zip",
+];
+
+/// Largest entry `extract_archive_book` writes out. Sizes come from the zip
+/// directory, which can lie, so the copy itself is capped too.
+const MAX_EXTRACT_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Largest entry read into memory for its title; bigger ones are listed
+/// under their file name.
+const MAX_TITLE_READ_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How deep `list_archive_books` looks into a folder.
+const MAX_FOLDER_DEPTH: usize = 4;
+
+/// Extractions older than this are assumed orphaned by a crashed process.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBook {
+    /// Entry name in the zip, or the path relative to the folder, with `/`
+    /// separators. Pass it back to `extract_archive_book`.
+    pub inner_path: String,
+    /// Extension identifying the format, e.g. `epub` or `fb2.zip`.
+    pub format: String,
+    pub title: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+}
+
+/// Books extracted by this process, removed on release or exit.
+#[derive(Default)]
+pub struct ExtractedBooks(Mutex<Vec<PathBuf>>);
+
+/// Format of a book entry named `name`, or `None` if it isn't one.
+fn book_format(name: &str) -> Option<String> {
+    let (_, ext) = split_book_name(Path::new(name));
+    let ext = ext.to_ascii_lowercase();
+    BOOK_FORMATS.contains(&ext.as_str()).then_some(ext)
+}
+
+/// File name of `inner_path` without its book extension.
+fn title_from_name(inner_path: &str) -> String {
+    split_book_name(Path::new(inner_path)).0
+}
+
+fn list_zip_books<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Vec<ArchiveBook> {
+    let mut books = Vec::new();
+    for index in 0..zip.len() {
+        let Ok(mut entry) = zip.by_index(index) else {
+            continue;
+        };
+        if entry.is_dir() || entry.enclosed_name().is_none() {
+            continue;
+        }
+        let inner_path = entry.name().to_string();
+        let Some(format) = book_format(&inner_path) else {
+            continue;
+        };
+        let size = entry.size();
+        let title = if TITLED_FORMATS.contains(&format.as_str()) && size <= MAX_TITLE_READ_BYTES {
+            let mut bytes = Vec::with_capacity(size as usize);
+            (&mut entry)
+                .take(MAX_TITLE_READ_BYTES)
+                .read_to_end(&mut bytes)
+                .ok()
+                .and_then(|_| read_metadata_from_bytes(&format, &bytes).ok())
+                .and_then(|metadata| metadata.title)
+        } else {
+            None
+        };
+        books.push(ArchiveBook {
+            title: title.unwrap_or_else(|| title_from_name(&inner_path)),
+            inner_path,
+            format,
+            size,
+        });
+    }
+    books
+}
+
+fn list_folder_books(dir: &Path) -> Vec<ArchiveBook> {
+    let mut books = Vec::new();
+    for entry in WalkDir::new(dir)
+        .max_depth(MAX_FOLDER_DEPTH)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(dir) else {
+            continue;
+        };
+        let inner_path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let Some(format) = book_format(&inner_path) else {
+            continue;
+        };
+        let title = TITLED_FORMATS
+            .contains(&format.as_str())
+            .then(|| read_metadata(entry.path()).ok())
+            .flatten()
+            .and_then(|metadata| metadata.title);
+        books.push(ArchiveBook {
+            title: title.unwrap_or_else(|| title_from_name(&inner_path)),
+            size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            inner_path,
+            format,
+        });
+    }
+    books
+}
+
+fn list_archive_books_sync(path: &Path) -> Result<Vec<ArchiveBook>, String> {
+    if path.is_dir() {
+        return Ok(list_folder_books(path));
+    }
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    Ok(list_zip_books(&mut zip))
+}
+
+/// Books inside the zip archive or folder at `path`.
+#[tauri::command]
+pub async fn list_archive_books(app: AppHandle, path: String) -> Result<Vec<ArchiveBook>, String> {
+    let path = PathBuf::from(path);
+    if !app.fs_scope().is_allowed(&path) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || list_archive_books_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Copy entry `inner_path` of the zip to `target`, refusing entries larger
+/// than `max_bytes` whatever the zip directory claims.
+fn extract_zip_entry<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    inner_path: &str,
+    target: &Path,
+    max_bytes: u64,
+) -> Result<(), String> {
+    let entry = zip
+        .by_name(inner_path)
+        .map_err(|e| format!("{inner_path}: {e}"))?;
+    if entry.enclosed_name().is_none() || book_format(inner_path).is_none() {
+        return Err(format!("not a book entry: {inner_path}"));
+    }
+    if entry.size() > max_bytes {
+        return Err(format!(
+            "{inner_path} is too large to extract ({} bytes)",
+            entry.size()
+        ));
+    }
+    let mut out = File::create(target).map_err(|e| format!("create failed: {e}"))?;
+    let written = std::io::copy(&mut entry.take(max_bytes + 1), &mut out)
+        .map_err(|e| format!("extract failed: {e}"))?;
+    if written > max_bytes {
+        drop(out);
+        let _ = fs::remove_file(target);
+        return Err(format!("{inner_path} is too large to extract"));
+    }
+    Ok(())
+}
+
+/// A book inside the folder `dir`, refusing paths that lead out of it.
+fn folder_book(dir: &Path, inner_path: &str) -> Result<PathBuf, String> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| format!("open failed: {e}"))?;
+    let book = dir
+        .join(inner_path)
+        .canonicalize()
+        .map_err(|e| format!("{inner_path}: {e}"))?;
+    if !book.starts_with(&dir) || !book.is_file() || book_format(inner_path).is_none() {
+        return Err(format!("not a book entry: {inner_path}"));
+    }
+    Ok(book)
+}
+
+fn extract_archive_book_sync(
+    root: &Path,
+    archive: &Path,
+    inner_path: &str,
+) -> Result<PathBuf, String> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = inner_path
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| format!("not a book entry: {inner_path}"))?;
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create {}: {e}", root.display()))?;
+    crate::portable::sweep_stale(root, STALE_AFTER);
+    // One folder per extraction keeps the book's own file name, which the
+    // reader falls back to for the title.
+    let dir = root.join(format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let target = dir.join(file_name);
+
+    let file = File::open(archive).map_err(|e| format!("open failed: {e}"))?;
+    let result = ZipArchive::new(file)
+        .map_err(|e| format!("zip open failed: {e}"))
+        .and_then(|mut zip| extract_zip_entry(&mut zip, inner_path, &target, MAX_EXTRACT_BYTES));
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    Ok(target)
+}
+
+/// Path to open for the book `inner_path` of the archive or folder at
+/// `path`: an extracted copy for archives, the book itself for folders.
+#[tauri::command]
+pub async fn extract_archive_book(
+    app: AppHandle,
+    path: String,
+    inner_path: String,
+) -> Result<String, String> {
+    let archive = PathBuf::from(path);
+    if !app.fs_scope().is_allowed(&archive) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    if archive.is_dir() {
+        let book = folder_book(&archive, &inner_path)?;
+        return Ok(book.to_string_lossy().into_owned());
+    }
+
+    let root = crate::portable::cache_dir(&app)?.join("archive-books");
+    let target = tauri::async_runtime::spawn_blocking(move || {
+        extract_archive_book_sync(&root, &archive, &inner_path)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))??;
+
+    if let Ok(mut extracted) = app.state::<ExtractedBooks>().0.lock() {
+        extracted.push(target.clone());
+    }
+    crate::allow_file_in_scopes(&app, vec![target.clone()]);
+    Ok(target.to_string_lossy().into_owned())
+}
+
+/// Remove a copy made by `extract_archive_book` once its book is closed.
+/// Paths it didn't extract are ignored.
+#[tauri::command]
+pub fn release_archive_book(app: AppHandle, path: String) {
+    let path = PathBuf::from(path);
+    let state = app.state::<ExtractedBooks>();
+    let Ok(mut extracted) = state.0.lock() else {
+        return;
+    };
+    if let Some(index) = extracted.iter().position(|p| *p == path) {
+        remove_extracted(&extracted.swap_remove(index));
+    }
+}
+
+/// Remove the extraction folder holding `book`.
+fn remove_extracted(book: &Path) {
+    if let Some(dir) = book.parent() {
+        if let Err(e) = fs::remove_dir_all(dir) {
+            log::warn!("Failed to remove {}: {e}", dir.display());
+        }
+    }
+}
+
+/// Delete every copy extracted by this process.
+pub fn cleanup(app: &AppHandle) {
+    let Some(state) = app.try_state::<ExtractedBooks>() else {
+        return;
+    };
+    let Ok(mut extracted) = state.0.lock() else {
+        return;
+    };
+    for book in extracted.drain(..) {
+        remove_extracted(&book);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::zip_with;
+
+    #[test]
+    fn lists_book_entries_with_titles() {
+        let fb2 = b"<FictionBook><description><title-info>\
+            <book-title>Roadside Picnic</book-title></title-info></description></FictionBook>";
+        let mut zip = zip_with(&[
+            ("readme.md", b"# books"),
+            ("Strugatsky/picnic.fb2", fb2),
+            ("comics/Issue 01.cbz", b"PK"),
+            ("broken.epub", b"not a zip"),
+        ]);
+        let books = list_zip_books(&mut zip);
+        let summary: Vec<_> = books
+            .iter()
+            .map(|b| (b.inner_path.as_str(), b.format.as_str(), b.title.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Strugatsky/picnic.fb2", "fb2", "Roadside Picnic"),
+                ("comics/Issue 01.cbz", "cbz", "Issue 01"),
+                ("broken.epub", "epub", "broken"),
+            ]
+        );
+    }
+
+    #[test]
+    fn extraction_is_capped_and_limited_to_book_entries() {
+        let dir = std::env::temp_dir().join(format!("readest-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut zip = zip_with(&[("book.txt", b"Chapter 1"), ("notes.md", b"-")]);
+
+        let target = dir.join("book.txt");
+        extract_zip_entry(&mut zip, "book.txt", &target, 64).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"Chapter 1");
+        assert!(extract_zip_entry(&mut zip, "book.txt", &dir.join("big.txt"), 4).is_err());
+        assert!(!dir.join("big.txt").exists());
+        assert!(extract_zip_entry(&mut zip, "notes.md", &dir.join("notes.md"), 64).is_err());
+        assert!(folder_book(&dir, "../book.txt").is_err());
+        assert_eq!(
+            folder_book(&dir, "book.txt").unwrap(),
+            target.canonicalize().unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
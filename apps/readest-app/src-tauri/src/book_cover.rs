@@ -0,0 +1,584 @@
+//! `set_book_cover`: make a cover the user picked the one the book shows.
+//!
+//! EPUBs get the image embedded, as `readest-cover.<ext>` beside the OPF.
+//! The OPF declares it `properties="cover-image"` (taking the property off
+//! the previous cover) and points `<meta name="cover">` at it, which is what
+//! both the import path and the Explorer thumbnails resolve first. The old
+//! image stays in the archive for any cover page that shows it. The book is
+//! repacked into a temp file beside it, with untouched entries copied raw so
+//! nothing is recompressed and `mimetype` stays first and stored, and then
+//! renamed over the original.
+//!
+//! Other formats can't be rewritten safely, so the image is stored as a
+//! sidecar, `<book>.readest-cover.<ext>`, which `mobi_parser`, `cover_color`
+//! and the Explorer thumbnail provider read instead of the embedded cover.
+//!
+//! The image is decoded before anything is written, and every write goes
+//! through a temp file, so a failure leaves the book and any previous cover
+//! as they were. On Windows, Explorer is then told the book changed so it
+//! drops its cached thumbnail; the provider's own cache keys on the book's
+//! length and the sidecar, so it misses too.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::AppHandle;
+use tauri_plugin_fs::FsExt;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::epub_parser::{local_name_eq, read_rootfile_path, read_zip_entry, resolve_relative};
+use crate::parser_common::{attribute, RawCoverImage};
+
+/// Manifest id and file stem of an embedded replacement cover.
+const COVER_ID: &str = "readest-cover";
+const SIDECAR_INFIX: &str = ".readest-cover.";
+const MAX_COVER_BYTES: usize = 20 * 1024 * 1024;
+const MAX_COVER_PIXELS: u64 = 40_000_000;
+
+/// Distinguishes temp files of concurrent writes from this process.
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageKind {
+    ext: &'static str,
+    mime: &'static str,
+}
+
+const IMAGE_KINDS: [ImageKind; 3] = [
+    ImageKind {
+        ext: "jpg",
+        mime: "image/jpeg",
+    },
+    ImageKind {
+        ext: "png",
+        mime: "image/png",
+    },
+    ImageKind {
+        ext: "gif",
+        mime: "image/gif",
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoverTarget {
+    /// Written into the EPUB itself.
+    Embedded,
+    /// Stored beside the book.
+    Sidecar,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedCover {
+    pub target: CoverTarget,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The type and dimensions of `bytes`, once it has decoded as a JPEG, PNG or
+/// GIF of reasonable size.
+fn validate_image(bytes: &[u8]) -> Result<(ImageKind, u32, u32), String> {
+    if bytes.is_empty() {
+        return Err("empty cover image".to_string());
+    }
+    if bytes.len() > MAX_COVER_BYTES {
+        return Err(format!("cover image larger than {MAX_COVER_BYTES} bytes"));
+    }
+    let format = image::guess_format(bytes).map_err(|e| format!("unknown image type: {e}"))?;
+    let kind = match format {
+        image::ImageFormat::Jpeg => IMAGE_KINDS[0],
+        image::ImageFormat::Png => IMAGE_KINDS[1],
+        image::ImageFormat::Gif => IMAGE_KINDS[2],
+        other => return Err(format!("unsupported cover format: {other:?}")),
+    };
+    let (width, height) = image::ImageReader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|e| format!("invalid image: {e}"))?;
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_COVER_PIXELS {
+        return Err(format!("unsupported cover dimensions: {width}x{height}"));
+    }
+    image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| format!("invalid image: {e}"))?;
+    Ok((kind, width, height))
+}
+
+fn is_epub(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"))
+}
+
+/// `book.mobi` → `book.mobi.readest-cover.jpg`.
+fn sidecar_path(book: &Path, ext: &str) -> PathBuf {
+    let mut name = book.as_os_str().to_os_string();
+    name.push(SIDECAR_INFIX);
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// The cover stored beside `book` by [`set_book_cover`], if any.
+pub(crate) fn read_cover_sidecar(book: &Path) -> Option<RawCoverImage> {
+    IMAGE_KINDS.iter().find_map(|kind| {
+        let bytes = fs::read(sidecar_path(book, kind.ext)).ok()?;
+        Some(RawCoverImage {
+            bytes,
+            mime: kind.mime.to_string(),
+        })
+    })
+}
+
+/// Write `target` through a uniquely named temp file beside it, renamed over
+/// it once `write` succeeds. On failure the temp file is removed and `target`
+/// is left as it was.
+fn replace_file(
+    target: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), String>,
+) -> Result<(), String> {
+    replace_file_checked(target, write, |_| Ok(()))
+}
+
+/// [`replace_file`], with `check` run on the finished temp file before it
+/// replaces `target`; an error from it leaves `target` untouched.
+pub(crate) fn replace_file_checked(
+    target: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), String>,
+    check: impl FnOnce(&Path) -> Result<(), String>,
+) -> Result<(), String> {
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", target.display()))?
+        .to_string_lossy();
+    let temp = target.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = File::create(&temp)
+        .map_err(|e| format!("create {}: {e}", temp.display()))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            let file = writer
+                .into_inner()
+                .map_err(|e| format!("write {}: {e}", temp.display()))?;
+            file.sync_all()
+                .map_err(|e| format!("sync {}: {e}", temp.display()))
+        })
+        .and_then(|()| check(&temp))
+        .and_then(|()| {
+            fs::rename(&temp, target).map_err(|e| format!("replace {}: {e}", target.display()))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// `e` with attribute `name` set to `value`, or removed when `value` is
+/// `None`. Other attributes keep their order.
+fn with_attribute(e: &BytesStart, name: &str, value: Option<&str>) -> BytesStart<'static> {
+    let mut out = BytesStart::new(String::from_utf8_lossy(e.name().as_ref()).into_owned());
+    let mut replaced = false;
+    for attr in e.attributes().flatten() {
+        if attr.key.as_ref() == name.as_bytes() {
+            replaced = true;
+            if let Some(value) = value {
+                out.push_attribute((name, value));
+            }
+        } else {
+            out.push_attribute(attr);
+        }
+    }
+    if let (false, Some(value)) = (replaced, value) {
+        out.push_attribute((name, value));
+    }
+    out
+}
+
+/// `manifest` → `item`, `opf:manifest` → `opf:item`.
+fn sibling_name(container: &[u8], local: &str) -> String {
+    let container = String::from_utf8_lossy(container);
+    match container.split_once(':') {
+        Some((prefix, _)) => format!("{prefix}:{local}"),
+        None => local.to_string(),
+    }
+}
+
+/// Rewrite an OPF so `href` is the cover: a `cover-image` manifest item with
+/// id [`COVER_ID`] (replacing one from an earlier call) and a legacy
+/// `<meta name="cover">` pointing at it. Everything else passes through.
+fn rewrite_opf(opf: &[u8], href: &str, media_type: &str) -> Result<Vec<u8>, String> {
+    let mut reader = Reader::from_reader(opf);
+    let mut writer = Writer::new(Vec::with_capacity(opf.len() + 256));
+    let mut buf = Vec::new();
+    let mut skipping_item = false;
+    let mut meta_written = false;
+    let mut item_written = false;
+
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("opf xml: {e}"))?;
+        let event = match event {
+            Event::Eof => break,
+            _ if skipping_item => {
+                if matches!(&event, Event::End(e) if local_name_eq(e.name().as_ref(), b"item")) {
+                    skipping_item = false;
+                }
+                continue;
+            }
+            Event::Start(ref e) | Event::Empty(ref e)
+                if local_name_eq(e.name().as_ref(), b"item")
+                    && attribute(e, b"id").as_deref() == Some(COVER_ID) =>
+            {
+                skipping_item = matches!(event, Event::Start(_));
+                continue;
+            }
+            Event::Start(ref e) | Event::Empty(ref e)
+                if local_name_eq(e.name().as_ref(), b"item") =>
+            {
+                let properties = attribute(e, b"properties").unwrap_or_default();
+                if properties
+                    .split_ascii_whitespace()
+                    .any(|p| p == "cover-image")
+                {
+                    let rest = properties
+                        .split_ascii_whitespace()
+                        .filter(|p| *p != "cover-image")
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let item = with_attribute(
+                        e,
+                        "properties",
+                        Some(rest.as_str()).filter(|p| !p.is_empty()),
+                    );
+                    match event {
+                        Event::Start(_) => Event::Start(item),
+                        _ => Event::Empty(item),
+                    }
+                } else {
+                    event
+                }
+            }
+            Event::Start(ref e) | Event::Empty(ref e)
+                if local_name_eq(e.name().as_ref(), b"meta")
+                    && attribute(e, b"name")
+                        .is_some_and(|name| name.eq_ignore_ascii_case("cover")) =>
+            {
+                meta_written = true;
+                let meta = with_attribute(e, "content", Some(COVER_ID));
+                match event {
+                    Event::Start(_) => Event::Start(meta),
+                    _ => Event::Empty(meta),
+                }
+            }
+            Event::End(ref e) if local_name_eq(e.name().as_ref(), b"metadata") && !meta_written => {
+                let mut meta = BytesStart::new(sibling_name(e.name().as_ref(), "meta"));
+                meta.push_attribute(("name", "cover"));
+                meta.push_attribute(("content", COVER_ID));
+                writer
+                    .write_event(Event::Empty(meta))
+                    .map_err(|e| format!("opf write: {e}"))?;
+                meta_written = true;
+                event
+            }
+            Event::End(ref e) if local_name_eq(e.name().as_ref(), b"manifest") => {
+                let mut item = BytesStart::new(sibling_name(e.name().as_ref(), "item"));
+                item.push_attribute(("id", COVER_ID));
+                item.push_attribute(("href", href));
+                item.push_attribute(("media-type", media_type));
+                item.push_attribute(("properties", "cover-image"));
+                writer
+                    .write_event(Event::Empty(item))
+                    .map_err(|e| format!("opf write: {e}"))?;
+                item_written = true;
+                event
+            }
+            event => event,
+        };
+        writer
+            .write_event(event)
+            .map_err(|e| format!("opf write: {e}"))?;
+    }
+
+    if !item_written {
+        return Err("opf has no manifest".to_string());
+    }
+    Ok(writer.into_inner())
+}
+
+/// Repack the EPUB at `book` with `image` as its cover.
+fn embed_epub_cover(book: &Path, image: &[u8], kind: ImageKind) -> Result<(), String> {
+    let file = File::open(book).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf = read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf: {e}"))?;
+    let opf = crate::epub_parser::strip_xml_bom(&opf);
+
+    let href = format!("{COVER_ID}.{}", kind.ext);
+    let new_opf = rewrite_opf(&opf, &href, kind.mime)?;
+    let cover_path = resolve_relative(&opf_path, &href);
+    // Covers embedded by earlier calls, whatever their type.
+    let old_covers: Vec<String> = IMAGE_KINDS
+        .iter()
+        .map(|kind| resolve_relative(&opf_path, &format!("{COVER_ID}.{}", kind.ext)))
+        .collect();
+
+    replace_file(book, |out| {
+        let mut writer = ZipWriter::new(out);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for index in 0..zip.len() {
+            let entry = zip
+                .by_index_raw(index)
+                .map_err(|e| format!("entry {index}: {e}"))?;
+            let name = entry.name().to_string();
+            if old_covers.contains(&name) {
+                continue;
+            }
+            if name == opf_path {
+                drop(entry);
+                writer
+                    .start_file(name.as_str(), deflated)
+                    .and_then(|()| Ok(writer.write_all(&new_opf)?))
+                    .map_err(|e| format!("write {name}: {e}"))?;
+            } else {
+                writer
+                    .raw_copy_file(entry)
+                    .map_err(|e| format!("copy {name}: {e}"))?;
+            }
+        }
+        // Already compressed; deflating again only costs time.
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        writer
+            .start_file(cover_path.as_str(), stored)
+            .and_then(|()| Ok(writer.write_all(image)?))
+            .map_err(|e| format!("write {cover_path}: {e}"))?;
+        writer.finish().map_err(|e| format!("zip finish: {e}"))?;
+        Ok(())
+    })
+}
+
+/// Store `image` as the cover sidecar of `book`, dropping sidecars of other
+/// image types.
+fn store_sidecar(book: &Path, image: &[u8], kind: ImageKind) -> Result<(), String> {
+    let sidecar = sidecar_path(book, kind.ext);
+    replace_file(&sidecar, |out| {
+        out.write_all(image)
+            .map_err(|e| format!("write {}: {e}", sidecar.display()))
+    })?;
+    for other in IMAGE_KINDS.iter().filter(|other| other.ext != kind.ext) {
+        let _ = fs::remove_file(sidecar_path(book, other.ext));
+    }
+    Ok(())
+}
+
+fn apply_cover(book: &Path, image: &[u8]) -> Result<AppliedCover, String> {
+    if !book.is_file() {
+        return Err(format!("file not found: {}", book.display()));
+    }
+    let (kind, width, height) = validate_image(image)?;
+    let target = if is_epub(book) {
+        embed_epub_cover(book, image, kind)?;
+        CoverTarget::Embedded
+    } else {
+        store_sidecar(book, image, kind)?;
+        CoverTarget::Sidecar
+    };
+    notify_shell(book);
+    Ok(AppliedCover {
+        target,
+        mime: kind.mime.to_string(),
+        width,
+        height,
+    })
+}
+
+/// Tell Explorer the book changed, so it asks for a new thumbnail.
+#[cfg(target_os = "windows")]
+fn notify_shell(book: &Path) {
+    use ::windows::Win32::UI::Shell::{SHChangeNotify, SHCNE_UPDATEITEM, SHCNF_PATHW};
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide: Vec<u16> = book
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        SHChangeNotify(
+            SHCNE_UPDATEITEM,
+            SHCNF_PATHW,
+            Some(wide.as_ptr() as *const std::ffi::c_void),
+            None,
+        );
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn notify_shell(_book: &Path) {}
+
+/// Make `image_bytes` (JPEG, PNG or GIF) the cover of the book at `path`:
+/// embedded for EPUBs, a sidecar for everything else.
+#[tauri::command]
+pub async fn set_book_cover(
+    app: AppHandle,
+    path: String,
+    image_bytes: Vec<u8>,
+) -> Result<AppliedCover, String> {
+    let book = PathBuf::from(&path);
+    let scope = app.fs_scope();
+    let writes_sidecar = !is_epub(&book);
+    if !scope.is_allowed(&book)
+        || (writes_sidecar && !scope.is_allowed(sidecar_path(&book, IMAGE_KINDS[0].ext)))
+    {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || apply_cover(&book, &image_bytes))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub_parser::extract_epub_cover_full_sync;
+    use crate::test_support::{temp_dir, write_epub};
+    use image::{Rgb, RgbImage};
+
+    fn png(color: [u8; 3]) -> Vec<u8> {
+        let mut out = Vec::new();
+        RgbImage::from_pixel(6, 9, Rgb(color))
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut out = Vec::new();
+        RgbImage::from_pixel(8, 12, Rgb([10, 20, 30]))
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Jpeg)
+            .unwrap();
+        out
+    }
+
+    const OPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Book &amp; Co</dc:title>
+  </metadata>
+  <manifest>
+    <item id="old" href="images/old.png" media-type="image/png" properties="cover-image svg"/>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="ch1"/></spine>
+</package>"#;
+
+    fn write_sample_epub(path: &Path) {
+        write_epub(
+            path,
+            OPF.as_bytes(),
+            &[
+                ("OEBPS/images/old.png", &png([255, 0, 0])),
+                ("OEBPS/ch1.xhtml", b"<html/>"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rewrites_opf_cover_references() {
+        let opf = rewrite_opf(OPF.as_bytes(), "readest-cover.jpg", "image/jpeg").unwrap();
+        let opf = String::from_utf8(opf).unwrap();
+        assert!(opf.contains(
+            r#"<item id="old" href="images/old.png" media-type="image/png" properties="svg"/>"#
+        ));
+        assert!(opf.contains(r#"<item id="readest-cover" href="readest-cover.jpg" media-type="image/jpeg" properties="cover-image"/>"#));
+        assert!(opf.contains(r#"<meta name="cover" content="readest-cover"/>"#));
+        assert!(opf.contains("Book &amp; Co"));
+
+        // A second call replaces the item instead of adding another.
+        let again = rewrite_opf(opf.as_bytes(), "readest-cover.png", "image/png").unwrap();
+        let again = String::from_utf8(again).unwrap();
+        assert_eq!(again.matches(r#"id="readest-cover""#).count(), 1);
+        assert_eq!(again.matches(r#"name="cover""#).count(), 1);
+        assert!(again.contains(r#"href="readest-cover.png""#));
+    }
+
+    #[test]
+    fn keeps_the_opf_namespace_prefix() {
+        let opf = br#"<opf:package xmlns:opf="http://www.idpf.org/2007/opf"><opf:metadata><opf:meta name="cover" content="old"/></opf:metadata><opf:manifest></opf:manifest></opf:package>"#;
+        let out = String::from_utf8(rewrite_opf(opf, "c.gif", "image/gif").unwrap()).unwrap();
+        assert!(out.contains(r#"<opf:meta name="cover" content="readest-cover"/>"#));
+        assert!(out.contains(r#"<opf:item id="readest-cover" href="c.gif""#));
+    }
+
+    #[test]
+    fn embeds_cover_into_epub() {
+        let dir = temp_dir("book-cover-epub");
+        let book = dir.join("book.epub");
+        write_sample_epub(&book);
+        let cover = jpeg();
+
+        let applied = apply_cover(&book, &cover).unwrap();
+        assert_eq!(applied.target, CoverTarget::Embedded);
+        assert_eq!((applied.width, applied.height), (8, 12));
+
+        let extracted = extract_epub_cover_full_sync(book.to_str().unwrap()).unwrap();
+        assert_eq!(extracted.bytes, cover);
+        let mut zip = ZipArchive::new(File::open(&book).unwrap()).unwrap();
+        let first = zip.by_index(0).unwrap();
+        assert_eq!(first.name(), "mimetype");
+        assert_eq!(first.compression(), CompressionMethod::Stored);
+        drop(first);
+        assert!(zip.by_name("OEBPS/images/old.png").is_ok());
+
+        // Switching type drops the previous replacement.
+        apply_cover(&book, &png([0, 0, 255])).unwrap();
+        let mut zip = ZipArchive::new(File::open(&book).unwrap()).unwrap();
+        assert!(zip.by_name("OEBPS/readest-cover.jpg").is_err());
+        assert!(zip.by_name("OEBPS/readest-cover.png").is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_image_leaves_the_book_untouched() {
+        let dir = temp_dir("book-cover-invalid");
+        let book = dir.join("book.epub");
+        write_sample_epub(&book);
+        let original = fs::read(&book).unwrap();
+
+        let mut truncated = png([0, 255, 0]);
+        truncated.truncate(truncated.len() / 2);
+        assert!(apply_cover(&book, &truncated).is_err());
+        assert!(apply_cover(&book, b"not an image").is_err());
+        assert_eq!(fs::read(&book).unwrap(), original);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn other_formats_get_a_sidecar() {
+        let dir = temp_dir("book-cover-sidecar");
+        let book = dir.join("book.mobi");
+        fs::write(&book, b"BOOKMOBI").unwrap();
+
+        let applied = apply_cover(&book, &jpeg()).unwrap();
+        assert_eq!(applied.target, CoverTarget::Sidecar);
+        assert_eq!(fs::read(&book).unwrap(), b"BOOKMOBI");
+        assert_eq!(read_cover_sidecar(&book).unwrap().mime, "image/jpeg");
+
+        let cover = png([0, 0, 0]);
+        apply_cover(&book, &cover).unwrap();
+        let sidecar = read_cover_sidecar(&book).unwrap();
+        assert_eq!((sidecar.bytes, sidecar.mime.as_str()), (cover, "image/png"));
+        assert!(!dir.join("book.mobi.readest-cover.jpg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+// `check_drm`: whether a book is DRM-protected, for the library's "locked"
+// badge, without attempting a cover extraction.
+//
+// Only what each format needs is read:
+//
+//   - EPUB: `META-INF/encryption.xml` and `META-INF/rights.xml`. Entries
+//     under the IDPF or Adobe font-obfuscation algorithms are not DRM (the
+//     book opens fine); anything else is encrypted content, reported as
+//     Adobe ADEPT when `rights.xml` carries an ADEPT license and as unknown
+//     DRM otherwise.
+//   - MOBI/AZW/AZW3: the encryption field of the first record's PalmDOC
+//     header (0 = none, 1 = old Mobipocket, 2 = Mobipocket). DRM-wrapped KFX
+//     saved as `.azw` is recognized by its signature.
+//   - LIT (Microsoft Reader): the container directory. Books bound to a
+//     reader carry an end-user license, `/DRMStorage/Licenses/EUL`; sealed
+//     and inscribed books open without one and count as unprotected.
+//   - FB2, CBZ/CBR, TXT and Markdown have no DRM scheme; PDF and anything
+//     else is reported as unsupported.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::{local_name_eq, read_zip_entry};
+
+const ENCRYPTION_XML: &str = "META-INF/encryption.xml";
+const RIGHTS_XML: &str = "META-INF/rights.xml";
+const ADEPT_NAMESPACE: &[u8] = b"http://ns.adobe.com/adept";
+const FONT_OBFUSCATION_ALGORITHMS: [&[u8]; 2] = [
+    b"http://www.idpf.org/2008/embedding",
+    b"http://ns.adobe.com/pdf/enc#RC",
+];
+
+/// PalmDB header length; the first record's offset follows it.
+const PALMDB_HEADER_LEN: usize = 78;
+const KFX_DRMION_MAGIC: &[u8] = b"\xeaDRMION\xee";
+const LIT_MAGIC: &[u8] = b"ITOLITLS";
+const LIT_OWNER_LICENSE: &[u8] = b"/DRMStorage/Licenses/EUL";
+/// Largest LIT directory read; real ones are a few KiB.
+const MAX_LIT_DIRECTORY: u32 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DrmStatus {
+    /// Not protected.
+    None,
+    /// Only embedded fonts are obfuscated; the book itself is readable.
+    FontObfuscation,
+    /// Adobe ADEPT (Adobe Digital Editions) encrypted EPUB.
+    Adept,
+    /// Mobipocket DRM.
+    Mobipocket,
+    /// Microsoft Reader owner-exclusive LIT.
+    MicrosoftReader,
+    /// Encrypted with a scheme we don't identify.
+    Unknown,
+    /// The format isn't checked.
+    Unsupported,
+}
+
+#[tauri::command]
+pub async fn check_drm(path: String, ext: String) -> Result<DrmStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || check_drm_sync(&path, &ext))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+pub(crate) fn check_drm_sync(file_path: &str, ext: &str) -> Result<DrmStatus, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    match ext.trim_start_matches('.').to_ascii_lowercase().as_str() {
+        "epub" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            epub_drm(&mut zip)
+        }
+        "mobi" | "azw" | "azw3" | "kf8" | "prc" | "pdb" => {
+            let mut file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            mobi_drm(&mut file).map_err(|e| format!("read failed: {e}"))
+        }
+        "lit" => {
+            let mut file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            lit_drm(&mut file).map_err(|e| format!("read failed: {e}"))
+        }
+        "fb2" | "fbz" | "cbz" | "cbr" | "txt" | "md" => Ok(DrmStatus::None),
+        _ => Ok(DrmStatus::Unsupported),
+    }
+}
+
+pub(crate) fn epub_drm<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<DrmStatus, String> {
+    let has_rights = zip.index_for_name(RIGHTS_XML).is_some();
+    let encryption = if zip.index_for_name(ENCRYPTION_XML).is_some() {
+        let bytes = read_zip_entry(zip, ENCRYPTION_XML)?;
+        classify_encryption(&bytes)
+    } else {
+        Encryption::None
+    };
+
+    let content_encrypted = match encryption {
+        Encryption::None if !has_rights => return Ok(DrmStatus::None),
+        Encryption::FontsOnly => return Ok(DrmStatus::FontObfuscation),
+        Encryption::None => false,
+        Encryption::Content => true,
+    };
+    let adept = has_rights && contains(&read_zip_entry(zip, RIGHTS_XML)?, ADEPT_NAMESPACE);
+    Ok(match (content_encrypted, adept) {
+        (_, true) => DrmStatus::Adept,
+        (true, false) => DrmStatus::Unknown,
+        // A `rights.xml` with nothing encrypted is a leftover license.
+        (false, false) => DrmStatus::None,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Encryption {
+    None,
+    FontsOnly,
+    Content,
+}
+
+/// What the `EncryptionMethod`s in encryption.xml protect. Stops at the first
+/// algorithm that isn't font obfuscation.
+fn classify_encryption(bytes: &[u8]) -> Encryption {
+    let mut reader = Reader::from_reader(bytes);
+    let mut buf = Vec::new();
+    let mut result = Encryption::None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if local_name_eq(e.name().as_ref(), b"EncryptionMethod") =>
+            {
+                let font = e
+                    .attributes()
+                    .flatten()
+                    .find(|a| a.key.as_ref() == b"Algorithm")
+                    .is_some_and(|a| FONT_OBFUSCATION_ALGORITHMS.contains(&a.value.as_ref()));
+                if !font {
+                    return Encryption::Content;
+                }
+                result = Encryption::FontsOnly;
+            }
+            Ok(Event::Eof) => break,
+            // Unparseable encryption.xml: something is encrypted, but we
+            // can't say what.
+            Err(_) => return Encryption::Content,
+            _ => {}
+        }
+        buf.clear();
+    }
+    result
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// The PalmDOC encryption field of a MOBI, read from the 78-byte PalmDB
+/// header and the first 14 bytes of record 0.
+pub(crate) fn mobi_drm<R: Read + Seek>(reader: &mut R) -> std::io::Result<DrmStatus> {
+    let mut header = [0u8; PALMDB_HEADER_LEN + 4];
+    let read = read_up_to(reader, &mut header)?;
+    if header[..read].starts_with(KFX_DRMION_MAGIC) {
+        return Ok(DrmStatus::Unknown);
+    }
+    if read < header.len() || !matches!(&header[60..68], b"BOOKMOBI" | b"TEXtREAd") {
+        return Ok(DrmStatus::Unsupported);
+    }
+    let record0 = u32::from_be_bytes(header[78..82].try_into().unwrap());
+
+    let mut palmdoc = [0u8; 14];
+    reader.seek(SeekFrom::Start(u64::from(record0)))?;
+    if read_up_to(reader, &mut palmdoc)? < palmdoc.len() {
+        return Ok(DrmStatus::Unsupported);
+    }
+    Ok(match u16::from_be_bytes([palmdoc[12], palmdoc[13]]) {
+        0 => DrmStatus::None,
+        1 | 2 => DrmStatus::Mobipocket,
+        _ => DrmStatus::Unknown,
+    })
+}
+
+/// Whether a LIT's directory lists an end-user license. The directory is
+/// the second piece in the table after the 40-byte header; entry names are
+/// stored as plain UTF-8, so the listing is searched as bytes.
+pub(crate) fn lit_drm<R: Read + Seek>(reader: &mut R) -> std::io::Result<DrmStatus> {
+    let mut header = [0u8; 24];
+    if read_up_to(reader, &mut header)? < header.len() || !header.starts_with(LIT_MAGIC) {
+        return Ok(DrmStatus::Unsupported);
+    }
+    let header_len = u32::from_le_bytes(header[12..16].try_into().unwrap());
+    let mut piece = [0u8; 16];
+    reader.seek(SeekFrom::Start(u64::from(header_len) + 16))?;
+    if read_up_to(reader, &mut piece)? < piece.len() {
+        return Ok(DrmStatus::Unsupported);
+    }
+    let offset = u32::from_le_bytes(piece[0..4].try_into().unwrap());
+    let size = u32::from_le_bytes(piece[8..12].try_into().unwrap());
+    if piece[4..8] != [0; 4] || piece[12..16] != [0; 4] || size > MAX_LIT_DIRECTORY {
+        return Ok(DrmStatus::Unsupported);
+    }
+    let mut directory = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(u64::from(offset)))?;
+    let read = read_up_to(reader, &mut directory)?;
+    Ok(if contains(&directory[..read], LIT_OWNER_LICENSE) {
+        DrmStatus::MicrosoftReader
+    } else {
+        DrmStatus::None
+    })
+}
+
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::zip_with;
+    use std::io::Cursor;
+
+    fn encryption_xml(algorithm: &str) -> Vec<u8> {
+        format!(
+            r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+                xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+              <enc:EncryptedData>
+                <enc:EncryptionMethod Algorithm="{algorithm}"/>
+                <enc:CipherData><enc:CipherReference URI="OEBPS/a.xhtml"/></enc:CipherData>
+              </enc:EncryptedData>
+            </encryption>"#
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn classifies_epub_encryption() {
+        let mimetype: (&str, &[u8]) = ("mimetype", b"application/epub+zip");
+        let fonts = encryption_xml("http://www.idpf.org/2008/embedding");
+        let aes = encryption_xml("http://www.w3.org/2001/04/xmlenc#aes128-cbc");
+        let rights: &[u8] = br#"<adept:rights xmlns:adept="http://ns.adobe.com/adept"/>"#;
+
+        let check = |entries: &[(&str, &[u8])]| epub_drm(&mut zip_with(entries)).unwrap();
+        assert_eq!(check(&[mimetype]), DrmStatus::None);
+        assert_eq!(
+            check(&[mimetype, (ENCRYPTION_XML, &fonts)]),
+            DrmStatus::FontObfuscation
+        );
+        assert_eq!(
+            check(&[mimetype, (ENCRYPTION_XML, &aes)]),
+            DrmStatus::Unknown
+        );
+        assert_eq!(
+            check(&[mimetype, (ENCRYPTION_XML, &aes), (RIGHTS_XML, rights)]),
+            DrmStatus::Adept
+        );
+    }
+
+    #[test]
+    fn reads_mobi_encryption_field() {
+        let mobi = |encryption: u16| {
+            let mut book = vec![0u8; PALMDB_HEADER_LEN];
+            book[60..68].copy_from_slice(b"BOOKMOBI");
+            book.extend_from_slice(&88u32.to_be_bytes());
+            book.resize(88, 0);
+            let mut palmdoc = [0u8; 16];
+            palmdoc[12..14].copy_from_slice(&encryption.to_be_bytes());
+            book.extend_from_slice(&palmdoc);
+            Cursor::new(book)
+        };
+        assert_eq!(mobi_drm(&mut mobi(0)).unwrap(), DrmStatus::None);
+        assert_eq!(mobi_drm(&mut mobi(2)).unwrap(), DrmStatus::Mobipocket);
+        assert_eq!(
+            mobi_drm(&mut Cursor::new(b"\xeaDRMION\xee\0\0".to_vec())).unwrap(),
+            DrmStatus::Unknown
+        );
+        assert_eq!(
+            mobi_drm(&mut Cursor::new(b"not a palm database".to_vec())).unwrap(),
+            DrmStatus::Unsupported
+        );
+    }
+
+    #[test]
+    fn finds_lit_owner_license() {
+        let lit = |entries: &[&str]| {
+            let mut book = vec![0u8; 120];
+            book[..8].copy_from_slice(LIT_MAGIC);
+            book[12..16].copy_from_slice(&40u32.to_le_bytes());
+            let listing: Vec<u8> = entries.iter().flat_map(|name| name.bytes()).collect();
+            book[56..60].copy_from_slice(&120u32.to_le_bytes());
+            book[64..68].copy_from_slice(&(listing.len() as u32).to_le_bytes());
+            book.extend(listing);
+            Cursor::new(book)
+        };
+        assert_eq!(
+            lit_drm(&mut lit(&["/data/toc", "/DRMStorage/DRMSealed"])).unwrap(),
+            DrmStatus::None
+        );
+        assert_eq!(
+            lit_drm(&mut lit(&["/data/toc", "/DRMStorage/Licenses/EUL"])).unwrap(),
+            DrmStatus::MicrosoftReader
+        );
+        assert_eq!(
+            lit_drm(&mut Cursor::new(b"BOOKMOBI".to_vec())).unwrap(),
+            DrmStatus::Unsupported
+        );
+    }
+}
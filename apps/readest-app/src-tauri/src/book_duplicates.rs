@@ -0,0 +1,270 @@
+//! `find_duplicates`: group copies of the same book in a library folder so
+//! the UI can offer to clean them up.
+//!
+//! The folder is scanned like `export_library_index` does. Files with the
+//! same book id (see `book_id`) are exact duplicates, whatever their names.
+//! Files whose normalized title and author match but whose ids differ are
+//! only reported as possible duplicates: a re-download, another format, or
+//! another edition. Books missing a title or an author in their metadata
+//! never match on metadata, since file names alone are too weak a signal.
+//!
+//! Hashing every book takes a while, so progress is reported on a channel
+//! and [`cancel_find_duplicates`] stops a running scan.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::{ipc::Channel, AppHandle};
+
+use crate::book_id::book_id;
+use crate::book_metadata::read_metadata;
+use crate::book_rename::split_book_name;
+use crate::dir_scanner::{self, ScannedFile};
+use crate::library_index::BOOK_EXTENSIONS;
+
+/// Progress is reported after this many books, and once at the end.
+const PROGRESS_EVERY: usize = 25;
+
+/// Set by [`cancel_find_duplicates`]; cleared when a scan starts.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKind {
+    /// Same book id: the files hold the same content.
+    Exact,
+    /// Same title and author but different content.
+    Possible,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateBook {
+    pub path: String,
+    /// See [`book_format`].
+    pub format: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// The shared book id for exact duplicates, the normalized
+    /// `title / author` for possible ones.
+    pub key: String,
+    /// Sorted by path.
+    pub books: Vec<DuplicateBook>,
+}
+
+/// How far a scan of the library has got, sent every [`PROGRESS_EVERY`]
+/// books.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    processed: usize,
+    total: usize,
+}
+
+/// What a scanned book is compared on.
+struct BookKeys {
+    book: DuplicateBook,
+    id: String,
+    fingerprint: Option<String>,
+}
+
+/// Lower-case alphanumeric words of `text`, separated by single spaces, so
+/// punctuation, case and spacing don't keep two titles apart.
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `title / author`, both normalized. The author's words are sorted, so
+/// "Frank Herbert" and "Herbert, Frank" match. `None` unless both are
+/// present.
+fn fingerprint(title: Option<&str>, author: Option<&str>) -> Option<String> {
+    let title = normalize(title?);
+    let mut author: Vec<_> = normalize(author?).split(' ').map(str::to_string).collect();
+    author.sort();
+    let author = author.join(" ");
+    (!title.is_empty() && !author.is_empty()).then(|| format!("{title} / {author}"))
+}
+
+/// Lower-case extension of `path`, e.g. `epub` or `fb2.zip`.
+pub(crate) fn book_format(path: &Path) -> String {
+    split_book_name(path).1.to_ascii_lowercase()
+}
+
+fn book_keys(file: &ScannedFile) -> Option<BookKeys> {
+    let path = Path::new(&file.path);
+    let id = book_id(path).ok()?;
+    let metadata = read_metadata(path).unwrap_or_default();
+    Some(BookKeys {
+        book: DuplicateBook {
+            path: file.path.clone(),
+            format: book_format(path),
+            size: file.size,
+        },
+        id,
+        fingerprint: fingerprint(metadata.title.as_deref(), metadata.author.as_deref()),
+    })
+}
+
+/// Exact groups first, then possible ones, each ordered by key. A file can
+/// be in an exact group and a possible one when a third copy differs.
+fn group_duplicates(keys: Vec<BookKeys>) -> Vec<DuplicateGroup> {
+    let mut by_id: BTreeMap<String, Vec<DuplicateBook>> = BTreeMap::new();
+    let mut by_fingerprint: BTreeMap<String, Vec<(String, DuplicateBook)>> = BTreeMap::new();
+    for keys in keys {
+        if let Some(fingerprint) = keys.fingerprint {
+            by_fingerprint
+                .entry(fingerprint)
+                .or_default()
+                .push((keys.id.clone(), keys.book.clone()));
+        }
+        by_id.entry(keys.id).or_default().push(keys.book);
+    }
+
+    let exact = by_id
+        .into_iter()
+        .filter(|(_, books)| books.len() > 1)
+        .map(|(key, books)| (DuplicateKind::Exact, key, books));
+    let possible = by_fingerprint
+        .into_iter()
+        .filter(|(_, books)| {
+            let ids: BTreeSet<_> = books.iter().map(|(id, _)| id).collect();
+            ids.len() > 1
+        })
+        .map(|(key, books)| {
+            let books = books.into_iter().map(|(_, book)| book).collect();
+            (DuplicateKind::Possible, key, books)
+        });
+    exact
+        .chain(possible)
+        .map(|(kind, key, mut books)| {
+            books.sort_by(|a: &DuplicateBook, b| a.path.cmp(&b.path));
+            DuplicateGroup { kind, key, books }
+        })
+        .collect()
+}
+
+/// Run `visit` over `files` in order, keeping the `Some` results. Progress
+/// goes to `on_progress` every [`PROGRESS_EVERY`] books and once at the end.
+/// Returns `None` as soon as `cancelled` is set.
+pub(crate) fn scan_books<T>(
+    files: &[ScannedFile],
+    cancelled: &AtomicBool,
+    mut visit: impl FnMut(&ScannedFile) -> Option<T>,
+    on_progress: impl Fn(ScanProgress),
+) -> Option<Vec<T>> {
+    let total = files.len();
+    let mut results = Vec::with_capacity(total);
+    for (index, file) in files.iter().enumerate() {
+        if cancelled.load(Ordering::SeqCst) {
+            return None;
+        }
+        results.extend(visit(file));
+        let processed = index + 1;
+        if processed % PROGRESS_EVERY == 0 || processed == total {
+            on_progress(ScanProgress { processed, total });
+        }
+    }
+    Some(results)
+}
+
+/// Group the duplicates among `files`. Returns `None` when cancelled.
+fn find(files: &[ScannedFile], on_progress: impl Fn(ScanProgress)) -> Option<Vec<DuplicateGroup>> {
+    scan_books(files, &CANCELLED, book_keys, on_progress).map(group_duplicates)
+}
+
+/// Duplicate books under `root`, exact groups first. Files that can't be
+/// read are left out. Fails with `"cancelled"` after
+/// [`cancel_find_duplicates`].
+#[tauri::command]
+pub async fn find_duplicates(
+    app: AppHandle,
+    root: String,
+    on_progress: Channel<ScanProgress>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    CANCELLED.store(false, Ordering::SeqCst);
+    let extensions = BOOK_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        let files = dir_scanner::read_dir(app, root, true, extensions)?;
+        let on_progress = |progress| {
+            let _ = on_progress.send(progress);
+        };
+        find(&files, on_progress).ok_or_else(|| "cancelled".to_string())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Stop the running [`find_duplicates`]. Does nothing if none is.
+#[tauri::command]
+pub fn cancel_find_duplicates() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scanned, temp_dir};
+
+    fn fb2(title: &str, author: &str, body: &str) -> String {
+        format!(
+            "<FictionBook><description><title-info>\
+             <author><nickname>{author}</nickname></author>\
+             <book-title>{title}</book-title></title-info></description>\
+             <body>{body}</body></FictionBook>"
+        )
+    }
+
+    #[test]
+    fn fingerprints_ignore_case_punctuation_and_name_order() {
+        assert_eq!(
+            fingerprint(Some("Dune: Messiah"), Some("Frank Herbert")),
+            fingerprint(Some("dune  messiah"), Some("Herbert, Frank"))
+        );
+        assert_eq!(fingerprint(Some("Dune"), None), None);
+        assert_eq!(fingerprint(Some("?!"), Some("Frank Herbert")), None);
+    }
+
+    #[test]
+    fn groups_exact_and_possible_duplicates() {
+        let dir = temp_dir("book-duplicates");
+        let original = dir.join("dune.fb2");
+        std::fs::write(&original, fb2("Dune", "Frank Herbert", "Arrakis")).unwrap();
+        let copy = dir.join("dune (1).fb2");
+        std::fs::copy(&original, &copy).unwrap();
+        let edition = dir.join("dune-2nd.fb2");
+        std::fs::write(&edition, fb2("DUNE", "Herbert, Frank", "Arrakis, revised")).unwrap();
+        let other = dir.join("emma.fb2");
+        std::fs::write(&other, fb2("Emma", "Jane Austen", "Highbury")).unwrap();
+        let untitled = dir.join("notes.txt");
+        std::fs::write(&untitled, "Arrakis").unwrap();
+
+        let files: Vec<_> = [&original, &copy, &edition, &other, &untitled]
+            .into_iter()
+            .map(|path| scanned(path))
+            .collect();
+        let groups = find(&files, |_| {}).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0].kind, DuplicateKind::Exact);
+        assert_eq!(groups[0].key, book_id(&original).unwrap());
+        let paths: Vec<_> = groups[0].books.iter().map(|b| b.path.clone()).collect();
+        assert_eq!(paths, [scanned(&copy).path, scanned(&original).path]);
+        assert_eq!(groups[0].books[0].format, "fb2");
+
+        assert_eq!(groups[1].kind, DuplicateKind::Possible);
+        assert_eq!(groups[1].key, "dune / frank herbert");
+        assert_eq!(groups[1].books.len(), 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,83 @@
+//! `compute_book_id`: the content-derived id a book has on every device.
+//!
+//! The id is the partialMD5 from `utils/md5.ts`, which is already the
+//! `Books/<hash>/` directory name and the hash the sync server keys books
+//! by. It hashes up to twelve 1 KiB samples, starting at offsets 0 and
+//! `1024 << 2i` for `i` in `0..=10`. The file size only decides how many
+//! samples exist and where the last one ends; it is not hashed. Mixing it
+//! in would give every book in every existing library a new id.
+//!
+//! The native parsers compute the same digest through
+//! [`compute_partial_md5`]. The golden tests below pin it, so a change to
+//! that function that would re-key libraries fails here first.
+
+use std::path::{Path, PathBuf};
+
+use crate::parser_common::compute_partial_md5;
+
+/// The book id of the file at `path`, as 32 lowercase hex digits.
+pub fn book_id(path: &Path) -> std::io::Result<String> {
+    compute_partial_md5(path)
+}
+
+#[tauri::command]
+pub async fn compute_book_id(path: String) -> Result<String, String> {
+    let path = PathBuf::from(path);
+    tauri::async_runtime::spawn_blocking(move || book_id(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+        .map_err(|e| format!("failed to compute book id: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    /// Deterministic contents, so the golden ids don't depend on fixtures.
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|k| (k.wrapping_mul(31) + (k >> 8)) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn ids_match_the_golden_values() {
+        // Expected values come from running `utils/md5.ts::partialMD5` under
+        // Node on the same buffers. 1_200_000 bytes reaches the sample at
+        // 1 MiB; 300_000 stops after the one at 256 KiB.
+        let cases = [
+            (0, "d41d8cd98f00b204e9800998ecf8427e"),
+            (11, "7f88844cf07e294a2273ae238562cbff"),
+            (5_000, "aaa0089e67235344d2638a97d31b8bb0"),
+            (300_000, "c79e3a462e9603309f040cfc7f422fb6"),
+            (1_200_000, "0fca5c751677ecbc2dcfe80ea75ace32"),
+        ];
+        let dir = temp_dir("book-id-golden");
+        for (len, expected) in cases {
+            let path = dir.join(format!("book-{len}.bin"));
+            std::fs::write(&path, sample(len)).unwrap();
+            assert_eq!(book_id(&path).unwrap(), expected, "length {len}");
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn id_ignores_bytes_between_samples() {
+        let dir = temp_dir("book-id-samples");
+        let mut data = sample(10_000);
+        let original = dir.join("original.bin");
+        std::fs::write(&original, &data).unwrap();
+
+        // 3000 falls between the samples at 1 KiB and 4 KiB.
+        data[3_000] ^= 0xff;
+        let edited = dir.join("edited.bin");
+        std::fs::write(&edited, &data).unwrap();
+        assert_eq!(book_id(&original).unwrap(), book_id(&edited).unwrap());
+
+        data[4_100] ^= 0xff;
+        std::fs::write(&edited, &data).unwrap();
+        assert_ne!(book_id(&original).unwrap(), book_id(&edited).unwrap());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
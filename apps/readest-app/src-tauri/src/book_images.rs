@@ -0,0 +1,483 @@
+// `list_book_images`: every image a book carries, for the illustrations
+// gallery, and `extract_book_image` to fetch one of them lazily.
+//
+// Images per format:
+//   - EPUB: manifest items with an `image/*` media type, in manifest order,
+//     named by their href (relative to the OPF, as `extract_epub_resource`
+//     takes it);
+//   - CBZ/CBR: every image entry of the zip, by path, in name order;
+//   - FB2/FBZ: the `<binary>` blocks with an image content type, by id.
+//
+// Width and height come from the first [`HEADER_PROBE_BYTES`] of each image,
+// so nothing is decoded to pixels; formats the `image` crate can't size from
+// a header (SVG, WebP, …) are listed without dimensions. Listings stop at
+// [`MAX_LISTED_IMAGES`], reporting the full count, so a comic archive with
+// thousands of pages doesn't stall the gallery. Other formats have no images
+// to list and return an empty one.
+
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::book_rename::split_book_name;
+use crate::epub_parser::{
+    local_name, read_epub_resource, read_rootfile_path, read_zip_entry, resolve_relative,
+    strip_xml_bom, EpubResource,
+};
+use crate::parser_common::read_fbz_document;
+
+/// Most images one listing returns.
+const MAX_LISTED_IMAGES: usize = 1000;
+/// Bytes read from each image to find its dimensions; headers (even with
+/// EXIF) sit well inside it.
+const HEADER_PROBE_BYTES: u64 = 64 * 1024;
+/// Largest image [`extract_book_image`] returns from a comic archive.
+const MAX_IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookImage {
+    /// What [`extract_book_image`] takes: the manifest href for EPUB, the
+    /// zip path for comics, the `<binary>` id for FB2.
+    pub href: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Size in bytes (uncompressed, or decoded from base64).
+    pub bytes_len: u64,
+    pub mime: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookImages {
+    /// The first [`MAX_LISTED_IMAGES`] images, in reading order.
+    pub images: Vec<BookImage>,
+    /// Images in the book, including those past the cap.
+    pub total: usize,
+}
+
+#[tauri::command]
+pub async fn list_book_images(path: String) -> Result<BookImages, String> {
+    tauri::async_runtime::spawn_blocking(move || list_book_images_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// One image from [`list_book_images`], by its `href`.
+#[tauri::command]
+pub async fn extract_book_image(path: String, href: String) -> Result<EpubResource, String> {
+    tauri::async_runtime::spawn_blocking(move || extract_book_image_sync(&path, &href))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn list_book_images_sync(file_path: &str) -> Result<BookImages, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let (_, ext) = split_book_name(path);
+    match ext.to_ascii_lowercase().as_str() {
+        "epub" => list_epub_images(&mut open_zip(path)?, MAX_LISTED_IMAGES),
+        "cbz" | "cbr" => list_comic_images(&mut open_zip(path)?, MAX_LISTED_IMAGES),
+        "fb2" => {
+            let bytes = std::fs::read(path).map_err(|e| format!("read failed: {e}"))?;
+            list_fb2_images(&bytes, MAX_LISTED_IMAGES)
+        }
+        "fbz" | "fb2.zip" => {
+            let bytes = read_fbz_document(&mut open_zip(path)?)?;
+            list_fb2_images(&bytes, MAX_LISTED_IMAGES)
+        }
+        _ => Ok(BookImages::default()),
+    }
+}
+
+fn extract_book_image_sync(file_path: &str, href: &str) -> Result<EpubResource, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let (_, ext) = split_book_name(path);
+    match ext.to_ascii_lowercase().as_str() {
+        "epub" => read_epub_resource(&mut open_zip(path)?, href),
+        "cbz" | "cbr" => read_comic_image(&mut open_zip(path)?, href),
+        "fb2" => {
+            let bytes = std::fs::read(path).map_err(|e| format!("read failed: {e}"))?;
+            read_fb2_image(&bytes, href)
+        }
+        "fbz" | "fb2.zip" => {
+            let bytes = read_fbz_document(&mut open_zip(path)?)?;
+            read_fb2_image(&bytes, href)
+        }
+        other => Err(format!("no images in .{other} books")),
+    }
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))
+}
+
+/// Width and height of an image from its first bytes.
+fn header_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(Cursor::new(head))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Size and dimensions of the zip entry `name`, reading only its header.
+fn probe_zip_image<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    name: &str,
+) -> Option<(u64, Option<(u32, u32)>)> {
+    let entry = zip.by_name(name).ok()?;
+    let size = entry.size();
+    let mut head = Vec::new();
+    entry.take(HEADER_PROBE_BYTES).read_to_end(&mut head).ok()?;
+    Some((size, header_dimensions(&head)))
+}
+
+fn image_mime(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        _ => return None,
+    })
+}
+
+fn list_epub_images<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    limit: usize,
+) -> Result<BookImages, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf = read_zip_entry(zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    let items = manifest_images(&opf)?;
+
+    let mut listing = BookImages {
+        total: items.len(),
+        ..Default::default()
+    };
+    for (href, mime) in items.into_iter().take(limit) {
+        let zip_path = resolve_relative(&opf_path, &href);
+        // Manifest items missing from the zip are the book's bug; skip them.
+        let Some((bytes_len, dimensions)) = probe_zip_image(zip, &zip_path) else {
+            listing.total -= 1;
+            continue;
+        };
+        listing.images.push(BookImage {
+            href,
+            width: dimensions.map(|(w, _)| w),
+            height: dimensions.map(|(_, h)| h),
+            bytes_len,
+            mime,
+        });
+    }
+    Ok(listing)
+}
+
+/// `(href, media-type)` of every manifest item with an `image/*` type.
+fn manifest_images(opf: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let normalized = strip_xml_bom(opf);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut items = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if local_name(e.name().as_ref()) == b"item" =>
+            {
+                let mut href = None;
+                let mut media_type = None;
+                for attr in e.attributes().flatten() {
+                    let value = attr
+                        .unescape_value()
+                        .map(|v| v.into_owned())
+                        .unwrap_or_default();
+                    match attr.key.as_ref() {
+                        b"href" => href = Some(value),
+                        b"media-type" => media_type = Some(value.to_ascii_lowercase()),
+                        _ => {}
+                    }
+                }
+                if let (Some(href), Some(mime)) = (href, media_type) {
+                    if mime.starts_with("image/") {
+                        items.push((href, mime));
+                    }
+                }
+            }
+            Ok(Event::End(e)) if local_name(e.name().as_ref()) == b"manifest" => break,
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(items)
+}
+
+fn list_comic_images<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    limit: usize,
+) -> Result<BookImages, String> {
+    let mut names: Vec<String> = zip
+        .file_names()
+        .filter(|name| {
+            // macOS archivers add `__MACOSX/._page.jpg` resource forks.
+            !name.to_ascii_lowercase().starts_with("__macosx/") && image_mime(name).is_some()
+        })
+        .map(str::to_string)
+        .collect();
+    names.sort();
+
+    let mut listing = BookImages {
+        total: names.len(),
+        ..Default::default()
+    };
+    for name in names.into_iter().take(limit) {
+        let Some((bytes_len, dimensions)) = probe_zip_image(zip, &name) else {
+            continue;
+        };
+        listing.images.push(BookImage {
+            mime: image_mime(&name).unwrap_or("image/jpeg").to_string(),
+            href: name,
+            width: dimensions.map(|(w, _)| w),
+            height: dimensions.map(|(_, h)| h),
+            bytes_len,
+        });
+    }
+    Ok(listing)
+}
+
+fn read_comic_image<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    href: &str,
+) -> Result<EpubResource, String> {
+    let mime = image_mime(href).ok_or_else(|| format!("not an image: {href}"))?;
+    let entry = zip
+        .by_name(href)
+        .map_err(|e| format!("entry {href}: {e}"))?;
+    if entry.size() > MAX_IMAGE_BYTES {
+        return Err(format!("image too large: {href}"));
+    }
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry
+        .take(MAX_IMAGE_BYTES)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("read {href}: {e}"))?;
+    Ok(EpubResource {
+        bytes,
+        mime: mime.to_string(),
+    })
+}
+
+/// Every image `<binary>` of an FB2 as `(id, content-type, decoded bytes)`,
+/// in document order.
+fn fb2_binaries(bytes: &[u8]) -> Result<Vec<(String, String, Vec<u8>)>, String> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut binaries = Vec::new();
+    // (id, content type, base64 text) of the `<binary>` being read.
+    let mut current: Option<(String, String, String)> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if local_name(e.name().as_ref()) == b"binary" => {
+                let mut id = None;
+                let mut mime = None;
+                for attr in e.attributes().flatten() {
+                    let value = attr
+                        .unescape_value()
+                        .map(|v| v.into_owned())
+                        .unwrap_or_default();
+                    match local_name(attr.key.as_ref()) {
+                        b"id" => id = Some(value),
+                        b"content-type" => mime = Some(value.to_ascii_lowercase()),
+                        _ => {}
+                    }
+                }
+                if let (Some(id), Some(mime)) = (id, mime) {
+                    if mime.starts_with("image/") {
+                        current = Some((id, mime, String::new()));
+                    }
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let Some((_, _, text)) = current.as_mut() {
+                    text.push_str(&String::from_utf8_lossy(&t));
+                }
+            }
+            Ok(Event::End(e)) if local_name(e.name().as_ref()) == b"binary" => {
+                if let Some((id, mime, text)) = current.take() {
+                    let data: String = text.split_whitespace().collect();
+                    if let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(data) {
+                        binaries.push((id, mime, decoded));
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(binaries)
+}
+
+fn list_fb2_images(bytes: &[u8], limit: usize) -> Result<BookImages, String> {
+    let binaries = fb2_binaries(bytes)?;
+    Ok(BookImages {
+        total: binaries.len(),
+        images: binaries
+            .into_iter()
+            .take(limit)
+            .map(|(href, mime, data)| {
+                let head = &data[..data.len().min(HEADER_PROBE_BYTES as usize)];
+                let dimensions = header_dimensions(head);
+                BookImage {
+                    href,
+                    width: dimensions.map(|(w, _)| w),
+                    height: dimensions.map(|(_, h)| h),
+                    bytes_len: data.len() as u64,
+                    mime,
+                }
+            })
+            .collect(),
+    })
+}
+
+fn read_fb2_image(bytes: &[u8], href: &str) -> Result<EpubResource, String> {
+    let id = href.trim_start_matches('#');
+    fb2_binaries(bytes)?
+        .into_iter()
+        .find(|(binary_id, _, _)| binary_id == id)
+        .map(|(_, mime, bytes)| EpubResource { bytes, mime })
+        .ok_or_else(|| format!("no image with id {id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::zip_with;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn lists_epub_manifest_images_with_dimensions() {
+        let container = br#"<container><rootfiles>
+            <rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+        let opf = br#"<package xmlns="http://www.idpf.org/2007/opf"><manifest>
+            <item id="c" href="images/cover.png" media-type="image/png"/>
+            <item id="t" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+            <item id="m" href="images/missing.jpg" media-type="image/jpeg"/>
+            <item id="s" href="images/map.svg" media-type="image/svg+xml"/>
+        </manifest></package>"#;
+        let cover = png(30, 45);
+        let mut zip = zip_with(&[
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/images/cover.png", &cover),
+            ("OEBPS/images/map.svg", b"<svg/>"),
+        ]);
+        let listing = list_epub_images(&mut zip, 10).unwrap();
+        assert_eq!(listing.total, 2);
+        assert_eq!(
+            listing.images,
+            vec![
+                BookImage {
+                    href: "images/cover.png".into(),
+                    width: Some(30),
+                    height: Some(45),
+                    bytes_len: cover.len() as u64,
+                    mime: "image/png".into(),
+                },
+                BookImage {
+                    href: "images/map.svg".into(),
+                    width: None,
+                    height: None,
+                    bytes_len: 6,
+                    mime: "image/svg+xml".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_comic_pages_up_to_the_cap() {
+        let (page, wide) = (png(10, 16), png(32, 16));
+        let mut zip = zip_with(&[
+            ("p02.png", &wide),
+            ("ComicInfo.xml", b"<ComicInfo/>"),
+            ("__MACOSX/._p01.png", b"fork"),
+            ("p01.png", &page),
+            ("p03.png", &page),
+        ]);
+        let listing = list_comic_images(&mut zip, 2).unwrap();
+        assert_eq!(listing.total, 3);
+        let pages: Vec<_> = listing
+            .images
+            .iter()
+            .map(|i| (i.href.as_str(), i.width, i.height))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![
+                ("p01.png", Some(10), Some(16)),
+                ("p02.png", Some(32), Some(16))
+            ]
+        );
+        assert_eq!(read_comic_image(&mut zip, "p02.png").unwrap().bytes, wide);
+        assert!(read_comic_image(&mut zip, "ComicInfo.xml").is_err());
+    }
+
+    #[test]
+    fn lists_and_reads_fb2_binaries() {
+        let cover = png(8, 12);
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&cover);
+        let (head, tail) = encoded.split_at(10);
+        let fb2 = format!(
+            r#"<FictionBook xmlns:l="http://www.w3.org/1999/xlink">
+  <body><p>Text</p></body>
+  <binary id="cover.png" content-type="image/png">{head}
+    {tail}</binary>
+  <binary id="font" content-type="application/x-font-ttf">AAAA</binary>
+</FictionBook>"#
+        );
+        let listing = list_fb2_images(fb2.as_bytes(), 10).unwrap();
+        assert_eq!(
+            listing,
+            BookImages {
+                images: vec![BookImage {
+                    href: "cover.png".into(),
+                    width: Some(8),
+                    height: Some(12),
+                    bytes_len: cover.len() as u64,
+                    mime: "image/png".into(),
+                }],
+                total: 1,
+            }
+        );
+        assert_eq!(
+            read_fb2_image(fb2.as_bytes(), "#cover.png").unwrap().bytes,
+            cover
+        );
+        assert!(read_fb2_image(fb2.as_bytes(), "font").is_err());
+    }
+}
@@ -0,0 +1,409 @@
+//! `ingest_book` / `ingest_books`: everything the library needs to add a
+//! book, in one call instead of a round-trip each for metadata, cover and
+//! DRM status.
+//!
+//! The file is opened once. Its format is sniffed from the first bytes,
+//! with the extension deciding between formats that look alike (AZW3 and
+//! MOBI, say). The book id is hashed from the open file, and the same
+//! handle, or for ZIP-based books the same archive, feeds the metadata
+//! reader, the DRM check and the cover extraction. MOBI books are read into
+//! memory once, since their parser needs all of it anyway. A cover set with
+//! `set_book_cover` wins over the embedded one, and either is shrunk to the
+//! library thumbnail by `maybe_resize_cover`. Unreadable metadata or a
+//! missing cover don't fail the book; a file that can't be opened does.
+//!
+//! `ingest_books` spreads a folder import over one worker per core and
+//! returns a result per path, in order.
+
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use mobi::Mobi;
+use serde::Serialize;
+use zip::ZipArchive;
+
+use crate::book_cover::read_cover_sidecar;
+use crate::book_drm::{check_drm_sync, epub_drm, lit_drm, mobi_drm, DrmStatus};
+use crate::book_metadata::{
+    epub_metadata, fbz_metadata, mobi_metadata, read_metadata_from_bytes, BookMetadata, Creator,
+    Identifier,
+};
+use crate::book_rename::split_book_name;
+use crate::epub_parser::epub_cover;
+use crate::parser_common::{
+    maybe_resize_cover, partial_md5_of, sniff_extension, sniff_zip_archive, RawCoverImage,
+};
+
+/// Bytes read to sniff the format; enough for every signature we check.
+const HEAD_LEN: u64 = 1024;
+/// Most workers [`ingest_books`] runs, however many cores there are: past
+/// this the disk, not the CPU, is the bottleneck.
+const MAX_INGEST_WORKERS: usize = 8;
+const MOBI_EXTENSIONS: [&str; 6] = ["mobi", "azw", "azw3", "kf8", "prc", "pdb"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookIngest {
+    pub path: String,
+    /// Lower-case format, e.g. `epub`, `azw3` or `fb2.zip`.
+    pub format: String,
+    pub book_id: String,
+    pub size: u64,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub creators: Vec<Creator>,
+    pub publisher: Option<String>,
+    pub identifiers: Vec<Identifier>,
+    pub language: Option<String>,
+    pub drm: DrmStatus,
+    /// Library thumbnail of the cover, if one was found.
+    pub cover: Option<RawCoverImage>,
+}
+
+/// One path of [`ingest_books`]: the book, or why it couldn't be read.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResult {
+    pub path: String,
+    pub book: Option<BookIngest>,
+    pub error: Option<String>,
+}
+
+/// What a format reader found, before the cover is shrunk.
+#[derive(Default)]
+struct Contents {
+    metadata: BookMetadata,
+    drm: Option<DrmStatus>,
+    cover: Option<RawCoverImage>,
+}
+
+/// The book's format: its extension, unless the content says it is
+/// something else, like an EPUB saved as `.zip`. A ZIP the sniffer can only
+/// call a comic keeps its extension unless that is plain `zip`.
+fn detect_format(ext: &str, sniffed: Option<&'static str>) -> String {
+    match sniffed {
+        Some("mobi") if MOBI_EXTENSIONS.contains(&ext) => ext.to_string(),
+        Some("fbz") if ext == "fb2.zip" => ext.to_string(),
+        Some("cbz") if ext != "zip" => ext.to_string(),
+        Some("txt") | None => ext.to_string(),
+        Some(sniffed) => sniffed.to_string(),
+    }
+}
+
+fn metadata_or_default(path: &Path, metadata: Result<BookMetadata, String>) -> BookMetadata {
+    metadata.unwrap_or_else(|e| {
+        log::debug!("No metadata for {}: {e}", path.display());
+        BookMetadata::default()
+    })
+}
+
+fn read_zip_book<R: Read + Seek>(
+    path: &Path,
+    format: &str,
+    zip: &mut ZipArchive<R>,
+    want_cover: bool,
+) -> Result<Contents, String> {
+    Ok(match format {
+        "epub" => Contents {
+            metadata: metadata_or_default(path, epub_metadata(zip)),
+            drm: Some(epub_drm(zip)?),
+            cover: want_cover.then(|| epub_cover(zip).ok()).flatten(),
+        },
+        "fbz" | "fb2.zip" => Contents {
+            metadata: metadata_or_default(path, fbz_metadata(zip)),
+            drm: Some(DrmStatus::None),
+            cover: None,
+        },
+        _ => Contents::default(),
+    })
+}
+
+fn read_plain_book(
+    path: &Path,
+    format: &str,
+    mut file: File,
+    size: u64,
+    want_cover: bool,
+) -> Result<Contents, String> {
+    let read_all = |file: &mut File| {
+        let mut bytes = Vec::with_capacity(size as usize);
+        file.rewind()
+            .and_then(|()| file.read_to_end(&mut bytes))
+            .map(|_| bytes)
+            .map_err(|e| format!("read failed: {e}"))
+    };
+    Ok(match format {
+        _ if MOBI_EXTENSIONS.contains(&format) => {
+            let bytes = read_all(&mut file)?;
+            let drm =
+                mobi_drm(&mut Cursor::new(&bytes)).map_err(|e| format!("read failed: {e}"))?;
+            match Mobi::from_read(bytes.as_slice()) {
+                Ok(mobi) => Contents {
+                    metadata: mobi_metadata(&mobi),
+                    drm: Some(drm),
+                    cover: want_cover
+                        .then(|| crate::mobi_parser::extract_cover(&mobi))
+                        .flatten(),
+                },
+                Err(e) => {
+                    log::debug!("No metadata for {}: {e}", path.display());
+                    Contents {
+                        drm: Some(drm),
+                        ..Default::default()
+                    }
+                }
+            }
+        }
+        "fb2" => {
+            let bytes = read_all(&mut file)?;
+            Contents {
+                metadata: metadata_or_default(path, read_metadata_from_bytes("fb2", &bytes)),
+                ..Default::default()
+            }
+        }
+        "lit" => Contents {
+            drm: Some(lit_drm(&mut file).map_err(|e| format!("read failed: {e}"))?),
+            ..Default::default()
+        },
+        _ => Contents::default(),
+    })
+}
+
+/// Everything [`BookIngest`] holds for the book at `path`.
+fn ingest(path: &Path) -> Result<BookIngest, String> {
+    if !path.is_file() {
+        return Err(format!("file not found: {}", path.display()));
+    }
+    let mut file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("stat failed: {e}"))?
+        .len();
+    let book_id =
+        partial_md5_of(&mut file, size).map_err(|e| format!("partial_md5 failed: {e}"))?;
+    let mut head = Vec::new();
+    file.rewind()
+        .and_then(|()| {
+            Read::by_ref(&mut file)
+                .take(HEAD_LEN)
+                .read_to_end(&mut head)
+        })
+        .map_err(|e| format!("read failed: {e}"))?;
+    let (_, ext) = split_book_name(path);
+    let ext = ext.to_ascii_lowercase();
+
+    let sidecar = read_cover_sidecar(path);
+    let want_cover = sidecar.is_none();
+    let (format, contents) = if head.starts_with(b"PK\x03\x04") {
+        let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+        let format = detect_format(&ext, Some(sniff_zip_archive(&mut zip)));
+        let contents = read_zip_book(path, &format, &mut zip, want_cover)?;
+        (format, contents)
+    } else {
+        let format = detect_format(&ext, sniff_extension(&head));
+        let contents = read_plain_book(path, &format, file, size, want_cover)?;
+        (format, contents)
+    };
+    let drm = match contents.drm {
+        Some(drm) => drm,
+        None => check_drm_sync(&path.to_string_lossy(), &format)?,
+    };
+    let cover = sidecar.or(contents.cover).map(|cover| {
+        let (bytes, mime) = maybe_resize_cover(cover.bytes, &cover.mime);
+        RawCoverImage { bytes, mime }
+    });
+
+    let metadata = contents.metadata;
+    Ok(BookIngest {
+        path: path.to_string_lossy().into_owned(),
+        format,
+        book_id,
+        size,
+        title: metadata.title,
+        author: metadata.author,
+        creators: metadata.creators,
+        publisher: metadata.publisher,
+        identifiers: metadata.identifiers,
+        language: metadata.language,
+        drm,
+        cover,
+    })
+}
+
+/// [`ingest`] every path on `workers` threads taking paths from a shared
+/// queue. Results come back in the order of `paths`.
+fn ingest_all(paths: &[String], workers: usize) -> Vec<IngestResult> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<IngestResult>> =
+        std::iter::repeat_with(|| None).take(paths.len()).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers.clamp(1, paths.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(index) else {
+                            break;
+                        };
+                        let result = ingest(Path::new(path));
+                        done.push((index, result));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            // A panicking worker leaves its paths unset; they fail below.
+            if let Ok(done) = handle.join() {
+                for (index, result) in done {
+                    let path = paths[index].clone();
+                    results[index] = Some(match result {
+                        Ok(book) => IngestResult {
+                            path,
+                            book: Some(book),
+                            error: None,
+                        },
+                        Err(error) => IngestResult {
+                            path,
+                            book: None,
+                            error: Some(error),
+                        },
+                    });
+                }
+            }
+        }
+    });
+    results
+        .into_iter()
+        .zip(paths)
+        .map(|(result, path)| {
+            result.unwrap_or_else(|| IngestResult {
+                path: path.clone(),
+                book: None,
+                error: Some("ingest worker panicked".to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Format, book id, metadata, DRM status and cover thumbnail of the book at
+/// `path`, from a single pass over the file.
+#[tauri::command]
+pub async fn ingest_book(path: String) -> Result<BookIngest, String> {
+    tauri::async_runtime::spawn_blocking(move || ingest(Path::new(&path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// [`ingest_book`] for many paths at once, in parallel. Each path gets its
+/// own result, in the order given; one unreadable book doesn't fail the
+/// others.
+#[tauri::command]
+pub async fn ingest_books(paths: Vec<String>) -> Result<Vec<IngestResult>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+        ingest_all(&paths, cores.min(MAX_INGEST_WORKERS))
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book_id::book_id;
+    use crate::test_support::{temp_dir, write_epub};
+    use image::{Rgb, RgbImage};
+
+    fn write_sample_epub(path: &Path) {
+        let mut cover = Vec::new();
+        RgbImage::from_pixel(600, 900, Rgb([200, 40, 40]))
+            .write_to(&mut Cursor::new(&mut cover), image::ImageFormat::Png)
+            .unwrap();
+        let opf = br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Dune</dc:title><dc:creator>Frank Herbert</dc:creator>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="cover" href="cover.png" media-type="image/png" properties="cover-image"/>
+  </manifest>
+  <spine/>
+</package>"#;
+        write_epub(path, opf, &[("OEBPS/cover.png", &cover)]);
+    }
+
+    #[test]
+    fn format_follows_content_then_extension() {
+        assert_eq!(detect_format("zip", Some("epub")), "epub");
+        assert_eq!(detect_format("azw3", Some("mobi")), "azw3");
+        assert_eq!(detect_format("epub", Some("mobi")), "mobi");
+        assert_eq!(detect_format("fb2.zip", Some("fbz")), "fb2.zip");
+        assert_eq!(detect_format("zip", Some("cbz")), "cbz");
+        assert_eq!(detect_format("kfx-zip", Some("cbz")), "kfx-zip");
+        assert_eq!(detect_format("md", Some("txt")), "md");
+    }
+
+    #[test]
+    fn ingests_everything_in_one_pass() {
+        let dir = temp_dir("book-ingest-epub");
+        // Saved with the wrong extension: the content decides.
+        let book = dir.join("dune.zip");
+        write_sample_epub(&book);
+
+        let ingest = ingest(&book).unwrap();
+        assert_eq!(ingest.format, "epub");
+        assert_eq!(ingest.book_id, book_id(&book).unwrap());
+        assert_eq!(ingest.size, std::fs::metadata(&book).unwrap().len());
+        assert_eq!(ingest.title.as_deref(), Some("Dune"));
+        assert_eq!(ingest.author.as_deref(), Some("Frank Herbert"));
+        assert_eq!(ingest.language.as_deref(), Some("en"));
+        assert_eq!(ingest.drm, DrmStatus::None);
+        let cover = ingest.cover.unwrap();
+        assert_eq!(cover.mime, "image/jpeg");
+        let thumbnail = image::load_from_memory(&cover.bytes).unwrap();
+        assert_eq!(thumbnail.height(), 512);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn batch_keeps_order_and_reports_failures() {
+        let dir = temp_dir("book-ingest-batch");
+        let epub = dir.join("dune.epub");
+        write_sample_epub(&epub);
+        let fb2 = dir.join("emma.fb2");
+        std::fs::write(
+            &fb2,
+            "<FictionBook><description><title-info><book-title>Emma</book-title>\
+             </title-info></description><body><p>Highbury</p></body></FictionBook>",
+        )
+        .unwrap();
+        let paths: Vec<String> = [&epub, &dir.join("missing.epub"), &fb2]
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        let results = ingest_all(&paths, 2);
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].book.as_ref().unwrap().title.as_deref(),
+            Some("Dune")
+        );
+        assert!(results[1].book.is_none());
+        assert!(results[1].error.as_deref().unwrap().contains("not found"));
+        let emma = results[2].book.as_ref().unwrap();
+        assert_eq!(
+            (emma.format.as_str(), emma.title.as_deref()),
+            ("fb2", Some("Emma"))
+        );
+        assert_eq!(emma.drm, DrmStatus::None);
+        assert!(emma.cover.is_none());
+        for (result, path) in results.iter().zip(&paths) {
+            assert_eq!(&result.path, path);
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,718 @@
+// `detect_book_language`: the language a book declares and, when that is
+// missing or doubtful, the language its text is actually written in, so the
+// reader can pick hyphenation patterns and a TTS voice before opening it.
+//
+// The declared language comes from the first OPF `dc:language` (EPUB), the
+// EXTH 524 record or MobiHeader locale (MOBI/AZW), or `<title-info><lang>`
+// (FB2), normalized to a BCP-47 tag. A declaration is doubtful when it is
+// absent, unparseable, a placeholder such as `und`, or plain English, which
+// authoring tools write by default. In that case the first
+// [`SAMPLE_CHARS`] characters of body text are run through `whatlang`.
+// Both tags are returned so the UI can warn on a mismatch. Plain text has
+// no declaration and is only detected; its encoding is taken from a BOM or
+// guessed by `windows_thumbnail::decode_text_sample`, and reported too.
+// Formats whose text isn't easily reached (PDF, comics) report what they
+// declare, which is nothing.
+
+use mobi::headers::{ExthRecord, Language};
+use mobi::Mobi;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use windows_thumbnail::decode_text_sample;
+use zip::ZipArchive;
+
+use crate::book_rename::split_book_name;
+use crate::epub_parser::{
+    local_name, local_name_eq, read_rootfile_path, read_zip_entry, resolve_relative,
+    spine_documents, strip_xml_bom,
+};
+use crate::parser_common::read_fbz_document;
+
+/// Characters of body text handed to the detector. Enough for a confident
+/// guess; more only costs time.
+const SAMPLE_CHARS: usize = 4000;
+/// Below this the detector's guess isn't worth reporting.
+const MIN_SAMPLE_CHARS: usize = 40;
+/// Most of a plain-text file read for the sample.
+const MAX_TXT_BYTES: u64 = 64 * 1024;
+/// Most of an HTML document scanned for the sample.
+const MAX_HTML_SCAN: usize = 1024 * 1024;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookLanguage {
+    /// What the book says it is written in, as BCP-47.
+    pub declared: Option<String>,
+    /// What its text looks like, as BCP-47. Only set when the declaration
+    /// is missing or doubtful.
+    pub detected: Option<String>,
+    /// The detector's confidence in `detected`, 0–1; 0 when nothing was
+    /// detected.
+    pub confidence: f32,
+    /// Character encoding of a plain-text book (WHATWG name, e.g. `UTF-8`,
+    /// `UTF-16LE`, `windows-1251`); `None` for other formats.
+    pub encoding: Option<String>,
+}
+
+#[tauri::command]
+pub async fn detect_book_language(path: String) -> Result<BookLanguage, String> {
+    tauri::async_runtime::spawn_blocking(move || detect_book_language_sync(Path::new(&path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn detect_book_language_sync(path: &Path) -> Result<BookLanguage, String> {
+    if !path.is_file() {
+        return Err(format!("file not found: {}", path.display()));
+    }
+    let (_, ext) = split_book_name(path);
+    let ext = ext.to_ascii_lowercase();
+    let mut encoding = None;
+    // The declared tag, and a way to get a text sample if it is needed.
+    let (declared, sample): (Option<String>, Box<dyn FnOnce() -> String>) = match ext.as_str() {
+        "epub" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            let opf_path =
+                read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+            let opf_bytes = read_zip_entry(&mut zip, &opf_path)
+                .map_err(|e| format!("read opf {opf_path}: {e}"))?;
+            let opf = parse_opf(&opf_bytes)?;
+            let declared = opf.language.as_deref().and_then(normalize_tag);
+            (
+                declared,
+                Box::new(move || epub_sample(&mut zip, &opf_path, &opf)),
+            )
+        }
+        "mobi" | "azw" | "azw3" | "prc" => {
+            let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
+            let declared = mobi_language(&mobi);
+            (
+                declared,
+                Box::new(move || html_text_sample(&mobi.content_as_string_lossy())),
+            )
+        }
+        "fb2" => {
+            let bytes = std::fs::read(path).map_err(|e| format!("read failed: {e}"))?;
+            (fb2_language(&bytes), Box::new(move || fb2_sample(&bytes)))
+        }
+        "fbz" | "fb2.zip" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            let bytes = read_fbz_document(&mut zip)?;
+            (fb2_language(&bytes), Box::new(move || fb2_sample(&bytes)))
+        }
+        "txt" => {
+            let mut bytes = Vec::new();
+            File::open(path)
+                .and_then(|file| file.take(MAX_TXT_BYTES).read_to_end(&mut bytes))
+                .map_err(|e| format!("read failed: {e}"))?;
+            let complete = (bytes.len() as u64) < MAX_TXT_BYTES;
+            let sample = decode_text_sample(&bytes, complete);
+            encoding = Some(sample.encoding.to_string());
+            (
+                None,
+                Box::new(move || truncate_chars(&sample.text, SAMPLE_CHARS)),
+            )
+        }
+        _ => return Ok(BookLanguage::default()),
+    };
+
+    if !is_doubtful(declared.as_deref()) {
+        return Ok(BookLanguage {
+            declared,
+            encoding,
+            ..Default::default()
+        });
+    }
+    let (detected, confidence) = detect(&sample()).unzip();
+    Ok(BookLanguage {
+        declared,
+        detected,
+        confidence: confidence.unwrap_or(0.0),
+        encoding,
+    })
+}
+
+/// Language of `text` as BCP-47, with the detector's confidence.
+fn detect(text: &str) -> Option<(String, f32)> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_SAMPLE_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(text)?;
+    Some((
+        whatlang_tag(info.lang()).to_string(),
+        info.confidence() as f32,
+    ))
+}
+
+/// Whether a declared tag needs checking against the text.
+fn is_doubtful(declared: Option<&str>) -> bool {
+    let Some(tag) = declared else {
+        return true;
+    };
+    let primary = tag.split('-').next().unwrap_or(tag);
+    // `und`, `mul`, `zxx` and `mis` say nothing about the text; `en` is what
+    // many authoring tools fill in when the author didn't choose.
+    matches!(primary, "und" | "mul" | "zxx" | "mis" | "en")
+}
+
+/// ISO 639-2 codes of languages that also have a two-letter ISO 639-1 code,
+/// which BCP-47 requires instead. Bibliographic (`fre`) and terminological
+/// (`fra`) forms both appear in the wild.
+const ISO_639_2_TO_1: &[(&str, &str)] = &[
+    ("afr", "af"),
+    ("aka", "ak"),
+    ("alb", "sq"),
+    ("amh", "am"),
+    ("ara", "ar"),
+    ("arm", "hy"),
+    ("aze", "az"),
+    ("baq", "eu"),
+    ("bel", "be"),
+    ("ben", "bn"),
+    ("bul", "bg"),
+    ("bur", "my"),
+    ("cat", "ca"),
+    ("ces", "cs"),
+    ("chi", "zh"),
+    ("cym", "cy"),
+    ("cze", "cs"),
+    ("dan", "da"),
+    ("deu", "de"),
+    ("dut", "nl"),
+    ("ell", "el"),
+    ("eng", "en"),
+    ("epo", "eo"),
+    ("est", "et"),
+    ("eus", "eu"),
+    ("fas", "fa"),
+    ("fin", "fi"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("geo", "ka"),
+    ("ger", "de"),
+    ("gle", "ga"),
+    ("gre", "el"),
+    ("guj", "gu"),
+    ("heb", "he"),
+    ("hin", "hi"),
+    ("hrv", "hr"),
+    ("hun", "hu"),
+    ("hye", "hy"),
+    ("ice", "is"),
+    ("ind", "id"),
+    ("isl", "is"),
+    ("ita", "it"),
+    ("jav", "jv"),
+    ("jpn", "ja"),
+    ("kan", "kn"),
+    ("kat", "ka"),
+    ("khm", "km"),
+    ("kor", "ko"),
+    ("lat", "la"),
+    ("lav", "lv"),
+    ("lit", "lt"),
+    ("mac", "mk"),
+    ("mal", "ml"),
+    ("mar", "mr"),
+    ("may", "ms"),
+    ("mkd", "mk"),
+    ("msa", "ms"),
+    ("mya", "my"),
+    ("nep", "ne"),
+    ("nld", "nl"),
+    ("nob", "nb"),
+    ("nor", "no"),
+    ("ori", "or"),
+    ("pan", "pa"),
+    ("per", "fa"),
+    ("pol", "pl"),
+    ("por", "pt"),
+    ("ron", "ro"),
+    ("rum", "ro"),
+    ("rus", "ru"),
+    ("sin", "si"),
+    ("slk", "sk"),
+    ("slo", "sk"),
+    ("slv", "sl"),
+    ("sna", "sn"),
+    ("spa", "es"),
+    ("sqi", "sq"),
+    ("srp", "sr"),
+    ("swe", "sv"),
+    ("tam", "ta"),
+    ("tel", "te"),
+    ("tgl", "tl"),
+    ("tha", "th"),
+    ("tuk", "tk"),
+    ("tur", "tr"),
+    ("ukr", "uk"),
+    ("urd", "ur"),
+    ("uzb", "uz"),
+    ("vie", "vi"),
+    ("wel", "cy"),
+    ("yid", "yi"),
+    ("zho", "zh"),
+    ("zul", "zu"),
+];
+
+/// `raw` as a BCP-47 tag in canonical case (`pt-BR`, `zh-Hant`), with
+/// three-letter codes shortened where a two-letter one exists. `None` when
+/// it doesn't look like a language tag at all.
+fn normalize_tag(raw: &str) -> Option<String> {
+    let mut subtags = raw.trim().split(['-', '_']);
+    let primary = subtags.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&primary.len()) || !primary.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    let primary = ISO_639_2_TO_1
+        .iter()
+        .find(|(long, _)| *long == primary)
+        .map_or(primary.clone(), |(_, short)| short.to_string());
+
+    let mut tag = primary;
+    for subtag in subtags {
+        if subtag.is_empty()
+            || subtag.len() > 8
+            || !subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+        tag.push('-');
+        match subtag.len() {
+            // Script: title case.
+            4 if subtag.bytes().all(|b| b.is_ascii_alphabetic()) => {
+                tag.push_str(&subtag[..1].to_ascii_uppercase());
+                tag.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            // Region: upper case.
+            2 => tag.push_str(&subtag.to_ascii_uppercase()),
+            _ => tag.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+    Some(tag)
+}
+
+/// BCP-47 tag of a `whatlang` language, whose codes are ISO 639-3.
+fn whatlang_tag(lang: whatlang::Lang) -> &'static str {
+    match lang.code() {
+        // Mandarin is written `zh`, Iranian Persian `fa`.
+        "cmn" => "zh",
+        "pes" => "fa",
+        code => ISO_639_2_TO_1
+            .iter()
+            .find(|(long, _)| *long == code)
+            .map_or(code, |(_, short)| short),
+    }
+}
+
+/// EXTH 524 when present, else the MobiHeader locale.
+pub(crate) fn mobi_language(mobi: &Mobi) -> Option<String> {
+    let exth = mobi
+        .metadata
+        .exth
+        .get_record(ExthRecord::Language)
+        .and_then(|records| records.first())
+        .and_then(|bytes| normalize_tag(&String::from_utf8_lossy(bytes)));
+    exth.or_else(|| mobi_locale_tag(mobi.language()).map(str::to_string))
+}
+
+/// Whether `tag` is a language written right to left: Arabic, Hebrew,
+/// Persian, Urdu and the others using those scripts, or any language
+/// tagged with one of them as its script.
+pub(crate) fn is_rtl_language(tag: &str) -> bool {
+    let mut subtags = tag.split(['-', '_']);
+    let primary = subtags.next().unwrap_or("").to_ascii_lowercase();
+    let script_rtl = subtags.any(|subtag| {
+        ["arab", "hebr", "syrc", "thaa", "nkoo", "adlm"]
+            .iter()
+            .any(|script| subtag.eq_ignore_ascii_case(script))
+    });
+    script_rtl
+        || matches!(
+            primary.as_str(),
+            "ar" | "arc"
+                | "ckb"
+                | "dv"
+                | "fa"
+                | "he"
+                | "iw"
+                | "ji"
+                | "ks"
+                | "ps"
+                | "sd"
+                | "syr"
+                | "ug"
+                | "ur"
+                | "yi"
+        )
+}
+
+fn mobi_locale_tag(language: Language) -> Option<&'static str> {
+    use Language::*;
+    Some(match language {
+        Afrikaans => "af",
+        Albanian => "sq",
+        Arabic => "ar",
+        Armenian => "hy",
+        Assamese => "as",
+        Azeri => "az",
+        Basque => "eu",
+        Belarusian => "be",
+        Bengali => "bn",
+        Bulgarian => "bg",
+        Catalan => "ca",
+        Chinese => "zh",
+        Czech => "cs",
+        Danish => "da",
+        Dutch => "nl",
+        English => "en",
+        Estonian => "et",
+        Faeroese => "fo",
+        Farsi => "fa",
+        Finnish => "fi",
+        French => "fr",
+        Georgian => "ka",
+        German => "de",
+        Greek => "el",
+        Gujarati => "gu",
+        Hebrew => "he",
+        Hindi => "hi",
+        Hungarian => "hu",
+        Icelandic => "is",
+        Indonesian => "id",
+        Italian => "it",
+        Japanese => "ja",
+        Kannada => "kn",
+        Kazak => "kk",
+        Konkani => "kok",
+        Korean => "ko",
+        Latvian => "lv",
+        Lithuanian => "lt",
+        Macedonian => "mk",
+        Malay => "ms",
+        Malayalam => "ml",
+        Maltese => "mt",
+        Marathi => "mr",
+        Nepali => "ne",
+        Norwegian => "no",
+        Oriya => "or",
+        Polish => "pl",
+        Portuguese => "pt",
+        Punjabi => "pa",
+        Rhaetoromanic => "rm",
+        Romanian => "ro",
+        Russian => "ru",
+        Sami => "se",
+        Sanskrit => "sa",
+        Serbian => "sr",
+        Slovak => "sk",
+        Slovenian => "sl",
+        Sorbian => "hsb",
+        Spanish => "es",
+        Sutu => "st",
+        Swahili => "sw",
+        Swedish => "sv",
+        Tamil => "ta",
+        Tatar => "tt",
+        Telugu => "te",
+        Thai => "th",
+        Tsonga => "ts",
+        Tswana => "tn",
+        Turkish => "tr",
+        Ukrainian => "uk",
+        Urdu => "ur",
+        Uzbek => "uz",
+        Vietnamese => "vi",
+        Xhosa => "xh",
+        Zulu => "zu",
+        Neutral | Unknown => return None,
+    })
+}
+
+/// What the OPF contributes: the first `dc:language` and the XHTML spine.
+#[derive(Debug, Default)]
+struct OpfLanguage {
+    language: Option<String>,
+    /// Hrefs of the XHTML spine items, in reading order.
+    spine: Vec<String>,
+}
+
+fn parse_opf(opf_bytes: &[u8]) -> Result<OpfLanguage, String> {
+    let normalized = strip_xml_bom(opf_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut language: Option<String> = None;
+    let mut in_language = false;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if local_name_eq(e.name().as_ref(), b"language") => {
+                in_language = language.is_none();
+                text.clear();
+            }
+            Ok(Event::Text(t)) if in_language => {
+                text.push_str(&t.unescape().map_err(|e| format!("xml: {e}"))?);
+            }
+            Ok(Event::End(e)) if in_language && local_name_eq(e.name().as_ref(), b"language") => {
+                in_language = false;
+                let value = text.trim();
+                if !value.is_empty() {
+                    language = Some(value.to_string());
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let spine = spine_documents(opf_bytes)?;
+    Ok(OpfLanguage { language, spine })
+}
+
+/// Body text from the start of the spine. Title and copyright pages are
+/// short, so they only contribute a little before the first chapter.
+fn epub_sample<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    opf_path: &str,
+    opf: &OpfLanguage,
+) -> String {
+    let mut sample = String::new();
+    for href in &opf.spine {
+        let Ok(bytes) = read_zip_entry(zip, &resolve_relative(opf_path, href)) else {
+            continue;
+        };
+        let text = html_text_sample(&String::from_utf8_lossy(&strip_xml_bom(&bytes)));
+        if !text.is_empty() {
+            sample.push_str(&text);
+            sample.push(' ');
+        }
+        if sample.chars().count() >= SAMPLE_CHARS {
+            break;
+        }
+    }
+    truncate_chars(&sample, SAMPLE_CHARS)
+}
+
+/// `<description><title-info><lang>`.
+fn fb2_language(bytes: &[u8]) -> Option<String> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut in_title_info = false;
+    let mut in_lang = false;
+    let mut text = String::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match local_name(e.name().as_ref()) {
+                b"title-info" => in_title_info = true,
+                b"lang" if in_title_info => in_lang = true,
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_lang => text.push_str(&t.unescape().ok()?),
+            Ok(Event::End(e)) => match local_name(e.name().as_ref()) {
+                b"lang" if in_lang => return normalize_tag(&text),
+                b"title-info" => return None,
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Text of the first `<body>`, which holds the main flow.
+fn fb2_sample(bytes: &[u8]) -> String {
+    let xml = String::from_utf8_lossy(&strip_xml_bom(bytes)).into_owned();
+    let start = xml.find("<body").unwrap_or(0);
+    let end = xml[start..]
+        .find("</body>")
+        .map_or(xml.len(), |i| start + i);
+    html_text_sample(&xml[start..end])
+}
+
+/// Up to [`SAMPLE_CHARS`] characters of visible text in an HTML or XML
+/// document: tags dropped, `<head>`, `<script>` and `<style>` skipped,
+/// common entities decoded, whitespace collapsed.
+fn html_text_sample(html: &str) -> String {
+    // A whole MOBI book comes in as one string; the sample is near its start.
+    let mut scan = html.len().min(MAX_HTML_SCAN);
+    while !html.is_char_boundary(scan) {
+        scan -= 1;
+    }
+    let html = &html[..scan];
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::new();
+    let mut count = 0;
+    let mut pos = 0;
+    while pos < html.len() && count < SAMPLE_CHARS {
+        let Some(open) = html[pos..].find('<').map(|i| pos + i) else {
+            push_text(&mut out, &html[pos..]);
+            break;
+        };
+        count += push_text(&mut out, &html[pos..open]);
+        let tag_end = html[open..].find('>').map_or(html.len(), |i| open + i);
+        let tag = &lower[open + 1..tag_end];
+        let name: String = tag
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == ':')
+            .collect();
+        pos = (tag_end + 1).min(html.len());
+        // Skip the contents of elements that aren't read.
+        if matches!(name.as_str(), "head" | "script" | "style") && !tag.ends_with('/') {
+            let closing = format!("</{name}");
+            pos = lower[pos..].find(&closing).map_or(html.len(), |i| pos + i);
+        } else {
+            out.push(' ');
+        }
+    }
+    truncate_chars(
+        &out.split_whitespace().collect::<Vec<_>>().join(" "),
+        SAMPLE_CHARS,
+    )
+}
+
+/// Append `text` with entities decoded; returns the characters added.
+fn push_text(out: &mut String, text: &str) -> usize {
+    let decoded = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    out.push_str(&decoded);
+    decoded.chars().count()
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRENCH: &str = "Longtemps, je me suis couché de bonne heure. Parfois, à peine \
+        ma bougie éteinte, mes yeux se fermaient si vite que je n'avais pas le temps \
+        de me dire : « Je m'endors. » Et, une demi-heure après, la pensée qu'il était \
+        temps de chercher le sommeil m'éveillait.";
+
+    #[test]
+    fn normalizes_declared_tags() {
+        assert_eq!(normalize_tag("en-us").as_deref(), Some("en-US"));
+        assert_eq!(normalize_tag(" fre ").as_deref(), Some("fr"));
+        assert_eq!(normalize_tag("zh_hant_tw").as_deref(), Some("zh-Hant-TW"));
+        assert_eq!(normalize_tag("ger").as_deref(), Some("de"));
+        assert_eq!(normalize_tag("haw").as_deref(), Some("haw"));
+        assert_eq!(normalize_tag("English"), None);
+        assert_eq!(normalize_tag(""), None);
+
+        assert!(is_doubtful(None));
+        assert!(is_doubtful(Some("und")));
+        assert!(is_doubtful(Some("en-GB")));
+        assert!(!is_doubtful(Some("fr")));
+    }
+
+    #[test]
+    fn reads_opf_language_and_spine() {
+        let opf = br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:language>en</dc:language>
+    <dc:language>fr</dc:language>
+  </metadata>
+  <manifest>
+    <item id="css" href="style.css" media-type="text/css"/>
+    <item id="c2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="c1"/><itemref idref="c2"/></spine>
+</package>"#;
+        let parsed = parse_opf(opf).unwrap();
+        assert_eq!(parsed.language.as_deref(), Some("en"));
+        assert_eq!(parsed.spine, ["text/ch1.xhtml", "text/ch2.xhtml"]);
+    }
+
+    #[test]
+    fn samples_visible_text_only() {
+        let html = "<html><head><title>Titre</title><style>p { color: red }</style></head>\
+            <body><p>Bonjour&nbsp;le <em>monde</em></p><script>var x = 1;</script>\
+            <p>Fin &amp; suite</p></body></html>";
+        assert_eq!(html_text_sample(html), "Bonjour le monde Fin & suite");
+    }
+
+    #[test]
+    fn detects_the_text_language() {
+        let (tag, confidence) = detect(FRENCH).unwrap();
+        assert_eq!(tag, "fr");
+        assert!(confidence > 0.5, "confidence {confidence}");
+        assert_eq!(detect("Too short."), None);
+    }
+
+    #[test]
+    fn doubtful_declarations_are_checked_against_the_text() {
+        let dir =
+            std::env::temp_dir().join(format!("readest-book-language-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let fb2 = |lang: &str| {
+            format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+                 <FictionBook xmlns=\"http://www.gribuser.ru/xml/fictionbook/2.0\">\
+                 <description><title-info><lang>{lang}</lang></title-info></description>\
+                 <body><section><p>{FRENCH}</p></section></body></FictionBook>"
+            )
+        };
+        let declared_en = dir.join("mislabelled.fb2");
+        std::fs::write(&declared_en, fb2("en")).unwrap();
+        let report = detect_book_language_sync(&declared_en).unwrap();
+        assert_eq!(report.declared.as_deref(), Some("en"));
+        assert_eq!(report.detected.as_deref(), Some("fr"));
+        assert!(report.confidence > 0.0);
+
+        let declared_fr = dir.join("labelled.fb2");
+        std::fs::write(&declared_fr, fb2("fr")).unwrap();
+        let report = detect_book_language_sync(&declared_fr).unwrap();
+        assert_eq!(report.declared.as_deref(), Some("fr"));
+        assert_eq!(report.detected, None);
+        assert_eq!(report.confidence, 0.0);
+
+        // Windows-1251 Russian, as old Russian-language TXT books come.
+        let russian = "Все счастливые семьи похожи друг на друга, каждая несчастливая \
+            семья несчастлива по-своему. Всё смешалось в доме Облонских.";
+        // А..я sit at 0xC0..0xFF, ё at 0xB8.
+        let cp1251: Vec<u8> = russian
+            .chars()
+            .map(|c| match c {
+                'ё' => 0xb8,
+                'А'..='я' => (c as u32 - 0x350) as u8,
+                _ => c as u8,
+            })
+            .collect();
+        let txt = dir.join("anna.txt");
+        std::fs::write(&txt, &cp1251).unwrap();
+        let report = detect_book_language_sync(&txt).unwrap();
+        assert_eq!(report.encoding.as_deref(), Some("windows-1251"));
+        assert_eq!(report.detected.as_deref(), Some("ru"));
+
+        let pdf = dir.join("scan.pdf");
+        std::fs::write(&pdf, b"%PDF-1.7").unwrap();
+        assert_eq!(
+            detect_book_language_sync(&pdf).unwrap(),
+            BookLanguage::default()
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,1110 @@
+//! Metadata read natively from EPUB, MOBI/AZW and FB2/FBZ books.
+//!
+//! [`read_book_metadata`] parses a book once and returns its title and
+//! creators, media overlays, publisher and identifiers, language, and page
+//! layout. The rest of the app uses the same reader to name files
+//! (`book_rename`), fill the Discord presence, find duplicates and index
+//! the library.
+//!
+//! This is deliberately not the library's metadata. foliate-js stays the
+//! source of truth for that (see the notes atop `epub_parser` and
+//! `mobi_parser`), so nothing read here is ever written back to `Book`.
+//!
+//! Sources per format:
+//!   - EPUB: the OPF `<metadata>`, `<manifest>` and `<spine>`.
+//!     - Title, publisher and language come from the first `<dc:title>`,
+//!       `<dc:publisher>` and `<dc:language>`.
+//!     - Every `<dc:creator>` is read, with its role (a MARC relator code:
+//!       `aut`, `edt`, `trl`, …) from EPUB 2 `opf:role` or EPUB 3
+//!       `<meta refines>`, ordered by `display-seq` when given.
+//!     - Every `<dc:identifier>` is read. Its scheme comes from `opf:scheme`,
+//!       an EPUB 3 `identifier-type` refinement, or the value's own prefix
+//!       (`urn:isbn:`, `urn:uuid:`, `doi:`).
+//!     - Media overlays are manifest items with a `media-overlay`
+//!       attribute. Their length is the book's `media:duration`, else the
+//!       sum of the per-overlay values.
+//!     - Page progression is the spine's `page-progression-direction`.
+//!     - The writing mode comes from `<meta name="primary-writing-mode">`,
+//!       else from the CSS of the first spine documents. Only CJK and
+//!       untagged books are checked, since only those are set vertically.
+//!   - MOBI/AZW: the EXTH title (falling back to the PalmDB name), author,
+//!     publisher, ISBNs (104), source (112), ASIN (113), language, writing
+//!     mode (525) and page progression (527). Without 527, Arabic, Hebrew
+//!     and other right-to-left languages read right to left.
+//!   - FB2/FBZ: `<title-info>` `<book-title>`, every `<author>` and every
+//!     `<translator>`.
+//!
+//! `author` is the first author. ISBNs are reduced to their digits and
+//! flagged when the check digit is wrong. A vertical-rl book that doesn't
+//! name its page progression reads right to left; a book with no
+//! progression at all is reported left to right.
+
+use mobi::headers::ExthRecord;
+use mobi::Mobi;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::book_language::{is_rtl_language, mobi_language};
+use crate::book_rename::split_book_name;
+use crate::epub_parser::{
+    collapse_whitespace, local_name, read_rootfile_path, read_zip_entry, strip_xml_bom,
+};
+use crate::epub_styles::{normalize_writing_mode, spine_writing_mode};
+use crate::parser_common::read_fbz_document;
+
+/// Extensions [`read_metadata`] reads.
+const METADATA_EXTENSIONS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "prc", "fb2", "fbz", "fb2.zip",
+];
+
+/// What [`read_book_metadata`] returns.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookMetadata {
+    pub title: Option<String>,
+    /// The first author; see [`format_authors`] for all of them.
+    pub author: Option<String>,
+    /// Every creator, in display order.
+    pub creators: Vec<Creator>,
+    /// Read-along narration, for the "read-along audio" badge. Only EPUB 3
+    /// has it.
+    pub media_overlays: MediaOverlays,
+    pub publisher: Option<String>,
+    /// Every identifier, in document order, for matching against online
+    /// catalogs. Only EPUB and MOBI identifiers are read.
+    pub identifiers: Vec<Identifier>,
+    /// The first declared language tag.
+    pub language: Option<String>,
+    /// Page progression; `None` when the book doesn't say, which the
+    /// frontend receives as `ltr`.
+    #[serde(serialize_with = "serialize_layout_direction")]
+    pub layout_direction: Option<LayoutDirection>,
+    /// Written in a vertical writing mode.
+    pub vertical: bool,
+}
+
+fn serialize_layout_direction<S: serde::Serializer>(
+    direction: &Option<LayoutDirection>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    direction.unwrap_or_default().serialize(serializer)
+}
+
+/// Which way pages turn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// EPUB 3 media overlays: SMIL files that sync narration audio with the text.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaOverlays {
+    /// Some manifest item points at a media overlay.
+    pub present: bool,
+    /// Total narration length in milliseconds, when `media:duration` says.
+    pub duration_ms: Option<u64>,
+}
+
+/// A `dc:identifier` or MOBI EXTH identifier record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identifier {
+    /// `isbn`, `uuid`, `doi`, `urn`, `asin`, or the scheme the book names,
+    /// lower-cased. `None` when neither the book nor the value says.
+    pub scheme: Option<String>,
+    /// The value without its scheme prefix; ISBNs as bare digits.
+    pub value: String,
+    /// For ISBNs, whether the check digit is right; `None` otherwise.
+    pub valid: Option<bool>,
+}
+
+/// A `dc:creator` (or FB2 `<author>`/`<translator>`) with its role.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Creator {
+    pub name: String,
+    /// MARC relator code, lower-cased: `aut`, `edt`, `trl`, `ill`, …
+    /// `None` when the book doesn't say, which by convention means author.
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Sort form, e.g. "Herbert, Frank".
+    #[serde(default)]
+    pub file_as: Option<String>,
+}
+
+impl Creator {
+    fn new(name: String, role: Option<&str>) -> Self {
+        Self {
+            name,
+            role: role.map(str::to_string),
+            file_as: None,
+        }
+    }
+
+    /// Authors proper, as opposed to editors, translators, illustrators….
+    pub fn is_author(&self) -> bool {
+        matches!(self.role.as_deref(), None | Some("aut"))
+    }
+}
+
+/// The authors among `creators` as one line: "A", "A & B", "A, B & C".
+/// `None` when no creator is an author.
+pub(crate) fn format_authors(creators: &[Creator]) -> Option<String> {
+    let names: Vec<&str> = creators
+        .iter()
+        .filter(|c| c.is_author())
+        .map(|c| c.name.as_str())
+        .collect();
+    match names.split_last()? {
+        (last, []) => Some(last.to_string()),
+        (last, rest) => Some(format!("{} & {last}", rest.join(", "))),
+    }
+}
+
+/// Everything [`BookMetadata`] holds for the book at `path`, from one parse.
+/// Formats without a metadata reader get empty metadata, which reads as
+/// left-to-right horizontal with no creators or identifiers.
+#[tauri::command]
+pub async fn read_book_metadata(path: String) -> Result<BookMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let (_, ext) = split_book_name(path);
+        if !METADATA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) {
+            return Ok(BookMetadata::default());
+        }
+        read_metadata(path)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Metadata of the book at `path`, by its extension; formats outside
+/// [`METADATA_EXTENSIONS`] are an error.
+pub(crate) fn read_metadata(path: &Path) -> Result<BookMetadata, String> {
+    let (_, ext) = split_book_name(path);
+    match ext.to_ascii_lowercase().as_str() {
+        "epub" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            epub_metadata(&mut zip)
+        }
+        "mobi" | "azw" | "azw3" | "prc" => {
+            let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
+            Ok(mobi_metadata(&mobi))
+        }
+        "fb2" => {
+            let bytes = std::fs::read(path).map_err(|e| format!("read failed: {e}"))?;
+            parse_fb2_metadata(&bytes)
+        }
+        "fbz" | "fb2.zip" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            fbz_metadata(&mut zip)
+        }
+        other => Err(format!("no metadata reader for .{other}")),
+    }
+}
+
+/// [`read_metadata`] for a book held in memory, such as an entry of an
+/// archive. `ext` is its extension as [`split_book_name`] returns it.
+pub(crate) fn read_metadata_from_bytes(ext: &str, bytes: &[u8]) -> Result<BookMetadata, String> {
+    match ext.to_ascii_lowercase().as_str() {
+        "epub" => {
+            let mut zip =
+                ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("zip open failed: {e}"))?;
+            epub_metadata(&mut zip)
+        }
+        "mobi" | "azw" | "azw3" | "prc" => {
+            let mobi = Mobi::from_read(bytes).map_err(|e| format!("parse mobi: {e}"))?;
+            Ok(mobi_metadata(&mobi))
+        }
+        "fb2" => parse_fb2_metadata(bytes),
+        "fbz" | "fb2.zip" => {
+            let mut zip =
+                ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("zip open failed: {e}"))?;
+            fbz_metadata(&mut zip)
+        }
+        other => Err(format!("no metadata reader for .{other}")),
+    }
+}
+
+/// Metadata of an EPUB: its OPF, plus the writing mode of the first spine
+/// documents when the OPF doesn't name one.
+pub(crate) fn epub_metadata<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+) -> Result<BookMetadata, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf_bytes =
+        read_zip_entry(zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    let mut metadata = parse_opf_metadata(&opf_bytes)?;
+    if !metadata.vertical && may_be_vertical(metadata.language.as_deref()) {
+        if let Some(mode) = spine_writing_mode(zip, &opf_path, &opf_bytes) {
+            apply_writing_mode(&mut metadata, mode);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Whether a book in `language` could be typeset vertically: Chinese,
+/// Japanese, Korean, Mongolian, or unknown.
+fn may_be_vertical(language: Option<&str>) -> bool {
+    let Some(language) = language else {
+        return true;
+    };
+    let primary = language.split(['-', '_']).next().unwrap_or("");
+    ["zh", "ja", "ko", "mn", "und"]
+        .iter()
+        .any(|code| primary.eq_ignore_ascii_case(code))
+}
+
+/// Record the CSS writing mode `mode` (as [`normalize_writing_mode`] spells
+/// it). Vertical-rl text is read right to left unless the book says
+/// otherwise.
+fn apply_writing_mode(metadata: &mut BookMetadata, mode: &str) {
+    metadata.vertical = mode.starts_with("vertical");
+    if mode == "vertical-rl" && metadata.layout_direction.is_none() {
+        metadata.layout_direction = Some(LayoutDirection::Rtl);
+    }
+}
+
+/// A `page-progression-direction` value; `default` and unknown values are
+/// left to the reader.
+fn parse_layout_direction(value: &str) -> Option<LayoutDirection> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "ltr" => Some(LayoutDirection::Ltr),
+        "rtl" => Some(LayoutDirection::Rtl),
+        _ => None,
+    }
+}
+
+/// Metadata from the EXTH header of a MOBI/AZW book.
+pub(crate) fn mobi_metadata(mobi: &Mobi) -> BookMetadata {
+    let author = mobi.author().and_then(non_empty);
+    let exth = &mobi.metadata.exth;
+    let records = |record: ExthRecord, scheme: Option<&'static str>| {
+        exth.get_record(record)
+            .into_iter()
+            .flatten()
+            .filter_map(|bytes| non_empty(String::from_utf8_lossy(bytes).into_owned()))
+            .map(move |value| classify_identifier(&value, scheme))
+    };
+    let identifiers = records(ExthRecord::Isbn, Some("isbn"))
+        .chain(records(ExthRecord::Source, None))
+        .chain(records(ExthRecord::Asin, Some("asin")))
+        .collect();
+    // KF8 `primary-writing-mode` (525) and `page-progression-direction` (527).
+    let text_record = |position: u32| {
+        exth.get_record(ExthRecord::Other(position))
+            .and_then(|records| records.first())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    };
+    let language = mobi_language(mobi);
+    let mut metadata = BookMetadata {
+        title: non_empty(mobi.title()),
+        creators: author
+            .iter()
+            .map(|name| Creator::new(name.clone(), None))
+            .collect(),
+        author,
+        media_overlays: MediaOverlays::default(),
+        publisher: mobi.publisher().and_then(non_empty),
+        identifiers,
+        layout_direction: text_record(527).as_deref().and_then(parse_layout_direction),
+        language,
+        vertical: false,
+    };
+    if let Some(mode) = text_record(525).as_deref().and_then(normalize_writing_mode) {
+        apply_writing_mode(&mut metadata, mode);
+    }
+    if metadata.layout_direction.is_none()
+        && metadata.language.as_deref().is_some_and(is_rtl_language)
+    {
+        metadata.layout_direction = Some(LayoutDirection::Rtl);
+    }
+    metadata
+}
+
+/// Metadata of the first FB2 document in an FBZ archive.
+pub(crate) fn fbz_metadata<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+) -> Result<BookMetadata, String> {
+    parse_fb2_metadata(&read_fbz_document(zip)?)
+}
+
+pub(crate) fn non_empty(text: String) -> Option<String> {
+    let text = collapse_whitespace(&text);
+    (!text.is_empty()).then_some(text)
+}
+
+/// The value of the attribute whose local name is `name`, so `opf:role`
+/// matches whatever prefix the OPF binds.
+fn local_attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| local_name(a.key.as_ref()) == name)
+        .and_then(|a| a.unescape_value().ok())
+        .and_then(|v| non_empty(v.into_owned()))
+}
+
+/// Case-insensitive [`str::strip_prefix`] for ASCII prefixes.
+fn strip_prefix_ci<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|head| head.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+/// `value` as a bare ISBN (digits, with an upper-case `X` check digit for
+/// ISBN-10) and whether its check digit is right. `None` when it isn't 10
+/// or 13 characters once hyphens and spaces are dropped.
+fn normalize_isbn(value: &str) -> Option<(String, bool)> {
+    let isbn: String = value
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let digit = |b: u8| u32::from(b - b'0');
+    let bytes = isbn.as_bytes();
+    let valid = match bytes.len() {
+        10 if bytes[..9].iter().all(u8::is_ascii_digit)
+            && (bytes[9].is_ascii_digit() || bytes[9] == b'X') =>
+        {
+            let sum: u32 = bytes
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| (10 - i as u32) * if b == b'X' { 10 } else { digit(b) })
+                .sum();
+            sum % 11 == 0
+        }
+        13 if bytes.iter().all(u8::is_ascii_digit) => {
+            let sum: u32 = bytes
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| digit(b) * if i % 2 == 0 { 1 } else { 3 })
+                .sum();
+            sum % 10 == 0
+        }
+        _ => return None,
+    };
+    Some((isbn, valid))
+}
+
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// An identifier from its text and the scheme the book declares for it.
+/// A scheme prefix in the value wins over the declared one; without
+/// either, UUIDs, DOIs and valid ISBNs are still recognized by shape.
+fn classify_identifier(value: &str, declared: Option<&str>) -> Identifier {
+    let value = value.trim();
+    let prefixed = [
+        ("urn:isbn:", "isbn"),
+        ("isbn:", "isbn"),
+        ("urn:uuid:", "uuid"),
+        ("uuid:", "uuid"),
+        ("urn:doi:", "doi"),
+        ("doi:", "doi"),
+        ("https://doi.org/", "doi"),
+        ("http://dx.doi.org/", "doi"),
+    ]
+    .iter()
+    .find_map(|(prefix, scheme)| strip_prefix_ci(value, prefix).map(|rest| (*scheme, rest.trim())));
+    let (scheme, value) = match (prefixed, declared) {
+        (Some((scheme, rest)), _) => (Some(scheme.to_string()), rest),
+        (None, Some(declared)) => (Some(declared.to_ascii_lowercase()), value),
+        (None, None) if strip_prefix_ci(value, "urn:").is_some() => (Some("urn".into()), value),
+        (None, None) if is_uuid(value) => (Some("uuid".into()), value),
+        (None, None) if value.starts_with("10.") && value.contains('/') => {
+            (Some("doi".into()), value)
+        }
+        (None, None) if normalize_isbn(value).is_some_and(|(_, valid)| valid) => {
+            (Some("isbn".into()), value)
+        }
+        (None, None) => (None, value),
+    };
+    if scheme.as_deref() == Some("isbn") {
+        let (value, valid) = normalize_isbn(value).unwrap_or_else(|| (value.to_string(), false));
+        return Identifier {
+            scheme,
+            value,
+            valid: Some(valid),
+        };
+    }
+    Identifier {
+        scheme,
+        value: value.to_string(),
+        valid: None,
+    }
+}
+
+/// The scheme an ONIX code list 5 `identifier-type` refinement names.
+fn onix_identifier_scheme(code: &str) -> Option<&'static str> {
+    match code.trim() {
+        "02" | "15" => Some("isbn"),
+        "06" => Some("doi"),
+        "22" => Some("urn"),
+        _ => None,
+    }
+}
+
+/// A `dc:identifier` before its scheme is settled.
+#[derive(Default)]
+struct OpfIdentifier {
+    id: Option<String>,
+    scheme: Option<String>,
+    value: String,
+}
+
+/// A `dc:creator` with what EPUB 3 `<meta refines>` may add to it.
+#[derive(Default)]
+struct OpfCreator {
+    id: Option<String>,
+    display_seq: Option<u32>,
+    creator: Option<Creator>,
+}
+
+/// Milliseconds in a SMIL clock value: `1:02:03.5`, `02:03.5`, `3.5s`,
+/// `200ms`, `5min`, `1.5h`, or a bare number of seconds.
+fn parse_clock_value(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let seconds = if value.contains(':') {
+        let (hours, minutes, seconds) = match value.split(':').collect::<Vec<_>>()[..] {
+            [h, m, s] => (h.parse::<u64>().ok()?, m.parse::<u64>().ok()?, s),
+            [m, s] => (0, m.parse::<u64>().ok()?, s),
+            _ => return None,
+        };
+        (hours * 3600 + minutes * 60) as f64 + seconds.parse::<f64>().ok()?
+    } else {
+        // `ms` before `s`, which it ends with.
+        let (number, scale) = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)]
+            .iter()
+            .find_map(|(unit, scale)| value.strip_suffix(unit).map(|n| (n, *scale)))
+            .unwrap_or((value, 1.0));
+        number.trim().parse::<f64>().ok()? * scale
+    };
+    (seconds.is_finite() && seconds >= 0.0).then(|| (seconds * 1000.0).round() as u64)
+}
+
+/// First `dc:title`, `dc:publisher` and `dc:language`, every `dc:creator`
+/// and `dc:identifier` inside the OPF `<metadata>`, the writing mode it
+/// declares, the media overlays the `<manifest>` references, and the
+/// `<spine>`'s page progression.
+fn parse_opf_metadata(opf_bytes: &[u8]) -> Result<BookMetadata, String> {
+    let normalized = strip_xml_bom(opf_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut metadata = BookMetadata::default();
+    let mut creators: Vec<OpfCreator> = Vec::new();
+    let mut identifiers: Vec<OpfIdentifier> = Vec::new();
+    let mut pending_identifier = OpfIdentifier::default();
+    // (refined id, property, value) from EPUB 3 `<meta refines="#id">`.
+    let mut refinements: Vec<(String, String, String)> = Vec::new();
+    let mut in_metadata = false;
+    let mut in_manifest = false;
+    // Book-level `media:duration`, which wins over the per-overlay sum.
+    let mut total_duration: Option<u64> = None;
+    // Which field the current text belongs to, if any.
+    let mut capturing: Option<&'static str> = None;
+    let mut pending = OpfCreator::default();
+    let mut refining: Option<(String, String)> = None;
+    let mut text = String::new();
+    let mut writing_mode: Option<&'static str> = None;
+    // `<meta name="primary-writing-mode" content="vertical-rl"/>`, the
+    // convention Kindle and Japanese publishers use.
+    let primary_writing_mode = |e: &BytesStart| {
+        (local_attr(e, b"name").as_deref() == Some("primary-writing-mode"))
+            .then(|| local_attr(e, b"content"))
+            .flatten()
+            .and_then(|mode| normalize_writing_mode(&mode))
+    };
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match local_name(e.name().as_ref()) {
+                b"metadata" => in_metadata = true,
+                b"title" if in_metadata && metadata.title.is_none() => capturing = Some("title"),
+                b"publisher" if in_metadata && metadata.publisher.is_none() => {
+                    capturing = Some("publisher")
+                }
+                b"language" if in_metadata && metadata.language.is_none() => {
+                    capturing = Some("language")
+                }
+                b"identifier" if in_metadata => {
+                    pending_identifier = OpfIdentifier {
+                        id: local_attr(&e, b"id"),
+                        scheme: local_attr(&e, b"scheme"),
+                        value: String::new(),
+                    };
+                    capturing = Some("identifier");
+                }
+                b"creator" if in_metadata => {
+                    pending = OpfCreator {
+                        id: local_attr(&e, b"id"),
+                        creator: Some(Creator {
+                            name: String::new(),
+                            role: local_attr(&e, b"role").map(|r| r.to_ascii_lowercase()),
+                            file_as: local_attr(&e, b"file-as"),
+                        }),
+                        ..Default::default()
+                    };
+                    capturing = Some("creator");
+                }
+                b"meta" if in_metadata => {
+                    writing_mode = primary_writing_mode(&e).or(writing_mode);
+                    let refines = local_attr(&e, b"refines");
+                    let property = local_attr(&e, b"property");
+                    match (refines, property) {
+                        (Some(refines), Some(property)) => {
+                            refining =
+                                Some((refines.trim_start_matches('#').to_string(), property));
+                            capturing = Some("meta");
+                        }
+                        (None, Some(property)) if property == "media:duration" => {
+                            capturing = Some("duration");
+                        }
+                        _ => {}
+                    }
+                }
+                b"manifest" => in_manifest = true,
+                b"item" if in_manifest && local_attr(&e, b"media-overlay").is_some() => {
+                    metadata.media_overlays.present = true;
+                }
+                b"spine" => {
+                    metadata.layout_direction = local_attr(&e, b"page-progression-direction")
+                        .and_then(|d| parse_layout_direction(&d));
+                    break;
+                }
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match local_name(e.name().as_ref()) {
+                b"meta" if in_metadata => {
+                    writing_mode = primary_writing_mode(&e).or(writing_mode);
+                }
+                b"item" if in_manifest && local_attr(&e, b"media-overlay").is_some() => {
+                    metadata.media_overlays.present = true;
+                }
+                b"spine" => {
+                    metadata.layout_direction = local_attr(&e, b"page-progression-direction")
+                        .and_then(|d| parse_layout_direction(&d));
+                    break;
+                }
+                _ => {}
+            },
+            Ok(Event::Text(t)) if capturing.is_some() => {
+                text.push_str(&t.unescape().map_err(|e| format!("xml: {e}"))?);
+            }
+            Ok(Event::CData(t)) if capturing.is_some() => {
+                text.push_str(&String::from_utf8_lossy(&t));
+            }
+            Ok(Event::End(e)) => match local_name(e.name().as_ref()) {
+                b"metadata" => in_metadata = false,
+                b"manifest" => in_manifest = false,
+                b"title" if capturing == Some("title") => {
+                    metadata.title = non_empty(std::mem::take(&mut text));
+                    capturing = None;
+                }
+                b"publisher" if capturing == Some("publisher") => {
+                    metadata.publisher = non_empty(std::mem::take(&mut text));
+                    capturing = None;
+                }
+                b"language" if capturing == Some("language") => {
+                    metadata.language = non_empty(std::mem::take(&mut text));
+                    capturing = None;
+                }
+                b"identifier" if capturing == Some("identifier") => {
+                    if let Some(value) = non_empty(std::mem::take(&mut text)) {
+                        let mut identifier = std::mem::take(&mut pending_identifier);
+                        identifier.value = value;
+                        identifiers.push(identifier);
+                    }
+                    capturing = None;
+                }
+                b"creator" if capturing == Some("creator") => {
+                    if let Some(name) = non_empty(std::mem::take(&mut text)) {
+                        let mut creator = std::mem::take(&mut pending);
+                        if let Some(c) = creator.creator.as_mut() {
+                            c.name = name;
+                        }
+                        creators.push(creator);
+                    }
+                    capturing = None;
+                }
+                b"meta" if capturing == Some("meta") => {
+                    if let (Some((id, property)), Some(value)) =
+                        (refining.take(), non_empty(std::mem::take(&mut text)))
+                    {
+                        refinements.push((id, property, value));
+                    }
+                    capturing = None;
+                }
+                b"meta" if capturing == Some("duration") => {
+                    total_duration = parse_clock_value(&std::mem::take(&mut text));
+                    capturing = None;
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let mut overlay_durations: Option<u64> = None;
+    for (id, property, value) in refinements {
+        if property == "media:duration" {
+            if let Some(ms) = parse_clock_value(&value) {
+                overlay_durations = Some(overlay_durations.unwrap_or(0) + ms);
+            }
+            continue;
+        }
+        if property.rsplit(':').next() == Some("identifier-type") {
+            if let Some(target) = identifiers
+                .iter_mut()
+                .find(|i| i.id.as_deref() == Some(id.as_str()))
+            {
+                if let Some(scheme) = onix_identifier_scheme(&value) {
+                    target.scheme = Some(scheme.to_string());
+                }
+            }
+            continue;
+        }
+        let Some(target) = creators
+            .iter_mut()
+            .find(|c| c.id.as_deref() == Some(id.as_str()))
+        else {
+            continue;
+        };
+        let Some(creator) = target.creator.as_mut() else {
+            continue;
+        };
+        // EPUB 3 spells the properties bare or with the `opf:` prefix.
+        match property.rsplit(':').next().unwrap_or_default() {
+            "role" => creator.role = Some(value.to_ascii_lowercase()),
+            "file-as" => creator.file_as = Some(value),
+            "display-seq" => target.display_seq = value.parse().ok(),
+            _ => {}
+        }
+    }
+    // Stable, so creators without a `display-seq` keep document order after
+    // the numbered ones.
+    creators.sort_by_key(|c| (c.display_seq.is_none(), c.display_seq));
+    metadata.creators = creators.into_iter().filter_map(|c| c.creator).collect();
+    metadata.author = metadata
+        .creators
+        .iter()
+        .find(|c| c.is_author())
+        .or(metadata.creators.first())
+        .map(|c| c.name.clone());
+    if metadata.media_overlays.present {
+        metadata.media_overlays.duration_ms = total_duration.or(overlay_durations);
+    }
+    metadata.identifiers = identifiers
+        .iter()
+        .map(|i| classify_identifier(&i.value, i.scheme.as_deref()))
+        .collect();
+    if let Some(mode) = writing_mode {
+        apply_writing_mode(&mut metadata, mode);
+    }
+
+    Ok(metadata)
+}
+
+/// `<book-title>`, the `<author>`s and the `<translator>`s of
+/// `<description><title-info>`. People are written "First Middle Last",
+/// falling back to the nickname.
+fn parse_fb2_metadata(bytes: &[u8]) -> Result<BookMetadata, String> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut metadata = BookMetadata::default();
+    let mut in_title_info = false;
+    // Role of the `<author>`/`<translator>` being read.
+    let mut person: Option<&'static str> = None;
+    let mut field: Option<Vec<u8>> = None;
+    let mut text = String::new();
+    let mut name_parts: Vec<String> = Vec::new();
+    let mut nickname: Option<String> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                match name {
+                    b"title-info" => in_title_info = true,
+                    b"author" if in_title_info => person = Some("aut"),
+                    b"translator" if in_title_info => person = Some("trl"),
+                    b"book-title" if in_title_info => field = Some(name.to_vec()),
+                    b"first-name" | b"middle-name" | b"last-name" | b"nickname"
+                        if person.is_some() =>
+                    {
+                        field = Some(name.to_vec())
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Text(t)) if field.is_some() => {
+                text.push_str(&t.unescape().map_err(|e| format!("xml: {e}"))?);
+            }
+            Ok(Event::End(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if field.as_deref() == Some(name) {
+                    let value = non_empty(std::mem::take(&mut text));
+                    match (name, value) {
+                        (b"book-title", value) => metadata.title = value,
+                        (b"nickname", value) => nickname = value,
+                        (_, Some(value)) => name_parts.push(value),
+                        _ => {}
+                    }
+                    field = None;
+                } else if matches!(name, b"author" | b"translator") && person.is_some() {
+                    let full = non_empty(name_parts.join(" ")).or(nickname.take());
+                    if let Some(full) = full {
+                        metadata.creators.push(Creator::new(full, person));
+                    }
+                    name_parts.clear();
+                    nickname = None;
+                    person = None;
+                } else if name == b"title-info" {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    metadata.author = metadata
+        .creators
+        .iter()
+        .find(|c| c.is_author())
+        .map(|c| c.name.clone());
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        classify_identifier, format_authors, is_rtl_language, may_be_vertical, parse_clock_value,
+        parse_fb2_metadata, parse_opf_metadata, BookMetadata, Creator, Identifier, LayoutDirection,
+        MediaOverlays,
+    };
+
+    fn meta(title: Option<&str>, author: Option<&str>) -> BookMetadata {
+        BookMetadata {
+            title: title.map(str::to_string),
+            author: author.map(str::to_string),
+            creators: Vec::new(),
+            media_overlays: MediaOverlays::default(),
+            publisher: None,
+            identifiers: Vec::new(),
+            language: None,
+            layout_direction: None,
+            vertical: false,
+        }
+    }
+
+    fn creator(name: &str, role: Option<&str>) -> Creator {
+        Creator {
+            name: name.to_string(),
+            role: role.map(str::to_string),
+            file_as: None,
+        }
+    }
+
+    #[test]
+    fn opf_title_and_first_creator() {
+        let opf = br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <metadata>
+    <dc:title>  Dune
+      Messiah </dc:title>
+    <dc:creator id="a1">Frank Herbert</dc:creator>
+    <dc:creator id="a2">Someone Else</dc:creator>
+  </metadata>
+  <manifest><item id="title" href="title.xhtml"/></manifest>
+</package>"#;
+        assert_eq!(
+            parse_opf_metadata(opf).unwrap(),
+            BookMetadata {
+                creators: vec![
+                    creator("Frank Herbert", None),
+                    creator("Someone Else", None)
+                ],
+                ..meta(Some("Dune Messiah"), Some("Frank Herbert"))
+            }
+        );
+    }
+
+    #[test]
+    fn opf_epub3_creator_refinements() {
+        let opf = br##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Anthology</dc:title>
+    <dc:creator id="ed">Jane Editor</dc:creator>
+    <meta refines="#ed" property="role" scheme="marc:relators">edt</meta>
+    <meta refines="#ed" property="display-seq">3</meta>
+    <dc:creator id="b">Bob Writer</dc:creator>
+    <meta refines="#b" property="display-seq">2</meta>
+    <meta refines="#b" property="role" scheme="marc:relators">aut</meta>
+    <dc:creator id="a">Alice Writer</dc:creator>
+    <meta refines="#a" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#a" property="file-as">Writer, Alice</meta>
+    <meta refines="#a" property="display-seq">1</meta>
+  </metadata>
+</package>"##;
+        let parsed = parse_opf_metadata(opf).unwrap();
+        assert_eq!(
+            parsed.creators,
+            vec![
+                Creator {
+                    file_as: Some("Writer, Alice".into()),
+                    ..creator("Alice Writer", Some("aut"))
+                },
+                creator("Bob Writer", Some("aut")),
+                creator("Jane Editor", Some("edt")),
+            ]
+        );
+        assert_eq!(parsed.author.as_deref(), Some("Alice Writer"));
+    }
+
+    #[test]
+    fn opf_epub2_role_attributes() {
+        let opf = br#"<package xmlns:opf="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:creator opf:role="trl" opf:file-as="Pevear, Richard">Richard Pevear</dc:creator>
+    <dc:creator opf:role="AUT">Fyodor Dostoevsky</dc:creator>
+  </metadata>
+</package>"#;
+        let parsed = parse_opf_metadata(opf).unwrap();
+        assert_eq!(
+            parsed.creators,
+            vec![
+                Creator {
+                    file_as: Some("Pevear, Richard".into()),
+                    ..creator("Richard Pevear", Some("trl"))
+                },
+                creator("Fyodor Dostoevsky", Some("aut")),
+            ]
+        );
+        assert_eq!(parsed.author.as_deref(), Some("Fyodor Dostoevsky"));
+    }
+
+    #[test]
+    fn opf_media_overlays_and_duration() {
+        let opf = br##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Narrated</dc:title>
+    <meta property="media:duration" refines="#ch1-mo">0:10:00</meta>
+    <meta property="media:duration" refines="#ch2-mo">5min</meta>
+    <meta property="media:active-class">-epub-media-overlay-active</meta>
+  </metadata>
+  <manifest>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml" media-overlay="ch1-mo"/>
+    <item id="ch1-mo" href="ch1.smil" media-type="application/smil+xml"/>
+    <item id="ch2-mo" href="ch2.smil" media-type="application/smil+xml"/>
+  </manifest>
+</package>"##;
+        assert_eq!(
+            parse_opf_metadata(opf).unwrap().media_overlays,
+            MediaOverlays {
+                present: true,
+                duration_ms: Some(15 * 60 * 1000),
+            }
+        );
+
+        let with_total = String::from_utf8_lossy(opf).replace(
+            "<dc:title>",
+            r#"<meta property="media:duration">1:02:03.5</meta><dc:title>"#,
+        );
+        assert_eq!(
+            parse_opf_metadata(with_total.as_bytes())
+                .unwrap()
+                .media_overlays
+                .duration_ms,
+            Some(3_723_500)
+        );
+
+        let plain = br#"<package><metadata><dc:title>Plain</dc:title></metadata>
+  <manifest><item id="c" href="c.xhtml" media-type="application/xhtml+xml"/></manifest></package>"#;
+        assert_eq!(
+            parse_opf_metadata(plain).unwrap().media_overlays,
+            MediaOverlays::default()
+        );
+    }
+
+    #[test]
+    fn opf_publisher_and_identifiers() {
+        let opf = br##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"
+            xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:title>Dune</dc:title>
+    <dc:publisher> Chilton
+      Books </dc:publisher>
+    <dc:identifier id="uid">urn:uuid:1b4e28ba-2fa1-11d2-883f-0016d3cca427</dc:identifier>
+    <dc:identifier opf:scheme="ISBN">978-0-441-17271-9</dc:identifier>
+    <dc:identifier id="print">0441172717</dc:identifier>
+    <meta refines="#print" property="identifier-type" scheme="onix:codelist5">02</meta>
+    <dc:identifier>doi:10.1000/182</dc:identifier>
+    <dc:identifier opf:scheme="calibre">42</dc:identifier>
+  </metadata>
+  <manifest/>
+</package>"##;
+        let metadata = parse_opf_metadata(opf).unwrap();
+        assert_eq!(metadata.publisher.as_deref(), Some("Chilton Books"));
+        let id = |scheme: Option<&str>, value: &str, valid: Option<bool>| Identifier {
+            scheme: scheme.map(str::to_string),
+            value: value.to_string(),
+            valid,
+        };
+        assert_eq!(
+            metadata.identifiers,
+            vec![
+                id(Some("uuid"), "1b4e28ba-2fa1-11d2-883f-0016d3cca427", None),
+                id(Some("isbn"), "9780441172719", Some(true)),
+                id(Some("isbn"), "0441172717", Some(true)),
+                id(Some("doi"), "10.1000/182", None),
+                id(Some("calibre"), "42", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn opf_page_progression_and_writing_mode() {
+        let opf = |spine: &str| {
+            format!(
+                r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>吾輩は猫である</dc:title>
+    <dc:language>ja</dc:language>
+    <meta name="primary-writing-mode" content="vertical-rl"/>
+  </metadata>
+  <manifest><item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/></manifest>
+  {spine}
+</package>"#
+            )
+        };
+        let parsed =
+            parse_opf_metadata(opf("<spine><itemref idref=\"c1\"/></spine>").as_bytes()).unwrap();
+        assert_eq!(parsed.language.as_deref(), Some("ja"));
+        assert_eq!(parsed.layout_direction, Some(LayoutDirection::Rtl));
+        assert!(parsed.vertical);
+
+        let spine = r#"<spine page-progression-direction="ltr"><itemref idref="c1"/></spine>"#;
+        let parsed = parse_opf_metadata(opf(spine).as_bytes()).unwrap();
+        assert_eq!(parsed.layout_direction, Some(LayoutDirection::Ltr));
+        assert!(parsed.vertical);
+
+        let plain = parse_opf_metadata(
+            br#"<package><metadata><dc:language>en</dc:language></metadata>
+<spine><itemref idref="c1"/></spine></package>"#,
+        )
+        .unwrap();
+        assert_eq!((plain.layout_direction, plain.vertical), (None, false));
+        let json = serde_json::to_value(&plain).unwrap();
+        assert_eq!(json["layoutDirection"], "ltr");
+        assert!(!may_be_vertical(plain.language.as_deref()));
+        assert!(may_be_vertical(Some("zh-Hant")) && may_be_vertical(None));
+        assert!(is_rtl_language("ar-EG") && is_rtl_language("ku-Arab"));
+        assert!(!is_rtl_language("en") && !is_rtl_language("ja"));
+    }
+
+    #[test]
+    fn validates_isbn_check_digits() {
+        let isbn = |value: &str| {
+            let identifier = classify_identifier(value, None);
+            (identifier.scheme, identifier.value, identifier.valid)
+        };
+        let some =
+            |value: &str, valid: bool| (Some("isbn".to_string()), value.to_string(), Some(valid));
+        assert_eq!(isbn("ISBN: 0-8044-2957-x"), some("080442957X", true));
+        assert_eq!(
+            isbn("urn:isbn:978-0-441-17271-8"),
+            some("9780441172718", false)
+        );
+        assert_eq!(isbn("isbn:12345"), some("12345", false));
+        // Unprefixed digits only count as an ISBN when the check digit holds.
+        assert_eq!(isbn("9780441172719"), some("9780441172719", true));
+        assert_eq!(isbn("9780441172718").0, None);
+        assert_eq!(
+            classify_identifier("urn:oclc:123", None).scheme.as_deref(),
+            Some("urn")
+        );
+    }
+
+    #[test]
+    fn parses_smil_clock_values() {
+        assert_eq!(parse_clock_value("00:32:29.123"), Some(1_949_123));
+        assert_eq!(parse_clock_value("02:30"), Some(150_000));
+        assert_eq!(parse_clock_value("12.5s"), Some(12_500));
+        assert_eq!(parse_clock_value("250ms"), Some(250));
+        assert_eq!(parse_clock_value("1.5h"), Some(5_400_000));
+        assert_eq!(parse_clock_value("42"), Some(42_000));
+        assert_eq!(parse_clock_value("soon"), None);
+        assert_eq!(parse_clock_value("-3s"), None);
+    }
+
+    #[test]
+    fn formats_primary_authors() {
+        let one = [creator("A", None), creator("T", Some("trl"))];
+        let two = [creator("A", Some("aut")), creator("B", None)];
+        let three = [creator("A", None), creator("B", None), creator("C", None)];
+        assert_eq!(format_authors(&one).as_deref(), Some("A"));
+        assert_eq!(format_authors(&two).as_deref(), Some("A & B"));
+        assert_eq!(format_authors(&three).as_deref(), Some("A, B & C"));
+        assert_eq!(format_authors(&[creator("E", Some("edt"))]), None);
+    }
+
+    #[test]
+    fn fb2_title_and_author_name_parts() {
+        let fb2 = "<FictionBook><description><title-info>\
+            <author><first-name>Лев</first-name><last-name>Толстой</last-name></author>\
+            <author><nickname>second</nickname></author>\
+            <book-title>Война и мир</book-title>\
+            </title-info></description><body/></FictionBook>";
+        assert_eq!(
+            parse_fb2_metadata(fb2.as_bytes()).unwrap(),
+            BookMetadata {
+                creators: vec![
+                    creator("Лев Толстой", Some("aut")),
+                    creator("second", Some("aut"))
+                ],
+                ..meta(Some("Война и мир"), Some("Лев Толстой"))
+            }
+        );
+    }
+
+    #[test]
+    fn fb2_author_falls_back_to_nickname() {
+        let fb2 = "<FictionBook><description><title-info>\
+            <author><nickname>anon</nickname></author>\
+            </title-info></description></FictionBook>";
+        assert_eq!(
+            parse_fb2_metadata(fb2.as_bytes()).unwrap(),
+            BookMetadata {
+                creators: vec![creator("anon", Some("aut"))],
+                ..meta(None, Some("anon"))
+            }
+        );
+    }
+}
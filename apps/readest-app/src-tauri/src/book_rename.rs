@@ -0,0 +1,274 @@
+//! `normalize_filename`: rename an imported book after its metadata, e.g.
+//! `Frank Herbert - Dune.epub` instead of `dune_v2_FINAL(1).epub`.
+//!
+//! The title and first author come from [`crate::book_metadata`]. Anything
+//! missing is filled with a placeholder, so the new name is never empty.
+
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_fs::FsExt;
+
+use crate::book_metadata::{non_empty, read_metadata, BookMetadata};
+use crate::epub_parser::collapse_whitespace;
+
+const UNKNOWN_AUTHOR: &str = "Unknown Author";
+const UNTITLED: &str = "Untitled";
+
+/// Keep the stem well under the 255-byte name limit of common filesystems,
+/// leaving room for the extension and a ` (n)` collision suffix.
+const MAX_STEM_BYTES: usize = 200;
+
+/// Characters that are illegal in a filename on at least one platform.
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows refuses as file stems regardless of extension.
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Rename the book at `path` according to `pattern` (placeholders `{title}`
+/// and `{author}`), keeping it in the same folder and extension. Returns the
+/// new path, which is `path` itself when the name is already normalized.
+#[tauri::command]
+pub async fn normalize_filename(
+    app: AppHandle,
+    path: String,
+    pattern: String,
+) -> Result<String, String> {
+    let source = PathBuf::from(&path);
+    let scope = app.fs_scope();
+    if !scope.is_allowed(&source) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    if !source.is_file() {
+        return Err(format!("file not found: {path}"));
+    }
+
+    let (stem, ext) = split_book_name(&source);
+    let metadata = tauri::async_runtime::spawn_blocking({
+        let source = source.clone();
+        move || read_metadata(&source)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+    .unwrap_or_else(|e| {
+        log::warn!("No metadata for {path}: {e}");
+        BookMetadata::default()
+    });
+
+    let new_stem = format_stem(&pattern, &metadata, &stem);
+    let dir = source
+        .parent()
+        .ok_or_else(|| format!("no parent directory: {path}"))?;
+    let target = unique_path(dir, &new_stem, &ext, &source);
+    if target == source {
+        return Ok(path);
+    }
+    if !scope.is_allowed(&target) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+
+    std::fs::rename(&source, &target).map_err(|e| format!("rename failed: {e}"))?;
+    log::info!("Renamed {} -> {}", source.display(), target.display());
+    Ok(target.to_string_lossy().into_owned())
+}
+
+/// Split a book path into stem and extension, treating `.fb2.zip` as one
+/// extension. The extension keeps its original case and has no leading dot.
+pub(crate) fn split_book_name(path: &Path) -> (String, String) {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if name.to_lowercase().ends_with(".fb2.zip") {
+        let cut = name.len() - ".fb2.zip".len();
+        return (name[..cut].to_string(), name[cut + 1..].to_string());
+    }
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), ext.to_string()),
+        _ => (name, String::new()),
+    }
+}
+/// Fill `{title}` / `{author}` in `pattern` and sanitize the result. A
+/// missing title falls back to the current stem, a missing author to
+/// [`UNKNOWN_AUTHOR`]; an empty result becomes [`UNTITLED`].
+fn format_stem(pattern: &str, metadata: &BookMetadata, current_stem: &str) -> String {
+    let title = metadata
+        .title
+        .clone()
+        .or_else(|| non_empty(current_stem.to_string()))
+        .unwrap_or_else(|| UNTITLED.to_string());
+    let author = metadata
+        .author
+        .clone()
+        .unwrap_or_else(|| UNKNOWN_AUTHOR.to_string());
+    let pattern = if pattern.trim().is_empty() {
+        "{author} - {title}"
+    } else {
+        pattern
+    };
+
+    let formatted = pattern
+        .replace("{title}", &sanitize_component(&title))
+        .replace("{author}", &sanitize_component(&author));
+    let stem = sanitize_component(&formatted);
+    if stem.is_empty() {
+        UNTITLED.to_string()
+    } else {
+        stem
+    }
+}
+
+/// Make `name` safe as a file stem on Windows, macOS and Linux: illegal and
+/// control characters become `_`, whitespace is collapsed, leading/trailing
+/// dots and spaces are trimmed, reserved device names are suffixed, and the
+/// length is capped at [`MAX_STEM_BYTES`].
+fn sanitize_component(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || ILLEGAL_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let mut stem = collapse_whitespace(&replaced)
+        .trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+
+    if stem.len() > MAX_STEM_BYTES {
+        let mut cut = MAX_STEM_BYTES;
+        while !stem.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        stem.truncate(cut);
+        stem = stem.trim_end_matches([' ', '.']).to_string();
+    }
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(&stem))
+    {
+        stem.push('_');
+    }
+    stem
+}
+
+/// `dir/stem.ext`, or `dir/stem (n).ext` with the smallest `n` that is free.
+/// `current` counts as free so renaming a book to its own name is a no-op.
+fn unique_path(dir: &Path, stem: &str, ext: &str, current: &Path) -> PathBuf {
+    let file_name = |suffix: &str| {
+        if ext.is_empty() {
+            format!("{stem}{suffix}")
+        } else {
+            format!("{stem}{suffix}.{ext}")
+        }
+    };
+    let is_free = |candidate: &Path| candidate == current || !candidate.exists();
+
+    let candidate = dir.join(file_name(""));
+    if is_free(&candidate) {
+        return candidate;
+    }
+    (2..)
+        .map(|n| dir.join(file_name(&format!(" ({n})"))))
+        .find(|candidate| is_free(candidate))
+        .expect("unbounded counter always finds a free name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_stem, sanitize_component, split_book_name, unique_path, BookMetadata};
+    use std::path::Path;
+
+    fn meta(title: Option<&str>, author: Option<&str>) -> BookMetadata {
+        BookMetadata {
+            title: title.map(str::to_string),
+            author: author.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn splits_compound_fb2_zip_extension() {
+        assert_eq!(
+            split_book_name(Path::new("/b/War.And.Peace.FB2.zip")),
+            ("War.And.Peace".to_string(), "FB2.zip".to_string())
+        );
+        assert_eq!(
+            split_book_name(Path::new("/b/dune.epub")),
+            ("dune".to_string(), "epub".to_string())
+        );
+        assert_eq!(
+            split_book_name(Path::new("/b/.hidden")),
+            (".hidden".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn formats_pattern_with_placeholders() {
+        let m = meta(Some("Dune"), Some("Frank Herbert"));
+        assert_eq!(
+            format_stem("{author} - {title}", &m, "x"),
+            "Frank Herbert - Dune"
+        );
+        assert_eq!(
+            format_stem("{title} ({author})", &m, "x"),
+            "Dune (Frank Herbert)"
+        );
+    }
+
+    #[test]
+    fn missing_fields_use_placeholders() {
+        assert_eq!(
+            format_stem("{author} - {title}", &meta(None, None), "dune_v2"),
+            "Unknown Author - dune_v2"
+        );
+        assert_eq!(format_stem("{title}", &meta(None, None), ""), "Untitled");
+        assert_eq!(format_stem("...", &meta(None, None), "x"), "Untitled");
+        assert_eq!(
+            format_stem("", &meta(Some("Dune"), None), "x"),
+            "Unknown Author - Dune"
+        );
+    }
+
+    #[test]
+    fn sanitizes_illegal_characters_and_reserved_names() {
+        assert_eq!(
+            sanitize_component("AC/DC: Live? <2024>"),
+            "AC_DC_ Live_ _2024_"
+        );
+        assert_eq!(sanitize_component("  .hidden. "), "hidden");
+        assert_eq!(sanitize_component("con"), "con_");
+        assert_eq!(sanitize_component("tab\there"), "tab_here");
+
+        let long = "é".repeat(150);
+        let cut = sanitize_component(&long);
+        assert!(cut.len() <= 200);
+        assert!(cut.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn collisions_get_a_counter() {
+        let dir = std::env::temp_dir().join(format!("readest-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("download.epub");
+        std::fs::write(&source, b"").unwrap();
+        std::fs::write(dir.join("A - B.epub"), b"").unwrap();
+        std::fs::write(dir.join("A - B (2).epub"), b"").unwrap();
+
+        assert_eq!(
+            unique_path(&dir, "A - B", "epub", &source),
+            dir.join("A - B (3).epub")
+        );
+        assert_eq!(
+            unique_path(&dir, "download", "epub", &source),
+            source,
+            "renaming to the current name is a no-op"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
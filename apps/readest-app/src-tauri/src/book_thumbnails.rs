@@ -0,0 +1,871 @@
+//! Cover commands backed by the Explorer thumbnail provider's crate
+//! (`extensions/windows-thumbnail`), which the app links as a library.
+//!
+//! Covers come out of the same extraction and rendering as the Explorer
+//! thumbnails, and cached ones go to the same disk cache, so on Windows a
+//! thumbnail Explorer already drew is reused rather than rendered twice.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use windows_thumbnail as thumbnails;
+
+/// Image format of [`thumbnail_data_url`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataUrlFormat {
+    #[default]
+    Png,
+    Webp,
+}
+
+impl From<DataUrlFormat> for thumbnails::DataUrlFormat {
+    fn from(format: DataUrlFormat) -> Self {
+        match format {
+            DataUrlFormat::Png => thumbnails::DataUrlFormat::Png,
+            DataUrlFormat::Webp => thumbnails::DataUrlFormat::WebP,
+        }
+    }
+}
+
+/// Where a [`CoverCandidate`] lives in the book, handed back to
+/// [`cover_candidate_bytes`] once the user picks it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "camelCase")]
+pub enum CoverSource {
+    /// Entry path inside the archive (EPUB, CBZ).
+    ZipEntry(String),
+    /// `<binary id>` of an FB2 document.
+    Fb2Binary(String),
+    /// The format's single cover location (MOBI, TXT).
+    Primary,
+}
+
+impl From<thumbnails::CoverSource> for CoverSource {
+    fn from(source: thumbnails::CoverSource) -> Self {
+        match source {
+            thumbnails::CoverSource::ZipEntry(name) => CoverSource::ZipEntry(name),
+            thumbnails::CoverSource::Fb2Binary(id) => CoverSource::Fb2Binary(id),
+            thumbnails::CoverSource::Primary => CoverSource::Primary,
+        }
+    }
+}
+
+impl From<CoverSource> for thumbnails::CoverSource {
+    fn from(source: CoverSource) -> Self {
+        match source {
+            CoverSource::ZipEntry(name) => thumbnails::CoverSource::ZipEntry(name),
+            CoverSource::Fb2Binary(id) => thumbnails::CoverSource::Fb2Binary(id),
+            CoverSource::Primary => thumbnails::CoverSource::Primary,
+        }
+    }
+}
+
+/// One image that may be the book's cover.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverCandidate {
+    pub source: CoverSource,
+    /// Heuristic confidence, 0–100.
+    pub score: u8,
+    /// Size of the full image.
+    pub width: u32,
+    pub height: u32,
+    /// PNG at most 160 px on its long edge.
+    pub preview: Vec<u8>,
+}
+
+/// Cancellation flags of the running [`preview_pages`] calls, keyed by the
+/// caller's request id, so cancelling one preview leaves the others alone.
+#[derive(Default)]
+pub struct PreviewRequests(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl PreviewRequests {
+    /// Register `id` and return its flag. Reusing the id of a running
+    /// request takes the id over from it.
+    fn start(&self, id: &str) -> Result<Arc<AtomicBool>, String> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.0
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?
+            .insert(id.to_string(), cancel.clone());
+        Ok(cancel)
+    }
+
+    /// Forget `id` once its request is done, unless a newer request took it.
+    fn finish(&self, id: &str, cancel: &Arc<AtomicBool>) {
+        if let Ok(mut requests) = self.0.lock() {
+            if requests.get(id).is_some_and(|c| Arc::ptr_eq(c, cancel)) {
+                requests.remove(id);
+            }
+        }
+    }
+
+    /// Set the flag of `id`. `false` if no such request is running.
+    fn cancel(&self, id: &str) -> Result<bool, String> {
+        let requests = self
+            .0
+            .lock()
+            .map_err(|e| format!("Mutex lock error: {e}"))?;
+        let Some(cancel) = requests.get(id) else {
+            return Ok(false);
+        };
+        cancel.store(true, Ordering::SeqCst);
+        Ok(true)
+    }
+}
+
+/// Lower-case extension of the book at `path`, as the crate expects it.
+fn book_ext(path: &Path) -> Result<String, String> {
+    thumbnails::book_extension(path)
+        .ok_or_else(|| format!("unrecognized book file: {}", path.display()))
+}
+
+/// Run `f` on the blocking pool: extraction reads and decodes whole books.
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Cover of the book at `path` as a `data:` URL, for places where asset
+/// URLs don't work (canvas exports, generated documents). `size` is clamped
+/// to 512 px so the string stays small; `format` is `png` (the default) or
+/// `webp`. No Readest badge, and the encoded string is cached. `password`
+/// opens a password-protected EPUB or CBZ.
+#[tauri::command]
+pub async fn thumbnail_data_url(
+    path: String,
+    size: u32,
+    format: Option<DataUrlFormat>,
+    password: Option<String>,
+) -> Result<String, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        let format = format.unwrap_or_default().into();
+        thumbnails::thumbnail_data_url(&path, &ext, size, format, password.as_deref())
+            .map_err(|e| format!("Failed to render cover: {e:#}"))
+    })
+    .await
+}
+
+/// Every plausible cover of the book at `path`, best first; the first is the
+/// one thumbnails use. Lets the user pick a back cover or the volume cover
+/// instead.
+#[tauri::command]
+pub async fn list_cover_candidates(path: String) -> Result<Vec<CoverCandidate>, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        let candidates = thumbnails::list_cover_candidates(&path, &ext)
+            .map_err(|e| format!("Failed to list covers: {e:#}"))?;
+        Ok(candidates
+            .into_iter()
+            .map(|candidate| CoverCandidate {
+                source: candidate.source.into(),
+                score: candidate.score,
+                width: candidate.width,
+                height: candidate.height,
+                preview: candidate.preview,
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Full image of the candidate at `source`, from [`list_cover_candidates`].
+#[tauri::command]
+pub async fn cover_candidate_bytes(path: String, source: CoverSource) -> Result<Vec<u8>, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        thumbnails::cover_candidate_bytes(&path, &ext, &source.into())
+            .map_err(|e| format!("Failed to read cover: {e:#}"))
+    })
+    .await
+}
+
+/// Result of [`verify_thumbnail_cache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheReport {
+    /// Cache entries examined.
+    pub checked: usize,
+    /// Entries deleted because they were damaged.
+    pub removed: usize,
+}
+
+/// Decode every entry of the thumbnail cache and delete the damaged ones.
+/// Only files named like cache entries are touched.
+#[tauri::command]
+pub async fn verify_thumbnail_cache() -> Result<CacheReport, String> {
+    run_blocking(|| {
+        let report = thumbnails::verify_thumbnail_cache()
+            .map_err(|e| format!("Failed to verify the thumbnail cache: {e:#}"))?;
+        Ok(CacheReport {
+            checked: report.checked,
+            removed: report.removed,
+        })
+    })
+    .await
+}
+
+/// Layout of [`generate_contact_sheet`]. Anything left out keeps its
+/// default: 16 px spacing on white, square corners, no shadow.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactSheetOptions {
+    /// Gap between cells and around the grid, in pixels.
+    pub spacing: Option<u32>,
+    /// RGBA fill behind the covers.
+    pub background: Option<[u8; 4]>,
+    /// Corner radius of each cover, in pixels.
+    pub corner_radius: Option<u32>,
+    /// Set each cover on a soft drop shadow.
+    pub shadow: Option<bool>,
+}
+
+impl From<ContactSheetOptions> for thumbnails::ContactSheetOptions {
+    fn from(options: ContactSheetOptions) -> Self {
+        let mut sheet = thumbnails::ContactSheetOptions::default();
+        if let Some(spacing) = options.spacing {
+            sheet.spacing = spacing;
+        }
+        if let Some(background) = options.background {
+            sheet.background = image::Rgba(background);
+        }
+        sheet.style.corner_radius = options.corner_radius.unwrap_or(0);
+        if options.shadow.unwrap_or(false) {
+            sheet.style.shadow = Some(thumbnails::ShadowSpec::default());
+        }
+        sheet
+    }
+}
+
+/// The covers of `paths` as one PNG grid, `columns` wide, for sharing a
+/// reading list. Cells are `cell_size` px wide and 1.5 times as tall; books
+/// without a cover get a placeholder tile. Sheets over 8192 px on a side are
+/// refused.
+#[tauri::command]
+pub async fn generate_contact_sheet(
+    paths: Vec<String>,
+    columns: u32,
+    cell_size: u32,
+    options: Option<ContactSheetOptions>,
+) -> Result<Vec<u8>, String> {
+    run_blocking(move || {
+        let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+        let options = options.unwrap_or_default().into();
+        thumbnails::generate_contact_sheet(
+            &paths,
+            columns,
+            cell_size,
+            &options,
+            thumbnails::extraction_limit(),
+        )
+        .map_err(|e| format!("Failed to render contact sheet: {e:#}"))
+    })
+    .await
+}
+
+/// Page progression of a comic, from [`cbz_reading_direction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadingDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl From<thumbnails::ReadingDirection> for ReadingDirection {
+    fn from(direction: thumbnails::ReadingDirection) -> Self {
+        match direction {
+            thumbnails::ReadingDirection::LeftToRight => ReadingDirection::LeftToRight,
+            thumbnails::ReadingDirection::RightToLeft => ReadingDirection::RightToLeft,
+        }
+    }
+}
+
+/// Reading direction the CBZ at `path` declares in its ComicInfo.xml,
+/// left-to-right when it has none.
+#[tauri::command]
+pub async fn cbz_reading_direction(path: String) -> Result<ReadingDirection, String> {
+    run_blocking(move || {
+        let file = std::fs::File::open(&path).map_err(|e| format!("Failed to open {path}: {e}"))?;
+        thumbnails::cbz_reading_direction(file)
+            .map(ReadingDirection::from)
+            .map_err(|e| format!("Failed to read ComicInfo.xml: {e:#}"))
+    })
+    .await
+}
+
+/// Result of [`migrate_cache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    /// Entries moved into the destination.
+    pub moved: usize,
+    /// Entries the destination already had; the source copy was deleted.
+    pub duplicates: usize,
+    /// Damaged entries, left where they were.
+    pub skipped: usize,
+}
+
+/// Move every intact entry of the thumbnail cache at `from` into the one at
+/// `to`, e.g. to fold a beta build's cache into a shared one. Files not
+/// named like cache entries stay behind.
+#[tauri::command]
+pub async fn migrate_cache(from: String, to: String) -> Result<MigrationReport, String> {
+    run_blocking(move || {
+        let report = thumbnails::migrate_cache(Path::new(&from), Path::new(&to))
+            .map_err(|e| format!("Failed to migrate the thumbnail cache: {e:#}"))?;
+        Ok(MigrationReport {
+            moved: report.moved,
+            duplicates: report.duplicates,
+            skipped: report.skipped,
+        })
+    })
+    .await
+}
+
+/// Running totals for one format, as [`thumbnail_metrics_snapshot`] reports
+/// them. Durations are in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatMetrics {
+    pub requests: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub failures: u64,
+    pub lookup_ms: u64,
+    pub extract_ms: u64,
+    pub decode_ms: u64,
+    pub encode_ms: u64,
+    /// Longest single request.
+    pub slowest_ms: u64,
+}
+
+impl From<thumbnails::FormatMetrics> for FormatMetrics {
+    fn from(metrics: thumbnails::FormatMetrics) -> Self {
+        let ms = |d: std::time::Duration| d.as_millis() as u64;
+        FormatMetrics {
+            requests: metrics.requests,
+            cache_hits: metrics.cache_hits,
+            cache_misses: metrics.cache_misses,
+            failures: metrics.failures,
+            lookup_ms: ms(metrics.lookup),
+            extract_ms: ms(metrics.extract),
+            decode_ms: ms(metrics.decode),
+            encode_ms: ms(metrics.encode),
+            slowest_ms: ms(metrics.slowest),
+        }
+    }
+}
+
+/// Result of [`thumbnail_metrics_snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailMetrics {
+    /// Whether [`thumbnails::METRICS_ENV`] is set; `formats` stays empty
+    /// when it isn't.
+    pub enabled: bool,
+    /// Totals keyed by lower-cased book extension.
+    pub formats: BTreeMap<String, FormatMetrics>,
+}
+
+/// Timing totals per format of the thumbnails this app process generated
+/// since it started, for a "scanning is slow" report. Explorer's requests
+/// are counted in its own process and not included.
+#[tauri::command]
+pub fn thumbnail_metrics_snapshot() -> ThumbnailMetrics {
+    ThumbnailMetrics {
+        enabled: thumbnails::metrics_enabled(),
+        formats: thumbnails::thumbnail_metrics_snapshot()
+            .into_iter()
+            .map(|(ext, metrics)| (ext, metrics.into()))
+            .collect(),
+    }
+}
+
+/// Thumbnails of the first `count` pages (at most 32) of the comic at
+/// `path`, in natural order, for a swipeable import preview. Each fits
+/// `size` px and has no badge; undecodable pages are skipped. PDFs aren't
+/// supported. `request_id` names the call for [`cancel_preview_pages`].
+#[tauri::command]
+pub async fn preview_pages(
+    requests: State<'_, PreviewRequests>,
+    request_id: String,
+    path: String,
+    count: usize,
+    size: u32,
+) -> Result<Vec<Vec<u8>>, String> {
+    let cancel = requests.start(&request_id)?;
+    let flag = cancel.clone();
+    let result = run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        thumbnails::preview_pages(
+            &path,
+            &ext,
+            count,
+            size,
+            thumbnails::extraction_limit(),
+            &flag,
+        )
+        .map_err(|e| format!("Failed to preview pages: {e:#}"))
+    })
+    .await;
+    requests.finish(&request_id, &cancel);
+    result
+}
+
+/// Stop the running [`preview_pages`] call `request_id` before its next
+/// page. `false` if it already finished or never started.
+#[tauri::command]
+pub fn cancel_preview_pages(
+    requests: State<'_, PreviewRequests>,
+    request_id: String,
+) -> Result<bool, String> {
+    requests.cancel(&request_id)
+}
+
+/// JPEG chroma subsampling: `"444"` keeps full color resolution, `"420"`
+/// makes the smallest files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ChromaSubsampling {
+    #[serde(rename = "444")]
+    Yuv444,
+    #[serde(rename = "422")]
+    Yuv422,
+    #[serde(rename = "420")]
+    Yuv420,
+}
+
+impl From<ChromaSubsampling> for thumbnails::ChromaSubsampling {
+    fn from(subsampling: ChromaSubsampling) -> Self {
+        match subsampling {
+            ChromaSubsampling::Yuv444 => thumbnails::ChromaSubsampling::Yuv444,
+            ChromaSubsampling::Yuv422 => thumbnails::ChromaSubsampling::Yuv422,
+            ChromaSubsampling::Yuv420 => thumbnails::ChromaSubsampling::Yuv420,
+        }
+    }
+}
+
+/// Settings of [`generate_thumbnails_batch`]; anything left out keeps the
+/// crate's default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchOptions {
+    /// Logical size in px; the format's preferred size when left out.
+    pub size: Option<u32>,
+    /// Device-pixel ratio.
+    pub scale: Option<u32>,
+    /// JPEG quality of opaque covers.
+    pub quality: Option<u8>,
+    /// JPEG chroma subsampling; full color up to 512 px when left out.
+    pub subsampling: Option<ChromaSubsampling>,
+    /// Worker threads; one per core when left out.
+    pub concurrency: Option<usize>,
+    /// Run as a library scan, at background priority.
+    pub background: Option<bool>,
+    /// Readest badge on or off, per extension.
+    pub badges: Option<HashMap<String, bool>>,
+}
+
+impl BatchOptions {
+    fn into_parts(self) -> (thumbnails::BatchOptions, thumbnails::OverlayPolicy) {
+        let defaults = thumbnails::BatchOptions::default();
+        let options = thumbnails::BatchOptions {
+            size: self.size.unwrap_or(defaults.size),
+            scale: self.scale.unwrap_or(defaults.scale),
+            quality: self.quality.unwrap_or(defaults.quality),
+            subsampling: self.subsampling.map(Into::into),
+            concurrency: self.concurrency,
+            priority: if self.background.unwrap_or(false) {
+                thumbnails::BatchPriority::Background
+            } else {
+                thumbnails::BatchPriority::Foreground
+            },
+        };
+        let mut overlay = thumbnails::OverlayPolicy::new();
+        for (ext, enabled) in self.badges.unwrap_or_default() {
+            overlay.set(&ext, enabled);
+        }
+        (options, overlay)
+    }
+}
+
+/// One book of a [`generate_thumbnails_batch`] result: the encoded
+/// thumbnail, or why there is none.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchThumbnail {
+    pub path: String,
+    pub bytes: Option<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+    pub error: Option<String>,
+}
+
+/// Cached thumbnails of `paths`, in order, generated on several threads, e.g.
+/// to fill the cache after an import. Extractions share the limit of the
+/// other cover commands.
+#[tauri::command]
+pub async fn generate_thumbnails_batch(
+    paths: Vec<String>,
+    options: Option<BatchOptions>,
+) -> Result<Vec<BatchThumbnail>, String> {
+    run_blocking(move || {
+        let (options, overlay) = options.unwrap_or_default().into_parts();
+        let books: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        let results = thumbnails::generate_thumbnails_batch(
+            &books,
+            &options,
+            &overlay,
+            thumbnails::extraction_limit(),
+        );
+        Ok(paths
+            .into_iter()
+            .zip(results)
+            .map(|(path, result)| match result {
+                Ok(thumbnail) => BatchThumbnail {
+                    path,
+                    bytes: Some(thumbnail.bytes),
+                    width: thumbnail.width,
+                    height: thumbnail.height,
+                    error: None,
+                },
+                Err(e) => BatchThumbnail {
+                    path,
+                    error: Some(format!("{e:#}")),
+                    ..Default::default()
+                },
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Event [`warm_thumbnail_cache`] reports its progress on, with a
+/// [`CacheWarmingPayload`].
+pub const CACHE_WARMING_EVENT: &str = "thumbnail-cache-warming";
+
+/// Why cache warming ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WarmOutcome {
+    Completed,
+    Stopped,
+    CacheFull,
+}
+
+impl From<thumbnails::WarmOutcome> for WarmOutcome {
+    fn from(outcome: thumbnails::WarmOutcome) -> Self {
+        match outcome {
+            thumbnails::WarmOutcome::Completed => WarmOutcome::Completed,
+            thumbnails::WarmOutcome::Stopped => WarmOutcome::Stopped,
+            thumbnails::WarmOutcome::CacheFull => WarmOutcome::CacheFull,
+        }
+    }
+}
+
+/// One [`CACHE_WARMING_EVENT`]. `finished` is always the last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CacheWarmingPayload {
+    Progress {
+        done: usize,
+        total: usize,
+    },
+    /// Waiting for the app to lose focus or go idle, or for AC power.
+    Paused,
+    Resumed,
+    Finished {
+        generated: usize,
+        cached: usize,
+        failed: usize,
+        pruned: usize,
+        outcome: WarmOutcome,
+    },
+}
+
+impl From<thumbnails::WarmEvent> for CacheWarmingPayload {
+    fn from(event: thumbnails::WarmEvent) -> Self {
+        match event {
+            thumbnails::WarmEvent::Progress { done, total } => {
+                CacheWarmingPayload::Progress { done, total }
+            }
+            thumbnails::WarmEvent::Paused => CacheWarmingPayload::Paused,
+            thumbnails::WarmEvent::Resumed => CacheWarmingPayload::Resumed,
+            thumbnails::WarmEvent::Finished(report) => CacheWarmingPayload::Finished {
+                generated: report.generated,
+                cached: report.cached,
+                failed: report.failed,
+                pruned: report.pruned,
+                outcome: report.outcome.into(),
+            },
+        }
+    }
+}
+
+/// Generate thumbnails for every book under `root` in the background, so the
+/// library opens with its covers ready. Returns once warming has started;
+/// progress comes as [`CACHE_WARMING_EVENT`]s. Warming holds off while the
+/// app has focus and, once done, trims the cache back to its size limit.
+/// Fails if warming is already running.
+#[tauri::command]
+pub fn warm_thumbnail_cache(
+    app: AppHandle,
+    root: String,
+    options: Option<BatchOptions>,
+) -> Result<(), String> {
+    let (options, overlay) = options.unwrap_or_default().into_parts();
+    thumbnails::warm_cache_background(
+        Path::new(&root),
+        options,
+        overlay,
+        thumbnails::extraction_limit(),
+        move |event| {
+            let _ = app.emit(CACHE_WARMING_EVENT, CacheWarmingPayload::from(event));
+        },
+    )
+    .map_err(|e| format!("Failed to warm the thumbnail cache: {e:#}"))
+}
+
+/// Stop a running [`warm_thumbnail_cache`] after its current books. Returns
+/// whether one was running.
+#[tauri::command]
+pub fn stop_thumbnail_cache_warming() -> bool {
+    thumbnails::stop_cache_warming()
+}
+
+/// Power source, from [`power_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    /// Running on battery; `false` when unknown or there is no battery.
+    pub on_battery: bool,
+    /// Remaining charge, if known.
+    pub percent: Option<u8>,
+}
+
+/// Whether the machine runs on battery, which background thumbnail work
+/// throttles for while battery saving is on.
+#[tauri::command]
+pub fn power_status() -> PowerStatus {
+    let status = thumbnails::power_status();
+    PowerStatus {
+        on_battery: status.on_battery,
+        percent: status.percent,
+    }
+}
+
+/// Let background batches and cache warming slow down on battery and pause
+/// below 30 % (the default), or run them at full speed on any power source.
+/// Applies until the app exits.
+#[tauri::command]
+pub fn set_battery_saver(enabled: bool) {
+    thumbnails::set_battery_saver(enabled);
+}
+
+/// Looping GIF of the first `frames` pages (at most 12) of the comic at
+/// `path` in reading order, for the book detail view. Pages fit `size` px;
+/// a comic with one readable page gets a still thumbnail instead.
+#[tauri::command]
+pub async fn animated_preview(path: String, frames: usize, size: u32) -> Result<Vec<u8>, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        thumbnails::animated_preview(&path, &ext, frames, size, thumbnails::extraction_limit())
+            .map_err(|e| format!("Failed to render preview: {e:#}"))
+    })
+    .await
+}
+
+/// Cover of the book at `path` as a PNG fitted to `size` px (the format's
+/// preferred size when left out), for transient previews such as the import
+/// confirmation screen. No badge, and the thumbnail cache is neither read
+/// nor written, so books that aren't imported leave nothing behind.
+/// `password` opens a password-protected EPUB or CBZ.
+#[tauri::command]
+pub async fn preview_cover(
+    path: String,
+    size: Option<u32>,
+    password: Option<String>,
+) -> Result<Vec<u8>, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = book_ext(&path)?;
+        let size = size.unwrap_or(thumbnails::FORMAT_DEFAULT_SIZE);
+        thumbnails::preview_cover(&path, &ext, size, password.as_deref())
+            .map_err(|e| format!("Failed to render cover: {e:#}"))
+    })
+    .await
+}
+
+/// Thumbnail of a book that is still downloading, such as `Dune.epub.part`,
+/// for the transfer list: its cover once that part of the file has arrived
+/// (EPUB, CBZ and MOBI), else a "downloading" tile. Fitted to `size` px with
+/// no badge, and never cached, since the file is still changing.
+#[tauri::command]
+pub async fn partial_download_thumbnail(path: String, size: u32) -> Result<Vec<u8>, String> {
+    run_blocking(move || {
+        let path = PathBuf::from(path);
+        let ext = thumbnails::partial_download_ext(&path)
+            .ok_or_else(|| format!("not a partial download: {}", path.display()))?;
+        let mut overlay = thumbnails::OverlayPolicy::new();
+        overlay.set(&ext, false);
+        thumbnails::partial_thumbnail_for_path(
+            &path,
+            &ext,
+            size,
+            1,
+            thumbnails::DEFAULT_THUMBNAIL_QUALITY,
+            &overlay,
+        )
+        .map(|thumbnail| thumbnail.bytes)
+        .map_err(|e| format!("Failed to render cover: {e:#}"))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_url_format_defaults_to_png() {
+        let format: DataUrlFormat = serde_json::from_str("\"webp\"").unwrap();
+        assert_eq!(format, DataUrlFormat::Webp);
+        assert!(serde_json::from_str::<DataUrlFormat>("\"gif\"").is_err());
+        assert_eq!(
+            thumbnails::DataUrlFormat::from(DataUrlFormat::default()),
+            thumbnails::DataUrlFormat::Png
+        );
+    }
+
+    #[test]
+    fn cover_sources_round_trip_through_json() {
+        let source = CoverSource::from(thumbnails::CoverSource::ZipEntry("OEBPS/c.jpg".into()));
+        let json = serde_json::to_string(&source).unwrap();
+        assert_eq!(json, r#"{"kind":"zipEntry","id":"OEBPS/c.jpg"}"#);
+        let back: CoverSource = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            thumbnails::CoverSource::from(back),
+            thumbnails::CoverSource::ZipEntry("OEBPS/c.jpg".into())
+        );
+        let primary: CoverSource = serde_json::from_str(r#"{"kind":"primary"}"#).unwrap();
+        assert_eq!(primary, CoverSource::Primary);
+    }
+
+    #[test]
+    fn contact_sheet_options_fill_in_defaults() {
+        let options: ContactSheetOptions =
+            serde_json::from_str(r#"{"cornerRadius":8,"shadow":true}"#).unwrap();
+        let sheet = thumbnails::ContactSheetOptions::from(options);
+        let defaults = thumbnails::ContactSheetOptions::default();
+        assert_eq!(sheet.spacing, defaults.spacing);
+        assert_eq!(sheet.background, defaults.background);
+        assert_eq!(sheet.style.corner_radius, 8);
+        assert!(sheet.style.shadow.is_some());
+        assert_eq!(
+            thumbnails::ContactSheetOptions::from(ContactSheetOptions::default()),
+            defaults
+        );
+    }
+
+    #[test]
+    fn reading_direction_serializes_in_camel_case() {
+        let direction = ReadingDirection::from(thumbnails::ReadingDirection::RightToLeft);
+        assert_eq!(
+            serde_json::to_string(&direction).unwrap(),
+            r#""rightToLeft""#
+        );
+    }
+
+    #[test]
+    fn batch_options_fill_in_defaults() {
+        let options: BatchOptions = serde_json::from_str(
+            r#"{"scale":2,"subsampling":"422","background":true,"badges":{".CBZ":true}}"#,
+        )
+        .unwrap();
+        let (options, overlay) = options.into_parts();
+        let defaults = thumbnails::BatchOptions::default();
+        assert_eq!(options.size, defaults.size);
+        assert_eq!(options.scale, 2);
+        assert_eq!(
+            options.subsampling,
+            Some(thumbnails::ChromaSubsampling::Yuv422)
+        );
+        assert!(serde_json::from_str::<BatchOptions>(r#"{"subsampling":"411"}"#).is_err());
+        assert_eq!(options.priority, thumbnails::BatchPriority::Background);
+        assert!(overlay.is_enabled("cbz"));
+        assert!(!overlay.is_enabled("cbr"));
+        assert_eq!(BatchOptions::default().into_parts().0, defaults);
+    }
+
+    #[test]
+    fn cache_warming_events_are_tagged() {
+        let progress =
+            CacheWarmingPayload::from(thumbnails::WarmEvent::Progress { done: 8, total: 20 });
+        assert_eq!(
+            serde_json::to_string(&progress).unwrap(),
+            r#"{"kind":"progress","done":8,"total":20}"#
+        );
+        let finished =
+            CacheWarmingPayload::from(thumbnails::WarmEvent::Finished(thumbnails::WarmReport {
+                outcome: thumbnails::WarmOutcome::CacheFull,
+                ..Default::default()
+            }));
+        let json = serde_json::to_string(&finished).unwrap();
+        assert!(json.starts_with(r#"{"kind":"finished","#));
+        assert!(json.ends_with(r#""outcome":"cacheFull"}"#));
+    }
+
+    #[test]
+    fn metrics_are_reported_in_milliseconds() {
+        let metrics = FormatMetrics::from(thumbnails::FormatMetrics {
+            requests: 2,
+            cache_misses: 2,
+            extract: std::time::Duration::from_micros(40_900),
+            slowest: std::time::Duration::from_millis(45),
+            ..Default::default()
+        });
+        let json = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(json["extractMs"], 40);
+        assert_eq!(json["slowestMs"], 45);
+        assert_eq!(json["cacheMisses"], 2);
+    }
+
+    #[test]
+    fn previews_are_cancelled_by_request_id() {
+        let requests = PreviewRequests::default();
+        let first = requests.start("a").unwrap();
+        let second = requests.start("b").unwrap();
+        assert!(requests.cancel("a").unwrap());
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!second.load(Ordering::SeqCst));
+
+        requests.finish("b", &second);
+        assert!(!requests.cancel("b").unwrap());
+        // A finished request doesn't drop a newer one that reused its id.
+        let newer = requests.start("a").unwrap();
+        requests.finish("a", &first);
+        assert!(requests.cancel("a").unwrap());
+        assert!(newer.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unrecognized_files_are_refused() {
+        assert!(book_ext(Path::new("notes")).is_err());
+        assert_eq!(
+            book_ext(Path::new("Dune.KEPUB.epub")).unwrap(),
+            "kepub.epub"
+        );
+    }
+}
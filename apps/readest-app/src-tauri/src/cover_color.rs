@@ -0,0 +1,227 @@
+//! Representative cover color, for tinting the reader UI to the book.
+//!
+//! The cover is downsampled to a small sample, pixels that are nearly white
+//! or nearly black (page margins, frames, letterboxing) are set aside, and
+//! the rest is split into color boxes by median cut. The average of the most
+//! populated box is the result. Results are cached on disk by the MD5 of the
+//! cover bytes, so a book is only analyzed once however often it's opened,
+//! and books sharing a cover share the entry.
+
+use image::imageops::FilterType;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::book_cover::read_cover_sidecar;
+use crate::epub_parser::extract_epub_cover_full_sync;
+use crate::mobi_parser::extract_mobi_cover_full_sync;
+
+/// Edge of the square the cover is downsampled to before analysis.
+const SAMPLE_EDGE: u32 = 64;
+/// Boxes median cut splits the sample into.
+const MAX_BOXES: usize = 8;
+/// Channels all above this (or all below [`NEAR_BLACK`]) count as border.
+const NEAR_WHITE: u8 = 235;
+const NEAR_BLACK: u8 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Returned for books without a usable cover: a mid grey that tints nothing.
+pub const NEUTRAL_COLOR: Rgb = Rgb {
+    r: 128,
+    g: 128,
+    b: 128,
+};
+
+fn is_border_color([r, g, b]: [u8; 3]) -> bool {
+    (r > NEAR_WHITE && g > NEAR_WHITE && b > NEAR_WHITE)
+        || (r < NEAR_BLACK && g < NEAR_BLACK && b < NEAR_BLACK)
+}
+
+/// Per-channel spread of `pixels`, as `(channel, max - min)` of the widest.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let (min, max) = pixels
+                .iter()
+                .fold((u8::MAX, 0), |(lo, hi), p| (lo.min(p[c]), hi.max(p[c])));
+            (c, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> Rgb {
+    let n = pixels.len().max(1) as u64;
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += u64::from(p[c]);
+        }
+    }
+    Rgb {
+        r: (sum[0] / n) as u8,
+        g: (sum[1] / n) as u8,
+        b: (sum[2] / n) as u8,
+    }
+}
+
+/// Median cut: repeatedly halve the box with the widest channel at that
+/// channel's median, then average the most populated box.
+fn dominant_color(mut pixels: Vec<[u8; 3]>) -> Option<Rgb> {
+    if pixels.is_empty() {
+        return None;
+    }
+    let mut boxes: Vec<Vec<[u8; 3]>> = vec![std::mem::take(&mut pixels)];
+    while boxes.len() < MAX_BOXES {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| (i, widest_channel(b)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(i, (channel, _))| (i, channel))
+        else {
+            break;
+        };
+        let mut split = boxes.swap_remove(index);
+        split.sort_unstable_by_key(|p| p[channel]);
+        let upper = split.split_off(split.len() / 2);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+    boxes.iter().max_by_key(|b| b.len()).map(|b| average(b))
+}
+
+/// Representative color of an encoded cover image. Border-like pixels are
+/// ignored unless the cover is made of nothing else.
+fn cover_color(cover: &[u8]) -> Option<Rgb> {
+    let img = image::load_from_memory(cover).ok()?;
+    let sample = img
+        .resize(SAMPLE_EDGE, SAMPLE_EDGE, FilterType::Triangle)
+        .to_rgba8();
+    let opaque: Vec<[u8; 3]> = sample
+        .pixels()
+        .filter(|p| p.0[3] >= 128)
+        .map(|p| [p.0[0], p.0[1], p.0[2]])
+        .collect();
+    let content: Vec<[u8; 3]> = opaque
+        .iter()
+        .copied()
+        .filter(|p| !is_border_color(*p))
+        .collect();
+    if content.is_empty() {
+        dominant_color(opaque)
+    } else {
+        dominant_color(content)
+    }
+}
+
+/// Cover bytes of the book at `path`: a cover set with `set_book_cover`, or
+/// the embedded one for the formats we extract natively.
+fn extract_cover(path: &str) -> Option<Vec<u8>> {
+    if let Some(cover) = read_cover_sidecar(Path::new(path)) {
+        return Some(cover.bytes);
+    }
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let cover = match ext.as_str() {
+        "epub" => extract_epub_cover_full_sync(path),
+        "mobi" | "azw" | "azw3" | "prc" | "kf8" => extract_mobi_cover_full_sync(path),
+        _ => return None,
+    };
+    cover
+        .inspect_err(|e| log::debug!("No cover for {path}: {e}"))
+        .ok()
+        .map(|cover| cover.bytes)
+}
+
+fn cache_path(cache_dir: &Path, cover: &[u8]) -> PathBuf {
+    cache_dir.join(format!("{:x}.json", Md5::digest(cover)))
+}
+
+fn cached_color(cache_dir: &Path, path: &str) -> Rgb {
+    let Some(cover) = extract_cover(path) else {
+        return NEUTRAL_COLOR;
+    };
+    let entry = cache_path(cache_dir, &cover);
+    if let Some(color) = fs::read(&entry)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Rgb>(&bytes).ok())
+    {
+        return color;
+    }
+    let Some(color) = cover_color(&cover) else {
+        return NEUTRAL_COLOR;
+    };
+    // A failed write only costs a recomputation next time.
+    let _ = fs::create_dir_all(cache_dir)
+        .and_then(|()| fs::write(&entry, serde_json::to_vec(&color).unwrap_or_default()));
+    color
+}
+
+/// Representative color of the cover of the book at `path`, or
+/// [`NEUTRAL_COLOR`] when it has none we can read.
+#[tauri::command]
+pub async fn cover_dominant_color(app: AppHandle, path: String) -> Result<Rgb, String> {
+    let cache_dir = crate::portable::cache_dir(&app)?.join("cover-colors");
+    tauri::async_runtime::spawn_blocking(move || cached_color(&cache_dir, &path))
+        .await
+        .map_err(|e| format!("join error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png(img: RgbaImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn white_margins_do_not_wash_out_the_cover() {
+        // A red cover with a wide white frame, mostly frame by area.
+        let img = RgbaImage::from_fn(100, 150, |x, y| {
+            if (30..70).contains(&x) && (40..110).contains(&y) {
+                Rgba([200, 30, 40, 255])
+            } else {
+                Rgba([250, 250, 250, 255])
+            }
+        });
+        let color = cover_color(&png(img)).unwrap();
+        assert!(color.r > 150 && color.g < 80 && color.b < 80, "{color:?}");
+    }
+
+    #[test]
+    fn largest_color_region_wins() {
+        let img = RgbaImage::from_fn(90, 90, |x, _| {
+            if x < 60 {
+                Rgba([20, 60, 160, 255])
+            } else {
+                Rgba([230, 200, 40, 255])
+            }
+        });
+        let color = cover_color(&png(img)).unwrap();
+        assert!(color.b > 120 && color.r < 80, "{color:?}");
+    }
+
+    #[test]
+    fn all_black_cover_still_has_a_color() {
+        let img = RgbaImage::from_pixel(32, 32, Rgba([5, 5, 5, 255]));
+        assert_eq!(cover_color(&png(img)), Some(Rgb { r: 5, g: 5, b: 5 }));
+        assert_eq!(cover_color(b"not an image"), None);
+        assert_eq!(dominant_color(Vec::new()), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Local crash breadcrumbs, for "attach crash info to bug report".
+//!
+//! Notable events (file opened, thumbnail generated, transfer started) are
+//! recorded with [`breadcrumb`] into a small ring buffer. When a panic
+//! happens, the hook installed by [`install`] writes the panic message, its
+//! location and the breadcrumbs to `last_crash.json` in the app data dir,
+//! then hands over to the previous hook. Nothing leaves the machine: the UI
+//! reads the report back with [`get_last_crash`] and the user decides whether
+//! to paste it anywhere.
+//!
+//! A panic inside a panic hook aborts the process, so the hook never unwraps,
+//! never blocks on the breadcrumb lock (the panicking thread may hold it) and
+//! ignores every I/O error.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Mutex, TryLockError};
+
+use tauri::AppHandle;
+
+use crate::portable;
+use crate::position_sidecar::now_millis;
+
+const CRASH_FILENAME: &str = "last_crash.json";
+/// Breadcrumbs kept; older ones are dropped first.
+const CAPACITY: usize = 32;
+/// Longest breadcrumb detail kept, in characters.
+const MAX_DETAIL_CHARS: usize = 256;
+
+static BREADCRUMBS: Mutex<VecDeque<Breadcrumb>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumb {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub message: String,
+    /// `file:line:column` of the panic, when known.
+    pub location: Option<String>,
+    pub app_version: String,
+    pub os: String,
+    /// Oldest first.
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+/// Record an event for the next crash report. `kind` is a short tag such as
+/// `"file-opened"`; `detail` is truncated to 256 characters.
+pub fn breadcrumb(kind: &str, detail: impl AsRef<str>) {
+    let crumb = Breadcrumb {
+        timestamp: now_millis(),
+        kind: kind.to_string(),
+        detail: detail.as_ref().chars().take(MAX_DETAIL_CHARS).collect(),
+    };
+    let mut crumbs = BREADCRUMBS.lock().unwrap_or_else(|e| e.into_inner());
+    if crumbs.len() == CAPACITY {
+        crumbs.pop_front();
+    }
+    crumbs.push_back(crumb);
+}
+
+/// The breadcrumbs, without waiting: empty when another thread holds the
+/// lock, or when this one does because it panicked mid-[`breadcrumb`].
+fn snapshot() -> Vec<Breadcrumb> {
+    match BREADCRUMBS.try_lock() {
+        Ok(crumbs) => crumbs.iter().cloned().collect(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().iter().cloned().collect(),
+        Err(TryLockError::WouldBlock) => Vec::new(),
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Write `report` to `dir`, via a temporary file so a crash mid-write never
+/// leaves a truncated report behind.
+fn write_report(dir: &Path, report: &CrashReport) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(report).map_err(std::io::Error::other)?;
+    std::fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!("{CRASH_FILENAME}.tmp"));
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, dir.join(CRASH_FILENAME))
+}
+
+fn read_report(dir: &Path) -> Result<Option<CrashReport>, String> {
+    let bytes = match std::fs::read(dir.join(CRASH_FILENAME)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read crash report: {e}")),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse crash report: {e}"))
+}
+
+/// Chain a panic hook that writes `last_crash.json`. The directory is
+/// resolved now, so the hook itself needs nothing from the app.
+pub fn install(app: &AppHandle) {
+    let dir = match portable::app_data_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Crash reports disabled: {e}");
+            return;
+        }
+    };
+    let app_version = app.package_info().version.to_string();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = CrashReport {
+            timestamp: now_millis(),
+            message: panic_message(info.payload()),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            app_version: app_version.clone(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            breadcrumbs: snapshot(),
+        };
+        let _ = write_report(&dir, &report);
+        previous(info);
+    }));
+}
+
+/// The report left by the last panic, or `None` when there hasn't been one.
+#[tauri::command]
+pub fn get_last_crash(app: AppHandle) -> Result<Option<CrashReport>, String> {
+    read_report(&portable::app_data_dir(&app)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn ring_keeps_the_latest_breadcrumbs() {
+        for i in 0..CAPACITY + 5 {
+            breadcrumb("file-opened", format!("book-{i}.epub"));
+        }
+        breadcrumb("transfer-started", "x".repeat(1000));
+        let crumbs = BREADCRUMBS.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(crumbs.len(), CAPACITY);
+        assert_eq!(crumbs[CAPACITY - 1].kind, "transfer-started");
+        assert_eq!(crumbs[CAPACITY - 1].detail.len(), MAX_DETAIL_CHARS);
+        assert_eq!(
+            crumbs[CAPACITY - 2].detail,
+            format!("book-{}.epub", CAPACITY + 4)
+        );
+    }
+
+    #[test]
+    fn snapshot_does_not_wait_for_a_held_lock() {
+        let _held = BREADCRUMBS.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(snapshot().is_empty());
+    }
+
+    #[test]
+    fn report_round_trips_through_the_file() {
+        let dir = temp_dir("crash-report");
+        assert_eq!(read_report(&dir).unwrap(), None);
+
+        let report = CrashReport {
+            timestamp: 1,
+            message: "index out of bounds".into(),
+            location: Some("src/lib.rs:10:5".into()),
+            app_version: "0.9.0".into(),
+            os: "linux x86_64".into(),
+            breadcrumbs: vec![Breadcrumb {
+                timestamp: 0,
+                kind: "thumbnail-generated".into(),
+                detail: "https://example.com/cover.jpg".into(),
+            }],
+        };
+        write_report(&dir, &report).unwrap();
+        assert_eq!(read_report(&dir).unwrap(), Some(report));
+        assert!(!dir.join(format!("{CRASH_FILENAME}.tmp")).exists());
+    }
+
+    #[test]
+    fn panic_message_handles_both_payload_types() {
+        let err = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(err.as_ref()), "static");
+        let n = 3;
+        let err = std::panic::catch_unwind(|| panic!("formatted {n}")).unwrap_err();
+        assert_eq!(panic_message(err.as_ref()), "formatted 3");
+    }
+}
@@ -0,0 +1,257 @@
+//! "Is Readest the default app for this format?" queries for the onboarding
+//! and settings screens.
+//!
+//! Each desktop platform keeps its own association database, so the lookup is
+//! platform-specific:
+//!   - Windows: `AssocQueryStringW(ASSOCSTR_EXECUTABLE)` for `.ext`, compared
+//!     against our own executable (the same query the thumbnail provider uses
+//!     to gate Explorer thumbnails);
+//!   - macOS: extension → UTI via `UTTypeCreatePreferredIdentifierForTag`, then
+//!     `LSCopyDefaultRoleHandlerForContentType`, compared against our bundle id;
+//!   - Linux: extension → MIME via the table below (mirrors the
+//!     `fileAssociations` in `tauri.conf.json`), then `xdg-mime query default`,
+//!     matched against our `.desktop` entry.
+//!
+//! Mobile platforms have no user-visible default-app concept for documents,
+//! so both commands report `false` there.
+//!
+//! [`default_handler`] exposes the lookup itself, for formats Readest hands
+//! to another app (`acsm`).
+
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+/// Strip a leading dot and lowercase, so `".EPUB"`, `"epub"` and `"Epub"` all
+/// resolve to the same association.
+fn normalize_extension(ext: &str) -> String {
+    ext.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// MIME types registered for each extension in `tauri.conf.json`, plus the
+/// Adobe fulfillment tickets handed to other apps.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mime_for_extension(ext: &str) -> Option<&'static str> {
+    match ext {
+        "acsm" => Some("application/vnd.adobe.adept+xml"),
+        "epub" => Some("application/epub+zip"),
+        "mobi" => Some("application/x-mobipocket-ebook"),
+        "azw" => Some("application/vnd.amazon.ebook"),
+        "azw3" => Some("application/vnd.amazon.mobi8-ebook"),
+        "fb2" => Some("application/x-fictionbook+xml"),
+        "cbz" => Some("application/vnd.comicbook+zip"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+/// Executable path of the handler.
+#[cfg(target_os = "windows")]
+fn query_default_handler(ext: &str) -> Option<String> {
+    use ::windows::core::{PCWSTR, PWSTR};
+    use ::windows::Win32::UI::Shell::{AssocQueryStringW, ASSOCF_NONE, ASSOCSTR_EXECUTABLE};
+
+    let ext_wide: Vec<u16> = format!(".{ext}")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut buffer = [0u16; 260];
+    let mut buffer_size = buffer.len() as u32;
+
+    let result = unsafe {
+        AssocQueryStringW(
+            ASSOCF_NONE,
+            ASSOCSTR_EXECUTABLE,
+            PCWSTR(ext_wide.as_ptr()),
+            None,
+            Some(PWSTR(buffer.as_mut_ptr())),
+            &mut buffer_size,
+        )
+    };
+    if result.is_err() {
+        return None;
+    }
+
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len])).filter(|handler| !handler.is_empty())
+}
+
+#[cfg(target_os = "windows")]
+fn is_own_handler(_app: &AppHandle, handler: &str) -> bool {
+    let Some(own_exe) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()))
+    else {
+        return false;
+    };
+    std::path::Path::new(handler)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase() == own_exe)
+        .unwrap_or(false)
+}
+
+/// Bundle id of the handler.
+#[cfg(target_os = "macos")]
+fn query_default_handler(ext: &str) -> Option<String> {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use std::ffi::{c_void, CStr};
+
+    type CFStringRef = *const c_void;
+    const K_LS_ROLES_ALL: u32 = 0xFFFF_FFFF;
+
+    #[link(name = "CoreServices", kind = "framework")]
+    extern "C" {
+        static kUTTagClassFilenameExtension: CFStringRef;
+        fn UTTypeCreatePreferredIdentifierForTag(
+            tag_class: CFStringRef,
+            tag: CFStringRef,
+            conforming_to: CFStringRef,
+        ) -> CFStringRef;
+        fn LSCopyDefaultRoleHandlerForContentType(
+            content_type: CFStringRef,
+            role: u32,
+        ) -> CFStringRef;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    unsafe {
+        // NSString is toll-free bridged to CFString.
+        let tag: id = NSString::alloc(nil).init_str(ext);
+        let uti = UTTypeCreatePreferredIdentifierForTag(
+            kUTTagClassFilenameExtension,
+            tag as CFStringRef,
+            std::ptr::null(),
+        );
+        let _: () = msg_send![tag, release];
+        if uti.is_null() {
+            return None;
+        }
+
+        let handler = LSCopyDefaultRoleHandlerForContentType(uti, K_LS_ROLES_ALL);
+        CFRelease(uti);
+        if handler.is_null() {
+            return None;
+        }
+
+        let utf8 = (handler as id).UTF8String();
+        let bundle_id = if utf8.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(utf8).to_string_lossy().into_owned()
+        };
+        CFRelease(handler);
+
+        Some(bundle_id).filter(|id| !id.is_empty())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn is_own_handler(app: &AppHandle, handler: &str) -> bool {
+    handler.eq_ignore_ascii_case(&app.config().identifier)
+}
+
+/// `.desktop` entry of the handler.
+#[cfg(target_os = "linux")]
+fn query_default_handler(ext: &str) -> Option<String> {
+    let mime = mime_for_extension(ext)?;
+    let output = match std::process::Command::new("xdg-mime")
+        .args(["query", "default", mime])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(_) => return None,
+        Err(e) => {
+            log::warn!("xdg-mime query failed: {e}");
+            return None;
+        }
+    };
+    let desktop_entry = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(desktop_entry).filter(|entry| !entry.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+fn is_own_handler(app: &AppHandle, handler: &str) -> bool {
+    let desktop_entry = handler.to_lowercase();
+    // Bundles install the entry as either `readest.desktop` or
+    // `<identifier>.desktop` (Flatpak), depending on the packaging.
+    desktop_entry.contains("readest")
+        || desktop_entry.contains(&app.config().identifier.to_lowercase())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn query_default_handler(_ext: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn is_own_handler(_app: &AppHandle, _handler: &str) -> bool {
+    false
+}
+
+/// The system's default app for files with `ext` (with or without the
+/// leading dot), as the platform names it: executable path on Windows,
+/// bundle id on macOS, `.desktop` entry on Linux. `None` when there is none.
+pub(crate) fn default_handler(ext: &str) -> Option<String> {
+    let ext = normalize_extension(ext);
+    if ext.is_empty() {
+        return None;
+    }
+    query_default_handler(&ext)
+}
+
+fn query_default_reader(app: &AppHandle, ext: &str) -> bool {
+    query_default_handler(ext).is_some_and(|handler| is_own_handler(app, &handler))
+}
+
+/// Whether Readest is the system default handler for files with `ext`
+/// (with or without the leading dot).
+#[tauri::command]
+pub fn is_default_reader(app: AppHandle, ext: String) -> bool {
+    let ext = normalize_extension(&ext);
+    !ext.is_empty() && query_default_reader(&app, &ext)
+}
+
+/// Batch form of [`is_default_reader`] for the settings screen. Keys are the
+/// normalized extensions (no dot, lowercase).
+#[tauri::command]
+pub fn default_reader_status(app: AppHandle, exts: Vec<String>) -> HashMap<String, bool> {
+    exts.iter()
+        .map(|ext| normalize_extension(ext))
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| {
+            let is_default = query_default_reader(&app, &ext);
+            (ext, is_default)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mime_for_extension, normalize_extension};
+
+    #[test]
+    fn normalizes_dot_and_case() {
+        assert_eq!(normalize_extension(".EPUB"), "epub");
+        assert_eq!(normalize_extension("Azw3"), "azw3");
+        assert_eq!(normalize_extension(" .pdf "), "pdf");
+        assert_eq!(normalize_extension("."), "");
+    }
+
+    #[test]
+    fn maps_registered_extensions_to_mime() {
+        assert_eq!(mime_for_extension("epub"), Some("application/epub+zip"));
+        assert_eq!(
+            mime_for_extension("fb2"),
+            Some("application/x-fictionbook+xml")
+        );
+        assert_eq!(
+            mime_for_extension("acsm"),
+            Some("application/vnd.adobe.adept+xml")
+        );
+        assert_eq!(mime_for_extension("docx"), None);
+    }
+}
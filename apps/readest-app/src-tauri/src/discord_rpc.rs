@@ -1,24 +1,56 @@
 #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 use serde::Deserialize;
-use std::sync::{Arc, Mutex};
-use tauri::State;
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::book_metadata::{format_authors, Creator};
 
 const DISCORD_APP_ID: &str = "1462683110612144348";
 const MAX_TITLE_LENGTH: usize = 128;
 const MAX_AUTHOR_LENGTH: usize = 128;
+/// How long the exit hook waits for an in-flight presence command to release
+/// the client before leaving the activity for Discord to time out.
+const SHUTDOWN_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+/// Discord accepts about five activity updates per 20 seconds. Page turns
+/// within a book are coalesced and sent at most this often by default.
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(15);
+/// Opening a different book is shown as soon as the rate limit allows.
+const BOOK_CHANGE_INTERVAL: Duration = Duration::from_secs(4);
+/// Asset shown when the book has no usable cover, or is private.
+const DEFAULT_LARGE_IMAGE: &str = "book_icon";
+/// All a private book's activity says.
+const PRIVATE_BOOK_DETAILS: &str = "Reading a book";
 
 #[derive(Debug)]
 pub struct DiscordRpcClient {
     client: Option<DiscordIpcClient>,
     current_book_hash: Option<String>,
+    /// The latest presence asked for that hasn't been sent yet; intermediate
+    /// ones are dropped.
+    pending: Option<BookPresenceData>,
+    /// When a timer will send `pending`.
+    flush_at: Option<Instant>,
+    last_flush: Option<Instant>,
+    update_interval: Duration,
 }
 
 impl DiscordRpcClient {
     pub fn new() -> Self {
+        Self::with_update_interval(DEFAULT_UPDATE_INTERVAL)
+    }
+
+    /// A client that sends updates within the same book at most once per
+    /// `update_interval`.
+    pub fn with_update_interval(update_interval: Duration) -> Self {
         DiscordRpcClient {
             client: None,
             current_book_hash: None,
+            pending: None,
+            flush_at: None,
+            last_flush: None,
+            update_interval: update_interval.max(BOOK_CHANGE_INTERVAL),
         }
     }
 
@@ -49,6 +81,104 @@ impl DiscordRpcClient {
         self.current_book_hash = None;
     }
 
+    /// Clear the activity and close the IPC connection. Safe to call more than
+    /// once; later calls find no client and only reset the book hash.
+    fn shutdown(&mut self) {
+        self.pending = None;
+        self.flush_at = None;
+        if let Some(ref mut client) = self.client {
+            match client.clear_activity() {
+                Ok(_) => log::info!("Cleared Discord presence on exit"),
+                Err(e) => log::warn!("Failed to clear Discord presence on exit: {}", e),
+            }
+        }
+        self.disconnect();
+    }
+
+    /// When `presence` may be sent: right away if nothing has been sent yet,
+    /// [`BOOK_CHANGE_INTERVAL`] after the last update for a different book,
+    /// and the update interval after it for the book already shown.
+    fn due_at(&self, presence: &BookPresenceData, now: Instant) -> Instant {
+        let Some(last) = self.last_flush else {
+            return now;
+        };
+        let interval = if self.current_book_hash.as_deref() == Some(presence.book_hash.as_str()) {
+            self.update_interval
+        } else {
+            BOOK_CHANGE_INTERVAL
+        };
+        (last + interval).max(now)
+    }
+
+    /// Make `presence` the one to show, replacing any update not yet sent.
+    /// Returns how long until it is due, or `None` when the flush already
+    /// scheduled comes soon enough.
+    fn queue(&mut self, presence: BookPresenceData, now: Instant) -> Option<Duration> {
+        let due = self.due_at(&presence, now);
+        self.pending = Some(presence);
+        if matches!(self.flush_at, Some(at) if at <= due) {
+            return None;
+        }
+        self.flush_at = Some(due);
+        Some(due - now)
+    }
+
+    /// Send the pending presence if its time has come.
+    fn flush_due(&mut self, now: Instant) -> Result<(), String> {
+        if !matches!(self.flush_at, Some(at) if at <= now) {
+            return Ok(());
+        }
+        self.flush_at = None;
+        match self.pending.take() {
+            Some(presence) => self.send(presence),
+            None => Ok(()),
+        }
+    }
+
+    fn send(&mut self, presence: BookPresenceData) -> Result<(), String> {
+        if let Err(e) = self.ensure_connected() {
+            log::debug!("Discord not available: {}", e);
+            return Ok(());
+        }
+
+        let text = PresenceText::for_book(&presence);
+        let mut activity_builder = activity::Activity::new().details(&text.details);
+
+        if let Some(ref state_text) = text.state {
+            activity_builder = activity_builder.state(state_text);
+        }
+
+        activity_builder = activity_builder
+            .timestamps(activity::Timestamps::new().start(presence.session_start / 1000));
+
+        let assets_builder = activity::Assets::new()
+            .large_image(&text.large_image)
+            .large_text(&text.large_text);
+
+        activity_builder = activity_builder.assets(assets_builder);
+
+        let button = activity::Button::new("Read on Readest", "https://web.readest.com");
+        activity_builder = activity_builder.buttons(vec![button]);
+
+        if let Some(ref mut discord_client) = self.client {
+            match discord_client.set_activity(activity_builder) {
+                Ok(_) => {
+                    log::info!("Successfully updated Discord presence");
+                    self.current_book_hash = Some(presence.book_hash);
+                    self.last_flush = Some(Instant::now());
+                    Ok(())
+                }
+                Err(e) => {
+                    log::error!("Failed to update Discord activity: {}", e);
+                    self.disconnect();
+                    Err(format!("Failed to update Discord activity: {}", e))
+                }
+            }
+        } else {
+            Err("Discord client not initialized".to_string())
+        }
+    }
+
     fn truncate_string(s: &str, max_len: usize) -> String {
         if s.len() <= max_len {
             s.to_string()
@@ -58,14 +188,65 @@ impl DiscordRpcClient {
     }
 }
 
+/// What the activity shows for a book.
+#[derive(Debug, PartialEq, Eq)]
+struct PresenceText {
+    details: String,
+    state: Option<String>,
+    large_image: String,
+    large_text: String,
+}
+
+impl PresenceText {
+    fn for_book(presence: &BookPresenceData) -> Self {
+        if presence.is_private {
+            return PresenceText {
+                details: PRIVATE_BOOK_DETAILS.to_string(),
+                state: None,
+                large_image: DEFAULT_LARGE_IMAGE.to_string(),
+                large_text: PRIVATE_BOOK_DETAILS.to_string(),
+            };
+        }
+
+        let author = format_authors(&presence.creators).or_else(|| presence.author.clone());
+        // Truncate title and author to avoid Discord API limits
+        let title = DiscordRpcClient::truncate_string(&presence.title, MAX_TITLE_LENGTH);
+        let state = author.map(|author_name| {
+            let truncated_author =
+                DiscordRpcClient::truncate_string(&author_name, MAX_AUTHOR_LENGTH);
+            format!("by {}", truncated_author)
+        });
+        let large_image = presence
+            .cover_url
+            .as_deref()
+            .filter(|url| url.starts_with("https://"))
+            .unwrap_or(DEFAULT_LARGE_IMAGE)
+            .to_string();
+        PresenceText {
+            details: title.clone(),
+            state,
+            large_image,
+            large_text: title,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BookPresenceData {
     book_hash: String,
     title: String,
     author: Option<String>,
+    /// When given, the authors shown are the `aut` creators among these,
+    /// joined as "A, B & C"; `author` is the fallback.
+    #[serde(default)]
+    creators: Vec<Creator>,
     cover_url: Option<String>,
     session_start: i64,
+    /// Hide this book: the activity only says a book is being read, with no
+    /// title, author or cover. The session timer still runs.
+    #[serde(default)]
+    is_private: bool,
 }
 
 #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
@@ -78,66 +259,43 @@ pub async fn update_book_presence(
         .lock()
         .map_err(|e| format!("Mutex lock error: {}", e))?;
 
-    if let Err(e) = client.ensure_connected() {
-        log::debug!("Discord not available: {}", e);
-        return Ok(());
-    }
-
-    let BookPresenceData {
-        book_hash,
-        title,
-        author,
-        cover_url,
-        session_start,
-    } = presence;
-
-    // Truncate title and author to avoid Discord API limits
-    let truncated_title = DiscordRpcClient::truncate_string(&title, MAX_TITLE_LENGTH);
-    let state_text = if let Some(ref author_name) = author {
-        let truncated_author = DiscordRpcClient::truncate_string(author_name, MAX_AUTHOR_LENGTH);
-        format!("by {}", truncated_author)
-    } else {
-        String::new()
-    };
-
-    let mut activity_builder = activity::Activity::new().details(&truncated_title);
-
-    if !state_text.is_empty() {
-        activity_builder = activity_builder.state(&state_text);
+    let now = Instant::now();
+    match client.queue(presence, now) {
+        Some(delay) if delay.is_zero() => client.flush_due(now),
+        Some(delay) => {
+            schedule_flush(state.inner().clone(), delay);
+            Ok(())
+        }
+        None => Ok(()),
     }
+}
 
-    activity_builder =
-        activity_builder.timestamps(activity::Timestamps::new().start(session_start / 1000));
-
-    let large_image = cover_url
-        .as_deref()
-        .filter(|url| url.starts_with("https://"))
-        .unwrap_or("book_icon");
-    let assets_builder = activity::Assets::new()
-        .large_image(large_image)
-        .large_text(&truncated_title);
-
-    activity_builder = activity_builder.assets(assets_builder);
-
-    let button = activity::Button::new("Read on Readest", "https://web.readest.com");
-    activity_builder = activity_builder.buttons(vec![button]);
-
-    if let Some(ref mut discord_client) = client.client {
-        match discord_client.set_activity(activity_builder) {
-            Ok(_) => {
-                log::info!("Successfully updated Discord presence");
-                client.current_book_hash = Some(book_hash);
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Failed to update Discord activity: {}", e);
-                client.disconnect();
-                Err(format!("Failed to update Discord activity: {}", e))
-            }
+/// Send whatever presence is pending once `delay` has passed. A flush that
+/// was moved earlier or already happened makes this a no-op.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn schedule_flush(state: Arc<Mutex<DiscordRpcClient>>, delay: Duration) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let mut client = state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = client.flush_due(Instant::now()) {
+            log::warn!("Deferred Discord presence update failed: {}", e);
         }
-    } else {
-        Err("Discord client not initialized".to_string())
-    }
+    });
+}
+
+/// Change how often presence updates within the same book reach Discord.
+/// Never goes below the interval used for switching books.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+#[tauri::command]
+pub async fn set_presence_update_interval(
+    state: State<'_, Arc<Mutex<DiscordRpcClient>>>,
+    seconds: u64,
+) -> Result<(), String> {
+    let mut client = state
+        .lock()
+        .map_err(|e| format!("Mutex lock error: {}", e))?;
+    client.update_interval = Duration::from_secs(seconds).max(BOOK_CHANGE_INTERVAL);
+    Ok(())
 }
 
 #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
@@ -149,11 +307,16 @@ pub async fn clear_book_presence(
         .lock()
         .map_err(|e| format!("Mutex lock error: {}", e))?;
 
+    // A clear supersedes any update still waiting on the rate limit.
+    client.pending = None;
+    client.flush_at = None;
+
     if let Some(ref mut discord_client) = client.client {
         match discord_client.clear_activity() {
             Ok(_) => {
                 log::info!("Successfully cleared Discord presence");
                 client.current_book_hash = None;
+                client.last_flush = Some(Instant::now());
                 Ok(())
             }
             Err(e) => {
@@ -169,14 +332,129 @@ pub async fn clear_book_presence(
     }
 }
 
+/// Clear the presence and disconnect before the process terminates, so the
+/// user isn't shown as "reading" after quitting. Called from the app's exit
+/// events; never blocks longer than [`SHUTDOWN_LOCK_TIMEOUT`] on the mutex.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn shutdown_presence<R: Runtime>(app: &AppHandle<R>) {
+    let Some(state) = app.try_state::<Arc<Mutex<DiscordRpcClient>>>() else {
+        return;
+    };
+
+    let deadline = Instant::now() + SHUTDOWN_LOCK_TIMEOUT;
+    loop {
+        match state.try_lock() {
+            Ok(mut client) => return client.shutdown(),
+            // A command panicked mid-update; the connection is still ours to close.
+            Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner().shutdown(),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(TryLockError::WouldBlock) => {
+                log::warn!("Discord client busy during shutdown, skipping presence cleanup");
+                return;
+            }
+        }
+    }
+}
+
 #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 #[tauri::command]
 pub async fn update_book_presence(_presence: BookPresenceData) -> Result<(), String> {
     Ok(()) // No-op on non-desktop platforms
 }
 
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+#[tauri::command]
+pub async fn set_presence_update_interval(_seconds: u64) -> Result<(), String> {
+    Ok(()) // No-op on non-desktop platforms
+}
+
 #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 #[tauri::command]
 pub async fn clear_book_presence() -> Result<(), String> {
     Ok(()) // No-op on non-desktop platforms
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presence(book_hash: &str) -> BookPresenceData {
+        BookPresenceData {
+            book_hash: book_hash.to_string(),
+            title: book_hash.to_string(),
+            author: None,
+            creators: Vec::new(),
+            cover_url: None,
+            session_start: 0,
+            is_private: false,
+        }
+    }
+
+    #[test]
+    fn coalesces_updates_and_hurries_book_changes() {
+        let mut client = DiscordRpcClient::with_update_interval(Duration::from_secs(30));
+        let start = Instant::now();
+        assert_eq!(client.queue(presence("a"), start), Some(Duration::ZERO));
+
+        // As if "a" had just been sent.
+        client.flush_at = None;
+        client.pending = None;
+        client.current_book_hash = Some("a".to_string());
+        client.last_flush = Some(start);
+
+        let now = start + Duration::from_secs(1);
+        assert_eq!(
+            client.queue(presence("a"), now),
+            Some(Duration::from_secs(29))
+        );
+        // A second page turn rides on the timer already running.
+        assert_eq!(client.queue(presence("a"), now), None);
+        // A new book moves the flush up.
+        assert_eq!(
+            client.queue(presence("b"), now),
+            Some(BOOK_CHANGE_INTERVAL - Duration::from_secs(1))
+        );
+        assert_eq!(client.queue(presence("b"), now), None);
+        assert_eq!(client.pending.as_ref().unwrap().book_hash, "b");
+
+        // The timer scheduled for "a" finds nothing due yet.
+        assert!(client.flush_due(now).is_ok());
+        assert!(client.pending.is_some());
+    }
+
+    #[test]
+    fn private_books_are_redacted() {
+        let mut book = presence("a");
+        book.title = "A Sensitive Title".to_string();
+        book.author = Some("Someone".to_string());
+        book.cover_url = Some("https://covers.example/a.jpg".to_string());
+        assert_eq!(
+            PresenceText::for_book(&book),
+            PresenceText {
+                details: "A Sensitive Title".to_string(),
+                state: Some("by Someone".to_string()),
+                large_image: "https://covers.example/a.jpg".to_string(),
+                large_text: "A Sensitive Title".to_string(),
+            }
+        );
+
+        book.is_private = true;
+        assert_eq!(
+            PresenceText::for_book(&book),
+            PresenceText {
+                details: PRIVATE_BOOK_DETAILS.to_string(),
+                state: None,
+                large_image: DEFAULT_LARGE_IMAGE.to_string(),
+                large_text: PRIVATE_BOOK_DETAILS.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn update_interval_never_beats_the_rate_limit() {
+        let client = DiscordRpcClient::with_update_interval(Duration::ZERO);
+        assert_eq!(client.update_interval, BOOK_CHANGE_INTERVAL);
+    }
+}
@@ -0,0 +1,176 @@
+//! Free space on the volume holding a path, and the size of the thumbnail
+//! cache, so the app can warn before a restore or download that won't fit.
+//!
+//! [`available_disk_space`] asks the OS about the volume of the given path
+//! (`GetDiskFreeSpaceExW` on Windows, `statvfs` elsewhere). The path need
+//! not exist yet: the nearest existing ancestor is queried, so a download
+//! target can be checked before the file is created. `transfer_file` uses
+//! [`ensure_space_for`] to fail a download whose size is known up front.
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    /// Bytes this process may still write; quotas and reserved blocks are
+    /// already taken off.
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// `path` itself if it exists, otherwise its closest existing ancestor.
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|dir| !dir.as_os_str().is_empty() && dir.exists())
+}
+
+/// Space on the volume containing `path`.
+pub fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    let dir = existing_ancestor(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no existing directory above {}", path.display()),
+        )
+    })?;
+    volume_space(dir)
+}
+
+#[cfg(target_os = "windows")]
+fn volume_space(dir: &Path) -> io::Result<DiskSpace> {
+    use ::windows::core::PCWSTR;
+    use ::windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide: Vec<u16> = dir
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available = 0u64;
+    let mut total = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(wide.as_ptr()),
+            Some(&mut available),
+            Some(&mut total),
+            None,
+        )
+    }
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(DiskSpace {
+        available_bytes: available,
+        total_bytes: total,
+    })
+}
+
+#[cfg(unix)]
+fn volume_space(dir: &Path) -> io::Result<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The field widths differ between platforms (32-bit block counts on
+    // macOS), so the casts are only redundant on some of them.
+    #[allow(clippy::unnecessary_cast)]
+    let (block, available, total) = (
+        stat.f_frsize as u64,
+        stat.f_bavail as u64,
+        stat.f_blocks as u64,
+    );
+    Ok(DiskSpace {
+        available_bytes: available.saturating_mul(block),
+        total_bytes: total.saturating_mul(block),
+    })
+}
+
+/// Fail when `needed` bytes won't fit next to `path`. A volume that can't
+/// be queried doesn't block the write; the error comes from the write
+/// itself then.
+pub fn ensure_space_for(path: &Path, needed: u64) -> Result<(), (u64, u64)> {
+    match disk_space(path) {
+        Ok(space) if space.available_bytes < needed => Err((needed, space.available_bytes)),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            log::debug!("could not read free space for {}: {e}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Total size of the files under `dir`; 0 when it doesn't exist. Symlinks
+/// are not followed.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map_or(0, |m| m.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[tauri::command]
+pub async fn available_disk_space(path: String) -> Result<DiskSpace, String> {
+    tauri::async_runtime::spawn_blocking(move || disk_space(Path::new(&path)))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+        .map_err(|e| format!("failed to read disk space: {e}"))
+}
+
+/// Bytes used by the Explorer thumbnail provider's cache. Only Windows has
+/// one, so this is 0 elsewhere.
+#[tauri::command]
+pub async fn cache_usage(app: AppHandle) -> Result<u64, String> {
+    #[cfg(desktop)]
+    let dir = crate::app_reset::thumbnail_cache_dir(&app);
+    #[cfg(not(desktop))]
+    let dir: Option<std::path::PathBuf> = {
+        let _ = app;
+        None
+    };
+    tauri::async_runtime::spawn_blocking(move || dir.as_deref().map_or(0, dir_size))
+        .await
+        .map_err(|e| format!("join error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn space_and_usage_are_measured() {
+        let dir = temp_dir("disk-space");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a.bin"), [0u8; 100]).unwrap();
+        std::fs::write(dir.join("nested").join("b.bin"), [0u8; 23]).unwrap();
+        assert_eq!(dir_size(&dir), 123);
+        assert_eq!(dir_size(&dir.join("missing")), 0);
+
+        // A file that doesn't exist yet is measured on its parent's volume.
+        let target = dir.join("not").join("yet").join("book.epub");
+        assert_eq!(existing_ancestor(&target), Some(dir.as_path()));
+        let space = disk_space(&target).unwrap();
+        assert!(space.total_bytes > 0);
+        assert!(space.available_bytes <= space.total_bytes);
+
+        assert!(ensure_space_for(&target, 0).is_ok());
+        assert!(matches!(
+            ensure_space_for(&target, u64::MAX),
+            Err((u64::MAX, _))
+        ));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,235 @@
+// `read_accessibility`: the schema.org accessibility metadata an EPUB
+// declares, so the library can show an accessibility badge and summary
+// before the book is opened.
+//
+// The EPUB Accessibility spec puts these in the OPF `<metadata>`:
+//
+//   - EPUB 3: `<meta property="schema:accessibilityFeature">tableOfContents</meta>`;
+//   - EPUB 2: `<meta name="schema:accessibilityFeature" content="tableOfContents"/>`.
+//
+// Both forms are read, repeated properties accumulate, and values are kept
+// as written (`alternativeText`, `noFlashingHazard`, …) for the UI to
+// localize. A book that declares none of them comes back with `specified`
+// unset rather than an error, as do formats other than EPUB.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::{
+    collapse_whitespace, local_name_eq, read_rootfile_path, read_zip_entry, strip_xml_bom,
+};
+use crate::parser_common::attribute;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessibilityInfo {
+    /// The book declares at least one of the properties below. When unset
+    /// its accessibility is unknown, not absent.
+    pub specified: bool,
+    /// `schema:accessibilityFeature`, e.g. `structuralNavigation`.
+    pub features: Vec<String>,
+    /// `schema:accessibilityHazard`, e.g. `none` or `flashing`.
+    pub hazards: Vec<String>,
+    /// `schema:accessMode`, e.g. `textual`, `visual`.
+    pub access_modes: Vec<String>,
+    /// `schema:accessModeSufficient`; each entry is one sufficient
+    /// combination, e.g. `textual,visual`.
+    pub access_modes_sufficient: Vec<String>,
+    /// `schema:accessibilitySummary`, free text.
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    Feature,
+    Hazard,
+    AccessMode,
+    AccessModeSufficient,
+    Summary,
+}
+
+impl Property {
+    /// The property a `property`/`name` attribute names, with or without
+    /// the `schema:` prefix.
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().strip_prefix("schema:").unwrap_or(value.trim()) {
+            "accessibilityFeature" => Some(Self::Feature),
+            "accessibilityHazard" => Some(Self::Hazard),
+            "accessMode" => Some(Self::AccessMode),
+            "accessModeSufficient" => Some(Self::AccessModeSufficient),
+            "accessibilitySummary" => Some(Self::Summary),
+            _ => None,
+        }
+    }
+}
+
+impl AccessibilityInfo {
+    fn add(&mut self, property: Property, value: String) {
+        let value = collapse_whitespace(&value);
+        if value.is_empty() {
+            return;
+        }
+        self.specified = true;
+        let list = match property {
+            Property::Feature => &mut self.features,
+            Property::Hazard => &mut self.hazards,
+            Property::AccessMode => &mut self.access_modes,
+            Property::AccessModeSufficient => &mut self.access_modes_sufficient,
+            Property::Summary => {
+                self.summary.get_or_insert(value);
+                return;
+            }
+        };
+        if !list.contains(&value) {
+            list.push(value);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn read_accessibility(path: String) -> Result<AccessibilityInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || read_accessibility_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn read_accessibility_sync(file_path: &str) -> Result<AccessibilityInfo, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let is_epub = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"));
+    if !is_epub {
+        return Ok(AccessibilityInfo::default());
+    }
+
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf_bytes =
+        read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    parse_accessibility(&opf_bytes)
+}
+
+/// EPUB 2 form: the property in `name`, the value in `content`.
+fn legacy_meta(e: &BytesStart) -> Option<(Property, String)> {
+    let property = Property::parse(&attribute(e, b"name")?)?;
+    Some((property, attribute(e, b"content")?))
+}
+
+/// Accessibility properties in the OPF `<metadata>`.
+fn parse_accessibility(opf_bytes: &[u8]) -> Result<AccessibilityInfo, String> {
+    let normalized = strip_xml_bom(opf_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut info = AccessibilityInfo::default();
+    let mut in_metadata = false;
+    // The EPUB 3 property whose text is being read, if any.
+    let mut capturing: Option<Property> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                if local_name_eq(e.name().as_ref(), b"metadata") {
+                    in_metadata = true;
+                } else if in_metadata && local_name_eq(e.name().as_ref(), b"meta") {
+                    if let Some((property, value)) = legacy_meta(&e) {
+                        info.add(property, value);
+                    } else {
+                        capturing = attribute(&e, b"property").and_then(|p| Property::parse(&p));
+                        text.clear();
+                    }
+                }
+            }
+            Ok(Event::Empty(e)) if in_metadata && local_name_eq(e.name().as_ref(), b"meta") => {
+                if let Some((property, value)) = legacy_meta(&e) {
+                    info.add(property, value);
+                }
+            }
+            Ok(Event::Text(t)) if capturing.is_some() => {
+                text.push_str(&t.unescape().map_err(|e| format!("xml: {e}"))?);
+            }
+            Ok(Event::CData(t)) if capturing.is_some() => {
+                text.push_str(&String::from_utf8_lossy(&t));
+            }
+            Ok(Event::End(e)) => {
+                if local_name_eq(e.name().as_ref(), b"metadata") {
+                    break;
+                }
+                if local_name_eq(e.name().as_ref(), b"meta") {
+                    if let Some(property) = capturing.take() {
+                        info.add(property, std::mem::take(&mut text));
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_epub3_and_epub2_properties() {
+        let opf = br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Accessible</dc:title>
+    <meta property="schema:accessMode">textual</meta>
+    <meta property="schema:accessMode">visual</meta>
+    <meta property="schema:accessModeSufficient">textual</meta>
+    <meta property="schema:accessibilityFeature">structuralNavigation</meta>
+    <meta property="schema:accessibilityFeature">alternativeText</meta>
+    <meta property="schema:accessibilityHazard">none</meta>
+    <meta property="schema:accessibilitySummary">
+      All images have   alt text &amp; long descriptions.
+    </meta>
+    <meta name="schema:accessibilityFeature" content="tableOfContents"/>
+    <meta name="schema:accessibilityFeature" content="alternativeText"/>
+    <meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>
+  </metadata>
+  <manifest/>
+</package>"#;
+        let info = parse_accessibility(opf).unwrap();
+        assert_eq!(
+            info,
+            AccessibilityInfo {
+                specified: true,
+                features: vec![
+                    "structuralNavigation".into(),
+                    "alternativeText".into(),
+                    "tableOfContents".into(),
+                ],
+                hazards: vec!["none".into()],
+                access_modes: vec!["textual".into(), "visual".into()],
+                access_modes_sufficient: vec!["textual".into()],
+                summary: Some("All images have alt text & long descriptions.".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn books_without_metadata_are_unspecified() {
+        let opf = br#"<package><metadata><dc:title xmlns:dc="x">Plain</dc:title>
+            <meta name="cover" content="img"/></metadata></package>"#;
+        assert_eq!(
+            parse_accessibility(opf).unwrap(),
+            AccessibilityInfo::default()
+        );
+    }
+}
@@ -0,0 +1,791 @@
+// `list_embedded_fonts`: the fonts an EPUB ships, for the "fonts used" panel
+// and for diagnosing books that render with fallback fonts.
+//
+// Every `.ttf`/`.otf`/`.woff`/`.woff2` entry in the zip is reported with its
+// uncompressed size and the family/style from its `name` table. Publishers
+// often obfuscate embedded fonts (OCF font obfuscation, listed in
+// `META-INF/encryption.xml`); those are de-obfuscated in memory to read the
+// names, using the key derived from the OPF identifier:
+//
+//   - IDPF: SHA-1 of the `unique-identifier` with whitespace removed, XORed
+//     over the first 1040 bytes;
+//   - Adobe: the 16 bytes of the book's `urn:uuid:` identifier, XORed over
+//     the first 1024 bytes.
+//
+// Fonts under any other algorithm are DRM-encrypted; they're listed without
+// names. Non-EPUB formats have no embedded font files and return an empty
+// list.
+
+use percent_encoding::percent_decode;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::{
+    local_name_eq, read_rootfile_path, read_zip_entry, resolve_relative, strip_xml_bom,
+};
+
+const IDPF_ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+const ADOBE_ALGORITHM: &str = "http://ns.adobe.com/pdf/enc#RC";
+const IDPF_OBFUSCATED_LEN: usize = 1040;
+const ADOBE_OBFUSCATED_LEN: usize = 1024;
+
+/// Upper bound for a decompressed WOFF/WOFF2 table, against zip-bomb fonts.
+const MAX_TABLE_LEN: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontFormat {
+    Ttf,
+    Otf,
+    Woff,
+    Woff2,
+}
+
+impl FontFormat {
+    fn from_path(path: &str) -> Option<Self> {
+        let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        match ext.as_str() {
+            "ttf" => Some(Self::Ttf),
+            "otf" => Some(Self::Otf),
+            "woff" => Some(Self::Woff),
+            "woff2" => Some(Self::Woff2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Obfuscation {
+    Idpf,
+    Adobe,
+    /// Encrypted with an algorithm we can't undo (DRM).
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontInfo {
+    /// Zip path of the font file.
+    pub path: String,
+    pub format: FontFormat,
+    /// Typographic family (name ID 16), else the legacy family (ID 1).
+    pub family: Option<String>,
+    /// Typographic subfamily (ID 17), else the legacy subfamily (ID 2),
+    /// e.g. "Bold Italic".
+    pub style: Option<String>,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    pub obfuscated: bool,
+    pub obfuscation: Option<Obfuscation>,
+}
+
+#[tauri::command]
+pub async fn list_embedded_fonts(path: String) -> Result<Vec<FontInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || list_embedded_fonts_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn list_embedded_fonts_sync(file_path: &str) -> Result<Vec<FontInfo>, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let is_epub = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"));
+    if !is_epub {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    list_fonts(&mut zip)
+}
+
+fn list_fonts<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Vec<FontInfo>, String> {
+    let encrypted = read_zip_entry(zip, "META-INF/encryption.xml")
+        .map(|bytes| parse_encryption(&bytes))
+        .unwrap_or_default();
+    // Only needed to undo obfuscation, so skip the OPF when nothing is.
+    let identifiers = if encrypted.is_empty() {
+        BookIdentifiers::default()
+    } else {
+        read_identifiers(zip).unwrap_or_else(|e| {
+            log::warn!("No identifiers to de-obfuscate fonts: {e}");
+            BookIdentifiers::default()
+        })
+    };
+
+    let mut fonts = Vec::new();
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("entry {index}: {e}"))?;
+        let name = entry.name().to_string();
+        let Some(format) = FontFormat::from_path(&name).filter(|_| entry.is_file()) else {
+            continue;
+        };
+        let size = entry.size();
+        let mut bytes = Vec::with_capacity(size as usize);
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("read {name}: {e}"))?;
+
+        let obfuscation = encrypted.get(&name).copied();
+        let readable = match obfuscation {
+            None => true,
+            Some(Obfuscation::Idpf) => identifiers.unique.as_deref().is_some_and(|uid| {
+                xor_prefix(&mut bytes, &idpf_key(uid), IDPF_OBFUSCATED_LEN);
+                true
+            }),
+            Some(Obfuscation::Adobe) => adobe_key(&identifiers.all).is_some_and(|key| {
+                xor_prefix(&mut bytes, &key, ADOBE_OBFUSCATED_LEN);
+                true
+            }),
+            Some(Obfuscation::Unknown) => false,
+        };
+        let names = if readable {
+            font_names(&bytes).unwrap_or_default()
+        } else {
+            FontNames::default()
+        };
+
+        fonts.push(FontInfo {
+            path: name,
+            format,
+            family: names.family,
+            style: names.style,
+            size,
+            obfuscated: obfuscation.is_some(),
+            obfuscation,
+        });
+    }
+    Ok(fonts)
+}
+
+// ---------------------------------------------------------------------------
+// Obfuscation: encryption.xml and the OPF identifiers the keys come from
+// ---------------------------------------------------------------------------
+
+/// Zip path → algorithm for every `CipherReference` in encryption.xml.
+/// URIs are relative to the container root and may be percent-encoded.
+fn parse_encryption(bytes: &[u8]) -> HashMap<String, Obfuscation> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut algorithm = None;
+    let mut encrypted = HashMap::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = e.name();
+                let attr = |key: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.as_ref() == key)
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+                };
+                if local_name_eq(name.as_ref(), b"EncryptedData") {
+                    algorithm = None;
+                } else if local_name_eq(name.as_ref(), b"EncryptionMethod") {
+                    algorithm = attr(b"Algorithm").map(|alg| match alg.as_str() {
+                        IDPF_ALGORITHM => Obfuscation::Idpf,
+                        ADOBE_ALGORITHM => Obfuscation::Adobe,
+                        _ => Obfuscation::Unknown,
+                    });
+                } else if local_name_eq(name.as_ref(), b"CipherReference") {
+                    if let (Some(uri), Some(alg)) = (attr(b"URI"), algorithm) {
+                        let path = percent_decode(uri.trim_start_matches('/').as_bytes())
+                            .decode_utf8_lossy()
+                            .into_owned();
+                        encrypted.insert(path, alg);
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    encrypted
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct BookIdentifiers {
+    /// Value of the `dc:identifier` named by `<package unique-identifier>`.
+    unique: Option<String>,
+    /// Every `dc:identifier`, the unique one first.
+    all: Vec<String>,
+}
+
+fn read_identifiers<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<BookIdentifiers, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf_bytes = read_zip_entry(zip, &resolve_relative("", &opf_path))
+        .map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    parse_identifiers(&opf_bytes)
+}
+
+fn parse_identifiers(opf_bytes: &[u8]) -> Result<BookIdentifiers, String> {
+    let normalized = strip_xml_bom(opf_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    let mut unique_id = None;
+    // (element id, text) of the identifier being read.
+    let mut current: Option<(Option<String>, String)> = None;
+    let mut identifiers = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let id_attr = |key: &[u8]| {
+                    e.attributes()
+                        .flatten()
+                        .find(|a| a.key.as_ref() == key)
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+                };
+                if local_name_eq(e.name().as_ref(), b"package") {
+                    unique_id = id_attr(b"unique-identifier");
+                } else if local_name_eq(e.name().as_ref(), b"identifier") {
+                    current = Some((id_attr(b"id"), String::new()));
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let Some((_, text)) = current.as_mut() {
+                    text.push_str(&t.unescape().map_err(|e| format!("xml: {e}"))?);
+                }
+            }
+            Ok(Event::End(e)) if local_name_eq(e.name().as_ref(), b"identifier") => {
+                if let Some((id, text)) = current.take() {
+                    identifiers.push((id, text.trim().to_string()));
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let unique = identifiers
+        .iter()
+        .find(|(id, _)| id.is_some() && *id == unique_id)
+        .map(|(_, value)| value.clone());
+    let mut all: Vec<String> = identifiers.into_iter().map(|(_, value)| value).collect();
+    if let Some(unique) = &unique {
+        all.retain(|value| value != unique);
+        all.insert(0, unique.clone());
+    }
+    Ok(BookIdentifiers { unique, all })
+}
+
+fn idpf_key(unique_identifier: &str) -> [u8; 20] {
+    let stripped: String = unique_identifier
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .collect();
+    Sha1::digest(stripped.as_bytes()).into()
+}
+
+/// The 16 bytes of the first identifier that is a UUID (`urn:uuid:` or bare).
+fn adobe_key(identifiers: &[String]) -> Option<[u8; 16]> {
+    identifiers.iter().find_map(|id| {
+        let hex: String = id
+            .trim()
+            .trim_start_matches("urn:uuid:")
+            .chars()
+            .filter(|c| *c != '-')
+            .collect();
+        if hex.len() != 32 || !hex.is_ascii() {
+            return None;
+        }
+        let mut key = [0u8; 16];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+        }
+        Some(key)
+    })
+}
+
+fn xor_prefix(bytes: &mut [u8], key: &[u8], len: usize) {
+    for (i, byte) in bytes.iter_mut().take(len).enumerate() {
+        *byte ^= key[i % key.len()];
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Font `name` tables (sfnt, WOFF, WOFF2)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, PartialEq)]
+struct FontNames {
+    family: Option<String>,
+    style: Option<String>,
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    let b = bytes.get(at..at.checked_add(2)?)?;
+    Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at.checked_add(4)?)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Names of a font file in any supported container, told apart by magic
+/// rather than extension since `.otf` files are often TrueType and so on.
+fn font_names(font: &[u8]) -> Option<FontNames> {
+    let name_table = if font.starts_with(b"wOFF") {
+        woff_table(font, b"name")?
+    } else if font.starts_with(b"wOF2") {
+        woff2_name_table(font)?
+    } else {
+        sfnt_table(font, b"name")?.to_vec()
+    };
+    Some(parse_name_table(&name_table))
+}
+
+/// A table of a TrueType/OpenType font; the first font of a collection.
+fn sfnt_table<'a>(font: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    // Collection offsets, like the table offsets below, are from file start.
+    let dir = if font.starts_with(b"ttcf") {
+        be_u32(font, 12)? as usize
+    } else {
+        0
+    };
+    let num_tables = be_u16(font, dir.checked_add(4)?)? as usize;
+    (0..num_tables).find_map(|i| {
+        let record = dir + 12 + i * 16;
+        if font.get(record..record + 4)? != tag {
+            return None;
+        }
+        let offset = be_u32(font, record + 8)? as usize;
+        let len = be_u32(font, record + 12)? as usize;
+        font.get(offset..offset.checked_add(len)?)
+    })
+}
+
+/// A table of a WOFF 1.0 font, inflating it when stored compressed.
+fn woff_table(font: &[u8], tag: &[u8; 4]) -> Option<Vec<u8>> {
+    let num_tables = be_u16(font, 12)? as usize;
+    (0..num_tables).find_map(|i| {
+        let record = 44 + i * 20;
+        if font.get(record..record + 4)? != tag {
+            return None;
+        }
+        let offset = be_u32(font, record + 4)? as usize;
+        let comp_len = be_u32(font, record + 8)? as usize;
+        let orig_len = be_u32(font, record + 12)? as u64;
+        let data = font.get(offset..offset.checked_add(comp_len)?)?;
+        if (comp_len as u64) >= orig_len {
+            return Some(data.to_vec());
+        }
+        let mut table = Vec::new();
+        flate2::read::ZlibDecoder::new(data)
+            .take(orig_len.min(MAX_TABLE_LEN))
+            .read_to_end(&mut table)
+            .ok()?;
+        Some(table)
+    })
+}
+
+/// WOFF2 `UIntBase128`: big-endian base-128, at most 5 bytes, no leading
+/// zero bytes.
+fn read_base128(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        if i == 0 && byte == 0x80 {
+            return None;
+        }
+        if value & 0xFE00_0000 != 0 {
+            return None;
+        }
+        value = (value << 7) | u32::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Where the `name` table sits in a WOFF2 font's decompressed table stream,
+/// plus where the compressed stream is and how long it inflates to:
+/// `(name_start, name_len, stream_start, stream_compressed_len, stream_len)`.
+fn woff2_layout(font: &[u8]) -> Option<(usize, usize, usize, usize, usize)> {
+    // Known-tag indices from the WOFF2 table directory spec.
+    const NAME: u8 = 5;
+    const GLYF: u8 = 10;
+    const LOCA: u8 = 11;
+
+    // Collections carry an extra directory after the tables; not handled.
+    if font.get(4..8)? == b"ttcf" {
+        return None;
+    }
+    let num_tables = be_u16(font, 12)? as usize;
+    let compressed_len = be_u32(font, 20)? as usize;
+
+    let mut pos = 48;
+    let mut offset = 0usize;
+    let mut name = None;
+    for _ in 0..num_tables {
+        let flags = *font.get(pos)?;
+        pos += 1;
+        let tag_index = flags & 0x3F;
+        let is_name = if tag_index == 0x3F {
+            let tag = font.get(pos..pos + 4)?;
+            pos += 4;
+            tag == b"name"
+        } else {
+            tag_index == NAME
+        };
+        let version = flags >> 6;
+        let orig_len = read_base128(font, &mut pos)? as usize;
+        // glyf/loca are transformed unless version 3; others unless version 0.
+        let transformed = if matches!(tag_index, GLYF | LOCA) {
+            version != 3
+        } else {
+            version != 0
+        };
+        let len = if transformed {
+            read_base128(font, &mut pos)? as usize
+        } else {
+            orig_len
+        };
+        if is_name {
+            name = Some((offset, len));
+        }
+        offset = offset.checked_add(len)?;
+    }
+    let (name_start, name_len) = name?;
+    Some((name_start, name_len, pos, compressed_len, offset))
+}
+
+fn woff2_name_table(font: &[u8]) -> Option<Vec<u8>> {
+    let (name_start, name_len, stream_start, compressed_len, stream_len) = woff2_layout(font)?;
+    let compressed = font.get(stream_start..stream_start.checked_add(compressed_len)?)?;
+    // The name table can sit anywhere in the stream, so inflate up to its end.
+    let needed = (name_start + name_len).min(stream_len) as u64;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(needed.min(MAX_TABLE_LEN))
+        .read_to_end(&mut stream)
+        .ok()?;
+    stream
+        .get(name_start..name_start + name_len)
+        .map(<[u8]>::to_vec)
+}
+
+/// Family and style from a `name` table, preferring Windows English
+/// records, then other Windows/Unicode ones, then Mac Roman.
+fn parse_name_table(table: &[u8]) -> FontNames {
+    let count = be_u16(table, 2).unwrap_or(0) as usize;
+    let storage = be_u16(table, 4).unwrap_or(0) as usize;
+    // name ID → (rank, text); lower rank wins.
+    let mut best: HashMap<u16, (u8, String)> = HashMap::new();
+    for i in 0..count {
+        let record = 6 + i * 12;
+        let (Some(platform), Some(encoding), Some(language), Some(name_id)) = (
+            be_u16(table, record),
+            be_u16(table, record + 2),
+            be_u16(table, record + 4),
+            be_u16(table, record + 6),
+        ) else {
+            break;
+        };
+        if !matches!(name_id, 1 | 2 | 16 | 17) {
+            continue;
+        }
+        let rank = match (platform, encoding) {
+            (3, 1 | 10) if language == 0x409 => 0,
+            (3, 1 | 10) => 1,
+            (0, _) => 2,
+            (1, 0) if language == 0 => 3,
+            (1, 0) => 4,
+            _ => continue,
+        };
+        if best.get(&name_id).is_some_and(|(r, _)| *r <= rank) {
+            continue;
+        }
+        let (Some(len), Some(offset)) = (be_u16(table, record + 8), be_u16(table, record + 10))
+        else {
+            continue;
+        };
+        let start = storage + offset as usize;
+        let Some(raw) = table.get(start..start + len as usize) else {
+            continue;
+        };
+        let text = if platform == 1 {
+            // Mac Roman; Latin-1 is right for the ASCII names fonts use.
+            raw.iter().map(|&b| b as char).collect()
+        } else {
+            let units: Vec<u16> = raw
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        };
+        let text = text.trim_matches(char::from(0)).trim().to_string();
+        if !text.is_empty() {
+            best.insert(name_id, (rank, text));
+        }
+    }
+
+    let mut take = |id: u16| best.remove(&id).map(|(_, text)| text);
+    let typographic_family = take(16);
+    let typographic_style = take(17);
+    FontNames {
+        family: typographic_family.or_else(|| take(1)),
+        style: typographic_style.or_else(|| take(2)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::zip_with;
+    use std::io::Write;
+
+    /// `name` table with Windows English records for `(name_id, text)`.
+    fn name_table(names: &[(u16, &str)]) -> Vec<u8> {
+        let encoded: Vec<Vec<u8>> = names
+            .iter()
+            .map(|(_, text)| text.encode_utf16().flat_map(u16::to_be_bytes).collect())
+            .collect();
+        let storage = 6 + names.len() * 12;
+        let mut table = Vec::new();
+        table.extend_from_slice(&0u16.to_be_bytes());
+        table.extend_from_slice(&(names.len() as u16).to_be_bytes());
+        table.extend_from_slice(&(storage as u16).to_be_bytes());
+        let mut offset = 0u16;
+        for ((id, _), bytes) in names.iter().zip(&encoded) {
+            for field in [3u16, 1, 0x409, *id, bytes.len() as u16, offset] {
+                table.extend_from_slice(&field.to_be_bytes());
+            }
+            offset += bytes.len() as u16;
+        }
+        encoded
+            .iter()
+            .for_each(|bytes| table.extend_from_slice(bytes));
+        table
+    }
+
+    /// Minimal sfnt holding just `table` as its `name` table.
+    fn sfnt_with_name(table: &[u8]) -> Vec<u8> {
+        let mut font = Vec::new();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&1u16.to_be_bytes());
+        font.extend_from_slice(&[0; 6]);
+        font.extend_from_slice(b"name");
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&28u32.to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        font.extend_from_slice(table);
+        font
+    }
+
+    /// WOFF 1.0 wrapper around `table` as `name`, zlib-compressed if asked.
+    fn woff_with_name(table: &[u8], compress: bool) -> Vec<u8> {
+        let data = if compress {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(table).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            table.to_vec()
+        };
+        let mut font = b"wOFF".to_vec();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&1u16.to_be_bytes());
+        font.resize(44, 0);
+        font.extend_from_slice(b"name");
+        font.extend_from_slice(&64u32.to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&data);
+        font
+    }
+
+    #[test]
+    fn typographic_names_win_over_legacy_ones() {
+        let table = name_table(&[
+            (1, "Literata Bold"),
+            (2, "Regular"),
+            (16, "Literata"),
+            (17, "Bold"),
+        ]);
+        assert_eq!(
+            parse_name_table(&table),
+            FontNames {
+                family: Some("Literata".into()),
+                style: Some("Bold".into()),
+            }
+        );
+        let legacy = name_table(&[(1, "Charis SIL"), (2, "Italic")]);
+        assert_eq!(
+            parse_name_table(&legacy),
+            FontNames {
+                family: Some("Charis SIL".into()),
+                style: Some("Italic".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn names_are_read_from_sfnt_and_woff() {
+        let table = name_table(&[(1, "Gentium"), (2, "Regular")]);
+        let expected = Some(FontNames {
+            family: Some("Gentium".into()),
+            style: Some("Regular".into()),
+        });
+        assert_eq!(font_names(&sfnt_with_name(&table)), expected);
+        assert_eq!(font_names(&woff_with_name(&table, false)), expected);
+        assert_eq!(font_names(&woff_with_name(&table, true)), expected);
+        assert_eq!(font_names(b"not a font"), None);
+    }
+
+    #[test]
+    fn base128_rejects_leading_zeros_and_overflow() {
+        let mut pos = 0;
+        assert_eq!(read_base128(&[0x3F], &mut pos), Some(63));
+        pos = 0;
+        assert_eq!(read_base128(&[0x81, 0x00], &mut pos), Some(128));
+        assert_eq!(pos, 2);
+        pos = 0;
+        assert_eq!(read_base128(&[0x80, 0x01], &mut pos), None);
+        pos = 0;
+        assert_eq!(
+            read_base128(&[0x90, 0x80, 0x80, 0x80, 0x00], &mut pos),
+            None
+        );
+    }
+
+    #[test]
+    fn woff2_directory_locates_name_after_transformed_glyf() {
+        let mut font = b"wOF2".to_vec();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&3u16.to_be_bytes());
+        font.extend_from_slice(&0u16.to_be_bytes());
+        font.extend_from_slice(&0u32.to_be_bytes());
+        font.extend_from_slice(&99u32.to_be_bytes());
+        font.resize(48, 0);
+        // cmap (null transform), glyf (transformed, version 0), name.
+        font.extend_from_slice(&[0x00, 0x20]);
+        font.extend_from_slice(&[10, 0x81, 0x00, 0x50]);
+        font.extend_from_slice(&[5, 0x30]);
+        assert_eq!(woff2_layout(&font), Some((0x20 + 0x50, 0x30, 56, 99, 0xA0)));
+    }
+
+    #[test]
+    fn encryption_xml_maps_paths_to_algorithms() {
+        let xml = br#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+            xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+          <enc:EncryptedData>
+            <enc:EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/>
+            <enc:CipherData><enc:CipherReference URI="OEBPS/fonts/My%20Font.otf"/></enc:CipherData>
+          </enc:EncryptedData>
+          <enc:EncryptedData>
+            <enc:EncryptionMethod Algorithm="http://ns.adobe.com/pdf/enc#RC"/>
+            <enc:CipherData><enc:CipherReference URI="OEBPS/fonts/b.ttf"/></enc:CipherData>
+          </enc:EncryptedData>
+          <enc:EncryptedData>
+            <enc:EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/>
+            <enc:CipherData><enc:CipherReference URI="OEBPS/text/ch1.xhtml"/></enc:CipherData>
+          </enc:EncryptedData>
+        </encryption>"#;
+        let map = parse_encryption(xml);
+        assert_eq!(map.get("OEBPS/fonts/My Font.otf"), Some(&Obfuscation::Idpf));
+        assert_eq!(map.get("OEBPS/fonts/b.ttf"), Some(&Obfuscation::Adobe));
+        assert_eq!(map.get("OEBPS/text/ch1.xhtml"), Some(&Obfuscation::Unknown));
+    }
+
+    #[test]
+    fn identifiers_put_the_unique_one_first() {
+        let opf = br#"<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="uid">
+          <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+            <dc:identifier id="isbn">9780000000000</dc:identifier>
+            <dc:identifier id="uid"> urn:uuid:12345678-9abc-def0-1234-56789abcdef0 </dc:identifier>
+          </metadata>
+        </package>"#;
+        let ids = parse_identifiers(opf).unwrap();
+        assert_eq!(
+            ids.unique.as_deref(),
+            Some("urn:uuid:12345678-9abc-def0-1234-56789abcdef0")
+        );
+        assert_eq!(ids.all.len(), 2);
+        assert_eq!(
+            adobe_key(&ids.all),
+            Some([
+                0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc,
+                0xde, 0xf0
+            ])
+        );
+    }
+
+    #[test]
+    fn idpf_key_ignores_whitespace() {
+        // SHA-1("abc")
+        let expected = [
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+            0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ];
+        assert_eq!(idpf_key(" a b\n\tc "), expected);
+    }
+
+    #[test]
+    fn obfuscated_fonts_are_named_after_deobfuscation() {
+        let uid = "urn:uuid:12345678-9abc-def0-1234-56789abcdef0";
+        let font = sfnt_with_name(&name_table(&[(1, "Obfuscated Serif"), (2, "Regular")]));
+        let mut adobe = font.clone();
+        xor_prefix(
+            &mut adobe,
+            &adobe_key(&[uid.to_string()]).unwrap(),
+            ADOBE_OBFUSCATED_LEN,
+        );
+        let encryption = br#"<encryption xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+          <enc:EncryptedData>
+            <enc:EncryptionMethod Algorithm="http://ns.adobe.com/pdf/enc#RC"/>
+            <enc:CipherData><enc:CipherReference URI="OEBPS/a.ttf"/></enc:CipherData>
+          </enc:EncryptedData>
+          <enc:EncryptedData>
+            <enc:EncryptionMethod Algorithm="urn:example:drm"/>
+            <enc:CipherData><enc:CipherReference URI="OEBPS/b.woff2"/></enc:CipherData>
+          </enc:EncryptedData>
+        </encryption>"#;
+        let container = br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+        let opf = format!(
+            r#"<package unique-identifier="id"><metadata><dc:identifier id="id">{uid}</dc:identifier></metadata></package>"#
+        );
+        let mut zip = zip_with(&[
+            ("META-INF/container.xml", container),
+            ("META-INF/encryption.xml", encryption),
+            ("OEBPS/content.opf", opf.as_bytes()),
+            ("OEBPS/a.ttf", &adobe),
+            ("OEBPS/b.woff2", b"wOF2 encrypted"),
+            ("OEBPS/plain.otf", &font),
+            ("OEBPS/style.css", b"body {}"),
+        ]);
+
+        let fonts = list_fonts(&mut zip).unwrap();
+        assert_eq!(fonts.len(), 3);
+        assert_eq!(fonts[0].family.as_deref(), Some("Obfuscated Serif"));
+        assert_eq!(fonts[0].obfuscation, Some(Obfuscation::Adobe));
+        assert!(fonts[1].obfuscated && fonts[1].family.is_none());
+        assert_eq!(fonts[1].format, FontFormat::Woff2);
+        assert!(!fonts[2].obfuscated);
+        assert_eq!(fonts[2].style.as_deref(), Some("Regular"));
+        assert_eq!(fonts[2].size, font.len() as u64);
+    }
+}
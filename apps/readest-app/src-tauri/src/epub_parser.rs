@@ -159,27 +159,171 @@ pub async fn extract_epub_cover_full(file_path: String) -> Result<RawCoverImage,
         .map_err(|e| format!("join error: {e}"))?
 }
 
-fn extract_epub_cover_full_sync(file_path: &str) -> Result<RawCoverImage, String> {
+pub(crate) fn extract_epub_cover_full_sync(file_path: &str) -> Result<RawCoverImage, String> {
     let path = Path::new(file_path);
     if !path.exists() {
         return Err(format!("file not found: {file_path}"));
     }
     let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
     let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
-    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    epub_cover(&mut zip)
+}
+
+/// The original cover image of an EPUB already open as `zip`.
+pub(crate) fn epub_cover<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<RawCoverImage, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
     let opf_bytes =
-        read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+        read_zip_entry(zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
     let cover_inputs =
         parse_opf_cover_inputs(&opf_bytes).map_err(|e| format!("parse opf cover inputs: {e}"))?;
     let cover_zip_path =
         resolve_cover_path(&cover_inputs.manifest, &cover_inputs.cover_id, &opf_path)
             .ok_or_else(|| "no cover image in epub".to_string())?;
-    let bytes = read_zip_entry(&mut zip, &cover_zip_path)
+    let bytes = read_zip_entry(zip, &cover_zip_path)
         .map_err(|e| format!("read cover {cover_zip_path}: {e}"))?;
     let mime = guess_image_mime(&cover_zip_path).to_string();
     Ok(RawCoverImage { bytes, mime })
 }
 
+// ---------------------------------------------------------------------------
+// extract_epub_resource: one manifest resource (a stylesheet, an image a note
+// points at, …) by href, for the reader to fetch without loading the book in
+// JS. Resolution is the cover path's: relative to the OPF, percent-encoded
+// or not, plus a case-insensitive match for books whose hrefs disagree with
+// the zip about case. Hrefs that climb out of the archive are refused.
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubResource {
+    pub bytes: Vec<u8>,
+    /// Guessed from the file extension; `application/octet-stream` when
+    /// unknown.
+    pub mime: String,
+}
+
+#[tauri::command]
+pub async fn extract_epub_resource(path: String, href: String) -> Result<EpubResource, String> {
+    tauri::async_runtime::spawn_blocking(move || extract_epub_resource_sync(&path, &href))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn extract_epub_resource_sync(file_path: &str, href: &str) -> Result<EpubResource, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    read_epub_resource(&mut zip, href)
+}
+
+pub(crate) fn read_epub_resource<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    href: &str,
+) -> Result<EpubResource, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
+    let zip_path = resolve_resource_href(&opf_path, href)?;
+    let bytes = read_zip_entry(zip, &zip_path).or_else(|e| {
+        let name = find_entry_ignoring_case(zip, &zip_path).ok_or(e)?;
+        read_by_name(zip, &name)
+    })?;
+    Ok(EpubResource {
+        bytes,
+        mime: guess_resource_mime(&zip_path).to_string(),
+    })
+}
+
+/// The zip path `href` names, relative to the OPF (or to the archive root
+/// when it starts with `/`). Refuses URLs and any href whose `..` segments,
+/// literal or percent-encoded, climb above the root.
+fn resolve_resource_href(opf_path: &str, href: &str) -> Result<String, String> {
+    let href = href.split(['?', '#']).next().unwrap_or(href);
+    if href.is_empty() {
+        return Err("empty href".to_string());
+    }
+    if href
+        .split('/')
+        .next()
+        .is_some_and(|first| first.contains(':'))
+    {
+        return Err(format!("not a path inside the book: {href}"));
+    }
+    let (base, relative) = match href.strip_prefix('/') {
+        Some(rest) => ("", rest),
+        None => (opf_path.rsplit_once('/').map_or("", |(dir, _)| dir), href),
+    };
+    let decoded = percent_decode(relative.as_bytes()).decode_utf8_lossy();
+    if escapes_root(base, relative) || escapes_root(base, &decoded) {
+        return Err(format!("href escapes the archive: {href}"));
+    }
+    Ok(normalize_zip_path(&format!("{base}/{relative}")))
+}
+
+/// Whether walking `href` from the directory `base` goes above the root.
+/// Backslashes count as separators, as some zip tools treat them.
+fn escapes_root(base: &str, href: &str) -> bool {
+    let mut depth = base
+        .split('/')
+        .filter(|seg| !seg.is_empty() && *seg != ".")
+        .count();
+    for seg in href.split(['/', '\\']) {
+        match seg {
+            "" | "." => {}
+            ".." => match depth.checked_sub(1) {
+                Some(up) => depth = up,
+                None => return true,
+            },
+            _ => depth += 1,
+        }
+    }
+    false
+}
+
+/// The entry whose name matches `path` (or its percent-decoded form) when
+/// case is ignored.
+fn find_entry_ignoring_case<R: Read + Seek>(zip: &ZipArchive<R>, path: &str) -> Option<String> {
+    let lower = path.to_lowercase();
+    let decoded = percent_decode(path.as_bytes())
+        .decode_utf8_lossy()
+        .to_lowercase();
+    zip.file_names()
+        .find(|name| {
+            let name = name.to_lowercase();
+            name == lower || name == decoded
+        })
+        .map(str::to_string)
+}
+
+fn guess_resource_mime(path: &str) -> &'static str {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "xhtml" | "xht" => "application/xhtml+xml",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "xml" | "opf" => "application/xml",
+        "ncx" => "application/x-dtbncx+xml",
+        "smil" => "application/smil+xml",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" | "gif" | "webp" | "svg" => guess_image_mime(path),
+        "avif" => "image/avif",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "ogg" | "opus" => "audio/ogg",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
 // ---------------------------------------------------------------------------
 // parse_epub_full: open hot path (replaces zip.js + foliate EPUB.init() prelude)
 //
@@ -326,9 +470,9 @@ fn parse_epub_full_sync(file_path: &str) -> Result<ParsedEpubFull, String> {
 }
 
 /// Hrefs found in the OPF, *as written* (not yet resolved against opf_path).
-struct LocatedTocSources {
-    nav_href: Option<String>,
-    ncx_href: Option<String>,
+pub(crate) struct LocatedTocSources {
+    pub(crate) nav_href: Option<String>,
+    pub(crate) ncx_href: Option<String>,
 }
 
 /// Single-pass streaming scan of the OPF bytes to extract the nav document
@@ -337,7 +481,7 @@ struct LocatedTocSources {
 ///   - nav: first manifest <item> whose `properties` contains the token "nav"
 ///   - ncx: <spine toc="..."> resolves to manifest[id]; otherwise the first
 ///     manifest <item> with media-type application/x-dtbncx+xml
-fn locate_toc_sources(opf_bytes: &[u8]) -> Result<LocatedTocSources, String> {
+pub(crate) fn locate_toc_sources(opf_bytes: &[u8]) -> Result<LocatedTocSources, String> {
     // We collect manifest items by id in a small map and remember the
     // <spine toc="..."> attribute (if any). We also short-circuit nav_href
     // as soon as we find a "nav" property.
@@ -459,6 +603,243 @@ fn locate_toc_sources(opf_bytes: &[u8]) -> Result<LocatedTocSources, String> {
     Ok(LocatedTocSources { nav_href, ncx_href })
 }
 
+// ---------------------------------------------------------------------------
+// get_page_list: print-edition page numbers
+//
+// EPUB3 books can carry a `<nav epub:type="page-list">` in nav.xhtml mapping
+// print page labels to anchors in the content documents; EPUB2 books put the
+// same data in the NCX `<pageList>`. The reader uses it to show "page X of Y"
+// that matches the physical edition instead of synthetic page counts.
+//
+// The nav page-list wins when both exist (it's the EPUB3 source of truth and
+// the NCX copy is usually generated from it). Hrefs are resolved to zip
+// paths with their `#fragment` kept, since the fragment is the page anchor.
+// `None` means the book has no page-list at all.
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageListEntry {
+    /// Page label as printed (e.g. "12", "xiv").
+    pub label: String,
+    /// Zip path of the content document plus the anchor fragment, e.g.
+    /// "OEBPS/text/chapter1.xhtml#page12".
+    pub href: String,
+}
+
+#[tauri::command]
+pub async fn get_page_list(path: String) -> Result<Option<Vec<PageListEntry>>, String> {
+    tauri::async_runtime::spawn_blocking(move || get_page_list_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn get_page_list_sync(file_path: &str) -> Result<Option<Vec<PageListEntry>>, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+
+    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf_bytes =
+        read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    let LocatedTocSources { nav_href, ncx_href } =
+        locate_toc_sources(&opf_bytes).map_err(|e| format!("locate toc: {e}"))?;
+
+    if let Some(nav_path) = nav_href.map(|h| resolve_relative(&opf_path, &h)) {
+        // Soft-fail like parse_epub_full: a broken nav doc shouldn't hide an
+        // intact NCX page list.
+        if let Ok(bytes) = read_zip_entry(&mut zip, &nav_path) {
+            if let Ok(pages) = parse_nav_page_list(&bytes, &nav_path) {
+                if !pages.is_empty() {
+                    return Ok(Some(pages));
+                }
+            }
+        }
+    }
+
+    if let Some(ncx_path) = ncx_href.map(|h| resolve_relative(&opf_path, &h)) {
+        if let Ok(bytes) = read_zip_entry(&mut zip, &ncx_path) {
+            if let Ok(pages) = parse_ncx_page_list(&bytes, &ncx_path) {
+                if !pages.is_empty() {
+                    return Ok(Some(pages));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Resolve a page target href against the document it appears in, keeping
+/// the fragment. A bare `#anchor` points into `doc_path` itself.
+pub(crate) fn resolve_page_target(doc_path: &str, href: &str) -> String {
+    let (target, fragment) = match href.split_once('#') {
+        Some((target, fragment)) => (target, Some(fragment)),
+        None => (href, None),
+    };
+    let resolved = if target.is_empty() {
+        doc_path.to_string()
+    } else {
+        resolve_relative(doc_path, target)
+    };
+    match fragment {
+        Some(fragment) if !fragment.is_empty() => format!("{resolved}#{fragment}"),
+        _ => resolved,
+    }
+}
+
+/// Collapse runs of whitespace so labels split across inline elements
+/// ("Page <span>12</span>") come out as "Page 12".
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Extract `<nav epub:type="page-list">` entries from nav.xhtml in
+/// document order. Entries without an href are skipped.
+fn parse_nav_page_list(nav_bytes: &[u8], nav_path: &str) -> Result<Vec<PageListEntry>, String> {
+    let normalized = strip_xml_bom(nav_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut pages = Vec::new();
+    // Depth of <nav> elements inside the page-list nav; 0 = not inside one.
+    let mut page_list_depth = 0usize;
+    // href and label text of the <a> currently being read.
+    let mut current: Option<(String, String)> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                if local_name_eq(name.as_ref(), b"nav") {
+                    let is_page_list = e.attributes().flatten().any(|a| {
+                        local_name_eq(a.key.as_ref(), b"type")
+                            && String::from_utf8_lossy(&a.value)
+                                .split_ascii_whitespace()
+                                .any(|t| t == "page-list")
+                    });
+                    if page_list_depth > 0 || is_page_list {
+                        page_list_depth += 1;
+                    }
+                } else if page_list_depth > 0 && local_name_eq(name.as_ref(), b"a") {
+                    let href = e
+                        .attributes()
+                        .flatten()
+                        .find(|a| local_name_eq(a.key.as_ref(), b"href"))
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned());
+                    current = href.map(|h| (h, String::new()));
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let Some((_, label)) = current.as_mut() {
+                    let text = t.unescape().map_err(|e| format!("xml: {e}"))?;
+                    label.push_str(&text);
+                }
+            }
+            Ok(Event::CData(t)) => {
+                if let Some((_, label)) = current.as_mut() {
+                    label.push_str(&String::from_utf8_lossy(&t));
+                }
+            }
+            Ok(Event::End(e)) => {
+                let name = e.name();
+                if page_list_depth > 0 && local_name_eq(name.as_ref(), b"a") {
+                    if let Some((href, label)) = current.take() {
+                        pages.push(PageListEntry {
+                            label: collapse_whitespace(&label),
+                            href: resolve_page_target(nav_path, &href),
+                        });
+                    }
+                } else if page_list_depth > 0 && local_name_eq(name.as_ref(), b"nav") {
+                    page_list_depth -= 1;
+                    if page_list_depth == 0 {
+                        break;
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(pages)
+}
+
+/// Extract NCX `<pageList><pageTarget>` entries in document order, taking the
+/// label from `<navLabel><text>` and the target from `<content src>`.
+fn parse_ncx_page_list(ncx_bytes: &[u8], ncx_path: &str) -> Result<Vec<PageListEntry>, String> {
+    let normalized = strip_xml_bom(ncx_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut pages = Vec::new();
+    let mut in_page_list = false;
+    let mut in_label_text = false;
+    let mut label = String::new();
+    let mut src: Option<String> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"pageList" {
+                    in_page_list = true;
+                } else if in_page_list && name == b"pageTarget" {
+                    label.clear();
+                    src = None;
+                } else if in_page_list && name == b"text" {
+                    in_label_text = true;
+                } else if in_page_list && name == b"content" {
+                    src = content_src(&e);
+                }
+            }
+            Ok(Event::Empty(e)) if in_page_list && local_name_eq(e.name().as_ref(), b"content") => {
+                src = content_src(&e);
+            }
+            Ok(Event::Text(t)) if in_label_text => {
+                let text = t.unescape().map_err(|e| format!("xml: {e}"))?;
+                label.push_str(&text);
+            }
+            Ok(Event::End(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"text" {
+                    in_label_text = false;
+                } else if in_page_list && name == b"pageTarget" {
+                    if let Some(src) = src.take() {
+                        pages.push(PageListEntry {
+                            label: collapse_whitespace(&label),
+                            href: resolve_page_target(ncx_path, &src),
+                        });
+                    }
+                } else if name == b"pageList" {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(pages)
+}
+
+fn content_src(e: &quick_xml::events::BytesStart<'_>) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == b"src")
+        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+}
+
 // `maybe_resize_cover` is now defined in `parser_common`; the description
 // below is retained here for navigation from EPUB-side call sites.
 //
@@ -486,7 +867,10 @@ fn locate_toc_sources(opf_bytes: &[u8]) -> Result<LocatedTocSources, String> {
 // block above is retained here for navigation from EPUB-side call sites.)
 // ---------------------------------------------------------------------------
 
-fn read_zip_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> Result<Vec<u8>, String> {
+pub(crate) fn read_zip_entry<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<Vec<u8>, String> {
     // Two-pass lookup, mirroring what epub-rs does (archive.rs) and what
     // foliate-js does on the JS side: many EPUBs declare manifest hrefs that
     // are percent-encoded (e.g. "Text/My%20Chapter.xhtml" or CJK %E4%BB%96)
@@ -516,7 +900,9 @@ fn read_by_name<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<V
     Ok(buf)
 }
 
-fn read_rootfile_path<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<String, String> {
+pub(crate) fn read_rootfile_path<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+) -> Result<String, String> {
     let bytes = read_zip_entry(zip, "META-INF/container.xml")?;
     let normalized = strip_xml_bom(&bytes);
     let mut reader = Reader::from_reader(normalized.as_ref());
@@ -542,6 +928,49 @@ fn read_rootfile_path<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<String,
     Err("rootfile not found".into())
 }
 
+/// Hrefs (relative to the OPF) of the XHTML documents in the OPF's spine, in
+/// reading order. Itemrefs to other media types are skipped.
+pub(crate) fn spine_documents(opf_bytes: &[u8]) -> Result<Vec<String>, String> {
+    let normalized = strip_xml_bom(opf_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+    // id -> href of XHTML manifest items.
+    let mut documents: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    let mut idrefs: Vec<String> = Vec::new();
+    let attr = |e: &quick_xml::events::BytesStart, name: &[u8]| {
+        e.attributes()
+            .flatten()
+            .find(|a| local_name(a.key.as_ref()) == name)
+            .and_then(|a| a.unescape_value().ok())
+            .map(|v| v.into_owned())
+    };
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match local_name(e.name().as_ref()) {
+                b"item" => {
+                    let is_xhtml = attr(&e, b"media-type")
+                        .is_some_and(|t| t == "application/xhtml+xml" || t == "text/html");
+                    if let (true, Some(id), Some(href)) =
+                        (is_xhtml, attr(&e, b"id"), attr(&e, b"href"))
+                    {
+                        documents.insert(id, href);
+                    }
+                }
+                b"itemref" => idrefs.extend(attr(&e, b"idref")),
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(idrefs
+        .iter()
+        .filter_map(|id| documents.get(id).cloned())
+        .collect())
+}
+
 // ---------------------------------------------------------------------------
 // OPF parsing — *cover-only* slice
 //
@@ -733,7 +1162,7 @@ fn resolve_cover_path(
     chosen.map(|item| resolve_relative(opf_path, &item.href))
 }
 
-fn resolve_relative(opf_path: &str, href: &str) -> String {
+pub(crate) fn resolve_relative(opf_path: &str, href: &str) -> String {
     // Strip query/fragment that occasionally appear in manifest hrefs.
     let href = href.split(['?', '#']).next().unwrap_or(href);
     let dir = match opf_path.rfind('/') {
@@ -794,7 +1223,7 @@ fn guess_image_mime(path: &str) -> &'static str {
 ///     publisher tools (notably old Adobe InDesign exports) still emit it.
 ///
 /// Returns a `Cow` so the common (UTF-8, no BOM) case stays zero-copy.
-fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
+pub(crate) fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
     if bytes.len() >= 3 && bytes[0] == 0xEF && bytes[1] == 0xBB && bytes[2] == 0xBF {
         return Cow::Borrowed(&bytes[3..]);
     }
@@ -823,14 +1252,14 @@ fn strip_xml_bom(bytes: &[u8]) -> Cow<'_, [u8]> {
     Cow::Borrowed(bytes)
 }
 
-fn local_name(qname: &[u8]) -> &[u8] {
+pub(crate) fn local_name(qname: &[u8]) -> &[u8] {
     match qname.iter().rposition(|b| *b == b':') {
         Some(idx) => &qname[idx + 1..],
         None => qname,
     }
 }
 
-fn local_name_eq(qname: &[u8], local: &[u8]) -> bool {
+pub(crate) fn local_name_eq(qname: &[u8], local: &[u8]) -> bool {
     local_name(qname) == local
 }
 
@@ -1262,6 +1691,60 @@ mod tests {
         assert!(read_zip_entry(&mut zip, "missing.txt").is_err());
     }
 
+    #[test]
+    fn resource_hrefs_resolve_against_the_opf_and_stay_inside() {
+        assert_eq!(
+            resolve_resource_href("OEBPS/content.opf", "Styles/main.css#x").unwrap(),
+            "OEBPS/Styles/main.css"
+        );
+        assert_eq!(
+            resolve_resource_href("OEBPS/content.opf", "../META-INF/a.xml").unwrap(),
+            "META-INF/a.xml"
+        );
+        assert_eq!(
+            resolve_resource_href("OEBPS/content.opf", "/images/a.png").unwrap(),
+            "images/a.png"
+        );
+        for bad in [
+            "../../etc/passwd",
+            "Text/../../../x",
+            "%2e%2e/%2e%2e/x",
+            "..\\..\\x",
+            "https://example.com/a.css",
+            "",
+        ] {
+            assert!(
+                resolve_resource_href("OEBPS/content.opf", bad).is_err(),
+                "{bad} should be refused"
+            );
+        }
+        assert!(resolve_resource_href("content.opf", "../x").is_err());
+    }
+
+    #[test]
+    fn epub_resource_lookup_ignores_case() {
+        use std::io::Write;
+        let mut buf = Vec::<u8>::new();
+        {
+            let mut w = zip::ZipWriter::new(Cursor::new(&mut buf));
+            let opts = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            w.start_file("META-INF/container.xml", opts).unwrap();
+            w.write_all(
+                br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            )
+            .unwrap();
+            w.start_file("OEBPS/Styles/Main.css", opts).unwrap();
+            w.write_all(b"p{}").unwrap();
+            w.finish().unwrap();
+        }
+        let mut zip = ZipArchive::new(Cursor::new(buf)).unwrap();
+        let resource = read_epub_resource(&mut zip, "styles/main.css").unwrap();
+        assert_eq!(resource.bytes, b"p{}");
+        assert_eq!(resource.mime, "text/css");
+        assert!(read_epub_resource(&mut zip, "styles/missing.css").is_err());
+    }
+
     #[test]
     fn partial_md5_medium_file_uses_step_windows() {
         // For a >2 KiB file the i = 0 iteration reads bytes [1024..2048],
@@ -1290,4 +1773,85 @@ mod tests {
         assert_eq!(hash, "1576a94d6cb334dd126cb1c27f19e0f2");
         let _ = std::fs::remove_file(path);
     }
+
+    #[test]
+    fn nav_page_list_resolves_targets_and_labels() {
+        let nav = br##"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+  <body>
+    <nav epub:type="toc"><ol><li><a href="text/ch1.xhtml">Chapter 1</a></li></ol></nav>
+    <nav epub:type="page-list" hidden="">
+      <ol>
+        <li><a href="text/ch1.xhtml#p1">1</a></li>
+        <li><a href="../Text/ch2.xhtml#pg2">Page <span>ii</span></a></li>
+        <li><a href="#p3">3</a></li>
+      </ol>
+    </nav>
+  </body>
+</html>"##;
+        let pages = parse_nav_page_list(nav, "OEBPS/nav.xhtml").unwrap();
+        assert_eq!(
+            pages,
+            vec![
+                PageListEntry {
+                    label: "1".into(),
+                    href: "OEBPS/text/ch1.xhtml#p1".into(),
+                },
+                PageListEntry {
+                    label: "Page ii".into(),
+                    href: "Text/ch2.xhtml#pg2".into(),
+                },
+                PageListEntry {
+                    label: "3".into(),
+                    href: "OEBPS/nav.xhtml#p3".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nav_without_page_list_yields_nothing() {
+        let nav = br#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<nav epub:type="toc"><ol><li><a href="ch1.xhtml">One</a></li></ol></nav>
+</body></html>"#;
+        assert!(parse_nav_page_list(nav, "nav.xhtml").unwrap().is_empty());
+    }
+
+    #[test]
+    fn ncx_page_list_is_parsed_in_order() {
+        let ncx = br#"<?xml version="1.0"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/">
+  <navMap><navPoint id="n1"><navLabel><text>Ch 1</text></navLabel><content src="ch1.html"/></navPoint></navMap>
+  <pageList>
+    <pageTarget id="p1" type="normal" value="1" playOrder="2">
+      <navLabel><text>1</text></navLabel>
+      <content src="ch1.html#page1"/>
+    </pageTarget>
+    <pageTarget id="p2" type="normal" value="2" playOrder="3">
+      <navLabel><text>2</text></navLabel>
+      <content src="ch1.html#page2"/>
+    </pageTarget>
+  </pageList>
+</ncx>"#;
+        let pages = parse_ncx_page_list(ncx, "OPS/toc.ncx").unwrap();
+        let labels: Vec<_> = pages.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["1", "2"]);
+        assert_eq!(pages[1].href, "OPS/ch1.html#page2");
+    }
+
+    #[test]
+    fn resolve_page_target_keeps_fragment() {
+        assert_eq!(
+            resolve_page_target("OEBPS/nav.xhtml", "text/a.xhtml#x"),
+            "OEBPS/text/a.xhtml#x"
+        );
+        assert_eq!(
+            resolve_page_target("OEBPS/nav.xhtml", "#x"),
+            "OEBPS/nav.xhtml#x"
+        );
+        assert_eq!(
+            resolve_page_target("OEBPS/nav.xhtml", "text/a.xhtml"),
+            "OEBPS/text/a.xhtml"
+        );
+    }
 }
@@ -0,0 +1,229 @@
+//! `repack_epub`: rebuild an EPUB whose zip structure strict readers reject.
+//!
+//! The OCF spec wants `mimetype` as the first entry, stored uncompressed and
+//! holding exactly `application/epub+zip`, so readers can sniff the type
+//! from the first bytes of the file. Many tools get this wrong: `mimetype`
+//! deflated, written after other entries, or ending in a newline. The
+//! repacked book gets a correct `mimetype` first; every other entry is
+//! deflated, with entries that already are copied raw so nothing is
+//! recompressed. Duplicate entry names keep their first copy.
+//!
+//! The result is written to a temp file beside the target and reopened
+//! before it replaces anything: `mimetype` must come first and stored, the
+//! OPF must be reachable from `container.xml`, and every entry must read
+//! back with a good CRC. A book that fails this is left as it was.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use tauri::AppHandle;
+use tauri_plugin_fs::FsExt;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::book_cover::replace_file_checked;
+use crate::epub_parser::{read_rootfile_path, read_zip_entry};
+
+const MIMETYPE: &str = "mimetype";
+const EPUB_MIMETYPE: &[u8] = b"application/epub+zip";
+
+/// Write the entries of `zip` to `out` with `mimetype` first and stored.
+fn write_repacked<R: Read + Seek, W: Write + Seek>(
+    zip: &mut ZipArchive<R>,
+    out: W,
+) -> Result<(), String> {
+    let mut writer = ZipWriter::new(out);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    writer
+        .start_file(MIMETYPE, stored)
+        .and_then(|()| Ok(writer.write_all(EPUB_MIMETYPE)?))
+        .map_err(|e| format!("write {MIMETYPE}: {e}"))?;
+
+    let mut seen = HashSet::from([MIMETYPE.to_string()]);
+    for index in 0..zip.len() {
+        let entry = zip
+            .by_index_raw(index)
+            .map_err(|e| format!("entry {index}: {e}"))?;
+        let name = entry.name().to_string();
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        if entry.is_dir() {
+            writer
+                .add_directory(name.as_str(), SimpleFileOptions::default())
+                .map_err(|e| format!("write {name}: {e}"))?;
+        } else if entry.compression() == CompressionMethod::Deflated {
+            writer
+                .raw_copy_file(entry)
+                .map_err(|e| format!("copy {name}: {e}"))?;
+        } else {
+            let large = entry.size() >= u64::from(u32::MAX);
+            drop(entry);
+            let mut entry = zip
+                .by_index(index)
+                .map_err(|e| format!("entry {name}: {e}"))?;
+            let deflated = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .large_file(large);
+            writer
+                .start_file(name.as_str(), deflated)
+                .map_err(|e| format!("write {name}: {e}"))?;
+            io::copy(&mut entry, &mut writer).map_err(|e| format!("copy {name}: {e}"))?;
+        }
+    }
+    writer.finish().map_err(|e| format!("zip finish: {e}"))?;
+    Ok(())
+}
+
+/// Whether the EPUB at `path` is structured the way strict readers expect,
+/// and every entry reads back intact.
+fn check_epub(path: &Path) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    {
+        let mut first = zip.by_index(0).map_err(|e| format!("first entry: {e}"))?;
+        if first.name() != MIMETYPE || first.compression() != CompressionMethod::Stored {
+            return Err("mimetype is not the first stored entry".into());
+        }
+        let mut mimetype = Vec::new();
+        first
+            .read_to_end(&mut mimetype)
+            .map_err(|e| format!("read {MIMETYPE}: {e}"))?;
+        if mimetype != EPUB_MIMETYPE {
+            return Err("mimetype is not application/epub+zip".into());
+        }
+    }
+    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf: {e}"))?;
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("entry {index}: {e}"))?;
+        let name = entry.name().to_string();
+        // Reading to the end is what verifies the CRC.
+        io::copy(&mut entry, &mut io::sink()).map_err(|e| format!("read {name}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Repack the EPUB at `source` into `target`, which may be `source` itself.
+fn repack(source: &Path, target: &Path) -> Result<(), String> {
+    let file = File::open(source).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    replace_file_checked(target, |out| write_repacked(&mut zip, out), check_epub)
+}
+
+/// Rebuild the EPUB at `path` with a stored `mimetype` as its first entry
+/// and the rest deflated. Writes to `output` when given, otherwise replaces
+/// the book, and returns the path written. Fails without touching anything
+/// if the result wouldn't open.
+#[tauri::command]
+pub async fn repack_epub(
+    app: AppHandle,
+    path: String,
+    output: Option<String>,
+) -> Result<String, String> {
+    let source = PathBuf::from(&path);
+    let target = output.map_or_else(|| source.clone(), PathBuf::from);
+    let scope = app.fs_scope();
+    if !scope.is_allowed(&source) || !scope.is_allowed(&target) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    if !source.is_file() {
+        return Err(format!("file not found: {path}"));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        repack(&source, &target)?;
+        Ok(target.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8], CompressionMethod)]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, data, method) in entries {
+            let options = SimpleFileOptions::default().compression_method(*method);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    const CONTAINER: &[u8] =
+        br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+
+    #[test]
+    fn puts_a_stored_mimetype_first() {
+        let dir = temp_dir("epub-repack-fix");
+        let book = dir.join("book.epub");
+        write_zip(
+            &book,
+            &[
+                (
+                    "META-INF/container.xml",
+                    CONTAINER,
+                    CompressionMethod::Stored,
+                ),
+                (
+                    "OEBPS/content.opf",
+                    b"<package/>",
+                    CompressionMethod::Deflated,
+                ),
+                (
+                    "mimetype",
+                    b"application/epub+zip\n",
+                    CompressionMethod::Deflated,
+                ),
+            ],
+        );
+        assert!(check_epub(&book).is_err());
+
+        let fixed = dir.join("fixed.epub");
+        repack(&book, &fixed).unwrap();
+        check_epub(&fixed).unwrap();
+        let mut zip = ZipArchive::new(File::open(&fixed).unwrap()).unwrap();
+        let names: Vec<_> = zip.file_names().map(str::to_string).collect();
+        assert_eq!(names.len(), 3);
+        for index in 1..zip.len() {
+            let entry = zip.by_index(index).unwrap();
+            assert_eq!(entry.compression(), CompressionMethod::Deflated);
+        }
+        assert_eq!(
+            read_zip_entry(&mut zip, "OEBPS/content.opf").unwrap(),
+            b"<package/>"
+        );
+        // `output` was given, so the original is untouched.
+        assert!(check_epub(&book).is_err());
+
+        repack(&book, &book).unwrap();
+        check_epub(&book).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unreadable_result_leaves_the_book_untouched() {
+        let dir = temp_dir("epub-repack-broken");
+        let book = dir.join("book.epub");
+        write_zip(
+            &book,
+            &[(
+                "OEBPS/content.opf",
+                b"<package/>",
+                CompressionMethod::Stored,
+            )],
+        );
+        let before = std::fs::read(&book).unwrap();
+        assert!(repack(&book, &book).unwrap_err().contains("container.xml"));
+        assert_eq!(std::fs::read(&book).unwrap(), before);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,588 @@
+// `list_epub_stylesheets`: the CSS an EPUB ships, for the reading-styles
+// inspector and for diagnosing "the book ignores my font settings" reports.
+//
+// Every `.css` entry in the zip is listed with the spine documents that link
+// it through `<link rel="stylesheet">`, in reading order. With `inline`, the
+// `<style>` blocks of spine documents are listed too, named
+// `<document>#style-<n>` (1-based). Bodies are returned only when asked for,
+// and only up to [`MAX_CONTENT_BYTES`] in total; past that, entries are still
+// listed with their size but without content. Non-EPUB formats have no
+// stylesheets and return an empty list.
+
+use percent_encoding::percent_decode_str;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::{
+    local_name, read_rootfile_path, read_zip_entry, resolve_relative, spine_documents,
+    strip_xml_bom,
+};
+
+/// Most CSS returned by one call, summed over every body.
+const MAX_CONTENT_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StylesheetKind {
+    /// A `.css` file in the zip.
+    File,
+    /// A `<style>` block in a spine document.
+    Inline,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StylesheetInfo {
+    /// Zip path of the file, or `<document>#style-<n>` for an inline block.
+    pub href: String,
+    pub kind: StylesheetKind,
+    /// Size in bytes (uncompressed).
+    pub size: u64,
+    /// The CSS, when requested and within the size budget.
+    pub content: Option<String>,
+    /// Zip paths of the spine documents using it, in reading order.
+    pub linked_from: Vec<String>,
+}
+
+/// Stylesheets of the EPUB at `path`. `inline` adds `<style>` blocks from
+/// spine documents; `with_content` returns the CSS itself, not just where it
+/// is (both off by default).
+#[tauri::command]
+pub async fn list_epub_stylesheets(
+    path: String,
+    inline: Option<bool>,
+    with_content: Option<bool>,
+) -> Result<Vec<StylesheetInfo>, String> {
+    let inline = inline.unwrap_or(false);
+    let with_content = with_content.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        list_epub_stylesheets_sync(&path, inline, with_content)
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+fn list_epub_stylesheets_sync(
+    file_path: &str,
+    inline: bool,
+    with_content: bool,
+) -> Result<Vec<StylesheetInfo>, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+    let is_epub = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"));
+    if !is_epub {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+    list_stylesheets(&mut zip, inline, with_content)
+}
+
+/// Spine documents [`spine_writing_mode`] looks at. Books usually style
+/// every chapter alike, but the first pages are often a horizontal cover or
+/// title page.
+const WRITING_MODE_DOCUMENTS: usize = 4;
+
+/// What one spine document pulls in.
+#[derive(Debug, Default, PartialEq)]
+struct DocumentStyles {
+    /// Resolved, percent-decoded zip paths of linked stylesheets.
+    links: Vec<String>,
+    /// Text of each `<style>` block.
+    blocks: Vec<String>,
+    /// Simple selectors matching the document's `<html>` or `<body>`: the
+    /// element names, `:root`, and their classes (`.c`) and ids (`#i`).
+    root_selectors: Vec<String>,
+    /// `style` attributes of `<html>` and `<body>`.
+    root_inline: Vec<String>,
+}
+
+fn list_stylesheets<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    inline: bool,
+    with_content: bool,
+) -> Result<Vec<StylesheetInfo>, String> {
+    // A book without a readable spine still has its CSS files listed.
+    let documents = spine_paths(zip).unwrap_or_else(|e| {
+        log::warn!("No spine to link stylesheets to: {e}");
+        Vec::new()
+    });
+    let mut linked_from: HashMap<String, Vec<String>> = HashMap::new();
+    let mut blocks: Vec<(String, String)> = Vec::new();
+    for doc in &documents {
+        let Ok(bytes) = read_zip_entry(zip, doc) else {
+            continue;
+        };
+        let styles = document_styles(&bytes, doc);
+        for link in styles.links {
+            let users = linked_from.entry(link).or_default();
+            if !users.contains(doc) {
+                users.push(doc.clone());
+            }
+        }
+        if inline {
+            for (n, css) in styles.blocks.into_iter().enumerate() {
+                blocks.push((format!("{doc}#style-{}", n + 1), css));
+            }
+        }
+    }
+
+    let mut budget = if with_content { MAX_CONTENT_BYTES } else { 0 };
+    let mut take = |size: u64| {
+        let fits = size <= budget;
+        if fits {
+            budget -= size;
+        }
+        fits
+    };
+
+    let mut sheets = Vec::new();
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("entry {index}: {e}"))?;
+        let name = entry.name().to_string();
+        if !entry.is_file() || !name.to_ascii_lowercase().ends_with(".css") {
+            continue;
+        }
+        let size = entry.size();
+        let content = if with_content && take(size) {
+            let mut bytes = Vec::with_capacity(size as usize);
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("read {name}: {e}"))?;
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        } else {
+            None
+        };
+        sheets.push(StylesheetInfo {
+            linked_from: linked_from.remove(&name).unwrap_or_default(),
+            href: name,
+            kind: StylesheetKind::File,
+            size,
+            content,
+        });
+    }
+    for (href, css) in blocks {
+        let size = css.len() as u64;
+        let doc = href
+            .rsplit_once('#')
+            .map_or(&*href, |(doc, _)| doc)
+            .to_string();
+        sheets.push(StylesheetInfo {
+            content: (with_content && take(size)).then_some(css),
+            href,
+            kind: StylesheetKind::Inline,
+            size,
+            linked_from: vec![doc],
+        });
+    }
+    Ok(sheets)
+}
+
+/// Zip paths of the spine documents, in reading order.
+fn spine_paths<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Vec<String>, String> {
+    let opf_path = read_rootfile_path(zip)?;
+    let opf_bytes = read_zip_entry(zip, &opf_path)?;
+    Ok(spine_documents(&opf_bytes)?
+        .iter()
+        .map(|href| resolve_relative(&opf_path, href))
+        .collect())
+}
+
+/// The writing mode the first spine documents of the EPUB give their
+/// `<html>` or `<body>`, through a `style` attribute or a rule whose
+/// selector names the element, `:root`, or one of its classes or ids
+/// (the `class="vrtl"` convention of Japanese publishers). A vertical mode
+/// in any of the first [`WRITING_MODE_DOCUMENTS`] wins over horizontal
+/// ones, which are usually just the cover. `None` when none of them sets one.
+pub(crate) fn spine_writing_mode<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    opf_path: &str,
+    opf_bytes: &[u8],
+) -> Option<&'static str> {
+    let documents = spine_documents(opf_bytes).ok()?;
+    let mut found = None;
+    for href in documents.iter().take(WRITING_MODE_DOCUMENTS) {
+        let doc = resolve_relative(opf_path, href);
+        let Ok(bytes) = read_zip_entry(zip, &doc) else {
+            continue;
+        };
+        let styles = document_styles(&bytes, &doc);
+        let mut mode = None;
+        for link in &styles.links {
+            if let Ok(css) = read_zip_entry(zip, link) {
+                let css = String::from_utf8_lossy(&css);
+                mode = css_root_writing_mode(&css, &styles.root_selectors).or(mode);
+            }
+        }
+        for css in &styles.blocks {
+            mode = css_root_writing_mode(css, &styles.root_selectors).or(mode);
+        }
+        for declarations in &styles.root_inline {
+            mode = declared_writing_mode(declarations).or(mode);
+        }
+        if let Some(mode) = mode {
+            if mode.starts_with("vertical") {
+                return Some(mode);
+            }
+            found = Some(mode);
+        }
+    }
+    found
+}
+
+/// A CSS `writing-mode` value in its CSS 3 spelling, mapping the SVG 1.1
+/// forms older books use (`tb-rl`, `lr-tb`, …). `None` for anything else.
+pub(crate) fn normalize_writing_mode(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_ascii_lowercase();
+    let value = value.trim_end_matches("!important").trim();
+    match value {
+        "horizontal-tb" | "lr-tb" | "lr" | "rl-tb" | "rl" => Some("horizontal-tb"),
+        "vertical-rl" | "tb-rl" | "tb" => Some("vertical-rl"),
+        "vertical-lr" | "tb-lr" => Some("vertical-lr"),
+        _ => None,
+    }
+}
+
+/// The last `writing-mode` (or its `-epub-`/`-webkit-` alias) in a
+/// declaration block.
+fn declared_writing_mode(declarations: &str) -> Option<&'static str> {
+    declarations
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(property, _)| {
+            let property = property.trim().to_ascii_lowercase();
+            matches!(
+                property.as_str(),
+                "writing-mode" | "-epub-writing-mode" | "-webkit-writing-mode"
+            )
+        })
+        .filter_map(|(_, value)| normalize_writing_mode(value))
+        .next_back()
+}
+
+/// Whether a single selector matches the root: its last compound is made
+/// only of `roots`.
+fn selects_root(selector: &str, roots: &[String]) -> bool {
+    let Some(compound) = selector
+        .rsplit(|c: char| c.is_whitespace() || matches!(c, '>' | '+' | '~'))
+        .find(|part| !part.is_empty())
+    else {
+        return false;
+    };
+    let mut simple = Vec::new();
+    let mut start = 0;
+    for (i, c) in compound.char_indices().skip(1) {
+        if matches!(c, '.' | '#') || (c == ':' && !compound[..i].ends_with(':')) {
+            simple.push(&compound[start..i]);
+            start = i;
+        }
+    }
+    simple.push(&compound[start..]);
+    simple
+        .iter()
+        .all(|s| roots.iter().any(|root| root.eq_ignore_ascii_case(s)))
+}
+
+/// The last `writing-mode` set on the root by the rules of `css`. Rules in
+/// `@media` and `@supports` count; other at-rules are skipped.
+fn css_root_writing_mode(css: &str, roots: &[String]) -> Option<&'static str> {
+    let mut css = css.to_string();
+    while let Some(start) = css.find("/*") {
+        let end = css[start + 2..]
+            .find("*/")
+            .map_or(css.len(), |e| start + e + 4);
+        css.replace_range(start..end, " ");
+    }
+    let mut rest = css.as_str();
+    let mut found = None;
+    while let Some(open) = rest.find('{') {
+        let prelude = rest[..open].rsplit(['}', ';']).next().unwrap_or("").trim();
+        let after = &rest[open + 1..];
+        if let Some(at_rule) = prelude.strip_prefix('@') {
+            let name = at_rule
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            if matches!(name.as_str(), "media" | "supports") {
+                rest = after;
+                continue;
+            }
+            let mut depth = 1;
+            let end = after
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(after.len(), |(i, _)| i + 1);
+            rest = &after[end..];
+            continue;
+        }
+        let close = after.find('}').unwrap_or(after.len());
+        if prelude
+            .split(',')
+            .any(|selector| selects_root(selector, roots))
+        {
+            found = declared_writing_mode(&after[..close]).or(found);
+        }
+        rest = after.get(close + 1..).unwrap_or("");
+    }
+    found
+}
+
+/// Stylesheet links and `<style>` blocks of the XHTML document at zip path
+/// `doc`. Lenient about HTML that isn't well-formed XML: a parse error ends
+/// the scan with whatever was found so far.
+fn document_styles(bytes: &[u8], doc: &str) -> DocumentStyles {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    reader.config_mut().check_end_names = false;
+    let mut buf = Vec::new();
+    let mut styles = DocumentStyles::default();
+    let mut block: Option<String> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if local_name(e.name().as_ref()).eq_ignore_ascii_case(b"link") =>
+            {
+                let mut rel = String::new();
+                let mut href = None;
+                for attr in e.attributes().flatten() {
+                    let value = attr.unescape_value().ok().map(|v| v.into_owned());
+                    match attr.key.as_ref() {
+                        b"rel" => rel = value.unwrap_or_default(),
+                        b"href" => href = value,
+                        _ => {}
+                    }
+                }
+                let is_stylesheet = rel
+                    .split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("stylesheet"));
+                if let (true, Some(href)) = (is_stylesheet, href) {
+                    let resolved = resolve_relative(doc, &href);
+                    let resolved = percent_decode_str(&resolved)
+                        .decode_utf8_lossy()
+                        .into_owned();
+                    if !styles.links.contains(&resolved) {
+                        styles.links.push(resolved);
+                    }
+                }
+            }
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if matches!(
+                    local_name(e.name().as_ref())
+                        .to_ascii_lowercase()
+                        .as_slice(),
+                    b"html" | b"body"
+                ) =>
+            {
+                let element = String::from_utf8_lossy(local_name(e.name().as_ref())).into_owned();
+                if !styles.root_selectors.contains(&element) {
+                    styles.root_selectors.push(element);
+                }
+                for attr in e.attributes().flatten() {
+                    let value = attr.unescape_value().ok().map(|v| v.into_owned());
+                    match (attr.key.as_ref(), value) {
+                        (b"class", Some(classes)) => styles
+                            .root_selectors
+                            .extend(classes.split_whitespace().map(|c| format!(".{c}"))),
+                        (b"id", Some(id)) => styles.root_selectors.push(format!("#{}", id.trim())),
+                        (b"style", Some(style)) => styles.root_inline.push(style),
+                        _ => {}
+                    }
+                }
+                if !styles.root_selectors.iter().any(|s| s == ":root") {
+                    styles.root_selectors.push(":root".to_string());
+                }
+            }
+            Ok(Event::Start(e)) if local_name(e.name().as_ref()).eq_ignore_ascii_case(b"style") => {
+                block = Some(String::new());
+            }
+            Ok(Event::Text(t)) if block.is_some() => {
+                if let (Some(css), Ok(text)) = (block.as_mut(), t.unescape()) {
+                    css.push_str(&text);
+                }
+            }
+            Ok(Event::CData(t)) if block.is_some() => {
+                if let Some(css) = block.as_mut() {
+                    css.push_str(&String::from_utf8_lossy(&t));
+                }
+            }
+            Ok(Event::End(e)) if local_name(e.name().as_ref()).eq_ignore_ascii_case(b"style") => {
+                if let Some(css) = block.take() {
+                    styles.blocks.push(css);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn epub(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut w = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            w.start_file(*name, SimpleFileOptions::default()).unwrap();
+            w.write_all(data.as_bytes()).unwrap();
+        }
+        ZipArchive::new(w.finish().unwrap()).unwrap()
+    }
+
+    const CONTAINER: &str = r#"<container><rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+    const OPF: &str = r#"<package><manifest>
+  <item id="c1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  <item id="c2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+  <item id="css" href="styles/book.css" media-type="text/css"/>
+</manifest><spine><itemref idref="c2"/><itemref idref="c1"/></spine></package>"#;
+    const CH1: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml"><head>
+<link rel="stylesheet" type="text/css" href="../styles/book.css"/>
+<style>p { font-family: "Old Style"; }</style>
+</head><body><p>One</p></body></html>"#;
+    const CH2: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml"><head>
+<link href="../styles/book.css" rel="stylesheet"/>
+<link href="../styles/My%20Fonts.css" rel="alternate stylesheet"/>
+</head><body><p>Two</p></body></html>"#;
+
+    fn sample() -> ZipArchive<Cursor<Vec<u8>>> {
+        epub(&[
+            ("META-INF/container.xml", CONTAINER),
+            ("OEBPS/content.opf", OPF),
+            ("OEBPS/text/ch1.xhtml", CH1),
+            ("OEBPS/text/ch2.xhtml", CH2),
+            ("OEBPS/styles/book.css", "body { margin: 0 }"),
+            ("OEBPS/styles/My Fonts.css", "@font-face { font-family: X }"),
+            ("OEBPS/styles/unused.css", "h1 { color: red }"),
+        ])
+    }
+
+    #[test]
+    fn lists_files_with_the_documents_linking_them() {
+        let sheets = list_stylesheets(&mut sample(), false, false).unwrap();
+        let summary: Vec<(&str, Vec<&str>, bool)> = sheets
+            .iter()
+            .map(|s| {
+                let users = s.linked_from.iter().map(String::as_str).collect();
+                (s.href.as_str(), users, s.content.is_some())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "OEBPS/styles/book.css",
+                    vec!["OEBPS/text/ch2.xhtml", "OEBPS/text/ch1.xhtml"],
+                    false
+                ),
+                (
+                    "OEBPS/styles/My Fonts.css",
+                    vec!["OEBPS/text/ch2.xhtml"],
+                    false
+                ),
+                ("OEBPS/styles/unused.css", vec![], false),
+            ]
+        );
+        assert_eq!(sheets[0].size, 18);
+    }
+
+    #[test]
+    fn inline_blocks_and_bodies_on_request() {
+        let sheets = list_stylesheets(&mut sample(), true, true).unwrap();
+        assert_eq!(sheets.len(), 4);
+        assert_eq!(sheets[0].content.as_deref(), Some("body { margin: 0 }"));
+        let inline = &sheets[3];
+        assert_eq!(inline.kind, StylesheetKind::Inline);
+        assert_eq!(inline.href, "OEBPS/text/ch1.xhtml#style-1");
+        assert_eq!(inline.linked_from, ["OEBPS/text/ch1.xhtml"]);
+        assert_eq!(
+            inline.content.as_deref(),
+            Some(r#"p { font-family: "Old Style"; }"#)
+        );
+    }
+
+    #[test]
+    fn content_stops_at_the_budget() {
+        let big = "a".repeat(MAX_CONTENT_BYTES as usize - 10);
+        let mut zip = epub(&[
+            ("big.css", big.as_str()),
+            ("small.css", "p {}"),
+            ("next.css", "body { color: black }"),
+        ]);
+        let sheets = list_stylesheets(&mut zip, false, true).unwrap();
+        let has_content: Vec<bool> = sheets.iter().map(|s| s.content.is_some()).collect();
+        assert_eq!(has_content, [true, true, false]);
+        assert_eq!(sheets[2].size, 21);
+    }
+
+    #[test]
+    fn writing_mode_comes_from_rules_on_the_root() {
+        let opf = r#"<package><manifest>
+  <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+  <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+</manifest><spine><itemref idref="cover"/><itemref idref="c1"/></spine></package>"#;
+        let css = "/* html { writing-mode: vertical-lr } */\n\
+            @font-face { font-family: Mincho; src: url(m.otf) }\n\
+            .hltr { writing-mode: horizontal-tb }\n\
+            @media amzn-kf8 { body p { writing-mode: vertical-lr } }\n\
+            html.vrtl { -epub-writing-mode: tb-rl; }";
+        let doc = |class: &str| {
+            format!(
+                r#"<html xmlns="http://www.w3.org/1999/xhtml" class="{class}"><head>
+<link rel="stylesheet" href="book.css"/></head><body><p>本</p></body></html>"#
+            )
+        };
+        let (cover, chapter) = (doc("hltr"), doc("vrtl"));
+        let mut zip = epub(&[
+            ("META-INF/container.xml", CONTAINER),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/cover.xhtml", &cover),
+            ("OEBPS/ch1.xhtml", &chapter),
+            ("OEBPS/book.css", css),
+        ]);
+        assert_eq!(
+            spine_writing_mode(&mut zip, "OEBPS/content.opf", opf.as_bytes()),
+            Some("vertical-rl")
+        );
+
+        let roots = ["html".to_string(), ":root".to_string()];
+        assert_eq!(
+            css_root_writing_mode(":root { writing-mode: vertical-rl !important }", &roots),
+            Some("vertical-rl")
+        );
+        assert_eq!(
+            css_root_writing_mode("html p { writing-mode: tb-rl }", &roots),
+            None
+        );
+        assert_eq!(
+            declared_writing_mode("color: red; writing-mode: sideways"),
+            None
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! `open_external_url`: the single place links leave the app.
+//!
+//! Every URL is parsed and its scheme checked against [`scheme_policy`]
+//! before anything is launched, so the frontend can't be talked into opening
+//! `file://`, `javascript:` or an arbitrary custom protocol handler. The
+//! webview's `on_navigation` hook routes Alipay links through
+//! [`open_url`] too, so both paths share the same policy.
+
+use serde::Serialize;
+use tauri::{AppHandle, Url};
+
+#[cfg(target_os = "android")]
+use tauri_plugin_native_bridge::{NativeBridgeExt, OpenExternalUrlRequest};
+use tauri_plugin_opener::OpenerExt;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ExternalUrlError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("scheme is not allowed: {0}")]
+    DisallowedScheme(String),
+    #[error("failed to open URL: {0}")]
+    Failed(String),
+}
+
+/// How an allowed scheme is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SchemePolicy {
+    /// Handed to the system default handler (browser, mail client).
+    System,
+    /// Payment apps. Android needs the native bridge to launch them with an
+    /// explicit intent; elsewhere they go to the system handler like links.
+    PaymentApp,
+}
+
+fn scheme_policy(scheme: &str) -> Option<SchemePolicy> {
+    match scheme {
+        "http" | "https" | "mailto" => Some(SchemePolicy::System),
+        "alipay" | "alipays" => Some(SchemePolicy::PaymentApp),
+        _ => None,
+    }
+}
+
+/// Whether navigation to `url` should be intercepted and sent to a payment
+/// app instead of loading in the webview.
+pub fn is_payment_url(url: &Url) -> bool {
+    scheme_policy(url.scheme()) == Some(SchemePolicy::PaymentApp)
+}
+
+/// Open an already-parsed URL outside the app, enforcing the scheme policy.
+pub fn open_url(app: &AppHandle, url: &Url) -> Result<(), ExternalUrlError> {
+    let policy = scheme_policy(url.scheme())
+        .ok_or_else(|| ExternalUrlError::DisallowedScheme(url.scheme().to_string()))?;
+
+    #[cfg(target_os = "android")]
+    if policy == SchemePolicy::PaymentApp {
+        let response = app
+            .native_bridge()
+            .open_external_url(OpenExternalUrlRequest {
+                url: url.to_string(),
+            })
+            .map_err(|e| ExternalUrlError::Failed(e.to_string()))?;
+        return if response.success {
+            Ok(())
+        } else {
+            Err(ExternalUrlError::Failed(
+                response
+                    .error
+                    .unwrap_or_else(|| "unknown error".to_string()),
+            ))
+        };
+    }
+    #[cfg(not(target_os = "android"))]
+    let _ = policy;
+
+    app.opener()
+        .open_url(url.as_str(), None::<&str>)
+        .map_err(|e| ExternalUrlError::Failed(e.to_string()))
+}
+
+/// Open `url` in the system handler for its scheme. Only `http`, `https`,
+/// `mailto` and the Alipay payment schemes are accepted.
+#[tauri::command]
+pub async fn open_external_url(app: AppHandle, url: String) -> Result<(), ExternalUrlError> {
+    let parsed =
+        Url::parse(url.trim()).map_err(|e| ExternalUrlError::InvalidUrl(format!("{url}: {e}")))?;
+    open_url(&app, &parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_payment_url, scheme_policy, SchemePolicy};
+    use tauri::Url;
+
+    #[test]
+    fn allows_web_mail_and_payment_schemes() {
+        assert_eq!(scheme_policy("https"), Some(SchemePolicy::System));
+        assert_eq!(scheme_policy("http"), Some(SchemePolicy::System));
+        assert_eq!(scheme_policy("mailto"), Some(SchemePolicy::System));
+        assert_eq!(scheme_policy("alipays"), Some(SchemePolicy::PaymentApp));
+    }
+
+    #[test]
+    fn rejects_local_and_script_schemes() {
+        for scheme in ["file", "javascript", "data", "tauri", "ms-settings"] {
+            assert_eq!(scheme_policy(scheme), None, "{scheme}");
+        }
+    }
+
+    #[test]
+    fn detects_payment_urls() {
+        let pay = Url::parse("alipays://platformapi/startapp?appId=1").unwrap();
+        let web = Url::parse("https://readest.com").unwrap();
+        assert!(is_payment_url(&pay));
+        assert!(!is_payment_url(&web));
+    }
+}
@@ -22,31 +22,82 @@ use tauri_plugin_fs::FsExt;
 
 #[cfg(desktop)]
 use tauri::{Listener, Url};
+#[cfg(desktop)]
+mod acsm_handoff;
+#[cfg(desktop)]
+mod app_reset;
+#[cfg(target_os = "linux")]
+mod appimage_update;
+#[cfg(desktop)]
+mod archive_books;
+mod book_cover;
+mod book_drm;
+mod book_duplicates;
+mod book_id;
+mod book_images;
+mod book_ingest;
+mod book_language;
+mod book_metadata;
+mod book_rename;
+mod book_thumbnails;
 mod clip_url;
+mod cover_color;
+mod crash_report;
+mod default_reader;
 mod dir_scanner;
 #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
 mod discord_rpc;
+mod disk_space;
+mod epub_accessibility;
+mod epub_fonts;
 mod epub_parser;
+mod epub_repack;
+mod epub_styles;
+mod external_url;
+mod library_index;
+#[cfg(desktop)]
+mod library_watcher;
 #[cfg(target_os = "macos")]
 mod macos;
 mod mobi_parser;
 mod nightly_update;
+mod oauth_server;
+mod opds;
 mod parser_common;
+mod portable;
+mod position_sidecar;
+mod qr_code;
 mod range_file;
+mod reader_capture;
+mod reading_time;
+mod recent_files;
+mod remote_cover;
+#[cfg(desktop)]
+mod stdin_book;
+mod taskbar_progress;
+#[cfg(test)]
+mod test_support;
+#[cfg(target_os = "windows")]
+mod thumbnail_registration;
+mod toc_parser;
 mod transfer_file;
+mod tts_voices;
+#[cfg(desktop)]
+mod window_activity;
+#[cfg(desktop)]
+mod window_on_top;
 #[cfg(desktop)]
 mod window_state;
+#[cfg(desktop)]
+mod window_title;
 #[cfg(target_os = "windows")]
 use tauri::webview::ScrollBarStyle;
-use tauri::{command, Emitter, WebviewUrl, WebviewWindowBuilder, Window};
+use tauri::{command, Emitter, WebviewUrl, WebviewWindowBuilder};
 #[cfg(target_os = "android")]
 use tauri_plugin_native_bridge::register_select_directory_callback;
-#[cfg(target_os = "android")]
-use tauri_plugin_native_bridge::{NativeBridgeExt, OpenExternalUrlRequest};
-use tauri_plugin_oauth::start;
-#[cfg(not(target_os = "android"))]
-use tauri_plugin_opener::OpenerExt;
-use transfer_file::{download_file, upload_file};
+use transfer_file::{
+    download_file, pause_all_transfers, probe_url, resume_all_transfers, upload_file,
+};
 
 #[cfg(any(desktop, target_os = "ios"))]
 fn allow_file_in_scopes(app: &AppHandle, files: Vec<PathBuf>) {
@@ -203,6 +254,9 @@ fn get_files_from_argv(argv: Vec<String>) -> Vec<PathBuf> {
 
 #[cfg(desktop)]
 fn set_window_open_with_files(app: &AppHandle, files: Vec<PathBuf>) {
+    for file in &files {
+        crash_report::breadcrumb("file-opened", file.to_string_lossy());
+    }
     let files = files
         .into_iter()
         .map(|f| {
@@ -221,16 +275,6 @@ fn set_window_open_with_files(app: &AppHandle, files: Vec<PathBuf>) {
     }
 }
 
-#[command]
-async fn start_server(window: Window) -> Result<u16, String> {
-    start(move |url| {
-        // Because of the unprotected localhost port, you must verify the URL here.
-        // Preferebly send back only the token, or nothing at all if you can handle everything else in Rust.
-        let _ = window.emit("redirect_uri", url);
-    })
-    .map_err(|err| err.to_string())
-}
-
 #[tauri::command]
 fn get_environment_variable(name: &str) -> String {
     std::env::var(String::from(name)).unwrap_or(String::from(""))
@@ -245,6 +289,46 @@ fn get_executable_dir() -> String {
         .unwrap_or_default()
 }
 
+/// Open or close DevTools on the main window and return whether they are now
+/// open. Only live in debug builds, or in `devtools`-feature builds run with
+/// `READEST_DEVTOOLS` set; otherwise a no-op that returns `false`, so release
+/// users can't reach the inspector.
+#[tauri::command]
+fn toggle_devtools(app: AppHandle) -> bool {
+    #[cfg(any(debug_assertions, feature = "devtools"))]
+    {
+        if !cfg!(debug_assertions) && std::env::var_os("READEST_DEVTOOLS").is_none() {
+            return false;
+        }
+        let Some(window) = app.get_webview_window("main") else {
+            return false;
+        };
+        // Opening is asynchronous on some platforms, so report the state we
+        // asked for rather than re-querying `is_devtools_open`.
+        let open = !window.is_devtools_open();
+        if open {
+            window.open_devtools();
+        } else {
+            window.close_devtools();
+        }
+        open
+    }
+    #[cfg(not(any(debug_assertions, feature = "devtools")))]
+    {
+        let _ = app;
+        false
+    }
+}
+
+/// `--safe-mode` flag or a non-empty, non-`0` `READEST_SAFE_MODE`: start
+/// without the optional plugins (single instance, deep links, updater, saved
+/// window geometry, Discord presence) so a misbehaving one can be ruled out
+/// without reinstalling. The webview sees `window.__READEST_SAFE_MODE`.
+fn is_safe_mode() -> bool {
+    let from_env = std::env::var_os("READEST_SAFE_MODE").is_some_and(|v| !v.is_empty() && v != "0");
+    from_env || std::env::args().skip(1).any(|arg| arg == "--safe-mode")
+}
+
 #[derive(Clone, serde::Serialize)]
 #[allow(dead_code)]
 struct SingleInstancePayload {
@@ -254,30 +338,112 @@ struct SingleInstancePayload {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let safe_mode = is_safe_mode();
+    #[cfg(desktop)]
+    let stdin_mode = stdin_book::requested();
+
+    portable::prepare();
+
+    let log_builder = tauri_plugin_log::Builder::new()
+        .level(log::LevelFilter::Info)
+        .level_for("tracing", log::LevelFilter::Warn)
+        .level_for("tantivy", log::LevelFilter::Warn);
+    // Portable installs log beside the executable instead of the profile.
+    let log_builder = match portable::log_dir() {
+        Some(path) => {
+            use tauri_plugin_log::{Target, TargetKind};
+            log_builder.clear_targets().targets([
+                Target::new(TargetKind::Stdout),
+                Target::new(TargetKind::Folder {
+                    path,
+                    file_name: None,
+                }),
+            ])
+        }
+        None => log_builder,
+    };
+
     let builder = tauri::Builder::default()
-        .plugin(
-            tauri_plugin_log::Builder::new()
-                .level(log::LevelFilter::Info)
-                .level_for("tracing", log::LevelFilter::Warn)
-                .level_for("tantivy", log::LevelFilter::Warn)
-                .build(),
-        )
+        .plugin(log_builder.build())
         .plugin(tauri_plugin_websocket::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_oauth::init())
+        .manage(oauth_server::OAuthServers::default())
         .invoke_handler(tauri::generate_handler![
-            start_server,
+            oauth_server::start_server,
+            oauth_server::stop_server,
             download_file,
             upload_file,
+            probe_url,
+            pause_all_transfers,
+            resume_all_transfers,
             get_environment_variable,
             get_executable_dir,
+            portable::get_portable_data_dir,
+            toggle_devtools,
             allow_paths_in_scopes,
+            default_reader::is_default_reader,
+            default_reader::default_reader_status,
             dir_scanner::read_dir,
+            disk_space::available_disk_space,
+            disk_space::cache_usage,
             epub_parser::parse_epub_metadata,
             epub_parser::extract_epub_cover_full,
             epub_parser::parse_epub_full,
+            epub_parser::get_page_list,
+            epub_parser::extract_epub_resource,
+            toc_parser::read_toc,
+            epub_fonts::list_embedded_fonts,
+            epub_styles::list_epub_stylesheets,
+            epub_accessibility::read_accessibility,
+            book_metadata::read_book_metadata,
+            book_rename::normalize_filename,
+            book_id::compute_book_id,
+            book_images::list_book_images,
+            book_images::extract_book_image,
+            book_ingest::ingest_book,
+            book_ingest::ingest_books,
+            book_language::detect_book_language,
+            reading_time::estimate_reading_time,
+            book_cover::set_book_cover,
+            book_thumbnails::thumbnail_data_url,
+            book_thumbnails::list_cover_candidates,
+            book_thumbnails::cover_candidate_bytes,
+            book_thumbnails::verify_thumbnail_cache,
+            book_thumbnails::generate_contact_sheet,
+            book_thumbnails::cbz_reading_direction,
+            book_thumbnails::migrate_cache,
+            book_thumbnails::thumbnail_metrics_snapshot,
+            book_thumbnails::preview_pages,
+            book_thumbnails::cancel_preview_pages,
+            book_thumbnails::generate_thumbnails_batch,
+            book_thumbnails::warm_thumbnail_cache,
+            book_thumbnails::stop_thumbnail_cache_warming,
+            book_thumbnails::power_status,
+            book_thumbnails::set_battery_saver,
+            book_thumbnails::animated_preview,
+            book_thumbnails::preview_cover,
+            book_thumbnails::partial_download_thumbnail,
+            epub_repack::repack_epub,
+            library_index::export_library_index,
+            library_index::cancel_library_export,
+            book_duplicates::find_duplicates,
+            book_duplicates::cancel_find_duplicates,
+            #[cfg(desktop)]
+            archive_books::list_archive_books,
+            #[cfg(desktop)]
+            archive_books::extract_archive_book,
+            #[cfg(desktop)]
+            archive_books::release_archive_book,
+            #[cfg(desktop)]
+            stdin_book::take_stdin_book,
+            #[cfg(desktop)]
+            stdin_book::release_stdin_book,
             mobi_parser::parse_mobi_metadata,
             mobi_parser::extract_mobi_cover_full,
+            book_drm::check_drm,
+            cover_color::cover_dominant_color,
+            crash_report::get_last_crash,
             #[cfg(target_os = "macos")]
             macos::safari_auth::auth_with_safari,
             #[cfg(target_os = "macos")]
@@ -286,14 +452,48 @@ pub fn run() {
             macos::traffic_light::set_traffic_lights,
             #[cfg(target_os = "macos")]
             macos::system_dictionary::show_lookup_popover,
+            #[cfg(target_os = "macos")]
+            macos::services::take_service_requests,
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
             discord_rpc::update_book_presence,
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
             discord_rpc::clear_book_presence,
+            #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+            discord_rpc::set_presence_update_interval,
             clip_url::clip_url,
+            reader_capture::capture_reader_view,
+            external_url::open_external_url,
+            opds::resolve_opds,
+            remote_cover::cached_remote_cover,
+            recent_files::add_recent_file,
+            recent_files::get_recent_files,
+            recent_files::clear_recent_files,
+            taskbar_progress::set_progress,
+            tts_voices::list_tts_voices,
+            tts_voices::tts_warmup,
+            position_sidecar::read_position,
+            position_sidecar::write_position,
+            qr_code::generate_qr,
+            #[cfg(target_os = "windows")]
+            thumbnail_registration::thumbnail_registration_report,
+            #[cfg(desktop)]
+            library_watcher::watch_library,
+            #[cfg(desktop)]
+            library_watcher::unwatch_library,
+            #[cfg(desktop)]
+            window_activity::get_window_state,
+            #[cfg(desktop)]
+            window_title::set_window_title,
+            #[cfg(desktop)]
+            window_on_top::set_always_on_top,
+            #[cfg(desktop)]
+            app_reset::reset_app_data,
             nightly_update::verify_update_signature,
+            nightly_update::verify_update,
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
             nightly_update::install_nightly_update,
+            #[cfg(target_os = "linux")]
+            appimage_update::install_appimage_update,
         ])
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_persisted_scope::init())
@@ -314,38 +514,94 @@ pub fn run() {
         // re-apply the offset. Scope-gated by `asset_protocol_scope`.
         .register_asynchronous_uri_scheme_protocol(range_file::SCHEME, range_file::handle);
 
+    // A piped book can't be forwarded to a running instance, so stdin mode
+    // always starts its own.
     #[cfg(desktop)]
-    let builder = builder.plugin(
-        tauri_plugin_single_instance::Builder::new()
-            .callback(move |app, argv, cwd| {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.set_focus();
-                }
-                let files = get_files_from_argv(argv.clone());
-                if !files.is_empty() {
-                    allow_file_in_scopes(app, files.clone());
-                }
-                app.emit("single-instance", SingleInstancePayload { args: argv, cwd })
-                    .unwrap();
-            })
-            .dbus_id("com.bilingify.readest".to_owned())
-            .build(),
-    );
+    let builder = if safe_mode || stdin_mode {
+        builder
+    } else {
+        builder.plugin(
+            tauri_plugin_single_instance::Builder::new()
+                .callback(move |app, argv, cwd| {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.set_focus();
+                    }
+                    let (acsm, files) = acsm_handoff::partition(get_files_from_argv(argv.clone()));
+                    if !acsm.is_empty() {
+                        acsm_handoff::hand_off(app, acsm);
+                    }
+                    for file in &files {
+                        crash_report::breadcrumb("file-opened", file.to_string_lossy());
+                    }
+                    recent_files::record(app, &files);
+                    if !files.is_empty() {
+                        allow_file_in_scopes(app, files.clone());
+                    }
+                    let argv = acsm_handoff::strip_args(argv);
+                    app.emit("single-instance", SingleInstancePayload { args: argv, cwd })
+                        .unwrap();
+                })
+                .dbus_id("com.bilingify.readest".to_owned())
+                .build(),
+        )
+    };
 
-    let builder = builder.plugin(tauri_plugin_deep_link::init());
+    let builder = if safe_mode {
+        builder
+    } else {
+        builder.plugin(tauri_plugin_deep_link::init())
+    };
 
     #[cfg(desktop)]
-    let builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
+    let builder = if safe_mode {
+        builder
+    } else {
+        builder.plugin(tauri_plugin_updater::Builder::new().build())
+    };
+
+    // Library folder watchers only feed the main window, so drop them (and
+    // their debounce threads) when it is destroyed. Focus and visibility
+    // changes are forwarded to the frontend as events, pinned windows are
+    // re-pinned as they enter or leave fullscreen, and thumbnail cache
+    // warming holds off while a window has focus.
+    #[cfg(desktop)]
+    let builder = builder
+        .manage(library_watcher::LibraryWatchers::default())
+        .manage(archive_books::ExtractedBooks::default())
+        .manage(book_thumbnails::PreviewRequests::default())
+        .manage(window_activity::WindowVisibility::default())
+        .manage(window_on_top::AlwaysOnTop::default())
+        .on_window_event(|window, event| {
+            window_activity::handle_window_event(window, event);
+            window_on_top::handle_window_event(window, event);
+            if let tauri::WindowEvent::Focused(focused) = event {
+                windows_thumbnail::set_cache_warming_paused(*focused);
+            }
+            if matches!(event, tauri::WindowEvent::Destroyed) && window.label() == "main" {
+                library_watcher::unwatch_all(window.app_handle());
+            }
+        });
 
     // Strip invalid geometry from the saved window state before the
     // window-state plugin loads it, so a bad `.window-state.json` (e.g. the
     // Windows minimized `-32000` sentinel) can't crash WebView2 on launch.
     // See https://github.com/readest/readest/issues/4398.
+    // Safe mode opens the window at its default size instead of restoring
+    // saved geometry.
     #[cfg(desktop)]
-    let builder = builder.plugin(window_state::init());
-
-    #[cfg(desktop)]
-    let builder = builder.plugin(tauri_plugin_window_state::Builder::default().build());
+    let builder = if safe_mode {
+        builder
+    } else {
+        // An absolute file name overrides the plugin's config-dir location.
+        let state_plugin = match portable::data_dir() {
+            Some(dir) => tauri_plugin_window_state::Builder::default()
+                .with_filename(dir.join(window_state::STATE_FILENAME).to_string_lossy()),
+            None => tauri_plugin_window_state::Builder::default(),
+        };
+        builder
+            .plugin(window_state::init())
+            .plugin(state_plugin.build())
+    };
 
     #[cfg(target_os = "macos")]
     let builder = builder.plugin(macos::traffic_light::init());
@@ -366,7 +622,13 @@ pub fn run() {
     let builder = builder.plugin(tauri_plugin_webdriver::init());
 
     builder
-        .setup(|#[allow(unused_variables)] app| {
+        .setup(move |#[allow(unused_variables)] app| {
+            crash_report::install(app.handle());
+
+            if safe_mode {
+                log::warn!("Starting in safe mode: optional plugins are disabled");
+            }
+
             // When running with the webdriver feature (E2E/integration tests),
             // grant all default permissions to remote URLs (http://127.0.0.1:*)
             // so that Vitest browser-mode tests can call plugin commands.
@@ -376,7 +638,7 @@ pub fn run() {
                 app.add_capability(include_str!("../capabilities-extra/webdriver.json"))?;
             }
             #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
-            {
+            if !safe_mode {
                 use std::sync::{Arc, Mutex};
                 let discord_client = Arc::new(Mutex::new(discord_rpc::DiscordRpcClient::new()));
                 app.manage(discord_client);
@@ -384,7 +646,18 @@ pub fn run() {
 
             #[cfg(desktop)]
             {
-                let files = get_files_from_argv(std::env::args().collect());
+                let (acsm, files) =
+                    acsm_handoff::partition(get_files_from_argv(std::env::args().collect()));
+                recent_files::record(app.handle(), &files);
+                if !acsm.is_empty() {
+                    let app_handle = app.handle().clone();
+                    app.once("window-ready", move |_| {
+                        acsm_handoff::hand_off(&app_handle, acsm);
+                    });
+                }
+                if stdin_mode {
+                    stdin_book::start(app.handle());
+                }
                 if !files.is_empty() {
                     let app_handle = app.handle().clone();
                     allow_file_in_scopes(&app_handle, files.clone());
@@ -400,13 +673,21 @@ pub fn run() {
                 allow_dir_in_scopes(app.handle(), &PathBuf::from(get_executable_dir()));
             }
 
+            #[cfg(desktop)]
+            if let Some(payload) = portable::first_run(app.handle()) {
+                let app_handle = app.handle().clone();
+                app.once("window-ready", move |_| {
+                    let _ = app_handle.emit(portable::FIRST_RUN_EVENT, payload);
+                });
+            }
+
             #[cfg(target_os = "android")]
             register_select_directory_callback(app.handle(), move |app, path| {
                 allow_dir_in_scopes(app, path);
             });
 
             #[cfg(any(target_os = "windows", target_os = "linux"))]
-            {
+            if !safe_mode {
                 use tauri_plugin_deep_link::DeepLinkExt;
                 let _ = app.deep_link().register_all();
             }
@@ -445,7 +726,7 @@ pub fn run() {
                     || std::path::Path::new("/.flatpak-info").exists();
                 #[cfg(not(target_os = "linux"))]
                 let is_flatpak = false;
-                std::env::var("READEST_DISABLE_UPDATER").is_ok() || is_flatpak
+                std::env::var("READEST_DISABLE_UPDATER").is_ok() || is_flatpak || safe_mode
             };
             #[cfg(not(desktop))]
             let updater_disabled = false;
@@ -456,6 +737,7 @@ pub fn run() {
                     if ({cli_access}) window.__READEST_CLI_ACCESS = true;
                     if ({is_appimage}) window.__READEST_IS_APPIMAGE = true;
                     if ({updater_disabled}) window.__READEST_UPDATER_DISABLED = true;
+                    if ({safe_mode}) window.__READEST_SAFE_MODE = true;
                     window.addEventListener('DOMContentLoaded', function() {{
                         document.documentElement.classList.add('edge-to-edge');
                         const isTauriLocal = window.location.protocol === 'tauri:' ||
@@ -480,7 +762,8 @@ pub fn run() {
                 is_eink = is_eink,
                 cli_access = cli_access,
                 is_appimage = is_appimage,
-                updater_disabled = updater_disabled
+                updater_disabled = updater_disabled,
+                safe_mode = safe_mode
             );
 
             let app_handle = app.handle().clone();
@@ -493,28 +776,17 @@ pub fn run() {
                 })
                 .initialization_script(&init_script)
                 .on_navigation(move |url| {
-                    if url.scheme() == "alipays" || url.scheme() == "alipay" {
-                        let url_str = url.as_str().to_string();
-                        #[cfg(target_os = "android")]
-                        {
-                            let handle = app_handle.clone();
-                            tauri::async_runtime::spawn(async move {
-                                match handle
-                                    .native_bridge()
-                                    .open_external_url(OpenExternalUrlRequest { url: url_str })
-                                {
-                                    Ok(result) => println!("Result: {:?}", result),
-                                    Err(e) => eprintln!("Error: {:?}", e),
-                                }
-                            });
-                        }
-                        #[cfg(not(target_os = "android"))]
-                        {
-                            let _ = app_handle.opener().open_url(url_str, None::<&str>);
-                        }
-                        return false;
+                    if !external_url::is_payment_url(url) {
+                        return true;
                     }
-                    true
+                    let handle = app_handle.clone();
+                    let url = url.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = external_url::open_url(&handle, &url) {
+                            log::error!("Failed to open payment URL: {e}");
+                        }
+                    });
+                    false
                 });
 
             #[cfg(target_os = "macos")]
@@ -526,7 +798,8 @@ pub fn run() {
             let win_builder = win_builder
                 .decorations(true)
                 .title_bar_style(TitleBarStyle::Overlay)
-                .title("");
+                .hidden_title(true)
+                .title(window_title::DEFAULT_TITLE);
 
             #[cfg(all(not(target_os = "macos"), desktop))]
             let win_builder = {
@@ -534,7 +807,7 @@ pub fn run() {
                     .decorations(false)
                     .visible(false)
                     .shadow(true)
-                    .title("Readest");
+                    .title(window_title::DEFAULT_TITLE);
 
                 #[cfg(target_os = "windows")]
                 {
@@ -556,8 +829,6 @@ pub fn run() {
             {
                 win_builder.build().unwrap();
             }
-            // let win = win_builder.build().unwrap();
-            // win.open_devtools();
 
             #[cfg(target_os = "macos")]
             {
@@ -575,8 +846,17 @@ pub fn run() {
                 });
             }
 
+            // Safe mode starts unpinned, like it starts at the default size.
+            #[cfg(desktop)]
+            if !safe_mode {
+                window_on_top::restore(app.handle());
+            }
+
             #[cfg(target_os = "macos")]
-            macos::menu::setup_macos_menu(app.handle())?;
+            {
+                macos::menu::setup_macos_menu(app.handle())?;
+                macos::services::setup_services(app.handle());
+            }
 
             app.handle().emit("window-ready", ()).unwrap();
 
@@ -587,6 +867,24 @@ pub fn run() {
         .run(
             #[allow(unused_variables)]
             |app_handle, event| {
+                // `ExitRequested` covers closing the last window and `app.exit()`;
+                // `Exit` also fires for Cmd+Q on macOS. Cleanup is idempotent, so
+                // handling both is harmless.
+                #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+                if matches!(
+                    event,
+                    tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit
+                ) {
+                    discord_rpc::shutdown_presence(app_handle);
+                }
+
+                #[cfg(desktop)]
+                if matches!(event, tauri::RunEvent::Exit) {
+                    stdin_book::cleanup(app_handle);
+                    archive_books::cleanup(app_handle);
+                    app_reset::cleanup(app_handle);
+                }
+
                 #[cfg(target_os = "macos")]
                 match event {
                     tauri::RunEvent::Opened { urls } => {
@@ -594,13 +892,19 @@ pub fn run() {
                             .into_iter()
                             .filter_map(|url| url.to_file_path().ok())
                             .collect::<Vec<_>>();
-
-                        let app_handler_clone = app_handle.clone();
-                        allow_file_in_scopes(app_handle, files.clone());
-                        app_handle.listen("window-ready", move |_| {
-                            println!("Window is ready, proceeding to handle files.");
-                            set_window_open_with_files(&app_handler_clone, files.clone());
-                        });
+                        let (acsm, files) = acsm_handoff::partition(files);
+                        if !acsm.is_empty() {
+                            acsm_handoff::hand_off(app_handle, acsm);
+                        }
+                        recent_files::record(app_handle, &files);
+                        if !files.is_empty() {
+                            let app_handler_clone = app_handle.clone();
+                            allow_file_in_scopes(app_handle, files.clone());
+                            app_handle.listen("window-ready", move |_| {
+                                println!("Window is ready, proceeding to handle files.");
+                                set_window_open_with_files(&app_handler_clone, files.clone());
+                            });
+                        }
                     }
                     // When the user reopens the app from the dock after closing all
                     // windows, re-show the main window instead of leaving the dock
@@ -0,0 +1,326 @@
+//! `export_library_index`: write a catalog of a library folder, to back it up
+//! or open it in another OPDS-aware reader.
+//!
+//! The folder is scanned with `dir_scanner::read_dir` (so the same scope
+//! rules apply) and each book's title and first author come from
+//! `book_metadata::read_metadata`, falling back to the file name. The
+//! catalog is written to the app data dir either as a JSON array or as an
+//! OPDS 1.2 Atom acquisition feed. Cover paths are relative to that file and
+//! point at the covers the library already keeps in `Readest/Books/<id>/`;
+//! books without one simply have no cover.
+//!
+//! Large libraries take a while, so progress is reported on a channel and
+//! [`cancel_library_export`] stops a running export before anything is
+//! written.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use tauri::{ipc::Channel, AppHandle};
+
+use crate::book_duplicates::{book_format, scan_books, ScanProgress};
+use crate::book_id::book_id;
+use crate::book_metadata::read_metadata;
+use crate::book_rename::split_book_name;
+use crate::dir_scanner::{self, ScannedFile};
+use crate::portable;
+
+/// Extensions picked up by the scan.
+pub(crate) const BOOK_EXTENSIONS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "prc", "fb2", "fbz", "cbz", "cbr", "pdf", "txt",
+];
+/// Folder under the app data dir holding the library, where the index's
+/// cover paths point; `DATA_SUBDIR` in the frontend's constants.
+const DATA_SUBDIR: &str = "Readest";
+
+/// Set by [`cancel_library_export`]; cleared when an export starts.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexFormat {
+    Opds,
+    Json,
+}
+
+impl IndexFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            IndexFormat::Opds => "library-index.opds.xml",
+            IndexFormat::Json => "library-index.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntry {
+    /// The book id (partialMD5), as in `Books/<id>/`.
+    pub id: String,
+    pub title: String,
+    pub author: Option<String>,
+    /// See [`book_format`].
+    pub format: String,
+    pub path: String,
+    pub size: u64,
+    /// Relative to the index file; `None` when the library has no cover.
+    pub cover_path: Option<String>,
+    /// Last modification of the file, in seconds since the Unix epoch.
+    #[serde(skip)]
+    pub modified: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 UTC timestamp, for Atom's
+/// `<updated>`.
+fn rfc3339(secs: u64) -> String {
+    // Civil date from a day count (Howard Hinnant's `civil_from_days`).
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+fn mime_type(format: &str) -> &'static str {
+    match format {
+        "epub" => "application/epub+zip",
+        "mobi" | "prc" => "application/x-mobipocket-ebook",
+        "azw" | "azw3" => "application/vnd.amazon.ebook",
+        "fb2" => "application/x-fictionbook+xml",
+        "fbz" | "fb2.zip" => "application/x-zip-compressed-fb2",
+        "cbz" => "application/vnd.comicbook+zip",
+        "cbr" => "application/vnd.comicbook-rar",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Catalog entry for one scanned file. `library` is the app data dir's
+/// `Readest` folder, where covers are looked up.
+fn index_entry(file: &ScannedFile, library: &Path) -> IndexEntry {
+    let path = Path::new(&file.path);
+    let (stem, _) = split_book_name(path);
+    let metadata = read_metadata(path).unwrap_or_default();
+    let id = book_id(path).unwrap_or_default();
+    let cover = format!("Books/{id}/cover.png");
+    let cover_path = (!id.is_empty() && library.join(&cover).is_file())
+        .then(|| format!("{DATA_SUBDIR}/{cover}"));
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    IndexEntry {
+        id,
+        title: metadata.title.unwrap_or(stem),
+        author: metadata.author,
+        format: book_format(path),
+        path: file.path.clone(),
+        size: file.size,
+        cover_path,
+        modified,
+    }
+}
+
+fn to_json(entries: &[IndexEntry]) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(entries).map_err(|e| format!("Failed to serialize index: {e}"))
+}
+
+/// An OPDS 1.2 acquisition feed with one entry per book. Acquisition links
+/// are `file://` URLs, so the feed is only useful on this machine.
+fn to_opds(entries: &[IndexEntry], updated: u64) -> String {
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\" \
+         xmlns:dc=\"http://purl.org/dc/terms/\" \
+         xmlns:opds=\"http://opds-spec.org/2010/catalog\">\n",
+    );
+    feed.push_str("  <id>urn:readest:library</id>\n");
+    feed.push_str("  <title>Readest Library</title>\n");
+    feed.push_str(&format!("  <updated>{}</updated>\n", rfc3339(updated)));
+    feed.push_str("  <author><name>Readest</name></author>\n");
+    for entry in entries {
+        let mime = mime_type(&entry.format);
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        feed.push_str(&format!("    <id>urn:readest:book:{}</id>\n", entry.id));
+        feed.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            rfc3339(entry.modified)
+        ));
+        if let Some(author) = &entry.author {
+            feed.push_str(&format!(
+                "    <author><name>{}</name></author>\n",
+                escape(author)
+            ));
+        }
+        feed.push_str(&format!("    <dc:format>{mime}</dc:format>\n"));
+        let href = tauri::Url::from_file_path(&entry.path)
+            .map(String::from)
+            .unwrap_or_else(|_| entry.path.clone());
+        feed.push_str(&format!(
+            "    <link rel=\"http://opds-spec.org/acquisition\" href=\"{}\" type=\"{mime}\" length=\"{}\"/>\n",
+            escape(&href),
+            entry.size
+        ));
+        if let Some(cover) = &entry.cover_path {
+            for rel in [
+                "http://opds-spec.org/image",
+                "http://opds-spec.org/image/thumbnail",
+            ] {
+                feed.push_str(&format!(
+                    "    <link rel=\"{rel}\" href=\"{}\" type=\"image/png\"/>\n",
+                    escape(cover)
+                ));
+            }
+        }
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+/// Index `files` and write the catalog to `dir`. Returns `Ok(None)` when
+/// cancelled, in which case nothing is written.
+fn export(
+    files: &[ScannedFile],
+    dir: &Path,
+    format: IndexFormat,
+    on_progress: impl Fn(ScanProgress),
+) -> Result<Option<PathBuf>, String> {
+    let library = dir.join(DATA_SUBDIR);
+    let visit = |file: &ScannedFile| Some(index_entry(file, &library));
+    let Some(mut entries) = scan_books(files, &CANCELLED, visit, on_progress) else {
+        return Ok(None);
+    };
+    entries.sort_by_key(|entry| entry.title.to_lowercase());
+
+    let bytes = match format {
+        IndexFormat::Json => to_json(&entries)?,
+        IndexFormat::Opds => to_opds(&entries, now_secs()).into_bytes(),
+    };
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let target = dir.join(format.file_name());
+    let tmp = dir.join(format!("{}.tmp", format.file_name()));
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write index: {e}"))?;
+    std::fs::rename(&tmp, &target).map_err(|e| format!("Failed to write index: {e}"))?;
+    Ok(Some(target))
+}
+
+/// Catalog every book under `root` and write it to the app data dir as
+/// `library-index.json` or `library-index.opds.xml`. Returns the file's
+/// path. Fails with `"cancelled"` after [`cancel_library_export`].
+#[tauri::command]
+pub async fn export_library_index(
+    app: AppHandle,
+    root: String,
+    format: IndexFormat,
+    on_progress: Channel<ScanProgress>,
+) -> Result<String, String> {
+    CANCELLED.store(false, Ordering::SeqCst);
+    let dir = portable::app_data_dir(&app)?;
+    let extensions = BOOK_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+    tauri::async_runtime::spawn_blocking(move || {
+        let files = dir_scanner::read_dir(app, root, true, extensions)?;
+        let on_progress = |progress| {
+            let _ = on_progress.send(progress);
+        };
+        match export(&files, &dir, format, on_progress)? {
+            Some(path) => Ok(path.to_string_lossy().into_owned()),
+            None => Err("cancelled".to_string()),
+        }
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?
+}
+
+/// Stop the running [`export_library_index`]. Does nothing if none is.
+#[tauri::command]
+pub fn cancel_library_export() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scanned, temp_dir};
+
+    #[test]
+    fn formats_rfc3339_timestamps() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn exports_json_and_opds_with_covers() {
+        let dir = temp_dir("library-index");
+        let books = dir.join("books");
+        std::fs::create_dir_all(&books).unwrap();
+        let fb2 = books.join("dune.fb2");
+        std::fs::write(
+            &fb2,
+            "<FictionBook><description><title-info>\
+             <author><first-name>Frank</first-name><last-name>Herbert</last-name></author>\
+             <book-title>Dune &amp; Sons</book-title></title-info></description></FictionBook>",
+        )
+        .unwrap();
+        let txt = books.join("notes.txt");
+        std::fs::write(&txt, "plain text").unwrap();
+
+        let id = book_id(&fb2).unwrap();
+        let cover_dir = dir.join(DATA_SUBDIR).join("Books").join(&id);
+        std::fs::create_dir_all(&cover_dir).unwrap();
+        std::fs::write(cover_dir.join("cover.png"), b"png").unwrap();
+
+        let files = [scanned(&txt), scanned(&fb2)];
+        let path = export(&files, &dir, IndexFormat::Json, |_| {})
+            .unwrap()
+            .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(json[0]["title"], "Dune & Sons");
+        assert_eq!(json[0]["author"], "Frank Herbert");
+        assert_eq!(json[0]["format"], "fb2");
+        assert_eq!(
+            json[0]["coverPath"],
+            format!("Readest/Books/{id}/cover.png")
+        );
+        assert_eq!(json[1]["title"], "notes");
+        assert!(json[1]["coverPath"].is_null());
+
+        let path = export(&files, &dir, IndexFormat::Opds, |_| {})
+            .unwrap()
+            .unwrap();
+        let feed = std::fs::read_to_string(path).unwrap();
+        assert!(feed.contains("<title>Dune &amp; Sons</title>"));
+        assert!(feed.contains(&format!("<id>urn:readest:book:{id}</id>")));
+        assert!(feed.contains("type=\"application/x-fictionbook+xml\""));
+        assert!(feed.contains(&format!(
+            "rel=\"http://opds-spec.org/image\" href=\"Readest/Books/{id}/cover.png\""
+        )));
+        assert!(feed.contains("type=\"text/plain\""));
+    }
+}
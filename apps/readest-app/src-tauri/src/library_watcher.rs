@@ -0,0 +1,241 @@
+//! Watch library folders for books added, changed or removed outside the app.
+//!
+//! `watch_library(root)` installs a recursive `notify` watcher on `root` and
+//! emits a debounced `library-changed` event listing the affected book files.
+//! Covers are persisted by the JS side under `Books/<hash>/`, so there is no
+//! Rust-side cover cache to purge: `modified` and `removed` paths are the
+//! signal for the frontend to drop or regenerate those covers.
+//!
+//! Watchers live in [`LibraryWatchers`] state keyed by root and are dropped by
+//! `unwatch_library(root)` or when the main window is destroyed.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_fs::FsExt;
+
+pub const LIBRARY_CHANGED_EVENT: &str = "library-changed";
+
+/// Quiet period before a burst of filesystem events is reported. Copying a
+/// book produces a create followed by many writes; this folds them into one.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Extensions the library imports. Everything else under the root (sidecar
+/// files, partial downloads, covers) is ignored.
+const BOOK_EXTENSIONS: &[&str] = &[
+    "epub", "mobi", "azw", "azw3", "fb2", "fbz", "cbz", "pdf", "txt",
+];
+
+#[derive(Default)]
+pub struct LibraryWatchers(Mutex<HashMap<PathBuf, RecommendedWatcher>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryChangedPayload {
+    pub root: String,
+    pub created: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+fn is_book_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .is_some_and(|e| BOOK_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Fold a new change for a path into the one already pending for it.
+/// `None` means the two cancel out (a file created and deleted inside one
+/// debounce window never existed as far as the library is concerned).
+fn merge_change(pending: Option<Change>, next: Change) -> Option<Change> {
+    match (pending, next) {
+        (None, next) => Some(next),
+        (Some(Change::Created), Change::Modified) => Some(Change::Created),
+        (Some(Change::Created), Change::Removed) => None,
+        (Some(Change::Removed), Change::Created) => Some(Change::Modified),
+        (Some(_), next) => Some(next),
+    }
+}
+
+fn classify(kind: &EventKind, path: &Path) -> Option<Change> {
+    use notify::event::ModifyKind;
+
+    match kind {
+        EventKind::Create(_) => Some(Change::Created),
+        EventKind::Remove(_) => Some(Change::Removed),
+        // Renames arrive as from/to halves (or both paths at once) depending
+        // on the backend; whether the path still exists says which side it is.
+        EventKind::Modify(ModifyKind::Name(_)) => Some(if path.exists() {
+            Change::Created
+        } else {
+            Change::Removed
+        }),
+        EventKind::Modify(_) | EventKind::Any => Some(Change::Modified),
+        EventKind::Access(_) | EventKind::Other => None,
+    }
+}
+
+fn emit_changes(app: &AppHandle, root: &Path, pending: &mut BTreeMap<PathBuf, Change>) {
+    if pending.is_empty() {
+        return;
+    }
+    let mut payload = LibraryChangedPayload {
+        root: root.to_string_lossy().into_owned(),
+        created: Vec::new(),
+        modified: Vec::new(),
+        removed: Vec::new(),
+    };
+    for (path, change) in std::mem::take(pending) {
+        let path = path.to_string_lossy().into_owned();
+        match change {
+            Change::Created => payload.created.push(path),
+            Change::Modified => payload.modified.push(path),
+            Change::Removed => payload.removed.push(path),
+        }
+    }
+    if let Err(e) = app.emit(LIBRARY_CHANGED_EVENT, payload) {
+        log::warn!("Failed to emit {LIBRARY_CHANGED_EVENT}: {e}");
+    }
+}
+
+/// Collect raw events and flush them once the tree has been quiet for
+/// [`DEBOUNCE`]. Exits when the watcher (and with it the sender) is dropped.
+fn debounce_loop(app: AppHandle, root: PathBuf, rx: mpsc::Receiver<notify::Result<Event>>) {
+    let mut pending: BTreeMap<PathBuf, Change> = BTreeMap::new();
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(DEBOUNCE)
+        };
+        match received {
+            Ok(Ok(event)) => {
+                for path in event.paths.iter().filter(|p| is_book_file(p)) {
+                    let Some(change) = classify(&event.kind, path) else {
+                        continue;
+                    };
+                    match merge_change(pending.get(path).copied(), change) {
+                        Some(change) => pending.insert(path.clone(), change),
+                        None => pending.remove(path),
+                    };
+                }
+            }
+            Ok(Err(e)) => log::warn!("Library watcher error under {}: {e}", root.display()),
+            Err(RecvTimeoutError::Timeout) => emit_changes(&app, &root, &mut pending),
+            Err(RecvTimeoutError::Disconnected) => {
+                emit_changes(&app, &root, &mut pending);
+                log::debug!("Stopped watching {}", root.display());
+                return;
+            }
+        }
+    }
+}
+
+/// Start watching `root` recursively for book changes. Watching a root that
+/// is already watched is a no-op.
+#[tauri::command]
+pub fn watch_library(
+    app: AppHandle,
+    state: State<'_, LibraryWatchers>,
+    root: String,
+) -> Result<(), String> {
+    let root = PathBuf::from(&root);
+    if !app.fs_scope().is_allowed(&root) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+
+    let mut watchers = state
+        .0
+        .lock()
+        .map_err(|e| format!("Mutex lock error: {e}"))?;
+    if watchers.contains_key(&root) {
+        return Ok(());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("Failed to create watcher: {e}"))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {e}", root.display()))?;
+
+    let thread_app = app.clone();
+    let thread_root = root.clone();
+    std::thread::Builder::new()
+        .name("library-watcher".into())
+        .spawn(move || debounce_loop(thread_app, thread_root, rx))
+        .map_err(|e| format!("Failed to start watcher thread: {e}"))?;
+
+    log::info!("Watching library folder {}", root.display());
+    watchers.insert(root, watcher);
+    Ok(())
+}
+
+/// Stop watching `root`. Unknown roots are ignored.
+#[tauri::command]
+pub fn unwatch_library(state: State<'_, LibraryWatchers>, root: String) -> Result<(), String> {
+    let mut watchers = state
+        .0
+        .lock()
+        .map_err(|e| format!("Mutex lock error: {e}"))?;
+    watchers.remove(Path::new(&root));
+    Ok(())
+}
+
+/// Drop every watcher; called when the main window goes away.
+pub fn unwatch_all(app: &AppHandle) {
+    if let Some(state) = app.try_state::<LibraryWatchers>() {
+        if let Ok(mut watchers) = state.0.lock() {
+            watchers.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_book_file, merge_change, Change};
+    use std::path::Path;
+
+    #[test]
+    fn only_book_extensions_are_tracked() {
+        assert!(is_book_file(Path::new("/lib/Dune.EPUB")));
+        assert!(is_book_file(Path::new("/lib/comics/issue.cbz")));
+        assert!(!is_book_file(Path::new("/lib/Dune.epub.part")));
+        assert!(!is_book_file(Path::new("/lib/cover.jpg")));
+        assert!(!is_book_file(Path::new("/lib/folder")));
+    }
+
+    #[test]
+    fn bursts_fold_into_one_change() {
+        let created = merge_change(None, Change::Created);
+        assert_eq!(
+            merge_change(created, Change::Modified),
+            Some(Change::Created)
+        );
+        assert_eq!(merge_change(created, Change::Removed), None);
+        assert_eq!(
+            merge_change(Some(Change::Removed), Change::Created),
+            Some(Change::Modified)
+        );
+        assert_eq!(
+            merge_change(Some(Change::Modified), Change::Removed),
+            Some(Change::Removed)
+        );
+    }
+}
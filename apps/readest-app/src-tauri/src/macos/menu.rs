@@ -1,7 +1,10 @@
 use crate::allow_file_in_scopes;
 use std::path::PathBuf;
 use tauri::menu::MenuEvent;
-use tauri::menu::{MenuItemBuilder, SubmenuBuilder, HELP_SUBMENU_ID};
+use tauri::menu::{
+    CheckMenuItemBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder, HELP_SUBMENU_ID,
+    WINDOW_SUBMENU_ID,
+};
 use tauri::AppHandle;
 use tauri::Emitter;
 use tauri_plugin_opener::OpenerExt;
@@ -36,6 +39,17 @@ pub fn setup_macos_menu(app: &AppHandle) -> tauri::Result<()> {
         }
     }
 
+    if let Some(window_menu) = global_menu.get(WINDOW_SUBMENU_ID) {
+        if let Some(window_submenu) = window_menu.as_submenu() {
+            let on_top_item =
+                CheckMenuItemBuilder::with_id(crate::window_on_top::MENU_ITEM_ID, "Float on Top")
+                    .build(app)?;
+            window_submenu.append(&PredefinedMenuItem::separator(app)?)?;
+            window_submenu.append(&on_top_item)?;
+            crate::window_on_top::set_menu_item(app, on_top_item);
+        }
+    }
+
     global_menu.append(
         &SubmenuBuilder::new(app, "Help")
             .text("privacy_policy", "Privacy Policy")
@@ -56,6 +70,8 @@ pub fn handle_menu_event(app: &AppHandle, event: &MenuEvent) {
     let opener = app.opener();
     if event.id() == "open_file" {
         handle_open_file(app);
+    } else if event.id() == crate::window_on_top::MENU_ITEM_ID {
+        crate::window_on_top::toggle_focused(app);
     } else if event.id() == "privacy_policy" {
         let _ = opener.open_url("https://readest.com/privacy-policy", None::<&str>);
     } else if event.id() == "report_issue" {
@@ -74,14 +90,22 @@ fn handle_open_file(app: &AppHandle) {
         .file()
         .add_filter(
             "Files",
-            &["epub", "pdf", "mobi", "azw", "azw3", "fb2", "cbz", "txt"],
+            &[
+                "epub", "pdf", "mobi", "azw", "azw3", "fb2", "cbz", "txt", "acsm",
+            ],
         )
         .pick_file(move |file_path| {
             if let Some(path) = file_path {
+                let path_buf = PathBuf::from(path.to_string());
+                if crate::acsm_handoff::is_acsm(&path_buf) {
+                    crate::acsm_handoff::hand_off(&app_handle, vec![path_buf]);
+                    return;
+                }
                 let payload = OpenFilesPayload {
                     files: vec![path.to_string()],
                 };
                 allow_file_in_scopes(&app_handle, vec![PathBuf::from(path.to_string())]);
+                crate::recent_files::record(&app_handle, &[path_buf]);
                 let _ = app_handle.emit("open-files", payload);
             }
         });
@@ -1,5 +1,7 @@
 pub mod apple_auth;
 pub mod menu;
 pub mod safari_auth;
+pub mod services;
 pub mod system_dictionary;
 pub mod traffic_light;
+pub mod webview_snapshot;
@@ -0,0 +1,221 @@
+/// macOS Services: "Open in Readest" for book files selected in Finder and
+/// "Search Library in Readest" for text selected in any app.
+///
+/// The menu items are declared under `NSServices` in `Info.plist`; AppKit
+/// delivers them to the object registered with `-[NSApplication
+/// setServicesProvider:]`, calling the `NSMessage` selector with the
+/// pasteboard holding the selection:
+///
+///     - (void)openFiles:(NSPasteboard *)pboard userData:(NSString *)data error:(NSString **)error;
+///     - (void)searchLibrary:(NSPasteboard *)pboard userData:(NSString *)data error:(NSString **)error;
+///
+/// Files go through the same `open-files` event as File → Open…, and text
+/// is emitted as `service-search`. When a Service cold-launches the app the
+/// message arrives before the frontend is listening, so requests are queued
+/// until the frontend drains them with [`take_service_requests`]; from then
+/// on they are emitted as they come in.
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use cocoa::base::{id, nil, YES};
+use cocoa::foundation::NSString;
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::allow_file_in_scopes;
+
+/// Emitted with a [`SearchPayload`] for text sent to "Search Library".
+pub const SERVICE_SEARCH_EVENT: &str = "service-search";
+
+/// `NSPasteboardTypeString`.
+const PASTEBOARD_TYPE_STRING: &str = "public.utf8-plain-text";
+/// `NSPasteboardURLReadingFileURLsOnlyKey`.
+const FILE_URLS_ONLY_KEY: &str = "NSPasteboardURLReadingFileURLsOnlyKey";
+
+#[derive(Clone, Serialize)]
+struct OpenFilesPayload {
+    files: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct SearchPayload {
+    query: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ServiceRequest {
+    OpenFiles { files: Vec<String> },
+    Search { query: String },
+}
+
+enum Delivery {
+    /// The frontend hasn't asked for requests yet; hold them.
+    Queued(Vec<ServiceRequest>),
+    /// The frontend is listening; emit right away.
+    Live,
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static DELIVERY: Mutex<Delivery> = Mutex::new(Delivery::Queued(Vec::new()));
+
+/// Register the Services provider. Call from app setup, which runs in
+/// `applicationDidFinishLaunching:`, so a Service that launched the app
+/// finds the provider in place.
+pub fn setup_services(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+    unsafe {
+        let provider: id = msg_send![services_provider_class(), new];
+        let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![ns_app, setServicesProvider: provider];
+    }
+}
+
+/// Requests that arrived before the frontend was listening. Every later
+/// request is emitted as an event instead.
+#[tauri::command]
+pub fn take_service_requests() -> Vec<ServiceRequest> {
+    let mut delivery = DELIVERY.lock().unwrap_or_else(|e| e.into_inner());
+    match std::mem::replace(&mut *delivery, Delivery::Live) {
+        Delivery::Queued(requests) => requests,
+        Delivery::Live => Vec::new(),
+    }
+}
+
+fn deliver(request: ServiceRequest) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    if let ServiceRequest::OpenFiles { files } = &request {
+        allow_file_in_scopes(app, files.iter().map(PathBuf::from).collect());
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+
+    let mut delivery = DELIVERY.lock().unwrap_or_else(|e| e.into_inner());
+    if let Delivery::Queued(requests) = &mut *delivery {
+        requests.push(request);
+        return;
+    }
+    drop(delivery);
+    let result = match request {
+        ServiceRequest::OpenFiles { files } => app.emit("open-files", OpenFilesPayload { files }),
+        ServiceRequest::Search { query } => app.emit(SERVICE_SEARCH_EVENT, SearchPayload { query }),
+    };
+    if let Err(e) = result {
+        log::error!("Failed to deliver service request: {e}");
+    }
+}
+
+fn services_provider_class() -> &'static Class {
+    let class_name = "ReadestServicesProvider";
+    let mut decl = match Class::get(class_name) {
+        Some(class) => return class,
+        None => ClassDecl::new(class_name, class!(NSObject)).unwrap(),
+    };
+
+    extern "C" fn open_files(
+        _this: &Object,
+        _sel: Sel,
+        pboard: id,
+        _user_data: id,
+        error: *mut id,
+    ) {
+        let files = unsafe { pasteboard_file_paths(pboard) };
+        if files.is_empty() {
+            unsafe { set_service_error(error, "No files to open.") };
+            return;
+        }
+        deliver(ServiceRequest::OpenFiles { files });
+    }
+
+    extern "C" fn search_library(
+        _this: &Object,
+        _sel: Sel,
+        pboard: id,
+        _user_data: id,
+        error: *mut id,
+    ) {
+        let query = unsafe { pasteboard_string(pboard) }
+            .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        if query.is_empty() {
+            unsafe { set_service_error(error, "No text to search for.") };
+            return;
+        }
+        deliver(ServiceRequest::Search { query });
+    }
+
+    unsafe {
+        decl.add_method(
+            sel!(openFiles:userData:error:),
+            open_files as extern "C" fn(&Object, Sel, id, id, *mut id),
+        );
+        decl.add_method(
+            sel!(searchLibrary:userData:error:),
+            search_library as extern "C" fn(&Object, Sel, id, id, *mut id),
+        );
+    }
+
+    decl.register()
+}
+
+/// An autoreleased `NSString`: the pasteboard and the error out-parameter
+/// only borrow it.
+unsafe fn ns_string(text: &str) -> id {
+    let string: id = NSString::alloc(nil).init_str(text);
+    msg_send![string, autorelease]
+}
+
+unsafe fn to_string(ns_string: id) -> Option<String> {
+    if ns_string == nil {
+        return None;
+    }
+    let utf8: *const std::os::raw::c_char = msg_send![ns_string, UTF8String];
+    if utf8.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(utf8).to_string_lossy().into_owned())
+}
+
+/// Paths of the file URLs on `pboard`.
+unsafe fn pasteboard_file_paths(pboard: id) -> Vec<String> {
+    let classes: id = msg_send![class!(NSArray), arrayWithObject: class!(NSURL)];
+    let yes: id = msg_send![class!(NSNumber), numberWithBool: YES];
+    let options: id = msg_send![
+        class!(NSDictionary),
+        dictionaryWithObject: yes
+        forKey: ns_string(FILE_URLS_ONLY_KEY)
+    ];
+    let urls: id = msg_send![pboard, readObjectsForClasses: classes options: options];
+    if urls == nil {
+        return Vec::new();
+    }
+    let count: usize = msg_send![urls, count];
+    (0..count)
+        .filter_map(|i| {
+            let url: id = msg_send![urls, objectAtIndex: i];
+            let path: id = msg_send![url, path];
+            to_string(path)
+        })
+        .collect()
+}
+
+unsafe fn pasteboard_string(pboard: id) -> Option<String> {
+    let text: id = msg_send![pboard, stringForType: ns_string(PASTEBOARD_TYPE_STRING)];
+    to_string(text)
+}
+
+/// Report a failure back to AppKit, which shows it to the user.
+unsafe fn set_service_error(error: *mut id, message: &str) {
+    if !error.is_null() {
+        *error = ns_string(message);
+    }
+}
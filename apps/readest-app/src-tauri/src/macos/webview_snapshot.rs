@@ -0,0 +1,105 @@
+/// WKWebView snapshot backing `reader_capture::capture_reader_view`.
+///
+/// `-[WKWebView takeSnapshotWithConfiguration:completionHandler:]` renders
+/// the visible viewport into an `NSImage` asynchronously on the main
+/// thread. We hop onto the main thread via `with_webview`, convert the
+/// image to PNG inside the completion handler (`TIFFRepresentation` →
+/// `NSBitmapImageRep` → PNG), and hand the bytes back over a oneshot.
+use std::sync::Mutex;
+
+use block::ConcreteBlock;
+use cocoa::base::{id, nil};
+use objc::runtime::Object;
+use tauri::WebviewWindow;
+use tokio::sync::oneshot;
+
+use crate::reader_capture::CaptureError;
+
+/// `NSBitmapImageFileTypePNG`.
+const NS_BITMAP_IMAGE_FILE_TYPE_PNG: u64 = 4;
+
+pub async fn snapshot_png(webview: &WebviewWindow) -> Result<Vec<u8>, CaptureError> {
+    let (tx, rx) = oneshot::channel::<Result<Vec<u8>, CaptureError>>();
+
+    webview
+        .with_webview(move |platform| unsafe {
+            let wk_webview = platform.inner() as *mut Object;
+            if wk_webview.is_null() {
+                let _ = tx.send(Err(CaptureError::Failed("no WKWebView".into())));
+                return;
+            }
+
+            // `ConcreteBlock` requires `Fn`; WebKit calls the handler once,
+            // so take the sender on first invocation.
+            let tx = Mutex::new(Some(tx));
+            let handler = ConcreteBlock::new(move |image: id, error: id| {
+                let result = if image == nil {
+                    Err(CaptureError::Failed(describe_error(error)))
+                } else {
+                    nsimage_to_png(image)
+                };
+                if let Some(tx) = tx.lock().ok().and_then(|mut guard| guard.take()) {
+                    let _ = tx.send(result);
+                }
+            });
+            let handler = handler.copy();
+
+            let config: id = msg_send![class!(WKSnapshotConfiguration), new];
+            let _: () = msg_send![
+                wk_webview,
+                takeSnapshotWithConfiguration: config
+                completionHandler: &*handler
+            ];
+            let _: () = msg_send![config, release];
+        })
+        .map_err(|e| CaptureError::Failed(e.to_string()))?;
+
+    rx.await
+        .map_err(|_| CaptureError::Failed("snapshot handler dropped".into()))?
+}
+
+/// SAFETY: must run on the main thread; `image` must be a live `NSImage*`.
+unsafe fn nsimage_to_png(image: id) -> Result<Vec<u8>, CaptureError> {
+    let tiff: id = msg_send![image, TIFFRepresentation];
+    if tiff == nil {
+        return Err(CaptureError::Failed(
+            "NSImage has no TIFF representation".into(),
+        ));
+    }
+    let rep: id = msg_send![class!(NSBitmapImageRep), imageRepWithData: tiff];
+    if rep == nil {
+        return Err(CaptureError::Failed(
+            "failed to build NSBitmapImageRep".into(),
+        ));
+    }
+    let properties: id = msg_send![class!(NSDictionary), dictionary];
+    let png: id = msg_send![
+        rep,
+        representationUsingType: NS_BITMAP_IMAGE_FILE_TYPE_PNG
+        properties: properties
+    ];
+    if png == nil {
+        return Err(CaptureError::Failed("PNG encoding failed".into()));
+    }
+    let length: usize = msg_send![png, length];
+    let bytes: *const u8 = msg_send![png, bytes];
+    if bytes.is_null() || length == 0 {
+        return Err(CaptureError::Failed("empty PNG data".into()));
+    }
+    Ok(std::slice::from_raw_parts(bytes, length).to_vec())
+}
+
+/// SAFETY: `error` must be `nil` or a live `NSError*`.
+unsafe fn describe_error(error: id) -> String {
+    if error == nil {
+        return "snapshot returned no image".into();
+    }
+    let description: id = msg_send![error, localizedDescription];
+    let utf8: *const std::os::raw::c_char = msg_send![description, UTF8String];
+    if utf8.is_null() {
+        return "snapshot failed".into();
+    }
+    std::ffi::CStr::from_ptr(utf8)
+        .to_string_lossy()
+        .into_owned()
+}
@@ -37,6 +37,7 @@ use mobi::Mobi;
 use serde::Serialize;
 use std::path::Path;
 
+use crate::book_cover::read_cover_sidecar;
 use crate::parser_common::{compute_partial_md5, maybe_resize_cover, RawCoverImage};
 
 #[derive(Debug, Serialize)]
@@ -73,10 +74,12 @@ fn parse_mobi_metadata_sync(file_path: &str) -> Result<ParsedMobi, String> {
 
     let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
 
-    let cover = extract_cover(&mobi).map(|raw| {
-        let (bytes, mime) = maybe_resize_cover(raw.bytes, &raw.mime);
-        RawCoverImage { bytes, mime }
-    });
+    let cover = read_cover_sidecar(path)
+        .or_else(|| extract_cover(&mobi))
+        .map(|raw| {
+            let (bytes, mime) = maybe_resize_cover(raw.bytes, &raw.mime);
+            RawCoverImage { bytes, mime }
+        });
 
     Ok(ParsedMobi { partial_md5, cover })
 }
@@ -99,11 +102,14 @@ pub async fn extract_mobi_cover_full(file_path: String) -> Result<RawCoverImage,
         .map_err(|e| format!("join error: {e}"))?
 }
 
-fn extract_mobi_cover_full_sync(file_path: &str) -> Result<RawCoverImage, String> {
+pub(crate) fn extract_mobi_cover_full_sync(file_path: &str) -> Result<RawCoverImage, String> {
     let path = Path::new(file_path);
     if !path.is_file() {
         return Err(format!("file not found: {file_path}"));
     }
+    if let Some(cover) = read_cover_sidecar(path) {
+        return Ok(cover);
+    }
     let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
     extract_cover(&mobi).ok_or_else(|| "no cover image in mobi".to_string())
 }
@@ -122,7 +128,7 @@ fn extract_mobi_cover_full_sync(file_path: &str) -> Result<RawCoverImage, String
 ///
 /// Returns `None` only when the file has no image records at all (rare
 /// for real Kindle content).
-fn extract_cover(mobi: &Mobi) -> Option<RawCoverImage> {
+pub(crate) fn extract_cover(mobi: &Mobi) -> Option<RawCoverImage> {
     let images = mobi.image_records();
     if images.is_empty() {
         return None;
@@ -59,22 +59,66 @@ pub async fn verify_update_signature(path: String, signature: String, pub_key: S
 /// be unit-tested without touching the filesystem. Returns `true` only when
 /// `data` is covered by `signature` under `pub_key`; any decode error or
 /// verification failure returns `false` (fail-closed).
-fn verify_signature_impl(data: &[u8], signature: &str, pub_key: &str) -> bool {
-    use minisign_verify::{PublicKey, Signature};
+pub(crate) fn verify_signature_impl(data: &[u8], signature: &str, pub_key: &str) -> bool {
+    verify_signature_detailed(data, signature, pub_key) == UpdateVerification::Valid
+}
+
+/// Outcome of [`verify_update`]. Serialized as `{ "status": "...", "detail": ... }`
+/// with `detail` present only for `malformedInput`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "camelCase")]
+pub enum UpdateVerification {
+    Valid,
+    /// Signed with this key, but not over these bytes (or the trusted comment
+    /// was altered).
+    BadSignature,
+    /// Signed with a different key than `pub_key`.
+    WrongKey,
+    /// The artifact couldn't be read, or the signature or key didn't decode.
+    MalformedInput(String),
+}
+
+/// The same checks as Tauri's `verify_signature`, keeping the reason a
+/// signature was rejected.
+fn verify_signature_detailed(data: &[u8], signature: &str, pub_key: &str) -> UpdateVerification {
+    use minisign_verify::{Error, PublicKey, Signature};
 
     let Some(pub_key_decoded) = base64_to_string(pub_key) else {
-        return false;
+        return UpdateVerification::MalformedInput("public key is not base64 text".into());
     };
-    let Ok(public_key) = PublicKey::decode(&pub_key_decoded) else {
-        return false;
+    let public_key = match PublicKey::decode(&pub_key_decoded) {
+        Ok(key) => key,
+        Err(e) => return UpdateVerification::MalformedInput(format!("public key: {e}")),
     };
     let Some(signature_decoded) = base64_to_string(signature) else {
-        return false;
+        return UpdateVerification::MalformedInput("signature is not base64 text".into());
     };
-    let Ok(sig) = Signature::decode(&signature_decoded) else {
-        return false;
+    let sig = match Signature::decode(&signature_decoded) {
+        Ok(sig) => sig,
+        Err(e) => return UpdateVerification::MalformedInput(format!("signature: {e}")),
     };
-    public_key.verify(data, &sig, true).is_ok()
+    match public_key.verify(data, &sig, true) {
+        Ok(()) => UpdateVerification::Valid,
+        Err(Error::InvalidSignature) => UpdateVerification::BadSignature,
+        Err(Error::UnexpectedKeyId) => UpdateVerification::WrongKey,
+        Err(e) => UpdateVerification::MalformedInput(format!("signature: {e}")),
+    }
+}
+
+/// Check a downloaded update artifact against its `.sig` the way the updater
+/// does before installing, for admins vetting artifacts ahead of deployment.
+/// `signature` and `pubkey` take the same base64 forms as
+/// [`verify_update_signature`]; unlike it, the result says why a check failed.
+#[tauri::command]
+pub async fn verify_update(
+    artifact_path: String,
+    signature: String,
+    pubkey: String,
+) -> UpdateVerification {
+    match tokio::fs::read(&artifact_path).await {
+        Ok(data) => verify_signature_detailed(&data, &signature, &pubkey),
+        Err(e) => UpdateVerification::MalformedInput(format!("artifact {artifact_path}: {e}")),
+    }
 }
 
 /// Progress event streamed to the JS install dialog over an IPC `Channel`.
@@ -145,7 +189,9 @@ pub async fn install_nightly_update<R: tauri::Runtime>(
 
 #[cfg(test)]
 mod tests {
-    use super::{is_update_newer, verify_signature_impl};
+    use super::{
+        is_update_newer, verify_signature_detailed, verify_signature_impl, UpdateVerification,
+    };
 
     // Fixtures generated with a THROWAWAY minisign keypair (`tauri signer
     // generate`/`sign`) over the exact bytes in TEST_DATA. The private key was
@@ -192,6 +238,42 @@ mod tests {
         assert!(!verify_signature_impl(TEST_DATA, TEST_SIG_B64, ""));
     }
 
+    #[test]
+    fn detailed_result_says_why() {
+        use base64::Engine;
+        let b64 = base64::engine::general_purpose::STANDARD;
+
+        assert_eq!(
+            verify_signature_detailed(TEST_DATA, TEST_SIG_B64, TEST_PUBKEY_B64),
+            UpdateVerification::Valid
+        );
+        assert_eq!(
+            verify_signature_detailed(b"tampered", TEST_SIG_B64, TEST_PUBKEY_B64),
+            UpdateVerification::BadSignature
+        );
+
+        // The same key under another key id, as a different signer's key
+        // would have.
+        let text = String::from_utf8(b64.decode(TEST_PUBKEY_B64).unwrap()).unwrap();
+        let (comment, key) = text.trim_end().split_once('\n').unwrap();
+        let mut raw = b64.decode(key).unwrap();
+        raw[2] ^= 0xff;
+        let other = b64.encode(format!("{comment}\n{}\n", b64.encode(raw)));
+        assert_eq!(
+            verify_signature_detailed(TEST_DATA, TEST_SIG_B64, &other),
+            UpdateVerification::WrongKey
+        );
+
+        assert!(matches!(
+            verify_signature_detailed(TEST_DATA, "not-base64-!!!", TEST_PUBKEY_B64),
+            UpdateVerification::MalformedInput(_)
+        ));
+        assert!(matches!(
+            verify_signature_detailed(TEST_DATA, TEST_SIG_B64, "aGVsbG8="),
+            UpdateVerification::MalformedInput(_)
+        ));
+    }
+
     #[test]
     fn matrix() {
         let cases: &[(&str, &str, bool)] = &[
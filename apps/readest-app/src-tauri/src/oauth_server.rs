@@ -0,0 +1,150 @@
+//! Localhost listener for OAuth redirects.
+//!
+//! Each `start_server` call binds a port through `tauri-plugin-oauth` and
+//! forwards matching redirects to the calling window as `redirect_uri`. A
+//! listener closes itself after delivering a redirect, when the frontend calls
+//! `stop_server`, or after its timeout, which emits `oauth-timeout` so an
+//! abandoned sign-in doesn't leave a port bound for the rest of the session.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Url, Window};
+
+pub const REDIRECT_URI_EVENT: &str = "redirect_uri";
+pub const OAUTH_TIMEOUT_EVENT: &str = "oauth-timeout";
+
+/// How long a listener waits for the redirect unless told otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Open listeners by handle id, with the port each one is bound to.
+#[derive(Default)]
+pub struct OAuthServers {
+    next_id: AtomicU64,
+    ports: Mutex<HashMap<u64, u16>>,
+}
+
+impl OAuthServers {
+    /// Forget listener `id`, returning its port if it was still open.
+    fn take(&self, id: u64) -> Option<u16> {
+        self.ports.lock().ok()?.remove(&id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthServerHandle {
+    pub id: u64,
+    pub port: u16,
+}
+
+/// `/callback`, `callback` and `/callback/` all mean the same redirect path.
+fn normalize_path(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+/// Whether a request to `url` is the redirect we're waiting for. Without an
+/// expected path any request counts, as before paths were configurable.
+fn is_expected_redirect(url: &str, expected_path: Option<&str>) -> bool {
+    let Some(expected) = expected_path else {
+        return true;
+    };
+    Url::parse(url).is_ok_and(|url| normalize_path(url.path()) == normalize_path(expected))
+}
+
+/// Close listener `id` if it is still open. Returns whether it was.
+fn close(app: &AppHandle, id: u64) -> bool {
+    let Some(port) = app.state::<OAuthServers>().take(id) else {
+        return false;
+    };
+    if let Err(e) = tauri_plugin_oauth::cancel(port) {
+        log::warn!("Failed to close OAuth listener on port {port}: {e}");
+    }
+    true
+}
+
+/// Start a localhost listener for an OAuth redirect.
+///
+/// Only requests to `redirect_path` (any path when omitted) are forwarded, and
+/// the listener closes after the first one. If none arrives within
+/// `timeout_secs` (default 120) it closes and `oauth-timeout` is emitted with
+/// the handle.
+#[tauri::command]
+pub async fn start_server(
+    app: AppHandle,
+    window: Window,
+    redirect_path: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<OAuthServerHandle, String> {
+    let servers = app.state::<OAuthServers>();
+    let id = servers.next_id.fetch_add(1, Ordering::Relaxed);
+
+    let handler_app = app.clone();
+    let port = tauri_plugin_oauth::start(move |url| {
+        // The port is open to anything on localhost, so only the expected
+        // redirect is passed on.
+        if !is_expected_redirect(&url, redirect_path.as_deref()) {
+            return;
+        }
+        let _ = window.emit(REDIRECT_URI_EVENT, url);
+        // Closing from inside the handler would block the listener's own
+        // thread; hand it off.
+        let app = handler_app.clone();
+        tauri::async_runtime::spawn(async move {
+            close(&app, id);
+        });
+    })
+    .map_err(|e| format!("Failed to start OAuth listener: {e}"))?;
+
+    servers
+        .ports
+        .lock()
+        .map_err(|e| format!("Failed to register OAuth listener: {e}"))?
+        .insert(id, port);
+
+    let handle = OAuthServerHandle { id, port };
+    let timeout = timeout_secs.map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+    let timeout_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(timeout).await;
+        if close(&app, timeout_handle.id) {
+            log::info!("OAuth listener on port {} timed out", timeout_handle.port);
+            let _ = app.emit(OAUTH_TIMEOUT_EVENT, timeout_handle);
+        }
+    });
+
+    Ok(handle)
+}
+
+/// Close the listener started by `start_server` with handle `id`. Returns
+/// `false` if it had already closed.
+#[tauri::command]
+pub fn stop_server(app: AppHandle, id: u64) -> bool {
+    close(&app, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_paths_match_loosely_on_slashes() {
+        let url = "http://localhost:4567/auth/callback?code=abc&state=xyz";
+        assert!(is_expected_redirect(url, Some("/auth/callback")));
+        assert!(is_expected_redirect(url, Some("auth/callback/")));
+        assert!(!is_expected_redirect(url, Some("/callback")));
+        assert!(!is_expected_redirect(
+            "http://localhost:4567/favicon.ico",
+            Some("/auth/callback")
+        ));
+    }
+
+    #[test]
+    fn any_request_matches_without_expected_path() {
+        assert!(is_expected_redirect("http://localhost:4567/", None));
+        assert!(is_expected_redirect("not a url", None));
+        assert!(!is_expected_redirect("not a url", Some("/")));
+    }
+}
@@ -0,0 +1,300 @@
+//! `resolve_opds`: check a pasted OPDS catalog URL before the import UI
+//! tries to browse it.
+//!
+//! The URL is normalized (scheme added when missing, fragment dropped),
+//! fetched with the HTTP plugin's client, and the response sniffed for an
+//! OPDS 1.x Atom feed or an OPDS 2.0 JSON feed. At most one redirect is
+//! followed and the URL it lands on is what gets returned, so the catalog is
+//! saved under its canonical address. Failures come back as a typed
+//! [`OpdsError`] so the UI can tell "wrong URL" from "server down" from
+//! "needs a login".
+
+use std::time::Duration;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use tauri::Url;
+use tauri_plugin_http::reqwest::{self, header, redirect, StatusCode};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// Enough of the body to sniff; feeds larger than this are judged on their
+/// `Content-Type` alone.
+const MAX_SNIFF_BYTES: usize = 4 * 1024 * 1024;
+const ACCEPT: &str = "application/opds+json, application/atom+xml;profile=opds-catalog, \
+                      application/atom+xml;q=0.9, application/json;q=0.8, */*;q=0.5";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpdsKind {
+    /// OPDS 1.x: an Atom feed.
+    Opds1,
+    /// OPDS 2.0: a JSON feed.
+    Opds2,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedOpds {
+    pub normalized_url: String,
+    pub kind: OpdsKind,
+}
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum OpdsError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("not an OPDS feed: {0}")]
+    NotOpds(String),
+    #[error("catalog unreachable: {0}")]
+    Unreachable(String),
+    #[error("catalog requires authentication: {0}")]
+    AuthRequired(String),
+}
+
+/// Parse what the user pasted. A bare host gets `https://`, `opds://`
+/// links (used by some catalogs to hand off to readers) become `https://`,
+/// and the fragment is dropped since servers never see it.
+fn normalize_url(input: &str) -> Result<Url, OpdsError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(OpdsError::InvalidUrl("empty URL".to_string()));
+    }
+    let with_scheme = match trimmed.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("opds") => format!("https://{rest}"),
+        Some(_) => trimmed.to_string(),
+        None => format!("https://{trimmed}"),
+    };
+    let mut url =
+        Url::parse(&with_scheme).map_err(|e| OpdsError::InvalidUrl(format!("{trimmed}: {e}")))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(OpdsError::InvalidUrl(format!(
+            "unsupported scheme: {}",
+            url.scheme()
+        )));
+    }
+    if url.host_str().unwrap_or_default().is_empty() {
+        return Err(OpdsError::InvalidUrl(format!("{trimmed}: missing host")));
+    }
+    url.set_fragment(None);
+    Ok(url)
+}
+
+/// The kind a `Content-Type` declares outright, if it is specific enough.
+fn kind_from_content_type(content_type: &str) -> Option<OpdsKind> {
+    let lower = content_type.to_ascii_lowercase();
+    let mime = lower.split(';').next().unwrap_or_default().trim();
+    match mime {
+        "application/opds+json" => Some(OpdsKind::Opds2),
+        "application/atom+xml" if lower.contains("opds-catalog") => Some(OpdsKind::Opds1),
+        _ => None,
+    }
+}
+
+/// An OPDS 2 feed is a JSON object with `metadata` and at least one of the
+/// collections a feed can carry.
+fn is_opds2_json(body: &[u8]) -> bool {
+    let Ok(serde_json::Value::Object(doc)) = serde_json::from_slice(body) else {
+        return false;
+    };
+    doc.get("metadata").is_some_and(|m| m.is_object())
+        && ["navigation", "publications", "groups", "facets", "links"]
+            .iter()
+            .any(|key| doc.get(*key).is_some_and(|v| v.is_array()))
+}
+
+/// An OPDS 1 feed is Atom: the root element is `feed`.
+fn is_atom_feed(body: &[u8]) -> bool {
+    let mut reader = Reader::from_reader(body);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                return e.local_name().as_ref() == b"feed";
+            }
+            Ok(Event::Eof) | Err(_) => return false,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+fn sniff_kind(content_type: &str, body: &[u8]) -> Option<OpdsKind> {
+    if let Some(kind) = kind_from_content_type(content_type) {
+        return Some(kind);
+    }
+    let start = body
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map_or(&[][..], |i| &body[i..]);
+    let start = start.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(start);
+    match start.first() {
+        Some(b'{') if is_opds2_json(start) => Some(OpdsKind::Opds2),
+        Some(b'<') if is_atom_feed(start) => Some(OpdsKind::Opds1),
+        _ => None,
+    }
+}
+
+fn realm(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(header::WWW_AUTHENTICATE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| response.status().to_string())
+}
+
+fn unreachable(e: reqwest::Error) -> OpdsError {
+    if e.is_timeout() {
+        OpdsError::Unreachable(format!("timed out: {e}"))
+    } else {
+        OpdsError::Unreachable(e.to_string())
+    }
+}
+
+/// Up to [`MAX_SNIFF_BYTES`] of the body.
+async fn read_head(mut response: reqwest::Response) -> Result<Vec<u8>, OpdsError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(unreachable)? {
+        let room = MAX_SNIFF_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if body.len() == MAX_SNIFF_BYTES {
+            break;
+        }
+    }
+    Ok(body)
+}
+
+/// Validate an OPDS catalog URL: fetch it, follow at most one redirect, and
+/// report the canonical URL and whether it is an OPDS 1 or OPDS 2 feed.
+#[tauri::command]
+pub async fn resolve_opds(url: String) -> Result<ResolvedOpds, OpdsError> {
+    let mut url = normalize_url(&url)?;
+    let client = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| OpdsError::Unreachable(e.to_string()))?;
+
+    let mut redirected = false;
+    let response = loop {
+        let response = client
+            .get(url.clone())
+            .header(header::ACCEPT, ACCEPT)
+            .send()
+            .await
+            .map_err(unreachable)?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        if redirected {
+            return Err(OpdsError::Unreachable(format!(
+                "{url} redirects more than once"
+            )));
+        }
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                OpdsError::Unreachable(format!("{} without a Location", response.status()))
+            })?;
+        url = url
+            .join(location)
+            .map_err(|e| OpdsError::Unreachable(format!("bad redirect {location}: {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(OpdsError::Unreachable(format!("redirected to {url}")));
+        }
+        url.set_fragment(None);
+        redirected = true;
+    };
+
+    let status = response.status();
+    if matches!(
+        status,
+        StatusCode::UNAUTHORIZED
+            | StatusCode::FORBIDDEN
+            | StatusCode::PROXY_AUTHENTICATION_REQUIRED
+    ) {
+        return Err(OpdsError::AuthRequired(realm(&response)));
+    }
+    if !status.is_success() {
+        return Err(OpdsError::Unreachable(format!("HTTP {status}")));
+    }
+
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let body = read_head(response).await?;
+    let kind = sniff_kind(&content_type, &body).ok_or_else(|| {
+        let served = if content_type.is_empty() {
+            "no content type"
+        } else {
+            content_type.as_str()
+        };
+        OpdsError::NotOpds(format!("{url} serves {served}"))
+    })?;
+
+    Ok(ResolvedOpds {
+        normalized_url: url.to_string(),
+        kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_pasted_urls() {
+        let url = |s: &str| normalize_url(s).map(|u| u.to_string());
+        assert_eq!(
+            url("  example.org/opds#top ").unwrap(),
+            "https://example.org/opds"
+        );
+        assert_eq!(
+            url("opds://example.org/catalog").unwrap(),
+            "https://example.org/catalog"
+        );
+        assert_eq!(
+            url("http://Example.org:8080/opds").unwrap(),
+            "http://example.org:8080/opds"
+        );
+        assert!(matches!(
+            url("ftp://example.org"),
+            Err(OpdsError::InvalidUrl(_))
+        ));
+        assert!(matches!(url(""), Err(OpdsError::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn sniffs_atom_and_json_feeds() {
+        let atom = br#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Catalog</title></feed>"#;
+        let json = br#"{"metadata":{"title":"Catalog"},"navigation":[]}"#;
+        assert_eq!(sniff_kind("application/xml", atom), Some(OpdsKind::Opds1));
+        assert_eq!(sniff_kind("application/json", json), Some(OpdsKind::Opds2));
+        assert_eq!(
+            sniff_kind(
+                "application/atom+xml;profile=opds-catalog;kind=navigation",
+                b""
+            ),
+            Some(OpdsKind::Opds1)
+        );
+        assert_eq!(
+            sniff_kind("application/opds+json; charset=utf-8", b""),
+            Some(OpdsKind::Opds2)
+        );
+        assert_eq!(
+            sniff_kind("text/html", b"<!DOCTYPE html><html></html>"),
+            None
+        );
+        assert_eq!(sniff_kind("application/json", br#"{"error":"nope"}"#), None);
+    }
+}
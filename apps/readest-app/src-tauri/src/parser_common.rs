@@ -8,6 +8,10 @@
 //   - clamp oversized cover artwork to the library-grid thumbnail size,
 //     re-encoding as JPEG q85 when downscaling actually fires.
 //
+// Bulk import (`book_ingest`) and stdin mode also share the sniffing of a
+// book's format from its bytes, and every FB2 reader shares the lookup of
+// the document inside a zipped FB2.
+//
 // Keeping these in a single module avoids drift between the two import
 // paths (a divergent partialMD5 implementation would silently re-import
 // every existing book under a new hash on the first run after a change).
@@ -19,10 +23,14 @@
 
 use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, GenericImageView};
 use md5::{Digest, Md5};
+use quick_xml::events::BytesStart;
 use serde::Serialize;
 use std::fs::File;
 use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::read_zip_entry;
 
 /// Cover thumbnail target. Sized for the library grid (~250-300px @2x)
 /// and the reader-sidebar / detail-view rows (which are smaller still).
@@ -108,11 +116,16 @@ pub fn maybe_resize_cover(bytes: Vec<u8>, hint_mime: &str) -> (Vec<u8>, String)
 /// d41d8cd9... We must reproduce that behaviour bit-for-bit so existing
 /// on-disk hashes (Books/<hash>/...) keep matching.
 pub fn compute_partial_md5(path: &Path) -> std::io::Result<String> {
-    const STEP: u32 = 1024;
-    const CHUNK: u64 = 1024;
-
     let mut file = File::open(path)?;
     let file_len = file.metadata()?.len();
+    partial_md5_of(&mut file, file_len)
+}
+
+/// [`compute_partial_md5`] of a book already open as `file`, `file_len`
+/// bytes long, so callers reading it anyway don't open it twice.
+pub fn partial_md5_of<R: Read + Seek>(file: &mut R, file_len: u64) -> std::io::Result<String> {
+    const STEP: u32 = 1024;
+    const CHUNK: u64 = 1024;
 
     let mut hasher = Md5::new();
     let mut buf = vec![0u8; CHUNK as usize];
@@ -144,3 +157,64 @@ pub fn compute_partial_md5(path: &Path) -> std::io::Result<String> {
 
     Ok(format!("{:x}", hasher.finalize()))
 }
+
+/// Extension for a book given only its bytes, or `None` if it isn't a
+/// format the reader opens. A ZIP-based book needs all of its bytes; for a
+/// file, open the archive and use [`sniff_zip_archive`] instead.
+pub(crate) fn sniff_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"%PDF-") {
+        return Some("pdf");
+    }
+    if bytes.get(60..68) == Some(b"BOOKMOBI") || bytes.get(60..68) == Some(b"TEXtREAd") {
+        return Some("mobi");
+    }
+    if bytes.starts_with(b"PK\x03\x04") {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).ok()?;
+        return Some(sniff_zip_archive(&mut archive));
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+    if head.contains("<FictionBook") {
+        return Some("fb2");
+    }
+    std::str::from_utf8(bytes).ok().map(|_| "txt")
+}
+
+/// EPUB (declared by its `mimetype` entry), zipped FB2, or otherwise a
+/// comic archive.
+pub(crate) fn sniff_zip_archive<R: Read + Seek>(archive: &mut ZipArchive<R>) -> &'static str {
+    if let Ok(mut entry) = archive.by_name("mimetype") {
+        let mut mimetype = String::new();
+        if entry.read_to_string(&mut mimetype).is_ok() && mimetype.trim() == "application/epub+zip"
+        {
+            return "epub";
+        }
+    }
+    if archive
+        .file_names()
+        .any(|name| name.to_ascii_lowercase().ends_with(".fb2"))
+    {
+        return "fbz";
+    }
+    "cbz"
+}
+
+/// Bytes of the first `.fb2` document inside an FBZ / `.fb2.zip` archive.
+pub(crate) fn read_fbz_document<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+) -> Result<Vec<u8>, String> {
+    let name = zip
+        .file_names()
+        .find(|n| n.to_lowercase().ends_with(".fb2"))
+        .map(str::to_string)
+        .ok_or_else(|| "no .fb2 document in archive".to_string())?;
+    read_zip_entry(zip, &name)
+}
+
+/// Unescaped value of the attribute named exactly `name` (prefix included).
+pub(crate) fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
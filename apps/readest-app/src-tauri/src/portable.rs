@@ -0,0 +1,188 @@
+//! Portable mode: everything under a `data` folder beside the executable.
+//!
+//! A `portable.txt` file next to the executable, or `READEST_PORTABLE` in the
+//! environment, turns it on — for installs carried around on a USB stick.
+//! Settings, the library, caches, logs, saved window state and the WebView2
+//! profile then live in `<exe dir>/data/` and nothing is written to the user
+//! profile. The frontend asks [`get_portable_data_dir`] where that is.
+//!
+//! Explorer can't see our environment, so the thumbnail provider
+//! (`extensions/windows-thumbnail`) looks for `portable.txt` beside its own
+//! DLL instead and caches under `data/cache/thumbnails` when it's there.
+//! The app's own cover commands use that same folder.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Marker file beside the executable that enables portable mode.
+const PORTABLE_MARKER: &str = "portable.txt";
+/// Environment variable that enables portable mode without the marker.
+const PORTABLE_ENV: &str = "READEST_PORTABLE";
+/// Folder beside the executable holding all portable data.
+const DATA_DIR: &str = "data";
+/// Settings file written by the frontend; its absence means a first run.
+pub(crate) const SETTINGS_FILENAME: &str = "settings.json";
+
+/// Emitted once the main window is ready when no settings exist yet.
+pub const FIRST_RUN_EVENT: &str = "first-run";
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirstRunPayload {
+    pub portable: bool,
+    pub data_dir: String,
+}
+
+fn detect(exe_dir: &Path, env_enabled: bool) -> Option<PathBuf> {
+    (env_enabled || exe_dir.join(PORTABLE_MARKER).is_file()).then(|| exe_dir.join(DATA_DIR))
+}
+
+/// The portable data folder, or `None` for a regular install. Detected once.
+pub fn data_dir() -> Option<&'static Path> {
+    static DATA: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DATA.get_or_init(|| {
+        let exe = std::env::current_exe().ok()?;
+        detect(exe.parent()?, std::env::var_os(PORTABLE_ENV).is_some())
+    })
+    .as_deref()
+}
+
+/// App cache directory, inside the portable data folder in portable mode.
+pub fn cache_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    match data_dir() {
+        Some(dir) => Ok(dir.join("cache")),
+        None => app
+            .path()
+            .app_cache_dir()
+            .map_err(|e| format!("Failed to resolve cache dir: {e}")),
+    }
+}
+
+/// Remove the files and folders directly in `dir` not modified for
+/// `max_age`, i.e. scratch space under [`cache_dir`] left behind by a
+/// process that didn't exit cleanly.
+pub(crate) fn sweep_stale(dir: &Path, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let stale = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > max_age);
+        if !stale {
+            continue;
+        }
+        let _ = if metadata.is_dir() {
+            std::fs::remove_dir_all(entry.path())
+        } else {
+            std::fs::remove_file(entry.path())
+        };
+    }
+}
+
+/// App data directory, the portable data folder itself in portable mode.
+pub fn app_data_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    match data_dir() {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data dir: {e}")),
+    }
+}
+
+/// App config directory (settings, window state): the portable data folder
+/// itself in portable mode.
+pub fn config_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    match data_dir() {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => app
+            .path()
+            .app_config_dir()
+            .map_err(|e| format!("Failed to resolve config dir: {e}")),
+    }
+}
+
+/// Log folder in portable mode; regular installs log to the app log dir.
+pub fn log_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Point state that is resolved before any of our code runs at the portable
+/// data folder. Call at the start of `run`, before the webview is created.
+pub fn prepare() {
+    let Some(dir) = data_dir() else {
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        log::error!("Failed to create portable data dir {}: {e}", dir.display());
+    }
+    #[cfg(target_os = "windows")]
+    std::env::set_var("WEBVIEW2_USER_DATA_FOLDER", dir.join("webview"));
+    windows_thumbnail::use_portable_cache_dir(dir);
+}
+
+/// What to send with [`FIRST_RUN_EVENT`], or `None` when settings already
+/// exist.
+pub fn first_run<R: Runtime>(app: &AppHandle<R>) -> Option<FirstRunPayload> {
+    let dir = config_dir(app).ok()?;
+    if dir.join(SETTINGS_FILENAME).exists() {
+        return None;
+    }
+    Some(FirstRunPayload {
+        portable: data_dir().is_some(),
+        data_dir: dir.to_string_lossy().into_owned(),
+    })
+}
+
+/// The portable data folder, or `None` for a regular install.
+#[tauri::command]
+pub fn get_portable_data_dir() -> Option<String> {
+    data_dir().map(|dir| dir.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_or_env_enables_portable_mode() {
+        let dir = std::env::temp_dir().join(format!("readest-portable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(detect(&dir, false), None);
+        assert_eq!(detect(&dir, true), Some(dir.join(DATA_DIR)));
+        std::fs::write(dir.join(PORTABLE_MARKER), b"").unwrap();
+        assert_eq!(detect(&dir, false), Some(dir.join(DATA_DIR)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_stale_entries_are_swept() {
+        let dir = std::env::temp_dir().join(format!("readest-sweep-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("fresh")).unwrap();
+        let old = dir.join("old.epub");
+        let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        std::fs::File::create(&old)
+            .unwrap()
+            .set_modified(day_ago)
+            .unwrap();
+
+        sweep_stale(&dir, Duration::from_secs(60 * 60));
+        assert!(!old.exists());
+        assert!(dir.join("fresh").is_dir());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
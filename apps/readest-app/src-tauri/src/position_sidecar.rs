@@ -0,0 +1,187 @@
+//! Last-read position stored next to the book, as `<book>.readest.json`.
+//!
+//! A lightweight alternative to the sync server: the sidecar travels with the
+//! book through a shared folder or a USB stick, so another machine can pick up
+//! where the reader left off. Only the position is stored — CFI and/or page,
+//! overall percentage and when it was written.
+//!
+//! Writes go to a uniquely named temp file in the same directory that is then
+//! renamed over the sidecar, so a concurrent reader sees either the old or the
+//! new position, never a partial one, and concurrent writers can't interleave.
+//! A missing or unreadable sidecar reads as `None`.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tauri_plugin_fs::FsExt;
+
+const SIDECAR_SUFFIX: &str = ".readest.json";
+
+/// Distinguishes temp files of concurrent writes from this process.
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfi: Option<String>,
+    /// Page number, for fixed-layout books and PDFs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Progress through the book, 0.0–1.0.
+    pub percentage: f64,
+    /// Milliseconds since the Unix epoch. Filled in on write when zero.
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+/// `book.epub` → `book.epub.readest.json`.
+fn sidecar_path(book: &Path) -> PathBuf {
+    let mut name = book.as_os_str().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Milliseconds since the Unix epoch, like JavaScript's `Date.now()`.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The stored position, or `None` if the sidecar is missing, unparsable or
+/// holds no usable position.
+fn load(sidecar: &Path) -> Option<Position> {
+    let bytes = fs::read(sidecar).ok()?;
+    let position: Position = serde_json::from_slice(&bytes)
+        .inspect_err(|e| log::warn!("Ignoring corrupt {}: {e}", sidecar.display()))
+        .ok()?;
+    (0.0..=1.0)
+        .contains(&position.percentage)
+        .then_some(position)
+}
+
+fn store(sidecar: &Path, position: &Position) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(position)
+        .map_err(|e| format!("Failed to serialize position: {e}"))?;
+    let file_name = sidecar
+        .file_name()
+        .ok_or_else(|| format!("Invalid sidecar path: {}", sidecar.display()))?
+        .to_string_lossy();
+    let temp = sidecar.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(&json)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&temp, sidecar)) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("Failed to write {}: {e}", sidecar.display()));
+    }
+    Ok(())
+}
+
+/// Both the book and its sidecar must be inside the filesystem scope.
+fn scoped_sidecar(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let book = PathBuf::from(path);
+    let sidecar = sidecar_path(&book);
+    let scope = app.fs_scope();
+    if !scope.is_allowed(&book) || !scope.is_allowed(&sidecar) {
+        return Err("Permission denied: Path not in filesystem scope".to_string());
+    }
+    Ok(sidecar)
+}
+
+/// Last-read position saved next to the book at `path`, if any.
+#[tauri::command]
+pub async fn read_position(app: AppHandle, path: String) -> Result<Option<Position>, String> {
+    let sidecar = scoped_sidecar(&app, &path)?;
+    tauri::async_runtime::spawn_blocking(move || load(&sidecar))
+        .await
+        .map_err(|e| format!("join error: {e}"))
+}
+
+/// Save `position` next to the book at `path`. The percentage is clamped to
+/// 0.0–1.0 and a zero `updatedAt` is set to the current time.
+#[tauri::command]
+pub async fn write_position(
+    app: AppHandle,
+    path: String,
+    mut position: Position,
+) -> Result<(), String> {
+    let sidecar = scoped_sidecar(&app, &path)?;
+    if !position.percentage.is_finite() {
+        return Err(format!("Invalid percentage: {}", position.percentage));
+    }
+    position.percentage = position.percentage.clamp(0.0, 1.0);
+    if position.updated_at == 0 {
+        position.updated_at = now_millis();
+    }
+    tauri::async_runtime::spawn_blocking(move || store(&sidecar, &position))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn sample() -> Position {
+        Position {
+            cfi: Some("epubcfi(/6/4!/4/2/1:0)".into()),
+            page: None,
+            percentage: 0.25,
+            updated_at: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn sidecar_keeps_the_book_extension() {
+        assert_eq!(
+            sidecar_path(Path::new("/books/Dune.epub")),
+            PathBuf::from("/books/Dune.epub.readest.json")
+        );
+    }
+
+    #[test]
+    fn position_round_trips_without_leaving_temp_files() {
+        let dir = temp_dir("position-round-trip");
+        let sidecar = sidecar_path(&dir.join("book.epub"));
+
+        store(&sidecar, &sample()).unwrap();
+        let mut next = sample();
+        next.percentage = 0.5;
+        store(&sidecar, &next).unwrap();
+
+        assert_eq!(load(&sidecar), Some(next));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_or_corrupt_sidecar_reads_as_none() {
+        let dir = temp_dir("position-corrupt");
+        let sidecar = sidecar_path(&dir.join("book.pdf"));
+        assert_eq!(load(&sidecar), None);
+
+        fs::write(&sidecar, b"{\"percentage\": 0.3").unwrap();
+        assert_eq!(load(&sidecar), None);
+        fs::write(&sidecar, b"{\"percentage\": 7}").unwrap();
+        assert_eq!(load(&sidecar), None);
+
+        fs::write(&sidecar, b"{\"page\": 12, \"percentage\": 0.3}").unwrap();
+        let position = load(&sidecar).unwrap();
+        assert_eq!((position.page, position.updated_at), (Some(12), 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! `generate_qr`: a PNG QR code for device-to-device handoff, typically of a
+//! `readest://open?book=...` deep link that a second device scans to open the
+//! same book and position.
+//!
+//! Modules are drawn at a whole number of pixels so the code stays crisp at
+//! any requested size; whatever doesn't divide evenly widens the white
+//! border around the standard 4-module quiet zone.
+
+use image::{GrayImage, Luma};
+use qrcode::{Color, EcLevel, QrCode};
+use std::io::Cursor;
+
+/// Modules of white border each side, as the QR spec requires.
+const QUIET_ZONE: u32 = 4;
+const MIN_SIZE: u32 = 64;
+const MAX_SIZE: u32 = 2048;
+/// Medium correction survives glare and moiré when scanning a screen.
+const EC_LEVEL: EcLevel = EcLevel::M;
+/// Byte-mode capacity of a version 40 code at [`EC_LEVEL`].
+const MAX_DATA_BYTES: usize = 2331;
+
+fn encode(data: &str) -> Result<QrCode, String> {
+    if data.is_empty() {
+        return Err("nothing to encode".to_string());
+    }
+    if data.len() > MAX_DATA_BYTES {
+        return Err(format!(
+            "data too long for a QR code: {} bytes, at most {MAX_DATA_BYTES}",
+            data.len()
+        ));
+    }
+    QrCode::with_error_correction_level(data, EC_LEVEL)
+        .map_err(|e| format!("QR encoding error: {e}"))
+}
+
+/// `code` drawn centred on a `size`×`size` white square.
+fn render(code: &QrCode, size: u32) -> Result<GrayImage, String> {
+    let width = code.width() as u32;
+    let modules = width + 2 * QUIET_ZONE;
+    let module_px = size / modules;
+    if module_px == 0 {
+        return Err(format!(
+            "{size}px is too small for a {modules}-module QR code"
+        ));
+    }
+    let offset = (size - module_px * modules) / 2 + QUIET_ZONE * module_px;
+
+    let mut img = GrayImage::from_pixel(size, size, Luma([255]));
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != Color::Dark {
+            continue;
+        }
+        let (col, row) = (i as u32 % width, i as u32 / width);
+        let (x0, y0) = (offset + col * module_px, offset + row * module_px);
+        for y in y0..y0 + module_px {
+            for x in x0..x0 + module_px {
+                img.put_pixel(x, y, Luma([0]));
+            }
+        }
+    }
+    Ok(img)
+}
+
+/// PNG bytes of a QR code for `data`, `size` pixels square (clamped to
+/// 64–2048). Errors when `data` is empty or exceeds QR capacity.
+#[tauri::command]
+pub fn generate_qr(data: String, size: u32) -> Result<Vec<u8>, String> {
+    let code = encode(&data)?;
+    let img = render(&code, size.clamp(MIN_SIZE, MAX_SIZE))?;
+    let mut out = Vec::new();
+    img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+        .map_err(|e| format!("PNG encode error: {e}"))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_requested_size_with_quiet_zone() {
+        let png =
+            generate_qr("readest://open?book=abc123&cfi=epubcfi(/6/4)".into(), 300).expect("qr");
+        let img = image::load_from_memory(&png).expect("png").to_luma8();
+        assert_eq!(img.dimensions(), (300, 300));
+
+        let code = encode("readest://open?book=abc123&cfi=epubcfi(/6/4)").unwrap();
+        let modules = code.width() as u32 + 2 * QUIET_ZONE;
+        let module_px = 300 / modules;
+        let offset = (300 - module_px * modules) / 2 + QUIET_ZONE * module_px;
+        // The border is white and the top-left finder pattern starts right
+        // after it.
+        assert_eq!(img.get_pixel(offset - 1, offset - 1)[0], 255);
+        assert_eq!(img.get_pixel(offset, offset)[0], 0);
+        assert_eq!(img.get_pixel(0, 0)[0], 255);
+    }
+
+    #[test]
+    fn rejects_data_over_capacity() {
+        let err = generate_qr("x".repeat(MAX_DATA_BYTES + 1), 512).unwrap_err();
+        assert!(err.contains("too long"), "{err}");
+        assert!(generate_qr("x".repeat(MAX_DATA_BYTES), 2048).is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_data_and_tiny_canvas() {
+        assert!(generate_qr(String::new(), 256).is_err());
+        let code = encode(&"x".repeat(MAX_DATA_BYTES)).unwrap();
+        assert!(render(&code, 64).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! `capture_reader_view`: grab the calling webview's current frame as PNG
+//! bytes, for quote sharing and social cards (paired with the sharekit
+//! plugin on the JS side).
+//!
+//! Tauri has no cross-platform webview capture API, so this goes through the
+//! native engine where we already link the bindings: `WKWebView
+//! takeSnapshotWithConfiguration:` on macOS. WebView2 (`CapturePreview`) and
+//! WebKitGTK (`webkit_web_view_get_snapshot`) would need their own binding
+//! crates, so Windows, Linux and mobile report [`CaptureError::Unsupported`]
+//! and the frontend falls back to its DOM-based renderer.
+
+use serde::Serialize;
+use tauri::WebviewWindow;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CaptureError {
+    #[error("webview capture is not supported on this platform: {0}")]
+    Unsupported(String),
+    /// Only the macOS snapshot can fail once started.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    #[error("webview capture failed: {0}")]
+    Failed(String),
+}
+
+/// Capture the visible area of the calling webview and return it as PNG.
+#[tauri::command]
+pub async fn capture_reader_view(webview: WebviewWindow) -> Result<Vec<u8>, CaptureError> {
+    #[cfg(target_os = "macos")]
+    {
+        crate::macos::webview_snapshot::snapshot_png(&webview).await
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = webview;
+        Err(CaptureError::Unsupported(std::env::consts::OS.to_string()))
+    }
+}
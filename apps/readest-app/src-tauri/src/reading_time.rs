@@ -0,0 +1,467 @@
+//! `estimate_reading_time`: how long a book takes to read, overall and per
+//! chapter, so the reader can show "about 6 hours left" from any position.
+//!
+//! Words are counted in the visible text: each spine document of an EPUB
+//! (one chapter each) and each top-level `<section>` of an FB2's main body.
+//! Documents are parsed as a stream, so a large book is never held in
+//! memory. MOBI text is one compressed stream without chapter boundaries:
+//! the first few records are decompressed as a sample, and its words per
+//! byte are scaled to the text length in the PalmDOC header, giving a
+//! single chapter.
+//!
+//! Chinese and Japanese don't separate words with spaces, so their
+//! characters are counted instead and read at [`CJK_CHARS_PER_WORD`] times
+//! the given rate.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::Path;
+
+use mobi::headers::Compression;
+use mobi::Mobi;
+use quick_xml::events::{BytesText, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use zip::ZipArchive;
+
+use crate::book_rename::split_book_name;
+use crate::epub_parser::{
+    local_name, read_rootfile_path, read_zip_entry, resolve_relative, spine_documents,
+};
+
+/// Han and kana characters read in the time of one word of a
+/// space-separated language.
+const CJK_CHARS_PER_WORD: f64 = 1.5;
+/// MOBI text records decompressed for the words-per-byte sample.
+const MOBI_SAMPLE_RECORDS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingTime {
+    pub total_minutes: u32,
+    /// Minutes for each chapter, in reading order. Rounded separately, so
+    /// they may not add up to `total_minutes` exactly.
+    pub per_chapter: Vec<u32>,
+}
+
+/// Words, and CJK characters, of some text.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct WordCount {
+    words: u64,
+    cjk_chars: u64,
+}
+
+impl WordCount {
+    fn add_text(&mut self, text: &str) {
+        let mut in_word = false;
+        for c in text.chars() {
+            if is_cjk(c) {
+                self.cjk_chars += 1;
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                self.words += u64::from(!in_word);
+                in_word = true;
+            } else if !(in_word && matches!(c, '\'' | '’' | '-')) {
+                in_word = false;
+            }
+        }
+    }
+
+    fn minutes(&self, wpm: u32) -> f64 {
+        (self.words as f64 + self.cjk_chars as f64 / CJK_CHARS_PER_WORD) / f64::from(wpm)
+    }
+}
+
+/// Han ideographs and Japanese kana. Hangul is left out: Korean puts spaces
+/// between words.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+/// `text` with entities such as `&nbsp;` replaced by spaces.
+fn strip_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        match rest.find(';').filter(|end| *end <= 10) {
+            Some(end) => {
+                out.push(' ');
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Text of an XML event, unescaped; unknown entities are dropped rather
+/// than failing the whole run.
+fn event_text(text: &BytesText) -> String {
+    match text.unescape() {
+        Ok(unescaped) => unescaped.into_owned(),
+        Err(_) => strip_entities(&String::from_utf8_lossy(text)),
+    }
+}
+
+/// Elements whose text isn't read.
+fn is_hidden(name: &[u8]) -> bool {
+    matches!(local_name(name), b"head" | b"script" | b"style")
+}
+
+/// Words in the visible text of an XHTML document: `<head>`, `<script>` and
+/// `<style>` are skipped. Stops quietly at malformed markup.
+fn count_xhtml<R: BufRead>(source: R) -> WordCount {
+    let mut reader = Reader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut count = WordCount::default();
+    let mut hidden = 0usize;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if is_hidden(e.name().as_ref()) => hidden += 1,
+            Ok(Event::End(e)) if is_hidden(e.name().as_ref()) => hidden = hidden.saturating_sub(1),
+            Ok(Event::Text(t)) if hidden == 0 => count.add_text(&event_text(&t)),
+            Ok(Event::CData(t)) if hidden == 0 => count.add_text(&String::from_utf8_lossy(&t)),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    count
+}
+
+/// Word counts of the top-level sections of an FB2's first `<body>`. Text
+/// before the first section (the body title, an epigraph) goes with it.
+fn count_fb2<R: BufRead>(source: R) -> Vec<WordCount> {
+    let mut reader = Reader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut chapters = vec![WordCount::default()];
+    let mut in_body = false;
+    let mut section_depth = 0usize;
+    let mut seen_section = false;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match local_name(e.name().as_ref()) {
+                b"body" => in_body = true,
+                b"section" if in_body => {
+                    if section_depth == 0 {
+                        if seen_section {
+                            chapters.push(WordCount::default());
+                        }
+                        seen_section = true;
+                    }
+                    section_depth += 1;
+                }
+                _ => {}
+            },
+            Ok(Event::End(e)) => match local_name(e.name().as_ref()) {
+                // Later bodies hold notes and comments.
+                b"body" if in_body => break,
+                b"section" => section_depth = section_depth.saturating_sub(1),
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_body => {
+                let text = event_text(&t);
+                if let Some(chapter) = chapters.last_mut() {
+                    chapter.add_text(&text);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    chapters
+}
+
+/// One count per spine document, read one at a time.
+fn count_epub<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Vec<WordCount>, String> {
+    let opf_path = read_rootfile_path(zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf = read_zip_entry(zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    let spine = spine_documents(&opf)?;
+    Ok(spine
+        .iter()
+        .map(|href| {
+            let name = resolve_relative(&opf_path, href);
+            let decoded = percent_encoding::percent_decode_str(&name).decode_utf8_lossy();
+            let index = zip
+                .index_for_name(&name)
+                .or_else(|| zip.index_for_name(&decoded));
+            // A missing document reads as empty, keeping chapters aligned
+            // with the spine.
+            index
+                .and_then(|index| zip.by_index(index).ok())
+                .map(|entry| count_xhtml(BufReader::new(entry)))
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+/// Expand PalmDOC (LZ77-style) compressed `input` onto `out`.
+fn palmdoc_decompress(input: &[u8], out: &mut Vec<u8>) {
+    let mut pos = 0;
+    while pos < input.len() {
+        let byte = input[pos];
+        pos += 1;
+        match byte {
+            0x01..=0x08 => {
+                let end = (pos + usize::from(byte)).min(input.len());
+                out.extend_from_slice(&input[pos..end]);
+                pos = end;
+            }
+            0x80..=0xBF => {
+                let Some(&low) = input.get(pos) else {
+                    break;
+                };
+                pos += 1;
+                let pair = u16::from_be_bytes([byte, low]);
+                let distance = usize::from((pair >> 3) & 0x07FF);
+                let length = usize::from(pair & 0x07) + 3;
+                if distance == 0 || distance > out.len() {
+                    break;
+                }
+                for _ in 0..length {
+                    out.push(out[out.len() - distance]);
+                }
+            }
+            0xC0..=0xFF => out.extend_from_slice(&[b' ', byte ^ 0x80]),
+            _ => out.push(byte),
+        }
+    }
+}
+
+/// Words in the visible text of an HTML fragment, with tags dropped and
+/// `<head>`, `<script>` and `<style>` skipped. Unlike [`count_xhtml`], this
+/// doesn't need well-formed markup.
+fn count_html(html: &str) -> WordCount {
+    let lower = html.to_ascii_lowercase();
+    let mut count = WordCount::default();
+    let mut pos = 0;
+    while pos < html.len() {
+        let Some(open) = html[pos..].find('<').map(|i| pos + i) else {
+            count.add_text(&strip_entities(&html[pos..]));
+            break;
+        };
+        count.add_text(&strip_entities(&html[pos..open]));
+        let tag_end = html[open..].find('>').map_or(html.len(), |i| open + i);
+        let name: String = lower[open + 1..tag_end]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        pos = (tag_end + 1).min(html.len());
+        if matches!(name.as_str(), "head" | "script" | "style") {
+            let closing = format!("</{name}");
+            pos = lower[pos..].find(&closing).map_or(html.len(), |i| pos + i);
+        }
+    }
+    count
+}
+
+/// The book's words, estimated from a sample of its first text records.
+fn count_mobi(mobi: &Mobi) -> WordCount {
+    let text_length = u64::from(mobi.metadata.palmdoc.text_length);
+    let records = mobi.raw_records();
+    let sample_records = records.range(mobi.readable_records_range());
+    let sample_records = &sample_records[..sample_records.len().min(MOBI_SAMPLE_RECORDS)];
+    let sample = match mobi.compression() {
+        Compression::PalmDoc => {
+            let mut sample = Vec::new();
+            for record in sample_records {
+                palmdoc_decompress(record.content, &mut sample);
+            }
+            sample
+        }
+        Compression::No => sample_records
+            .iter()
+            .flat_map(|record| record.content.iter().copied())
+            .collect(),
+        // HUFF/CDIC can't be decompressed a record at a time; count it all.
+        Compression::Huff => return count_html(&mobi.content_as_string_lossy()),
+    };
+    if sample.is_empty() || text_length == 0 {
+        return WordCount::default();
+    }
+    let sampled = count_html(&String::from_utf8_lossy(&sample));
+    let sample_length = sample.len() as u64;
+    if sample_length >= text_length {
+        return sampled;
+    }
+    let scale = |n: u64| (n as f64 * text_length as f64 / sample_length as f64).round() as u64;
+    WordCount {
+        words: scale(sampled.words),
+        cjk_chars: scale(sampled.cjk_chars),
+    }
+}
+
+fn chapter_counts(path: &Path) -> Result<Vec<WordCount>, String> {
+    let (_, ext) = split_book_name(path);
+    match ext.to_ascii_lowercase().as_str() {
+        "epub" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            count_epub(&mut zip)
+        }
+        "mobi" | "azw" | "azw3" | "prc" => {
+            let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
+            Ok(vec![count_mobi(&mobi)])
+        }
+        "fb2" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            Ok(count_fb2(BufReader::new(file)))
+        }
+        "fbz" | "fb2.zip" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            let index = (0..zip.len())
+                .find(|&i| {
+                    zip.name_for_index(i)
+                        .is_some_and(|n| n.to_lowercase().ends_with(".fb2"))
+                })
+                .ok_or_else(|| "no .fb2 document in archive".to_string())?;
+            let entry = zip.by_index(index).map_err(|e| format!("read fb2: {e}"))?;
+            Ok(count_fb2(BufReader::new(entry)))
+        }
+        other => Err(format!("unsupported format: {other}")),
+    }
+}
+
+fn estimate(chapters: &[WordCount], wpm: u32) -> ReadingTime {
+    let minutes: Vec<f64> = chapters.iter().map(|c| c.minutes(wpm)).collect();
+    ReadingTime {
+        total_minutes: minutes.iter().sum::<f64>().round() as u32,
+        per_chapter: minutes.iter().map(|m| m.round() as u32).collect(),
+    }
+}
+
+fn estimate_reading_time_sync(path: &Path, wpm: u32) -> Result<ReadingTime, String> {
+    if wpm == 0 {
+        return Err("words per minute must be positive".to_string());
+    }
+    if !path.is_file() {
+        return Err(format!("file not found: {}", path.display()));
+    }
+    Ok(estimate(&chapter_counts(path)?, wpm))
+}
+
+/// Minutes to read the book at `path` at `wpm` words per minute, in total
+/// and for each chapter.
+#[tauri::command]
+pub async fn estimate_reading_time(path: String, wpm: u32) -> Result<ReadingTime, String> {
+    tauri::async_runtime::spawn_blocking(move || estimate_reading_time_sync(Path::new(&path), wpm))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn words(n: usize) -> String {
+        vec!["word"; n].join(" ")
+    }
+
+    #[test]
+    fn counts_words_and_cjk_characters() {
+        let mut count = WordCount::default();
+        count.add_text("Don't stop — well-known words, 42 of them.");
+        assert_eq!(count.words, 7);
+        count.add_text("吾輩は猫である。");
+        assert_eq!(count.cjk_chars, 7);
+        assert_eq!(count.minutes(10), (7.0 + 7.0 / CJK_CHARS_PER_WORD) / 10.0);
+
+        let html = count_xhtml(
+            "<html><head><title>Skipped title</title></head>\
+             <body><p>One&nbsp;two <b>three</b></p><script>var x;</script></body></html>"
+                .as_bytes(),
+        );
+        assert_eq!(html.words, 3);
+        assert_eq!(
+            count_html("<p>One <i>two</i></p><style>p {}</style>").words,
+            2
+        );
+    }
+
+    #[test]
+    fn epub_chapters_follow_the_spine() {
+        let dir = std::env::temp_dir().join(format!("readest-reading-time-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let book = dir.join("book.epub");
+        let mut writer = ZipWriter::new(File::create(&book).unwrap());
+        let opf = r#"<package><manifest>
+            <item id="a" href="a.xhtml" media-type="application/xhtml+xml"/>
+            <item id="b" href="b%20c.xhtml" media-type="application/xhtml+xml"/>
+            </manifest><spine><itemref idref="b"/><itemref idref="a"/></spine></package>"#;
+        let chapter = |n| format!("<html><body><p>{}</p></body></html>", words(n));
+        let entries = [
+            (
+                "META-INF/container.xml".to_string(),
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#
+                    .to_string(),
+            ),
+            ("OEBPS/content.opf".to_string(), opf.to_string()),
+            ("OEBPS/a.xhtml".to_string(), chapter(600)),
+            ("OEBPS/b c.xhtml".to_string(), chapter(1500)),
+        ];
+        for (name, data) in entries {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let time = estimate_reading_time_sync(&book, 300).unwrap();
+        assert_eq!(
+            time,
+            ReadingTime {
+                total_minutes: 7,
+                per_chapter: vec![5, 2],
+            }
+        );
+        assert!(estimate_reading_time_sync(&book, 0).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn fb2_chapters_are_top_level_sections() {
+        let fb2 = format!(
+            "<FictionBook><body><title><p>Book</p></title>\
+             <section><p>{}</p><section><p>{}</p></section></section>\
+             <section><p>{}</p></section></body>\
+             <body name=\"notes\"><section><p>{}</p></section></body></FictionBook>",
+            words(10),
+            words(20),
+            words(5),
+            words(100)
+        );
+        let chapters = count_fb2(fb2.as_bytes());
+        let counts: Vec<_> = chapters.iter().map(|c| c.words).collect();
+        assert_eq!(counts, [31, 5]);
+    }
+
+    #[test]
+    fn palmdoc_expands_literals_and_back_references() {
+        // "abcabcabc": three literals, then a 6-byte copy from 3 back.
+        let mut out = Vec::new();
+        let pair: u16 = 0x8000 | (3 << 3) | (6 - 3);
+        let mut input = b"abc".to_vec();
+        input.extend_from_slice(&pair.to_be_bytes());
+        input.extend_from_slice(&[0x02, 0xC1, b'!', 0xE1]);
+        palmdoc_decompress(&input, &mut out);
+        assert_eq!(out, b"abcabcabc\xC1! a");
+    }
+}
@@ -0,0 +1,212 @@
+//! Recently opened files, kept in one place for the native menus and the
+//! web UI's "Recent" view.
+//!
+//! Entries live in `recent_files.json` in the app data dir, newest first and
+//! at most [`MAX_ENTRIES`] of them. Opening a file through the OS (argv, a
+//! second instance, macOS `Opened`, File ▸ Open) records it here; the
+//! frontend adds the books it opens itself with [`add_recent_file`].
+//! Whether a file still exists is checked on every [`get_recent_files`], so
+//! moved or deleted books stay listed but can be shown as missing.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tauri::AppHandle;
+
+use crate::portable;
+use crate::position_sidecar::now_millis;
+
+const STORE_FILENAME: &str = "recent_files.json";
+/// Entries kept; the least recently opened go first.
+const MAX_ENTRIES: usize = 30;
+
+/// Serializes read-modify-write of the store within the process.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredEntry {
+    path: String,
+    title: String,
+    /// Milliseconds since the Unix epoch.
+    last_opened: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentEntry {
+    pub path: String,
+    pub title: String,
+    /// Milliseconds since the Unix epoch.
+    pub last_opened: u64,
+    pub exists: bool,
+}
+
+/// Title for a file nobody named: its name without the extension.
+fn default_title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// The stored entries; a missing or unreadable store is an empty list.
+fn load(dir: &Path) -> Vec<StoredEntry> {
+    let Ok(bytes) = std::fs::read(dir.join(STORE_FILENAME)) else {
+        return Vec::new();
+    };
+    serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable {STORE_FILENAME}: {e}");
+        Vec::new()
+    })
+}
+
+fn save(dir: &Path, entries: &[StoredEntry]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(entries)
+        .map_err(|e| format!("Failed to serialize recent files: {e}"))?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let tmp = dir.join(format!("{STORE_FILENAME}.tmp"));
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write recent files: {e}"))?;
+    std::fs::rename(&tmp, dir.join(STORE_FILENAME))
+        .map_err(|e| format!("Failed to write recent files: {e}"))
+}
+
+/// Move `path` to the front of the store in `dir`. A `None` title keeps the
+/// one recorded before, or falls back to the file name.
+fn add(dir: &Path, path: &str, title: Option<String>, now: u64) -> Result<(), String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = load(dir);
+    let previous = entries
+        .iter()
+        .position(|entry| entry.path == path)
+        .map(|index| entries.remove(index));
+    let title = title
+        .filter(|title| !title.trim().is_empty())
+        .or_else(|| previous.map(|entry| entry.title))
+        .unwrap_or_else(|| default_title(path));
+    entries.insert(
+        0,
+        StoredEntry {
+            path: path.to_string(),
+            title,
+            last_opened: now,
+        },
+    );
+    entries.truncate(MAX_ENTRIES);
+    save(dir, &entries)
+}
+
+fn list(dir: &Path) -> Vec<RecentEntry> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    load(dir)
+        .into_iter()
+        .map(|entry| RecentEntry {
+            exists: Path::new(&entry.path).exists(),
+            path: entry.path,
+            title: entry.title,
+            last_opened: entry.last_opened,
+        })
+        .collect()
+}
+
+/// Where the store lives, for [`crate::app_reset`].
+#[cfg(desktop)]
+pub(crate) fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(portable::app_data_dir(app)?.join(STORE_FILENAME))
+}
+
+/// Record files opened through the OS. Failures are logged, not returned:
+/// they must never get in the way of opening the book.
+pub(crate) fn record(app: &AppHandle, files: &[PathBuf]) {
+    let dir = match portable::app_data_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Not recording recent files: {e}");
+            return;
+        }
+    };
+    let now = now_millis();
+    for file in files {
+        if let Err(e) = add(&dir, &file.to_string_lossy(), None, now) {
+            log::warn!("Failed to record recent file {}: {e}", file.display());
+        }
+    }
+}
+
+/// Record `path` as just opened. `title` is the book title when the caller
+/// knows it; otherwise the previous title, or the file name, is kept.
+#[tauri::command]
+pub fn add_recent_file(app: AppHandle, path: String, title: Option<String>) -> Result<(), String> {
+    add(&portable::app_data_dir(&app)?, &path, title, now_millis())
+}
+
+/// Recently opened files, newest first.
+#[tauri::command]
+pub fn get_recent_files(app: AppHandle) -> Result<Vec<RecentEntry>, String> {
+    Ok(list(&portable::app_data_dir(&app)?))
+}
+
+#[tauri::command]
+pub fn clear_recent_files(app: AppHandle) -> Result<(), String> {
+    let dir = portable::app_data_dir(&app)?;
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    match std::fs::remove_file(dir.join(STORE_FILENAME)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to clear recent files: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn reopening_moves_to_front_and_keeps_title() {
+        let dir = temp_dir("recent-reopen");
+        let book = dir.join("Dune.epub");
+        std::fs::write(&book, b"epub").unwrap();
+        let book = book.to_string_lossy().into_owned();
+
+        add(&dir, &book, Some("Dune".into()), 1).unwrap();
+        add(&dir, "/gone/Emma.pdf", None, 2).unwrap();
+        add(&dir, &book, None, 3).unwrap();
+
+        let entries = list(&dir);
+        assert_eq!(
+            entries,
+            vec![
+                RecentEntry {
+                    path: book,
+                    title: "Dune".into(),
+                    last_opened: 3,
+                    exists: true,
+                },
+                RecentEntry {
+                    path: "/gone/Emma.pdf".into(),
+                    title: "Emma".into(),
+                    last_opened: 2,
+                    exists: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn store_is_bounded_and_survives_corruption() {
+        let dir = temp_dir("recent-bounded");
+        for i in 0..MAX_ENTRIES as u64 + 5 {
+            add(&dir, &format!("/books/{i}.epub"), None, i).unwrap();
+        }
+        let entries = list(&dir);
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].path, format!("/books/{}.epub", MAX_ENTRIES + 4));
+        assert_eq!(entries[MAX_ENTRIES - 1].path, "/books/5.epub");
+
+        std::fs::write(dir.join(STORE_FILENAME), b"{not json").unwrap();
+        assert!(list(&dir).is_empty());
+        add(&dir, "/books/new.epub", None, 100).unwrap();
+        assert_eq!(list(&dir).len(), 1);
+    }
+}
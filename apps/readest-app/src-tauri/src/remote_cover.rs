@@ -0,0 +1,291 @@
+//! `cached_remote_cover`: thumbnails for covers that live at a URL, as in
+//! OPDS catalogs.
+//!
+//! The image is fetched with the HTTP plugin's client, rendered by the
+//! Explorer thumbnail provider's crate exactly as it renders book covers (fit
+//! to `size`, optional Readest badge in the corner) and cached on disk under
+//! the MD5 of the URL.
+//! The source image is kept next to its `ETag`/`Last-Modified`, so a later
+//! request revalidates with a conditional GET and a `304` re-renders from
+//! disk. Bodies over [`MAX_DOWNLOAD_BYTES`] are refused. When the fetch
+//! fails the last cached copy is used, and without one a blank placeholder
+//! is returned, so the catalog grid never has holes.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_http::reqwest::{self, header, StatusCode};
+
+use crate::parser_common::COVER_JPEG_QUALITY;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Largest cover image downloaded. Catalog covers are a few hundred KB.
+const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
+/// Bounds on the requested thumbnail edge.
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 1024;
+/// Fill of the placeholder returned when there is no cover to show.
+const PLACEHOLDER_COLOR: Rgba<u8> = Rgba([0xd4, 0xd4, 0xd8, 0xff]);
+
+/// Validators of the cached source image.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheMeta {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Where one URL's files live in the cache.
+struct CacheEntry {
+    dir: PathBuf,
+    key: String,
+}
+
+impl CacheEntry {
+    fn new(dir: &Path, url: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            key: format!("{:x}", Md5::digest(url.as_bytes())),
+        }
+    }
+
+    fn meta_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.json", self.key))
+    }
+
+    fn source_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.img", self.key))
+    }
+
+    fn thumbnail_path(&self, size: u32, badge: bool) -> PathBuf {
+        let suffix = if badge { "-badge" } else { "" };
+        self.dir.join(format!("{}-{size}{suffix}.thumb", self.key))
+    }
+
+    fn meta(&self) -> Option<CacheMeta> {
+        let bytes = fs::read(self.meta_path()).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Replace the source image, dropping thumbnails rendered from the old
+    /// one.
+    fn store_source(&self, bytes: &[u8], meta: &CacheMeta) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let prefix = format!("{}-", self.key);
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        fs::write(self.source_path(), bytes)?;
+        fs::write(self.meta_path(), serde_json::to_vec(meta)?)
+    }
+
+    /// The cached thumbnail, rendering it from the cached source if needed.
+    fn thumbnail(&self, size: u32, badge: bool) -> Option<Vec<u8>> {
+        let path = self.thumbnail_path(size, badge);
+        if let Ok(bytes) = fs::read(&path) {
+            return Some(bytes);
+        }
+        let source = fs::read(self.source_path()).ok()?;
+        let thumbnail = windows_thumbnail::create_thumbnail_with_overlay(
+            &source,
+            size,
+            COVER_JPEG_QUALITY,
+            badge,
+        )
+        .ok()?;
+        let _ = fs::write(path, &thumbnail);
+        Some(thumbnail)
+    }
+}
+
+/// A blank cover with the usual 2:3 shape.
+fn placeholder(size: u32) -> Vec<u8> {
+    let height = size;
+    let width = (size * 2 / 3).max(1);
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, PLACEHOLDER_COLOR));
+    windows_thumbnail::encode_thumbnail(&img, COVER_JPEG_QUALITY).unwrap_or_default()
+}
+
+/// The body, or an error once it passes [`MAX_DOWNLOAD_BYTES`].
+async fn read_capped(mut response: reqwest::Response) -> Result<Vec<u8>, String> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_DOWNLOAD_BYTES as u64)
+    {
+        return Err(format!("cover larger than {MAX_DOWNLOAD_BYTES} bytes"));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if body.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err(format!("cover larger than {MAX_DOWNLOAD_BYTES} bytes"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn header_string(response: &reqwest::Response, name: header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Bring the cached source for `url` up to date. On error the cache is left
+/// as it was.
+async fn refresh(client: &reqwest::Client, url: &str, entry: &CacheEntry) -> Result<(), String> {
+    let mut request = client.get(url);
+    if entry.source_path().exists() {
+        if let Some(meta) = entry.meta() {
+            if let Some(etag) = meta.etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = meta.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, modified);
+            }
+        }
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => Ok(()),
+        status if status.is_success() => {
+            let meta = CacheMeta {
+                etag: header_string(&response, header::ETAG),
+                last_modified: header_string(&response, header::LAST_MODIFIED),
+            };
+            let body = read_capped(response).await?;
+            // Don't let a broken download replace a good cached cover.
+            image::load_from_memory(&body).map_err(|e| format!("bad cover: {e}"))?;
+            entry
+                .store_source(&body, &meta)
+                .map_err(|e| format!("failed to cache cover: {e}"))
+        }
+        status => Err(format!("HTTP {status}")),
+    }
+}
+
+/// Thumbnail for the cover at `url`, from `cache_dir` when still current.
+async fn remote_cover(
+    client: &reqwest::Client,
+    cache_dir: &Path,
+    url: &str,
+    size: u32,
+    badge: bool,
+) -> Vec<u8> {
+    let size = size.clamp(MIN_SIZE, MAX_SIZE);
+    let entry = CacheEntry::new(cache_dir, url);
+    if let Err(e) = refresh(client, url, &entry).await {
+        log::debug!("could not fetch cover {url}: {e}");
+    }
+    crate::crash_report::breadcrumb("thumbnail-generated", format!("{size}px {url}"));
+    tauri::async_runtime::spawn_blocking(move || {
+        entry
+            .thumbnail(size, badge)
+            .unwrap_or_else(|| placeholder(size))
+    })
+    .await
+    .unwrap_or_else(|_| placeholder(size))
+}
+
+/// Thumbnail of the cover image at `url`, at most `size` pixels on its long
+/// edge: JPEG, or PNG when the cover has transparency. `badge` adds the
+/// Readest badge, off by default. Never fails: a cover that can't be fetched
+/// comes back as a placeholder.
+#[tauri::command]
+pub async fn cached_remote_cover(
+    app: AppHandle,
+    url: String,
+    size: u32,
+    badge: Option<bool>,
+) -> Result<Vec<u8>, String> {
+    let cache_dir = crate::portable::cache_dir(&app)?.join("remote-covers");
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {e}"))?;
+    Ok(remote_cover(&client, &cache_dir, &url, size, badge.unwrap_or(false)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use image::GenericImageView;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, Rgba([200, 40, 40, 255]));
+        let mut out = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    fn dimensions(thumbnail: &[u8]) -> (u32, u32) {
+        image::load_from_memory(thumbnail).unwrap().dimensions()
+    }
+
+    /// Serves `body` with an `ETag`, answering `304` to a matching
+    /// `If-None-Match`. Counts the full responses sent.
+    async fn serve(body: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/cover.png", listener.local_addr().unwrap());
+        let full = Arc::new(AtomicUsize::new(0));
+        let counter = full.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0u8; 4096];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]).to_ascii_lowercase();
+                if request.contains("if-none-match: \"v1\"") {
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n")
+                        .await;
+                } else {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\netag: \"v1\"\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                }
+            }
+        });
+        (url, full)
+    }
+
+    #[tokio::test]
+    async fn covers_are_cached_and_revalidated() {
+        let dir = temp_dir("remote-cover");
+        let (url, full) = serve(png(300, 450)).await;
+        let client = reqwest::Client::new();
+
+        let first = remote_cover(&client, &dir, &url, 120, false).await;
+        assert_eq!(dimensions(&first), (80, 120));
+        // Revalidated with the cached ETag: no second download.
+        let second = remote_cover(&client, &dir, &url, 120, true).await;
+        assert_eq!(dimensions(&second), (80, 120));
+        assert_eq!(full.load(Ordering::SeqCst), 1);
+
+        // A dead URL still fills its grid cell.
+        let missing = remote_cover(&client, &dir, "http://127.0.0.1:1/none.png", 120, false).await;
+        assert_eq!(dimensions(&missing), (80, 120));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,211 @@
+//! Opening a book piped on stdin: `some-converter | readest -` (or `--stdin`).
+//!
+//! The pipe is read on a blocking task, so a slow converter doesn't hold up
+//! the window, and the bytes are spooled into `<cache>/stdin/` under a
+//! per-process name with an extension sniffed from the content. Stdin
+//! belongs to this process, so stdin mode skips the single-instance
+//! hand-off and opens in its own window instead of being forwarded to a
+//! running instance that can't see the pipe.
+//!
+//! The book reaches the frontend like a file from the macOS Services menu:
+//! if it is ready before the frontend listens it waits for
+//! [`take_stdin_book`], otherwise it is emitted as an `open-files` event.
+//!
+//! The library imports a copy of the book, so the spooled file goes as soon
+//! as the reader closes the book ([`release_stdin_book`]), which closing its
+//! window also does. Anything left is removed on exit, and leftovers from
+//! crashed sessions are swept the next time stdin mode starts.
+
+use serde::Serialize;
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::book_id::book_id;
+use crate::parser_common::sniff_extension;
+
+/// Spooled files older than this are assumed orphaned by a crashed process.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The book spooled by this process.
+#[derive(Default)]
+pub struct StdinBook(Mutex<Spool>);
+
+#[derive(Default)]
+struct Spool {
+    /// The spooled file, until it is removed.
+    path: Option<PathBuf>,
+    /// Its book id, the hash the frontend releases it by.
+    id: Option<String>,
+    delivery: Delivery,
+}
+
+enum Delivery {
+    /// The frontend hasn't asked yet; holds the book once it is read.
+    Queued(Option<PathBuf>),
+    /// The frontend has asked, so a book read now is emitted.
+    Live,
+}
+
+impl Default for Delivery {
+    fn default() -> Self {
+        Delivery::Queued(None)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct OpenFilesPayload {
+    files: Vec<String>,
+}
+
+/// Whether the command line asks to read a book from stdin.
+pub fn requested() -> bool {
+    std::env::args()
+        .skip(1)
+        .any(|arg| arg == "-" || arg == "--stdin")
+}
+
+fn spool_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::portable::cache_dir(app)?.join("stdin"))
+}
+
+/// Read stdin to the end and spool it into the cache dir, returning the
+/// path to open. Fails when nothing is piped in or the format is unknown.
+fn read_to_cache(app: &AppHandle) -> Result<PathBuf, String> {
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err("No book piped on stdin".to_string());
+    }
+    let mut bytes = Vec::new();
+    stdin
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read stdin: {e}"))?;
+    if bytes.is_empty() {
+        return Err("No book piped on stdin".to_string());
+    }
+    let ext = sniff_extension(&bytes).ok_or("Unrecognized book format on stdin")?;
+
+    let dir = spool_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    crate::portable::sweep_stale(&dir, STALE_AFTER);
+    let path = dir.join(format!("stdin-{}.{ext}", std::process::id()));
+    fs::write(&path, &bytes).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    let id = book_id(&path).ok();
+    if let Ok(mut spool) = app.state::<StdinBook>().0.lock() {
+        spool.path = Some(path.clone());
+        spool.id = id;
+    }
+    Ok(path)
+}
+
+/// Hand the spooled book to the frontend, or queue it until it asks.
+fn deliver(app: &AppHandle, path: PathBuf) {
+    crate::crash_report::breadcrumb("file-opened", path.to_string_lossy());
+    crate::allow_file_in_scopes(app, vec![path.clone()]);
+    let state = app.state::<StdinBook>();
+    let mut spool = state.0.lock().unwrap_or_else(|e| e.into_inner());
+    if let Delivery::Queued(queued) = &mut spool.delivery {
+        *queued = Some(path);
+        return;
+    }
+    drop(spool);
+    let files = vec![path.to_string_lossy().into_owned()];
+    if let Err(e) = app.emit("open-files", OpenFilesPayload { files }) {
+        log::error!("Failed to open book from stdin: {e}");
+    }
+}
+
+/// Start reading the piped book in the background. Call from app setup.
+pub fn start(app: &AppHandle) {
+    app.manage(StdinBook::default());
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || match read_to_cache(&app) {
+        Ok(path) => deliver(&app, path),
+        Err(e) => log::error!("Failed to open book from stdin: {e}"),
+    });
+}
+
+/// The book piped on stdin if it was read before the frontend was
+/// listening. Every later one is emitted as an `open-files` event instead.
+#[tauri::command]
+pub fn take_stdin_book(app: AppHandle) -> Option<String> {
+    let state = app.try_state::<StdinBook>()?;
+    let mut spool = state.0.lock().unwrap_or_else(|e| e.into_inner());
+    match std::mem::replace(&mut spool.delivery, Delivery::Live) {
+        Delivery::Queued(path) => path.map(|p| p.to_string_lossy().into_owned()),
+        Delivery::Live => None,
+    }
+}
+
+/// Remove the spooled file once the reader closes the book with id `hash`.
+/// Other books are ignored.
+#[tauri::command]
+pub fn release_stdin_book(app: AppHandle, hash: String) {
+    let Some(state) = app.try_state::<StdinBook>() else {
+        return;
+    };
+    let Ok(mut spool) = state.0.lock() else {
+        return;
+    };
+    if spool.id.as_deref() == Some(hash.as_str()) {
+        spool.id = None;
+        remove_spooled(&mut spool);
+    }
+}
+
+fn remove_spooled(spool: &mut Spool) {
+    if let Some(path) = spool.path.take() {
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("Failed to remove {}: {e}", path.display());
+        }
+    }
+}
+
+/// Delete the file spooled by this process, if it is still there.
+pub fn cleanup(app: &AppHandle) {
+    let Some(state) = app.try_state::<StdinBook>() else {
+        return;
+    };
+    let Ok(mut spool) = state.0.lock() else {
+        return;
+    };
+    remove_spooled(&mut spool);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::zip_bytes;
+
+    #[test]
+    fn sniffs_zip_based_formats() {
+        let epub = zip_bytes(&[
+            ("mimetype", b"application/epub+zip"),
+            ("META-INF/container.xml", b"<container/>"),
+        ]);
+        assert_eq!(sniff_extension(&epub), Some("epub"));
+        let fbz = zip_bytes(&[("book.FB2", b"<FictionBook/>")]);
+        assert_eq!(sniff_extension(&fbz), Some("fbz"));
+        let cbz = zip_bytes(&[("001.jpg", b"\xff\xd8"), ("002.jpg", b"\xff\xd8")]);
+        assert_eq!(sniff_extension(&cbz), Some("cbz"));
+    }
+
+    #[test]
+    fn sniffs_other_formats() {
+        assert_eq!(sniff_extension(b"%PDF-1.7\n"), Some("pdf"));
+        let mut mobi = vec![0u8; 78];
+        mobi[60..68].copy_from_slice(b"BOOKMOBI");
+        assert_eq!(sniff_extension(&mobi), Some("mobi"));
+        let fb2 = b"<?xml version=\"1.0\"?>\n<FictionBook xmlns=\"x\"></FictionBook>";
+        assert_eq!(sniff_extension(fb2), Some("fb2"));
+        assert_eq!(
+            sniff_extension("Chapter 1\nIt was…".as_bytes()),
+            Some("txt")
+        );
+        assert_eq!(sniff_extension(&[0xff, 0xfe, 0x00, 0x80]), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! OS-level progress for long-running work: the taskbar button's progress
+//! bar on Windows and a percentage badge on the macOS dock icon.
+//!
+//! Other platforms have no equivalent we can rely on (Linux launcher
+//! progress only works under Unity), so there every call is a no-op and
+//! reports `false`, letting the frontend fall back to in-app progress.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use tauri::{AppHandle, Manager};
+
+/// Whether this platform shows progress outside the window.
+pub const SUPPORTED: bool = cfg!(any(target_os = "windows", target_os = "macos"));
+
+/// `AtomicU8` value meaning "nothing shown".
+const CLEARED: u8 = u8::MAX;
+
+/// Percentage (0–100) for a fraction, or `None` to clear. Out-of-range
+/// values are clamped; NaN clears.
+fn to_percent(fraction: Option<f32>) -> Option<u8> {
+    let fraction = fraction.filter(|f| !f.is_nan())?;
+    Some((fraction.clamp(0.0, 1.0) * 100.0).round() as u8)
+}
+
+/// Show `percent` on the main window's taskbar button or dock icon, or
+/// clear it. Returns whether the platform supports it.
+fn show_percent(app: &AppHandle, percent: Option<u8>) -> bool {
+    let Some(window) = app.get_webview_window("main") else {
+        return false;
+    };
+
+    #[cfg(target_os = "windows")]
+    {
+        use tauri::window::{ProgressBarState, ProgressBarStatus};
+        let state = match percent {
+            Some(percent) => ProgressBarState {
+                status: Some(ProgressBarStatus::Normal),
+                progress: Some(percent.into()),
+            },
+            None => ProgressBarState {
+                status: Some(ProgressBarStatus::None),
+                progress: None,
+            },
+        };
+        if let Err(e) = window.set_progress_bar(state) {
+            log::warn!("Failed to set taskbar progress: {e}");
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        if let Err(e) = window.set_badge_label(percent.map(|p| format!("{p}%"))) {
+            log::warn!("Failed to set dock badge: {e}");
+        }
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let _ = (window, percent);
+
+    SUPPORTED
+}
+
+/// Set OS-level progress to `fraction` (0.0–1.0), or clear it with `None`.
+/// Returns `false` on platforms without taskbar/dock progress.
+#[tauri::command]
+pub fn set_progress(app: AppHandle, fraction: Option<f32>) -> bool {
+    show_percent(&app, to_percent(fraction))
+}
+
+/// Mirrors a transfer's progress onto the taskbar/dock, touching the OS only
+/// when the whole percentage changes, and clears it when dropped so an
+/// aborted transfer doesn't leave a stuck bar behind.
+pub struct TransferProgress {
+    app: AppHandle,
+    last_percent: AtomicU8,
+}
+
+impl TransferProgress {
+    pub fn new(app: &AppHandle) -> Self {
+        Self {
+            app: app.clone(),
+            last_percent: AtomicU8::new(CLEARED),
+        }
+    }
+
+    /// Record `done` of `total` bytes. Unknown totals (`0`) are ignored.
+    pub fn update(&self, done: u64, total: u64) {
+        if !SUPPORTED || total == 0 {
+            return;
+        }
+        let Some(percent) = to_percent(Some(done as f32 / total as f32)) else {
+            return;
+        };
+        if self.last_percent.swap(percent, Ordering::Relaxed) != percent {
+            show_percent(&self.app, Some(percent));
+        }
+    }
+}
+
+impl Drop for TransferProgress {
+    fn drop(&mut self) {
+        if self.last_percent.load(Ordering::Relaxed) != CLEARED {
+            show_percent(&self.app, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_percent;
+
+    #[test]
+    fn fractions_map_to_clamped_percentages() {
+        assert_eq!(to_percent(Some(0.0)), Some(0));
+        assert_eq!(to_percent(Some(0.426)), Some(43));
+        assert_eq!(to_percent(Some(1.0)), Some(100));
+        assert_eq!(to_percent(Some(1.7)), Some(100));
+        assert_eq!(to_percent(Some(-0.2)), Some(0));
+    }
+
+    #[test]
+    fn none_and_nan_clear() {
+        assert_eq!(to_percent(None), None);
+        assert_eq!(to_percent(Some(f32::NAN)), None);
+    }
+}
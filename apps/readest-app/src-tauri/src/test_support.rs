@@ -0,0 +1,70 @@
+//! Fixtures shared by the unit tests: scratch folders and small zip and
+//! EPUB files built in place.
+
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::dir_scanner::ScannedFile;
+
+/// An empty scratch folder for one test. `name` must be unique across the
+/// crate's tests, which run in parallel.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("readest-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `path` as `dir_scanner` would report it.
+pub(crate) fn scanned(path: &Path) -> ScannedFile {
+    ScannedFile {
+        path: path.to_string_lossy().into_owned(),
+        size: fs::metadata(path).unwrap().len(),
+    }
+}
+
+fn write_entries<W: Write + std::io::Seek>(writer: &mut ZipWriter<W>, entries: &[(&str, &[u8])]) {
+    for (name, data) in entries {
+        writer
+            .start_file(*name, SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(data).unwrap();
+    }
+}
+
+/// A zip of `entries`, in order, as bytes.
+pub(crate) fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    write_entries(&mut writer, entries);
+    writer.finish().unwrap().into_inner()
+}
+
+/// A zip of `entries`, in order, opened for reading.
+pub(crate) fn zip_with(entries: &[(&str, &[u8])]) -> ZipArchive<Cursor<Vec<u8>>> {
+    ZipArchive::new(Cursor::new(zip_bytes(entries))).unwrap()
+}
+
+/// Write an EPUB to `path`: the stored `mimetype`, a container pointing at
+/// `OEBPS/content.opf` holding `opf`, then `entries`.
+pub(crate) fn write_epub(path: &Path, opf: &[u8], entries: &[(&str, &[u8])]) {
+    let mut writer = ZipWriter::new(File::create(path).unwrap());
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    writer.start_file("mimetype", stored).unwrap();
+    writer.write_all(b"application/epub+zip").unwrap();
+    write_entries(
+        &mut writer,
+        &[
+            (
+                "META-INF/container.xml",
+                br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            ("OEBPS/content.opf", opf),
+        ],
+    );
+    write_entries(&mut writer, entries);
+    writer.finish().unwrap();
+}
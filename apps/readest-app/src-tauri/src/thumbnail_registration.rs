@@ -0,0 +1,268 @@
+//! Dry run of the Explorer thumbnail provider's registration, for the
+//! "Troubleshoot thumbnails" button in settings.
+//!
+//! Lists every registry value `DllRegisterServer` (`register_server_impl` in
+//! `extensions/windows-thumbnail`) would write — the CLSID key, its
+//! `InprocServer32` entry pointing at the installed DLL, and one `ShellEx`
+//! handler key per extension — next to what the registry holds now. Nothing
+//! is written, so support can compare a report taken before re-registering
+//! with one taken after.
+//!
+//! The per-format `DefaultIcon`s registration also sets are cosmetic and
+//! only written to ProgIDs Readest owns, so they aren't checked here.
+//!
+//! The plan below mirrors `register_server_impl` and `SUPPORTED_EXTENSIONS`
+//! there; keep them in sync.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use windows_thumbnail::{strip_verbatim_prefix, to_wide};
+
+use ::windows::core::PCWSTR;
+use ::windows::Win32::System::Registry::{
+    RegGetValueW, HKEY_CLASSES_ROOT, RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
+};
+
+const CLSID_READEST_THUMBNAIL: &str = "{A1B2C3D4-E5F6-7890-ABCD-EF1234567890}";
+const SHELL_THUMBNAIL_HANDLER: &str = "{e357fccd-a995-4576-b01f-234630154e96}";
+/// File name the installer gives the provider, next to `Readest.exe`.
+const PROVIDER_DLL: &str = "readest_thumbnail.dll";
+
+/// The provider's `shell_extensions()`: `.kepub.epub` is covered by `.epub`.
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    ".epub", ".epub3", ".kepub", ".mobi", ".azw", ".azw3", ".kf8", ".prc", ".fb2", ".fbz", ".cbz",
+    ".cbr", ".txt", ".html", ".htm", ".tar", ".tgz", ".lit",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum RegistryData {
+    Sz(String),
+    Dword(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValueStatus {
+    Matches,
+    Missing,
+    Differs,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryValueCheck {
+    /// Full key path, e.g. `HKEY_CLASSES_ROOT\.epub\ShellEx\{…}`.
+    pub key: String,
+    /// Value name; empty for the key's default value.
+    pub name: String,
+    pub expected: RegistryData,
+    pub actual: Option<RegistryData>,
+    pub status: ValueStatus,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationReport {
+    /// Path registration would write into `InprocServer32`.
+    pub dll_path: String,
+    pub dll_exists: bool,
+    /// Every value is present and matches.
+    pub registered: bool,
+    pub values: Vec<RegistryValueCheck>,
+}
+
+/// A value registration writes under `HKEY_CLASSES_ROOT`.
+#[derive(Debug, PartialEq)]
+struct PlannedValue {
+    subkey: String,
+    name: &'static str,
+    data: RegistryData,
+}
+
+fn planned(subkey: String, name: &'static str, data: RegistryData) -> PlannedValue {
+    PlannedValue { subkey, name, data }
+}
+
+/// Everything `register_server_impl` writes for a DLL at `dll_path`, in the
+/// order it writes it.
+fn registration_plan(dll_path: &str) -> Vec<PlannedValue> {
+    let clsid_key = format!("CLSID\\{CLSID_READEST_THUMBNAIL}");
+    let inproc_key = format!("{clsid_key}\\InprocServer32");
+    let mut plan = vec![
+        planned(
+            clsid_key.clone(),
+            "",
+            RegistryData::Sz("Readest Thumbnail Provider".into()),
+        ),
+        planned(clsid_key, "DisableProcessIsolation", RegistryData::Dword(1)),
+        planned(inproc_key.clone(), "", RegistryData::Sz(dll_path.into())),
+        planned(
+            inproc_key,
+            "ThreadingModel",
+            RegistryData::Sz("Apartment".into()),
+        ),
+    ];
+    plan.extend(SUPPORTED_EXTENSIONS.iter().map(|ext| {
+        planned(
+            format!("{ext}\\ShellEx\\{SHELL_THUMBNAIL_HANDLER}"),
+            "",
+            RegistryData::Sz(CLSID_READEST_THUMBNAIL.into()),
+        )
+    }));
+    plan
+}
+
+/// Registry strings here are GUIDs and paths, which Windows compares without
+/// regard to case.
+fn status(expected: &RegistryData, actual: Option<&RegistryData>) -> ValueStatus {
+    match (expected, actual) {
+        (_, None) => ValueStatus::Missing,
+        (RegistryData::Sz(want), Some(RegistryData::Sz(have)))
+            if want.eq_ignore_ascii_case(have) =>
+        {
+            ValueStatus::Matches
+        }
+        (RegistryData::Dword(want), Some(RegistryData::Dword(have))) if want == have => {
+            ValueStatus::Matches
+        }
+        _ => ValueStatus::Differs,
+    }
+}
+
+/// Where the installer puts the provider: beside our own executable. The
+/// path is canonicalized like the DLL's self-registration does, so it
+/// compares equal to what a successful registration wrote.
+fn provider_dll_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate executable: {e}"))?;
+    let dll = exe
+        .parent()
+        .map(|dir| dir.join(PROVIDER_DLL))
+        .ok_or_else(|| format!("Executable has no parent directory: {}", exe.display()))?;
+    Ok(std::fs::canonicalize(&dll).unwrap_or(dll))
+}
+
+/// Current value `name` of `HKEY_CLASSES_ROOT\<subkey>`, read as the type
+/// registration writes. `None` if the key or value is missing or has
+/// another type.
+fn read_value(subkey: &str, name: &str, like: &RegistryData) -> Option<RegistryData> {
+    let subkey = to_wide(subkey);
+    let name = to_wide(name);
+    match like {
+        RegistryData::Dword(_) => {
+            let mut value: u32 = 0;
+            let mut size = std::mem::size_of::<u32>() as u32;
+            let result = unsafe {
+                RegGetValueW(
+                    HKEY_CLASSES_ROOT,
+                    PCWSTR(subkey.as_ptr()),
+                    PCWSTR(name.as_ptr()),
+                    RRF_RT_REG_DWORD,
+                    None,
+                    Some(&mut value as *mut u32 as *mut _),
+                    Some(&mut size),
+                )
+            };
+            result.is_ok().then_some(RegistryData::Dword(value))
+        }
+        RegistryData::Sz(_) => {
+            let mut buffer = vec![0u16; 32_768];
+            let mut size = (buffer.len() * 2) as u32;
+            let result = unsafe {
+                RegGetValueW(
+                    HKEY_CLASSES_ROOT,
+                    PCWSTR(subkey.as_ptr()),
+                    PCWSTR(name.as_ptr()),
+                    RRF_RT_REG_SZ,
+                    None,
+                    Some(buffer.as_mut_ptr() as *mut _),
+                    Some(&mut size),
+                )
+            };
+            if result.is_err() {
+                return None;
+            }
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            Some(RegistryData::Sz(String::from_utf16_lossy(&buffer[..len])))
+        }
+    }
+}
+
+fn build_report(dll_path: &Path) -> RegistrationReport {
+    let dll = strip_verbatim_prefix(&dll_path.to_string_lossy());
+    let values: Vec<RegistryValueCheck> = registration_plan(&dll)
+        .into_iter()
+        .map(|value| {
+            let actual = read_value(&value.subkey, value.name, &value.data);
+            RegistryValueCheck {
+                status: status(&value.data, actual.as_ref()),
+                key: format!("HKEY_CLASSES_ROOT\\{}", value.subkey),
+                name: value.name.to_string(),
+                expected: value.data,
+                actual,
+            }
+        })
+        .collect();
+    RegistrationReport {
+        dll_exists: dll_path.is_file(),
+        registered: values.iter().all(|v| v.status == ValueStatus::Matches),
+        dll_path: dll,
+        values,
+    }
+}
+
+/// What registering the Explorer thumbnail provider would write, and how the
+/// registry compares right now. Read-only.
+#[tauri::command]
+pub async fn thumbnail_registration_report() -> Result<RegistrationReport, String> {
+    let dll_path = provider_dll_path()?;
+    tauri::async_runtime::spawn_blocking(move || build_report(&dll_path))
+        .await
+        .map_err(|e| format!("join error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_covers_clsid_inproc_server_and_every_extension() {
+        let plan = registration_plan(r"C:\Program Files\Readest\readest_thumbnail.dll");
+        assert_eq!(plan.len(), 4 + SUPPORTED_EXTENSIONS.len());
+        assert_eq!(
+            plan[2],
+            planned(
+                format!("CLSID\\{CLSID_READEST_THUMBNAIL}\\InprocServer32"),
+                "",
+                RegistryData::Sz(r"C:\Program Files\Readest\readest_thumbnail.dll".into()),
+            )
+        );
+        assert_eq!(
+            plan.last().unwrap().subkey,
+            format!(".lit\\ShellEx\\{SHELL_THUMBNAIL_HANDLER}")
+        );
+    }
+
+    #[test]
+    fn values_compare_by_type_and_ignore_case() {
+        let path = RegistryData::Sz(r"C:\Readest\readest_thumbnail.dll".into());
+        assert_eq!(status(&path, None), ValueStatus::Missing);
+        assert_eq!(
+            status(
+                &path,
+                Some(&RegistryData::Sz(
+                    r"c:\readest\READEST_THUMBNAIL.DLL".into()
+                ))
+            ),
+            ValueStatus::Matches
+        );
+        assert_eq!(
+            status(&path, Some(&RegistryData::Sz(r"D:\old\x.dll".into()))),
+            ValueStatus::Differs
+        );
+        assert_eq!(
+            status(&RegistryData::Dword(1), Some(&RegistryData::Dword(0))),
+            ValueStatus::Differs
+        );
+    }
+}
@@ -0,0 +1,583 @@
+// `read_toc`: one table-of-contents source for every format the library
+// previews before a book is opened.
+//
+// The reader still builds its live ToC through foliate-js; this command is
+// for pre-open surfaces (library details, "jump to chapter" from search) that
+// shouldn't spin up a full document just to list chapters. Each format maps
+// onto the same nested `TocEntry` tree:
+//
+//   - EPUB: `<nav epub:type="toc">` from nav.xhtml, falling back to the NCX
+//     `<navMap>` (located with the same OPF scan as `parse_epub_full`);
+//   - MOBI/AZW: the inline HTML ToC that `<guide><reference type="toc">`
+//     points at, read as a flat list of `filepos` links (hrefs use foliate's
+//     `filepos:<n>` form). KF8-only books without a legacy guide have none;
+//   - FB2/FBZ: titled `<section>`s of the main `<body>`, nested as written.
+//     Untitled wrapper sections are skipped and their children lifted.
+//
+// Formats without a usable ToC (PDF outlines, CBZ, TXT, or books whose ToC
+// is missing) return an empty list and log a warning rather than failing.
+
+use mobi::Mobi;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::epub_parser::{
+    collapse_whitespace, local_name, local_name_eq, locate_toc_sources, read_rootfile_path,
+    read_zip_entry, resolve_page_target, resolve_relative, strip_xml_bom, LocatedTocSources,
+};
+use crate::parser_common::read_fbz_document;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TocEntry {
+    pub label: String,
+    /// Zip path plus `#fragment` for EPUB, `filepos:<n>` for MOBI,
+    /// `#<section id>` for FB2. `None` for headings that don't link anywhere.
+    pub href: Option<String>,
+    /// Nesting level, 0 for top-level entries.
+    pub depth: u32,
+    pub children: Vec<TocEntry>,
+}
+
+#[tauri::command]
+pub async fn read_toc(path: String) -> Result<Vec<TocEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || read_toc_sync(&path))
+        .await
+        .map_err(|e| format!("join error: {e}"))?
+}
+
+fn read_toc_sync(file_path: &str) -> Result<Vec<TocEntry>, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(format!("file not found: {file_path}"));
+    }
+
+    let lower = file_path.to_lowercase();
+    let ext = if lower.ends_with(".fb2.zip") {
+        "fbz"
+    } else {
+        path.extension().and_then(|e| e.to_str()).unwrap_or("")
+    };
+
+    let mut toc = match ext.to_ascii_lowercase().as_str() {
+        "epub" => epub_toc(path)?,
+        "mobi" | "azw" | "azw3" | "prc" => {
+            let mobi = Mobi::from_path(path).map_err(|e| format!("parse mobi: {e}"))?;
+            parse_mobi_toc(&mobi.content_as_string_lossy())
+        }
+        "fb2" => {
+            let bytes = std::fs::read(path).map_err(|e| format!("read failed: {e}"))?;
+            parse_fb2_toc(&bytes)?
+        }
+        "fbz" => {
+            let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+            let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+            parse_fb2_toc(&read_fbz_document(&mut zip)?)?
+        }
+        _ => Vec::new(),
+    };
+
+    if toc.is_empty() {
+        log::warn!("No usable table of contents in {file_path}");
+    }
+    assign_depths(&mut toc, 0);
+    Ok(toc)
+}
+
+fn assign_depths(entries: &mut [TocEntry], depth: u32) {
+    for entry in entries {
+        entry.depth = depth;
+        assign_depths(&mut entry.children, depth + 1);
+    }
+}
+
+/// Attach a finished entry to its parent on the stack, or to the roots.
+fn attach(stack: &mut [TocEntry], roots: &mut Vec<TocEntry>, entry: TocEntry) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// EPUB
+// ---------------------------------------------------------------------------
+
+fn epub_toc(path: &Path) -> Result<Vec<TocEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("open failed: {e}"))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("zip open failed: {e}"))?;
+
+    let opf_path = read_rootfile_path(&mut zip).map_err(|e| format!("container.xml: {e}"))?;
+    let opf_bytes =
+        read_zip_entry(&mut zip, &opf_path).map_err(|e| format!("read opf {opf_path}: {e}"))?;
+    let LocatedTocSources { nav_href, ncx_href } =
+        locate_toc_sources(&opf_bytes).map_err(|e| format!("locate toc: {e}"))?;
+
+    // Same soft-fail policy as `get_page_list`: a broken nav doc falls
+    // through to the NCX instead of failing the whole request.
+    if let Some(nav_path) = nav_href.map(|h| resolve_relative(&opf_path, &h)) {
+        if let Ok(bytes) = read_zip_entry(&mut zip, &nav_path) {
+            match parse_nav_toc(&bytes, &nav_path) {
+                Ok(toc) if !toc.is_empty() => return Ok(toc),
+                Ok(_) => {}
+                Err(e) => log::warn!("nav toc {nav_path}: {e}"),
+            }
+        }
+    }
+
+    if let Some(ncx_path) = ncx_href.map(|h| resolve_relative(&opf_path, &h)) {
+        if let Ok(bytes) = read_zip_entry(&mut zip, &ncx_path) {
+            match parse_ncx_toc(&bytes, &ncx_path) {
+                Ok(toc) => return Ok(toc),
+                Err(e) => log::warn!("ncx toc {ncx_path}: {e}"),
+            }
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// Walk `<nav epub:type="toc">`: each `<li>` becomes an entry labelled by
+/// its `<a>` (or `<span>` for unlinked headings), nested `<ol>`s become
+/// children.
+fn parse_nav_toc(nav_bytes: &[u8], nav_path: &str) -> Result<Vec<TocEntry>, String> {
+    let normalized = strip_xml_bom(nav_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<TocEntry> = Vec::new();
+    let mut toc_nav_depth = 0usize;
+    let mut capturing = false;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"nav" {
+                    let is_toc = e.attributes().flatten().any(|a| {
+                        local_name_eq(a.key.as_ref(), b"type")
+                            && String::from_utf8_lossy(&a.value)
+                                .split_ascii_whitespace()
+                                .any(|t| t == "toc")
+                    });
+                    if toc_nav_depth > 0 || is_toc {
+                        toc_nav_depth += 1;
+                    }
+                } else if toc_nav_depth == 0 {
+                    // Outside the ToC nav.
+                } else if name == b"li" {
+                    stack.push(TocEntry::default());
+                } else if name == b"a" || name == b"span" {
+                    if let Some(entry) = stack.last_mut() {
+                        if entry.label.is_empty() && entry.href.is_none() {
+                            capturing = true;
+                            entry.href = e
+                                .attributes()
+                                .flatten()
+                                .find(|a| local_name_eq(a.key.as_ref(), b"href"))
+                                .map(|a| {
+                                    resolve_page_target(
+                                        nav_path,
+                                        &String::from_utf8_lossy(&a.value),
+                                    )
+                                });
+                        }
+                    }
+                }
+            }
+            Ok(Event::Text(t)) if capturing => {
+                let text = t.unescape().map_err(|e| format!("xml: {e}"))?;
+                if let Some(entry) = stack.last_mut() {
+                    entry.label.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) if toc_nav_depth > 0 => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"a" || name == b"span" {
+                    capturing = false;
+                } else if name == b"li" {
+                    if let Some(mut entry) = stack.pop() {
+                        entry.label = collapse_whitespace(&entry.label);
+                        attach(&mut stack, &mut roots, entry);
+                    }
+                } else if name == b"nav" {
+                    toc_nav_depth -= 1;
+                    if toc_nav_depth == 0 {
+                        break;
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(roots)
+}
+
+/// Walk the NCX `<navMap>`: nested `<navPoint>`s with a `<navLabel><text>`
+/// and a `<content src>`.
+fn parse_ncx_toc(ncx_bytes: &[u8], ncx_path: &str) -> Result<Vec<TocEntry>, String> {
+    let normalized = strip_xml_bom(ncx_bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<TocEntry> = Vec::new();
+    let mut in_nav_map = false;
+    let mut in_text = false;
+
+    let content_href = |e: &quick_xml::events::BytesStart<'_>| {
+        e.attributes()
+            .flatten()
+            .find(|a| a.key.as_ref() == b"src")
+            .map(|a| resolve_page_target(ncx_path, &String::from_utf8_lossy(&a.value)))
+    };
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"navMap" {
+                    in_nav_map = true;
+                } else if in_nav_map && name == b"navPoint" {
+                    stack.push(TocEntry::default());
+                } else if in_nav_map && name == b"text" {
+                    in_text = true;
+                } else if in_nav_map && name == b"content" {
+                    if let Some(entry) = stack.last_mut() {
+                        entry.href = content_href(&e);
+                    }
+                }
+            }
+            Ok(Event::Empty(e)) if in_nav_map && local_name_eq(e.name().as_ref(), b"content") => {
+                if let Some(entry) = stack.last_mut() {
+                    entry.href = content_href(&e);
+                }
+            }
+            Ok(Event::Text(t)) if in_text => {
+                let text = t.unescape().map_err(|e| format!("xml: {e}"))?;
+                if let Some(entry) = stack.last_mut() {
+                    entry.label.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) if in_nav_map => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"text" {
+                    in_text = false;
+                } else if name == b"navPoint" {
+                    if let Some(mut entry) = stack.pop() {
+                        entry.label = collapse_whitespace(&entry.label);
+                        attach(&mut stack, &mut roots, entry);
+                    }
+                } else if name == b"navMap" {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(roots)
+}
+
+// ---------------------------------------------------------------------------
+// MOBI
+// ---------------------------------------------------------------------------
+
+/// Value of `name=...` inside a single HTML tag, quoted or not.
+fn tag_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let start = search + found;
+        search = start + name.len();
+        let preceded_by_space = lower[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lower[search..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let offset = tag.len() - value.len();
+        return Some(match value.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let end = value[1..].find(q).map_or(value.len(), |i| i + 1);
+                &tag[offset + 1..offset + end]
+            }
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+                    .unwrap_or(value.len());
+                &tag[offset..offset + end]
+            }
+        });
+    }
+    None
+}
+
+/// Drop tags and collapse whitespace from an HTML fragment.
+fn html_text(fragment: &str) -> String {
+    let mut out = String::with_capacity(fragment.len());
+    let mut in_tag = false;
+    for c in fragment.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                out.push(' ');
+            }
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    collapse_whitespace(&out.replace("&amp;", "&").replace("&nbsp;", " "))
+}
+
+/// Read the ToC page a MOBI `<guide>` points at and collect its `filepos`
+/// links in order. The page ends at the next `<mbp:pagebreak>`.
+fn parse_mobi_toc(html: &str) -> Vec<TocEntry> {
+    let lower = html.to_ascii_lowercase();
+
+    let toc_start = lower.match_indices("<reference").find_map(|(start, _)| {
+        let end = start + lower[start..].find('>')?;
+        let tag = &html[start..end];
+        if !tag_attr(tag, "type")?.eq_ignore_ascii_case("toc") {
+            return None;
+        }
+        tag_attr(tag, "filepos")?.parse::<usize>().ok()
+    });
+    let Some(mut start) = toc_start.filter(|&pos| pos < html.len()) else {
+        return Vec::new();
+    };
+    // `filepos` is a byte offset into the raw text; step back to a char
+    // boundary in case lossy decoding shifted it into a multi-byte sequence.
+    while !html.is_char_boundary(start) {
+        start -= 1;
+    }
+    let end = lower[start..]
+        .find("<mbp:pagebreak")
+        .map_or(html.len(), |i| start + i);
+
+    let mut entries = Vec::new();
+    let mut cursor = start;
+    while let Some(found) = lower[cursor..end].find("<a ") {
+        let tag_start = cursor + found;
+        let Some(tag_len) = lower[tag_start..end].find('>') else {
+            break;
+        };
+        let tag_end = tag_start + tag_len;
+        let close = lower[tag_end..end]
+            .find("</a>")
+            .map_or(end, |i| tag_end + i);
+        cursor = close;
+
+        let Some(pos) =
+            tag_attr(&html[tag_start..tag_end], "filepos").and_then(|v| v.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let label = html_text(&html[tag_end + 1..close]);
+        if !label.is_empty() {
+            entries.push(TocEntry {
+                label,
+                href: Some(format!("filepos:{pos}")),
+                ..Default::default()
+            });
+        }
+    }
+    entries
+}
+
+// ---------------------------------------------------------------------------
+// FB2
+// ---------------------------------------------------------------------------
+
+/// Collect titled `<section>`s of the main `<body>`. The notes body
+/// (`<body name="notes">`) is skipped.
+fn parse_fb2_toc(bytes: &[u8]) -> Result<Vec<TocEntry>, String> {
+    let normalized = strip_xml_bom(bytes);
+    let mut reader = Reader::from_reader(normalized.as_ref());
+    let mut buf = Vec::new();
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<TocEntry> = Vec::new();
+    let mut in_main_body = false;
+    let mut title_depth = 0usize;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"body" {
+                    in_main_body = !e.attributes().flatten().any(|a| {
+                        local_name_eq(a.key.as_ref(), b"name") && a.value.as_ref() == b"notes"
+                    });
+                } else if in_main_body && name == b"section" {
+                    let href = e
+                        .attributes()
+                        .flatten()
+                        .find(|a| local_name_eq(a.key.as_ref(), b"id"))
+                        .map(|a| format!("#{}", String::from_utf8_lossy(&a.value)));
+                    stack.push(TocEntry {
+                        href,
+                        ..Default::default()
+                    });
+                } else if in_main_body && !stack.is_empty() && name == b"title" {
+                    title_depth += 1;
+                } else if title_depth > 0 && name == b"p" {
+                    if let Some(entry) = stack.last_mut() {
+                        entry.label.push(' ');
+                    }
+                }
+            }
+            Ok(Event::Text(t)) if title_depth > 0 => {
+                let text = t.unescape().map_err(|e| format!("xml: {e}"))?;
+                if let Some(entry) = stack.last_mut() {
+                    entry.label.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) if in_main_body => {
+                let name = e.name();
+                let name = local_name(name.as_ref());
+                if name == b"title" && title_depth > 0 {
+                    title_depth -= 1;
+                } else if name == b"section" {
+                    if let Some(mut entry) = stack.pop() {
+                        entry.label = collapse_whitespace(&entry.label);
+                        if entry.label.is_empty() {
+                            // Untitled wrapper: lift its children one level.
+                            for child in std::mem::take(&mut entry.children) {
+                                attach(&mut stack, &mut roots, child);
+                            }
+                        } else {
+                            attach(&mut stack, &mut roots, entry);
+                        }
+                    }
+                } else if name == b"body" {
+                    in_main_body = false;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("xml: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(entries: &[TocEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.label.as_str()).collect()
+    }
+
+    #[test]
+    fn nav_toc_nests_and_resolves_hrefs() {
+        let nav = br#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<nav epub:type="toc"><ol>
+  <li><a href="text/ch1.xhtml">Chapter <em>One</em></a>
+    <ol><li><a href="text/ch1.xhtml#s1">Section 1.1</a></li></ol>
+  </li>
+  <li><span>Part II</span>
+    <ol><li><a href="text/ch2.xhtml">Chapter Two</a></li></ol>
+  </li>
+</ol></nav>
+<nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+</body></html>"#;
+        let mut toc = parse_nav_toc(nav, "OEBPS/nav.xhtml").unwrap();
+        assign_depths(&mut toc, 0);
+        assert_eq!(labels(&toc), ["Chapter One", "Part II"]);
+        assert_eq!(toc[0].href.as_deref(), Some("OEBPS/text/ch1.xhtml"));
+        assert_eq!(
+            toc[0].children[0].href.as_deref(),
+            Some("OEBPS/text/ch1.xhtml#s1")
+        );
+        assert_eq!(toc[0].children[0].depth, 1);
+        assert_eq!(toc[1].href, None);
+        assert_eq!(labels(&toc[1].children), ["Chapter Two"]);
+    }
+
+    #[test]
+    fn ncx_toc_nests_nav_points() {
+        let ncx = br#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/"><navMap>
+  <navPoint id="a"><navLabel><text>One</text></navLabel><content src="c1.html"/>
+    <navPoint id="b"><navLabel><text>One.A</text></navLabel><content src="c1.html#a"/></navPoint>
+  </navPoint>
+  <navPoint id="c"><navLabel><text>Two</text></navLabel><content src="c2.html"/></navPoint>
+</navMap></ncx>"#;
+        let toc = parse_ncx_toc(ncx, "OPS/toc.ncx").unwrap();
+        assert_eq!(labels(&toc), ["One", "Two"]);
+        assert_eq!(labels(&toc[0].children), ["One.A"]);
+        assert_eq!(toc[1].href.as_deref(), Some("OPS/c2.html"));
+    }
+
+    #[test]
+    fn mobi_toc_follows_guide_reference() {
+        let prefix = "<html><head><guide><reference type=\"toc\" title=\"Contents\" filepos=0000000000 /></guide></head><body>";
+        let toc_page = "<p><a filepos=0000001234 >Chapter 1</a></p><p><a filepos=0000005678><b>Chapter</b> 2</a></p><mbp:pagebreak/><a filepos=9>Not ToC</a>";
+        let placeholder = prefix.replace("0000000000", &format!("{:010}", prefix.len()));
+        let html = format!("{placeholder}{toc_page}");
+        let toc = parse_mobi_toc(&html);
+        assert_eq!(labels(&toc), ["Chapter 1", "Chapter 2"]);
+        assert_eq!(toc[1].href.as_deref(), Some("filepos:5678"));
+    }
+
+    #[test]
+    fn mobi_without_guide_has_no_toc() {
+        assert!(parse_mobi_toc("<html><body><a filepos=12>x</a></body></html>").is_empty());
+    }
+
+    #[test]
+    fn fb2_toc_uses_titled_sections() {
+        let fb2 = br#"<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0">
+<body>
+  <title><p>Book</p></title>
+  <section id="p1"><title><p>Part One</p><p>Beginnings</p></title>
+    <section id="c1"><title><p>Chapter 1</p></title><p>Text</p></section>
+    <section><p>Untitled interlude</p>
+      <section id="c2"><title><p>Chapter 2</p></title></section>
+    </section>
+  </section>
+</body>
+<body name="notes"><section id="n1"><title><p>1</p></title></section></body>
+</FictionBook>"#;
+        let mut toc = parse_fb2_toc(fb2).unwrap();
+        assign_depths(&mut toc, 0);
+        assert_eq!(labels(&toc), ["Part One Beginnings"]);
+        assert_eq!(labels(&toc[0].children), ["Chapter 1", "Chapter 2"]);
+        assert_eq!(toc[0].children[1].href.as_deref(), Some("#c2"));
+        assert_eq!(toc[0].children[1].depth, 1);
+    }
+
+    #[test]
+    fn tag_attr_handles_quoted_and_bare_values() {
+        let tag = "<a class='x' filepos=0000001234 href=\"#a\"";
+        assert_eq!(tag_attr(tag, "filepos"), Some("0000001234"));
+        assert_eq!(tag_attr(tag, "class"), Some("x"));
+        assert_eq!(tag_attr(tag, "href"), Some("#a"));
+        assert_eq!(tag_attr(tag, "pos"), None);
+    }
+}
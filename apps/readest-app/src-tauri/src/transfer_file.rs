@@ -6,23 +6,40 @@
 //!
 //! Download files from a remote HTTP server to disk.
 
+use futures::future::Either;
 use futures_util::TryStreamExt;
 use serde::{ser::Serializer, Serialize};
-use tauri::{command, ipc::Channel, AppHandle};
+use tauri::{command, ipc::Channel, AppHandle, Emitter};
 use tauri_plugin_fs::FsExt;
 use tokio::{
     fs::File,
     io::{AsyncWriteExt, BufWriter},
+    sync::watch,
 };
 use tokio_util::codec::{BytesCodec, FramedRead};
 
 use read_progress_stream::ReadProgressStream;
 
-use std::time::Instant;
+use crate::taskbar_progress::TransferProgress;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, sync::Arc};
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Event emitted when a download is aborted for exceeding its `max_bytes` cap.
+pub const TRANSFER_LIMIT_EXCEEDED_EVENT: &str = "transfer-limit-exceeded";
+
+/// Time allowed to establish a connection, unless the caller sets one.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time allowed between two reads from the server, unless the caller sets
+/// one. Generous enough for slow mobile links, but a server that has stopped
+/// answering no longer hangs a sync forever.
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
 // The TransferStats struct tracks both transfer speed and cumulative transfer progress.
 pub struct TransferStats {
     accumulated_chunk_len: usize, // Total length of chunks transferred in the current period
@@ -78,13 +95,183 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
-    Request(#[from] reqwest::Error),
+    Request(reqwest::Error),
+    /// Connecting or waiting on the server took longer than allowed. Kept
+    /// apart from `Request` so callers can retry these and not, say, a
+    /// rejected certificate.
+    #[error("request timed out: {0}")]
+    Timeout(reqwest::Error),
     #[error("{0}")]
     ContentLength(String),
     #[error("request failed with status code {0}: {1}")]
     HttpErrorCode(u16, String),
     #[error("permission denied: path not in filesystem scope: {0}")]
     Forbidden(String),
+    #[error("transfer exceeded the {1}-byte limit after receiving {0} bytes")]
+    LimitExceeded(u64, u64),
+    #[error("not enough disk space: the download needs {0} bytes but only {1} are free")]
+    InsufficientSpace(u64, u64),
+    /// The server wants credentials (`401`, `403` or `407`), so retrying
+    /// without new headers won't help.
+    #[error("authentication required: server answered {0}")]
+    AuthRequired(u16),
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout(e)
+        } else {
+            Error::Request(e)
+        }
+    }
+}
+
+/// Emitted by [`pause_all_transfers`] with a [`TransfersPausedPayload`].
+pub const TRANSFERS_PAUSED_EVENT: &str = "transfers-paused";
+/// Emitted by [`resume_all_transfers`] with a [`TransfersPausedPayload`].
+pub const TRANSFERS_RESUMED_EVENT: &str = "transfers-resumed";
+
+/// Number of `download_file` and `upload_file` calls in flight.
+static ACTIVE_TRANSFERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a transfer as active for as long as it is alive.
+struct ActiveTransfer;
+
+impl ActiveTransfer {
+    fn start() -> Self {
+        ACTIVE_TRANSFERS.fetch_add(1, Ordering::SeqCst);
+        ActiveTransfer
+    }
+}
+
+impl Drop for ActiveTransfer {
+    fn drop(&mut self) {
+        ACTIVE_TRANSFERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// `true` while transfers are paused; every transfer watches it.
+fn pause_state() -> &'static watch::Sender<bool> {
+    static STATE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+    STATE.get_or_init(|| watch::channel(false).0)
+}
+
+/// Returns once transfers aren't paused.
+async fn wait_until_resumed() {
+    let mut paused = pause_state().subscribe();
+    let _ = paused.wait_for(|paused| !*paused).await;
+}
+
+/// The output of `fut`, or `None` when transfers are paused before it
+/// completes. `fut` is dropped then, closing any connection it held.
+async fn unless_paused<F: Future>(fut: F) -> Option<F::Output> {
+    let mut state = pause_state().subscribe();
+    let paused = async move {
+        let _ = state.wait_for(|paused| *paused).await;
+    };
+    futures::pin_mut!(fut, paused);
+    match futures::future::select(fut, paused).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+/// First byte of a `206` response, from `Content-Range: bytes <start>-…`.
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Body of the ranged request for the `len` bytes at `start`, read chunk by
+/// chunk. A server that ignores `Range` and sends the whole file, or sends
+/// more than was asked for, is cut off as soon as the part overruns `len`
+/// instead of being buffered; a short part is rejected too.
+async fn read_part(response: reqwest::Response, start: u64, len: u64) -> Result<Vec<u8>> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::HttpErrorCode(status.as_u16(), String::new()));
+    }
+    // A `200` is the whole file, which only fits a part starting at 0.
+    let partial = status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if (partial && content_range_start(&response) != Some(start)) || (!partial && start != 0) {
+        return Err(Error::ContentLength(format!(
+            "server did not honor the range starting at {start}"
+        )));
+    }
+    let mut body = Vec::with_capacity(len as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.try_next().await? {
+        if body.len() as u64 + chunk.len() as u64 > len {
+            return Err(Error::ContentLength(format!(
+                "server sent more than the {len} bytes requested at {start}"
+            )));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    if body.len() as u64 != len {
+        return Err(Error::ContentLength(format!(
+            "server sent {} of the {len} bytes requested at {start}",
+            body.len()
+        )));
+    }
+    Ok(body)
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransfersPausedPayload {
+    /// Transfers in flight when the state changed.
+    active: usize,
+}
+
+/// Suspend every transfer, e.g. for a "pause sync" toggle. Downloads drop
+/// their connection and keep what they have written; uploads are dropped.
+/// Transfers started while paused wait too. Does nothing if already paused.
+#[command]
+pub fn pause_all_transfers(app: AppHandle) {
+    if !pause_state().send_replace(true) {
+        let active = ACTIVE_TRANSFERS.load(Ordering::SeqCst);
+        log::info!("pausing {active} transfers");
+        let _ = app.emit(TRANSFERS_PAUSED_EVENT, TransfersPausedPayload { active });
+    }
+}
+
+/// Let paused transfers continue. Downloads ask for the rest of the file
+/// with a `Range` request, starting over if the server can't serve it or
+/// the file changed; uploads are sent again from the start.
+#[command]
+pub fn resume_all_transfers(app: AppHandle) {
+    if pause_state().send_replace(false) {
+        let active = ACTIVE_TRANSFERS.load(Ordering::SeqCst);
+        log::info!("resuming {active} transfers");
+        let _ = app.emit(TRANSFERS_RESUMED_EVENT, TransfersPausedPayload { active });
+    }
+}
+
+/// Client for one transfer. Timeouts are in seconds; `None` takes the
+/// default. The read timeout applies between reads rather than to the whole
+/// transfer, so large files on slow links still complete.
+fn build_client(
+    skip_ssl_verification: bool,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+) -> Result<reqwest::Client> {
+    Ok(reqwest::ClientBuilder::new()
+        .danger_accept_invalid_certs(skip_ssl_verification)
+        .danger_accept_invalid_hostnames(skip_ssl_verification)
+        .connect_timeout(connect_timeout.map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs))
+        .read_timeout(read_timeout.map_or(DEFAULT_READ_TIMEOUT, Duration::from_secs))
+        .build()?)
 }
 
 /// Reject paths the webview must not be allowed to target: relative paths and
@@ -145,6 +332,60 @@ pub struct ProgressPayload {
     transfer_speed: u64,
 }
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferLimitPayload {
+    url: String,
+    file_path: String,
+    received: u64,
+    max_bytes: u64,
+}
+
+/// Returns the cap when `received` bytes overrun an optional `max_bytes`.
+fn exceeded_limit(max_bytes: Option<u64>, received: u64) -> Option<u64> {
+    max_bytes.filter(|&limit| received > limit)
+}
+
+/// Fail before creating `file_path` when a download of `total` bytes won't
+/// fit on its volume. A file being replaced frees its own size.
+fn ensure_space(file_path: &str, total: u64) -> Result<()> {
+    let replaced = std::fs::metadata(file_path).map_or(0, |m| m.len());
+    crate::disk_space::ensure_space_for(
+        std::path::Path::new(file_path),
+        total.saturating_sub(replaced),
+    )
+    .map_err(|(needed, available)| Error::InsufficientSpace(needed, available))
+}
+
+/// Tell the webview how far an over-limit transfer got and build the error
+/// returned to the caller. The server is untrusted here: it may omit or lie
+/// about `Content-Length`, so this is reached from the streaming loop (after
+/// the partial file is deleted) as well as from the up-front length check.
+fn limit_exceeded(
+    app: &AppHandle,
+    url: &str,
+    file_path: &str,
+    received: u64,
+    max_bytes: u64,
+) -> Error {
+    log::warn!("download of {url} aborted: {received} bytes exceeds limit of {max_bytes}");
+    let _ = app.emit(
+        TRANSFER_LIMIT_EXCEEDED_EVENT,
+        TransferLimitPayload {
+            url: url.to_string(),
+            file_path: file_path.to_string(),
+            received,
+            max_bytes,
+        },
+    );
+    Error::LimitExceeded(received, max_bytes)
+}
+
+/// `max_bytes` caps the number of bytes written to `file_path`. Compressed
+/// responses (`gzip`/`br`) are decoded by reqwest before they reach us, so the
+/// cap applies to the decompressed size rather than the bytes on the wire.
+/// `connect_timeout` and `read_timeout` are in seconds (defaults 30 and 60);
+/// exceeding either fails with `Error::Timeout`.
 #[command]
 #[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
 pub async fn download_file(
@@ -155,6 +396,9 @@ pub async fn download_file(
     body: Option<String>,
     single_threaded: Option<bool>,
     skip_ssl_verification: Option<bool>,
+    max_bytes: Option<u64>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
     on_progress: Channel<ProgressPayload>,
 ) -> Result<HashMap<String, String>> {
     use futures::stream::{self, StreamExt};
@@ -162,77 +406,169 @@ pub async fn download_file(
     use tokio::io::AsyncSeekExt;
 
     ensure_path_allowed(&app, file_path)?;
+    let _active = ActiveTransfer::start();
+    crate::crash_report::breadcrumb("transfer-started", format!("download {url}"));
 
     const PART_SIZE: u64 = 1024 * 1024;
 
-    let client = reqwest::ClientBuilder::new()
-        .danger_accept_invalid_certs(skip_ssl_verification.unwrap_or(false))
-        .danger_accept_invalid_hostnames(skip_ssl_verification.unwrap_or(false))
-        .build()?;
+    let client = build_client(
+        skip_ssl_verification.unwrap_or(false),
+        connect_timeout,
+        read_timeout,
+    )?;
     let force_single = single_threaded.unwrap_or(false);
 
+    #[allow(clippy::too_many_arguments)]
     async fn single_threaded_download(
+        app: &AppHandle,
         client: &reqwest::Client,
         url: &str,
         file_path: &str,
         headers: &HashMap<String, String>,
         body: &Option<String>,
+        max_bytes: Option<u64>,
         on_progress: Channel<ProgressPayload>,
     ) -> Result<HashMap<String, String>> {
-        let mut request = if let Some(body) = body {
-            client.post(url).body(body.clone())
-        } else {
-            client.get(url)
-        };
-
-        for (key, value) in headers {
-            request = request.header(key, value);
-        }
+        let taskbar = TransferProgress::new(app);
+        let mut stats = TransferStats::default();
+        let mut file: Option<BufWriter<File>> = None;
+        let mut total = 0;
+        let mut resp_headers = HashMap::new();
+        // `ETag` or `Last-Modified` of the body being written, so a resumed
+        // request only continues it if the file hasn't changed since.
+        let mut validator: Option<String> = None;
 
-        let response = request.send().await?;
-        if !response.status().is_success() {
-            return Err(Error::HttpErrorCode(
-                response.status().as_u16(),
-                response.text().await.unwrap_or_default(),
-            ));
-        }
+        // One iteration per request: the first, then one per resume.
+        'request: loop {
+            wait_until_resumed().await;
+            let offset = stats.total_transferred;
+            let mut request = if let Some(body) = body {
+                client.post(url).body(body.clone())
+            } else {
+                client.get(url)
+            };
+            for (key, value) in headers {
+                request = request.header(key, value);
+            }
+            if offset > 0 {
+                request = request
+                    .header(reqwest::header::RANGE, format!("bytes={offset}-"))
+                    .header(reqwest::header::ACCEPT_ENCODING, "identity");
+                if let Some(validator) = &validator {
+                    request = request.header(reqwest::header::IF_RANGE, validator);
+                }
+            }
 
-        let mut resp_headers = HashMap::new();
-        for (key, value) in response.headers().iter() {
-            if let Ok(val_str) = value.to_str() {
-                resp_headers.insert(key.to_string(), val_str.to_string());
+            let Some(response) = unless_paused(request.send()).await else {
+                continue;
+            };
+            let response = response?;
+            if !response.status().is_success() {
+                return Err(Error::HttpErrorCode(
+                    response.status().as_u16(),
+                    response.text().await.unwrap_or_default(),
+                ));
             }
-        }
 
-        let total = response.content_length().unwrap_or(0);
-        let mut file = BufWriter::new(File::create(file_path).await?);
-        let mut stream = response.bytes_stream();
+            let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+            let writer = match file.as_mut() {
+                Some(writer) if partial && content_range_start(&response) == Some(offset) => {
+                    log::info!("resuming download of {url} at {offset} bytes");
+                    writer
+                }
+                // A range other than the one asked for can't be used either
+                // way; start over without one.
+                _ if partial && offset > 0 => {
+                    stats = TransferStats::default();
+                    continue;
+                }
+                _ => {
+                    resp_headers.clear();
+                    for (key, value) in response.headers().iter() {
+                        if let Ok(val_str) = value.to_str() {
+                            resp_headers.insert(key.to_string(), val_str.to_string());
+                        }
+                    }
+                    validator = [reqwest::header::ETAG, reqwest::header::LAST_MODIFIED]
+                        .iter()
+                        .find_map(|name| response.headers().get(name)?.to_str().ok())
+                        .map(str::to_string);
 
-        let mut stats = TransferStats::default();
-        while let Some(chunk) = stream.try_next().await? {
-            file.write_all(&chunk).await?;
-            stats.record_chunk_transfer(chunk.len());
-            let _ = on_progress.send(ProgressPayload {
-                progress: stats.total_transferred,
-                total,
-                transfer_speed: stats.transfer_speed,
-            });
+                    // reqwest drops `Content-Length` when it decodes a compressed
+                    // body, so this only short-circuits honest uncompressed
+                    // responses; the loop below enforces the cap on everything
+                    // else.
+                    total = response.content_length().unwrap_or(0);
+                    if let Some(limit) = exceeded_limit(max_bytes, total) {
+                        return Err(limit_exceeded(app, url, file_path, 0, limit));
+                    }
+                    ensure_space(file_path, total)?;
+                    stats = TransferStats::default();
+                    file.insert(BufWriter::new(File::create(file_path).await?))
+                }
+            };
+
+            let mut stream = response.bytes_stream();
+            loop {
+                // Pausing drops the stream, and with it the connection; what
+                // has arrived so far stays on disk for the resumed request.
+                let Some(next) = unless_paused(stream.try_next()).await else {
+                    writer.flush().await?;
+                    log::info!(
+                        "download of {url} paused at {} bytes",
+                        stats.total_transferred
+                    );
+                    continue 'request;
+                };
+                let Some(chunk) = next? else {
+                    writer.flush().await?;
+                    break 'request;
+                };
+                let received = stats.total_transferred + chunk.len() as u64;
+                if let Some(limit) = exceeded_limit(max_bytes, received) {
+                    drop(file);
+                    let _ = tokio::fs::remove_file(file_path).await;
+                    return Err(limit_exceeded(app, url, file_path, received, limit));
+                }
+                writer.write_all(&chunk).await?;
+                stats.record_chunk_transfer(chunk.len());
+                taskbar.update(stats.total_transferred, total);
+                let _ = on_progress.send(ProgressPayload {
+                    progress: stats.total_transferred,
+                    total,
+                    transfer_speed: stats.transfer_speed,
+                });
+            }
         }
-        file.flush().await?;
 
         Ok(resp_headers)
     }
 
     if force_single {
-        return single_threaded_download(&client, url, file_path, &headers, &body, on_progress)
-            .await;
+        return single_threaded_download(
+            &app,
+            &client,
+            url,
+            file_path,
+            &headers,
+            &body,
+            max_bytes,
+            on_progress,
+        )
+        .await;
     }
 
-    // Check if server supports range requests
-    let mut range_req = client.get(url).header("Range", "bytes=0-0");
+    // Check if server supports range requests. Ranged requests ask for the
+    // identity encoding: byte ranges of a compressed representation can't be
+    // decoded independently, and `total` must be the size written to disk.
+    let mut range_req = client
+        .get(url)
+        .header("Range", "bytes=0-0")
+        .header(reqwest::header::ACCEPT_ENCODING, "identity");
     for (key, value) in headers.iter() {
         range_req = range_req.header(key, value);
     }
+    wait_until_resumed().await;
     let range_resp = range_req.send().await?;
     let accept_ranges = range_resp
         .headers()
@@ -256,10 +592,24 @@ pub async fn download_file(
     }
 
     if !accept_ranges || total == 0 {
-        return single_threaded_download(&client, url, file_path, &headers, &body, on_progress)
-            .await;
+        return single_threaded_download(
+            &app,
+            &client,
+            url,
+            file_path,
+            &headers,
+            &body,
+            max_bytes,
+            on_progress,
+        )
+        .await;
     }
 
+    if let Some(limit) = exceeded_limit(max_bytes, total) {
+        return Err(limit_exceeded(&app, url, file_path, 0, limit));
+    }
+    ensure_space(file_path, total)?;
+
     // Multi-part download with range access
     let part_count = total.div_ceil(PART_SIZE);
     let file = File::create(file_path).await?;
@@ -267,12 +617,18 @@ pub async fn download_file(
 
     let file = Arc::new(tokio::sync::Mutex::new(file));
     let progress = Arc::new(tokio::sync::Mutex::new(TransferStats::default()));
+    // The first part that fails the download; the others stop once it's set.
+    let failure: Arc<std::sync::Mutex<Option<Error>>> = Arc::default();
+    let taskbar = TransferProgress::new(&app);
+    let taskbar = &taskbar;
+    let app = &app;
 
     stream::iter(0..part_count)
         .for_each_concurrent(8, |i| {
             let client = client.clone();
             let file = Arc::clone(&file);
             let progress = Arc::clone(&progress);
+            let failure = Arc::clone(&failure);
             let headers = headers.clone();
             let url = url.to_string();
             let on_progress = on_progress.clone();
@@ -281,37 +637,53 @@ pub async fn download_file(
                 let start = i * PART_SIZE;
                 let end = min(start + PART_SIZE - 1, total - 1);
                 let range_header = format!("bytes={start}-{end}");
-
-                let mut req = client.get(&url).header("Range", range_header);
-                for (key, value) in headers {
-                    req = req.header(key, value);
-                }
-
-                let resp = match req.send().await {
-                    Ok(r) => r,
-                    Err(_) => return,
+                let fail = |e: Error| {
+                    let mut failure = failure.lock().unwrap_or_else(|e| e.into_inner());
+                    failure.get_or_insert(e);
                 };
 
-                if !resp.status().is_success()
-                    && resp.status() != reqwest::StatusCode::PARTIAL_CONTENT
-                {
-                    return;
-                }
+                // Parts already written are kept across a pause; one caught
+                // in flight is dropped and fetched again on resume.
+                let bytes = loop {
+                    if failure.lock().map_or(true, |f| f.is_some()) {
+                        return;
+                    }
+                    wait_until_resumed().await;
+                    let mut req = client
+                        .get(&url)
+                        .header("Range", &range_header)
+                        .header(reqwest::header::ACCEPT_ENCODING, "identity");
+                    for (key, value) in &headers {
+                        req = req.header(key, value);
+                    }
 
-                let bytes = match resp.bytes().await {
-                    Ok(b) => b,
-                    Err(_) => return,
+                    let fetch =
+                        async { read_part(req.send().await?, start, end - start + 1).await };
+                    match unless_paused(fetch).await {
+                        Some(Ok(bytes)) => break bytes,
+                        Some(Err(e)) => return fail(e),
+                        None => continue,
+                    }
                 };
 
-                {
-                    let mut f = file.lock().await;
-                    f.seek(std::io::SeekFrom::Start(start)).await.unwrap();
-                    f.write_all(&bytes).await.unwrap();
-                }
-
                 {
                     let mut stat = progress.lock().await;
+                    let received = stat.total_transferred + bytes.len() as u64;
+                    if let Some(limit) = exceeded_limit(max_bytes, received) {
+                        return fail(limit_exceeded(app, &url, file_path, received, limit));
+                    }
+                    {
+                        let mut f = file.lock().await;
+                        let written = async {
+                            f.seek(std::io::SeekFrom::Start(start)).await?;
+                            f.write_all(&bytes).await
+                        };
+                        if let Err(e) = written.await {
+                            return fail(e.into());
+                        }
+                    }
                     stat.record_chunk_transfer(bytes.len());
+                    taskbar.update(stat.total_transferred, total);
                     let _ = on_progress.send(ProgressPayload {
                         progress: stat.total_transferred,
                         total,
@@ -322,47 +694,174 @@ pub async fn download_file(
         })
         .await;
 
+    let failure = failure.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(e) = failure {
+        drop(file);
+        let _ = tokio::fs::remove_file(file_path).await;
+        return Err(e);
+    }
     Ok(resp_headers)
 }
 
+/// `connect_timeout` and `read_timeout` are in seconds, as for
+/// `download_file`.
 #[command]
+#[allow(clippy::too_many_arguments)] // Tauri command surface mirrors the JS caller's options.
 pub async fn upload_file(
     app: AppHandle,
     url: &str,
     file_path: &str,
     method: &str,
     headers: HashMap<String, String>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
     on_progress: Channel<ProgressPayload>,
 ) -> Result<String> {
     ensure_path_allowed(&app, file_path)?;
+    let _active = ActiveTransfer::start();
+    crate::crash_report::breadcrumb("transfer-started", format!("upload {url}"));
 
-    let file = File::open(file_path).await?;
-    let file_len = file.metadata().await.unwrap().len();
+    let client = build_client(false, connect_timeout, read_timeout)?;
+    // HTTP has no standard way to continue an upload, so a paused one is
+    // dropped and sent again from the start on resume.
+    loop {
+        wait_until_resumed().await;
+        let file = File::open(file_path).await?;
+        let file_len = file.metadata().await?.len();
 
-    let client = reqwest::Client::new();
-    let mut request = match method.to_uppercase().as_str() {
-        "POST" => client.post(url),
-        "PUT" => client.put(url),
-        _ => return Err(Error::ContentLength("Invalid HTTP method".into())),
-    };
+        let mut request = match method.to_uppercase().as_str() {
+            "POST" => client.post(url),
+            "PUT" => client.put(url),
+            _ => return Err(Error::ContentLength("Invalid HTTP method".into())),
+        };
 
-    request = request
-        .header(reqwest::header::CONTENT_LENGTH, file_len)
-        .body(file_to_body(on_progress.clone(), file, file_len));
+        request = request
+            .header(reqwest::header::CONTENT_LENGTH, file_len)
+            .body(file_to_body(on_progress.clone(), file, file_len));
 
+        for (key, value) in &headers {
+            request = request.header(key, value);
+        }
+
+        let upload = async {
+            let response = request.send().await?;
+            if response.status().is_success() {
+                response.text().await.map_err(Into::into)
+            } else {
+                Err(Error::HttpErrorCode(
+                    response.status().as_u16(),
+                    response.text().await.unwrap_or_default(),
+                ))
+            }
+        };
+        match unless_paused(upload).await {
+            Some(result) => return result,
+            None => log::info!("upload of {file_path} paused; it restarts on resume"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UrlProbe {
+    /// The server answered with a `2xx` status.
+    pub reachable: bool,
+    pub status: u16,
+    /// Size of the whole resource, when the server tells.
+    pub content_length: Option<u64>,
+    /// MIME type without parameters, e.g. `application/epub+zip`.
+    pub content_type: Option<String>,
+    /// `download_file` can fetch the resource in parallel parts and resume
+    /// it after a pause.
+    pub supports_range: bool,
+}
+
+fn header_str(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<&str> {
+    response.headers().get(name)?.to_str().ok()
+}
+
+/// Total size from `Content-Range: bytes <start>-<end>/<total>`.
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    header_str(response, reqwest::header::CONTENT_RANGE)?
+        .rsplit('/')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+async fn probe(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> Result<UrlProbe> {
+    let mut head = client.head(url);
     for (key, value) in headers {
-        request = request.header(&key, value);
+        head = head.header(key, value);
+    }
+    let mut response = head.send().await?;
+    // Some servers (and presigned URLs) only allow GET; a one-byte range
+    // request tells us the same without downloading the file.
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+    ) {
+        let mut get = client
+            .get(url)
+            .header(reqwest::header::RANGE, "bytes=0-0")
+            .header(reqwest::header::ACCEPT_ENCODING, "identity");
+        for (key, value) in headers {
+            get = get.header(key, value);
+        }
+        response = get.send().await?;
     }
 
-    let response = request.send().await?;
-    if response.status().is_success() {
-        response.text().await.map_err(Into::into)
-    } else {
-        Err(Error::HttpErrorCode(
-            response.status().as_u16(),
-            response.text().await.unwrap_or_default(),
-        ))
+    let status = response.status();
+    if matches!(status.as_u16(), 401 | 403 | 407) {
+        return Err(Error::AuthRequired(status.as_u16()));
     }
+    let partial = status == reqwest::StatusCode::PARTIAL_CONTENT;
+    let content_length = if partial {
+        content_range_total(&response)
+    } else {
+        header_str(&response, reqwest::header::CONTENT_LENGTH).and_then(|v| v.trim().parse().ok())
+    };
+    let accepts_bytes = header_str(&response, reqwest::header::ACCEPT_RANGES)
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("bytes"));
+    let content_type = header_str(&response, reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty());
+    // Dropping the response closes the connection instead of reading a body
+    // the server may have sent anyway.
+    Ok(UrlProbe {
+        reachable: status.is_success(),
+        status: status.as_u16(),
+        content_length,
+        content_type,
+        supports_range: status.is_success() && (partial || accepts_bytes),
+    })
+}
+
+/// Check `url` before starting a transfer: whether it answers, how big the
+/// resource is, its MIME type and whether it can be fetched in ranges. Sends
+/// a `HEAD`, falling back to a one-byte ranged `GET` when `HEAD` is refused.
+/// A server that can't be reached fails with `Error::Request` or
+/// `Error::Timeout`, one asking for credentials with `Error::AuthRequired`;
+/// any other status is reported in the result.
+#[command]
+pub async fn probe_url(
+    url: &str,
+    headers: Option<HashMap<String, String>>,
+    skip_ssl_verification: Option<bool>,
+    connect_timeout: Option<u64>,
+) -> Result<UrlProbe> {
+    let client = build_client(
+        skip_ssl_verification.unwrap_or(false),
+        connect_timeout,
+        None,
+    )?;
+    probe(&client, url, &headers.unwrap_or_default()).await
 }
 
 fn file_to_body(channel: Channel<ProgressPayload>, file: File, file_len: u64) -> reqwest::Body {
@@ -384,7 +883,241 @@ fn file_to_body(channel: Channel<ProgressPayload>, file: File, file_len: u64) ->
 
 #[cfg(test)]
 mod tests {
-    use super::{has_disallowed_components, is_within_app_storage};
+    use super::{
+        build_client, exceeded_limit, has_disallowed_components, is_within_app_storage,
+        pause_state, probe, read_part, unless_paused, wait_until_resumed, Error, UrlProbe,
+    };
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    /// Local server that accepts connections, writes `reply` and then never
+    /// sends another byte. Sockets are held open so the client sees a stall
+    /// rather than a closed connection.
+    async fn stalling_server(reply: &'static [u8]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let _ = socket.write_all(reply).await;
+                held.push(socket);
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn pausing_interrupts_work_until_resumed() {
+        assert_eq!(unless_paused(async { 7 }).await, Some(7));
+
+        let pause = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            pause_state().send_replace(true);
+        };
+        let (interrupted, ()) =
+            futures::join!(unless_paused(futures::future::pending::<()>()), pause);
+        assert_eq!(interrupted, None);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), wait_until_resumed())
+                .await
+                .is_err()
+        );
+
+        pause_state().send_replace(false);
+        tokio::time::timeout(Duration::from_secs(1), wait_until_resumed())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn never_responding_server_times_out() {
+        let addr = stalling_server(b"").await;
+        let client = build_client(false, Some(5), Some(1)).unwrap();
+        let err = client
+            .get(format!("http://{addr}/book.epub"))
+            .send()
+            .await
+            .map_err(Error::from)
+            .unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn body_stalling_mid_transfer_times_out() {
+        let addr = stalling_server(b"HTTP/1.1 200 OK\r\nContent-Length: 1024\r\n\r\npartial").await;
+        let client = build_client(false, Some(5), Some(1)).unwrap();
+        let response = client
+            .get(format!("http://{addr}/book.epub"))
+            .send()
+            .await
+            .unwrap();
+        let err = response.bytes().await.map_err(Error::from).unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn refused_connection_is_not_a_timeout() {
+        let addr = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let client = build_client(false, Some(5), Some(1)).unwrap();
+        let err = client
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .map_err(Error::from)
+            .unwrap_err();
+        assert!(matches!(err, Error::Request(_)), "{err:?}");
+    }
+
+    /// Local server answering `HEAD` with `head` and anything else with
+    /// `get`, one connection per request.
+    async fn method_server(head: &'static [u8], get: &'static [u8]) -> SocketAddr {
+        use tokio::io::AsyncReadExt;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let reply = if request.starts_with(b"HEAD ") {
+                    head
+                } else {
+                    get
+                };
+                let _ = socket.write_all(reply).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn probe_reads_head_response() {
+        let addr = method_server(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4096\r\nAccept-Ranges: bytes\r\n\
+              Content-Type: application/epub+zip; charset=binary\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await;
+        let client = build_client(false, Some(5), Some(5)).unwrap();
+        let probed = probe(
+            &client,
+            &format!("http://{addr}/book.epub"),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            probed,
+            UrlProbe {
+                reachable: true,
+                status: 200,
+                content_length: Some(4096),
+                content_type: Some("application/epub+zip".into()),
+                supports_range: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_falls_back_to_ranged_get() {
+        let addr = method_server(
+            b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-0/123456\r\n\
+              Content-Length: 1\r\nContent-Type: application/pdf\r\nConnection: close\r\n\r\n%",
+        )
+        .await;
+        let client = build_client(false, Some(5), Some(5)).unwrap();
+        let probed = probe(&client, &format!("http://{addr}/book.pdf"), &HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(probed.status, 206);
+        assert_eq!(probed.content_length, Some(123456));
+        assert_eq!(probed.content_type.as_deref(), Some("application/pdf"));
+        assert!(probed.reachable && probed.supports_range);
+    }
+
+    #[tokio::test]
+    async fn probe_reports_auth_and_missing_resources() {
+        let addr = method_server(
+            b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"",
+        )
+        .await;
+        let client = build_client(false, Some(5), Some(5)).unwrap();
+        let err = probe(&client, &format!("http://{addr}/"), &HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::AuthRequired(401)), "{err:?}");
+
+        let addr = method_server(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"",
+        )
+        .await;
+        let probed = probe(&client, &format!("http://{addr}/"), &HashMap::new())
+            .await
+            .unwrap();
+        assert!(!probed.reachable && !probed.supports_range);
+        assert_eq!(probed.status, 404);
+    }
+
+    #[tokio::test]
+    async fn ranged_part_rejects_an_ignored_range() {
+        let full: &'static [u8] = Box::leak(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 4096\r\nConnection: close\r\n\r\n{}",
+                "x".repeat(4096)
+            )
+            .into_bytes()
+            .into_boxed_slice(),
+        );
+        let addr = method_server(b"", full).await;
+        let client = build_client(false, Some(5), Some(5)).unwrap();
+        for start in [0, 1024] {
+            let response = client
+                .get(format!("http://{addr}/book.epub"))
+                .header("Range", format!("bytes={start}-{}", start + 1023))
+                .send()
+                .await
+                .unwrap();
+            let err = read_part(response, start, 1024).await.unwrap_err();
+            assert!(matches!(err, Error::ContentLength(_)), "{err:?}");
+        }
+
+        let addr = method_server(
+            b"",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 4-7/10\r\n\
+              Content-Length: 4\r\nConnection: close\r\n\r\nefgh",
+        )
+        .await;
+        let response = client
+            .get(format!("http://{addr}/book.epub"))
+            .header("Range", "bytes=4-7")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(read_part(response, 4, 4).await.unwrap(), b"efgh");
+    }
+
+    #[test]
+    fn limit_only_trips_past_max_bytes() {
+        assert_eq!(exceeded_limit(None, u64::MAX), None);
+        assert_eq!(exceeded_limit(Some(1024), 0), None);
+        assert_eq!(exceeded_limit(Some(1024), 1024), None);
+        assert_eq!(exceeded_limit(Some(1024), 1025), Some(1024));
+    }
 
     #[test]
     fn app_storage_fallback_accepts_app_paths() {
@@ -0,0 +1,156 @@
+//! `list_tts_voices` and `tts_warmup`: let the reader see the system TTS
+//! voices and load one before it starts reading aloud, so the first sentence
+//! doesn't wait for the engine to spin up.
+//!
+//! Both go through `tauri_plugin_native_tts`, which only has a backend on
+//! Android and iOS. Elsewhere the voice list comes back empty with
+//! `supported: false` and warmup reports `false`, so the frontend falls back
+//! to its other engines without treating it as an error.
+//!
+//! The voice list is cached for the session, since querying it starts the
+//! engine on Android. Pass `refresh: true` after the user installs voices.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::AppHandle;
+use tauri_plugin_native_tts::{GetVoicesResponse, NativeTtsExt, TTSVoice, WarmupArgs};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceInfo {
+    pub id: String,
+    pub name: String,
+    pub language: String,
+    /// `"male"` or `"female"`; not every platform reports it.
+    pub gender: Option<String>,
+    /// Synthesized on a server, so it needs a connection and may lag.
+    pub network: bool,
+}
+
+impl From<TTSVoice> for VoiceInfo {
+    fn from(voice: TTSVoice) -> Self {
+        Self {
+            id: voice.id,
+            name: voice.name,
+            language: voice.lang,
+            gender: voice.gender,
+            network: voice.network,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceList {
+    /// Whether this platform has a native TTS backend at all.
+    pub supported: bool,
+    pub voices: Vec<VoiceInfo>,
+}
+
+static VOICES: Mutex<Option<VoiceList>> = Mutex::new(None);
+
+/// The plugin's answer as a [`VoiceList`]. Disabled voices are dropped, and
+/// a platform without a backend is an empty, unsupported list rather than an
+/// error.
+fn voice_list(
+    response: tauri_plugin_native_tts::Result<GetVoicesResponse>,
+) -> Result<VoiceList, String> {
+    match response {
+        Ok(response) => Ok(VoiceList {
+            supported: true,
+            voices: response
+                .voices
+                .into_iter()
+                .filter(|voice| !voice.disabled)
+                .map(VoiceInfo::from)
+                .collect(),
+        }),
+        Err(tauri_plugin_native_tts::Error::UnsupportedPlatformError) => Ok(VoiceList {
+            supported: false,
+            voices: Vec::new(),
+        }),
+        Err(e) => Err(format!("failed to list voices: {e}")),
+    }
+}
+
+#[tauri::command]
+pub async fn list_tts_voices(app: AppHandle, refresh: Option<bool>) -> Result<VoiceList, String> {
+    if !refresh.unwrap_or(false) {
+        let cached = VOICES.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(list) = cached {
+            return Ok(list);
+        }
+    }
+    let response = tauri::async_runtime::spawn_blocking(move || app.native_tts().get_all_voices())
+        .await
+        .map_err(|e| format!("join error: {e}"))?;
+    let list = voice_list(response)?;
+    *VOICES.lock().unwrap_or_else(|e| e.into_inner()) = Some(list.clone());
+    Ok(list)
+}
+
+/// Start the engine and load `voice_id` (the current voice when omitted).
+/// Returns whether the engine is ready; `false` without an error when the
+/// platform has no native TTS.
+#[tauri::command]
+pub async fn tts_warmup(app: AppHandle, voice_id: Option<String>) -> Result<bool, String> {
+    let response = tauri::async_runtime::spawn_blocking(move || {
+        app.native_tts().warmup(WarmupArgs { voice: voice_id })
+    })
+    .await
+    .map_err(|e| format!("join error: {e}"))?;
+    match response {
+        Ok(response) => Ok(response.success),
+        Err(tauri_plugin_native_tts::Error::UnsupportedPlatformError) => Ok(false),
+        Err(e) => Err(format!("TTS warmup failed: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(id: &str, disabled: bool) -> TTSVoice {
+        TTSVoice {
+            id: id.to_string(),
+            name: id.to_string(),
+            lang: "en-US".to_string(),
+            disabled,
+            gender: Some("female".to_string()),
+            network: false,
+        }
+    }
+
+    #[test]
+    fn maps_plugin_voices() {
+        let list = voice_list(Ok(GetVoicesResponse {
+            voices: vec![voice("a", false), voice("b", true)],
+        }))
+        .unwrap();
+        assert!(list.supported);
+        assert_eq!(
+            list.voices,
+            vec![VoiceInfo {
+                id: "a".to_string(),
+                name: "a".to_string(),
+                language: "en-US".to_string(),
+                gender: Some("female".to_string()),
+                network: false,
+            }]
+        );
+
+        let unsupported = voice_list(Err(
+            tauri_plugin_native_tts::Error::UnsupportedPlatformError,
+        ))
+        .unwrap();
+        assert!(!unsupported.supported);
+        assert!(unsupported.voices.is_empty());
+
+        assert!(
+            voice_list(Err(tauri_plugin_native_tts::Error::NativeTTSError(
+                "engine gone".to_string()
+            )))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Window focus and visibility as one signal for the frontend.
+//!
+//! Discord idle handling, TTS and theme sync all want to know when the app is
+//! backgrounded. Rather than each of them polling, [`handle_window_event`]
+//! turns Tauri's window events into:
+//!
+//!   - `window-focus-changed` `{ label, focused }` on every focus change;
+//!   - `window-visibility-changed` `{ label, visible, minimized }` when the
+//!     window is hidden, minimized or restored. Platforms report minimizing
+//!     differently (a resize to 0x0 on Windows, a focus loss on macOS), so the
+//!     state is re-read on those events and only changes are emitted.
+//!
+//! `get_window_state` returns the current snapshot for initial render.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager, Runtime, Window, WindowEvent};
+
+pub const WINDOW_FOCUS_CHANGED_EVENT: &str = "window-focus-changed";
+pub const WINDOW_VISIBILITY_CHANGED_EVENT: &str = "window-visibility-changed";
+
+/// Last `(visible, minimized)` pair emitted per window label.
+#[derive(Default)]
+pub struct WindowVisibility(Mutex<HashMap<String, (bool, bool)>>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowStateSnapshot {
+    pub focused: bool,
+    pub visible: bool,
+    pub minimized: bool,
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FocusChangedPayload<'a> {
+    label: &'a str,
+    focused: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisibilityChangedPayload<'a> {
+    label: &'a str,
+    visible: bool,
+    minimized: bool,
+}
+
+fn snapshot<R: Runtime>(window: &Window<R>) -> tauri::Result<WindowStateSnapshot> {
+    Ok(WindowStateSnapshot {
+        focused: window.is_focused()?,
+        visible: window.is_visible()?,
+        minimized: window.is_minimized()?,
+        maximized: window.is_maximized()?,
+    })
+}
+
+fn emit_visibility_if_changed<R: Runtime>(window: &Window<R>) {
+    let (Ok(visible), Ok(minimized)) = (window.is_visible(), window.is_minimized()) else {
+        return;
+    };
+    let Some(state) = window.try_state::<WindowVisibility>() else {
+        return;
+    };
+    let Ok(mut last) = state.0.lock() else {
+        return;
+    };
+    let label = window.label();
+    if last.insert(label.to_string(), (visible, minimized)) == Some((visible, minimized)) {
+        return;
+    }
+    drop(last);
+
+    let payload = VisibilityChangedPayload {
+        label,
+        visible,
+        minimized,
+    };
+    if let Err(e) = window.emit(WINDOW_VISIBILITY_CHANGED_EVENT, payload) {
+        log::warn!("Failed to emit {WINDOW_VISIBILITY_CHANGED_EVENT}: {e}");
+    }
+}
+
+/// Forward focus and visibility changes of `window` to the frontend.
+pub fn handle_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    match event {
+        WindowEvent::Focused(focused) => {
+            let payload = FocusChangedPayload {
+                label: window.label(),
+                focused: *focused,
+            };
+            if let Err(e) = window.emit(WINDOW_FOCUS_CHANGED_EVENT, payload) {
+                log::warn!("Failed to emit {WINDOW_FOCUS_CHANGED_EVENT}: {e}");
+            }
+            emit_visibility_if_changed(window);
+        }
+        WindowEvent::Resized(_) => emit_visibility_if_changed(window),
+        WindowEvent::Destroyed => {
+            if let Some(state) = window.try_state::<WindowVisibility>() {
+                if let Ok(mut last) = state.0.lock() {
+                    last.remove(window.label());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Focus, visibility, minimized and maximized state of the calling window.
+#[tauri::command]
+pub fn get_window_state(window: Window) -> Result<WindowStateSnapshot, String> {
+    snapshot(&window).map_err(|e| format!("Failed to read window state: {e}"))
+}
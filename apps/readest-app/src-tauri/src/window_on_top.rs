@@ -0,0 +1,188 @@
+//! `set_always_on_top`: pin the calling window above other windows, for
+//! reading along with a video tutorial or a recipe.
+//!
+//! Only the calling window is pinned; other reader windows keep their own
+//! setting. The last choice is saved to `always_on_top.json` in the config
+//! dir and applied to the main window on the next launch. On macOS the
+//! Window menu has a checkable "Float on Top" item that toggles the focused
+//! window, and its check mark follows focus.
+//!
+//! A fullscreen window already covers its screen, and a raised window level
+//! keeps macOS from giving it a Space of its own, so the pin is lifted while
+//! a window is fullscreen and put back when it leaves. Both transitions
+//! resize the window, which is when [`handle_window_event`] re-applies it.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[cfg(target_os = "macos")]
+use tauri::menu::CheckMenuItem;
+use tauri::{AppHandle, Manager, Window, WindowEvent};
+
+use crate::portable;
+
+pub(crate) const STORE_FILENAME: &str = "always_on_top.json";
+#[cfg(target_os = "macos")]
+pub const MENU_ITEM_ID: &str = "always_on_top";
+
+#[derive(Default)]
+pub struct AlwaysOnTop {
+    /// Labels of the pinned windows, fullscreen ones included.
+    pinned: Mutex<HashSet<String>>,
+    #[cfg(target_os = "macos")]
+    menu_item: Mutex<Option<CheckMenuItem<tauri::Wry>>>,
+}
+
+impl AlwaysOnTop {
+    fn is_pinned(&self, label: &str) -> bool {
+        self.pinned
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(label)
+    }
+
+    fn set_pinned(&self, label: &str, pinned: bool) {
+        let mut labels = self.pinned.lock().unwrap_or_else(|e| e.into_inner());
+        if pinned {
+            labels.insert(label.to_string());
+        } else {
+            labels.remove(label);
+        }
+    }
+
+    /// Check the menu item when `label`, now in front, is pinned.
+    #[cfg(target_os = "macos")]
+    fn sync_menu(&self, label: &str) {
+        let item = self.menu_item.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(item) = item.as_ref() {
+            if let Err(e) = item.set_checked(self.is_pinned(label)) {
+                log::warn!("Failed to update the Float on Top menu item: {e}");
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn sync_menu(&self, _label: &str) {}
+}
+
+/// The saved preference; missing or unreadable means not pinned.
+fn load(dir: &Path) -> bool {
+    std::fs::read(dir.join(STORE_FILENAME))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or(false)
+}
+
+fn save(dir: &Path, pinned: bool) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    std::fs::write(
+        dir.join(STORE_FILENAME),
+        if pinned { "true" } else { "false" },
+    )
+    .map_err(|e| format!("Failed to save always on top: {e}"))
+}
+
+/// Raise or lower `window` to match its pin, leaving fullscreen windows at
+/// the normal level.
+fn apply(window: &Window, state: &AlwaysOnTop) -> tauri::Result<()> {
+    let on_top = state.is_pinned(window.label()) && !window.is_fullscreen()?;
+    if window.is_always_on_top()? != on_top {
+        window.set_always_on_top(on_top)?;
+    }
+    Ok(())
+}
+
+fn pin(window: &Window, pinned: bool) -> Result<(), String> {
+    let state = window.state::<AlwaysOnTop>();
+    state.set_pinned(window.label(), pinned);
+    apply(window, &state).map_err(|e| format!("Failed to set always on top: {e}"))?;
+    state.sync_menu(window.label());
+    if let Err(e) = portable::config_dir(window.app_handle()).and_then(|dir| save(&dir, pinned)) {
+        log::warn!("Always on top applied but not saved: {e}");
+    }
+    Ok(())
+}
+
+/// Keep the calling window above other windows, or stop doing so.
+#[tauri::command]
+pub fn set_always_on_top(window: Window, enabled: bool) -> Result<(), String> {
+    pin(&window, enabled)
+}
+
+/// Toggle the pin of the focused window, for the macOS menu item.
+#[cfg(target_os = "macos")]
+pub fn toggle_focused(app: &AppHandle) {
+    let focused = app
+        .webview_windows()
+        .into_values()
+        .map(|webview| webview.as_ref().window())
+        .find(|window| window.is_focused().unwrap_or(false));
+    let Some(window) = focused else {
+        return;
+    };
+    let pinned = app.state::<AlwaysOnTop>().is_pinned(window.label());
+    if let Err(e) = pin(&window, !pinned) {
+        log::warn!("{e}");
+    }
+}
+
+/// Hand over the macOS menu item, so its check mark can follow focus.
+#[cfg(target_os = "macos")]
+pub fn set_menu_item(app: &AppHandle, item: CheckMenuItem<tauri::Wry>) {
+    let state = app.state::<AlwaysOnTop>();
+    *state.menu_item.lock().unwrap_or_else(|e| e.into_inner()) = Some(item);
+    state.sync_menu("main");
+}
+
+/// Pin the main window at launch when it was pinned last time.
+pub fn restore(app: &AppHandle) {
+    let pinned = portable::config_dir(app).is_ok_and(|dir| load(&dir));
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let window = window.as_ref().window();
+    let state = app.state::<AlwaysOnTop>();
+    state.set_pinned(window.label(), pinned);
+    if let Err(e) = apply(&window, &state) {
+        log::warn!("Failed to restore always on top: {e}");
+    }
+    state.sync_menu(window.label());
+}
+
+/// Re-apply the pin when `window` enters or leaves fullscreen, keep the menu
+/// in step with the focused window, and forget closed windows.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    let Some(state) = window.try_state::<AlwaysOnTop>() else {
+        return;
+    };
+    match event {
+        WindowEvent::Resized(_) if state.is_pinned(window.label()) => {
+            if let Err(e) = apply(window, &state) {
+                log::warn!("Failed to re-apply always on top: {e}");
+            }
+        }
+        WindowEvent::Focused(true) => state.sync_menu(window.label()),
+        WindowEvent::Destroyed => state.set_pinned(window.label(), false),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preference_round_trips() {
+        let dir = std::env::temp_dir().join(format!("readest-on-top-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(!load(&dir));
+        save(&dir, true).unwrap();
+        assert!(load(&dir));
+        save(&dir, false).unwrap();
+        assert!(!load(&dir));
+        std::fs::write(dir.join(STORE_FILENAME), "garbage").unwrap();
+        assert!(!load(&dir));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
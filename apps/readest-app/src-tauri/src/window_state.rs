@@ -15,11 +15,11 @@
 use std::path::Path;
 use tauri::{
     plugin::{Builder, TauriPlugin},
-    Manager, Runtime,
+    Runtime,
 };
 
 /// Default filename used by `tauri-plugin-window-state`.
-const STATE_FILENAME: &str = ".window-state.json";
+pub(crate) const STATE_FILENAME: &str = ".window-state.json";
 
 /// Windows parks a minimized window at exactly `(-32000, -32000)`. Real
 /// monitors sit only a few thousand pixels off the origin even in multi-display
@@ -82,7 +82,7 @@ fn sanitize_file(path: &Path) {
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
     Builder::new("window-state-sanitizer")
         .setup(|app, _api| {
-            if let Ok(dir) = app.path().app_config_dir() {
+            if let Ok(dir) = crate::portable::config_dir(app) {
                 sanitize_file(&dir.join(STATE_FILENAME));
             }
             Ok(())
@@ -0,0 +1,68 @@
+//! `set_window_title`: show the open book in the window title, so windows
+//! can be told apart in the taskbar, alt-tab and the macOS Window menu.
+//!
+//! The title becomes "Book Title — Readest" and goes back to the one the
+//! window was built with when cleared. On Windows the taskbar button and
+//! alt-tab list show the window text, which `set_title` updates even
+//! though the window has no native title bar. macOS lists windows by title
+//! in the Window menu and Mission Control; the main window hides its title
+//! in the overlay title bar, so it is never drawn over the web content.
+
+use tauri::{Manager, WebviewWindow};
+
+/// Title the main window is built with. macOS shows nothing in the overlay
+/// title bar; elsewhere the taskbar needs a name.
+#[cfg(target_os = "macos")]
+pub const DEFAULT_TITLE: &str = "";
+#[cfg(not(target_os = "macos"))]
+pub const DEFAULT_TITLE: &str = "Readest";
+
+/// Longest book title kept, in characters; the taskbar truncates far
+/// sooner anyway.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// The window title for `book`: the book followed by `app_name`, or
+/// [`DEFAULT_TITLE`] when there is no book. Whitespace, including line
+/// breaks from metadata, collapses to single spaces.
+fn compose_title(book: Option<&str>, app_name: &str) -> String {
+    let book = book
+        .map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|title| !title.is_empty());
+    match book {
+        Some(title) => {
+            let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+            format!("{title} — {app_name}")
+        }
+        None => DEFAULT_TITLE.to_string(),
+    }
+}
+
+/// Set the calling window's title to the book being read, or restore the
+/// default with `None`.
+#[tauri::command]
+pub fn set_window_title(window: WebviewWindow, title: Option<String>) -> Result<(), String> {
+    let app_name = window.package_info().name.clone();
+    window
+        .set_title(&compose_title(title.as_deref(), &app_name))
+        .map_err(|e| format!("Failed to set window title: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composes_book_and_app_name() {
+        assert_eq!(
+            compose_title(Some("  Dune\n Messiah "), "Readest"),
+            "Dune Messiah — Readest"
+        );
+        assert_eq!(compose_title(Some(" \t"), "Readest"), DEFAULT_TITLE);
+        assert_eq!(compose_title(None, "Readest"), DEFAULT_TITLE);
+        let long = compose_title(Some(&"a".repeat(500)), "Readest");
+        assert_eq!(
+            long.chars().count(),
+            MAX_TITLE_CHARS + " — Readest".chars().count()
+        );
+    }
+}
@@ -26,6 +26,7 @@ import {
   navigateToLibrary,
 } from '@/utils/nav';
 import { clearDiscordPresence } from '@/utils/discord';
+import { releaseStdinBook } from '@/utils/bridge';
 import { BOOK_IDS_SEPARATOR } from '@/services/constants';
 import { BookDetailModal } from '@/components/metadata';
 import ShareBookDialog from '@/app/library/components/ShareBookDialog';
@@ -181,6 +182,10 @@ const ReaderContent: React.FC<{ ids?: string; settings: SystemSettings }> = ({ i
     if (viewState?.isPrimary && appService?.isDesktopApp) {
       await clearDiscordPresence(appService);
     }
+    const { book } = getBookData(bookKey) || {};
+    if (book && appService?.isDesktopApp) {
+      releaseStdinBook(book.hash).catch(() => {});
+    }
 
     try {
       getView(bookKey)?.close();
@@ -9,14 +9,19 @@ import { relaunch, exit } from '@tauri-apps/plugin-process';
 import { fetch as tauriFetch } from '@tauri-apps/plugin-http';
 import { Command } from '@tauri-apps/plugin-shell';
 import { invoke } from '@tauri-apps/api/core';
-import { desktopDir } from '@tauri-apps/api/path';
 import { isTauriAppPlatform } from '@/services/environment';
 import { useTranslator } from '@/hooks/useTranslator';
 import { useTranslation } from '@/hooks/useTranslation';
 import { useSearchParams } from 'next/navigation';
 import { getAppVersion } from '@/utils/version';
 import { tauriDownload } from '@/utils/transfer';
-import { installPackage, verifyUpdateSignature, installNightlyUpdate } from '@/utils/bridge';
+import {
+  installPackage,
+  verifyUpdateSignature,
+  installNightlyUpdate,
+  installAppImageUpdate,
+} from '@/utils/bridge';
+import type { AppImageUpdateError, NightlyProgress } from '@/utils/bridge';
 import { join } from '@tauri-apps/api/path';
 import { getLocale } from '@/utils/misc';
 import { setLastShownReleaseNotesVersion } from '@/helpers/updater';
@@ -214,6 +219,52 @@ export const UpdaterContent = ({
       console.log('File downloaded to', filePath);
       if (onEvent && !finished) onEvent({ event: 'Finished' });
     };
+    // Rust streams cumulative progress; the dialog expects per-chunk deltas
+    // and a 'Started' event once the total is known (a 0 total, from a server
+    // that omitted Content-Length, never starts so the percent math never
+    // divides by zero).
+    const toDownloadEvents = (onEvent?: (progress: DownloadEvent) => void) => {
+      let total = 0;
+      let lastDownloaded = 0;
+      return (p: NightlyProgress) => {
+        if (p.event === 'progress') {
+          if (!total && p.contentLength) {
+            total = p.contentLength;
+            onEvent?.({ event: 'Started', data: { contentLength: total } });
+          }
+          onEvent?.({ event: 'Progress', data: { chunkLength: p.downloaded - lastDownloaded } });
+          lastDownloaded = p.downloaded;
+        } else if (p.event === 'finished') {
+          onEvent?.({ event: 'Finished' });
+        }
+      };
+    };
+    // Replaces $APPIMAGE in place; the relaunch after downloadAndInstall then
+    // starts the new version.
+    const installAppImage = async (
+      url: string,
+      signature: string,
+      onEvent?: (progress: DownloadEvent) => void,
+    ) => {
+      try {
+        await installAppImageUpdate(
+          url,
+          signature,
+          READEST_UPDATER_PUBKEY,
+          toDownloadEvents(onEvent),
+        );
+      } catch (err) {
+        console.error('Failed to update AppImage:', err);
+        if ((err as AppImageUpdateError)?.kind === 'notWritable') {
+          setError(
+            _(
+              'Readest cannot replace its AppImage because the folder is not writable. Please download the new version manually.',
+            ),
+          );
+        }
+        throw err;
+      }
+    };
     const checkWindowsPortableUpdate = async () => {
       if (!appService) return;
       const fetch = isTauriAppPlatform() ? tauriFetch : window.fetch;
@@ -259,34 +310,14 @@ export const UpdaterContent = ({
         const OS_ARCH = osArch();
         const platformKey =
           OS_ARCH === 'x86_64' ? 'linux-x86_64-appimage' : 'linux-aarch64-appimage';
-        const arch = OS_ARCH === 'x86_64' ? 'x86_64' : 'aarch64';
-        const downloadUrl = data.platforms[platformKey]?.url as string;
-        const appImageFileName = `Readest_${data.version}_${arch}.AppImage`;
-        const appImageFilePath = await join(await desktopDir(), appImageFileName);
+        const platform = data.platforms[platformKey];
         setUpdate({
           currentVersion,
           version: data.version,
           date: data.pub_date,
           body: data.notes,
           downloadAndInstall: async (onEvent) => {
-            await downloadWithProgress(downloadUrl, appImageFilePath, onEvent);
-            try {
-              // Make the AppImage executable
-              const chmodCommand = Command.create('chmod-appimage', ['+x', appImageFilePath]);
-              await chmodCommand.execute();
-              console.log('AppImage made executable:', appImageFilePath);
-
-              // Launch the new AppImage
-              console.log('Launching new AppImage:', appImageFilePath);
-              const launchCommand = Command.create('launch-appimage', [appImageFilePath]);
-              await launchCommand.spawn();
-              console.log('New AppImage launched, exiting current app...');
-              setTimeout(async () => {
-                await exit(0);
-              }, 500);
-            } catch (error) {
-              console.error('Failed to launch new AppImage:', error);
-            }
+            await installAppImage(platform?.url as string, platform?.signature as string, onEvent);
           },
         } as GenericUpdate);
       }
@@ -299,31 +330,15 @@ export const UpdaterContent = ({
       downloadAndInstall: async (onEvent) => {
         if (TAURI_UPDATER_KEYS.has(n.platformKey)) {
           // macOS / Windows-NSIS: Tauri updater (verify + install +
-          // relaunch). A 0 contentLength (server omitted Content-Length) is
-          // tolerated: we only emit 'Started' once a non-zero total arrives so
-          // the percent math never divides by zero.
-          let total = 0;
-          let lastDownloaded = 0;
-          await installNightlyUpdate(n.endpoint, (p) => {
-            if (p.event === 'progress') {
-              if (!total && p.contentLength) {
-                total = p.contentLength;
-                onEvent?.({ event: 'Started', data: { contentLength: total } });
-              }
-              // p.downloaded is a cumulative running total from Rust, but the
-              // consumer treats chunkLength as a per-chunk delta, so convert.
-              onEvent?.({
-                event: 'Progress',
-                data: { chunkLength: p.downloaded - lastDownloaded },
-              });
-              lastDownloaded = p.downloaded;
-            } else if (p.event === 'finished') {
-              onEvent?.({ event: 'Finished' });
-            }
-          });
+          // relaunch).
+          await installNightlyUpdate(n.endpoint, toDownloadEvents(onEvent));
+          return;
+        }
+        if (n.platformKey.includes('appimage')) {
+          await installAppImage(n.url, n.signature, onEvent);
           return;
         }
-        // Windows-portable / Linux-AppImage / Android: download, verify, install.
+        // Windows-portable / Android: download, verify, install.
         const fileName = n.url.split('/').pop() || `Readest_${n.version}`;
         let filePath: string;
         if (n.platformKey.includes('portable')) {
@@ -343,14 +358,6 @@ export const UpdaterContent = ({
         if (n.platformKey.startsWith('android')) {
           const res = await installPackage({ path: filePath });
           if (!res.success) console.error('Failed to install APK:', res.error);
-        } else if (n.platformKey.includes('appimage')) {
-          const chmod = Command.create('chmod-appimage', ['+x', filePath]);
-          await chmod.execute();
-          const launch = Command.create('launch-appimage', [filePath]);
-          await launch.spawn();
-          setTimeout(async () => {
-            await exit(0);
-          }, 500);
         } else {
           // windows portable
           const command = Command.create('start-readest', ['/C', 'start', '', filePath]);
@@ -27,7 +27,8 @@ const parseCLIOpenWithFiles = async () => {
   if (args) {
     for (const name of ['file1', 'file2', 'file3', 'file4']) {
       const arg = args[name] as CliArgument;
-      if (arg && arg.occurrences > 0) {
+      // `-` asks for a book piped on stdin, which arrives separately.
+      if (arg && arg.occurrences > 0 && arg.value !== '-') {
         files.push(arg.value);
       }
     }
@@ -70,5 +71,7 @@ export const parseOpenWithFiles = async (appService: AppService | null) => {
   if (!files || files.length === 0) {
     files = await parseIntentOpenWithFiles(appService);
   }
-  return files;
+  // `.acsm` tickets are handed to Adobe Digital Editions by the native side
+  // (see the `acsm-handoff` event), so they never reach the importer.
+  return files?.filter((file) => !file.toLowerCase().endsWith('.acsm'));
 };
@@ -1,10 +1,11 @@
 import { useEffect } from 'react';
-import { addPluginListener, PluginListener } from '@tauri-apps/api/core';
+import { addPluginListener, invoke, PluginListener } from '@tauri-apps/api/core';
 import { onOpenUrl } from '@tauri-apps/plugin-deep-link';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { useEnv } from '@/context/EnvContext';
 import { isTauriAppPlatform } from '@/services/environment';
 import { eventDispatcher } from '@/utils/event';
+import { takeStdinBook } from '@/utils/bridge';
 
 interface SingleInstancePayload {
   args: string[];
@@ -15,6 +16,13 @@ interface OpenFilesPayload {
   files: string[];
 }
 
+interface ServiceSearchPayload {
+  query: string;
+}
+
+/** Requests from macOS Services that arrived before this hook mounted. */
+type ServiceRequest = { kind: 'openFiles'; files: string[] } | { kind: 'search'; query: string };
+
 interface SharedIntentPayload {
   urls: string[];
   /**
@@ -33,7 +41,8 @@ interface SharedIntentPayload {
  *
  * Subscribes to every Tauri channel that can deliver a URL on any platform:
  *   - `single-instance` event  — Win/Linux deep link, macOS open-file
- *   - `open-files` event       — macOS in-app open-files
+ *   - `open-files` event       — macOS in-app open-files and the
+ *                                "Open in Readest" Service
  *   - `shared-intent` plugin   — Android "Share to Readest" intent
  *   - `onOpenUrl`              — iOS / Android / macOS via Tauri v2
  *
@@ -48,6 +57,12 @@ interface SharedIntentPayload {
  *   - `useOpenWithBooks`        — file imports
  *   - `useOpenAnnotationLink`   — annotation deep links
  *
+ * Text sent to the macOS "Search Library in Readest" Service arrives as the
+ * `service-search` event and is re-broadcast under the same name. Service
+ * requests that cold-launched the app are queued natively until this hook
+ * drains them with `take_service_requests`. A book piped on stdin on desktop
+ * is queued the same way for `take_stdin_book`.
+ *
  * Cold-start URLs (`getCurrent()`) are intentionally NOT read here. Cold-
  * start handling is consumer-specific (a launching file goes through the
  * library init flow; an annotation jumps the reader), so each consumer
@@ -93,6 +108,35 @@ export function useAppUrlIngress() {
       },
     );
 
+    let unlistenServiceSearch: Promise<() => void> | null = null;
+    if (appService?.isMacOSApp) {
+      unlistenServiceSearch = getCurrentWindow().listen<ServiceSearchPayload>(
+        'service-search',
+        ({ payload }) => {
+          if (payload.query) eventDispatcher.dispatch('service-search', payload);
+        },
+      );
+      invoke<ServiceRequest[]>('take_service_requests')
+        .then((requests) => {
+          for (const request of requests) {
+            if (request.kind === 'openFiles') {
+              dispatch(request.files);
+            } else {
+              eventDispatcher.dispatch('service-search', { query: request.query });
+            }
+          }
+        })
+        .catch((e) => console.error('Failed to read queued service requests:', e));
+    }
+
+    if (appService?.isDesktopApp) {
+      takeStdinBook()
+        .then((file) => {
+          if (file) dispatch([file]);
+        })
+        .catch((e) => console.error('Failed to read the book piped on stdin:', e));
+    }
+
     // FIXME: register/unregister of this plugin listener has caused freezes
     // on iOS in the past, so it's gated to Android. The Tauri v2 onOpenUrl
     // listener below covers iOS.
@@ -114,6 +158,7 @@ export function useAppUrlIngress() {
     return () => {
       unlistenSingleInstance.then((f) => f());
       unlistenOpenFiles.then((f) => f());
+      unlistenServiceSearch?.then((f) => f());
       unlistenOpenUrl.then((f) => f());
       unlistenSharedIntent?.then((f) => f.unregister());
     };
@@ -5,12 +5,14 @@ import { getRuntimeConfig } from './runtimeConfig';
 declare global {
   interface Window {
     __READEST_CLI_ACCESS?: boolean;
+    __READEST_SAFE_MODE?: boolean;
   }
 }
 
 export const isTauriAppPlatform = () => process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'tauri';
 export const isWebAppPlatform = () => process.env['NEXT_PUBLIC_APP_PLATFORM'] === 'web';
 export const hasCli = () => window.__READEST_CLI_ACCESS === true;
+export const isSafeMode = () => window.__READEST_SAFE_MODE === true;
 export const isPWA = () => window.matchMedia('(display-mode: standalone)').matches;
 export const getBaseUrl = () =>
   getRuntimeConfig()?.apiBaseUrl ??
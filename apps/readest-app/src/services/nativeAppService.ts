@@ -146,6 +146,7 @@ async function ensureDirExists(
 // 1. If custom root dir is set, use it as base dir (baseDir = 0)
 // 2. If portable mode is detected (Settings.json in executable dir), use executable dir as base dir (baseDir = 0)
 // 3. If both custom root dir and portable mode are set, use custom root dir as base dir (baseDir = 0)
+// 4. If a portable data dir is reported (portable.txt / READEST_PORTABLE), settings and caches live there too
 // Path Resolver Usage:
 //  - appService.resolvePath and use returned baseDir + fp, when baseDir is 0, fp will be absolute path
 //  - fileSystem.getPrefix and use prefix + path
@@ -153,10 +154,12 @@ const getPathResolver = ({
   customRootDir,
   isPortable,
   execDir,
+  cacheDir,
 }: {
   customRootDir?: string;
   isPortable?: boolean;
   execDir?: string;
+  cacheDir?: string;
 } = {}) => {
   const customBaseDir = customRootDir ? 0 : undefined;
   const isCustomBaseDir = Boolean(customRootDir);
@@ -187,9 +190,9 @@ const getPathResolver = ({
         };
       case 'Cache':
         return {
-          baseDir: BaseDirectory.AppCache,
-          basePrefix: appCacheDir,
-          fp: path,
+          baseDir: cacheDir ? 0 : BaseDirectory.AppCache,
+          basePrefix: cacheDir ? async () => cacheDir : appCacheDir,
+          fp: cacheDir ? `${cacheDir}${path ? `/${path}` : ''}` : path,
           base,
         };
       case 'Log':
@@ -581,6 +584,7 @@ export class NativeAppService extends BaseAppService {
 
   private execDir?: string = undefined;
   private customRootDir?: string = undefined;
+  private portableCacheDir?: string = undefined;
 
   constructor(customRootDir?: string) {
     super();
@@ -590,9 +594,14 @@ export class NativeAppService extends BaseAppService {
   }
 
   override async init() {
-    const execDir = await invoke<string>('get_executable_dir');
+    // In marker-based portable mode everything lives in `<exe dir>/data`,
+    // which then stands in for the executable dir below.
+    const portableDataDir = await invoke<string | null>('get_portable_data_dir');
+    const execDir = portableDataDir ?? (await invoke<string>('get_executable_dir'));
     this.execDir = execDir;
+    this.portableCacheDir = portableDataDir ? `${portableDataDir}/cache` : undefined;
     if (
+      portableDataDir ||
       process.env['NEXT_PUBLIC_PORTABLE_APP'] ||
       (await this.fs.exists(`${execDir}/${SETTINGS_FILENAME}`, 'None'))
     ) {
@@ -601,6 +610,7 @@ export class NativeAppService extends BaseAppService {
         customRootDir: execDir,
         isPortable: this.isPortableApp,
         execDir,
+        cacheDir: this.portableCacheDir,
       });
     }
     const settings = await this.loadSettings();
@@ -609,6 +619,7 @@ export class NativeAppService extends BaseAppService {
         customRootDir: this.customRootDir || settings.customRootDir,
         isPortable: this.isPortableApp,
         execDir,
+        cacheDir: this.portableCacheDir,
       });
     }
     if (this.isIOSApp) {
@@ -666,6 +677,7 @@ export class NativeAppService extends BaseAppService {
       customRootDir,
       isPortable: this.isPortableApp,
       execDir: this.execDir,
+      cacheDir: this.portableCacheDir,
     });
     await this.prepareBooksDir();
   }
@@ -287,8 +287,9 @@ export async function isSyncKeychainAvailable(): Promise<SyncKeychainAvailableRe
 
 // ── Nightly updater (main-app commands, no native-bridge prefix) ─────────
 // `verify_update_signature` gates the custom install flows (portable /
-// AppImage / Android); `install_nightly_update` drives the Tauri updater for
-// the platform keys it natively installs (macOS / Windows-NSIS).
+// Android); `install_nightly_update` drives the Tauri updater for the
+// platform keys it natively installs (macOS / Windows-NSIS), and
+// `install_appimage_update` swaps the AppImage file in place.
 
 export async function verifyUpdateSignature(
   path: string,
@@ -312,3 +313,41 @@ export async function installNightlyUpdate(
   if (onProgress) channel.onmessage = onProgress;
   await invoke<void>('install_nightly_update', { endpoint, channel });
 }
+
+// Replaces the running AppImage (`$APPIMAGE`) in place after verifying the
+// download; the caller relaunches. Rejects with `{ kind, message }`, where
+// `kind: 'notWritable'` means the AppImage must be updated manually.
+export interface AppImageUpdateError {
+  kind:
+    | 'notAppImage'
+    | 'notWritable'
+    | 'download'
+    | 'invalidSignature'
+    | 'invalidImage'
+    | 'install';
+  message: string;
+}
+
+export async function installAppImageUpdate(
+  url: string,
+  signature: string,
+  pubKey: string,
+  onProgress?: (p: NightlyProgress) => void,
+): Promise<string> {
+  const channel = new Channel<NightlyProgress>();
+  if (onProgress) channel.onmessage = onProgress;
+  return invoke<string>('install_appimage_update', { url, signature, pubKey, channel });
+}
+
+// ── Book piped on stdin (desktop, `readest -`) ──────────────────────────
+// `take_stdin_book` returns a book read before the frontend was listening;
+// later ones arrive as `open-files`. `release_stdin_book` deletes the
+// spooled copy once the book with that hash is closed.
+
+export async function takeStdinBook(): Promise<string | null> {
+  return invoke<string | null>('take_stdin_book');
+}
+
+export async function releaseStdinBook(hash: string): Promise<void> {
+  await invoke<void>('release_stdin_book', { hash });
+}
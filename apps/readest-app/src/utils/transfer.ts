@@ -15,6 +15,15 @@ export interface ProgressPayload {
 
 export type ProgressHandler = (progress: ProgressPayload) => void;
 
+/** Native transfer timeouts in seconds. Omitted ones default to 30s (connect) and 60s (read). */
+export interface TransferTimeouts {
+  connectTimeout?: number;
+  readTimeout?: number;
+}
+
+/** Whether a native transfer failed by timing out, which is worth retrying. */
+export const isTransferTimeout = (error: unknown) => String(error).startsWith('request timed out');
+
 export const webUpload = (file: File, uploadUrl: string, onProgress?: ProgressHandler) => {
   return new Promise<void>((resolve, reject) => {
     const startTime = Date.now();
@@ -99,6 +108,7 @@ export const tauriUpload = async (
   method: UploadMethod,
   progressHandler?: ProgressHandler,
   headers?: Map<string, string>,
+  timeouts?: TransferTimeouts,
 ): Promise<string> => {
   const ids = new Uint32Array(1);
   window.crypto.getRandomValues(ids);
@@ -115,6 +125,8 @@ export const tauriUpload = async (
     filePath,
     method,
     headers: headers ?? {},
+    connectTimeout: timeouts?.connectTimeout,
+    readTimeout: timeouts?.readTimeout,
     onProgress,
   });
 };
@@ -127,6 +139,7 @@ export const tauriDownload = async (
   body?: string,
   singleThreaded?: boolean,
   skipSslVerification?: boolean,
+  timeouts?: TransferTimeouts,
 ): Promise<Record<string, string>> => {
   const ids = new Uint32Array(1);
   window.crypto.getRandomValues(ids);
@@ -146,6 +159,8 @@ export const tauriDownload = async (
     body,
     singleThreaded,
     skipSslVerification,
+    connectTimeout: timeouts?.connectTimeout,
+    readTimeout: timeouts?.readTimeout,
   });
   return responseHeaders;
 };